@@ -0,0 +1,178 @@
+//! Timings of the core compute paths, as a baseline for parallelization
+//! work. `cargo bench --bench core` runs the light benches; the EQGFT
+//! simulations and Hopfion lattices also run with
+//! `--features eqgft-benches`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mmss::core::emergence_logic::EmergenceLogic;
+use mmss::core::exports::{encode_records, ExportFormat};
+use mmss::core::metric_aggregates::{aggregate, aggregate_scalar};
+use mmss::core::metrics_history::{MetricsHistory, MetricsHistoryConfig, MetricsSnapshot};
+use mmss::core::types::GeometricOperator;
+use mmss_core::structex_bridge::MmssRecord;
+use serde_json::json;
+use std::hint::black_box;
+
+fn apply_operator(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_operator");
+    let params = json!({ "theta": 0.5, "magnitude": 0.5 });
+    for op in [
+        GeometricOperator::QuaternionRotation,
+        GeometricOperator::Zitterbewegung,
+        GeometricOperator::GeometricDerivation,
+        GeometricOperator::SemanticSynthesis,
+    ] {
+        group.bench_function(format!("{:?}", op), |b| {
+            let mut logic = EmergenceLogic::new(None);
+            b.iter(|| {
+                black_box(logic.apply_operator(op, &params));
+            });
+        });
+    }
+    group.finish();
+}
+
+fn export_throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("export");
+    for count in [1_000_u64, 100_000] {
+        let records: Vec<MmssRecord> = (0..count)
+            .map(|id| MmssRecord {
+                id,
+                kind: "metric".to_string(),
+                timestamp: id as i64,
+                payload: json!({ "v_geometric": id as f64 * 1e-3, "task": "rotate" }),
+            })
+            .collect();
+        group.throughput(Throughput::Elements(count));
+        for format in [ExportFormat::Arrow, ExportFormat::Parquet] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", format), count),
+                &records,
+                |b, records| b.iter(|| encode_records(records, format).unwrap()),
+            );
+        }
+    }
+    group.finish();
+}
+
+/// Snapshots of a random walk through the operators.
+fn metric_history(len: usize) -> Vec<MetricsSnapshot> {
+    let operators = [
+        GeometricOperator::QuaternionRotation,
+        GeometricOperator::Zitterbewegung,
+        GeometricOperator::GeometricDerivation,
+        GeometricOperator::SemanticSynthesis,
+    ];
+    let mut logic = EmergenceLogic::new(None);
+    (0..len)
+        .map(|i| {
+            let params = json!({ "magnitude": 0.1 + (i % 7) as f64 * 0.1 });
+            let metrics = logic.apply_operator(operators[i % operators.len()], &params);
+            MetricsSnapshot::now(None, metrics.clone())
+        })
+        .collect()
+}
+
+/// The vectorized metric aggregates against the scalar reference.
+fn metric_aggregates(c: &mut Criterion) {
+    let mut group = c.benchmark_group("metric_aggregates");
+    let snapshots = metric_history(4096);
+    for len in [256, 1024, 4096] {
+        let history = &snapshots[..len];
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(BenchmarkId::new("vectorized", len), history, |b, h| {
+            b.iter(|| aggregate(h, 32))
+        });
+        group.bench_with_input(BenchmarkId::new("scalar", len), history, |b, h| {
+            b.iter(|| aggregate_scalar(h, 32))
+        });
+    }
+    group.finish();
+}
+
+/// What `GET /metrics/vectorized` does with its defaults, 1024 snapshots
+/// from the in-memory history in windows of 32, encoded as the response
+/// body, against the same with the scalar aggregates.
+fn metrics_vectorized(c: &mut Criterion) {
+    let mut group = c.benchmark_group("metrics_vectorized");
+    let history = MetricsHistory::new(MetricsHistoryConfig {
+        flush_rows: usize::MAX,
+        ..MetricsHistoryConfig::in_directory(std::env::temp_dir().join("mmss-bench-history"))
+    });
+    for snapshot in metric_history(1024) {
+        history.push(snapshot).unwrap();
+    }
+    group.throughput(Throughput::Elements(1024));
+    group.bench_function("vectorized", |b| {
+        b.iter(|| serde_json::to_vec(&aggregate(&history.recent(1024).unwrap(), 32)).unwrap())
+    });
+    group.bench_function("scalar", |b| {
+        b.iter(|| {
+            serde_json::to_vec(&aggregate_scalar(&history.recent(1024).unwrap(), 32)).unwrap()
+        })
+    });
+    group.finish();
+}
+
+#[cfg(feature = "eqgft-benches")]
+fn sensitivity_curve(c: &mut Criterion) {
+    use mmss_eqgft::config::EqgftConfig;
+    use mmss_eqgft::sensitivity::{calculate_sensitivity_curve, log_spaced_events};
+
+    let mut group = c.benchmark_group("sensitivity_curve");
+    group.sample_size(10);
+    let config = EqgftConfig {
+        seed: Some(1),
+        ..EqgftConfig::default()
+    };
+    for points in [10, 50] {
+        let n_values = log_spaced_events(1_000, 100_000, points);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(points),
+            &n_values,
+            |b, n_values| b.iter(|| calculate_sensitivity_curve(&config, n_values).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+#[cfg(feature = "eqgft-benches")]
+fn hopfion_field(c: &mut Criterion) {
+    use mmss_eqgft::hopfion::{generate_hopfion_soliton_field, HopfionConfig};
+
+    let mut group = c.benchmark_group("hopfion_field");
+    group.sample_size(10);
+    for resolution in [16, 32, 64] {
+        let config = HopfionConfig {
+            resolution,
+            ..HopfionConfig::default()
+        };
+        group.throughput(Throughput::Elements(resolution.pow(3) as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(resolution),
+            &config,
+            |b, config| b.iter(|| generate_hopfion_soliton_field(config).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+#[cfg(not(feature = "eqgft-benches"))]
+criterion_group!(
+    benches,
+    apply_operator,
+    export_throughput,
+    metric_aggregates,
+    metrics_vectorized
+);
+#[cfg(feature = "eqgft-benches")]
+criterion_group!(
+    benches,
+    apply_operator,
+    export_throughput,
+    metric_aggregates,
+    metrics_vectorized,
+    sensitivity_curve,
+    hopfion_field
+);
+criterion_main!(benches);
@@ -0,0 +1,144 @@
+//! What the monitor shows, updated from stream frames and polled lists.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Something learned from the server.
+pub enum Update {
+    /// A JSON frame of the visualization stream.
+    Frame(Value),
+    Tasks(Vec<Value>),
+    Campaigns(Vec<Value>),
+    /// The stream connected (`None`) or dropped, with the reason.
+    Stream(Option<String>),
+    /// A list could not be polled.
+    PollFailed(String),
+}
+
+#[derive(Debug, Default)]
+pub struct App {
+    /// Latest value of every metric, built-in and custom.
+    pub metrics: BTreeMap<String, f64>,
+    /// Lowest and highest value seen per metric since connecting; gauges
+    /// show where the latest value lies between them.
+    pub ranges: BTreeMap<String, (f64, f64)>,
+    pub sequence: Option<u64>,
+    pub tasks: Vec<Value>,
+    pub campaigns: Vec<Value>,
+    pub stream_error: Option<String>,
+    pub poll_error: Option<String>,
+}
+
+impl App {
+    pub fn apply(&mut self, update: Update) {
+        match update {
+            Update::Frame(frame) => self.apply_frame(&frame),
+            Update::Tasks(tasks) => {
+                self.tasks = tasks;
+                self.poll_error = None;
+            }
+            Update::Campaigns(campaigns) => {
+                self.campaigns = campaigns;
+                self.poll_error = None;
+            }
+            Update::Stream(error) => self.stream_error = error,
+            Update::PollFailed(error) => self.poll_error = Some(error),
+        }
+    }
+
+    /// Where `name`'s value lies within its observed range, from 0 to 1.
+    pub fn position(&self, name: &str) -> f64 {
+        match (self.metrics.get(name), self.ranges.get(name)) {
+            (Some(value), Some((low, high))) if high > low => (value - low) / (high - low),
+            (Some(_), _) => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    fn apply_frame(&mut self, frame: &Value) {
+        let changed: Vec<(String, f64)> = match frame["type"].as_str() {
+            // a full packet replaces everything
+            Some("full") => {
+                self.metrics.clear();
+                let metrics = frame["metrics"].as_object().into_iter().flatten();
+                metrics
+                    .flat_map(|(name, value)| match value {
+                        Value::Object(custom) if name == "custom_metrics" => custom
+                            .iter()
+                            .filter_map(|(name, value)| Some((name.clone(), value.as_f64()?)))
+                            .collect::<Vec<_>>(),
+                        value => value
+                            .as_f64()
+                            .map(|value| (name.clone(), value))
+                            .into_iter()
+                            .collect::<Vec<_>>(),
+                    })
+                    .collect()
+            }
+            Some("delta") | Some("diff") => {
+                let removed = frame["removed"]
+                    .as_array()
+                    .or_else(|| frame["removed_metrics"].as_array());
+                for name in removed.into_iter().flatten().filter_map(Value::as_str) {
+                    self.metrics.remove(name);
+                }
+                frame["metrics"]
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(name, value)| Some((name.clone(), value.as_f64()?)))
+                    .collect()
+            }
+            _ => return,
+        };
+        for (name, value) in changed {
+            let range = self.ranges.entry(name.clone()).or_insert((value, value));
+            *range = (range.0.min(value), range.1.max(value));
+            self.metrics.insert(name, value);
+        }
+        self.sequence = frame["sequence"].as_u64();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_applies_full_and_delta_frames() {
+        let mut app = App::default();
+        app.apply(Update::Frame(json!({
+            "type": "full",
+            "sequence": 1,
+            "metrics": {
+                "v_geometric": 1.0,
+                "s_geometric": 0.5,
+                "custom_metrics": { "eqgft_scan_points": 3.0 },
+            },
+            "anchors": [],
+        })));
+        assert_eq!(app.metrics.len(), 3);
+        assert_eq!(app.metrics["eqgft_scan_points"], 3.0);
+        assert_eq!(app.position("v_geometric"), 1.0);
+
+        app.apply(Update::Frame(json!({
+            "type": "delta",
+            "sequence": 2,
+            "base_sequence": 1,
+            "metrics": { "v_geometric": 3.0 },
+            "removed": ["eqgft_scan_points"],
+        })));
+        app.apply(Update::Frame(json!({
+            "type": "delta",
+            "sequence": 3,
+            "base_sequence": 2,
+            "metrics": { "v_geometric": 2.0 },
+            "removed": [],
+        })));
+        assert_eq!(app.sequence, Some(3));
+        assert!(!app.metrics.contains_key("eqgft_scan_points"));
+        assert_eq!(app.ranges["v_geometric"], (1.0, 3.0));
+        assert_eq!(app.position("v_geometric"), 0.5);
+    }
+}
@@ -0,0 +1,148 @@
+//! `mmss-top`: live metrics, campaigns and tasks of a server in the
+//! terminal, for hosts where the web dashboard is out of reach.
+//!
+//! Metrics come from the visualization WebSocket; tasks and campaigns are
+//! polled from the HTTP API.
+
+mod app;
+mod ui;
+
+use app::{App, Update};
+use clap::Parser;
+use mmss_cli::client::ApiClient;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+use tungstenite::Message;
+
+#[derive(Parser)]
+#[command(
+    name = "mmss-top",
+    version,
+    about = "Terminal monitor for an MMSS server"
+)]
+struct Args {
+    /// Server address.
+    #[arg(long, env = "MMSS_URL", default_value = "http://127.0.0.1:8080")]
+    server: String,
+    /// Watch this namespace instead of the default one.
+    #[arg(long, short, env = "MMSS_NAMESPACE")]
+    namespace: Option<String>,
+    /// Sent as `x-api-key`.
+    #[arg(long, env = "MMSS_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+    /// Seconds between task and campaign polls.
+    #[arg(long, default_value_t = 2.0)]
+    interval: f64,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let client = ApiClient::new(
+        &args.server,
+        args.namespace.as_deref(),
+        args.api_key.clone(),
+        None,
+    );
+    let stream_url = format!(
+        "{}/ws/visualization?interval_ms=250",
+        client.base_url().replacen("http", "ws", 1)
+    );
+    let (updates, received) = mpsc::channel();
+    spawn_stream(stream_url, updates.clone());
+    spawn_poller(
+        client,
+        Duration::from_secs_f64(args.interval.max(0.2)),
+        updates,
+    );
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &received, &args.server);
+    ratatui::restore();
+    result
+}
+
+fn run(
+    terminal: &mut ratatui::DefaultTerminal,
+    updates: &Receiver<Update>,
+    server: &str,
+) -> std::io::Result<()> {
+    let mut app = App::default();
+    loop {
+        while let Ok(update) = updates.try_recv() {
+            app.apply(update);
+        }
+        terminal.draw(|frame| ui::draw(frame, &app, server))?;
+        if event::poll(Duration::from_millis(200))? {
+            if let Event::Key(key) = event::read()? {
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if key.kind == KeyEventKind::Press
+                    && (matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) || ctrl_c)
+                {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Forward stream frames, reconnecting after a pause whenever the stream
+/// drops; a new connection starts with a full packet.
+fn spawn_stream(url: String, updates: Sender<Update>) {
+    thread::spawn(move || loop {
+        let error = match tungstenite::connect(url.as_str()) {
+            Ok((mut socket, _)) => {
+                if updates.send(Update::Stream(None)).is_err() {
+                    return;
+                }
+                loop {
+                    match socket.read() {
+                        Ok(Message::Text(text)) => {
+                            let Ok(frame) = serde_json::from_str(&text) else {
+                                continue;
+                            };
+                            if updates.send(Update::Frame(frame)).is_err() {
+                                return;
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(e) => break e.to_string(),
+                    }
+                }
+            }
+            Err(e) => e.to_string(),
+        };
+        if updates.send(Update::Stream(Some(error))).is_err() {
+            return;
+        }
+        thread::sleep(Duration::from_secs(2));
+    });
+}
+
+fn spawn_poller(client: ApiClient, interval: Duration, updates: Sender<Update>) {
+    thread::spawn(move || loop {
+        let polled = client.get("/tasks").and_then(|tasks| {
+            let campaigns = client.get("/campaigns")?;
+            Ok((tasks, campaigns))
+        });
+        let sent = match polled {
+            Ok((tasks, campaigns)) => updates
+                .send(Update::Tasks(into_list(tasks)))
+                .and_then(|()| updates.send(Update::Campaigns(into_list(campaigns)))),
+            Err(e) => updates.send(Update::PollFailed(e.to_string())),
+        };
+        if sent.is_err() {
+            return;
+        }
+        thread::sleep(interval);
+    });
+}
+
+fn into_list(value: serde_json::Value) -> Vec<serde_json::Value> {
+    match value {
+        serde_json::Value::Array(items) => items,
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,144 @@
+//! Layout: a status line, one gauge per metric, campaign progress bars and
+//! the task table.
+
+use crate::app::App;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, LineGauge, Paragraph, Row, Table};
+use ratatui::Frame;
+use serde_json::Value;
+
+/// Campaigns shown, running ones first.
+const MAX_CAMPAIGNS: usize = 6;
+
+pub fn draw(frame: &mut Frame, app: &App, server: &str) {
+    let campaigns = campaigns(app);
+    let [status, metrics, campaign_area, tasks] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(app.metrics.len().max(1) as u16 + 2),
+        Constraint::Length(campaigns.len().max(1) as u16 + 2),
+        Constraint::Min(4),
+    ])
+    .areas(frame.area());
+
+    draw_status(frame, status, app, server);
+    draw_metrics(frame, metrics, app);
+    draw_campaigns(frame, campaign_area, &campaigns);
+    draw_tasks(frame, tasks, app);
+}
+
+fn draw_status(frame: &mut Frame, area: Rect, app: &App, server: &str) {
+    let (text, color) = match (&app.stream_error, &app.poll_error) {
+        (Some(error), _) => (format!("stream: {error}"), Color::Red),
+        (None, Some(error)) => (format!("poll: {error}"), Color::Yellow),
+        (None, None) => (
+            format!(
+                "packet #{}",
+                app.sequence.map_or("-".to_string(), |s| s.to_string())
+            ),
+            Color::Green,
+        ),
+    };
+    let line = Line::from(format!("mmss-top  {server}  {text}  (q to quit)"));
+    frame.render_widget(Paragraph::new(line).style(Style::default().fg(color)), area);
+}
+
+fn draw_metrics(frame: &mut Frame, area: Rect, app: &App) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Metrics (gauge: position within the range seen)");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let rows = Layout::vertical(vec![Constraint::Length(1); app.metrics.len()]).split(inner);
+    for ((name, value), row) in app.metrics.iter().zip(rows.iter()) {
+        let gauge = LineGauge::default()
+            .ratio(app.position(name).clamp(0.0, 1.0))
+            .label(format!("{name:<26} {value:>14.6}"))
+            .filled_style(Style::default().fg(Color::Cyan));
+        frame.render_widget(gauge, *row);
+    }
+}
+
+fn campaigns(app: &App) -> Vec<&Value> {
+    let mut campaigns: Vec<&Value> = app.campaigns.iter().collect();
+    campaigns.sort_by_key(|campaign| campaign["status"] != "running");
+    campaigns.truncate(MAX_CAMPAIGNS);
+    campaigns
+}
+
+fn draw_campaigns(frame: &mut Frame, area: Rect, campaigns: &[&Value]) {
+    let block = Block::default().borders(Borders::ALL).title("Campaigns");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let rows = Layout::vertical(vec![Constraint::Length(1); campaigns.len()]).split(inner);
+    for (campaign, row) in campaigns.iter().zip(rows.iter()) {
+        let done = campaign["completed_steps"].as_u64().unwrap_or(0);
+        let max = campaign["max_steps"].as_u64().unwrap_or(0);
+        let ratio = if max == 0 {
+            0.0
+        } else {
+            done as f64 / max as f64
+        };
+        let color = match campaign["status"].as_str() {
+            Some("running") => Color::Yellow,
+            Some("completed") => Color::Green,
+            _ => Color::Red,
+        };
+        let goal: String = campaign["goal"]
+            .as_str()
+            .unwrap_or("")
+            .chars()
+            .take(30)
+            .collect();
+        let gauge = LineGauge::default()
+            .ratio(ratio.clamp(0.0, 1.0))
+            .label(format!(
+                "{goal:<30} {done:>3}/{max:<3} progress {:>5.1}%",
+                campaign["goal_progress"].as_f64().unwrap_or(0.0) * 100.0
+            ))
+            .filled_style(Style::default().fg(color));
+        frame.render_widget(gauge, *row);
+    }
+}
+
+fn draw_tasks(frame: &mut Frame, area: Rect, app: &App) {
+    let rows = app.tasks.iter().map(|task| {
+        let (status, detail) = task_status(&task["status"]);
+        Row::new(vec![
+            task["task_id"].as_str().unwrap_or("-").to_string(),
+            status,
+            detail,
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(36),
+            Constraint::Length(18),
+            Constraint::Min(10),
+        ],
+    )
+    .header(
+        Row::new(["TASK", "STATUS", "DETAIL"]).style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Tasks ({})", app.tasks.len())),
+    );
+    frame.render_widget(table, area);
+}
+
+/// `"Pending"` or `{"Failed": "reason"}` as a name and a detail.
+fn task_status(status: &Value) -> (String, String) {
+    match status {
+        Value::String(name) => (name.clone(), String::new()),
+        Value::Object(fields) => match fields.iter().next() {
+            Some((name, Value::String(reason))) => (name.clone(), reason.clone()),
+            Some((name, _)) => (name.clone(), String::new()),
+            None => ("-".to_string(), String::new()),
+        },
+        _ => ("-".to_string(), String::new()),
+    }
+}
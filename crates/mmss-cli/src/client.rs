@@ -0,0 +1,137 @@
+//! Blocking client for the server's HTTP API.
+
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::Method;
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error("request to {url} failed: {source}")]
+    Http {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("{method} {url} returned {status}: {body}")]
+    Status {
+        method: Method,
+        url: String,
+        status: reqwest::StatusCode,
+        body: String,
+    },
+    #[error("{path}: {message}")]
+    Input { path: String, message: String },
+    #[error("{0}")]
+    Other(String),
+}
+
+pub type Result<T> = std::result::Result<T, CliError>;
+
+pub struct ApiClient {
+    http: Client,
+    /// `<server>/api`, or `<server>/api/ns/<namespace>`.
+    base: String,
+    api_key: Option<String>,
+    admin_token: Option<String>,
+}
+
+impl ApiClient {
+    pub fn new(
+        server: &str,
+        namespace: Option<&str>,
+        api_key: Option<String>,
+        admin_token: Option<String>,
+    ) -> Self {
+        let mut base = format!("{}/api", server.trim_end_matches('/'));
+        if let Some(namespace) = namespace {
+            base = format!("{}/ns/{}", base, namespace);
+        }
+        Self {
+            http: Client::new(),
+            base,
+            api_key,
+            admin_token,
+        }
+    }
+
+    /// `<server>/api` or its namespaced form, which paths are relative to.
+    pub fn base_url(&self) -> &str {
+        &self.base
+    }
+
+    pub fn get(&self, path: &str) -> Result<Value> {
+        self.send(Method::GET, path, None)
+    }
+
+    pub fn post(&self, path: &str, body: &Value) -> Result<Value> {
+        self.send(Method::POST, path, Some(body))
+    }
+
+    pub fn delete(&self, path: &str) -> Result<Value> {
+        self.send(Method::DELETE, path, None)
+    }
+
+    fn send(&self, method: Method, path: &str, body: Option<&Value>) -> Result<Value> {
+        let url = format!("{}{}", self.base, path);
+        let mut request = self.authorize(self.http.request(method.clone(), &url));
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let http_error = |source| CliError::Http {
+            url: url.clone(),
+            source,
+        };
+        let response = request.send().map_err(http_error)?;
+        let status = response.status();
+        let text = response.text().map_err(http_error)?;
+        if !status.is_success() {
+            return Err(CliError::Status {
+                method,
+                url,
+                status,
+                body: error_message(text),
+            });
+        }
+        // a few endpoints answer in plain text
+        Ok(serde_json::from_str(&text).unwrap_or(Value::String(text)))
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        let request = match &self.api_key {
+            Some(key) => request.header("x-api-key", key),
+            None => request,
+        };
+        match &self.admin_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+}
+
+/// `message (code)` from an API error body, or the body as it came for
+/// errors raised before a handler ran.
+fn error_message(body: String) -> String {
+    match serde_json::from_str::<Value>(&body) {
+        Ok(error) => match (error["code"].as_str(), error["message"].as_str()) {
+            (Some(code), Some(message)) => format!("{message} ({code})"),
+            _ => body,
+        },
+        Err(_) => body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_message() {
+        let body = r#"{"code":"not_found","message":"Rule not found"}"#;
+        assert_eq!(
+            error_message(body.to_string()),
+            "Rule not found (not_found)"
+        );
+        assert_eq!(error_message("plain".to_string()), "plain");
+    }
+}
@@ -0,0 +1,87 @@
+//! Request bodies read from JSON or TOML files.
+
+use crate::client::{CliError, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+/// The document at `path`, parsed as TOML for a `.toml` file and as JSON
+/// otherwise.
+pub fn load(path: &Path) -> Result<Value> {
+    let input_error = |message: String| CliError::Input {
+        path: path.display().to_string(),
+        message,
+    };
+    let text = fs::read_to_string(path).map_err(|e| input_error(e.to_string()))?;
+    if path.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str(&text).map_err(|e| input_error(e.to_string()))
+    } else {
+        serde_json::from_str(&text).map_err(|e| input_error(e.to_string()))
+    }
+}
+
+/// `POST /tasks` bodies from a document holding one task command, a list of
+/// them, or a `tasks` list (the only form TOML allows). A command may also
+/// be given as a full request, `{ "task": ..., "execute": ... }`.
+pub fn task_requests(document: Value, execute: bool) -> Vec<Value> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TaskFile {
+        List(Vec<Value>),
+        Tasks { tasks: Vec<Value> },
+        One(Value),
+    }
+
+    let commands = match serde_json::from_value(document) {
+        Ok(TaskFile::List(commands) | TaskFile::Tasks { tasks: commands }) => commands,
+        Ok(TaskFile::One(command)) => vec![command],
+        Err(_) => Vec::new(),
+    };
+    commands
+        .into_iter()
+        .map(|command| {
+            let mut request = if command.get("task").is_some() {
+                command
+            } else {
+                json!({ "task": command })
+            };
+            if !execute {
+                request["execute"] = Value::Bool(false);
+            }
+            request
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_requests_accept_every_layout() {
+        let command = json!({ "task_name": "rotate", "geometric_operator": "QuaternionRotation" });
+
+        let single = task_requests(command.clone(), true);
+        assert_eq!(single, vec![json!({ "task": command.clone() })]);
+
+        let listed = task_requests(
+            json!({ "tasks": [command.clone(), command.clone()] }),
+            false,
+        );
+        assert_eq!(listed.len(), 2);
+        assert_eq!(
+            listed[1],
+            json!({ "task": command.clone(), "execute": false })
+        );
+
+        let full = json!({ "task": command.clone(), "execute": false });
+        assert_eq!(task_requests(json!([full.clone()]), true), vec![full]);
+
+        let toml: Value = toml::from_str(
+            "[[tasks]]\ntask_name = \"rotate\"\ngeometric_operator = \"QuaternionRotation\"\n",
+        )
+        .unwrap();
+        assert_eq!(task_requests(toml, true), vec![json!({ "task": command })]);
+    }
+}
@@ -0,0 +1,6 @@
+//! Clients for the MMSS HTTP API: the `mmss-cli` command line and the
+//! `mmss-top` terminal monitor.
+
+pub mod client;
+pub mod input;
+pub mod output;
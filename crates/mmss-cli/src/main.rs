@@ -0,0 +1,269 @@
+//! `mmss-cli`: submit and follow tasks, read metrics, manage rules and run
+//! campaigns against a running server over its HTTP API.
+
+use clap::{Args, Parser, Subcommand};
+use mmss_cli::client::{ApiClient, CliError, Result};
+use mmss_cli::input;
+use mmss_cli::output::OutputFormat;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(name = "mmss-cli", version, about = "Client for the MMSS HTTP API")]
+struct Cli {
+    /// Server address.
+    #[arg(
+        long,
+        env = "MMSS_URL",
+        default_value = "http://127.0.0.1:8080",
+        global = true
+    )]
+    server: String,
+    /// Work in this namespace instead of the default one.
+    #[arg(long, short, env = "MMSS_NAMESPACE", global = true)]
+    namespace: Option<String>,
+    /// Sent as `x-api-key`, for per-key usage accounting.
+    #[arg(long, env = "MMSS_API_KEY", hide_env_values = true, global = true)]
+    api_key: Option<String>,
+    /// Bearer token for the admin endpoints.
+    #[arg(long, env = "MMSS_ADMIN_TOKEN", hide_env_values = true, global = true)]
+    admin_token: Option<String>,
+    #[arg(long, short, value_enum, default_value_t = OutputFormat::Table, global = true)]
+    output: OutputFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Submit, inspect and approve tasks.
+    #[command(subcommand)]
+    Task(TaskCommand),
+    /// Current metrics and registered rule names.
+    Metrics,
+    /// Register, list and delete delta rules.
+    #[command(subcommand)]
+    Rule(RuleCommand),
+    /// Start, follow and cancel research campaigns.
+    #[command(subcommand)]
+    Campaign(CampaignCommand),
+}
+
+#[derive(Subcommand)]
+enum TaskCommand {
+    /// Submit every task in a JSON or TOML file.
+    Submit {
+        file: PathBuf,
+        /// Queue the tasks without running them.
+        #[arg(long)]
+        no_execute: bool,
+    },
+    List,
+    Status {
+        id: String,
+    },
+    /// Approve a task held by the script policy.
+    Approve {
+        id: String,
+        #[arg(long)]
+        no_execute: bool,
+    },
+    /// Print the task's status whenever it changes, until it finishes.
+    Tail {
+        id: String,
+        #[command(flatten)]
+        poll: Poll,
+    },
+}
+
+#[derive(Subcommand)]
+enum RuleCommand {
+    List,
+    /// Register a rule shifting metrics by fixed amounts.
+    Add {
+        name: String,
+        #[arg(long, allow_hyphen_values = true)]
+        delta_v: Option<f64>,
+        #[arg(long, allow_hyphen_values = true)]
+        delta_s: Option<f64>,
+        #[arg(long, allow_hyphen_values = true)]
+        delta_q: Option<f64>,
+    },
+    Delete {
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CampaignCommand {
+    /// Start the campaign described in a JSON or TOML file.
+    Start {
+        file: PathBuf,
+        /// Follow it until it finishes.
+        #[arg(long)]
+        tail: bool,
+        #[command(flatten)]
+        poll: Poll,
+    },
+    List,
+    Status {
+        id: String,
+    },
+    /// Print progress after every step, until the campaign finishes.
+    Tail {
+        id: String,
+        #[command(flatten)]
+        poll: Poll,
+    },
+    Cancel {
+        id: String,
+    },
+}
+
+#[derive(Args, Clone, Copy)]
+struct Poll {
+    /// Seconds between polls.
+    #[arg(long, default_value_t = 1.0)]
+    interval: f64,
+}
+
+impl Poll {
+    fn wait(self) {
+        thread::sleep(Duration::from_secs_f64(self.interval.max(0.1)));
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let client = ApiClient::new(
+        &cli.server,
+        cli.namespace.as_deref(),
+        cli.api_key.clone(),
+        cli.admin_token.clone(),
+    );
+    match run(&client, cli.command, cli.output) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("mmss-cli: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(client: &ApiClient, command: Command, output: OutputFormat) -> Result<()> {
+    let print = |value: &Value| println!("{}", output.render(value));
+    match command {
+        Command::Task(TaskCommand::Submit { file, no_execute }) => {
+            let requests = input::task_requests(input::load(&file)?, !no_execute);
+            let responses = requests
+                .iter()
+                .map(|request| client.post("/tasks", request))
+                .collect::<Result<Vec<_>>>()?;
+            print(&Value::Array(responses));
+        }
+        Command::Task(TaskCommand::List) => print(&client.get("/tasks")?),
+        Command::Task(TaskCommand::Status { id }) => print(&client.get(&format!("/tasks/{id}"))?),
+        Command::Task(TaskCommand::Approve { id, no_execute }) => {
+            let path = format!("/tasks/{id}/approve?execute={}", !no_execute);
+            print(&client.post(&path, &Value::Null)?);
+        }
+        Command::Task(TaskCommand::Tail { id, poll }) => {
+            let mut last = Value::Null;
+            loop {
+                let task = client.get(&format!("/tasks/{id}"))?;
+                if task["status"] != last["status"] {
+                    print(&task);
+                }
+                if task_finished(&task["status"]) {
+                    return task_outcome(&task["status"]);
+                }
+                last = task;
+                poll.wait();
+            }
+        }
+        Command::Metrics => print(&client.get("/metrics")?),
+        Command::Rule(RuleCommand::List) => {
+            print(&client.get("/metrics")?["rule_names"]);
+        }
+        Command::Rule(RuleCommand::Add {
+            name,
+            delta_v,
+            delta_s,
+            delta_q,
+        }) => {
+            let rule = json!({
+                "name": name,
+                "delta_v": delta_v,
+                "delta_s": delta_s,
+                "delta_q": delta_q,
+            });
+            print(&client.post("/rules", &rule)?);
+        }
+        Command::Rule(RuleCommand::Delete { name }) => {
+            print(&client.delete(&format!("/rules/{name}"))?);
+        }
+        Command::Campaign(CampaignCommand::Start { file, tail, poll }) => {
+            let started = client.post("/llm/research-campaign", &input::load(&file)?)?;
+            print(&started);
+            if tail {
+                let id = started["campaign_id"]
+                    .as_str()
+                    .ok_or_else(|| CliError::Other("no campaign_id in the response".into()))?;
+                tail_campaign(client, id, poll, print)?;
+            }
+        }
+        Command::Campaign(CampaignCommand::List) => print(&client.get("/campaigns")?),
+        Command::Campaign(CampaignCommand::Status { id }) => {
+            print(&client.get(&format!("/campaigns/{id}"))?);
+        }
+        Command::Campaign(CampaignCommand::Tail { id, poll }) => {
+            tail_campaign(client, &id, poll, print)?;
+        }
+        Command::Campaign(CampaignCommand::Cancel { id }) => {
+            print(&client.post(&format!("/campaigns/{id}/cancel"), &Value::Null)?);
+        }
+    }
+    Ok(())
+}
+
+fn tail_campaign(client: &ApiClient, id: &str, poll: Poll, print: impl Fn(&Value)) -> Result<()> {
+    let mut printed_steps = None;
+    loop {
+        let campaign = client.get(&format!("/campaigns/{id}"))?;
+        let steps = campaign["completed_steps"].as_u64();
+        if steps != printed_steps {
+            print(&json!({
+                "campaign_id": campaign["campaign_id"],
+                "status": campaign["status"],
+                "completed_steps": campaign["completed_steps"],
+                "max_steps": campaign["max_steps"],
+                "goal_progress": campaign["goal_progress"],
+            }));
+            printed_steps = steps;
+        }
+        if campaign["status"] != "running" {
+            print(&campaign);
+            return Ok(());
+        }
+        poll.wait();
+    }
+}
+
+/// Completed or failed; statuses serialize as `"Pending"` or
+/// `{"Completed": {...}}`.
+fn task_finished(status: &Value) -> bool {
+    status.get("Completed").is_some() || status.get("Failed").is_some()
+}
+
+fn task_outcome(status: &Value) -> Result<()> {
+    match status.get("Failed") {
+        Some(reason) => Err(CliError::Other(format!(
+            "task failed: {}",
+            reason.as_str().unwrap_or_default()
+        ))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,114 @@
+//! `--output json|table` rendering of API responses.
+
+use clap::ValueEnum;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    Table,
+}
+
+impl OutputFormat {
+    pub fn render(self, value: &Value) -> String {
+        match self {
+            OutputFormat::Json => {
+                serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+            }
+            OutputFormat::Table => table(value),
+        }
+    }
+}
+
+/// An array of objects becomes one row per element, an object one row per
+/// field; nested values are shown compactly as JSON.
+pub fn table(value: &Value) -> String {
+    match value {
+        Value::Array(rows) if rows.iter().all(Value::is_object) && !rows.is_empty() => {
+            let mut columns: Vec<&str> = Vec::new();
+            for row in rows.iter().filter_map(Value::as_object) {
+                for key in row.keys() {
+                    if !columns.contains(&key.as_str()) {
+                        columns.push(key);
+                    }
+                }
+            }
+            let cells = rows
+                .iter()
+                .map(|row| {
+                    columns
+                        .iter()
+                        .map(|column| row.get(*column).map_or_else(String::new, cell))
+                        .collect()
+                })
+                .collect();
+            grid(
+                columns.iter().map(|column| column.to_uppercase()).collect(),
+                cells,
+            )
+        }
+        Value::Array(items) => items.iter().map(cell).collect::<Vec<_>>().join("\n"),
+        Value::Object(fields) => grid(
+            vec!["FIELD".to_string(), "VALUE".to_string()],
+            fields
+                .iter()
+                .map(|(key, value)| vec![key.clone(), cell(value)])
+                .collect(),
+        ),
+        scalar => cell(scalar),
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn grid(header: Vec<String>, rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|title| title.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    std::iter::once(&header)
+        .chain(&rows)
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_renders_tables() {
+        let tasks = json!([
+            { "id": "a1", "status": "Pending" },
+            { "id": "b2", "status": { "Failed": "boom" } },
+        ]);
+        assert_eq!(
+            table(&tasks),
+            "ID  STATUS\na1  Pending\nb2  {\"Failed\":\"boom\"}"
+        );
+        assert_eq!(
+            table(&json!({ "registered": true, "rule_count": 2 })),
+            "FIELD       VALUE\nregistered  true\nrule_count  2"
+        );
+        assert_eq!(table(&json!(["boost", "damp"])), "boost\ndamp");
+        assert_eq!(table(&json!([])), "");
+    }
+}
@@ -0,0 +1,95 @@
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+pub const HBAR: f64 = 1.054_571_817e-34; // J·s
+pub const C: f64 = 299_792_458.0; // m/s
+pub const ZITTER_FREQUENCY: f64 = 1.55e21; // rad/s
+pub const ZITTER_AMPLITUDE: f64 = 1.93e-13; // m
+pub const FINE_STRUCTURE: f64 = 1.0 / 137.035_999_084;
+
+/// Constants the emergence model is computed from. SI by default; any
+/// consistent set works, e.g. `hbar = c = 1` for natural-unit or toy-model
+/// runs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PhysicalConstants {
+    pub hbar: f64,
+    pub c: f64,
+    pub zitter_frequency: f64,
+    pub zitter_amplitude: f64,
+    pub fine_structure: f64,
+}
+
+impl PhysicalConstants {
+    pub const SI: Self = Self {
+        hbar: HBAR,
+        c: C,
+        zitter_frequency: ZITTER_FREQUENCY,
+        zitter_amplitude: ZITTER_AMPLITUDE,
+        fine_structure: FINE_STRUCTURE,
+    };
+
+    /// SI values overridden by `MMSS_HBAR`, `MMSS_SPEED_OF_LIGHT`,
+    /// `MMSS_ZITTER_FREQUENCY`, `MMSS_ZITTER_AMPLITUDE` and
+    /// `MMSS_FINE_STRUCTURE`. A value that is not a positive number is
+    /// ignored with a warning.
+    #[cfg(feature = "std")]
+    pub fn from_env() -> Self {
+        let read = |name: &str, default: f64| match std::env::var(name) {
+            Ok(raw) => match raw.parse::<f64>() {
+                Ok(value) if value.is_finite() && value > 0.0 => value,
+                _ => {
+                    log::warn!("Ignoring {}={}, not a positive number", name, raw);
+                    default
+                }
+            },
+            Err(_) => default,
+        };
+        let si = Self::SI;
+        Self {
+            hbar: read("MMSS_HBAR", si.hbar),
+            c: read("MMSS_SPEED_OF_LIGHT", si.c),
+            zitter_frequency: read("MMSS_ZITTER_FREQUENCY", si.zitter_frequency),
+            zitter_amplitude: read("MMSS_ZITTER_AMPLITUDE", si.zitter_amplitude),
+            fine_structure: read("MMSS_FINE_STRUCTURE", si.fine_structure),
+        }
+    }
+
+    /// Names of the constants that are not finite and positive.
+    pub fn invalid(&self) -> Vec<&'static str> {
+        [
+            ("hbar", self.hbar),
+            ("c", self.c),
+            ("zitter_frequency", self.zitter_frequency),
+            ("zitter_amplitude", self.zitter_amplitude),
+            ("fine_structure", self.fine_structure),
+        ]
+        .into_iter()
+        .filter(|(_, value)| !(value.is_finite() && *value > 0.0))
+        .map(|(name, _)| name)
+        .collect()
+    }
+
+    /// `ħ / 2cA` for a zitterbewegung amplitude `A`.
+    pub fn electron_mass_at(&self, amplitude: f64) -> f64 {
+        self.hbar / (2.0 * self.c * amplitude)
+    }
+
+    pub fn electron_mass(&self) -> f64 {
+        self.electron_mass_at(self.zitter_amplitude)
+    }
+}
+
+impl Default for PhysicalConstants {
+    fn default() -> Self {
+        Self::SI
+    }
+}
+
+pub fn compute_quaternion_coherence() -> f64 {
+    0.9997
+}
+
+pub fn compute_zitter_entropy() -> f64 {
+    0.0003
+}
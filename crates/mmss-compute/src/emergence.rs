@@ -0,0 +1,197 @@
+//! The operators of the cascade that depend only on the metrics, the
+//! constants and the task parameters. `EmergenceLogic` in the server runs
+//! these and adds the ones needing a script runner, a lattice or a plugin.
+
+use crate::constants::{compute_quaternion_coherence, compute_zitter_entropy, PhysicalConstants};
+use crate::types::{GeometricMetrics, GeometricOperator, MetricMap};
+use alloc::format;
+use libm::{fabs, sin, sqrt};
+use serde_json::Value;
+
+/// Metrics before any operator has run.
+pub fn baseline_metrics(constants: &PhysicalConstants) -> GeometricMetrics {
+    let coherence = compute_quaternion_coherence();
+    let entropy = compute_zitter_entropy();
+    let electron_mass = constants.electron_mass();
+    let fine_structure = constants.fine_structure;
+    let default_winding = 8.9997;
+
+    GeometricMetrics {
+        v_geometric: coherence,
+        s_geometric: entropy,
+        q_oscillator: default_winding,
+        quaternion_coherence: coherence,
+        emergent_electron_mass: electron_mass,
+        fine_structure_constant: fine_structure,
+        zitterbewegung_entropy: entropy,
+        topological_winding: default_winding,
+        custom_metrics: MetricMap::new(),
+    }
+}
+
+/// Apply `op` to `metrics` if it is one of the pure operators; `false`,
+/// leaving `metrics` alone, for the others.
+pub fn apply_pure_operator(
+    metrics: &mut GeometricMetrics,
+    constants: &PhysicalConstants,
+    op: GeometricOperator,
+    params: &Value,
+) -> bool {
+    let magnitude = extract_scalar(params).unwrap_or(1.0);
+
+    match op {
+        GeometricOperator::QuaternionRotation => {
+            let theta = params
+                .get("theta")
+                .and_then(Value::as_f64)
+                .unwrap_or(magnitude);
+            let axis = params
+                .get("axis")
+                .and_then(Value::as_array)
+                .and_then(|arr| normalize_axis(arr))
+                .unwrap_or([0.0, 1.0, 0.0]);
+
+            let axis_norm = sqrt(axis[0] * axis[0] + axis[1] * axis[1] + axis[2] * axis[2]);
+            let coherence_boost = fabs(sin(theta * 0.5)) * 0.005 * axis_norm.max(1e-6);
+
+            metrics.quaternion_coherence =
+                (metrics.quaternion_coherence + coherence_boost).clamp(0.0, 0.9999);
+            metrics.v_geometric = metrics.quaternion_coherence;
+        }
+        GeometricOperator::Zitterbewegung => {
+            let freq_scale = params
+                .get("frequency_scale")
+                .and_then(Value::as_f64)
+                .unwrap_or(fabs(magnitude));
+            let scaled_amplitude = fabs(constants.zitter_amplitude / freq_scale.max(1e-6));
+
+            metrics.emergent_electron_mass = constants.electron_mass_at(scaled_amplitude);
+            metrics.topological_winding =
+                (metrics.topological_winding + (freq_scale - 1.0) * 0.0001).max(0.0);
+            metrics.q_oscillator = metrics.topological_winding.max(0.0);
+        }
+        GeometricOperator::GeometricDerivation => {
+            let delta = params
+                .get("delta")
+                .and_then(Value::as_f64)
+                .unwrap_or(magnitude);
+            metrics.s_geometric = (metrics.s_geometric + delta * 0.001).clamp(0.0001, 1.0);
+            metrics.zitterbewegung_entropy = metrics.s_geometric;
+        }
+        GeometricOperator::SemanticSynthesis => {
+            let coherence_hint = params
+                .get("coherence_hint")
+                .and_then(Value::as_f64)
+                .unwrap_or(0.95);
+            let anchor_name = params
+                .get("anchor")
+                .and_then(Value::as_str)
+                .unwrap_or("quantum-atom");
+
+            let semantic_strength = (metrics.quaternion_coherence * coherence_hint * 10.0).max(0.0);
+            metrics
+                .custom_metrics
+                .insert(format!("anchor:{}", anchor_name), semantic_strength);
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Derived metrics recomputed after every operator, and the ones an
+/// operator zeroed restored from the constants.
+pub fn settle(metrics: &mut GeometricMetrics, constants: &PhysicalConstants) {
+    metrics.fine_structure_constant =
+        (constants.fine_structure / metrics.quaternion_coherence.max(1e-6)).min(1.0);
+    if metrics.zitterbewegung_entropy <= 0.0 {
+        metrics.zitterbewegung_entropy = compute_zitter_entropy();
+    }
+    if metrics.emergent_electron_mass <= 0.0 {
+        metrics.emergent_electron_mass = constants.electron_mass();
+    }
+    if metrics.quaternion_coherence <= 0.0 {
+        metrics.quaternion_coherence = compute_quaternion_coherence();
+    }
+    if metrics.topological_winding <= 0.0 {
+        metrics.topological_winding = metrics.q_oscillator;
+    }
+}
+
+/// The metrics `op` would leave behind, starting from `metrics`; `None`
+/// when `op` needs the server to run.
+pub fn preview(
+    metrics: &GeometricMetrics,
+    constants: &PhysicalConstants,
+    op: GeometricOperator,
+    params: &Value,
+) -> Option<GeometricMetrics> {
+    let mut metrics = metrics.clone();
+    if !apply_pure_operator(&mut metrics, constants, op, params) {
+        return None;
+    }
+    settle(&mut metrics, constants);
+    Some(metrics)
+}
+
+fn normalize_axis(arr: &[Value]) -> Option<[f64; 3]> {
+    if arr.len() < 3 {
+        return None;
+    }
+
+    let x = arr.first().and_then(Value::as_f64)?;
+    let y = arr.get(1).and_then(Value::as_f64)?;
+    let z = arr.get(2).and_then(Value::as_f64)?;
+    Some([x, y, z])
+}
+
+fn extract_scalar(params: &Value) -> Option<f64> {
+    if let Some(val) = params.as_f64() {
+        return Some(val);
+    }
+
+    if let Some(obj) = params.as_object() {
+        for key in ["magnitude", "value", "amount", "scale"] {
+            if let Some(v) = obj.get(key).and_then(Value::as_f64) {
+                return Some(v);
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_preview_rotation_raises_coherence() {
+        let baseline = baseline_metrics(&PhysicalConstants::SI);
+        let previewed = preview(
+            &baseline,
+            &PhysicalConstants::SI,
+            GeometricOperator::QuaternionRotation,
+            &json!({ "theta": 1.0 }),
+        )
+        .unwrap();
+        assert!(previewed.quaternion_coherence > baseline.quaternion_coherence);
+        assert_eq!(previewed.v_geometric, previewed.quaternion_coherence);
+        assert!(previewed.fine_structure_constant < 1.0);
+    }
+
+    #[test]
+    fn test_preview_refuses_server_operators() {
+        let baseline = baseline_metrics(&PhysicalConstants::SI);
+        for op in [
+            GeometricOperator::CustomPythonScript,
+            GeometricOperator::GenerateHopfionField,
+            GeometricOperator::PluginOperator,
+        ] {
+            assert_eq!(
+                preview(&baseline, &PhysicalConstants::SI, op, &json!({})),
+                None
+            );
+        }
+    }
+}
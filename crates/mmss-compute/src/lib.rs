@@ -0,0 +1,25 @@
+//! The pure computation of MMSS: geometric types, quaternion math, the
+//! operators of the SYS7-SYS1 cascade that need no server state, and the
+//! EQGFT sensitivity formulas.
+//!
+//! `no_std` without the default `std` feature, so it also compiles to
+//! `wasm32-unknown-unknown`; with `wasm` it exports the bindings the
+//! dashboard uses to preview an operator before submitting the task. The
+//! server and `mmss-eqgft` build on it, so a preview applies the same
+//! operator math as the server; the metric rules and plugins the server
+//! runs afterwards are not part of it.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod constants;
+pub mod emergence;
+pub mod quaternion;
+pub mod sensitivity;
+pub mod types;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use constants::PhysicalConstants;
+pub use types::{GeometricMetrics, GeometricOperator, MetricMap, Quaternion};
@@ -1,5 +1,6 @@
-use crate::core::types::Quaternion;
-use std::f64::consts::PI;
+use crate::types::Quaternion;
+use core::f64::consts::PI;
+use libm::{acos, asin, atan2, copysign, cos, fabs, sin, sqrt};
 
 impl Quaternion {
     /// Create a new quaternion
@@ -10,9 +11,9 @@ impl Quaternion {
     /// Create a quaternion from axis-angle representation
     pub fn from_axis_angle(axis: [f64; 3], angle_rad: f64) -> Self {
         let half_angle = angle_rad / 2.0;
-        let sin_half = half_angle.sin();
+        let sin_half = sin(half_angle);
         let [x, y, z] = axis;
-        let norm = (x * x + y * y + z * z).sqrt();
+        let norm = sqrt(x * x + y * y + z * z);
 
         if norm < 1e-10 {
             return Self::identity();
@@ -20,7 +21,7 @@ impl Quaternion {
 
         let s = sin_half / norm;
         Self {
-            w: half_angle.cos(),
+            w: cos(half_angle),
             x: x * s,
             y: y * s,
             z: z * s,
@@ -59,7 +60,7 @@ impl Quaternion {
 
     /// Quaternion norm (length)
     pub fn norm(&self) -> f64 {
-        (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+        sqrt(self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z)
     }
 
     /// Normalize the quaternion
@@ -96,20 +97,20 @@ impl Quaternion {
         // Roll (x-axis rotation)
         let sinr_cosp = 2.0 * (self.w * self.x + self.y * self.z);
         let cosr_cosp = 1.0 - 2.0 * (self.x * self.x + self.y * self.y);
-        let roll = sinr_cosp.atan2(cosr_cosp);
+        let roll = atan2(sinr_cosp, cosr_cosp);
 
         // Pitch (y-axis rotation)
         let sinp = 2.0 * (self.w * self.y - self.z * self.x);
-        let pitch = if sinp.abs() >= 1.0 {
-            (PI / 2.0).copysign(sinp) // Use 90 degrees if out of range
+        let pitch = if fabs(sinp) >= 1.0 {
+            copysign(PI / 2.0, sinp) // Use 90 degrees if out of range
         } else {
-            sinp.asin()
+            asin(sinp)
         };
 
         // Yaw (z-axis rotation)
         let siny_cosp = 2.0 * (self.w * self.z + self.x * self.y);
         let cosy_cosp = 1.0 - 2.0 * (self.y * self.y + self.z * self.z);
-        let yaw = siny_cosp.atan2(cosy_cosp);
+        let yaw = atan2(siny_cosp, cosy_cosp);
 
         (roll, pitch, yaw)
     }
@@ -139,12 +140,12 @@ impl Quaternion {
         }
 
         // Since dot is in range [0, DOT_THRESHOLD], acos is safe
-        let theta_0 = dot.acos();
+        let theta_0 = acos(dot);
         let theta = theta_0 * t;
-        let sin_theta = theta.sin();
-        let sin_theta_0 = theta_0.sin();
+        let sin_theta = sin(theta);
+        let sin_theta_0 = sin(theta_0);
 
-        let s1 = (theta_0 - theta).cos() - dot * sin_theta / sin_theta_0;
+        let s1 = cos(theta_0 - theta) - dot * sin_theta / sin_theta_0;
         let s2 = sin_theta / sin_theta_0;
 
         Quaternion {
@@ -0,0 +1,68 @@
+//! Expected reach of the EQGFT polarization-asymmetry measurement, from the
+//! error formula alone; `mmss-eqgft` simulates the measurements themselves.
+
+use alloc::vec::Vec;
+use libm::{ceil, exp, fabs, hypot, log, round, sqrt};
+
+/// Fine-structure constant α.
+pub const ALPHA: f64 = 0.007_297_352_569_3;
+
+/// EQGFT prediction 𝒜 = κα.
+pub fn predicted_asymmetry(kappa: f64) -> f64 {
+    kappa * ALPHA
+}
+
+/// Statistical error `√((3 - 𝒜²)/N)` of an asymmetry measured with
+/// `n_events`.
+pub fn expected_stat_error(asymmetry: f64, n_events: usize) -> f64 {
+    sqrt((3.0 - asymmetry * asymmetry) / n_events as f64)
+}
+
+/// Significance `|𝒜| / δ𝒜` expected for a true asymmetry `asymmetry`
+/// measured with `n_events`, given the absolute systematic error.
+pub fn expected_significance(asymmetry: f64, n_events: usize, systematic_error: f64) -> f64 {
+    fabs(asymmetry) / hypot(expected_stat_error(asymmetry, n_events), systematic_error)
+}
+
+/// Events needed before the expected significance of a true asymmetry
+/// `asymmetry` against the null reaches `target_sigma`, given the absolute
+/// systematic error. `None` when systematics alone prevent reaching it.
+pub fn required_events(asymmetry: f64, systematic_error: f64, target_sigma: f64) -> Option<u64> {
+    // |𝒜| / √((3 - 𝒜²)/N + σ_sys²) = Z  ⇒  N = (3 - 𝒜²) / ((𝒜/Z)² - σ_sys²)
+    let ratio = asymmetry / target_sigma;
+    let reachable = ratio * ratio - systematic_error * systematic_error;
+    if !(target_sigma > 0.0 && reachable > 0.0) {
+        return None;
+    }
+    Some(ceil((3.0 - asymmetry * asymmetry) / reachable) as u64)
+}
+
+/// `count` sample sizes spaced logarithmically between `min` and `max`,
+/// like `np.logspace(3, 6, 50)` in the prototype.
+pub fn log_spaced_events(min: usize, max: usize, count: usize) -> Vec<usize> {
+    let (low, high) = (log(min.max(1) as f64), log(max.max(1) as f64));
+    (0..count)
+        .map(|i| {
+            let t = if count > 1 {
+                i as f64 / (count - 1) as f64
+            } else {
+                0.0
+            };
+            round(exp(low + t * (high - low))) as usize
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_events_reach_target() {
+        let asymmetry = predicted_asymmetry(0.2);
+        let events = required_events(asymmetry, 0.0, 5.0).unwrap();
+        assert!(expected_significance(asymmetry, events as usize, 0.0) >= 5.0);
+        assert!(expected_significance(asymmetry, events as usize - 1, 0.0) < 5.0);
+        assert_eq!(required_events(asymmetry, asymmetry, 5.0), None);
+    }
+}
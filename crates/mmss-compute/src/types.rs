@@ -0,0 +1,67 @@
+use alloc::string::String;
+use serde::{Deserialize, Serialize};
+
+/// Custom metrics by name, in name order with or without `std`.
+pub type MetricMap = alloc::collections::BTreeMap<String, f64>;
+
+/// Geometric operators for the MMSS system
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GeometricOperator {
+    /// Quaternion rotation operator (⟲Q)
+    QuaternionRotation,
+    /// Zitterbewegung operator (⥁Z)
+    Zitterbewegung,
+    /// Geometric derivation operator (⇛G)
+    GeometricDerivation,
+    /// Semantic synthesis operator (⥂S)
+    SemanticSynthesis,
+    /// User- or model-supplied Python script, gated by `ScriptPolicy`
+    CustomPythonScript,
+    /// Hopfion soliton field construction (⊛H)
+    GenerateHopfionField,
+    /// Monte Carlo polarization-asymmetry measurement (𝒜 = κα)
+    SimulateEqgftAsymmetry,
+    /// Asymmetry measurements across a range of κ, i.e. a full exclusion curve
+    SimulateEqgftKappaScan,
+    /// Asymmetry measured in several channels and combined by BLUE
+    CombineEqgftChannels,
+    /// Operator of a plugin, named by the `operator` parameter
+    PluginOperator,
+}
+
+/// Quaternion type for geometric operations
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// Geometric metrics for system monitoring
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeometricMetrics {
+    /// Geometric volume metric
+    pub v_geometric: f64,
+    /// Geometric stability metric
+    pub s_geometric: f64,
+    /// Oscillator quality factor
+    pub q_oscillator: f64,
+    /// Quaternion coherence (SYS7)
+    #[serde(default)]
+    pub quaternion_coherence: f64,
+    /// Emergent electron mass from zitterbewegung
+    #[serde(default)]
+    pub emergent_electron_mass: f64,
+    /// Fine structure constant derived from geometry
+    #[serde(default)]
+    pub fine_structure_constant: f64,
+    /// Zitterbewegung entropy (SYS6)
+    #[serde(default)]
+    pub zitterbewegung_entropy: f64,
+    /// Topological winding number (SYS5)
+    #[serde(default)]
+    pub topological_winding: f64,
+    /// Additional custom metrics
+    pub custom_metrics: MetricMap,
+}
@@ -0,0 +1,63 @@
+//! Bindings for the dashboard. Values cross as JSON text, shaped like the
+//! server's: metrics as `GET /api/metrics` returns them, constants as the
+//! `[constants]` config section, operators by their variant name.
+
+use crate::constants::PhysicalConstants;
+use crate::emergence::preview;
+use crate::sensitivity::{self, predicted_asymmetry};
+use crate::types::{GeometricMetrics, GeometricOperator};
+use alloc::format;
+use alloc::string::{String, ToString};
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+fn parse<T: serde::de::DeserializeOwned>(what: &str, json: &str) -> Result<T, JsValue> {
+    serde_json::from_str(json).map_err(|e| JsValue::from_str(&format!("{what}: {e}")))
+}
+
+/// The metrics `operator` would produce from `metrics`, as JSON. Empty
+/// `params` means none, empty `constants` SI. Fails for operators only the
+/// server can run.
+#[wasm_bindgen(js_name = previewOperator)]
+pub fn preview_operator(
+    metrics: &str,
+    operator: &str,
+    params: &str,
+    constants: &str,
+) -> Result<String, JsValue> {
+    let metrics: GeometricMetrics = parse("metrics", metrics)?;
+    let op: GeometricOperator = serde_json::from_value(Value::String(operator.to_string()))
+        .map_err(|_| JsValue::from_str(&format!("unknown operator {operator}")))?;
+    let params: Value = if params.trim().is_empty() {
+        Value::Null
+    } else {
+        parse("params", params)?
+    };
+    let constants: PhysicalConstants = if constants.trim().is_empty() {
+        PhysicalConstants::SI
+    } else {
+        parse("constants", constants)?
+    };
+
+    let previewed = preview(&metrics, &constants, op, &params)
+        .ok_or_else(|| JsValue::from_str(&format!("{operator} runs on the server only")))?;
+    serde_json::to_string(&previewed).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Significance expected at coupling `kappa` with `n_events`.
+#[wasm_bindgen(js_name = expectedSignificance)]
+pub fn expected_significance(kappa: f64, n_events: u32, systematic_error: f64) -> f64 {
+    sensitivity::expected_significance(
+        predicted_asymmetry(kappa),
+        n_events as usize,
+        systematic_error,
+    )
+}
+
+/// Events needed at coupling `kappa` for `target_sigma`; undefined when
+/// systematics alone prevent reaching it.
+#[wasm_bindgen(js_name = requiredEvents)]
+pub fn required_events(kappa: f64, systematic_error: f64, target_sigma: f64) -> Option<f64> {
+    sensitivity::required_events(predicted_asymmetry(kappa), systematic_error, target_sigma)
+        .map(|events| events as f64)
+}
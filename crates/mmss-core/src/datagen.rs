@@ -0,0 +1,252 @@
+//! Reproducible synthetic records for integration environments.
+//!
+//! Records get ids from 0, kinds in turn from `kinds`, timestamps evenly
+//! spread over `[start, end)` and payloads drawn from a schema, every
+//! random value coming from one generator seeded with `seed`: the same
+//! configuration always yields the same records.
+
+use crate::record::{Kind, RecordError};
+use crate::structex_bridge::MmssRecord;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DatagenError {
+    #[error("At least one kind is required")]
+    NoKinds,
+    #[error("Time range [{0}, {1}) is empty")]
+    EmptyRange(i64, i64),
+    #[error("Field '{0}': {1}")]
+    InvalidField(String, String),
+    #[error(transparent)]
+    Record(#[from] RecordError),
+}
+
+/// How one payload field is drawn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FieldSpec {
+    /// Uniform in `[min, max)`.
+    Float { min: f64, max: f64 },
+    /// Uniform in `[min, max]`.
+    Int { min: i64, max: i64 },
+    /// One of `values`, uniformly.
+    Choice { values: Vec<JsonValue> },
+    /// `true` with `probability`.
+    Bool { probability: f64 },
+    /// The record's position in the dataset, plus `offset`.
+    Index {
+        #[serde(default)]
+        offset: i64,
+    },
+}
+
+impl FieldSpec {
+    fn validate(&self, name: &str) -> Result<(), DatagenError> {
+        let invalid = |reason: &str| {
+            Err(DatagenError::InvalidField(
+                name.to_string(),
+                reason.to_string(),
+            ))
+        };
+        match self {
+            FieldSpec::Float { min, max } if min.is_nan() || max.is_nan() || min >= max => {
+                invalid("min must be below max")
+            }
+            FieldSpec::Int { min, max } if min > max => invalid("min must not exceed max"),
+            FieldSpec::Choice { values } if values.is_empty() => {
+                invalid("no values to choose from")
+            }
+            FieldSpec::Bool { probability } if !(0.0..=1.0).contains(probability) => {
+                invalid("probability must be within [0, 1]")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn draw(&self, index: u64, rng: &mut StdRng) -> JsonValue {
+        match self {
+            FieldSpec::Float { min, max } => JsonValue::from(rng.gen_range(*min..*max)),
+            FieldSpec::Int { min, max } => JsonValue::from(rng.gen_range(*min..=*max)),
+            FieldSpec::Choice { values } => values[rng.gen_range(0..values.len())].clone(),
+            FieldSpec::Bool { probability } => JsonValue::from(rng.gen_bool(*probability)),
+            FieldSpec::Index { offset } => JsonValue::from(index as i64 + offset),
+        }
+    }
+}
+
+/// Payload fields by name, drawn in name order.
+pub type PayloadSchema = BTreeMap<String, FieldSpec>;
+
+/// Payload fields of every record, and per kind on top of them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatagenSchema {
+    pub fields: PayloadSchema,
+    /// Fields added to, or replacing shared ones in, records of a kind.
+    pub kinds: BTreeMap<String, PayloadSchema>,
+}
+
+impl DatagenSchema {
+    /// `value`, `unit` and `host`, as in the original example data.
+    pub fn metrics() -> Self {
+        let choice = |values: &[&str]| FieldSpec::Choice {
+            values: values.iter().map(|value| JsonValue::from(*value)).collect(),
+        };
+        Self {
+            fields: BTreeMap::from([
+                (
+                    "value".to_string(),
+                    FieldSpec::Float {
+                        min: 0.0,
+                        max: 100.0,
+                    },
+                ),
+                ("unit".to_string(), choice(&["%"])),
+                (
+                    "host".to_string(),
+                    choice(&["host-1", "host-2", "host-3", "host-4", "host-5"]),
+                ),
+            ]),
+            kinds: BTreeMap::from([(
+                "network".to_string(),
+                BTreeMap::from([("unit".to_string(), choice(&["MB/s"]))]),
+            )]),
+        }
+    }
+
+    fn for_kind(&self, kind: &str) -> PayloadSchema {
+        let mut fields = self.fields.clone();
+        if let Some(extra) = self.kinds.get(kind) {
+            fields.extend(
+                extra
+                    .iter()
+                    .map(|(name, spec)| (name.clone(), spec.clone())),
+            );
+        }
+        fields
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatagenConfig {
+    pub count: u64,
+    pub kinds: Vec<String>,
+    /// First timestamp, in the unit the dataset uses (seconds or ms).
+    pub start: i64,
+    /// Timestamps stay below this.
+    pub end: i64,
+    pub seed: u64,
+    pub schema: DatagenSchema,
+}
+
+impl Default for DatagenConfig {
+    fn default() -> Self {
+        Self {
+            count: 100,
+            kinds: ["cpu", "memory", "network", "disk"]
+                .map(String::from)
+                .to_vec(),
+            start: 1_732_400_000,
+            end: 1_732_400_000 + 100 * 60,
+            seed: 0,
+            schema: DatagenSchema::metrics(),
+        }
+    }
+}
+
+/// The records `config` describes.
+pub fn generate(config: &DatagenConfig) -> Result<Vec<MmssRecord>, DatagenError> {
+    if config.kinds.is_empty() {
+        return Err(DatagenError::NoKinds);
+    }
+    if config.end <= config.start {
+        return Err(DatagenError::EmptyRange(config.start, config.end));
+    }
+    let kinds = config
+        .kinds
+        .iter()
+        .map(|kind| {
+            let schema = config.schema.for_kind(kind);
+            for (name, spec) in &schema {
+                spec.validate(name)?;
+            }
+            Ok((Kind::new(kind.as_str())?, schema))
+        })
+        .collect::<Result<Vec<_>, DatagenError>>()?;
+
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let span = (config.end - config.start) as i128;
+    (0..config.count)
+        .map(|index| {
+            let (kind, schema) = &kinds[(index % kinds.len() as u64) as usize];
+            let offset = span * index as i128 / config.count as i128;
+            let payload: Map<String, JsonValue> = schema
+                .iter()
+                .map(|(name, spec)| (name.clone(), spec.draw(index, &mut rng)))
+                .collect();
+            Ok(MmssRecord::builder()
+                .id(index)
+                .kind(kind.clone())
+                .timestamp(config.start + offset as i64)
+                .payload(JsonValue::Object(payload))
+                .build()?)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_records() {
+        let config = DatagenConfig {
+            count: 8,
+            start: 0,
+            end: 80,
+            seed: 7,
+            ..DatagenConfig::default()
+        };
+        let records = generate(&config).unwrap();
+        assert_eq!(records, generate(&config).unwrap());
+        assert_ne!(
+            records,
+            generate(&DatagenConfig {
+                seed: 8,
+                ..config.clone()
+            })
+            .unwrap()
+        );
+
+        assert_eq!(records[2].kind, "network");
+        assert_eq!(records[2].payload["unit"], "MB/s");
+        assert_eq!(records[1].payload["unit"], "%");
+        assert_eq!(
+            records.iter().map(|r| r.timestamp).collect::<Vec<_>>(),
+            vec![0, 10, 20, 30, 40, 50, 60, 70]
+        );
+        let value = records[0].payload["value"].as_f64().unwrap();
+        assert!((0.0..100.0).contains(&value));
+    }
+
+    #[test]
+    fn test_rejects_invalid_fields() {
+        let schema: DatagenSchema = serde_json::from_value(serde_json::json!({
+            "fields": { "n": { "type": "int", "min": 5, "max": 1 } }
+        }))
+        .unwrap();
+        let config = DatagenConfig {
+            schema,
+            ..DatagenConfig::default()
+        };
+        assert!(matches!(
+            generate(&config),
+            Err(DatagenError::InvalidField(name, _)) if name == "n"
+        ));
+    }
+}
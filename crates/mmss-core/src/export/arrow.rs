@@ -1,37 +1,379 @@
 ﻿use arrow2::{
-    array::{Array, Int64Array, StringArray, UInt64Array},
+    array::{Array, Int64Array, UInt64Array, Utf8Array},
     chunk::Chunk,
     datatypes::{DataType, Field, Schema},
-    io::ipc::write::{FileWriter, WriteOptions},
+    io::ipc::read::{read_file_metadata, FileReader},
+    io::ipc::write::{Compression, FileWriter, StreamWriter, WriteOptions as IpcWriteOptions},
 };
-use std::{fs::File, path::Path};
+use std::{fs::File, io::{BufReader, Write}, path::Path};
 use crate::structex_bridge::MmssRecord;
 
-pub fn write_records_to_file(path: &Path, records: &[MmssRecord]) -> Result<(), Box<dyn std::error::Error>> {
-    let file = File::create(path)?;
-    let schema = Schema::from(vec![
+/// Version of the record schema written by this crate. Files without the
+/// version key predate versioning and count as version 0; `migrate` upgrades
+/// them.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Schema metadata key holding the version.
+pub const SCHEMA_VERSION_KEY: &str = "mmss.schema_version";
+
+/// Columns of an exported record file; `payload` holds the JSON text.
+pub(crate) fn record_schema() -> Schema {
+    Schema::from(vec![
         Field::new("id", DataType::UInt64, false),
         Field::new("kind", DataType::Utf8, false),
         Field::new("timestamp", DataType::Int64, false),
         Field::new("payload", DataType::Utf8, false),
-    ]);
-
-    let mut writer = FileWriter::try_new(file, &schema, None, Default::default())?;
-    let ids: Vec<_> = records.iter().map(|r| r.id).collect();
-    let kinds: Vec<_> = records.iter().map(|r| r.kind.as_str()).collect();
-    let timestamps: Vec<_> = records.iter().map(|r| r.timestamp).collect();
-    let payloads: Vec<_> = records.iter().map(|r| serde_json::to_string(&r.payload).unwrap()).collect();
-    let id_array = UInt64Array::from_slice(&ids);
-    let kind_array = StringArray::from_slice(kinds);
-    let timestamp_array = Int64Array::from_slice(&timestamps);
-    let payload_array = StringArray::from_slice(payloads);
-    let chunk = Chunk::try_new(vec![
-        &id_array as &dyn Array,
-        &kind_array,
-        &timestamp_array,
-        &payload_array,
-    ])?;
-    writer.write(&chunk, None)?;
+    ])
+    .with_metadata([(SCHEMA_VERSION_KEY.to_string(), SCHEMA_VERSION.to_string())].into())
+}
+
+/// The version recorded in a file's schema metadata, 0 when there is none.
+pub fn schema_version(schema: &Schema) -> Result<u32, Box<dyn std::error::Error>> {
+    match schema.metadata.get(SCHEMA_VERSION_KEY) {
+        None => Ok(0),
+        Some(version) => version
+            .parse()
+            .map_err(|_| format!("invalid {} '{}'", SCHEMA_VERSION_KEY, version).into()),
+    }
+}
+
+pub fn write_records_to_file(path: &Path, records: &[MmssRecord]) -> Result<(), Box<dyn std::error::Error>> {
+    write_records_to_file_with(path, records, &WriteOptions::default())
+}
+
+/// `write_records_to_file` with explicit options, e.g. compression.
+pub fn write_records_to_file_with(
+    path: &Path,
+    records: &[MmssRecord],
+    options: &WriteOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file = File::create(path)?;
+    let mut writer = RecordWriter::try_with_options(file, IpcFormat::File, records.len().max(1), options)?;
+    writer.write(records)?;
     writer.finish()?;
     Ok(())
 }
+
+/// Codec for IPC record batch bodies. Readers must support it too; arrow2,
+/// pyarrow and arrow-rs all read both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpcCompression {
+    /// Fast, with modest ratios.
+    Lz4,
+    /// Slower, and much smaller on repetitive payloads such as field samples.
+    Zstd,
+}
+
+/// Options for `write_records_to_file_with` and `RecordWriter::try_with_options`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WriteOptions {
+    /// Uncompressed when `None`.
+    pub compression: Option<IpcCompression>,
+}
+
+impl WriteOptions {
+    fn to_ipc(self) -> IpcWriteOptions {
+        IpcWriteOptions {
+            compression: self.compression.map(|compression| match compression {
+                IpcCompression::Lz4 => Compression::LZ4,
+                IpcCompression::Zstd => Compression::ZSTD,
+            }),
+        }
+    }
+}
+
+/// Arrow IPC container written by `RecordWriter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpcFormat {
+    /// Random-access file with a footer, as read by `read_records_from_file`.
+    File,
+    /// Stream format, readable while it arrives through a pipe or socket.
+    Stream,
+}
+
+enum IpcWriter<W: Write> {
+    File(FileWriter<W>),
+    Stream(StreamWriter<W>),
+}
+
+/// Incremental writer that buffers records and writes them as IPC batches
+/// of `chunk_size` rows, so an export never holds more than one batch of
+/// columns in memory.
+pub struct RecordWriter<W: Write> {
+    writer: IpcWriter<W>,
+    chunk_size: usize,
+    pending: PendingColumns,
+}
+
+#[derive(Default)]
+struct PendingColumns {
+    ids: Vec<u64>,
+    kinds: Vec<String>,
+    timestamps: Vec<i64>,
+    payloads: Vec<String>,
+}
+
+impl PendingColumns {
+    fn into_chunk(self) -> arrow2::error::Result<Chunk<Box<dyn Array>>> {
+        Chunk::try_new(vec![
+            UInt64Array::from_vec(self.ids).boxed(),
+            Utf8Array::<i32>::from_slice(self.kinds).boxed(),
+            Int64Array::from_vec(self.timestamps).boxed(),
+            Utf8Array::<i32>::from_slice(self.payloads).boxed(),
+        ])
+    }
+}
+
+/// `records` as one batch of `record_schema` columns.
+pub(crate) fn records_to_chunk(records: &[MmssRecord]) -> Result<Chunk<Box<dyn Array>>, Box<dyn std::error::Error>> {
+    let mut pending = PendingColumns::default();
+    for record in records {
+        pending.ids.push(record.id);
+        pending.kinds.push(record.kind.clone());
+        pending.timestamps.push(record.timestamp);
+        pending.payloads.push(serde_json::to_string(&record.payload)?);
+    }
+    Ok(pending.into_chunk()?)
+}
+
+impl<W: Write> RecordWriter<W> {
+    pub fn try_new(writer: W, format: IpcFormat, chunk_size: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::try_with_options(writer, format, chunk_size, &WriteOptions::default())
+    }
+
+    pub fn try_with_options(
+        writer: W,
+        format: IpcFormat,
+        chunk_size: usize,
+        options: &WriteOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if chunk_size == 0 {
+            return Err("chunk_size must be positive".into());
+        }
+        let options = options.to_ipc();
+        let writer = match format {
+            IpcFormat::File => IpcWriter::File(FileWriter::try_new(writer, record_schema(), None, options)?),
+            IpcFormat::Stream => {
+                let mut stream = StreamWriter::new(writer, options);
+                stream.start(&record_schema(), None)?;
+                IpcWriter::Stream(stream)
+            }
+        };
+        Ok(Self { writer, chunk_size, pending: PendingColumns::default() })
+    }
+
+    /// Buffer `records`, writing a batch whenever `chunk_size` are pending.
+    pub fn write(&mut self, records: &[MmssRecord]) -> Result<(), Box<dyn std::error::Error>> {
+        for record in records {
+            self.pending.ids.push(record.id);
+            self.pending.kinds.push(record.kind.clone());
+            self.pending.timestamps.push(record.timestamp);
+            self.pending.payloads.push(serde_json::to_string(&record.payload)?);
+            if self.pending.ids.len() == self.chunk_size {
+                self.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the pending records as one batch, if there are any.
+    pub fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.pending.ids.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::take(&mut self.pending).into_chunk()?;
+        match &mut self.writer {
+            IpcWriter::File(writer) => writer.write(&chunk, None)?,
+            IpcWriter::Stream(writer) => writer.write(&chunk, None)?,
+        }
+        Ok(())
+    }
+
+    /// Flush, write the footer or end-of-stream marker, and hand back the
+    /// destination.
+    pub fn finish(mut self) -> Result<W, Box<dyn std::error::Error>> {
+        self.flush()?;
+        Ok(match self.writer {
+            IpcWriter::File(mut writer) => {
+                writer.finish()?;
+                writer.into_inner()
+            }
+            IpcWriter::Stream(mut writer) => {
+                writer.finish()?;
+                writer.into_inner()
+            }
+        })
+    }
+}
+
+/// Every record of a file written by `write_records_to_file`.
+pub fn read_records_from_file(path: &Path) -> Result<Vec<MmssRecord>, Box<dyn std::error::Error>> {
+    let mut records = Vec::new();
+    for chunk in read_record_chunks(path)? {
+        records.extend(chunk?);
+    }
+    Ok(records)
+}
+
+/// Records one IPC batch at a time, so large exports need not fit in memory.
+/// The schema is checked before the first batch is read.
+pub fn read_record_chunks(path: &Path) -> Result<RecordChunks, Box<dyn std::error::Error>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let metadata = read_file_metadata(&mut file)?;
+    let version = schema_version(&metadata.schema)?;
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "{} has schema version {}, newer than the supported {}",
+            path.display(),
+            version,
+            SCHEMA_VERSION
+        )
+        .into());
+    }
+    let expected = record_schema();
+    let shape = |schema: &Schema| {
+        schema
+            .fields
+            .iter()
+            .map(|f| format!("{}: {:?}{}", f.name, f.data_type, if f.is_nullable { "?" } else { "" }))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    if shape(&metadata.schema) != shape(&expected) {
+        return Err(format!(
+            "unexpected schema in {}: expected [{}], found [{}]",
+            path.display(),
+            shape(&expected),
+            shape(&metadata.schema)
+        )
+        .into());
+    }
+    Ok(RecordChunks {
+        reader: FileReader::new(file, metadata, None, None),
+    })
+}
+
+pub struct RecordChunks {
+    reader: FileReader<BufReader<File>>,
+}
+
+impl Iterator for RecordChunks {
+    type Item = Result<Vec<MmssRecord>, Box<dyn std::error::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = self.reader.next()?;
+        Some(chunk.map_err(Into::into).and_then(|chunk| chunk_to_records(&chunk)))
+    }
+}
+
+pub(crate) fn chunk_to_records(chunk: &Chunk<Box<dyn Array>>) -> Result<Vec<MmssRecord>, Box<dyn std::error::Error>> {
+    fn column<T: 'static>(chunk: &Chunk<Box<dyn Array>>, index: usize) -> Result<&T, Box<dyn std::error::Error>> {
+        chunk.arrays()[index]
+            .as_any()
+            .downcast_ref::<T>()
+            .ok_or_else(|| format!("column {} has an unexpected array type", index).into())
+    }
+    let ids = column::<UInt64Array>(chunk, 0)?;
+    let kinds = column::<Utf8Array<i32>>(chunk, 1)?;
+    let timestamps = column::<Int64Array>(chunk, 2)?;
+    let payloads = column::<Utf8Array<i32>>(chunk, 3)?;
+
+    (0..chunk.len())
+        .map(|row| {
+            Ok(MmssRecord {
+                id: ids.value(row),
+                kind: kinds.value(row).to_string(),
+                timestamp: timestamps.value(row),
+                payload: serde_json::from_str(payloads.value(row))?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_records_round_trip() {
+        let dir = std::env::temp_dir().join(format!("mmss-core-arrow-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("records.arrow");
+        let records: Vec<MmssRecord> = (0..5)
+            .map(|id| MmssRecord {
+                id,
+                kind: "metric".to_string(),
+                timestamp: 1_700_000_000 + id as i64,
+                payload: json!({ "value": id as f64 / 2.0, "tags": ["a", "b"] }),
+            })
+            .collect();
+        write_records_to_file(&path, &records).unwrap();
+
+        let read = read_records_from_file(&path).unwrap();
+        assert_eq!(read.len(), 5);
+        assert_eq!(read[3].id, 3);
+        assert_eq!(read[3].payload, records[3].payload);
+        assert_eq!(read_record_chunks(&path).unwrap().count(), 1);
+
+        let other = dir.join("other.arrow");
+        let schema = Schema::from(vec![Field::new("id", DataType::Int64, false)]);
+        let mut writer = FileWriter::try_new(File::create(&other).unwrap(), schema, None, IpcWriteOptions { compression: None }).unwrap();
+        writer.write(&Chunk::try_new(vec![Int64Array::from_slice([1]).boxed()]).unwrap(), None).unwrap();
+        writer.finish().unwrap();
+        let err = read_records_from_file(&other).unwrap_err().to_string();
+        assert!(err.contains("unexpected schema"), "{}", err);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compressed_files_round_trip() {
+        let dir = std::env::temp_dir().join(format!("mmss-core-compressed-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let records: Vec<MmssRecord> = (0..200)
+            .map(|id| MmssRecord {
+                id,
+                kind: "field".to_string(),
+                timestamp: id as i64,
+                payload: json!({ "samples": vec![0.25; 64] }),
+            })
+            .collect();
+        let plain = dir.join("plain.arrow");
+        write_records_to_file(&plain, &records).unwrap();
+        let plain_size = std::fs::metadata(&plain).unwrap().len();
+
+        for compression in [IpcCompression::Lz4, IpcCompression::Zstd] {
+            let path = dir.join(format!("{:?}.arrow", compression));
+            let options = WriteOptions { compression: Some(compression) };
+            write_records_to_file_with(&path, &records, &options).unwrap();
+            assert!(std::fs::metadata(&path).unwrap().len() * 3 < plain_size, "{:?}", compression);
+            assert_eq!(read_records_from_file(&path).unwrap(), records);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_record_writer_flushes_chunks() {
+        use arrow2::io::ipc::read::{read_stream_metadata, StreamReader, StreamState};
+
+        let records: Vec<MmssRecord> = (0..7)
+            .map(|id| MmssRecord { id, kind: "event".to_string(), timestamp: id as i64, payload: json!(id) })
+            .collect();
+        let mut writer = RecordWriter::try_new(Vec::new(), IpcFormat::Stream, 3).unwrap();
+        writer.write(&records[..2]).unwrap();
+        writer.write(&records[2..]).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let mut cursor = std::io::Cursor::new(bytes);
+        let metadata = read_stream_metadata(&mut cursor).unwrap();
+        assert_eq!(metadata.schema, record_schema());
+        let lengths: Vec<usize> = StreamReader::new(cursor, metadata, None)
+            .map(|state| match state.unwrap() {
+                StreamState::Some(chunk) => chunk.len(),
+                StreamState::Waiting => 0,
+            })
+            .collect();
+        assert_eq!(lengths, vec![3, 3, 1]);
+        assert!(RecordWriter::try_new(Vec::new(), IpcFormat::File, 0).is_err());
+    }
+}
@@ -0,0 +1,80 @@
+//! CSV export of `MmssRecord`s for consumers without Arrow tooling.
+//!
+//! One header row `id,kind,timestamp,payload`, then one row per record with
+//! the payload as compact JSON. Fields are quoted per RFC 4180 when they
+//! contain a comma, quote or line break.
+
+use crate::structex_bridge::MmssRecord;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub fn write_records_to_csv(
+    path: &Path,
+    records: &[MmssRecord],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_csv(&mut writer, records)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the CSV for `records`, header included, to any destination.
+pub fn write_csv<W: Write>(
+    writer: &mut W,
+    records: &[MmssRecord],
+) -> Result<(), Box<dyn std::error::Error>> {
+    writer.write_all(b"id,kind,timestamp,payload\r\n")?;
+    for record in records {
+        let payload = serde_json::to_string(&record.payload)?;
+        write!(
+            writer,
+            "{},{},{},{}\r\n",
+            record.id,
+            quote(&record.kind),
+            record.timestamp,
+            quote(&payload)
+        )?;
+    }
+    Ok(())
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let records = [
+            MmssRecord {
+                id: 1,
+                kind: "metric".to_string(),
+                timestamp: 10,
+                payload: json!(2.5),
+            },
+            MmssRecord {
+                id: 2,
+                kind: "a,b".to_string(),
+                timestamp: -1,
+                payload: json!({ "note": "say \"hi\"" }),
+            },
+        ];
+        let mut out = Vec::new();
+        write_csv(&mut out, &records).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "id,kind,timestamp,payload\r\n\
+             1,metric,10,2.5\r\n\
+             2,\"a,b\",-1,\"{\"\"note\"\":\"\"say \\\"\"hi\\\"\"\"\"}\"\r\n"
+        );
+    }
+}
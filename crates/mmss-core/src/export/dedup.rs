@@ -0,0 +1,207 @@
+//! Deduplicating export: records are keyed, by default on `(id, kind)`, and
+//! a record whose key was already seen is either skipped or overwrites the
+//! earlier one in place. `upsert_file` applies the same rule against the
+//! records already in an Arrow file, so re-running a job that regenerates
+//! the same records leaves one row per key.
+
+use super::arrow::{read_records_from_file, write_records_to_file};
+use crate::structex_bridge::MmssRecord;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::Hash;
+use std::path::Path;
+
+/// What to do with a record whose key is already present.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnConflict {
+    /// Keep the record seen first.
+    #[default]
+    Skip,
+    /// Replace it with the later record, keeping its position.
+    Overwrite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MergeReport {
+    pub inserted: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+}
+
+/// The default key, `(id, kind)`.
+pub fn id_kind(record: &MmssRecord) -> (u64, String) {
+    (record.id, record.kind.clone())
+}
+
+/// Accumulates records, one per key, in first-seen order.
+pub struct Deduplicator<K, F> {
+    key: F,
+    on_conflict: OnConflict,
+    index: HashMap<K, usize>,
+    records: Vec<MmssRecord>,
+    report: MergeReport,
+}
+
+impl<K, F> Deduplicator<K, F>
+where
+    K: Eq + Hash,
+    F: Fn(&MmssRecord) -> K,
+{
+    pub fn new(key: F, on_conflict: OnConflict) -> Self {
+        Self {
+            key,
+            on_conflict,
+            index: HashMap::new(),
+            records: Vec::new(),
+            report: MergeReport::default(),
+        }
+    }
+
+    /// Seed with records already written downstream. They count towards
+    /// neither side of the report; duplicates among them are collapsed.
+    pub fn with_existing(mut self, records: Vec<MmssRecord>) -> Self {
+        for record in records {
+            self.insert(record);
+        }
+        self.report = MergeReport::default();
+        self
+    }
+
+    pub fn insert(&mut self, record: MmssRecord) {
+        match self.index.entry((self.key)(&record)) {
+            Entry::Vacant(entry) => {
+                entry.insert(self.records.len());
+                self.records.push(record);
+                self.report.inserted += 1;
+            }
+            Entry::Occupied(entry) => match self.on_conflict {
+                OnConflict::Skip => self.report.skipped += 1,
+                OnConflict::Overwrite => {
+                    self.records[*entry.get()] = record;
+                    self.report.overwritten += 1;
+                }
+            },
+        }
+    }
+
+    pub fn extend(&mut self, records: impl IntoIterator<Item = MmssRecord>) {
+        for record in records {
+            self.insert(record);
+        }
+    }
+
+    pub fn report(&self) -> MergeReport {
+        self.report
+    }
+
+    pub fn into_records(self) -> Vec<MmssRecord> {
+        self.records
+    }
+}
+
+/// `records` with one record per `(id, kind)`.
+pub fn deduplicate(records: &[MmssRecord], on_conflict: OnConflict) -> Vec<MmssRecord> {
+    let mut dedup = Deduplicator::new(id_kind, on_conflict);
+    dedup.extend(records.iter().cloned());
+    dedup.into_records()
+}
+
+/// Merge `records` into the Arrow file at `path`, which need not exist yet.
+/// The file is rewritten through a temporary file and renamed into place, so
+/// a failed write leaves the previous contents intact.
+pub fn upsert_file<K, F>(
+    path: &Path,
+    records: &[MmssRecord],
+    key: F,
+    on_conflict: OnConflict,
+) -> Result<MergeReport, Box<dyn std::error::Error>>
+where
+    K: Eq + Hash,
+    F: Fn(&MmssRecord) -> K,
+{
+    let existing = if path.exists() {
+        read_records_from_file(path)?
+    } else {
+        Vec::new()
+    };
+    let mut dedup = Deduplicator::new(key, on_conflict).with_existing(existing);
+    dedup.extend(records.iter().cloned());
+    let report = dedup.report();
+    if report.inserted == 0 && report.overwritten == 0 && path.exists() {
+        return Ok(report);
+    }
+
+    let temporary = path.with_extension("upserting");
+    if let Err(e) = write_records_to_file(&temporary, &dedup.into_records()) {
+        fs::remove_file(&temporary).ok();
+        return Err(e);
+    }
+    fs::rename(&temporary, path)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(id: u64, kind: &str, value: i64) -> MmssRecord {
+        MmssRecord {
+            id,
+            kind: kind.to_string(),
+            timestamp: 1_700_000_000_000 + value,
+            payload: json!({ "value": value }),
+        }
+    }
+
+    #[test]
+    fn test_deduplicate_skip_and_overwrite() {
+        let records = vec![
+            record(1, "cpu", 1),
+            record(1, "memory", 2),
+            record(1, "cpu", 3),
+        ];
+        let skipped = deduplicate(&records, OnConflict::Skip);
+        assert_eq!(skipped, vec![records[0].clone(), records[1].clone()]);
+        let overwritten = deduplicate(&records, OnConflict::Overwrite);
+        assert_eq!(overwritten, vec![records[2].clone(), records[1].clone()]);
+
+        // a caller-provided key: one record per kind
+        let mut by_kind = Deduplicator::new(|r: &MmssRecord| r.kind.clone(), OnConflict::Skip);
+        by_kind.extend(vec![record(1, "cpu", 1), record(2, "cpu", 2)]);
+        assert_eq!(by_kind.report().skipped, 1);
+        assert_eq!(by_kind.into_records().len(), 1);
+    }
+
+    #[test]
+    fn test_upsert_file_across_runs() {
+        let dir = std::env::temp_dir().join(format!("mmss-core-dedup-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.arrow");
+
+        let first: Vec<_> = (0..4).map(|i| record(i, "cpu", 0)).collect();
+        let report = upsert_file(&path, &first, id_kind, OnConflict::Overwrite).unwrap();
+        assert_eq!(report.inserted, 4);
+
+        let second: Vec<_> = (2..6).map(|i| record(i, "cpu", 1)).collect();
+        let report = upsert_file(&path, &second, id_kind, OnConflict::Overwrite).unwrap();
+        assert_eq!(
+            report,
+            MergeReport {
+                inserted: 2,
+                overwritten: 2,
+                skipped: 0
+            }
+        );
+        let records = read_records_from_file(&path).unwrap();
+        assert_eq!(records.len(), 6);
+        assert_eq!(records[1].payload, json!({ "value": 0 }));
+        assert_eq!(records[2].payload, json!({ "value": 1 }));
+
+        let report = upsert_file(&path, &second, id_kind, OnConflict::Skip).unwrap();
+        assert_eq!(report.skipped, 4);
+        assert_eq!(read_records_from_file(&path).unwrap(), records);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,164 @@
+//! Incremental export: remembers, per destination, the newest record already
+//! written and hands only later records to the writer on the next run, so a
+//! periodic export never writes a record twice.
+//!
+//! Records are ordered by `(timestamp, id)`; one that turns up later with a
+//! key below its destination's watermark is not exported. The watermarks are
+//! persisted as JSON next to the exports and only advance once the writer
+//! succeeded; a failed run is retried in full on the next invocation.
+
+use crate::structex_bridge::MmssRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// The last record written to a destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Watermark {
+    pub timestamp: i64,
+    pub id: u64,
+}
+
+impl Watermark {
+    pub fn of(record: &MmssRecord) -> Self {
+        Self {
+            timestamp: record.timestamp,
+            id: record.id,
+        }
+    }
+}
+
+pub struct IncrementalExporter {
+    state_path: PathBuf,
+    watermarks: BTreeMap<String, Watermark>,
+}
+
+impl IncrementalExporter {
+    /// Load the watermarks saved at `state_path`; a missing file means
+    /// nothing has been exported yet.
+    pub fn open(state_path: impl Into<PathBuf>) -> Result<Self, Box<dyn std::error::Error>> {
+        let state_path = state_path.into();
+        let watermarks = match fs::read(&state_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            state_path,
+            watermarks,
+        })
+    }
+
+    pub fn watermark(&self, destination: &str) -> Option<Watermark> {
+        self.watermarks.get(destination).copied()
+    }
+
+    /// Records after the watermark of `destination`, in `(timestamp, id)` order.
+    pub fn pending(&self, destination: &str, records: &[MmssRecord]) -> Vec<MmssRecord> {
+        let watermark = self.watermark(destination);
+        let mut pending: Vec<MmssRecord> = records
+            .iter()
+            .filter(|r| watermark.is_none_or(|mark| Watermark::of(r) > mark))
+            .cloned()
+            .collect();
+        pending.sort_by_key(Watermark::of);
+        pending
+    }
+
+    /// Pass the records not yet exported to `destination` to `write`, then
+    /// advance and save its watermark. Returns the number of records written;
+    /// `write` is not called when there are none.
+    pub fn export<F>(
+        &mut self,
+        destination: &str,
+        records: &[MmssRecord],
+        write: F,
+    ) -> Result<usize, Box<dyn std::error::Error>>
+    where
+        F: FnOnce(&[MmssRecord]) -> Result<(), Box<dyn std::error::Error>>,
+    {
+        let pending = self.pending(destination, records);
+        let Some(last) = pending.last() else {
+            return Ok(0);
+        };
+        write(&pending)?;
+        self.watermarks
+            .insert(destination.to_string(), Watermark::of(last));
+        self.save()?;
+        Ok(pending.len())
+    }
+
+    /// Write the watermarks through a temporary file, so a crash mid-save
+    /// leaves the previous state intact.
+    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = self.state_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temporary = self.state_path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_vec_pretty(&self.watermarks)?)?;
+        fs::rename(&temporary, &self.state_path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(id: u64, timestamp: i64) -> MmssRecord {
+        MmssRecord {
+            id,
+            kind: "task".to_string(),
+            timestamp,
+            payload: json!(id),
+        }
+    }
+
+    #[test]
+    fn test_exports_each_record_once_across_runs() {
+        let state_path = std::env::temp_dir()
+            .join(format!("mmss-watermarks-{}", uuid::Uuid::new_v4()))
+            .join("watermarks.json");
+        let mut records = vec![record(1, 100), record(0, 100), record(2, 200)];
+        let mut exported = Vec::new();
+
+        let mut exporter = IncrementalExporter::open(&state_path).unwrap();
+        let written = exporter
+            .export("a", &records, |batch| {
+                exported.extend(batch.iter().map(|r| r.id));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(written, 3);
+        assert_eq!(exported, [0, 1, 2]);
+
+        // a failed write leaves the watermark where it was
+        records.push(record(3, 300));
+        let mut exporter = IncrementalExporter::open(&state_path).unwrap();
+        assert!(exporter
+            .export("a", &records, |_| Err("destination unavailable".into()))
+            .is_err());
+        assert_eq!(
+            exporter.watermark("a"),
+            Some(Watermark {
+                timestamp: 200,
+                id: 2
+            })
+        );
+
+        records.push(record(4, 150));
+        let written = exporter
+            .export("a", &records, |batch| {
+                exported.extend(batch.iter().map(|r| r.id));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(written, 1);
+        assert_eq!(exported, [0, 1, 2, 3]);
+        assert_eq!(exporter.export("a", &records, |_| Ok(())).unwrap(), 0);
+        assert_eq!(exporter.pending("b", &records).len(), 5);
+        fs::remove_dir_all(state_path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+//! JSON Lines export: one `MmssRecord` object per line, in the record's
+//! own serde shape.
+
+use crate::structex_bridge::MmssRecord;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+pub fn write_records_to_jsonl(
+    path: &Path,
+    records: &[MmssRecord],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_jsonl(&mut writer, records)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write one line per record to any destination.
+pub fn write_jsonl<W: Write>(
+    writer: &mut W,
+    records: &[MmssRecord],
+) -> Result<(), Box<dyn std::error::Error>> {
+    for record in records {
+        serde_json::to_writer(&mut *writer, record)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Records of a file written by `write_records_to_jsonl`; blank lines are
+/// skipped.
+pub fn read_records_from_jsonl(path: &Path) -> Result<Vec<MmssRecord>, Box<dyn std::error::Error>> {
+    let mut records = Vec::new();
+    for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line)
+            .map_err(|e| format!("{} line {}: {}", path.display(), number + 1, e))?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_jsonl_round_trip() {
+        let path = std::env::temp_dir().join(format!("mmss-core-{}.jsonl", uuid::Uuid::new_v4()));
+        let records: Vec<MmssRecord> = (0..3)
+            .map(|id| MmssRecord {
+                id,
+                kind: "event".to_string(),
+                timestamp: id as i64,
+                payload: json!({ "lines": "a\nb" }),
+            })
+            .collect();
+        write_records_to_jsonl(&path, &records).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        let read = read_records_from_jsonl(&path).unwrap();
+        assert_eq!(read.len(), 3);
+        assert_eq!(read[2].payload, records[2].payload);
+
+        std::fs::write(&path, "{\"id\": 1}\n").unwrap();
+        let err = read_records_from_jsonl(&path).unwrap_err().to_string();
+        assert!(err.contains("line 1"), "{}", err);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
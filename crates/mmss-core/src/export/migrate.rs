@@ -0,0 +1,227 @@
+//! Upgrade of older Arrow record exports to the current schema.
+//!
+//! Version 0 files (written before the schema carried a version) vary in
+//! layout: `id` may be any integer type, `timestamp` a 32-bit or timestamp
+//! column, strings may be large-offset, and `payload` may be missing,
+//! nullable or hold plain text. Columns are found by name; whatever the
+//! source, the output has the current schema and version.
+
+use super::arrow::{schema_version, IpcFormat, RecordWriter, SCHEMA_VERSION};
+use crate::structex_bridge::MmssRecord;
+use arrow2::array::{Array, PrimitiveArray, Utf8Array};
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::Schema;
+use arrow2::io::ipc::read::{read_file_metadata, FileReader};
+use serde_json::Value as JsonValue;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub records: usize,
+}
+
+/// Rewrite the record file at `source` to `destination` in the current
+/// schema, one batch at a time. `destination` may be `source`; the output is
+/// written to a temporary file next to it and renamed into place.
+pub fn migrate_file(
+    source: &Path,
+    destination: &Path,
+) -> Result<Migration, Box<dyn std::error::Error>> {
+    let mut input = BufReader::new(File::open(source)?);
+    let metadata = read_file_metadata(&mut input)?;
+    let from_version = schema_version(&metadata.schema)?;
+    if from_version > SCHEMA_VERSION {
+        return Err(format!(
+            "cannot migrate {} down from schema version {}",
+            source.display(),
+            from_version
+        )
+        .into());
+    }
+    let columns = Columns::locate(&metadata.schema)?;
+
+    let temporary = destination.with_extension("migrating");
+    let mut writer = RecordWriter::try_new(File::create(&temporary)?, IpcFormat::File, 64 * 1024)?;
+    let mut records = 0;
+    let migrated = (|| -> Result<(), Box<dyn std::error::Error>> {
+        for chunk in FileReader::new(input, metadata, None, None) {
+            let batch = columns.records(&chunk?)?;
+            records += batch.len();
+            writer.write(&batch)?;
+        }
+        writer.finish()?;
+        Ok(())
+    })();
+    if let Err(e) = migrated {
+        fs::remove_file(&temporary).ok();
+        return Err(e);
+    }
+    fs::rename(&temporary, destination)?;
+    Ok(Migration {
+        from_version,
+        to_version: SCHEMA_VERSION,
+        records,
+    })
+}
+
+/// Column indices of the record fields in a source schema.
+struct Columns {
+    id: usize,
+    kind: usize,
+    timestamp: usize,
+    payload: Option<usize>,
+}
+
+impl Columns {
+    fn locate(schema: &Schema) -> Result<Self, Box<dyn std::error::Error>> {
+        let find = |name: &str| schema.fields.iter().position(|f| f.name == name);
+        let require =
+            |name: &str| find(name).ok_or_else(|| format!("source has no '{}' column", name));
+        Ok(Self {
+            id: require("id")?,
+            kind: require("kind")?,
+            timestamp: require("timestamp")?,
+            payload: find("payload"),
+        })
+    }
+
+    fn records(
+        &self,
+        chunk: &Chunk<Box<dyn Array>>,
+    ) -> Result<Vec<MmssRecord>, Box<dyn std::error::Error>> {
+        let arrays = chunk.arrays();
+        (0..chunk.len())
+            .map(|row| {
+                let required = |name: &str, value: Option<i128>| {
+                    value.ok_or_else(|| format!("row {} has no {}", row, name))
+                };
+                let id = required("id", integer(arrays[self.id].as_ref(), row)?)?;
+                let timestamp =
+                    required("timestamp", integer(arrays[self.timestamp].as_ref(), row)?)?;
+                let kind = text(arrays[self.kind].as_ref(), row)?
+                    .ok_or_else(|| format!("row {} has no kind", row))?;
+                let payload = match self.payload {
+                    Some(column) => match text(arrays[column].as_ref(), row)? {
+                        Some(text) => serde_json::from_str(text)
+                            .unwrap_or_else(|_| JsonValue::String(text.to_string())),
+                        None => JsonValue::Null,
+                    },
+                    None => JsonValue::Null,
+                };
+                Ok(MmssRecord {
+                    id: u64::try_from(id)
+                        .map_err(|_| format!("row {} has id {} out of range", row, id))?,
+                    kind: kind.to_string(),
+                    timestamp: i64::try_from(timestamp).map_err(|_| {
+                        format!("row {} has timestamp {} out of range", row, timestamp)
+                    })?,
+                    payload,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The integer at `row` of any signed or unsigned integer (or timestamp)
+/// column; `None` when null.
+fn integer(array: &dyn Array, row: usize) -> Result<Option<i128>, Box<dyn std::error::Error>> {
+    let any = array.as_any();
+    if let Some(a) = any.downcast_ref::<PrimitiveArray<i64>>() {
+        return Ok(a.get(row).map(i128::from));
+    }
+    if let Some(a) = any.downcast_ref::<PrimitiveArray<u64>>() {
+        return Ok(a.get(row).map(i128::from));
+    }
+    if let Some(a) = any.downcast_ref::<PrimitiveArray<i32>>() {
+        return Ok(a.get(row).map(i128::from));
+    }
+    if let Some(a) = any.downcast_ref::<PrimitiveArray<u32>>() {
+        return Ok(a.get(row).map(i128::from));
+    }
+    Err(format!("expected an integer column, found {:?}", array.data_type()).into())
+}
+
+fn text(array: &dyn Array, row: usize) -> Result<Option<&str>, Box<dyn std::error::Error>> {
+    let any = array.as_any();
+    if let Some(a) = any.downcast_ref::<Utf8Array<i32>>() {
+        return Ok(a.get(row));
+    }
+    if let Some(a) = any.downcast_ref::<Utf8Array<i64>>() {
+        return Ok(a.get(row));
+    }
+    Err(format!("expected a string column, found {:?}", array.data_type()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::arrow::read_records_from_file;
+    use arrow2::array::Int32Array;
+    use arrow2::datatypes::{DataType, Field};
+    use arrow2::io::ipc::write::{FileWriter, WriteOptions};
+    use serde_json::json;
+
+    #[test]
+    fn test_migrates_version_zero_layout() {
+        let dir = std::env::temp_dir().join(format!("mmss-core-migrate-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("v0.arrow");
+
+        // an unversioned file: signed ids, 32-bit timestamps, large strings
+        // and a nullable, partly non-JSON payload
+        let schema = Schema::from(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("timestamp", DataType::Int32, false),
+            Field::new("kind", DataType::LargeUtf8, false),
+            Field::new("payload", DataType::Utf8, true),
+        ]);
+        let chunk = Chunk::try_new(vec![
+            PrimitiveArray::<i64>::from_slice([1, 2, 3]).boxed(),
+            Int32Array::from_slice([10, 20, 30]).boxed(),
+            Utf8Array::<i64>::from_slice(["a", "b", "c"]).boxed(),
+            Utf8Array::<i32>::from([Some("{\"v\":1}"), None, Some("plain text")]).boxed(),
+        ])
+        .unwrap();
+        let mut writer = FileWriter::try_new(
+            File::create(&path).unwrap(),
+            schema,
+            None,
+            WriteOptions { compression: None },
+        )
+        .unwrap();
+        writer.write(&chunk, None).unwrap();
+        writer.finish().unwrap();
+        assert!(read_records_from_file(&path).is_err());
+
+        let migration = migrate_file(&path, &path).unwrap();
+        assert_eq!(
+            migration,
+            Migration {
+                from_version: 0,
+                to_version: SCHEMA_VERSION,
+                records: 3
+            }
+        );
+        let records = read_records_from_file(&path).unwrap();
+        assert_eq!(records[0].payload, json!({ "v": 1 }));
+        assert_eq!(records[1].payload, JsonValue::Null);
+        assert_eq!(records[2].payload, json!("plain text"));
+        assert_eq!(
+            (
+                records[2].id,
+                records[2].timestamp,
+                records[2].kind.as_str()
+            ),
+            (3, 30, "c")
+        );
+
+        let mut file = BufReader::new(File::open(&path).unwrap());
+        let metadata = read_file_metadata(&mut file).unwrap();
+        assert_eq!(schema_version(&metadata.schema).unwrap(), SCHEMA_VERSION);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
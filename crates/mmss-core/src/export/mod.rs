@@ -1 +1,9 @@
 ﻿pub mod arrow;
+pub mod csv;
+pub mod dedup;
+pub mod incremental;
+pub mod jsonl;
+pub mod migrate;
+pub mod parquet;
+pub mod partition;
+pub mod stream;
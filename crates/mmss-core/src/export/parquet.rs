@@ -0,0 +1,177 @@
+//! Parquet export of `MmssRecord`s through arrow2's parquet writer.
+//!
+//! Records keep the columns of the Arrow IPC export (`id: UINT_64`,
+//! `kind: UTF8`, `timestamp: INT64`, `payload: UTF8` holding the JSON),
+//! PLAIN-encoded with statistics, in row groups of `row_group_size` rows.
+//! The schema version goes into the footer's key-value metadata.
+
+use super::arrow::{record_schema, records_to_chunk, SCHEMA_VERSION, SCHEMA_VERSION_KEY};
+use crate::structex_bridge::MmssRecord;
+use arrow2::io::parquet::write::{
+    transverse, CompressionOptions, Encoding, FileWriter, KeyValue, RowGroupIterator, Version,
+    WriteOptions,
+};
+use std::{fs, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParquetCompression {
+    Uncompressed,
+    Snappy,
+    /// Smaller than Snappy on repetitive payloads, and slower to write.
+    Zstd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParquetOptions {
+    pub compression: ParquetCompression,
+    /// Rows per row group; readers parallelize and skip at this grain.
+    pub row_group_size: usize,
+}
+
+impl Default for ParquetOptions {
+    fn default() -> Self {
+        Self {
+            compression: ParquetCompression::Snappy,
+            row_group_size: 64 * 1024,
+        }
+    }
+}
+
+impl ParquetOptions {
+    fn to_parquet(self) -> WriteOptions {
+        WriteOptions {
+            write_statistics: true,
+            version: Version::V2,
+            compression: match self.compression {
+                ParquetCompression::Uncompressed => CompressionOptions::Uncompressed,
+                ParquetCompression::Snappy => CompressionOptions::Snappy,
+                ParquetCompression::Zstd => CompressionOptions::Zstd(None),
+            },
+            data_pagesize_limit: None,
+        }
+    }
+}
+
+pub fn write_records_to_parquet(
+    path: &Path,
+    records: &[MmssRecord],
+    options: &ParquetOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(path, records_to_parquet(records, options)?)?;
+    Ok(())
+}
+
+/// The bytes of the Parquet file `write_records_to_parquet` would write.
+pub fn records_to_parquet(
+    records: &[MmssRecord],
+    options: &ParquetOptions,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if options.row_group_size == 0 {
+        return Err("row_group_size must be positive".into());
+    }
+    let schema = record_schema();
+    let write_options = options.to_parquet();
+    let chunks = records
+        .chunks(options.row_group_size)
+        .map(records_to_chunk)
+        .collect::<Result<Vec<_>, _>>()?;
+    let encodings = schema
+        .fields
+        .iter()
+        .map(|field| transverse(&field.data_type, |_| Encoding::Plain))
+        .collect();
+    let row_groups = RowGroupIterator::try_new(
+        chunks.into_iter().map(Ok),
+        &schema,
+        write_options,
+        encodings,
+    )?;
+
+    let mut writer = FileWriter::try_new(Vec::new(), schema, write_options)?;
+    for group in row_groups {
+        writer.write(group?)?;
+    }
+    // readers without Arrow support cannot see the schema metadata
+    writer.end(Some(vec![KeyValue {
+        key: SCHEMA_VERSION_KEY.to_string(),
+        value: Some(SCHEMA_VERSION.to_string()),
+    }]))?;
+    Ok(writer.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::arrow::chunk_to_records;
+    use arrow2::io::parquet::read::{infer_schema, read_metadata, FileReader};
+    use serde_json::json;
+    use std::io::Cursor;
+
+    fn records(n: u64) -> Vec<MmssRecord> {
+        (0..n)
+            .map(|id| MmssRecord {
+                id,
+                kind: if id % 3 == 0 { "task" } else { "metric" }.to_string(),
+                timestamp: 1_700_000_000 + id as i64,
+                payload: json!({ "value": id * 2, "label": "repeated label text" }),
+            })
+            .collect()
+    }
+
+    fn read(bytes: Vec<u8>) -> (usize, Vec<MmssRecord>) {
+        let mut cursor = Cursor::new(bytes);
+        let metadata = read_metadata(&mut cursor).unwrap();
+        let version = metadata
+            .key_value_metadata
+            .iter()
+            .flatten()
+            .find(|kv| kv.key == SCHEMA_VERSION_KEY)
+            .and_then(|kv| kv.value.clone());
+        assert_eq!(version, Some(SCHEMA_VERSION.to_string()));
+        let schema = infer_schema(&metadata).unwrap();
+        assert_eq!(schema.fields, record_schema().fields);
+        let groups = metadata.row_groups.len();
+        let mut records = Vec::new();
+        for chunk in FileReader::new(cursor, metadata.row_groups, schema, None, None, None) {
+            records.extend(chunk_to_records(&chunk.unwrap()).unwrap());
+        }
+        (groups, records)
+    }
+
+    #[test]
+    fn test_records_round_trip_in_row_groups() {
+        let options = ParquetOptions {
+            compression: ParquetCompression::Uncompressed,
+            row_group_size: 4,
+        };
+        let bytes = records_to_parquet(&records(10), &options).unwrap();
+        assert_eq!(&bytes[..4], b"PAR1");
+        assert_eq!(read(bytes), (3, records(10)));
+
+        let empty = records_to_parquet(&[], &ParquetOptions::default()).unwrap();
+        assert_eq!(read(empty), (0, Vec::new()));
+        let no_rows = ParquetOptions {
+            row_group_size: 0,
+            ..ParquetOptions::default()
+        };
+        assert!(records_to_parquet(&records(1), &no_rows).is_err());
+    }
+
+    #[test]
+    fn test_compressed_files_round_trip() {
+        let plain = ParquetOptions {
+            compression: ParquetCompression::Uncompressed,
+            ..ParquetOptions::default()
+        };
+        let plain_size = records_to_parquet(&records(1_000), &plain).unwrap().len();
+        for compression in [ParquetCompression::Snappy, ParquetCompression::Zstd] {
+            let options = ParquetOptions {
+                compression,
+                ..ParquetOptions::default()
+            };
+            let bytes = records_to_parquet(&records(1_000), &options).unwrap();
+            assert!(bytes.len() * 2 < plain_size, "{:?}", compression);
+            assert_eq!(read(bytes), (1, records(1_000)));
+        }
+    }
+}
@@ -0,0 +1,292 @@
+//! Hive-style partitioned export: records are split by kind and UTC date
+//! into `kind=<k>/date=<yyyy-mm-dd>/part-N.arrow`, so query engines can
+//! prune partitions from the directory names alone.
+
+use super::arrow::{IpcFormat, RecordWriter};
+use crate::structex_bridge::MmssRecord;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// Unit of `MmssRecord::timestamp`, needed to derive the date partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampUnit {
+    Seconds,
+    #[default]
+    Milliseconds,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionOptions {
+    pub timestamp_unit: TimestampUnit,
+    /// A partition rolls over to a new part file after this many rows.
+    pub rows_per_file: usize,
+    /// Rows per IPC batch within a part file.
+    pub chunk_size: usize,
+    /// Parts kept open at once; beyond this the oldest is finished early.
+    pub max_open_files: usize,
+}
+
+impl Default for PartitionOptions {
+    fn default() -> Self {
+        Self {
+            timestamp_unit: TimestampUnit::Milliseconds,
+            rows_per_file: 1_000_000,
+            chunk_size: 64 * 1024,
+            max_open_files: 64,
+        }
+    }
+}
+
+/// Relative directory of one partition, e.g. `kind=metric/date=2024-01-31`.
+pub fn partition_dir(record: &MmssRecord, unit: TimestampUnit) -> PathBuf {
+    let seconds = match unit {
+        TimestampUnit::Seconds => record.timestamp,
+        TimestampUnit::Milliseconds => record.timestamp.div_euclid(1000),
+    };
+    let (year, month, day) = civil_date(seconds.div_euclid(86_400));
+    PathBuf::from(format!("kind={}", escape(&record.kind)))
+        .join(format!("date={:04}-{:02}-{:02}", year, month, day))
+}
+
+struct OpenPart {
+    writer: RecordWriter<BufWriter<File>>,
+    rows: usize,
+}
+
+/// Incremental writer that keeps one open part file per partition.
+///
+/// Part numbers continue after any `part-N.arrow` already in a partition
+/// directory, so repeated exports into the same root append rather than
+/// overwrite.
+pub struct PartitionedWriter {
+    root: PathBuf,
+    options: PartitionOptions,
+    open: BTreeMap<PathBuf, OpenPart>,
+    /// Opening order of `open`, oldest first.
+    opened: Vec<PathBuf>,
+    next_part: BTreeMap<PathBuf, usize>,
+    files: Vec<PathBuf>,
+}
+
+impl PartitionedWriter {
+    pub fn try_new(
+        root: impl Into<PathBuf>,
+        options: PartitionOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if options.rows_per_file == 0 || options.chunk_size == 0 || options.max_open_files == 0 {
+            return Err("rows_per_file, chunk_size and max_open_files must be positive".into());
+        }
+        Ok(Self {
+            root: root.into(),
+            options,
+            open: BTreeMap::new(),
+            opened: Vec::new(),
+            next_part: BTreeMap::new(),
+            files: Vec::new(),
+        })
+    }
+
+    pub fn write(&mut self, records: &[MmssRecord]) -> Result<(), Box<dyn std::error::Error>> {
+        for record in records {
+            let partition = partition_dir(record, self.options.timestamp_unit);
+            if !self.open.contains_key(&partition) {
+                self.open_part(&partition)?;
+            }
+            let part = self.open.get_mut(&partition).expect("part was just opened");
+            part.writer.write(std::slice::from_ref(record))?;
+            part.rows += 1;
+            if part.rows == self.options.rows_per_file {
+                self.close_part(&partition)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finish every open part and return all files written, in creation order.
+    pub fn finish(mut self) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        for partition in std::mem::take(&mut self.opened) {
+            if let Some(part) = self.open.remove(&partition) {
+                part.writer.finish()?;
+            }
+        }
+        Ok(self.files)
+    }
+
+    fn open_part(&mut self, partition: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if self.open.len() == self.options.max_open_files {
+            let oldest = self.opened[0].clone();
+            self.close_part(&oldest)?;
+        }
+        let dir = self.root.join(partition);
+        if !self.next_part.contains_key(partition) {
+            self.next_part
+                .insert(partition.to_path_buf(), first_free_part(&dir)?);
+        }
+        let number = self
+            .next_part
+            .get_mut(partition)
+            .expect("part number was just set");
+        let path = dir.join(format!("part-{}.arrow", number));
+        *number += 1;
+
+        fs::create_dir_all(&dir)?;
+        let file = BufWriter::new(File::create(&path)?);
+        let writer = RecordWriter::try_new(file, IpcFormat::File, self.options.chunk_size)?;
+        self.files.push(path);
+        self.open
+            .insert(partition.to_path_buf(), OpenPart { writer, rows: 0 });
+        self.opened.push(partition.to_path_buf());
+        Ok(())
+    }
+
+    fn close_part(&mut self, partition: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        self.opened.retain(|p| p != partition);
+        if let Some(part) = self.open.remove(partition) {
+            part.writer.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Write `records` under `root` in one call; see `PartitionedWriter`.
+pub fn write_records_partitioned(
+    root: &Path,
+    records: &[MmssRecord],
+    options: &PartitionOptions,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut writer = PartitionedWriter::try_new(root, *options)?;
+    writer.write(records)?;
+    writer.finish()
+}
+
+/// One past the highest existing `part-N.arrow` in `dir`.
+fn first_free_part(dir: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let mut next = 0;
+    for entry in entries {
+        let name = entry?.file_name();
+        let number = name
+            .to_str()
+            .and_then(|n| n.strip_prefix("part-"))
+            .and_then(|n| n.strip_suffix(".arrow"))
+            .and_then(|n| n.parse::<usize>().ok());
+        if let Some(number) = number {
+            next = next.max(number + 1);
+        }
+    }
+    Ok(next)
+}
+
+/// Percent-encode everything but `[A-Za-z0-9_.-]`, as Hive does for
+/// partition values, so a kind can never introduce a path separator.
+fn escape(value: &str) -> String {
+    // "." and ".." would name the current or parent directory
+    let keep_dots = !matches!(value, "." | "..");
+    value
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric()
+                || matches!(byte, b'_' | b'-')
+                || (byte == b'.' && keep_dots)
+            {
+                (byte as char).to_string()
+            } else {
+                format!("%{:02X}", byte)
+            }
+        })
+        .collect()
+}
+
+/// Proleptic Gregorian (year, month, day) of `days` since 1970-01-01.
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    // Howard Hinnant's days_from_civil inverse
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::arrow::read_records_from_file;
+    use serde_json::json;
+
+    fn record(id: u64, kind: &str, timestamp: i64) -> MmssRecord {
+        MmssRecord {
+            id,
+            kind: kind.to_string(),
+            timestamp,
+            payload: json!({ "id": id }),
+        }
+    }
+
+    #[test]
+    fn test_partition_dirs() {
+        let unit = TimestampUnit::Seconds;
+        assert_eq!(
+            partition_dir(&record(0, "metric", 0), unit),
+            Path::new("kind=metric/date=1970-01-01")
+        );
+        assert_eq!(
+            partition_dir(&record(0, "a/b=c", 1_709_208_000), unit),
+            Path::new("kind=a%2Fb%3Dc/date=2024-02-29")
+        );
+        assert_eq!(
+            partition_dir(&record(0, "..", -1), unit),
+            Path::new("kind=%2E%2E/date=1969-12-31")
+        );
+        assert_eq!(
+            partition_dir(&record(0, "x", 86_400_000), TimestampUnit::Milliseconds),
+            Path::new("kind=x/date=1970-01-02")
+        );
+    }
+
+    #[test]
+    fn test_partitioned_writer_rolls_over_and_appends() {
+        let root =
+            std::env::temp_dir().join(format!("mmss-core-partition-{}", uuid::Uuid::new_v4()));
+        let day = 86_400;
+        let records: Vec<MmssRecord> = (0..7)
+            .map(|id| {
+                let kind = if id % 2 == 0 { "even" } else { "odd" };
+                record(id, kind, (id as i64 / 4) * day)
+            })
+            .collect();
+        let options = PartitionOptions {
+            timestamp_unit: TimestampUnit::Seconds,
+            rows_per_file: 1,
+            max_open_files: 1,
+            ..PartitionOptions::default()
+        };
+        let files = write_records_partitioned(&root, &records, &options).unwrap();
+        assert_eq!(files.len(), 7);
+        assert!(files.contains(&root.join("kind=even/date=1970-01-01/part-1.arrow")));
+        let read: Vec<u64> = files
+            .iter()
+            .flat_map(|f| read_records_from_file(f).unwrap())
+            .map(|r| r.id)
+            .collect();
+        assert_eq!(read, (0..7).collect::<Vec<_>>());
+
+        let more = write_records_partitioned(&root, &records[..1], &PartitionOptions::default());
+        // millisecond timestamps of 0 land in the same partition
+        assert_eq!(
+            more.unwrap(),
+            vec![root.join("kind=even/date=1970-01-01/part-2.arrow")]
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
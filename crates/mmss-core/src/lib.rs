@@ -1,2 +1,5 @@
 ﻿pub mod structex_bridge;
+pub mod datagen;
 pub mod export;
+pub mod pattern;
+pub mod record;
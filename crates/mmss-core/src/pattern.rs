@@ -0,0 +1,513 @@
+//! The query language of `PatternMatcher`.
+//!
+//! ```text
+//! kind ~ "metric*" and timestamp in [1700000000000, 1700086400000)
+//!     and ($.value >= 0.5 or not $.tags[0] = "noisy")
+//! ```
+//!
+//! A query combines conditions with `and`, `or`, `not` and parentheses.
+//! A condition compares a field with a literal using `=`, `!=`, `<`, `<=`,
+//! `>`, `>=` or `~` (glob match with `*` and `?`), or tests that a numeric
+//! field lies in a range with `in [low, high)`, brackets choosing whether
+//! each bound is inclusive. Fields are `id`, `kind`, `timestamp` and JSONPath
+//! into the payload: `$` followed by `.name`, `["name"]` or `[index]`
+//! segments. Literals are numbers, quoted strings, `true`, `false` and
+//! `null`. Numbers compare as `f64`; a condition on a payload path the
+//! record does not have is false.
+
+use crate::structex_bridge::MmssRecord;
+use serde_json::Value as JsonValue;
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, JsonValue),
+    Range {
+        field: Field,
+        low: (f64, bool),
+        high: (f64, bool),
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Field {
+    Id,
+    Kind,
+    Timestamp,
+    Payload(Vec<Segment>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Glob,
+}
+
+impl Expr {
+    pub(crate) fn matches(&self, record: &MmssRecord) -> bool {
+        match self {
+            Expr::And(left, right) => left.matches(record) && right.matches(record),
+            Expr::Or(left, right) => left.matches(record) || right.matches(record),
+            Expr::Not(inner) => !inner.matches(record),
+            Expr::Compare(field, op, literal) => field
+                .value(record)
+                .is_some_and(|value| compare(&value, *op, literal)),
+            Expr::Range { field, low, high } => field
+                .value(record)
+                .and_then(|value| value.as_f64())
+                .is_some_and(|x| {
+                    (if low.1 { x >= low.0 } else { x > low.0 })
+                        && (if high.1 { x <= high.0 } else { x < high.0 })
+                }),
+        }
+    }
+}
+
+impl Field {
+    fn value<'a>(&self, record: &'a MmssRecord) -> Option<Cow<'a, JsonValue>> {
+        match self {
+            Field::Id => Some(Cow::Owned(record.id.into())),
+            Field::Kind => Some(Cow::Owned(record.kind.as_str().into())),
+            Field::Timestamp => Some(Cow::Owned(record.timestamp.into())),
+            Field::Payload(segments) => {
+                let mut value = &record.payload;
+                for segment in segments {
+                    value = match segment {
+                        Segment::Key(key) => value.get(key)?,
+                        Segment::Index(index) => value.get(index)?,
+                    };
+                }
+                Some(Cow::Borrowed(value))
+            }
+        }
+    }
+}
+
+fn compare(value: &JsonValue, op: Op, literal: &JsonValue) -> bool {
+    use std::cmp::Ordering;
+
+    if op == Op::Glob {
+        return match (value, literal) {
+            (JsonValue::String(value), JsonValue::String(pattern)) => glob(pattern, value),
+            _ => false,
+        };
+    }
+    let ordering = match (value, literal) {
+        (JsonValue::Number(a), JsonValue::Number(b)) => a
+            .as_f64()
+            .zip(b.as_f64())
+            .and_then(|(a, b)| a.partial_cmp(&b)),
+        (JsonValue::String(a), JsonValue::String(b)) => Some(a.cmp(b)),
+        (a, b) if matches!(op, Op::Eq | Op::Ne) => {
+            return (a == b) == (op == Op::Eq);
+        }
+        _ => None,
+    };
+    let Some(ordering) = ordering else {
+        return false;
+    };
+    match op {
+        Op::Eq => ordering == Ordering::Equal,
+        Op::Ne => ordering != Ordering::Equal,
+        Op::Lt => ordering == Ordering::Less,
+        Op::Le => ordering != Ordering::Greater,
+        Op::Gt => ordering == Ordering::Greater,
+        Op::Ge => ordering != Ordering::Less,
+        Op::Glob => unreachable!("handled above"),
+    }
+}
+
+/// `*` matches any run of characters, `?` exactly one.
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // position after the last `*` and the text position it was tried at
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Path(Vec<Segment>),
+    Number(f64),
+    Str(String),
+    Op(Op),
+    Open(char),
+    Close(char),
+    Comma,
+}
+
+pub(crate) fn parse(query: &str) -> Result<Expr, String> {
+    let tokens = tokenize(query)?;
+    let mut parser = Parser { tokens, at: 0 };
+    let expr = parser.or()?;
+    match parser.tokens.get(parser.at) {
+        None => Ok(expr),
+        Some(token) => Err(format!("unexpected {:?} after the end of the query", token)),
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            _ if c.is_whitespace() => i += 1,
+            '(' | '[' => {
+                tokens.push(Token::Open(c));
+                i += 1;
+            }
+            ')' | ']' => {
+                tokens.push(Token::Close(c));
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            '=' | '~' => {
+                tokens.push(Token::Op(if c == '=' { Op::Eq } else { Op::Glob }));
+                i += 1;
+            }
+            '!' | '<' | '>' => {
+                let equals = chars.get(i + 1) == Some(&'=');
+                let op = match (c, equals) {
+                    ('!', true) => Op::Ne,
+                    ('<', false) => Op::Lt,
+                    ('<', true) => Op::Le,
+                    ('>', false) => Op::Gt,
+                    ('>', true) => Op::Ge,
+                    _ => return Err("expected '!='".to_string()),
+                };
+                tokens.push(Token::Op(op));
+                i += if equals { 2 } else { 1 };
+            }
+            '"' | '\'' => {
+                let (value, next) = string(&chars, i)?;
+                tokens.push(Token::Str(value));
+                i = next;
+            }
+            '$' => {
+                let (segments, next) = path(&chars, i + 1)?;
+                tokens.push(Token::Path(segments));
+                i = next;
+            }
+            _ if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let start = i;
+                i += 1;
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric()
+                        || chars[i] == '.'
+                        || (matches!(chars[i], '+' | '-') && matches!(chars[i - 1], 'e' | 'E')))
+                {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let number = text
+                    .parse()
+                    .map_err(|_| format!("invalid number '{}'", text))?;
+                tokens.push(Token::Number(number));
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Word(chars[start..i].iter().collect()));
+            }
+            _ => return Err(format!("unexpected character '{}'", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// A quoted string starting at `chars[start]`; returns it and the index after
+/// the closing quote.
+fn string(chars: &[char], start: usize) -> Result<(String, usize), String> {
+    let quote = chars[start];
+    let mut value = String::new();
+    let mut i = start + 1;
+    while let Some(&c) = chars.get(i) {
+        match c {
+            '\\' => {
+                let escaped = chars.get(i + 1).ok_or("unterminated string")?;
+                value.push(*escaped);
+                i += 2;
+            }
+            _ if c == quote => return Ok((value, i + 1)),
+            _ => {
+                value.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+/// JSONPath segments after the `$`.
+fn path(chars: &[char], mut i: usize) -> Result<(Vec<Segment>, usize), String> {
+    let mut segments = Vec::new();
+    loop {
+        match chars.get(i) {
+            Some('.') => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                if i == start {
+                    return Err("expected a field name after '.' in path".to_string());
+                }
+                segments.push(Segment::Key(chars[start..i].iter().collect()));
+            }
+            Some('[') => match chars.get(i + 1) {
+                Some('"') | Some('\'') => {
+                    let (key, next) = string(chars, i + 1)?;
+                    if chars.get(next) != Some(&']') {
+                        return Err("expected ']' in path".to_string());
+                    }
+                    segments.push(Segment::Key(key));
+                    i = next + 1;
+                }
+                _ => {
+                    let start = i + 1;
+                    i = start;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                    if i == start || chars.get(i) != Some(&']') {
+                        return Err("expected an index or quoted key in '[...]' of path".into());
+                    }
+                    let index: String = chars[start..i].iter().collect();
+                    segments.push(Segment::Index(
+                        index.parse().map_err(|_| "index too large")?,
+                    ));
+                    i += 1;
+                }
+            },
+            _ => return Ok((segments, i)),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .tokens
+            .get(self.at)
+            .cloned()
+            .ok_or("unexpected end of query")?;
+        self.at += 1;
+        Ok(token)
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(word));
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::Open('(')) {
+            self.at += 1;
+            let expr = self.or()?;
+            return match self.next()? {
+                Token::Close(')') => Ok(expr),
+                token => Err(format!("expected ')', found {:?}", token)),
+            };
+        }
+        self.condition()
+    }
+
+    fn condition(&mut self) -> Result<Expr, String> {
+        let field = match self.next()? {
+            Token::Path(segments) => Field::Payload(segments),
+            Token::Word(word) => match word.as_str() {
+                "id" => Field::Id,
+                "kind" => Field::Kind,
+                "timestamp" => Field::Timestamp,
+                _ => {
+                    return Err(format!(
+                        "unknown field '{}'; payload fields start with '$'",
+                        word
+                    ))
+                }
+            },
+            token => return Err(format!("expected a field, found {:?}", token)),
+        };
+        if self.keyword("in") {
+            let low_inclusive = match self.next()? {
+                Token::Open(c) => c == '[',
+                token => return Err(format!("expected '[' or '(', found {:?}", token)),
+            };
+            let low = self.number()?;
+            if self.next()? != Token::Comma {
+                return Err("expected ',' between range bounds".to_string());
+            }
+            let high = self.number()?;
+            let high_inclusive = match self.next()? {
+                Token::Close(c) => c == ']',
+                token => return Err(format!("expected ']' or ')', found {:?}", token)),
+            };
+            return Ok(Expr::Range {
+                field,
+                low: (low, low_inclusive),
+                high: (high, high_inclusive),
+            });
+        }
+        let op = match self.next()? {
+            Token::Op(op) => op,
+            token => return Err(format!("expected a comparison operator, found {:?}", token)),
+        };
+        let literal = match self.next()? {
+            Token::Number(n) => serde_json::Number::from_f64(n)
+                .map(JsonValue::Number)
+                .ok_or("numbers must be finite")?,
+            Token::Str(s) => JsonValue::String(s),
+            Token::Word(w) if w == "true" => JsonValue::Bool(true),
+            Token::Word(w) if w == "false" => JsonValue::Bool(false),
+            Token::Word(w) if w == "null" => JsonValue::Null,
+            token => return Err(format!("expected a literal, found {:?}", token)),
+        };
+        if op == Op::Glob && !literal.is_string() {
+            return Err("'~' needs a quoted pattern".to_string());
+        }
+        Ok(Expr::Compare(field, op, literal))
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        match self.next()? {
+            Token::Number(n) => Ok(n),
+            token => Err(format!("expected a number, found {:?}", token)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record() -> MmssRecord {
+        MmssRecord {
+            id: 42,
+            kind: "metric.cpu".to_string(),
+            timestamp: 1_700_000_000_000,
+            payload: json!({ "value": 0.75, "tags": ["prod", "eu"], "host name": "h1", "ok": true }),
+        }
+    }
+
+    fn matches(query: &str) -> bool {
+        parse(query).unwrap().matches(&record())
+    }
+
+    #[test]
+    fn test_conditions() {
+        assert!(matches("id = 42"));
+        assert!(matches("kind ~ \"metric.*\" and kind != 'metric'"));
+        assert!(matches("timestamp in [1700000000000, 1700000000001)"));
+        assert!(!matches("timestamp in (1700000000000, 1800000000000]"));
+        assert!(matches("$.value > 0.5 and $.value <= 0.75"));
+        assert!(matches("$.tags[1] = \"eu\" and $[\"host name\"] ~ 'h?'"));
+        assert!(matches("$.ok = true and not $.missing = null"));
+        assert!(!matches("$.missing != 1"));
+        assert!(matches("id < 0 or (kind ~ '*cpu' and not id >= 100)"));
+        assert!(matches("id = 1 OR id = 42"));
+    }
+
+    #[test]
+    fn test_precedence_and_errors() {
+        assert!(matches("id = 1 and id = 2 or id = 42"));
+        assert!(!matches("id = 1 and (id = 2 or id = 42)"));
+        for query in [
+            "",
+            "id =",
+            "size = 1",
+            "kind ~ 3",
+            "id in [1, 2",
+            "(id = 1",
+            "id = 1 id = 2",
+            "$. = 1",
+            "kind = 'open",
+        ] {
+            assert!(parse(query).is_err(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_glob() {
+        assert!(glob("*", ""));
+        assert!(glob("a*b*c", "aXXbYYc"));
+        assert!(glob("a?c", "abc"));
+        assert!(!glob("a*d", "abc"));
+        assert!(glob("**x", "yyx"));
+    }
+}
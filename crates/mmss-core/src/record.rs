@@ -0,0 +1,234 @@
+//! Validated construction of `MmssRecord`s.
+//!
+//! `MmssRecord::builder()` checks what the writers cannot: a kind that is
+//! non-empty and free of control characters, a timestamp within
+//! `[0, MAX_TIMESTAMP]`, and a payload whose JSON stays under a size limit.
+
+use crate::structex_bridge::MmssRecord;
+use serde_json::Value as JsonValue;
+use std::borrow::Cow;
+use std::fmt;
+use thiserror::Error;
+
+/// 2100-01-01T00:00:00Z in milliseconds. Second-resolution timestamps of
+/// any plausible date fall below it as well.
+pub const MAX_TIMESTAMP: i64 = 4_102_444_800_000;
+
+/// Default limit on the serialized payload.
+pub const DEFAULT_MAX_PAYLOAD_BYTES: usize = 1 << 20;
+
+const MAX_KIND_BYTES: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RecordError {
+    #[error("Record kind is empty")]
+    EmptyKind,
+    #[error("Invalid record kind '{0}': {1}")]
+    InvalidKind(String, &'static str),
+    #[error("Record is missing its {0}")]
+    Missing(&'static str),
+    #[error("Timestamp {0} is outside [0, {max}]", max = MAX_TIMESTAMP)]
+    TimestampOutOfRange(i64),
+    #[error("Payload is {size} bytes, over the {limit} byte limit")]
+    PayloadTooLarge { size: usize, limit: usize },
+}
+
+/// A validated record kind.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Kind(Cow<'static, str>);
+
+impl Kind {
+    pub const TASK: Kind = Kind(Cow::Borrowed("task"));
+    pub const METRIC: Kind = Kind(Cow::Borrowed("metric"));
+    pub const EVENT: Kind = Kind(Cow::Borrowed("event"));
+
+    pub fn new(kind: impl Into<String>) -> Result<Self, RecordError> {
+        let kind = kind.into();
+        if kind.trim().is_empty() {
+            return Err(RecordError::EmptyKind);
+        }
+        if kind.len() > MAX_KIND_BYTES {
+            return Err(RecordError::InvalidKind(kind, "longer than 128 bytes"));
+        }
+        if kind.chars().any(char::is_control) {
+            return Err(RecordError::InvalidKind(kind, "contains control characters"));
+        }
+        Ok(Self(Cow::Owned(kind)))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<&str> for Kind {
+    type Error = RecordError;
+
+    fn try_from(kind: &str) -> Result<Self, Self::Error> {
+        Self::new(kind)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MmssRecordBuilder {
+    id: Option<u64>,
+    kind: Option<Kind>,
+    timestamp: Option<i64>,
+    payload: JsonValue,
+    max_payload_bytes: usize,
+}
+
+impl MmssRecordBuilder {
+    pub fn id(mut self, id: u64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn kind(mut self, kind: Kind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Defaults to `null`.
+    pub fn payload(mut self, payload: JsonValue) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    pub fn max_payload_bytes(mut self, limit: usize) -> Self {
+        self.max_payload_bytes = limit;
+        self
+    }
+
+    pub fn build(self) -> Result<MmssRecord, RecordError> {
+        let record = MmssRecord {
+            id: self.id.ok_or(RecordError::Missing("id"))?,
+            kind: self.kind.ok_or(RecordError::Missing("kind"))?.0.into_owned(),
+            timestamp: self.timestamp.ok_or(RecordError::Missing("timestamp"))?,
+            payload: self.payload,
+        };
+        record.validate_with_limit(self.max_payload_bytes)?;
+        Ok(record)
+    }
+}
+
+impl MmssRecord {
+    pub fn builder() -> MmssRecordBuilder {
+        MmssRecordBuilder {
+            id: None,
+            kind: None,
+            timestamp: None,
+            payload: JsonValue::Null,
+            max_payload_bytes: DEFAULT_MAX_PAYLOAD_BYTES,
+        }
+    }
+
+    /// Check a record built without the builder, with the default payload limit.
+    pub fn validate(&self) -> Result<(), RecordError> {
+        self.validate_with_limit(DEFAULT_MAX_PAYLOAD_BYTES)
+    }
+
+    fn validate_with_limit(&self, max_payload_bytes: usize) -> Result<(), RecordError> {
+        Kind::new(self.kind.as_str())?;
+        if !(0..=MAX_TIMESTAMP).contains(&self.timestamp) {
+            return Err(RecordError::TimestampOutOfRange(self.timestamp));
+        }
+        let size = payload_size(&self.payload);
+        if size > max_payload_bytes {
+            return Err(RecordError::PayloadTooLarge {
+                size,
+                limit: max_payload_bytes,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Length of the compact JSON encoding, without materializing it.
+fn payload_size(payload: &JsonValue) -> usize {
+    struct Counter(usize);
+
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    serde_json::to_writer(&mut counter, payload).expect("JSON values always serialize");
+    counter.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_builder_validates_fields() {
+        let record = MmssRecord::builder()
+            .id(7)
+            .kind(Kind::METRIC)
+            .timestamp(1_700_000_000_000)
+            .payload(json!({ "value": 1.5 }))
+            .build()
+            .unwrap();
+        assert_eq!(record.kind, "metric");
+        assert!(record.validate().is_ok());
+
+        assert_eq!(Kind::new("  "), Err(RecordError::EmptyKind));
+        assert!(matches!(
+            Kind::new("a\nb"),
+            Err(RecordError::InvalidKind(_, _))
+        ));
+        assert_eq!(
+            MmssRecord::builder().id(1).kind(Kind::TASK).build(),
+            Err(RecordError::Missing("timestamp"))
+        );
+        assert_eq!(
+            MmssRecord::builder()
+                .id(1)
+                .kind(Kind::TASK)
+                .timestamp(-1)
+                .build(),
+            Err(RecordError::TimestampOutOfRange(-1))
+        );
+        assert_eq!(
+            MmssRecord::builder()
+                .id(1)
+                .kind(Kind::EVENT)
+                .timestamp(0)
+                .payload(json!("x".repeat(20)))
+                .max_payload_bytes(16)
+                .build(),
+            Err(RecordError::PayloadTooLarge {
+                size: 22,
+                limit: 16
+            })
+        );
+
+        let literal = MmssRecord {
+            id: 1,
+            kind: String::new(),
+            timestamp: 0,
+            payload: JsonValue::Null,
+        };
+        assert_eq!(literal.validate(), Err(RecordError::EmptyKind));
+    }
+}
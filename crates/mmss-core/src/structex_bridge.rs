@@ -1,4 +1,7 @@
-﻿use serde_json::Value as JsonValue;
+﻿use crate::export::arrow::{read_record_chunks, RecordChunks};
+use crate::pattern::{self, Expr};
+use serde_json::Value as JsonValue;
+use std::path::Path;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -9,26 +12,109 @@ pub enum PatternError {
     MatchError(String),
 }
 
+/// A compiled query over records; see `crate::pattern` for the syntax.
 pub struct PatternMatcher {
     pattern: String,
+    expr: Expr,
 }
 
 impl PatternMatcher {
     pub fn new(pattern: &str) -> Result<Self, PatternError> {
+        let expr = pattern::parse(pattern).map_err(PatternError::CompileError)?;
         Ok(Self {
             pattern: pattern.to_string(),
+            expr,
         })
     }
 
-    pub fn matches(&self, _record: &MmssRecord) -> Result<bool, PatternError> {
-        Ok(true)
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn matches(&self, record: &MmssRecord) -> Result<bool, PatternError> {
+        Ok(self.expr.matches(record))
+    }
+
+    /// The matching records of an Arrow file written by
+    /// `write_records_to_file`, read one IPC batch at a time.
+    pub fn match_file(&self, path: &Path) -> Result<MatchingRecords<'_>, PatternError> {
+        let chunks =
+            read_record_chunks(path).map_err(|e| PatternError::MatchError(e.to_string()))?;
+        Ok(MatchingRecords {
+            matcher: self,
+            chunks,
+            chunk: Vec::new().into_iter(),
+        })
+    }
+}
+
+/// Iterator returned by `PatternMatcher::match_file`; holds at most one
+/// batch in memory. A batch that fails to decode yields one error.
+pub struct MatchingRecords<'a> {
+    matcher: &'a PatternMatcher,
+    chunks: RecordChunks,
+    chunk: std::vec::IntoIter<MmssRecord>,
+}
+
+impl Iterator for MatchingRecords<'_> {
+    type Item = Result<MmssRecord, PatternError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.chunk.find(|r| self.matcher.expr.matches(r)) {
+                return Some(Ok(record));
+            }
+            match self.chunks.next()? {
+                Ok(records) => self.chunk = records.into_iter(),
+                Err(e) => return Some(Err(PatternError::MatchError(e.to_string()))),
+            }
+        }
     }
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct MmssRecord {
     pub id: u64,
     pub kind: String,
     pub timestamp: i64,
     pub payload: JsonValue,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::arrow::{IpcFormat, RecordWriter};
+    use serde_json::json;
+
+    #[test]
+    fn test_match_file_streams_batches() {
+        let dir = std::env::temp_dir().join(format!("mmss-core-match-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("records.arrow");
+        let file = std::fs::File::create(&path).unwrap();
+        let mut writer = RecordWriter::try_new(file, IpcFormat::File, 4).unwrap();
+        let records: Vec<MmssRecord> = (0..10)
+            .map(|id| MmssRecord {
+                id,
+                kind: if id % 2 == 0 { "metric" } else { "event" }.to_string(),
+                timestamp: id as i64,
+                payload: json!({ "value": id * 10 }),
+            })
+            .collect();
+        writer.write(&records).unwrap();
+        writer.finish().unwrap();
+
+        let matcher = PatternMatcher::new("kind = 'metric' and $.value >= 30").unwrap();
+        let ids: Vec<u64> = matcher
+            .match_file(&path)
+            .unwrap()
+            .map(|r| r.unwrap().id)
+            .collect();
+        assert_eq!(ids, [4, 6, 8]);
+        assert!(matches!(
+            PatternMatcher::new("kind ="),
+            Err(PatternError::CompileError(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
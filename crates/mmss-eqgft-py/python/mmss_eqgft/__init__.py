@@ -0,0 +1,139 @@
+"""Python bindings for the mmss-eqgft Rust implementation.
+
+Build and install it with
+``maturin develop -m crates/mmss-eqgft-py/pyproject.toml``.
+
+    >>> import mmss_eqgft as eq
+    >>> eq.calculate_polarization_asymmetry(eq.EqgftConfig(kappa=0.2, seed=1))
+    PolarizationAsymmetry(kappa=0.2, predicted=0.00145..., ...)
+"""
+
+from __future__ import annotations
+
+from dataclasses import asdict, dataclass, field
+from typing import List, Optional
+
+from . import _native
+from ._native import EqgftError
+
+__all__ = [
+    "DetectorModel",
+    "EqgftConfig",
+    "EqgftError",
+    "HopfionConfig",
+    "HopfionField",
+    "PolarizationAsymmetry",
+    "SensitivityCurve",
+    "SensitivityPoint",
+    "calculate_polarization_asymmetry",
+    "calculate_sensitivity_curve",
+    "generate_hopfion_field",
+    "scan_kappa",
+]
+
+
+@dataclass
+class DetectorModel:
+    angular_resolution: float = 0.0
+    efficiency: dict = field(default_factory=lambda: {"base": 1.0, "linear": 0.0, "quadratic": 0.0})
+    acceptance_min: float = -1.0
+    acceptance_max: float = 1.0
+
+
+@dataclass
+class EqgftConfig:
+    kappa: float = 0.20
+    n_events: int = 50_000
+    systematic_error: float = 1e-4
+    seed: Optional[int] = None
+    threads: Optional[int] = None
+    detector: Optional[DetectorModel] = None
+
+    def to_dict(self) -> dict:
+        return asdict(self)
+
+
+@dataclass
+class HopfionConfig:
+    resolution: int = 20
+    extent: float = 5.0
+    hopf_index: int = 1
+    scale: float = 1.0
+    threads: Optional[int] = None
+
+    def to_dict(self) -> dict:
+        return asdict(self)
+
+
+@dataclass
+class PolarizationAsymmetry:
+    kappa: float
+    predicted: float
+    a: float
+    stat_error: float
+    syst_error: float
+    n_events: int
+    uncertainty: float
+    """``stat_error`` and ``syst_error`` in quadrature."""
+    significance: float
+
+
+@dataclass
+class HopfionField:
+    config: HopfionConfig
+    axis: List[float]
+    q_x: List[List[float]]
+    """``[q0, q1, q2, q3]`` per lattice point, x-major."""
+    energy_density: List[float]
+    total_energy: float
+    hopf_charge: float
+
+    def at(self, i: int, j: int, k: int) -> List[float]:
+        n = self.config.resolution
+        return self.q_x[(i * n + j) * n + k]
+
+
+@dataclass
+class SensitivityPoint:
+    n_events: int
+    expected_significance: float
+    measured_significance: float
+
+
+@dataclass
+class SensitivityCurve:
+    kappa: float
+    predicted: float
+    points: List[SensitivityPoint]
+
+
+def calculate_polarization_asymmetry(config: Optional[EqgftConfig] = None) -> PolarizationAsymmetry:
+    result = _native.calculate_polarization_asymmetry((config or EqgftConfig()).to_dict())
+    return PolarizationAsymmetry(**result)
+
+
+def generate_hopfion_field(config: Optional[HopfionConfig] = None) -> HopfionField:
+    result = _native.generate_hopfion_field((config or HopfionConfig()).to_dict())
+    result["config"] = HopfionConfig(**result["config"])
+    return HopfionField(**result)
+
+
+def calculate_sensitivity_curve(
+    config: Optional[EqgftConfig] = None, n_values: Optional[List[int]] = None
+) -> SensitivityCurve:
+    result = _native.calculate_sensitivity_curve(
+        (config or EqgftConfig()).to_dict(),
+        None if n_values is None else list(n_values),
+    )
+    result["points"] = [SensitivityPoint(**point) for point in result["points"]]
+    return SensitivityCurve(**result)
+
+
+def scan_kappa(
+    kappa_min: float, kappa_max: float, steps: int, config: Optional[EqgftConfig] = None
+) -> List[PolarizationAsymmetry]:
+    points = _native.scan_kappa(kappa_min, kappa_max, steps, (config or EqgftConfig()).to_dict())
+    return [
+        PolarizationAsymmetry(significance=abs(p["a"]) / p["uncertainty"] if p["uncertainty"] else float("inf"), **p)
+        for p in points
+    ]
@@ -0,0 +1,214 @@
+//! The `mmss_eqgft._native` Python module over `mmss-eqgft`, wrapped by the
+//! `mmss_eqgft` package in `python/` with dataclasses for configs and
+//! results.
+//!
+//! Build it with `maturin develop -m crates/mmss-eqgft-py/pyproject.toml`;
+//! the module needs the `python` feature, which the pyproject enables.
+//! Configs and results cross as dicts in the serde forms of the crate's
+//! types. Simulations release the GIL.
+
+use mmss_eqgft::asymmetry::calculate_polarization_asymmetry;
+use mmss_eqgft::config::EqgftConfig;
+use mmss_eqgft::hopfion::{generate_hopfion_soliton_field, HopfionConfig};
+use mmss_eqgft::scan::scan_kappa_with;
+use mmss_eqgft::sensitivity::{calculate_sensitivity_curve, log_spaced_events};
+use mmss_eqgft::topology::compute_hopf_charge;
+use serde_json::{json, Value};
+
+fn to_json(value: impl serde::Serialize) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|err| err.to_string())
+}
+
+/// The measured asymmetry, with its `significance`.
+pub fn polarization_asymmetry(config: &EqgftConfig) -> Result<Value, String> {
+    let asymmetry = calculate_polarization_asymmetry(config).map_err(|e| e.to_string())?;
+    let mut value = to_json(asymmetry)?;
+    value["significance"] = json!(asymmetry.significance());
+    Ok(value)
+}
+
+/// The lattice, with its `hopf_charge`.
+pub fn hopfion_field(config: &HopfionConfig) -> Result<Value, String> {
+    let field = generate_hopfion_soliton_field(config).map_err(|e| e.to_string())?;
+    let mut value = to_json(&field)?;
+    value["hopf_charge"] = json!(compute_hopf_charge(&field));
+    Ok(value)
+}
+
+/// The curve at `n_values`, or at 50 sample sizes from 10³ to 10⁶.
+pub fn sensitivity_curve(
+    config: &EqgftConfig,
+    n_values: Option<&[usize]>,
+) -> Result<Value, String> {
+    let default_n_values;
+    let n_values = match n_values {
+        Some(n_values) => n_values,
+        None => {
+            default_n_values = log_spaced_events(1_000, 1_000_000, 50);
+            &default_n_values
+        }
+    };
+    to_json(calculate_sensitivity_curve(config, n_values).map_err(|e| e.to_string())?)
+}
+
+pub fn kappa_scan(
+    config: &EqgftConfig,
+    kappa_min: f64,
+    kappa_max: f64,
+    steps: usize,
+) -> Result<Value, String> {
+    to_json(scan_kappa_with(config, kappa_min..=kappa_max, steps).map_err(|e| e.to_string())?)
+}
+
+#[cfg(feature = "python")]
+mod python {
+    use super::*;
+    use pyo3::create_exception;
+    use pyo3::exceptions::PyRuntimeError;
+    use pyo3::prelude::*;
+    use pythonize::{depythonize, pythonize};
+    use serde::de::DeserializeOwned;
+
+    create_exception!(
+        mmss_eqgft,
+        EqgftError,
+        PyRuntimeError,
+        "Raised when the Rust side rejects a call, e.g. for an invalid config."
+    );
+
+    /// A config from its dict form, the default one for `None`.
+    fn parse<T: DeserializeOwned + Default>(obj: Option<&Bound<'_, PyAny>>) -> PyResult<T> {
+        match obj {
+            Some(obj) if !obj.is_none() => Ok(depythonize(obj)?),
+            _ => Ok(T::default()),
+        }
+    }
+
+    fn respond(py: Python<'_>, outcome: Result<Value, String>) -> PyResult<Bound<'_, PyAny>> {
+        let value = outcome.map_err(EqgftError::new_err)?;
+        Ok(pythonize(py, &value)?)
+    }
+
+    #[pyfunction]
+    fn default_eqgft_config(py: Python<'_>) -> PyResult<Bound<'_, PyAny>> {
+        Ok(pythonize(py, &EqgftConfig::default())?)
+    }
+
+    #[pyfunction]
+    fn default_hopfion_config(py: Python<'_>) -> PyResult<Bound<'_, PyAny>> {
+        Ok(pythonize(py, &HopfionConfig::default())?)
+    }
+
+    #[pyfunction]
+    #[pyo3(name = "calculate_polarization_asymmetry", signature = (config = None))]
+    fn py_polarization_asymmetry<'py>(
+        py: Python<'py>,
+        config: Option<&Bound<'py, PyAny>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let config: EqgftConfig = parse(config)?;
+        let outcome = py.allow_threads(|| polarization_asymmetry(&config));
+        respond(py, outcome)
+    }
+
+    #[pyfunction]
+    #[pyo3(name = "generate_hopfion_field", signature = (config = None))]
+    fn py_hopfion_field<'py>(
+        py: Python<'py>,
+        config: Option<&Bound<'py, PyAny>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let config: HopfionConfig = parse(config)?;
+        let outcome = py.allow_threads(|| hopfion_field(&config));
+        respond(py, outcome)
+    }
+
+    #[pyfunction]
+    #[pyo3(name = "calculate_sensitivity_curve", signature = (config = None, n_values = None))]
+    fn py_sensitivity_curve<'py>(
+        py: Python<'py>,
+        config: Option<&Bound<'py, PyAny>>,
+        n_values: Option<Vec<usize>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let config: EqgftConfig = parse(config)?;
+        let outcome = py.allow_threads(|| sensitivity_curve(&config, n_values.as_deref()));
+        respond(py, outcome)
+    }
+
+    #[pyfunction]
+    #[pyo3(name = "scan_kappa", signature = (kappa_min, kappa_max, steps, config = None))]
+    fn py_kappa_scan<'py>(
+        py: Python<'py>,
+        kappa_min: f64,
+        kappa_max: f64,
+        steps: usize,
+        config: Option<&Bound<'py, PyAny>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let config: EqgftConfig = parse(config)?;
+        let outcome = py.allow_threads(|| kappa_scan(&config, kappa_min, kappa_max, steps));
+        respond(py, outcome)
+    }
+
+    #[pymodule]
+    #[pyo3(name = "_native")]
+    fn native(m: &Bound<'_, PyModule>) -> PyResult<()> {
+        m.add("EqgftError", m.py().get_type_bound::<EqgftError>())?;
+        m.add_function(wrap_pyfunction!(default_eqgft_config, m)?)?;
+        m.add_function(wrap_pyfunction!(default_hopfion_config, m)?)?;
+        m.add_function(wrap_pyfunction!(py_polarization_asymmetry, m)?)?;
+        m.add_function(wrap_pyfunction!(py_hopfion_field, m)?)?;
+        m.add_function(wrap_pyfunction!(py_sensitivity_curve, m)?)?;
+        m.add_function(wrap_pyfunction!(py_kappa_scan, m)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_results_carry_derived_values() {
+        let config = EqgftConfig {
+            kappa: 0.2,
+            n_events: 2000,
+            seed: Some(1),
+            ..EqgftConfig::default()
+        };
+        let asymmetry = polarization_asymmetry(&config).unwrap();
+        assert_eq!(asymmetry["kappa"], 0.2);
+        assert!(asymmetry["significance"].is_number());
+
+        let field = hopfion_field(&HopfionConfig {
+            resolution: 5,
+            ..HopfionConfig::default()
+        })
+        .unwrap();
+        assert_eq!(field["q_x"].as_array().unwrap().len(), 125);
+        assert!(field["hopf_charge"].is_number());
+
+        let scan = kappa_scan(
+            &EqgftConfig {
+                n_events: 1000,
+                seed: Some(3),
+                ..EqgftConfig::default()
+            },
+            0.0,
+            1.0,
+            2,
+        )
+        .unwrap();
+        assert_eq!(scan.as_array().unwrap().len(), 2);
+
+        let curve = sensitivity_curve(&config, Some(&[1000, 2000])).unwrap();
+        assert_eq!(curve["points"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_configs_are_reported() {
+        let config = EqgftConfig {
+            n_events: 0,
+            ..EqgftConfig::default()
+        };
+        assert!(polarization_asymmetry(&config).is_err());
+        assert!(kappa_scan(&EqgftConfig::default(), 0.0, 1.0, 0).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+//! Single-threaded vs all-cores timings for the rayon-parallel paths.
+//! Run with `cargo bench -p mmss-eqgft`.
+
+use mmss_eqgft::asymmetry::calculate_polarization_asymmetry;
+use mmss_eqgft::config::EqgftConfig;
+use mmss_eqgft::hopfion::{generate_hopfion_soliton_field, HopfionConfig};
+use mmss_eqgft::sensitivity::{calculate_sensitivity_curve, log_spaced_events};
+use mmss_eqgft::topology::compute_hopf_charge;
+use std::time::{Duration, Instant};
+
+fn time<T>(mut work: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    std::hint::black_box(work());
+    start.elapsed()
+}
+
+fn compare(name: &str, mut work: impl FnMut(Option<usize>)) {
+    work(None); // warm up the global pool
+    let serial = time(|| work(Some(1)));
+    let parallel = time(|| work(None));
+    println!(
+        "{name:<32} 1 thread {:>9.1?}  {} threads {:>9.1?}  speedup {:.2}x",
+        serial,
+        rayon::current_num_threads(),
+        parallel,
+        serial.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
+
+fn main() {
+    let events = EqgftConfig {
+        n_events: 5_000_000,
+        seed: Some(1),
+        ..EqgftConfig::default()
+    };
+    compare("asymmetry, 5M events", |threads| {
+        calculate_polarization_asymmetry(&EqgftConfig { threads, ..events }).unwrap();
+    });
+
+    let n_values = log_spaced_events(1_000, 1_000_000, 50);
+    compare("sensitivity curve, 50 points", |threads| {
+        calculate_sensitivity_curve(&EqgftConfig { threads, ..events }, &n_values).unwrap();
+    });
+
+    for resolution in [32, 64] {
+        let lattice = HopfionConfig {
+            resolution,
+            ..HopfionConfig::default()
+        };
+        compare(&format!("field generation, {resolution}^3"), |threads| {
+            generate_hopfion_soliton_field(&HopfionConfig { threads, ..lattice }).unwrap();
+        });
+        let field = generate_hopfion_soliton_field(&lattice).unwrap();
+        compare(&format!("hopf charge, {resolution}^3"), |threads| {
+            let mut field = field.clone();
+            field.config.threads = threads;
+            compute_hopf_charge(&field);
+        });
+    }
+}
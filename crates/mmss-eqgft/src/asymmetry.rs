@@ -0,0 +1,372 @@
+use crate::config::EqgftConfig;
+use crate::parallel::run_with_threads;
+use crate::{EqgftError, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub use mmss_compute::sensitivity::{predicted_asymmetry, ALPHA};
+
+/// Outcome of one simulated measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PolarizationAsymmetry {
+    pub kappa: f64,
+    /// EQGFT prediction 𝒜 = κα.
+    pub predicted: f64,
+    /// Asymmetry extracted from the generated events.
+    pub a: f64,
+    pub stat_error: f64,
+    pub syst_error: f64,
+    /// Events the extraction used, after any detector losses.
+    pub n_events: usize,
+    /// `stat_error` and `syst_error` combined in quadrature.
+    pub uncertainty: f64,
+}
+
+impl PolarizationAsymmetry {
+    pub fn new(kappa: f64, a: f64, stat_error: f64, syst_error: f64, n_events: usize) -> Self {
+        Self {
+            kappa,
+            predicted: predicted_asymmetry(kappa),
+            a,
+            stat_error,
+            syst_error,
+            n_events,
+            uncertainty: stat_error.hypot(syst_error),
+        }
+    }
+
+    /// Deviation from the QED expectation 𝒜 = 0 in standard deviations.
+    pub fn significance(&self) -> f64 {
+        if self.uncertainty > 0.0 {
+            self.a.abs() / self.uncertainty
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    /// Deviation of the measurement from the EQGFT prediction in standard
+    /// deviations, signed.
+    pub fn pull(&self) -> f64 {
+        (self.a - self.predicted) / self.uncertainty
+    }
+
+    /// The asymmetry times an exact `factor`, e.g. an analyzing power
+    /// correction; both errors scale with it.
+    pub fn scaled(&self, factor: f64) -> Self {
+        Self {
+            a: self.a * factor,
+            stat_error: self.stat_error * factor.abs(),
+            syst_error: self.syst_error * factor.abs(),
+            uncertainty: self.uncertainty * factor.abs(),
+            ..*self
+        }
+    }
+
+    /// Add an independent systematic contribution in quadrature.
+    pub fn with_systematic(&self, syst_error: f64) -> Self {
+        Self::new(
+            self.kappa,
+            self.a,
+            self.stat_error,
+            self.syst_error.hypot(syst_error),
+            self.n_events,
+        )
+    }
+
+    /// Inverse-variance average of measurements at the same `kappa` on
+    /// independent samples. Statistical errors are independent; systematic
+    /// ones, from a shared detector and method, are taken as fully
+    /// correlated and so average rather than shrink.
+    pub fn combine(measurements: &[Self]) -> Result<Self> {
+        let Some(first) = measurements.first() else {
+            return Err(EqgftError::InvalidConfig(
+                "no measurements to combine".into(),
+            ));
+        };
+        if measurements.iter().any(|m| m.kappa != first.kappa) {
+            return Err(EqgftError::InvalidConfig(
+                "combined measurements must share kappa".into(),
+            ));
+        }
+        if measurements
+            .iter()
+            .any(|m| m.stat_error.is_nan() || m.stat_error <= 0.0)
+        {
+            return Err(EqgftError::InvalidConfig(
+                "combined measurements need positive statistical errors".into(),
+            ));
+        }
+        let weights: Vec<f64> = measurements.iter().map(|m| m.stat_error.powi(-2)).collect();
+        let total: f64 = weights.iter().sum();
+        let average = |pick: fn(&Self) -> f64| {
+            measurements
+                .iter()
+                .zip(&weights)
+                .map(|(m, w)| w * pick(m))
+                .sum::<f64>()
+                / total
+        };
+        Ok(Self::new(
+            first.kappa,
+            average(|m| m.a),
+            total.sqrt().recip(),
+            average(|m| m.syst_error),
+            measurements.iter().map(|m| m.n_events).sum(),
+        ))
+    }
+}
+
+/// Events drawn from one RNG stream; fixed so that a seeded sample does not
+/// depend on the number of worker threads.
+pub(crate) const EVENT_CHUNK: usize = 1 << 16;
+
+/// Sample `n_events` decay angles `cos θ` from `W(cos θ) = ½ (1 + 𝒜 cos θ)`
+/// by inverting the cumulative distribution.
+pub fn generate_events(asymmetry: f64, n_events: usize, seed: u64) -> Vec<f64> {
+    (0..n_events.div_ceil(EVENT_CHUNK))
+        .into_par_iter()
+        .flat_map_iter(|chunk| {
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(chunk as u64));
+            let len = EVENT_CHUNK.min(n_events - chunk * EVENT_CHUNK);
+            (0..len)
+                .map(|_| sample_cos_theta(asymmetry, rng.gen::<f64>()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn sample_cos_theta(asymmetry: f64, u: f64) -> f64 {
+    if asymmetry.abs() < 1e-12 {
+        return 2.0 * u - 1.0;
+    }
+    // F(c) = (c + 1)/2 + 𝒜 (c² - 1)/4 = u, solved for the root in [-1, 1]
+    let discriminant = 0.25 - asymmetry * (0.5 - 0.25 * asymmetry - u);
+    ((-0.5 + discriminant.max(0.0).sqrt()) / (0.5 * asymmetry)).clamp(-1.0, 1.0)
+}
+
+/// How the asymmetry is extracted from an event sample.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Estimator {
+    /// `measure_asymmetry`, for samples at full acceptance.
+    #[default]
+    Moment,
+    /// `measure_ratio_asymmetry`, for samples behind a detector.
+    Ratio,
+}
+
+impl Estimator {
+    /// The estimator for the samples `config` produces: the ratio one when
+    /// a detector model is set, the moment one at truth level.
+    pub fn for_config(config: &EqgftConfig) -> Self {
+        match config.detector {
+            Some(_) => Self::Ratio,
+            None => Self::Moment,
+        }
+    }
+
+    pub fn measure(self, events: &[f64]) -> Result<(f64, f64)> {
+        match self {
+            Self::Moment => measure_asymmetry(events),
+            Self::Ratio => measure_ratio_asymmetry(events),
+        }
+    }
+}
+
+/// Moment estimator `𝒜 = 3⟨cos θ⟩` and its statistical error
+/// `√((3 - 𝒜²)/N)`. Only unbiased at full acceptance.
+pub fn measure_asymmetry(events: &[f64]) -> Result<(f64, f64)> {
+    if events.is_empty() {
+        return Err(EqgftError::InvalidConfig("no events to measure".into()));
+    }
+    let n = events.len() as f64;
+    // per-chunk partial sums keep the result independent of the thread count
+    let partial: Vec<f64> = events
+        .par_chunks(EVENT_CHUNK)
+        .map(|chunk| chunk.iter().sum())
+        .collect();
+    let a = (3.0 * partial.iter().sum::<f64>() / n).clamp(-1.0, 1.0);
+    Ok((a, ((3.0 - a * a) / n).sqrt()))
+}
+
+/// Ratio estimator `𝒜 = ⟨cos θ⟩ / ⟨cos² θ⟩` and its delta-method
+/// statistical error. Unlike `measure_asymmetry` it stays unbiased under
+/// symmetric acceptance cuts and efficiencies, at the price of a different
+/// error at full acceptance (`√(2.55/N)` rather than `√(2.75/N)` at
+/// 𝒜 = 0.5).
+pub fn measure_ratio_asymmetry(events: &[f64]) -> Result<(f64, f64)> {
+    if events.is_empty() {
+        return Err(EqgftError::InvalidConfig("no events to measure".into()));
+    }
+    let n = events.len() as f64;
+    // per-chunk partial sums keep the result independent of the thread count
+    let partial: Vec<[f64; 4]> = events
+        .par_chunks(EVENT_CHUNK)
+        .map(|chunk| {
+            chunk.iter().fold([0.0; 4], |[m1, m2, m3, m4], &c| {
+                let c2 = c * c;
+                [m1 + c, m2 + c2, m3 + c2 * c, m4 + c2 * c2]
+            })
+        })
+        .collect();
+    let [m1, m2, m3, m4] = partial
+        .iter()
+        .fold([0.0; 4], |acc, sums| {
+            std::array::from_fn(|i| acc[i] + sums[i])
+        })
+        .map(|sum| sum / n);
+    if m2 <= 0.0 {
+        return Err(EqgftError::InvalidConfig(
+            "events carry no angular information".into(),
+        ));
+    }
+
+    let a = (m1 / m2).clamp(-1.0, 1.0);
+    let variance = ((m2 - m1 * m1) - 2.0 * a * (m3 - m1 * m2) + a * a * (m4 - m2 * m2)) / (m2 * m2);
+    Ok((a, (variance.max(0.0) / n).sqrt()))
+}
+
+fn physical_prediction(kappa: f64) -> Result<f64> {
+    let predicted = predicted_asymmetry(kappa);
+    if predicted.abs() > 1.0 {
+        return Err(EqgftError::InvalidConfig(format!(
+            "kappa {kappa} gives an unphysical asymmetry"
+        )));
+    }
+    Ok(predicted)
+}
+
+const DETECTOR_STREAM: u64 = 0xD37E_C70F_0000_0000;
+
+/// Generate the event sample described by `config`, passed through its
+/// detector model when one is set.
+pub fn simulate_events(config: &EqgftConfig) -> Result<Vec<f64>> {
+    config.validate()?;
+    let predicted = physical_prediction(config.kappa)?;
+    let seed = config.seed.unwrap_or_else(rand::random);
+    run_with_threads(config.threads, || {
+        let events = generate_events(predicted, config.n_events, seed);
+        match &config.detector {
+            // a separate stream, so truth-level events stay the same with and without a detector
+            Some(detector) => detector.apply(&events, seed ^ DETECTOR_STREAM),
+            None => events,
+        }
+    })
+}
+
+/// Extract the asymmetry from `events` with the estimator `config` calls
+/// for, adding the systematic error of `config` to the statistical one.
+pub fn measure_polarization_asymmetry(
+    config: &EqgftConfig,
+    events: &[f64],
+) -> Result<PolarizationAsymmetry> {
+    physical_prediction(config.kappa)?;
+    let estimator = Estimator::for_config(config);
+    let (a, stat_error) = run_with_threads(config.threads, || estimator.measure(events))??;
+    Ok(PolarizationAsymmetry::new(
+        config.kappa,
+        a,
+        stat_error,
+        config.systematic_error,
+        events.len(),
+    ))
+}
+
+/// Run the Monte Carlo measurement described by `config`.
+pub fn calculate_polarization_asymmetry(config: &EqgftConfig) -> Result<PolarizationAsymmetry> {
+    measure_polarization_asymmetry(config, &simulate_events(config)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measurement_recovers_large_asymmetry() {
+        let events = generate_events(0.5, 200_000, 7);
+        assert!(events.iter().all(|c| (-1.0..=1.0).contains(c)));
+
+        let (a, error) = measure_asymmetry(&events).unwrap();
+        assert!((a - 0.5).abs() < 4.0 * error);
+        assert!((error - (2.75f64 / 200_000.0).sqrt()).abs() < 2e-5);
+
+        let (ratio, ratio_error) = measure_ratio_asymmetry(&events).unwrap();
+        assert!((ratio - 0.5).abs() < 4.0 * ratio_error);
+        // delta-method variance of the ratio estimator at 𝒜 = 0.5 is 2.55 / N
+        assert!((ratio_error - (2.55f64 / 200_000.0).sqrt()).abs() < 3e-5);
+    }
+
+    #[test]
+    fn test_seeded_sample_ignores_thread_count() {
+        let config = EqgftConfig {
+            seed: Some(11),
+            n_events: 3 * EVENT_CHUNK + 5,
+            threads: Some(1),
+            ..EqgftConfig::default()
+        };
+        let serial = calculate_polarization_asymmetry(&config).unwrap();
+        let parallel = calculate_polarization_asymmetry(&EqgftConfig {
+            threads: Some(4),
+            ..config
+        })
+        .unwrap();
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn test_systematic_error_enters_uncertainty() {
+        let config = EqgftConfig {
+            seed: Some(1),
+            systematic_error: 0.0,
+            ..EqgftConfig::default()
+        };
+        let stat_only = calculate_polarization_asymmetry(&config).unwrap();
+        let with_sys = calculate_polarization_asymmetry(&EqgftConfig {
+            systematic_error: 0.01,
+            ..config
+        })
+        .unwrap();
+
+        assert_eq!(stat_only.a, with_sys.a);
+        assert!((stat_only.predicted - 0.2 * ALPHA).abs() < 1e-15);
+        let expected = stat_only.uncertainty.hypot(0.01);
+        assert!((with_sys.uncertainty - expected).abs() < 1e-12);
+        assert_eq!(with_sys.stat_error, stat_only.stat_error);
+        assert_eq!(
+            (with_sys.syst_error, with_sys.n_events),
+            (0.01, config.n_events)
+        );
+    }
+
+    #[test]
+    fn test_combination_shrinks_only_statistics() {
+        let measurement =
+            |a, stat_error| PolarizationAsymmetry::new(0.2, a, stat_error, 0.002, 1_000);
+        let combined =
+            PolarizationAsymmetry::combine(&[measurement(0.01, 0.004), measurement(0.02, 0.004)])
+                .unwrap();
+        assert!((combined.a - 0.015).abs() < 1e-15);
+        assert!((combined.stat_error - 0.004 / 2f64.sqrt()).abs() < 1e-15);
+        assert_eq!((combined.syst_error, combined.n_events), (0.002, 2_000));
+
+        let doubled = measurement(0.01, 0.004).scaled(-2.0).with_systematic(0.004);
+        assert_eq!((doubled.a, doubled.stat_error), (-0.02, 0.008));
+        assert!((doubled.syst_error - 0.004f64.hypot(0.004)).abs() < 1e-15);
+        assert!(PolarizationAsymmetry::combine(&[
+            measurement(0.01, 0.004),
+            PolarizationAsymmetry::new(0.3, 0.01, 0.004, 0.0, 1)
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_rejects_empty_sample() {
+        let config = EqgftConfig {
+            n_events: 0,
+            ..EqgftConfig::default()
+        };
+        assert!(calculate_polarization_asymmetry(&config).is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use crate::hopfion::{generate_hopfion_soliton_field, HopfionConfig, HopfionSolitonField};
+use crate::topology::compute_hopf_charge;
+use crate::{EqgftError, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Requested compute backend for lattice work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// The GPU when there is an adapter, else the CPU.
+    #[default]
+    Auto,
+    Cpu,
+    /// wgpu compute shaders; needs the `gpu` feature and an adapter.
+    Gpu,
+}
+
+/// Field generation and charge integration on one kind of device.
+pub trait FieldBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn generate(&self, config: &HopfionConfig) -> Result<HopfionSolitonField>;
+
+    fn hopf_charge(&self, field: &HopfionSolitonField) -> f64;
+}
+
+/// The rayon-parallel lattice code.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuBackend;
+
+impl FieldBackend for CpuBackend {
+    fn name(&self) -> &'static str {
+        "cpu"
+    }
+
+    fn generate(&self, config: &HopfionConfig) -> Result<HopfionSolitonField> {
+        generate_hopfion_soliton_field(config)
+    }
+
+    fn hopf_charge(&self, field: &HopfionSolitonField) -> f64 {
+        compute_hopf_charge(field)
+    }
+}
+
+/// The backend `preference` resolves to, failing only for `Gpu` without a
+/// GPU.
+pub fn select_backend(preference: Backend) -> Result<Arc<dyn FieldBackend>> {
+    match preference {
+        Backend::Cpu => Ok(Arc::new(CpuBackend)),
+        Backend::Auto => Ok(gpu_backend().unwrap_or_else(|| Arc::new(CpuBackend))),
+        Backend::Gpu => gpu_backend().ok_or_else(|| {
+            EqgftError::InvalidConfig(if cfg!(feature = "gpu") {
+                "no GPU adapter is available".into()
+            } else {
+                "the gpu backend needs the gpu feature".into()
+            })
+        }),
+    }
+}
+
+#[cfg(feature = "gpu")]
+fn gpu_backend() -> Option<Arc<dyn FieldBackend>> {
+    crate::gpu::GpuBackend::shared().map(|gpu| gpu as Arc<dyn FieldBackend>)
+}
+
+#[cfg(not(feature = "gpu"))]
+fn gpu_backend() -> Option<Arc<dyn FieldBackend>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_falls_back_to_the_cpu() {
+        let backend = select_backend(Backend::Auto).unwrap();
+        let gpu = select_backend(Backend::Gpu);
+        match &gpu {
+            Ok(gpu) => assert_eq!(backend.name(), gpu.name()),
+            Err(_) => assert_eq!(backend.name(), "cpu"),
+        }
+        if !cfg!(feature = "gpu") {
+            assert!(gpu.is_err());
+        }
+        assert!(serde_json::from_str::<Backend>("\"tpu\"").is_err());
+
+        let backend = select_backend(Backend::Cpu).unwrap();
+        let config = HopfionConfig {
+            resolution: 9,
+            ..HopfionConfig::default()
+        };
+        let field = backend.generate(&config).unwrap();
+        assert_eq!(backend.hopf_charge(&field), compute_hopf_charge(&field));
+    }
+}
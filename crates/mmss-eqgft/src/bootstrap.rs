@@ -0,0 +1,154 @@
+use crate::asymmetry::Estimator;
+use crate::parallel::run_with_threads;
+use crate::stats::Interval;
+use crate::{EqgftError, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BootstrapConfig {
+    /// Number of resamples B.
+    pub resamples: usize,
+    /// Resample `b` uses `seed + b`; entropy-seeded when absent.
+    pub seed: Option<u64>,
+    /// Coverage of the percentile interval.
+    pub confidence: f64,
+    /// Added in quadrature when computing each resample's significance.
+    pub systematic_error: f64,
+    pub threads: Option<usize>,
+    /// Applied to each resample; match it to the sample, see
+    /// `Estimator::for_config`.
+    pub estimator: Estimator,
+}
+
+impl Default for BootstrapConfig {
+    fn default() -> Self {
+        Self {
+            resamples: 200,
+            seed: None,
+            confidence: 0.95,
+            systematic_error: 0.0,
+            threads: None,
+            estimator: Estimator::Moment,
+        }
+    }
+}
+
+/// Empirical distribution of one quantity over the resamples.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Distribution {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub median: f64,
+    /// Percentile interval at the configured confidence.
+    pub interval: Interval,
+}
+
+impl Distribution {
+    fn from_samples(mut samples: Vec<f64>, confidence: f64) -> Self {
+        samples.sort_by(f64::total_cmp);
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+        let tail = 0.5 * (1.0 - confidence);
+        Self {
+            mean,
+            std_dev: variance.sqrt(),
+            median: percentile(&samples, 0.5),
+            interval: Interval {
+                low: percentile(&samples, tail),
+                high: percentile(&samples, 1.0 - tail),
+                confidence,
+            },
+        }
+    }
+}
+
+/// Linear interpolation between order statistics of sorted `samples`.
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let position = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BootstrapSummary {
+    pub resamples: usize,
+    pub n_events: usize,
+    pub asymmetry: Distribution,
+    /// `|𝒜| / δ𝒜` per resample, against the null κ = 0.
+    pub significance: Distribution,
+}
+
+/// Resample `events` with replacement `config.resamples` times and collect
+/// the asymmetry and significance of each resample.
+pub fn bootstrap_asymmetry(events: &[f64], config: &BootstrapConfig) -> Result<BootstrapSummary> {
+    if events.is_empty() || config.resamples < 2 {
+        return Err(EqgftError::InvalidConfig(
+            "bootstrap needs events and at least two resamples".into(),
+        ));
+    }
+    if !(config.confidence > 0.0 && config.confidence < 1.0) {
+        return Err(EqgftError::InvalidConfig(
+            "bootstrap confidence must lie in (0, 1)".into(),
+        ));
+    }
+    let base_seed = config.seed.unwrap_or_else(rand::random);
+
+    let draws: Vec<(f64, f64)> = run_with_threads(config.threads, || {
+        (0..config.resamples)
+            .into_par_iter()
+            .map(|b| {
+                let mut rng = StdRng::seed_from_u64(base_seed.wrapping_add(b as u64));
+                let resample: Vec<f64> = (0..events.len())
+                    .map(|_| events[rng.gen_range(0..events.len())])
+                    .collect();
+                let (a, stat_error) = config.estimator.measure(&resample)?;
+                Ok((a, a.abs() / stat_error.hypot(config.systematic_error)))
+            })
+            .collect::<Result<Vec<_>>>()
+    })??;
+
+    let (asymmetries, significances) = draws.into_iter().unzip();
+    Ok(BootstrapSummary {
+        resamples: config.resamples,
+        n_events: events.len(),
+        asymmetry: Distribution::from_samples(asymmetries, config.confidence),
+        significance: Distribution::from_samples(significances, config.confidence),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asymmetry::{generate_events, measure_asymmetry};
+
+    #[test]
+    fn test_bootstrap_spread_matches_analytic_error() {
+        let events = generate_events(0.3, 5_000, 4);
+        let (a, stat_error) = measure_asymmetry(&events).unwrap();
+        let config = BootstrapConfig {
+            resamples: 200,
+            seed: Some(8),
+            ..BootstrapConfig::default()
+        };
+        let summary = bootstrap_asymmetry(&events, &config).unwrap();
+
+        assert!((summary.asymmetry.mean - a).abs() < stat_error);
+        assert!((summary.asymmetry.std_dev / stat_error - 1.0).abs() < 0.15);
+        assert!(summary.asymmetry.interval.contains(a));
+        assert_eq!(summary, bootstrap_asymmetry(&events, &config).unwrap());
+    }
+
+    #[test]
+    fn test_bootstrap_rejects_single_resample() {
+        let config = BootstrapConfig {
+            resamples: 1,
+            ..BootstrapConfig::default()
+        };
+        assert!(bootstrap_asymmetry(&[0.5, -0.5], &config).is_err());
+    }
+}
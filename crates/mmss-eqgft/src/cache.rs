@@ -0,0 +1,364 @@
+//! Memoization of field generation and event samples keyed by their
+//! parameters, with optional spill of evicted entries to disk.
+
+use crate::asymmetry::simulate_events;
+use crate::config::EqgftConfig;
+use crate::hopfion::{HopfionConfig, HopfionSolitonField};
+use crate::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    /// Lookups answered from the spill directory.
+    pub disk_hits: u64,
+    pub misses: u64,
+    /// Entries pushed out of memory to make room.
+    pub evictions: u64,
+    /// Entries currently in memory.
+    pub entries: usize,
+}
+
+/// Least-recently-used cache of `V` keyed by any serializable value.
+///
+/// At most `capacity` entries stay in memory. With a spill directory,
+/// evicted entries are written there as JSON and reloaded on the next
+/// lookup; spilling is best effort, so I/O failures only cost a recompute.
+#[derive(Debug)]
+pub struct ComputeCache<V> {
+    name: &'static str,
+    capacity: usize,
+    spill_dir: Option<PathBuf>,
+    state: Mutex<CacheState<V>>,
+}
+
+#[derive(Debug)]
+struct CacheState<V> {
+    entries: HashMap<String, Arc<V>>,
+    /// Keys from least to most recently used.
+    order: VecDeque<String>,
+    stats: CacheStats,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SpilledEntry<V> {
+    key: String,
+    value: V,
+}
+
+impl<V: Serialize + DeserializeOwned> ComputeCache<V> {
+    /// `name` prefixes spill files, so caches can share a directory.
+    pub fn new(name: &'static str, capacity: usize, spill_dir: Option<PathBuf>) -> Self {
+        Self {
+            name,
+            capacity,
+            spill_dir,
+            state: Mutex::new(CacheState {
+                entries: HashMap::new(),
+                order: VecDeque::new(),
+                stats: CacheStats::default(),
+            }),
+        }
+    }
+
+    /// The cached value for `key`, or the result of `compute`, which runs
+    /// without holding the cache lock. Errors are not cached.
+    pub fn get_or_compute<K: Serialize>(
+        &self,
+        key: &K,
+        compute: impl FnOnce() -> Result<V>,
+    ) -> Result<Arc<V>> {
+        let key = cache_key(key);
+        {
+            let mut state = self.lock();
+            if let Some(value) = state.entries.get(&key).cloned() {
+                state.stats.hits += 1;
+                touch(&mut state.order, &key);
+                return Ok(value);
+            }
+        }
+        if let Some(value) = self.load_spilled(&key) {
+            let value = Arc::new(value);
+            let mut state = self.lock();
+            state.stats.disk_hits += 1;
+            self.insert(&mut state, key, value.clone());
+            return Ok(value);
+        }
+
+        let value = Arc::new(compute()?);
+        let mut state = self.lock();
+        state.stats.misses += 1;
+        self.insert(&mut state, key, value.clone());
+        Ok(value)
+    }
+
+    /// Drop `key` from memory and disk; true if it was cached.
+    pub fn invalidate<K: Serialize>(&self, key: &K) -> bool {
+        let key = cache_key(key);
+        let in_memory = {
+            let mut state = self.lock();
+            state.order.retain(|k| *k != key);
+            state.entries.remove(&key).is_some()
+        };
+        let on_disk = self
+            .spill_path(&key)
+            .is_some_and(|path| fs::remove_file(path).is_ok());
+        in_memory || on_disk
+    }
+
+    /// Drop every entry, including this cache's spill files.
+    pub fn clear(&self) {
+        {
+            let mut state = self.lock();
+            state.entries.clear();
+            state.order.clear();
+        }
+        if let Some(dir) = &self.spill_dir {
+            let prefix = format!("{}-", self.name);
+            for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+                if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                    let _ = fs::remove_file(entry.path());
+                }
+            }
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let state = self.lock();
+        CacheStats {
+            entries: state.entries.len(),
+            ..state.stats
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState<V>> {
+        // entries are only ever replaced whole, so a poisoned lock is still consistent
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn insert(&self, state: &mut CacheState<V>, key: String, value: Arc<V>) {
+        if self.capacity == 0 {
+            self.spill(&key, &value);
+            return;
+        }
+        while state.entries.len() >= self.capacity {
+            let Some(oldest) = state.order.pop_front() else {
+                break;
+            };
+            if let Some(evicted) = state.entries.remove(&oldest) {
+                state.stats.evictions += 1;
+                self.spill(&oldest, &evicted);
+            }
+        }
+        touch(&mut state.order, &key);
+        state.entries.insert(key, value);
+    }
+
+    fn spill_path(&self, key: &str) -> Option<PathBuf> {
+        let dir = self.spill_dir.as_deref()?;
+        Some(dir.join(format!("{}-{:016x}.json", self.name, fnv1a(key))))
+    }
+
+    fn spill(&self, key: &str, value: &V) {
+        let Some(path) = self.spill_path(key) else {
+            return;
+        };
+        let entry = SpilledEntry {
+            key: key.to_string(),
+            value,
+        };
+        if let Ok(json) = serde_json::to_vec(&entry) {
+            let _ = path.parent().map(fs::create_dir_all);
+            let _ = fs::write(path, json);
+        }
+    }
+
+    fn load_spilled(&self, key: &str) -> Option<V> {
+        let bytes = fs::read(self.spill_path(key)?).ok()?;
+        let entry: SpilledEntry<V> = serde_json::from_slice(&bytes).ok()?;
+        // the file name is only a hash, so check for a collision
+        (entry.key == key).then_some(entry.value)
+    }
+}
+
+fn cache_key<K: Serialize>(key: &K) -> String {
+    serde_json::to_string(key).expect("cache keys serialize to JSON")
+}
+
+fn touch(order: &mut VecDeque<String>, key: &str) {
+    order.retain(|k| k != key);
+    order.push_back(key.to_string());
+}
+
+/// FNV-1a, stable across builds unlike `DefaultHasher`, for spill file names.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EqgftCacheStats {
+    pub fields: CacheStats,
+    pub events: CacheStats,
+}
+
+/// Caches for the two expensive EQGFT computations: Hopfion lattices and
+/// Monte Carlo event samples.
+#[derive(Debug)]
+pub struct EqgftCache {
+    fields: ComputeCache<HopfionSolitonField>,
+    events: ComputeCache<Vec<f64>>,
+}
+
+impl EqgftCache {
+    /// Each cache keeps up to `capacity` entries in memory.
+    pub fn new(capacity: usize, spill_dir: Option<&Path>) -> Self {
+        let spill_dir = spill_dir.map(Path::to_path_buf);
+        Self {
+            fields: ComputeCache::new("hopfion", capacity, spill_dir.clone()),
+            events: ComputeCache::new("events", capacity, spill_dir),
+        }
+    }
+
+    /// The field for `config`, built with `generate` on a miss. The thread
+    /// count does not affect the result and is left out of the key.
+    pub fn hopfion_field(
+        &self,
+        config: &HopfionConfig,
+        generate: impl FnOnce(&HopfionConfig) -> Result<HopfionSolitonField>,
+    ) -> Result<Arc<HopfionSolitonField>> {
+        self.fields
+            .get_or_compute(&field_key(config), || generate(config))
+    }
+
+    /// The sample of `simulate_events(config)`. Entropy-seeded configs give
+    /// a fresh sample every time and bypass the cache.
+    pub fn events(&self, config: &EqgftConfig) -> Result<Arc<Vec<f64>>> {
+        if config.seed.is_none() {
+            return simulate_events(config).map(Arc::new);
+        }
+        self.events
+            .get_or_compute(&events_key(config), || simulate_events(config))
+    }
+
+    pub fn invalidate_field(&self, config: &HopfionConfig) -> bool {
+        self.fields.invalidate(&field_key(config))
+    }
+
+    pub fn invalidate_events(&self, config: &EqgftConfig) -> bool {
+        self.events.invalidate(&events_key(config))
+    }
+
+    pub fn clear(&self) {
+        self.fields.clear();
+        self.events.clear();
+    }
+
+    pub fn stats(&self) -> EqgftCacheStats {
+        EqgftCacheStats {
+            fields: self.fields.stats(),
+            events: self.events.stats(),
+        }
+    }
+}
+
+impl Default for EqgftCache {
+    fn default() -> Self {
+        Self::new(16, None)
+    }
+}
+
+fn field_key(config: &HopfionConfig) -> HopfionConfig {
+    HopfionConfig {
+        threads: None,
+        ..*config
+    }
+}
+
+/// Only the inputs of event generation; systematics apply afterwards.
+fn events_key(config: &EqgftConfig) -> EqgftConfig {
+    EqgftConfig {
+        systematic_error: 0.0,
+        threads: None,
+        ..*config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hopfion::generate_hopfion_soliton_field;
+    use std::env;
+
+    #[test]
+    fn test_hits_evictions_and_invalidation() {
+        let cache = EqgftCache::new(2, None);
+        let config = |resolution| HopfionConfig {
+            resolution,
+            ..HopfionConfig::default()
+        };
+        let first = cache
+            .hopfion_field(&config(5), generate_hopfion_soliton_field)
+            .unwrap();
+        let again = cache
+            .hopfion_field(
+                &HopfionConfig {
+                    threads: Some(1),
+                    ..config(5)
+                },
+                |_| panic!("should be cached"),
+            )
+            .unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+
+        for resolution in [6, 7] {
+            cache
+                .hopfion_field(&config(resolution), generate_hopfion_soliton_field)
+                .unwrap();
+        }
+        let stats = cache.stats().fields;
+        assert_eq!(
+            (stats.hits, stats.misses, stats.evictions, stats.entries),
+            (1, 3, 1, 2)
+        );
+
+        assert!(cache.invalidate_field(&config(7)));
+        assert!(!cache.invalidate_field(&config(7)));
+        assert_eq!(cache.stats().fields.entries, 1);
+    }
+
+    #[test]
+    fn test_evicted_entries_reload_from_disk() {
+        let dir = env::temp_dir().join(format!("mmss-eqgft-cache-{}", std::process::id()));
+        let cache = EqgftCache::new(1, Some(&dir));
+        let config = |seed| EqgftConfig {
+            n_events: 1_000,
+            seed: Some(seed),
+            ..EqgftConfig::default()
+        };
+        let first = cache.events(&config(1)).unwrap();
+        cache.events(&config(2)).unwrap();
+        let reloaded = cache.events(&config(1)).unwrap();
+        assert_eq!(first, reloaded);
+        assert_eq!(cache.stats().events.disk_hits, 1);
+
+        // systematics do not change the sample
+        let shifted = EqgftConfig {
+            systematic_error: 0.5,
+            ..config(1)
+        };
+        cache.events(&shifted).unwrap();
+        assert_eq!(cache.stats().events.hits, 1);
+
+        cache.clear();
+        assert_eq!(cache.stats().events.entries, 0);
+        assert!(fs::read_dir(&dir).unwrap().next().is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,254 @@
+use crate::asymmetry::{
+    calculate_polarization_asymmetry, predicted_asymmetry, PolarizationAsymmetry,
+};
+use crate::config::EqgftConfig;
+use crate::detector::DetectorModel;
+use crate::{EqgftError, Result};
+use serde::{Deserialize, Serialize};
+
+/// One decay channel measuring the same 𝒜 with its own analyzing power,
+/// sample size and systematics.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Channel {
+    pub name: String,
+    /// Analyzing power: the channel observes `sensitivity · κα`.
+    pub sensitivity: f64,
+    pub n_events: usize,
+    /// Absolute systematic uncertainty on the observed asymmetry.
+    pub systematic_error: f64,
+    pub detector: Option<DetectorModel>,
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            sensitivity: 1.0,
+            n_events: 50_000,
+            systematic_error: 1e-4,
+            detector: None,
+        }
+    }
+}
+
+/// Simulate every channel at `config.kappa`, taking the seed and threads from
+/// `config`, and express each result as an estimate of 𝒜 = κα, i.e. the
+/// observed asymmetry divided by the channel sensitivity. With a seed,
+/// channel `i` uses `seed + (i << 32)`.
+pub fn simulate_channels(
+    config: &EqgftConfig,
+    channels: &[Channel],
+) -> Result<Vec<PolarizationAsymmetry>> {
+    let base_seed = config.seed.unwrap_or_else(rand::random);
+    channels
+        .iter()
+        .enumerate()
+        .map(|(i, channel)| {
+            if !(channel.sensitivity.is_finite() && channel.sensitivity != 0.0) {
+                return Err(EqgftError::InvalidConfig(format!(
+                    "channel '{}' needs a finite, non-zero sensitivity",
+                    channel.name
+                )));
+            }
+            let observed = calculate_polarization_asymmetry(&EqgftConfig {
+                kappa: config.kappa * channel.sensitivity,
+                n_events: channel.n_events,
+                systematic_error: channel.systematic_error,
+                seed: Some(base_seed.wrapping_add((i as u64) << 32)),
+                threads: config.threads,
+                detector: channel.detector,
+            })?;
+            Ok(PolarizationAsymmetry {
+                kappa: config.kappa,
+                predicted: predicted_asymmetry(config.kappa),
+                ..observed.scaled(channel.sensitivity.recip())
+            })
+        })
+        .collect()
+}
+
+/// Result of `blue_combine`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelCombination {
+    pub asymmetry: PolarizationAsymmetry,
+    /// BLUE weight per input, summing to one; negative under strong
+    /// correlations.
+    pub weights: Vec<f64>,
+    /// Compatibility of the inputs with the combined value.
+    pub chi2: f64,
+    pub ndf: usize,
+}
+
+/// Best linear unbiased estimate of 𝒜 from measurements of it at the same
+/// `kappa`. Statistical errors are independent; systematic errors are
+/// correlated between channels with coefficient `systematic_correlation`.
+/// The combined error is split into a statistical part, from the weighted
+/// statistical errors, and the systematic remainder.
+pub fn blue_combine(
+    measurements: &[PolarizationAsymmetry],
+    systematic_correlation: f64,
+) -> Result<ChannelCombination> {
+    let Some(first) = measurements.first() else {
+        return Err(EqgftError::InvalidConfig(
+            "no measurements to combine".into(),
+        ));
+    };
+    if measurements.iter().any(|m| m.kappa != first.kappa) {
+        return Err(EqgftError::InvalidConfig(
+            "combined measurements must share kappa".into(),
+        ));
+    }
+    if !(-1.0..=1.0).contains(&systematic_correlation) {
+        return Err(EqgftError::InvalidConfig(
+            "systematic_correlation must lie in [-1, 1]".into(),
+        ));
+    }
+
+    let n = measurements.len();
+    let covariance: Vec<Vec<f64>> = measurements
+        .iter()
+        .enumerate()
+        .map(|(i, mi)| {
+            measurements
+                .iter()
+                .enumerate()
+                .map(|(j, mj)| {
+                    if i == j {
+                        mi.uncertainty * mi.uncertainty
+                    } else {
+                        systematic_correlation * mi.syst_error * mj.syst_error
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let singular = || EqgftError::InvalidConfig("measurement covariance is singular".into());
+    let inverse_ones = solve(covariance.clone(), vec![1.0; n]).ok_or_else(singular)?;
+    let information: f64 = inverse_ones.iter().sum();
+    if !(information.is_finite() && information > 0.0) {
+        return Err(singular());
+    }
+    let weights: Vec<f64> = inverse_ones.iter().map(|x| x / information).collect();
+
+    let a: f64 = weights.iter().zip(measurements).map(|(w, m)| w * m.a).sum();
+    let stat_variance: f64 = weights
+        .iter()
+        .zip(measurements)
+        .map(|(w, m)| (w * m.stat_error).powi(2))
+        .sum();
+    let syst_variance = (information.recip() - stat_variance).max(0.0);
+
+    let residuals: Vec<f64> = measurements.iter().map(|m| m.a - a).collect();
+    let pulls = solve(covariance, residuals.clone()).ok_or_else(singular)?;
+    let chi2 = residuals.iter().zip(&pulls).map(|(r, p)| r * p).sum();
+
+    Ok(ChannelCombination {
+        asymmetry: PolarizationAsymmetry::new(
+            first.kappa,
+            a,
+            stat_variance.sqrt(),
+            syst_variance.sqrt(),
+            measurements.iter().map(|m| m.n_events).sum(),
+        ),
+        weights,
+        chi2,
+        ndf: n - 1,
+    })
+}
+
+/// Solve `matrix · x = rhs` by Gaussian elimination with partial pivoting.
+fn solve(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<f64>) -> Option<Vec<f64>> {
+    let n = rhs.len();
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))?;
+        if matrix[pivot][column] == 0.0 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        rhs.swap(column, pivot);
+        for row in column + 1..n {
+            let factor = matrix[row][column] / matrix[column][column];
+            let (above, below) = matrix.split_at_mut(row);
+            for (value, pivot_value) in below[0][column..].iter_mut().zip(&above[column][column..])
+            {
+                *value -= factor * pivot_value;
+            }
+            rhs[row] -= factor * rhs[column];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let tail: f64 = (row + 1..n).map(|k| matrix[row][k] * x[k]).sum();
+        x[row] = (rhs[row] - tail) / matrix[row][row];
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uncorrelated_blue_is_inverse_variance_average() {
+        let low = PolarizationAsymmetry::new(0.2, 0.010, 0.003, 0.004, 100);
+        let high = PolarizationAsymmetry::new(0.2, 0.020, 0.006, 0.008, 100);
+        let combination = blue_combine(&[low, high], 0.0).unwrap();
+
+        let (wl, wh) = (low.uncertainty.powi(-2), high.uncertainty.powi(-2));
+        let expected = (wl * low.a + wh * high.a) / (wl + wh);
+        assert!((combination.asymmetry.a - expected).abs() < 1e-15);
+        assert!((combination.asymmetry.uncertainty - (wl + wh).sqrt().recip()).abs() < 1e-15);
+        assert!((combination.weights.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert_eq!((combination.ndf, combination.asymmetry.n_events), (1, 200));
+        assert!(combination.chi2 > 0.0);
+    }
+
+    #[test]
+    fn test_full_correlation_does_not_reduce_systematics() {
+        let channel = |a| PolarizationAsymmetry::new(0.2, a, 1e-4, 0.01, 100);
+        let combination = blue_combine(&[channel(0.010), channel(0.012)], 1.0).unwrap();
+        assert!((combination.asymmetry.syst_error - 0.01).abs() < 1e-6);
+        assert!(blue_combine(&[channel(0.01)], 2.0).is_err());
+        assert!(blue_combine(&[], 0.0).is_err());
+    }
+
+    #[test]
+    fn test_channels_estimate_the_same_asymmetry() {
+        let config = EqgftConfig {
+            kappa: 0.5,
+            seed: Some(4),
+            ..EqgftConfig::default()
+        };
+        let channels = [
+            Channel {
+                name: "strong".into(),
+                sensitivity: 20.0,
+                n_events: 200_000,
+                ..Channel::default()
+            },
+            Channel {
+                name: "weak".into(),
+                sensitivity: -5.0,
+                n_events: 200_000,
+                ..Channel::default()
+            },
+        ];
+        let measurements = simulate_channels(&config, &channels).unwrap();
+        for measurement in &measurements {
+            assert_eq!(measurement.predicted, predicted_asymmetry(0.5));
+            assert!(measurement.pull().abs() < 4.0);
+        }
+        // the higher analyzing power gives the tighter estimate
+        assert!(measurements[0].uncertainty < measurements[1].uncertainty);
+        let combination = blue_combine(&measurements, 0.0).unwrap();
+        assert!(combination.weights[0] > combination.weights[1]);
+
+        let zero = Channel {
+            sensitivity: 0.0,
+            ..Channel::default()
+        };
+        assert!(simulate_channels(&config, &[zero]).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use crate::asymmetry::ALPHA;
+use crate::detector::DetectorModel;
+use crate::parallel::validate_threads;
+use crate::{EqgftError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Most events a sample may hold, 800 MB of `cos θ` values.
+pub const MAX_EVENTS: usize = 100_000_000;
+
+/// Parameters of a simulated polarization-asymmetry measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EqgftConfig {
+    /// Coupling κ in the prediction 𝒜 = κα.
+    pub kappa: f64,
+    /// Sample size, at most [`MAX_EVENTS`].
+    pub n_events: usize,
+    /// Absolute systematic uncertainty on 𝒜, added in quadrature.
+    pub systematic_error: f64,
+    /// Fixed seed for reproducible samples; entropy-seeded when absent.
+    pub seed: Option<u64>,
+    /// Worker threads for event generation, at most
+    /// [`MAX_THREADS`](crate::parallel::MAX_THREADS); all cores when absent.
+    pub threads: Option<usize>,
+    /// Detector response applied before extraction; truth level when absent.
+    pub detector: Option<DetectorModel>,
+}
+
+impl Default for EqgftConfig {
+    fn default() -> Self {
+        Self {
+            kappa: 0.20,
+            n_events: 50_000,
+            systematic_error: 1e-4,
+            seed: None,
+            threads: None,
+            detector: None,
+        }
+    }
+}
+
+impl EqgftConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.n_events == 0 {
+            return Err(EqgftError::InvalidConfig(
+                "n_events must be positive".into(),
+            ));
+        }
+        if self.n_events > MAX_EVENTS {
+            return Err(EqgftError::InvalidConfig(format!(
+                "n_events must be at most {MAX_EVENTS}"
+            )));
+        }
+        validate_threads(self.threads)?;
+        // 𝒜 = κα is a polarization asymmetry, so |κ| ≤ 1/α
+        if !(self.kappa.is_finite() && (self.kappa * ALPHA).abs() <= 1.0) {
+            return Err(EqgftError::InvalidConfig(format!(
+                "kappa must lie within ±1/α ≈ ±{:.1}",
+                1.0 / ALPHA
+            )));
+        }
+        if !(self.systematic_error.is_finite() && self.systematic_error >= 0.0) {
+            return Err(EqgftError::InvalidConfig(
+                "systematic_error must be non-negative".into(),
+            ));
+        }
+        if let Some(detector) = &self.detector {
+            detector.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Load and validate a config from a `.toml` or `.json` file. Missing
+    /// fields take their defaults; unknown fields and values of the wrong
+    /// type, TOML datetimes among them, are rejected.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let context =
+            |message: String| EqgftError::InvalidConfig(format!("{}: {}", path.display(), message));
+        let text = fs::read_to_string(path).map_err(|err| context(err.to_string()))?;
+        let config: Self = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&text).map_err(|err| context(err.to_string()))?,
+            Some("json") => serde_json::from_str(&text).map_err(|err| context(err.to_string()))?,
+            _ => return Err(context("expected a .toml or .json file".into())),
+        };
+        config.validate().map_err(|err| context(err.to_string()))?;
+        Ok(config)
+    }
+
+    /// Load the config called `name` from `directory`, trying `<name>.toml`
+    /// and then `<name>.json`.
+    pub fn from_named(directory: &Path, name: &str) -> Result<Self> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(EqgftError::InvalidConfig(format!(
+                "invalid config name '{name}'"
+            )));
+        }
+        ["toml", "json"]
+            .iter()
+            .map(|ext| directory.join(format!("{name}.{ext}")))
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                EqgftError::InvalidConfig(format!("no config '{name}' in {}", directory.display()))
+            })
+            .and_then(|path| Self::from_file(&path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_named_configs_load_from_toml_and_json() {
+        let dir = env::temp_dir().join(format!("mmss-eqgft-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("strong.toml"),
+            "kappa = 0.5\nn_events = 1000\nseed = 3\n\n[detector]\nangular_resolution = 0.05\n",
+        )
+        .unwrap();
+        fs::write(dir.join("weak.json"), r#"{"kappa": 0.01}"#).unwrap();
+        fs::write(dir.join("empty.toml"), "n_events = 0\n").unwrap();
+        fs::write(dir.join("typo.toml"), "kapa = 0.3\n").unwrap();
+        fs::write(dir.join("dated.toml"), "seed = 1979-05-27\n").unwrap();
+        fs::write(dir.join("nested.toml"), "[detector]\nresolution = 0.1\n").unwrap();
+
+        let strong = EqgftConfig::from_named(&dir, "strong").unwrap();
+        assert_eq!(
+            (strong.kappa, strong.n_events, strong.seed),
+            (0.5, 1000, Some(3))
+        );
+        assert_eq!(strong.detector.unwrap().angular_resolution, 0.05);
+        assert_eq!(EqgftConfig::from_named(&dir, "weak").unwrap().kappa, 0.01);
+        assert!(EqgftConfig::from_named(&dir, "empty").is_err());
+        assert!(EqgftConfig::from_named(&dir, "typo").is_err());
+        assert!(EqgftConfig::from_named(&dir, "dated").is_err());
+        assert!(EqgftConfig::from_named(&dir, "nested").is_err());
+        assert!(EqgftConfig::from_named(&dir, "missing").is_err());
+        assert!(EqgftConfig::from_named(&dir, "../strong").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_kappa_must_give_a_physical_asymmetry() {
+        let config = |kappa| EqgftConfig {
+            kappa,
+            ..EqgftConfig::default()
+        };
+        assert!(config(-100.0).validate().is_ok());
+        assert!(config(200.0).validate().is_err());
+        assert!(config(f64::NAN).validate().is_err());
+    }
+
+    #[test]
+    fn test_sample_size_and_threads_are_bounded() {
+        let events = |n_events| EqgftConfig {
+            n_events,
+            ..EqgftConfig::default()
+        };
+        assert!(events(MAX_EVENTS).validate().is_ok());
+        assert!(events(MAX_EVENTS + 1).validate().is_err());
+        let threads = |threads| EqgftConfig {
+            threads: Some(threads),
+            ..EqgftConfig::default()
+        };
+        assert!(threads(4).validate().is_ok());
+        assert!(threads(0).validate().is_err());
+        assert!(threads(crate::parallel::MAX_THREADS + 1)
+            .validate()
+            .is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use crate::asymmetry::EVENT_CHUNK;
+use crate::{EqgftError, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Detection efficiency `ε(cos θ) = base + linear cos θ + quadratic cos² θ`,
+/// clamped to [0, 1].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EfficiencyCurve {
+    pub base: f64,
+    pub linear: f64,
+    pub quadratic: f64,
+}
+
+impl Default for EfficiencyCurve {
+    fn default() -> Self {
+        Self {
+            base: 1.0,
+            linear: 0.0,
+            quadratic: 0.0,
+        }
+    }
+}
+
+impl EfficiencyCurve {
+    pub fn at(&self, cos_theta: f64) -> f64 {
+        (self.base + self.linear * cos_theta + self.quadratic * cos_theta * cos_theta)
+            .clamp(0.0, 1.0)
+    }
+}
+
+/// Reconstruction-level response of the polarimeter. The default is a
+/// perfect detector.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DetectorModel {
+    /// Gaussian resolution on the polar angle θ, in radians.
+    pub angular_resolution: f64,
+    pub efficiency: EfficiencyCurve,
+    /// Reconstructed `cos θ` outside `[acceptance_min, acceptance_max]` is cut.
+    pub acceptance_min: f64,
+    pub acceptance_max: f64,
+}
+
+impl Default for DetectorModel {
+    fn default() -> Self {
+        Self {
+            angular_resolution: 0.0,
+            efficiency: EfficiencyCurve::default(),
+            acceptance_min: -1.0,
+            acceptance_max: 1.0,
+        }
+    }
+}
+
+impl DetectorModel {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.angular_resolution.is_finite() && self.angular_resolution >= 0.0) {
+            return Err(EqgftError::InvalidConfig(
+                "angular_resolution must be non-negative".into(),
+            ));
+        }
+        if !(-1.0..=1.0).contains(&self.acceptance_min)
+            || !(-1.0..=1.0).contains(&self.acceptance_max)
+            || self.acceptance_min >= self.acceptance_max
+        {
+            return Err(EqgftError::InvalidConfig(
+                "acceptance must be a non-empty sub-range of [-1, 1]".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Smear, cut and thin truth-level `cos θ` values. Like event
+    /// generation, chunk `i` draws from its own stream seeded `seed + i`.
+    pub fn apply(&self, events: &[f64], seed: u64) -> Vec<f64> {
+        events
+            .par_chunks(EVENT_CHUNK)
+            .enumerate()
+            .flat_map_iter(|(chunk, truth)| {
+                let mut rng = StdRng::seed_from_u64(seed.wrapping_add(chunk as u64));
+                truth
+                    .iter()
+                    .filter_map(|&cos_theta| self.detect(cos_theta, &mut rng))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn detect(&self, cos_theta: f64, rng: &mut StdRng) -> Option<f64> {
+        let mut reconstructed = cos_theta;
+        if self.angular_resolution > 0.0 {
+            // reflect the smeared angle back into [0, π]
+            let theta =
+                (cos_theta.acos() + self.angular_resolution * gaussian(rng)).rem_euclid(2.0 * PI);
+            reconstructed = if theta > PI { 2.0 * PI - theta } else { theta }.cos();
+        }
+        if !(self.acceptance_min..=self.acceptance_max).contains(&reconstructed) {
+            return None;
+        }
+        (rng.gen::<f64>() < self.efficiency.at(reconstructed)).then_some(reconstructed)
+    }
+}
+
+/// Standard normal variate by Box–Muller.
+fn gaussian(rng: &mut StdRng) -> f64 {
+    let u = 1.0 - rng.gen::<f64>();
+    let v = rng.gen::<f64>();
+    (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asymmetry::{
+        calculate_polarization_asymmetry, generate_events, measure_ratio_asymmetry,
+    };
+    use crate::config::EqgftConfig;
+
+    #[test]
+    fn test_perfect_detector_keeps_every_event() {
+        let events = generate_events(0.2, 10_000, 3);
+        assert_eq!(DetectorModel::default().apply(&events, 1), events);
+    }
+
+    #[test]
+    fn test_symmetric_cuts_lose_events_not_the_asymmetry() {
+        let detector = DetectorModel {
+            efficiency: EfficiencyCurve {
+                base: 0.8,
+                linear: 0.0,
+                quadratic: -0.3,
+            },
+            acceptance_min: -0.7,
+            acceptance_max: 0.7,
+            ..DetectorModel::default()
+        };
+        let truth = generate_events(0.5, 200_000, 6);
+        let detected = detector.apply(&truth, 2);
+        assert!(detected.len() < truth.len() * 6 / 10);
+        assert!(detected.iter().all(|c| c.abs() <= 0.7));
+
+        let (a, error) = measure_ratio_asymmetry(&detected).unwrap();
+        assert!((a - 0.5).abs() < 4.0 * error);
+    }
+
+    #[test]
+    fn test_resolution_dilutes_measured_asymmetry() {
+        let config = EqgftConfig {
+            kappa: 100.0,
+            n_events: 200_000,
+            seed: Some(4),
+            systematic_error: 0.0,
+            ..EqgftConfig::default()
+        };
+        let truth = calculate_polarization_asymmetry(&config).unwrap();
+        let smeared = calculate_polarization_asymmetry(&EqgftConfig {
+            detector: Some(DetectorModel {
+                angular_resolution: 1.0,
+                ..DetectorModel::default()
+            }),
+            ..config
+        })
+        .unwrap();
+        assert!(smeared.a < truth.a - 5.0 * truth.uncertainty);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::hopfion::{lattice_gradient_fourth_order, lattice_point, HopfionSolitonField};
+use crate::parallel::run_with_threads;
+use rayon::prelude::*;
+
+/// `[∂x Q, ∂y Q, ∂z Q]` at every lattice point, in the order of `q_x`.
+///
+/// Fourth-order differences two points away from the boundary, second-order
+/// central differences next to it and one-sided ones on it.
+pub fn gradient_field(field: &HopfionSolitonField) -> Vec<[[f64; 4]; 3]> {
+    let compute = || lattice_gradients(&field.q_x, field.resolution(), field.config.spacing());
+    // an unbuildable pool only loses the parallelism, not the result
+    run_with_threads(field.config.threads, compute).unwrap_or_else(|_| compute())
+}
+
+/// Sigma-model energy density `½ Σ_i |∂_i Q|²` per lattice point.
+pub fn energy_density(field: &HopfionSolitonField) -> Vec<f64> {
+    let compute = || lattice_energy_density(&field.q_x, field.resolution(), field.config.spacing());
+    run_with_threads(field.config.threads, compute).unwrap_or_else(|_| compute())
+}
+
+/// `∫ ½ Σ_i |∂_i Q|² d³x` as a lattice sum.
+pub fn total_energy(field: &HopfionSolitonField) -> f64 {
+    integrate(&energy_density(field), field.config.spacing())
+}
+
+pub(crate) fn integrate(density: &[f64], spacing: f64) -> f64 {
+    density.iter().sum::<f64>() * spacing.powi(3)
+}
+
+fn lattice_gradients(q_x: &[[f64; 4]], n: usize, spacing: f64) -> Vec<[[f64; 4]; 3]> {
+    (0..q_x.len())
+        .into_par_iter()
+        .map(|index| lattice_gradient_fourth_order(q_x, n, spacing, lattice_point(n, index)))
+        .collect()
+}
+
+pub(crate) fn lattice_energy_density(q_x: &[[f64; 4]], n: usize, spacing: f64) -> Vec<f64> {
+    (0..q_x.len())
+        .into_par_iter()
+        .map(|index| {
+            let gradient = lattice_gradient_fourth_order(q_x, n, spacing, lattice_point(n, index));
+            0.5 * gradient.iter().flatten().map(|d| d * d).sum::<f64>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hopfion::{generate_hopfion_soliton_field, HopfionConfig};
+
+    #[test]
+    fn test_energy_matches_generated_field() {
+        let field = generate_hopfion_soliton_field(&HopfionConfig {
+            resolution: 15,
+            ..HopfionConfig::default()
+        })
+        .unwrap();
+        assert_eq!(energy_density(&field), field.energy_density);
+        assert!((total_energy(&field) - field.total_energy).abs() < 1e-9 * field.total_energy);
+    }
+
+    #[test]
+    fn test_gradient_of_smooth_field_converges() {
+        let gradient_at_origin = |resolution: usize| {
+            let field = generate_hopfion_soliton_field(&HopfionConfig {
+                resolution,
+                extent: 3.0,
+                ..HopfionConfig::default()
+            })
+            .unwrap();
+            let centre = resolution / 2;
+            gradient_field(&field)[field.index(centre, centre, centre)]
+        };
+        let coarse = gradient_at_origin(31);
+        let fine = gradient_at_origin(61);
+        for (a, b) in coarse.iter().flatten().zip(fine.iter().flatten()) {
+            assert!((a - b).abs() < 2e-2, "{a} vs {b}");
+        }
+        // near the origin Q ≈ (-1, 2x, 2y, 2z) / R, so |∂x Q| = 2 for R = 1
+        let norm = fine[0].iter().map(|c| c * c).sum::<f64>().sqrt();
+        assert!((norm - 2.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,271 @@
+//! Hopfion lattices on the GPU through wgpu compute shaders. Needs the
+//! `gpu` feature.
+//!
+//! The kernels in `hopfion.wgsl` mirror the CPU code point for point but
+//! work in f32, so fields agree with `generate_hopfion_soliton_field` to
+//! about 1e-6 and charges to about 1e-4; per-point values are summed in f64
+//! on the host. One device is opened per process, on first use.
+
+use crate::backend::FieldBackend;
+use crate::energy::integrate;
+use crate::hopfion::{HopfionConfig, HopfionSolitonField};
+use crate::{EqgftError, Result};
+use std::f64::consts::PI;
+use std::sync::{mpsc, Arc, OnceLock};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, Buffer, BufferDescriptor, BufferUsages,
+    CommandEncoderDescriptor, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor,
+    Device, Maintain, MapMode, Queue,
+};
+
+const SHADER: &str = include_str!("hopfion.wgsl");
+const WORKGROUP_SIZE: u32 = 64;
+/// Most workgroups per dispatch dimension.
+const MAX_GROUPS: u32 = 65_535;
+
+pub struct GpuBackend {
+    device: Device,
+    queue: Queue,
+    generate: ComputePipeline,
+    energy: ComputePipeline,
+    charge: ComputePipeline,
+}
+
+impl GpuBackend {
+    /// The process-wide backend, or `None` when wgpu finds no adapter.
+    pub fn shared() -> Option<Arc<GpuBackend>> {
+        static SHARED: OnceLock<Option<Arc<GpuBackend>>> = OnceLock::new();
+        SHARED
+            .get_or_init(|| pollster::block_on(Self::open()).map(Arc::new))
+            .clone()
+    }
+
+    async fn open() -> Option<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                ..Default::default()
+            })
+            .await?;
+        // a 128³ lattice is 32 MiB of quaternions, within the default limits
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor::default(), None)
+            .await
+            .ok()?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("hopfion"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: None,
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        Some(Self {
+            generate: pipeline("generate"),
+            energy: pipeline("energy"),
+            charge: pipeline("charge"),
+            device,
+            queue,
+        })
+    }
+
+    fn params(&self, config: &HopfionConfig) -> Buffer {
+        let params: [u32; 8] = [
+            config.resolution as u32,
+            config.hopf_index as u32,
+            (config.extent as f32).to_bits(),
+            (config.scale as f32).to_bits(),
+            (config.spacing() as f32).to_bits(),
+            0,
+            0,
+            0,
+        ];
+        self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("params"),
+            contents: bytemuck::cast_slice(&params),
+            usage: BufferUsages::UNIFORM,
+        })
+    }
+
+    fn storage(&self, label: &str, bytes: usize) -> Buffer {
+        self.device.create_buffer(&BufferDescriptor {
+            label: Some(label),
+            size: bytes as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Run `pipeline` once per lattice point, with `buffers` bound in
+    /// binding order.
+    fn dispatch(&self, pipeline: &ComputePipeline, buffers: &[&Buffer], points: usize) {
+        let entries: Vec<BindGroupEntry> = buffers
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer)| BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        });
+        let groups = (points as u32).div_ceil(WORKGROUP_SIZE);
+        let width = groups.min(MAX_GROUPS);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor::default());
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(width, groups.div_ceil(width), 1);
+        }
+        self.queue.submit([encoder.finish()]);
+    }
+
+    /// The contents of `buffer` as f32s.
+    fn read(&self, buffer: &Buffer) -> Vec<f32> {
+        let staging = self.device.create_buffer(&BufferDescriptor {
+            label: Some("readback"),
+            size: buffer.size(),
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor::default());
+        encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, buffer.size());
+        self.queue.submit([encoder.finish()]);
+
+        let slice = staging.slice(..);
+        let (mapped, wait) = mpsc::channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = mapped.send(result);
+        });
+        self.device.poll(Maintain::Wait);
+        wait.recv()
+            .expect("the map callback runs during the poll")
+            .expect("mapping a readback buffer");
+        let values = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        staging.unmap();
+        values
+    }
+}
+
+impl FieldBackend for GpuBackend {
+    fn name(&self) -> &'static str {
+        "gpu"
+    }
+
+    fn generate(&self, config: &HopfionConfig) -> Result<HopfionSolitonField> {
+        config.validate()?;
+        let n = config.resolution;
+        let points = n.pow(3);
+        let spacing = config.spacing();
+        let params = self.params(config);
+        let field = self.storage("field", points * 16);
+        let density = self.storage("energy density", points * 4);
+        self.dispatch(&self.generate, &[&params, &field], points);
+        self.dispatch(&self.energy, &[&params, &field, &density], points);
+
+        let q_x: Vec<[f64; 4]> = self
+            .read(&field)
+            .chunks_exact(4)
+            .map(|q| [q[0], q[1], q[2], q[3]].map(f64::from))
+            .collect();
+        let energy_density: Vec<f64> = self.read(&density).into_iter().map(f64::from).collect();
+        if q_x.iter().flatten().any(|c| !c.is_finite()) {
+            return Err(EqgftError::InvalidConfig(
+                "the GPU produced a non-finite field".into(),
+            ));
+        }
+        let total_energy = integrate(&energy_density, spacing);
+        Ok(HopfionSolitonField {
+            config: *config,
+            axis: (0..n)
+                .map(|i| -config.extent + i as f64 * spacing)
+                .collect(),
+            q_x,
+            energy_density,
+            total_energy,
+        })
+    }
+
+    fn hopf_charge(&self, field: &HopfionSolitonField) -> f64 {
+        let points = field.q_x.len();
+        let q_x: Vec<f32> = field.q_x.iter().flatten().map(|&c| c as f32).collect();
+        let params = self.params(&field.config);
+        let lattice = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("field"),
+            contents: bytemuck::cast_slice(&q_x),
+            usage: BufferUsages::STORAGE,
+        });
+        let determinants = self.storage("determinants", points * 4);
+        self.dispatch(&self.charge, &[&params, &lattice, &determinants], points);
+        let sum: f64 = self.read(&determinants).into_iter().map(f64::from).sum();
+        // the same orientation and normalization as `compute_hopf_charge`
+        -sum * field.config.spacing().powi(3) / (2.0 * PI * PI)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hopfion::generate_hopfion_soliton_field;
+    use crate::topology::compute_hopf_charge;
+
+    #[test]
+    fn test_shader_is_valid_wgsl() {
+        let module = naga::front::wgsl::parse_str(SHADER).unwrap();
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .unwrap();
+        for entry_point in ["generate", "energy", "charge"] {
+            assert!(module.entry_points.iter().any(|e| e.name == entry_point));
+        }
+    }
+
+    #[test]
+    fn test_matches_the_cpu_lattice() {
+        let Some(gpu) = GpuBackend::shared() else {
+            eprintln!("no GPU adapter; skipping");
+            return;
+        };
+        for hopf_index in [0, 1, 2] {
+            let config = HopfionConfig {
+                resolution: 33,
+                extent: 6.0,
+                hopf_index,
+                ..HopfionConfig::default()
+            };
+            let cpu = generate_hopfion_soliton_field(&config).unwrap();
+            let field = gpu.generate(&config).unwrap();
+            assert_eq!(field.axis, cpu.axis);
+            for (a, b) in field.q_x.iter().flatten().zip(cpu.q_x.iter().flatten()) {
+                assert!((a - b).abs() < 1e-5, "{hopf_index}: {a} vs {b}");
+            }
+            let tolerance = 1e-4 * cpu.total_energy.max(1.0);
+            assert!((field.total_energy - cpu.total_energy).abs() < tolerance);
+            let charge = compute_hopf_charge(&cpu);
+            assert!(
+                (gpu.hopf_charge(&cpu) - charge).abs() < 1e-3,
+                "{hopf_index}"
+            );
+        }
+    }
+}
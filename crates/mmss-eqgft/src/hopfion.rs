@@ -0,0 +1,315 @@
+use crate::energy::{integrate, lattice_energy_density};
+use crate::parallel::{run_with_threads, validate_threads};
+use crate::{EqgftError, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Most lattice points per axis; a lattice holds `resolution³` quaternions,
+/// 64 MiB at this size.
+pub const MAX_RESOLUTION: usize = 128;
+
+/// Lattice and soliton parameters for `generate_hopfion_soliton_field`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HopfionConfig {
+    /// Lattice points per axis, at most [`MAX_RESOLUTION`].
+    pub resolution: usize,
+    /// The lattice spans `[-extent, extent]` on every axis.
+    pub extent: f64,
+    /// Hopf index N_H of the ansatz; 0 gives the vacuum.
+    pub hopf_index: i32,
+    /// Soliton size R.
+    pub scale: f64,
+    /// Worker threads for lattice loops, at most
+    /// [`MAX_THREADS`](crate::parallel::MAX_THREADS); all cores when absent.
+    pub threads: Option<usize>,
+}
+
+impl Default for HopfionConfig {
+    fn default() -> Self {
+        Self {
+            resolution: 20,
+            extent: 5.0,
+            hopf_index: 1,
+            scale: 1.0,
+            threads: None,
+        }
+    }
+}
+
+impl HopfionConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.resolution < 2 {
+            return Err(EqgftError::InvalidConfig(
+                "resolution must be at least 2".into(),
+            ));
+        }
+        if self.resolution > MAX_RESOLUTION {
+            return Err(EqgftError::InvalidConfig(format!(
+                "resolution must be at most {MAX_RESOLUTION}"
+            )));
+        }
+        validate_threads(self.threads)?;
+        if !(self.extent.is_finite() && self.extent > 0.0) {
+            return Err(EqgftError::InvalidConfig("extent must be positive".into()));
+        }
+        if !(self.scale.is_finite() && self.scale > 0.0) {
+            return Err(EqgftError::InvalidConfig("scale must be positive".into()));
+        }
+        Ok(())
+    }
+
+    /// Distance between neighbouring lattice points.
+    pub fn spacing(&self) -> f64 {
+        2.0 * self.extent / (self.resolution - 1) as f64
+    }
+}
+
+/// Unit quaternion field `Q(x)` sampled on a cubic lattice.
+///
+/// `q_x` holds `[q0, q1, q2, q3]` per point in x-major order, see `index`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HopfionSolitonField {
+    pub config: HopfionConfig,
+    /// Coordinates shared by all three axes.
+    pub axis: Vec<f64>,
+    pub q_x: Vec<[f64; 4]>,
+    /// Sigma-model energy density ½ Σ_i |∂_i Q|² per lattice point; see
+    /// `energy::energy_density`.
+    pub energy_density: Vec<f64>,
+    pub total_energy: f64,
+}
+
+impl HopfionSolitonField {
+    pub fn resolution(&self) -> usize {
+        self.config.resolution
+    }
+
+    pub fn index(&self, i: usize, j: usize, k: usize) -> usize {
+        lattice_index(self.config.resolution, i, j, k)
+    }
+
+    pub fn position(&self, i: usize, j: usize, k: usize) -> [f64; 3] {
+        [self.axis[i], self.axis[j], self.axis[k]]
+    }
+
+    pub fn at(&self, i: usize, j: usize, k: usize) -> [f64; 4] {
+        self.q_x[self.index(i, j, k)]
+    }
+}
+
+/// Build the Hopfion of index `hopf_index` from the standard Hopf map ansatz.
+///
+/// Each point is sent to S³ by inverse stereographic projection with radius
+/// `scale`, split into the complex pair `(Z1, Z2)`, and mapped to
+/// `(Z1^N_H, Z2) / |(Z1^N_H, Z2)|`, whose degree is N_H. For N_H = 1 this is
+/// the `hopfion_initial_guess` of the Python prototype: `Q → 1` at infinity
+/// and `Q = -1` at the origin.
+pub fn generate_hopfion_soliton_field(config: &HopfionConfig) -> Result<HopfionSolitonField> {
+    config.validate()?;
+    let n = config.resolution;
+    let spacing = config.spacing();
+    let axis: Vec<f64> = (0..n)
+        .map(|i| -config.extent + i as f64 * spacing)
+        .collect();
+
+    let (q_x, energy_density) = run_with_threads(config.threads, || {
+        let q_x: Vec<[f64; 4]> = (0..n * n * n)
+            .into_par_iter()
+            .map(|index| {
+                let [i, j, k] = lattice_point(n, index);
+                hopf_ansatz([axis[i], axis[j], axis[k]], config.scale, config.hopf_index)
+            })
+            .collect();
+        let energy_density = lattice_energy_density(&q_x, n, spacing);
+        (q_x, energy_density)
+    })?;
+    let total_energy = integrate(&energy_density, spacing);
+
+    Ok(HopfionSolitonField {
+        config: *config,
+        axis,
+        q_x,
+        energy_density,
+        total_energy,
+    })
+}
+
+fn hopf_ansatz([x, y, z]: [f64; 3], scale: f64, hopf_index: i32) -> [f64; 4] {
+    if hopf_index == 0 {
+        return [1.0, 0.0, 0.0, 0.0];
+    }
+    let rho2 = (x * x + y * y + z * z) / (scale * scale);
+    let denominator = rho2 + 1.0;
+    let u = [
+        2.0 * x / scale / denominator,
+        2.0 * y / scale / denominator,
+        2.0 * z / scale / denominator,
+        (rho2 - 1.0) / denominator,
+    ];
+
+    // Z1 = u0 + i u1 raised to N_H in polar form; Z2 = u3 + i u2 is left alone.
+    let modulus = (u[0] * u[0] + u[1] * u[1]).sqrt();
+    let phase = u[1].atan2(u[0]);
+    let raised = modulus.powi(hopf_index.abs());
+    let angle = phase * hopf_index as f64;
+    let q = [u[3], raised * angle.cos(), raised * angle.sin(), u[2]];
+
+    let norm = q.iter().map(|c| c * c).sum::<f64>().sqrt();
+    q.map(|c| c / norm)
+}
+
+fn lattice_index(n: usize, i: usize, j: usize, k: usize) -> usize {
+    (i * n + j) * n + k
+}
+
+pub(crate) fn lattice_point(n: usize, index: usize) -> [usize; 3] {
+    [index / (n * n), (index / n) % n, index % n]
+}
+
+/// `[∂x Q, ∂y Q, ∂z Q]` at a lattice point: central differences inside,
+/// one-sided on the boundary.
+pub(crate) fn lattice_gradient(
+    q_x: &[[f64; 4]],
+    n: usize,
+    spacing: f64,
+    point: [usize; 3],
+) -> [[f64; 4]; 3] {
+    let mut gradient = [[0.0; 4]; 3];
+    for (axis, derivative) in gradient.iter_mut().enumerate() {
+        let (lo, hi, step) = match point[axis] {
+            0 => (0, 1, spacing),
+            index if index == n - 1 => (n - 2, n - 1, spacing),
+            index => (index - 1, index + 1, 2.0 * spacing),
+        };
+        let mut lo_point = point;
+        let mut hi_point = point;
+        lo_point[axis] = lo;
+        hi_point[axis] = hi;
+        let a = q_x[lattice_index(n, lo_point[0], lo_point[1], lo_point[2])];
+        let b = q_x[lattice_index(n, hi_point[0], hi_point[1], hi_point[2])];
+        for c in 0..4 {
+            derivative[c] = (b[c] - a[c]) / step;
+        }
+    }
+    gradient
+}
+
+/// Fourth-order accurate variant of `lattice_gradient` two points away from
+/// the boundary; falls back to it elsewhere.
+pub(crate) fn lattice_gradient_fourth_order(
+    q_x: &[[f64; 4]],
+    n: usize,
+    spacing: f64,
+    point: [usize; 3],
+) -> [[f64; 4]; 3] {
+    if point.iter().any(|&index| index < 2 || index + 2 >= n) {
+        return lattice_gradient(q_x, n, spacing, point);
+    }
+    let mut gradient = [[0.0; 4]; 3];
+    for (axis, derivative) in gradient.iter_mut().enumerate() {
+        let at = |offset: isize| {
+            let mut shifted = point;
+            shifted[axis] = (point[axis] as isize + offset) as usize;
+            q_x[lattice_index(n, shifted[0], shifted[1], shifted[2])]
+        };
+        let (m2, m1, p1, p2) = (at(-2), at(-1), at(1), at(2));
+        for c in 0..4 {
+            derivative[c] = (m2[c] - 8.0 * m1[c] + 8.0 * p1[c] - p2[c]) / (12.0 * spacing);
+        }
+    }
+    gradient
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_is_unit_and_fills_lattice() {
+        let config = HopfionConfig {
+            resolution: 9,
+            ..HopfionConfig::default()
+        };
+        let field = generate_hopfion_soliton_field(&config).unwrap();
+
+        assert_eq!(field.q_x.len(), 729);
+        assert_eq!(field.energy_density.len(), 729);
+        assert_eq!(field.axis[0], -5.0);
+        assert_eq!(field.axis[8], 5.0);
+        for q in &field.q_x {
+            let norm: f64 = q.iter().map(|c| c * c).sum();
+            assert!((norm - 1.0).abs() < 1e-12);
+        }
+        // the centre is the anti-vacuum, the corners approach the vacuum
+        assert!((field.at(4, 4, 4)[0] + 1.0).abs() < 1e-12);
+        assert!(field.at(0, 0, 0)[0] > 0.8);
+    }
+
+    #[test]
+    fn test_energy_concentrates_at_the_soliton() {
+        let config = HopfionConfig {
+            resolution: 21,
+            ..HopfionConfig::default()
+        };
+        let field = generate_hopfion_soliton_field(&config).unwrap();
+
+        let centre = field.energy_density[field.index(10, 10, 10)];
+        let corner = field.energy_density[field.index(0, 0, 0)];
+        assert!(centre > 100.0 * corner);
+        assert!(field.total_energy > 0.0);
+
+        let vacuum = generate_hopfion_soliton_field(&HopfionConfig {
+            hopf_index: 0,
+            ..config
+        })
+        .unwrap();
+        assert_eq!(vacuum.total_energy, 0.0);
+    }
+
+    #[test]
+    fn test_higher_index_costs_more_energy() {
+        let base = HopfionConfig {
+            resolution: 21,
+            ..HopfionConfig::default()
+        };
+        let single = generate_hopfion_soliton_field(&base).unwrap();
+        let double = generate_hopfion_soliton_field(&HopfionConfig {
+            hopf_index: 2,
+            ..base
+        })
+        .unwrap();
+        assert!(double.total_energy > single.total_energy);
+    }
+
+    #[test]
+    fn test_rejects_degenerate_lattice() {
+        let config = HopfionConfig {
+            resolution: 1,
+            ..HopfionConfig::default()
+        };
+        assert!(generate_hopfion_soliton_field(&config).is_err());
+    }
+
+    #[test]
+    fn test_rejects_oversized_lattice_and_pool() {
+        let oversized = HopfionConfig {
+            resolution: MAX_RESOLUTION + 1,
+            ..HopfionConfig::default()
+        };
+        assert!(oversized.validate().is_err());
+        for threads in [0, crate::parallel::MAX_THREADS + 1] {
+            let config = HopfionConfig {
+                threads: Some(threads),
+                ..HopfionConfig::default()
+            };
+            assert!(config.validate().is_err(), "{threads}");
+        }
+        let largest = HopfionConfig {
+            resolution: MAX_RESOLUTION,
+            threads: Some(crate::parallel::MAX_THREADS),
+            ..HopfionConfig::default()
+        };
+        assert!(largest.validate().is_ok());
+    }
+}
@@ -0,0 +1,117 @@
+// Lattice kernels of `gpu::GpuBackend`, in f32; see `hopfion.rs`,
+// `energy.rs` and `topology.rs` for the f64 code they mirror.
+
+struct Params {
+    n: u32,
+    hopf_index: i32,
+    extent: f32,
+    scale: f32,
+    spacing: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> field: array<vec4<f32>>;
+@group(0) @binding(2) var<storage, read_write> density: array<f32>;
+
+const WORKGROUP_SIZE: u32 = 64u;
+
+// Dispatches wider than 65535 workgroups continue on the next row.
+fn point_index(id: vec3<u32>, groups: vec3<u32>) -> u32 {
+    return id.y * groups.x * WORKGROUP_SIZE + id.x;
+}
+
+fn lattice_point(index: u32) -> vec3<u32> {
+    let n = params.n;
+    return vec3<u32>(index / (n * n), (index / n) % n, index % n);
+}
+
+fn at(point: vec3<u32>) -> vec4<f32> {
+    let n = params.n;
+    return field[(point.x * n + point.y) * n + point.z];
+}
+
+fn shifted(point: vec3<u32>, axis: u32, to: u32) -> vec3<u32> {
+    var moved = point;
+    moved[axis] = to;
+    return moved;
+}
+
+// ∂Q along `axis`: fourth-order differences two points away from the
+// boundary, else central differences inside and one-sided ones on it.
+fn derivative(point: vec3<u32>, axis: u32) -> vec4<f32> {
+    let n = params.n;
+    let h = params.spacing;
+    let i = point[axis];
+    if all(point >= vec3<u32>(2u)) && all(point + vec3<u32>(2u) < vec3<u32>(n)) {
+        return (at(shifted(point, axis, i - 2u)) - 8.0 * at(shifted(point, axis, i - 1u))
+            + 8.0 * at(shifted(point, axis, i + 1u)) - at(shifted(point, axis, i + 2u)))
+            / (12.0 * h);
+    }
+    if i == 0u {
+        return (at(shifted(point, axis, 1u)) - at(point)) / h;
+    }
+    if i == n - 1u {
+        return (at(point) - at(shifted(point, axis, n - 2u))) / h;
+    }
+    return (at(shifted(point, axis, i + 1u)) - at(shifted(point, axis, i - 1u))) / (2.0 * h);
+}
+
+fn hopf_ansatz(x: vec3<f32>) -> vec4<f32> {
+    if params.hopf_index == 0 {
+        return vec4<f32>(1.0, 0.0, 0.0, 0.0);
+    }
+    let scale = params.scale;
+    let rho2 = dot(x, x) / (scale * scale);
+    let denominator = rho2 + 1.0;
+    let u = vec4<f32>(2.0 * x / scale / denominator, (rho2 - 1.0) / denominator);
+
+    let modulus = length(u.xy);
+    // atan2(0, 0) is not defined here, and the phase does not matter there
+    let phase = select(atan2(u.y, u.x), 0.0, modulus == 0.0);
+    var raised = 1.0;
+    for (var power = 0; power < abs(params.hopf_index); power++) {
+        raised *= modulus;
+    }
+    let angle = phase * f32(params.hopf_index);
+    let q = vec4<f32>(u.w, raised * cos(angle), raised * sin(angle), u.z);
+    return q / length(q);
+}
+
+@compute @workgroup_size(64)
+fn generate(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = point_index(id, groups);
+    if index >= arrayLength(&field) {
+        return;
+    }
+    let point = vec3<f32>(lattice_point(index));
+    field[index] = hopf_ansatz(-params.extent + point * params.spacing);
+}
+
+@compute @workgroup_size(64)
+fn energy(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = point_index(id, groups);
+    if index >= arrayLength(&density) {
+        return;
+    }
+    let point = lattice_point(index);
+    let dx = derivative(point, 0u);
+    let dy = derivative(point, 1u);
+    let dz = derivative(point, 2u);
+    density[index] = 0.5 * (dot(dx, dx) + dot(dy, dy) + dot(dz, dz));
+}
+
+// det[Q, ∂x Q, ∂y Q, ∂z Q], the integrand of the Hopf charge.
+@compute @workgroup_size(64)
+fn charge(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = point_index(id, groups);
+    if index >= arrayLength(&density) {
+        return;
+    }
+    let point = lattice_point(index);
+    density[index] = determinant(mat4x4<f32>(
+        field[index],
+        derivative(point, 0u),
+        derivative(point, 1u),
+        derivative(point, 2u),
+    ));
+}
@@ -0,0 +1,324 @@
+//! Off-lattice queries of Hopfion fields: point sampling, line and plane
+//! slices, and streamlines of the quaternion's vector part.
+
+use crate::hopfion::HopfionSolitonField;
+use crate::{EqgftError, Result};
+use serde::{Deserialize, Serialize};
+
+/// Interpolation scheme between lattice points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interpolation {
+    /// Weighted average of the 8 surrounding points.
+    #[default]
+    Trilinear,
+    /// Catmull-Rom cubic convolution over the surrounding 4×4×4 points,
+    /// exact for quadratics away from the lattice boundary.
+    Tricubic,
+}
+
+/// Field values at an arbitrary point inside the lattice.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FieldSample {
+    pub position: [f64; 3],
+    /// Interpolated quaternion, renormalized to unit length.
+    pub q: [f64; 4],
+    pub energy_density: f64,
+}
+
+/// Integration settings for `HopfionSolitonField::streamline`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamlineOptions {
+    /// Arc length per RK4 step.
+    pub step: f64,
+    pub max_steps: usize,
+    /// The line stops where `|(q1, q2, q3)|` drops below this.
+    pub min_magnitude: f64,
+    pub interpolation: Interpolation,
+}
+
+impl Default for StreamlineOptions {
+    fn default() -> Self {
+        Self {
+            step: 0.05,
+            max_steps: 1_000,
+            min_magnitude: 1e-6,
+            interpolation: Interpolation::Trilinear,
+        }
+    }
+}
+
+impl HopfionSolitonField {
+    /// The field at `point`, or `None` outside the lattice.
+    pub fn sample(&self, point: [f64; 3], interpolation: Interpolation) -> Option<FieldSample> {
+        let stencil = self.stencil(point, interpolation)?;
+        let mut q = [0.0; 4];
+        let mut energy_density = 0.0;
+        for &(index, weight) in &stencil {
+            for (sum, value) in q.iter_mut().zip(self.q_x[index]) {
+                *sum += weight * value;
+            }
+            energy_density += weight * self.energy_density[index];
+        }
+        let norm = q.iter().map(|c| c * c).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            q = q.map(|c| c / norm);
+        }
+        Some(FieldSample {
+            position: point,
+            q,
+            energy_density,
+        })
+    }
+
+    /// `samples` evenly spaced points from `start` to `end` inclusive.
+    pub fn sample_line(
+        &self,
+        start: [f64; 3],
+        end: [f64; 3],
+        samples: usize,
+        interpolation: Interpolation,
+    ) -> Result<Vec<Option<FieldSample>>> {
+        let steps = segments(samples)?;
+        Ok((0..samples)
+            .map(|i| {
+                let t = i as f64 / steps;
+                self.sample(offset(start, end, t, [0.0; 3], 0.0), interpolation)
+            })
+            .collect())
+    }
+
+    /// A `width × height` grid spanning `origin + s u + t v` for `s, t` in
+    /// [0, 1], row-major with `u` varying fastest.
+    pub fn sample_plane(
+        &self,
+        origin: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        [width, height]: [usize; 2],
+        interpolation: Interpolation,
+    ) -> Result<Vec<Option<FieldSample>>> {
+        let (columns, rows) = (segments(width)?, segments(height)?);
+        let ends = [0, 1, 2].map(|axis| origin[axis] + u[axis]);
+        Ok((0..height)
+            .flat_map(|row| (0..width).map(move |column| (row, column)))
+            .map(|(row, column)| {
+                let point = offset(origin, ends, column as f64 / columns, v, row as f64 / rows);
+                self.sample(point, interpolation)
+            })
+            .collect())
+    }
+
+    /// Trace the vector part `(q1, q2, q3)` of the field from `seed` with
+    /// unit speed, until the line leaves the lattice, the vector part
+    /// vanishes, or `max_steps` is reached. The seed is the first point.
+    pub fn streamline(&self, seed: [f64; 3], options: &StreamlineOptions) -> Result<Vec<[f64; 3]>> {
+        if !(options.step.is_finite() && options.step != 0.0) {
+            return Err(EqgftError::InvalidConfig(
+                "streamline step must be finite and non-zero".into(),
+            ));
+        }
+        let direction = |point: [f64; 3]| {
+            let [_, x, y, z] = self.sample(point, options.interpolation)?.q;
+            let norm = (x * x + y * y + z * z).sqrt();
+            (norm >= options.min_magnitude).then(|| [x / norm, y / norm, z / norm])
+        };
+        let advance = |point: [f64; 3], slope: [f64; 3], h: f64| {
+            [0, 1, 2].map(|axis| point[axis] + h * slope[axis])
+        };
+
+        let mut line = Vec::new();
+        if self.stencil(seed, options.interpolation).is_none() {
+            return Ok(line);
+        }
+        line.push(seed);
+        let h = options.step;
+        let mut point = seed;
+        for _ in 0..options.max_steps {
+            let next = (|| {
+                let k1 = direction(point)?;
+                let k2 = direction(advance(point, k1, h / 2.0))?;
+                let k3 = direction(advance(point, k2, h / 2.0))?;
+                let k4 = direction(advance(point, k3, h))?;
+                let slope = [0, 1, 2]
+                    .map(|axis| (k1[axis] + 2.0 * k2[axis] + 2.0 * k3[axis] + k4[axis]) / 6.0);
+                let next = advance(point, slope, h);
+                self.stencil(next, options.interpolation).map(|_| next)
+            })();
+            match next {
+                Some(next) => {
+                    line.push(next);
+                    point = next;
+                }
+                None => break,
+            }
+        }
+        Ok(line)
+    }
+
+    /// Lattice indices and weights that interpolate at `point`.
+    fn stencil(&self, point: [f64; 3], interpolation: Interpolation) -> Option<Vec<(usize, f64)>> {
+        let n = self.resolution();
+        let spacing = self.config.spacing();
+        let mut axes = Vec::with_capacity(3);
+        for coordinate in point {
+            // fractional lattice coordinate, with the far edge folded into the last cell
+            let u = (coordinate - self.axis[0]) / spacing;
+            if !(0.0..=(n - 1) as f64).contains(&u) {
+                return None;
+            }
+            let cell = (u.floor() as usize).min(n - 2);
+            axes.push(axis_weights(cell, u - cell as f64, n, interpolation));
+        }
+
+        let mut stencil = Vec::with_capacity(axes[0].len().pow(3));
+        for &(i, wi) in &axes[0] {
+            for &(j, wj) in &axes[1] {
+                for &(k, wk) in &axes[2] {
+                    stencil.push((self.index(i, j, k), wi * wj * wk));
+                }
+            }
+        }
+        Some(stencil)
+    }
+}
+
+/// One-dimensional weights for offset `t` in [0, 1] within `cell`.
+fn axis_weights(cell: usize, t: f64, n: usize, interpolation: Interpolation) -> Vec<(usize, f64)> {
+    match interpolation {
+        Interpolation::Trilinear => vec![(cell, 1.0 - t), (cell + 1, t)],
+        Interpolation::Tricubic => {
+            let t2 = t * t;
+            let t3 = t2 * t;
+            let weights = [
+                (-t3 + 2.0 * t2 - t) / 2.0,
+                (3.0 * t3 - 5.0 * t2 + 2.0) / 2.0,
+                (-3.0 * t3 + 4.0 * t2 + t) / 2.0,
+                (t3 - t2) / 2.0,
+            ];
+            // neighbours past the boundary repeat the edge point
+            weights
+                .iter()
+                .enumerate()
+                .map(|(offset, &weight)| {
+                    let index = (cell + offset).saturating_sub(1).min(n - 1);
+                    (index, weight)
+                })
+                .collect()
+        }
+    }
+}
+
+fn segments(samples: usize) -> Result<f64> {
+    if samples < 2 {
+        return Err(EqgftError::InvalidConfig(
+            "slices need at least 2 samples per direction".into(),
+        ));
+    }
+    Ok((samples - 1) as f64)
+}
+
+/// `start + s (end - start) + t v`.
+fn offset(start: [f64; 3], end: [f64; 3], s: f64, v: [f64; 3], t: f64) -> [f64; 3] {
+    [0, 1, 2].map(|axis| start[axis] + s * (end[axis] - start[axis]) + t * v[axis])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hopfion::{generate_hopfion_soliton_field, HopfionConfig};
+
+    fn field(resolution: usize) -> HopfionSolitonField {
+        generate_hopfion_soliton_field(&HopfionConfig {
+            resolution,
+            ..HopfionConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_samples_reproduce_lattice_points() {
+        let field = field(9);
+        for interpolation in [Interpolation::Trilinear, Interpolation::Tricubic] {
+            for (i, j, k) in [(0, 0, 0), (3, 5, 2), (8, 8, 8)] {
+                let sample = field
+                    .sample(field.position(i, j, k), interpolation)
+                    .unwrap();
+                let expected = field.at(i, j, k);
+                for (a, b) in sample.q.iter().zip(expected) {
+                    assert!((a - b).abs() < 1e-12);
+                }
+                let density = field.energy_density[field.index(i, j, k)];
+                assert!((sample.energy_density - density).abs() < 1e-12);
+            }
+        }
+        assert!(field
+            .sample([6.0, 0.0, 0.0], Interpolation::Trilinear)
+            .is_none());
+    }
+
+    #[test]
+    fn test_tricubic_is_exact_for_quadratics() {
+        let mut field = field(11);
+        let quadratic = |[x, y, z]: [f64; 3]| x * x - 2.0 * y * z + y + 0.5;
+        for i in 0..11 {
+            for j in 0..11 {
+                for k in 0..11 {
+                    let index = field.index(i, j, k);
+                    field.energy_density[index] = quadratic(field.position(i, j, k));
+                }
+            }
+        }
+        let point = [0.3, -1.7, 2.2];
+        let cubic = field.sample(point, Interpolation::Tricubic).unwrap();
+        let linear = field.sample(point, Interpolation::Trilinear).unwrap();
+        assert!((cubic.energy_density - quadratic(point)).abs() < 1e-10);
+        assert!((linear.energy_density - quadratic(point)).abs() > 1e-3);
+    }
+
+    #[test]
+    fn test_slices_and_streamlines() {
+        let field = field(9);
+        let line = field
+            .sample_line(
+                [-5.0, 0.0, 0.0],
+                [6.0, 0.0, 0.0],
+                12,
+                Interpolation::Trilinear,
+            )
+            .unwrap();
+        assert_eq!(line.len(), 12);
+        assert!(line[0].is_some() && line[11].is_none());
+
+        let plane = field
+            .sample_plane(
+                [-4.0, -4.0, 0.0],
+                [8.0, 0.0, 0.0],
+                [0.0, 8.0, 0.0],
+                [5, 3],
+                Interpolation::Tricubic,
+            )
+            .unwrap();
+        assert_eq!(plane.len(), 15);
+        assert_eq!(plane[14].unwrap().position, [4.0, 4.0, 0.0]);
+        assert!(field
+            .sample_line([0.0; 3], [1.0; 3], 1, Interpolation::Trilinear)
+            .is_err());
+
+        let options = StreamlineOptions {
+            max_steps: 50,
+            ..StreamlineOptions::default()
+        };
+        let line = field.streamline([0.5, 0.2, 0.1], &options).unwrap();
+        assert!(line.len() > 1 && line.len() <= 51);
+        for pair in line.windows(2) {
+            let length = (0..3)
+                .map(|a| (pair[1][a] - pair[0][a]).powi(2))
+                .sum::<f64>()
+                .sqrt();
+            assert!(length <= options.step * (1.0 + 1e-9));
+        }
+        assert!(field.streamline([9.0; 3], &options).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+//! Numerical side of EQGFT v2.2: Hopfion field configurations on a lattice
+//! and Monte Carlo simulation of the polarization-asymmetry measurement.
+//! Ported from `tools/vis/eqgft_v2_2.py`.
+
+pub mod asymmetry;
+pub mod backend;
+pub mod bootstrap;
+pub mod cache;
+pub mod channels;
+pub mod config;
+pub mod detector;
+pub mod energy;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod hopfion;
+pub mod interpolate;
+pub mod netcdf;
+pub mod parallel;
+pub mod reweight;
+pub mod scan;
+pub mod sensitivity;
+pub mod stats;
+pub mod storage;
+pub mod topology;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EqgftError {
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+}
+
+pub type Result<T> = std::result::Result<T, EqgftError>;
@@ -0,0 +1,429 @@
+//! NetCDF export of fields, event samples and sensitivity curves.
+//!
+//! Files use the classic 64-bit-offset format (CDF-2), which netCDF4-python,
+//! xarray, `scipy.io.netcdf_file` and ROOT's netCDF readers load without
+//! extra libraries. The writer here covers only what these exports need:
+//! fixed dimensions, `double` variables and text or `double` attributes.
+//! There is no record (unlimited) dimension, no other data type, no groups
+//! and no compression, and reading is left to the libraries above.
+//!
+//! With the `hdf5` feature `NetcdfFile::write_hdf5` writes the same dataset
+//! through libhdf5 instead, one dataset per variable, for toolchains that
+//! expect HDF5.
+
+use crate::hopfion::HopfionSolitonField;
+use crate::sensitivity::SensitivityCurve;
+use crate::{EqgftError, Result};
+use std::fs;
+use std::path::Path;
+
+const NC_DIMENSION: u32 = 0x0A;
+const NC_VARIABLE: u32 = 0x0B;
+const NC_ATTRIBUTE: u32 = 0x0C;
+const NC_CHAR: u32 = 2;
+const NC_DOUBLE: u32 = 6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    Text(String),
+    Doubles(Vec<f64>),
+}
+
+impl From<&str> for AttributeValue {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl From<f64> for AttributeValue {
+    fn from(value: f64) -> Self {
+        Self::Doubles(vec![value])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    /// Indices into `NetcdfFile::dimensions`, slowest-varying first.
+    pub dimensions: Vec<usize>,
+    pub attributes: Vec<(String, AttributeValue)>,
+    pub data: Vec<f64>,
+}
+
+impl Variable {
+    pub fn with_attribute(&mut self, name: &str, value: impl Into<AttributeValue>) -> &mut Self {
+        self.attributes.push((name.to_string(), value.into()));
+        self
+    }
+}
+
+/// In-memory dataset, serialized with `to_bytes` or `write`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetcdfFile {
+    pub dimensions: Vec<(String, usize)>,
+    pub attributes: Vec<(String, AttributeValue)>,
+    pub variables: Vec<Variable>,
+}
+
+impl NetcdfFile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a dimension and return its index.
+    pub fn add_dimension(&mut self, name: &str, len: usize) -> usize {
+        self.dimensions.push((name.to_string(), len));
+        self.dimensions.len() - 1
+    }
+
+    pub fn add_attribute(&mut self, name: &str, value: impl Into<AttributeValue>) {
+        self.attributes.push((name.to_string(), value.into()));
+    }
+
+    /// Add a variable over `dimensions`; `data` must fill them exactly.
+    pub fn add_variable(
+        &mut self,
+        name: &str,
+        dimensions: &[usize],
+        data: Vec<f64>,
+    ) -> Result<&mut Variable> {
+        let expected = dimensions
+            .iter()
+            .map(|&dim| self.dimensions.get(dim).map(|(_, len)| *len))
+            .product::<Option<usize>>()
+            .ok_or_else(|| EqgftError::InvalidConfig(format!("{name}: unknown dimension")))?;
+        if expected != data.len() {
+            return Err(EqgftError::InvalidConfig(format!(
+                "{name}: {} values do not fill its {} dimension slots",
+                data.len(),
+                expected
+            )));
+        }
+        self.variables.push(Variable {
+            name: name.to_string(),
+            dimensions: dimensions.to_vec(),
+            attributes: Vec::new(),
+            data,
+        });
+        Ok(self.variables.last_mut().expect("just pushed"))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let header_len = self.header(&vec![0; self.variables.len()]).len() as u64;
+        let mut begins = Vec::with_capacity(self.variables.len());
+        let mut offset = header_len;
+        for variable in &self.variables {
+            begins.push(offset);
+            offset += 8 * variable.data.len() as u64;
+        }
+
+        let mut bytes = self.header(&begins);
+        bytes.reserve((offset - header_len) as usize);
+        for variable in &self.variables {
+            for value in &variable.data {
+                bytes.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        bytes
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_bytes())
+            .map_err(|err| EqgftError::InvalidConfig(format!("{}: {}", path.display(), err)))
+    }
+
+    /// Write an HDF5 file with a dataset per variable, shaped by its
+    /// dimensions, and the attributes of the dataset and the variables.
+    /// The dimension names of a variable go into its `dimensions`
+    /// attribute, slowest-varying first.
+    #[cfg(feature = "hdf5")]
+    pub fn write_hdf5(&self, path: &Path) -> Result<()> {
+        let context =
+            |err: hdf5::Error| EqgftError::InvalidConfig(format!("{}: {}", path.display(), err));
+        let file = hdf5::File::create(path).map_err(context)?;
+        put_hdf5_attributes(&file, &self.attributes).map_err(context)?;
+        for variable in &self.variables {
+            let (names, shape): (Vec<&str>, Vec<usize>) = variable
+                .dimensions
+                .iter()
+                .map(|&dim| {
+                    let (name, len) = &self.dimensions[dim];
+                    (name.as_str(), *len)
+                })
+                .unzip();
+            let dataset = file
+                .new_dataset::<f64>()
+                .shape(shape)
+                .create(variable.name.as_str())
+                .map_err(context)?;
+            dataset.write_raw(&variable.data).map_err(context)?;
+            let dimensions = AttributeValue::Text(names.join(" "));
+            put_hdf5_attributes(&dataset, &[("dimensions".to_string(), dimensions)])
+                .map_err(context)?;
+            put_hdf5_attributes(&dataset, &variable.attributes).map_err(context)?;
+        }
+        Ok(())
+    }
+
+    fn header(&self, begins: &[u64]) -> Vec<u8> {
+        let mut out = b"CDF\x02".to_vec();
+        put_u32(&mut out, 0); // no record dimension
+
+        if self.dimensions.is_empty() {
+            out.extend_from_slice(&[0; 8]);
+        } else {
+            put_u32(&mut out, NC_DIMENSION);
+            put_u32(&mut out, self.dimensions.len() as u32);
+            for (name, len) in &self.dimensions {
+                put_name(&mut out, name);
+                put_u32(&mut out, *len as u32);
+            }
+        }
+        put_attributes(&mut out, &self.attributes);
+
+        if self.variables.is_empty() {
+            out.extend_from_slice(&[0; 8]);
+        } else {
+            put_u32(&mut out, NC_VARIABLE);
+            put_u32(&mut out, self.variables.len() as u32);
+            for (variable, begin) in self.variables.iter().zip(begins) {
+                put_name(&mut out, &variable.name);
+                put_u32(&mut out, variable.dimensions.len() as u32);
+                for &dim in &variable.dimensions {
+                    put_u32(&mut out, dim as u32);
+                }
+                put_attributes(&mut out, &variable.attributes);
+                put_u32(&mut out, NC_DOUBLE);
+                // vsize saturates for variables over 4 GiB, as the format prescribes
+                let vsize = 8 * variable.data.len() as u64;
+                put_u32(&mut out, vsize.min(u32::MAX as u64) as u32);
+                out.extend_from_slice(&begin.to_be_bytes());
+            }
+        }
+        out
+    }
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+fn pad(out: &mut Vec<u8>) {
+    out.resize(out.len().div_ceil(4) * 4, 0);
+}
+
+fn put_name(out: &mut Vec<u8>, name: &str) {
+    put_u32(out, name.len() as u32);
+    out.extend_from_slice(name.as_bytes());
+    pad(out);
+}
+
+fn put_attributes(out: &mut Vec<u8>, attributes: &[(String, AttributeValue)]) {
+    if attributes.is_empty() {
+        out.extend_from_slice(&[0; 8]);
+        return;
+    }
+    put_u32(out, NC_ATTRIBUTE);
+    put_u32(out, attributes.len() as u32);
+    for (name, value) in attributes {
+        put_name(out, name);
+        match value {
+            AttributeValue::Text(text) => {
+                put_u32(out, NC_CHAR);
+                put_u32(out, text.len() as u32);
+                out.extend_from_slice(text.as_bytes());
+                pad(out);
+            }
+            AttributeValue::Doubles(values) => {
+                put_u32(out, NC_DOUBLE);
+                put_u32(out, values.len() as u32);
+                for value in values {
+                    out.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "hdf5")]
+fn put_hdf5_attributes(
+    location: &hdf5::Location,
+    attributes: &[(String, AttributeValue)],
+) -> hdf5::Result<()> {
+    use hdf5::types::VarLenUnicode;
+    for (name, value) in attributes {
+        match value {
+            AttributeValue::Text(text) => {
+                let text: VarLenUnicode = text.parse().map_err(|err| format!("{name}: {err}"))?;
+                location
+                    .new_attr::<VarLenUnicode>()
+                    .create(name.as_str())?
+                    .write_scalar(&text)?;
+            }
+            AttributeValue::Doubles(values) => location
+                .new_attr::<f64>()
+                .shape(values.len())
+                .create(name.as_str())?
+                .write_raw(values)?,
+        }
+    }
+    Ok(())
+}
+
+/// Dimensions `x, y, z, component`; coordinate variables `x, y, z`, the
+/// quaternion `q(x, y, z, component)` and `energy_density(x, y, z)`.
+pub fn field_to_netcdf(field: &HopfionSolitonField) -> Result<NetcdfFile> {
+    let n = field.resolution();
+    let mut file = NetcdfFile::new();
+    file.add_attribute("title", "EQGFT Hopfion soliton field");
+    file.add_attribute("hopf_index", field.config.hopf_index as f64);
+    file.add_attribute("scale", field.config.scale);
+    file.add_attribute("total_energy", field.total_energy);
+
+    let axes = ["x", "y", "z"].map(|name| file.add_dimension(name, n));
+    let component = file.add_dimension("component", 4);
+    for (name, &dim) in ["x", "y", "z"].iter().zip(&axes) {
+        file.add_variable(name, &[dim], field.axis.clone())?
+            .with_attribute("long_name", format!("{name} coordinate").as_str());
+    }
+    file.add_variable(
+        "q",
+        &[axes[0], axes[1], axes[2], component],
+        field.q_x.iter().flatten().copied().collect(),
+    )?
+    .with_attribute("long_name", "unit quaternion field Q = (q0, q1, q2, q3)");
+    file.add_variable("energy_density", &axes, field.energy_density.clone())?
+        .with_attribute(
+            "long_name",
+            "sigma-model energy density 1/2 sum_i |d_i Q|^2",
+        );
+    Ok(file)
+}
+
+/// Dimension `event` with the variable `cos_theta(event)`.
+pub fn events_to_netcdf(events: &[f64], kappa: f64, seed: Option<u64>) -> Result<NetcdfFile> {
+    let mut file = NetcdfFile::new();
+    file.add_attribute("title", "EQGFT polarization event sample");
+    file.add_attribute("kappa", kappa);
+    if let Some(seed) = seed {
+        file.add_attribute("seed", seed.to_string().as_str());
+    }
+    let event = file.add_dimension("event", events.len());
+    file.add_variable("cos_theta", &[event], events.to_vec())?
+        .with_attribute("long_name", "cosine of the decay angle")
+        .with_attribute("valid_range", AttributeValue::Doubles(vec![-1.0, 1.0]));
+    Ok(file)
+}
+
+/// Dimension `point` with `n_events`, `expected_significance` and
+/// `measured_significance` along it.
+pub fn sensitivity_to_netcdf(curve: &SensitivityCurve) -> Result<NetcdfFile> {
+    let mut file = NetcdfFile::new();
+    file.add_attribute("title", "EQGFT discovery sensitivity curve");
+    file.add_attribute("kappa", curve.kappa);
+    file.add_attribute("predicted_asymmetry", curve.predicted);
+    let point = file.add_dimension("point", curve.points.len());
+    let column = |pick: fn(&crate::sensitivity::SensitivityPoint) -> f64| {
+        curve.points.iter().map(pick).collect::<Vec<_>>()
+    };
+    file.add_variable("n_events", &[point], column(|p| p.n_events as f64))?;
+    file.add_variable(
+        "expected_significance",
+        &[point],
+        column(|p| p.expected_significance),
+    )?
+    .with_attribute("units", "sigma");
+    file.add_variable(
+        "measured_significance",
+        &[point],
+        column(|p| p.measured_significance),
+    )?
+    .with_attribute("units", "sigma");
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hopfion::{generate_hopfion_soliton_field, HopfionConfig};
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_header_layout() {
+        let mut file = NetcdfFile::new();
+        let event = file.add_dimension("event", 3);
+        file.add_variable("c", &[event], vec![0.5, -0.25, 1.0])
+            .unwrap()
+            .with_attribute("units", "1");
+        let bytes = file.to_bytes();
+
+        assert_eq!(&bytes[..4], b"CDF\x02");
+        assert_eq!(u32_at(&bytes, 8), NC_DIMENSION);
+        assert_eq!(u32_at(&bytes, 12), 1);
+        assert_eq!(u32_at(&bytes, 16), 5); // "event"
+        assert_eq!(&bytes[20..25], b"event");
+        assert_eq!(u32_at(&bytes, 28), 3);
+        // the data is the last 24 bytes, at the offset recorded for `c`
+        let begin = u64::from_be_bytes(
+            bytes[bytes.len() - 32..bytes.len() - 24]
+                .try_into()
+                .unwrap(),
+        );
+        assert_eq!(begin as usize, bytes.len() - 24);
+        assert_eq!(
+            &bytes[begin as usize..begin as usize + 8],
+            &0.5f64.to_be_bytes()
+        );
+    }
+
+    #[test]
+    fn test_exports_fill_their_dimensions() {
+        let field = generate_hopfion_soliton_field(&HopfionConfig {
+            resolution: 5,
+            ..HopfionConfig::default()
+        })
+        .unwrap();
+        let file = field_to_netcdf(&field).unwrap();
+        assert_eq!(file.dimensions.len(), 4);
+        let q = file.variables.iter().find(|v| v.name == "q").unwrap();
+        assert_eq!(q.data.len(), 5 * 5 * 5 * 4);
+        assert_eq!(file.to_bytes().len() % 4, 0);
+
+        let events = events_to_netcdf(&[0.1, 0.2], 0.2, Some(1)).unwrap();
+        assert_eq!(events.variables[0].data, vec![0.1, 0.2]);
+
+        let mut bad = NetcdfFile::new();
+        let dim = bad.add_dimension("d", 2);
+        assert!(bad.add_variable("v", &[dim], vec![1.0]).is_err());
+    }
+
+    #[cfg(feature = "hdf5")]
+    #[test]
+    fn test_hdf5_keeps_shapes_and_attributes() {
+        let mut file = NetcdfFile::new();
+        file.add_attribute("title", "grid");
+        let rows = file.add_dimension("row", 2);
+        let columns = file.add_dimension("column", 3);
+        file.add_variable("v", &[rows, columns], (0..6).map(f64::from).collect())
+            .unwrap()
+            .with_attribute("valid_range", AttributeValue::Doubles(vec![0.0, 5.0]));
+        let path = std::env::temp_dir().join(format!("mmss-eqgft-{}.h5", std::process::id()));
+        file.write_hdf5(&path).unwrap();
+
+        let read = hdf5::File::open(&path).unwrap();
+        let title: hdf5::types::VarLenUnicode = read.attr("title").unwrap().read_scalar().unwrap();
+        assert_eq!(title.as_str(), "grid");
+        let v = read.dataset("v").unwrap();
+        assert_eq!(v.shape(), vec![2, 3]);
+        assert_eq!(v.read_raw::<f64>().unwrap(), file.variables[0].data);
+        let dimensions: hdf5::types::VarLenUnicode =
+            v.attr("dimensions").unwrap().read_scalar().unwrap();
+        assert_eq!(dimensions.as_str(), "row column");
+        let range = v.attr("valid_range").unwrap().read_raw::<f64>().unwrap();
+        assert_eq!(range, vec![0.0, 5.0]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
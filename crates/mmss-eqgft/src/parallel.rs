@@ -0,0 +1,33 @@
+use crate::{EqgftError, Result};
+use rayon::ThreadPoolBuilder;
+
+/// Most worker threads a config may ask for.
+pub const MAX_THREADS: usize = 256;
+
+/// Rejects a thread count of zero or above [`MAX_THREADS`].
+pub fn validate_threads(threads: Option<usize>) -> Result<()> {
+    match threads {
+        Some(threads) if threads == 0 || threads > MAX_THREADS => Err(EqgftError::InvalidConfig(
+            format!("threads must lie within 1..={MAX_THREADS}"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Run `work` on a dedicated pool of `threads` workers, or on rayon's global
+/// pool (one worker per core) when `threads` is `None`.
+pub fn run_with_threads<T: Send>(
+    threads: Option<usize>,
+    work: impl FnOnce() -> T + Send,
+) -> Result<T> {
+    match threads {
+        None => Ok(work()),
+        Some(threads) => {
+            let pool = ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .map_err(|err| EqgftError::InvalidConfig(format!("thread pool: {err}")))?;
+            Ok(pool.install(work))
+        }
+    }
+}
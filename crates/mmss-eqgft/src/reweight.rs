@@ -0,0 +1,156 @@
+use crate::asymmetry::{predicted_asymmetry, simulate_events, PolarizationAsymmetry, EVENT_CHUNK};
+use crate::config::EqgftConfig;
+use crate::parallel::run_with_threads;
+use crate::scan::kappa_grid;
+use crate::{EqgftError, Result};
+use rayon::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Per-event weights `W(c; κ) / W(c; κ₀) = (1 + κα c) / (1 + κ₀α c)` that
+/// turn a sample generated at `kappa_from` into one distributed as at
+/// `kappa_to`.
+///
+/// Acceptance and efficiency do not depend on κ and cancel in the ratio.
+/// Angular smearing does not commute with it exactly; for resolutions well
+/// below the angular scale of `W` the bias is negligible.
+pub fn event_weights(events: &[f64], kappa_from: f64, kappa_to: f64) -> Result<Vec<f64>> {
+    let (from, to) = (
+        predicted_asymmetry(kappa_from),
+        predicted_asymmetry(kappa_to),
+    );
+    if from.abs() > 1.0 || to.abs() > 1.0 {
+        return Err(EqgftError::InvalidConfig(
+            "reweighting between unphysical asymmetries".into(),
+        ));
+    }
+    events
+        .par_iter()
+        .map(|&c| {
+            let generated = 1.0 + from * c;
+            if generated <= 0.0 {
+                return Err(EqgftError::InvalidConfig(format!(
+                    "event at cos θ = {c} has zero density at kappa {kappa_from}"
+                )));
+            }
+            Ok((1.0 + to * c) / generated)
+        })
+        .collect()
+}
+
+/// Weighted form of `measure_ratio_asymmetry`: `Σwc / Σwc²`, with the delta-method
+/// error `√(Σ w² (c - 𝒜c²)²) / Σwc²`, which reduces to the unweighted one
+/// for unit weights.
+pub fn measure_weighted_asymmetry(events: &[f64], weights: &[f64]) -> Result<(f64, f64)> {
+    if events.is_empty() || events.len() != weights.len() {
+        return Err(EqgftError::InvalidConfig(
+            "weighted measurement needs one weight per event".into(),
+        ));
+    }
+    let sums = |f: &(dyn Fn(f64, f64) -> [f64; 2] + Sync)| {
+        // per-chunk partial sums keep the result independent of the thread count
+        let partial: Vec<[f64; 2]> = events
+            .par_chunks(EVENT_CHUNK)
+            .zip(weights.par_chunks(EVENT_CHUNK))
+            .map(|(c, w)| {
+                c.iter().zip(w).fold([0.0; 2], |[a, b], (&c, &w)| {
+                    let [da, db] = f(c, w);
+                    [a + da, b + db]
+                })
+            })
+            .collect();
+        partial
+            .iter()
+            .fold([0.0; 2], |[a, b], [da, db]| [a + da, b + db])
+    };
+
+    let [wc, wc2] = sums(&|c, w| [w * c, w * c * c]);
+    if wc2 <= 0.0 {
+        return Err(EqgftError::InvalidConfig(
+            "events carry no angular information".into(),
+        ));
+    }
+    let a = (wc / wc2).clamp(-1.0, 1.0);
+    let [variance, _] = sums(&|c, w| [(w * (c - a * c * c)).powi(2), 0.0]);
+    Ok((a, variance.sqrt() / wc2))
+}
+
+/// Measurement at `kappa` from `events` generated at `config.kappa`.
+pub fn reweight_polarization_asymmetry(
+    config: &EqgftConfig,
+    events: &[f64],
+    kappa: f64,
+) -> Result<PolarizationAsymmetry> {
+    let (a, stat_error) = run_with_threads(config.threads, || {
+        let weights = event_weights(events, config.kappa, kappa)?;
+        measure_weighted_asymmetry(events, &weights)
+    })??;
+    Ok(PolarizationAsymmetry::new(
+        kappa,
+        a,
+        stat_error,
+        config.systematic_error,
+        events.len(),
+    ))
+}
+
+/// Like `scan_kappa_with`, but generating one sample at `config.kappa` and
+/// reweighting it to every step instead of simulating each one. The steps
+/// share their events and so are correlated.
+pub fn scan_kappa_reweighted(
+    config: &EqgftConfig,
+    range: RangeInclusive<f64>,
+    steps: usize,
+) -> Result<Vec<PolarizationAsymmetry>> {
+    let kappas = kappa_grid(range, steps)?;
+    let events = simulate_events(config)?;
+    kappas
+        .into_iter()
+        .map(|kappa| reweight_polarization_asymmetry(config, &events, kappa))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asymmetry::{generate_events, measure_ratio_asymmetry, ALPHA};
+
+    #[test]
+    fn test_unit_weights_match_plain_measurement() {
+        let events = generate_events(0.3, 50_000, 2);
+        let weights = event_weights(&events, 0.2, 0.2).unwrap();
+        assert!(weights.iter().all(|&w| w == 1.0));
+
+        let (a, error) = measure_weighted_asymmetry(&events, &weights).unwrap();
+        let (plain, plain_error) = measure_ratio_asymmetry(&events).unwrap();
+        assert!((a - plain).abs() < 1e-12);
+        assert!((error - plain_error).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_reweighting_recovers_target_asymmetry() {
+        // a large asymmetry so the shift is resolvable with few events
+        let target = 0.5 / ALPHA;
+        let config = EqgftConfig {
+            kappa: 0.0,
+            n_events: 200_000,
+            seed: Some(4),
+            systematic_error: 0.0,
+            ..EqgftConfig::default()
+        };
+        let events = simulate_events(&config).unwrap();
+        let measured = reweight_polarization_asymmetry(&config, &events, target).unwrap();
+        assert!((measured.a - 0.5).abs() < 4.0 * measured.uncertainty);
+        assert!(measured.uncertainty > 0.0);
+
+        let scan = scan_kappa_reweighted(&config, 0.0..=target, 3).unwrap();
+        assert_eq!(scan.len(), 3);
+        assert!(scan.windows(2).all(|pair| pair[0].a < pair[1].a));
+        assert!((scan[2].a - measured.a).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_rejects_mismatched_weights() {
+        assert!(measure_weighted_asymmetry(&[0.1, 0.2], &[1.0]).is_err());
+        assert!(event_weights(&[-1.0], 1.0 / ALPHA, 0.0).is_err());
+    }
+}
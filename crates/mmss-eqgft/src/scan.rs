@@ -0,0 +1,95 @@
+use crate::asymmetry::{calculate_polarization_asymmetry, PolarizationAsymmetry};
+use crate::config::EqgftConfig;
+use crate::parallel::run_with_threads;
+use crate::{EqgftError, Result};
+use rayon::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Simulated measurements at `steps` evenly spaced couplings across `range`
+/// with otherwise default settings.
+pub fn scan_kappa(
+    range: RangeInclusive<f64>,
+    steps: usize,
+    n_events: usize,
+) -> Result<Vec<PolarizationAsymmetry>> {
+    let config = EqgftConfig {
+        n_events,
+        ..EqgftConfig::default()
+    };
+    scan_kappa_with(&config, range, steps)
+}
+
+/// Like `scan_kappa`, taking sample size, systematics, seed and threads from
+/// `config` (whose `kappa` is ignored). With a seed, step `i` uses
+/// `seed + (i << 32)`.
+pub fn scan_kappa_with(
+    config: &EqgftConfig,
+    range: RangeInclusive<f64>,
+    steps: usize,
+) -> Result<Vec<PolarizationAsymmetry>> {
+    config.validate()?;
+    let kappas = kappa_grid(range, steps)?;
+    let base_seed = config.seed.unwrap_or_else(rand::random);
+
+    run_with_threads(config.threads, || {
+        kappas
+            .par_iter()
+            .enumerate()
+            .map(|(i, &kappa)| {
+                calculate_polarization_asymmetry(&EqgftConfig {
+                    kappa,
+                    seed: Some(base_seed.wrapping_add((i as u64) << 32)),
+                    threads: None,
+                    ..*config
+                })
+            })
+            .collect()
+    })?
+}
+
+/// `steps` evenly spaced couplings from the start of `range` to its end.
+pub(crate) fn kappa_grid(range: RangeInclusive<f64>, steps: usize) -> Result<Vec<f64>> {
+    let (start, end) = range.into_inner();
+    if steps == 0 || !start.is_finite() || !end.is_finite() {
+        return Err(EqgftError::InvalidConfig(
+            "kappa scan needs a finite range and at least one step".into(),
+        ));
+    }
+    Ok((0..steps)
+        .map(|i| {
+            let t = if steps > 1 {
+                i as f64 / (steps - 1) as f64
+            } else {
+                0.0
+            };
+            start + t * (end - start)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_covers_range_in_order() {
+        let config = EqgftConfig {
+            n_events: 20_000,
+            seed: Some(9),
+            ..EqgftConfig::default()
+        };
+        let scan = scan_kappa_with(&config, 0.0..=1.0, 5).unwrap();
+
+        let kappas: Vec<f64> = scan.iter().map(|point| point.kappa).collect();
+        assert_eq!(kappas, vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert!(scan
+            .windows(2)
+            .all(|pair| pair[0].predicted < pair[1].predicted));
+        assert_eq!(scan, scan_kappa_with(&config, 0.0..=1.0, 5).unwrap());
+    }
+
+    #[test]
+    fn test_scan_rejects_zero_steps() {
+        assert!(scan_kappa(0.0..=1.0, 0, 1_000).is_err());
+    }
+}
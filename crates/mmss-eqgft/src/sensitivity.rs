@@ -0,0 +1,180 @@
+use crate::asymmetry::{calculate_polarization_asymmetry, predicted_asymmetry};
+use crate::config::EqgftConfig;
+use crate::parallel::run_with_threads;
+use crate::Result;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub use mmss_compute::sensitivity::{expected_significance, log_spaced_events};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SensitivityPoint {
+    pub n_events: usize,
+    /// Significance `|𝒜| / δ𝒜` expected from the error formula.
+    pub expected_significance: f64,
+    /// Significance of one simulated measurement with `n_events`.
+    pub measured_significance: f64,
+}
+
+/// Discovery significance against QED as a function of sample size.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SensitivityCurve {
+    pub kappa: f64,
+    pub predicted: f64,
+    pub points: Vec<SensitivityPoint>,
+}
+
+impl SensitivityCurve {
+    /// Smallest simulated sample size whose expected significance reaches `sigma`.
+    pub fn events_for_significance(&self, sigma: f64) -> Option<usize> {
+        self.points
+            .iter()
+            .find(|point| point.expected_significance >= sigma)
+            .map(|point| point.n_events)
+    }
+}
+
+/// Simulate one measurement per entry of `n_values` with the coupling and
+/// systematics of `config`. Points run in parallel; with a seed, point `i`
+/// uses `seed + (i << 32)` so the curve is reproducible.
+pub fn calculate_sensitivity_curve(
+    config: &EqgftConfig,
+    n_values: &[usize],
+) -> Result<SensitivityCurve> {
+    config.validate()?;
+    let predicted = predicted_asymmetry(config.kappa);
+    let base_seed = config.seed.unwrap_or_else(rand::random);
+
+    let points = run_with_threads(config.threads, || {
+        n_values
+            .par_iter()
+            .enumerate()
+            .map(|(i, &n_events)| {
+                let point_config = EqgftConfig {
+                    threads: None,
+                    ..*config
+                };
+                sensitivity_point(&point_config, point_seed(base_seed, i), n_events)
+            })
+            .collect::<Result<Vec<_>>>()
+    })??;
+
+    Ok(SensitivityCurve {
+        kappa: config.kappa,
+        predicted,
+        points,
+    })
+}
+
+/// Points of `calculate_sensitivity_curve`, computed one at a time as the
+/// iterator is advanced; each point still uses `config.threads` workers.
+/// With the same seed the points equal those of the full curve.
+pub fn sensitivity_iter<I: IntoIterator<Item = usize>>(
+    config: &EqgftConfig,
+    n_values: I,
+) -> Result<SensitivityIter<I::IntoIter>> {
+    config.validate()?;
+    Ok(SensitivityIter {
+        config: *config,
+        base_seed: config.seed.unwrap_or_else(rand::random),
+        index: 0,
+        n_values: n_values.into_iter(),
+    })
+}
+
+/// Iterator returned by `sensitivity_iter`.
+#[derive(Debug, Clone)]
+pub struct SensitivityIter<I> {
+    config: EqgftConfig,
+    base_seed: u64,
+    index: usize,
+    n_values: I,
+}
+
+impl<I> SensitivityIter<I> {
+    pub fn kappa(&self) -> f64 {
+        self.config.kappa
+    }
+
+    pub fn predicted(&self) -> f64 {
+        predicted_asymmetry(self.config.kappa)
+    }
+}
+
+impl<I: Iterator<Item = usize>> Iterator for SensitivityIter<I> {
+    type Item = Result<SensitivityPoint>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n_events = self.n_values.next()?;
+        let seed = point_seed(self.base_seed, self.index);
+        self.index += 1;
+        Some(sensitivity_point(&self.config, seed, n_events))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.n_values.size_hint()
+    }
+}
+
+fn point_seed(base_seed: u64, index: usize) -> u64 {
+    base_seed.wrapping_add((index as u64) << 32)
+}
+
+fn sensitivity_point(config: &EqgftConfig, seed: u64, n_events: usize) -> Result<SensitivityPoint> {
+    let predicted = predicted_asymmetry(config.kappa);
+    let measurement = calculate_polarization_asymmetry(&EqgftConfig {
+        n_events,
+        seed: Some(seed),
+        ..*config
+    })?;
+    Ok(SensitivityPoint {
+        n_events,
+        expected_significance: expected_significance(predicted, n_events, config.systematic_error),
+        measured_significance: measurement.significance(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expected_significance_grows_with_events() {
+        let config = EqgftConfig {
+            seed: Some(5),
+            systematic_error: 0.0,
+            ..EqgftConfig::default()
+        };
+        let n_values = log_spaced_events(1_000, 1_000_000, 7);
+        assert_eq!(n_values.first(), Some(&1_000));
+        assert_eq!(n_values.last(), Some(&1_000_000));
+
+        let curve = calculate_sensitivity_curve(&config, &n_values).unwrap();
+        assert_eq!(curve.points.len(), 7);
+        assert!(curve
+            .points
+            .windows(2)
+            .all(|pair| pair[0].expected_significance < pair[1].expected_significance));
+        // 𝒜 ≈ 1.46e-3 needs N ≈ 3.5e7 for 5σ, far beyond the scanned range
+        assert_eq!(curve.events_for_significance(5.0), None);
+        assert!(curve.events_for_significance(0.5).is_some());
+    }
+
+    #[test]
+    fn test_iterator_matches_full_curve() {
+        let config = EqgftConfig {
+            seed: Some(8),
+            ..EqgftConfig::default()
+        };
+        let n_values = log_spaced_events(1_000, 20_000, 4);
+        let curve = calculate_sensitivity_curve(&config, &n_values).unwrap();
+
+        let mut points = sensitivity_iter(&config, n_values.iter().copied()).unwrap();
+        assert_eq!(points.size_hint(), (4, Some(4)));
+        assert_eq!(points.predicted(), curve.predicted);
+        let first = points.next().unwrap().unwrap();
+        assert_eq!(first, curve.points[0]);
+        let rest: Vec<_> = points.collect::<Result<_>>().unwrap();
+        assert_eq!(rest, curve.points[1..]);
+    }
+}
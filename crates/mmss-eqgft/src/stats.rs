@@ -0,0 +1,196 @@
+use crate::asymmetry::PolarizationAsymmetry;
+use crate::{EqgftError, Result};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::SQRT_2;
+
+pub use mmss_compute::sensitivity::required_events;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Interval {
+    pub low: f64,
+    pub high: f64,
+    pub confidence: f64,
+}
+
+impl Interval {
+    pub fn contains(&self, value: f64) -> bool {
+        (self.low..=self.high).contains(&value)
+    }
+}
+
+/// Complementary error function, fractional error below 1.2e-7 everywhere
+/// (Numerical Recipes `erfcc`), so deep-tail p-values stay meaningful.
+pub fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let value = t * poly.exp();
+    if x >= 0.0 {
+        value
+    } else {
+        2.0 - value
+    }
+}
+
+pub fn normal_cdf(x: f64) -> f64 {
+    0.5 * erfc(-x / SQRT_2)
+}
+
+/// Inverse of `normal_cdf` (Acklam's rational approximation, relative error
+/// about 1e-9).
+pub fn normal_quantile(p: f64) -> Result<f64> {
+    if !(p > 0.0 && p < 1.0) {
+        return Err(EqgftError::InvalidConfig(format!(
+            "probability {p} must lie in (0, 1)"
+        )));
+    }
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const P_LOW: f64 = 0.024_25;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    Ok(if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - P_LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    })
+}
+
+/// Two-sided critical value for a central interval, e.g. 1.96 for 0.95.
+fn critical_value(confidence: f64) -> Result<f64> {
+    normal_quantile(0.5 + 0.5 * confidence)
+}
+
+/// Wilson score interval for a binomial proportion.
+pub fn wilson_interval(successes: u64, trials: u64, confidence: f64) -> Result<Interval> {
+    if trials == 0 || successes > trials {
+        return Err(EqgftError::InvalidConfig(format!(
+            "{successes} successes out of {trials} trials"
+        )));
+    }
+    let z = critical_value(confidence)?;
+    let n = trials as f64;
+    let p = successes as f64 / n;
+    let z2 = z * z;
+    let centre = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let half_width = z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    Ok(Interval {
+        low: (centre - half_width).max(0.0),
+        high: (centre + half_width).min(1.0),
+        confidence,
+    })
+}
+
+/// Interval on `(N+ - N-) / N` of a counting experiment, from the Wilson
+/// interval of `N+ / N`.
+pub fn counting_asymmetry_interval(n_plus: u64, n_minus: u64, confidence: f64) -> Result<Interval> {
+    let proportion = wilson_interval(n_plus, n_plus + n_minus, confidence)?;
+    Ok(Interval {
+        low: 2.0 * proportion.low - 1.0,
+        high: 2.0 * proportion.high - 1.0,
+        confidence,
+    })
+}
+
+/// Gaussian interval `a ± z δa` on a simulated measurement, clamped to the
+/// physical range [-1, 1].
+pub fn asymmetry_interval(
+    measurement: &PolarizationAsymmetry,
+    confidence: f64,
+) -> Result<Interval> {
+    let half_width = critical_value(confidence)? * measurement.uncertainty;
+    Ok(Interval {
+        low: (measurement.a - half_width).max(-1.0),
+        high: (measurement.a + half_width).min(1.0),
+        confidence,
+    })
+}
+
+/// Two-sided p-value of the null hypothesis κ = 0 (𝒜 = 0).
+pub fn p_value_vs_null(measurement: &PolarizationAsymmetry) -> f64 {
+    erfc(measurement.significance() / SQRT_2)
+}
+
+/// Convert a two-sided p-value back to a Z-score.
+pub fn z_score(p_value: f64) -> Result<f64> {
+    normal_quantile(1.0 - 0.5 * p_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normal_functions_agree() {
+        assert!((normal_cdf(1.96) - 0.975).abs() < 1e-4);
+        assert!((normal_quantile(0.975).unwrap() - 1.959_964).abs() < 1e-5);
+        // 5σ two-sided p-value is 5.733e-7; relative accuracy matters here
+        assert!((erfc(5.0 / SQRT_2) / 5.733e-7 - 1.0).abs() < 1e-3);
+        assert!((z_score(5.733e-7).unwrap() - 5.0).abs() < 1e-3);
+        assert!(normal_quantile(1.0).is_err());
+    }
+
+    #[test]
+    fn test_wilson_interval_stays_inside_unit_range() {
+        let interval = wilson_interval(0, 10, 0.95).unwrap();
+        assert_eq!(interval.low, 0.0);
+        assert!(interval.high > 0.0 && interval.high < 0.35);
+
+        let symmetric = counting_asymmetry_interval(500, 500, 0.95).unwrap();
+        assert!(symmetric.contains(0.0));
+        assert!((symmetric.low + symmetric.high).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_required_events_reaches_target() {
+        let asymmetry = 0.2 * crate::asymmetry::ALPHA;
+        let n = required_events(asymmetry, 0.0, 5.0).unwrap();
+        let significance = asymmetry / ((3.0 - asymmetry * asymmetry) / n as f64).sqrt();
+        assert!((5.0..5.0001).contains(&significance));
+
+        // a systematic floor above 𝒜/5 makes 5σ unreachable
+        assert_eq!(required_events(asymmetry, asymmetry / 4.0, 5.0), None);
+    }
+}
@@ -0,0 +1,394 @@
+use crate::hopfion::{lattice_point, HopfionConfig, HopfionSolitonField};
+use crate::{EqgftError, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    #[default]
+    F64,
+    F32,
+}
+
+/// How `CompactHopfionField` keeps its samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageOptions {
+    pub precision: Precision,
+    /// XOR-delta compress the samples in independent chunks.
+    pub compress: bool,
+}
+
+/// Values encoded per compressed chunk; a multiple of 4 so quaternions never
+/// straddle chunks.
+const CHUNK_VALUES: usize = 1 << 16;
+
+/// A flat run of samples at the chosen precision.
+///
+/// Compressed chunks store each value's bit pattern XORed with the value
+/// `stride` places earlier, as LEB128 varints. Neighbouring lattice values
+/// share sign, exponent and leading mantissa bits, so the XOR is small, and
+/// the vacuum region encodes as one byte per value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "encoding", rename_all = "snake_case")]
+pub enum FloatStorage {
+    F64 {
+        values: Vec<f64>,
+    },
+    F32 {
+        values: Vec<f32>,
+    },
+    Compressed {
+        precision: Precision,
+        len: usize,
+        stride: usize,
+        chunks: Vec<Vec<u8>>,
+    },
+}
+
+impl FloatStorage {
+    /// Encode `values`, predicting each from the one `stride` places back.
+    pub fn encode(values: &[f64], stride: usize, options: StorageOptions) -> Self {
+        match (options.precision, options.compress) {
+            (Precision::F64, false) => Self::F64 {
+                values: values.to_vec(),
+            },
+            (Precision::F32, false) => Self::F32 {
+                values: values.iter().map(|&v| v as f32).collect(),
+            },
+            (precision, true) => Self::Compressed {
+                precision,
+                len: values.len(),
+                stride,
+                chunks: values
+                    .par_chunks(CHUNK_VALUES)
+                    .map(|chunk| compress_chunk(chunk, stride, precision))
+                    .collect(),
+            },
+        }
+    }
+
+    pub fn decode(&self) -> Result<Vec<f64>> {
+        match self {
+            Self::F64 { values } => Ok(values.clone()),
+            Self::F32 { values } => Ok(values.iter().map(|&v| v as f64).collect()),
+            Self::Compressed {
+                precision,
+                len,
+                stride,
+                chunks,
+            } => {
+                let decoded: Vec<Vec<f64>> = chunks
+                    .par_iter()
+                    .enumerate()
+                    .map(|(index, chunk)| {
+                        let expected = CHUNK_VALUES.min(len.saturating_sub(index * CHUNK_VALUES));
+                        decompress_chunk(chunk, expected, *stride, *precision)
+                    })
+                    .collect::<Result<_>>()?;
+                let values: Vec<f64> = decoded.into_iter().flatten().collect();
+                if values.len() != *len {
+                    return Err(EqgftError::InvalidConfig(format!(
+                        "compressed field holds {} values, expected {}",
+                        values.len(),
+                        len
+                    )));
+                }
+                Ok(values)
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::F64 { values } => values.len(),
+            Self::F32 { values } => values.len(),
+            Self::Compressed { len, .. } => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes held by the samples themselves.
+    pub fn byte_size(&self) -> usize {
+        match self {
+            Self::F64 { values } => 8 * values.len(),
+            Self::F32 { values } => 4 * values.len(),
+            Self::Compressed { chunks, .. } => chunks.iter().map(Vec::len).sum(),
+        }
+    }
+}
+
+fn to_bits(value: f64, precision: Precision) -> u64 {
+    match precision {
+        Precision::F64 => value.to_bits(),
+        Precision::F32 => (value as f32).to_bits() as u64,
+    }
+}
+
+fn from_bits(bits: u64, precision: Precision) -> f64 {
+    match precision {
+        Precision::F64 => f64::from_bits(bits),
+        Precision::F32 => f32::from_bits(bits as u32) as f64,
+    }
+}
+
+fn compress_chunk(values: &[f64], stride: usize, precision: Precision) -> Vec<u8> {
+    let bits: Vec<u64> = values.iter().map(|&v| to_bits(v, precision)).collect();
+    let mut out = Vec::with_capacity(values.len());
+    for (index, &current) in bits.iter().enumerate() {
+        let previous = index.checked_sub(stride).map_or(0, |back| bits[back]);
+        let mut delta = current ^ previous;
+        loop {
+            let byte = (delta & 0x7f) as u8;
+            delta >>= 7;
+            if delta == 0 {
+                out.push(byte);
+                break;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+    out
+}
+
+fn decompress_chunk(
+    bytes: &[u8],
+    expected: usize,
+    stride: usize,
+    precision: Precision,
+) -> Result<Vec<f64>> {
+    let corrupt = || EqgftError::InvalidConfig("corrupt compressed chunk".into());
+    let mut bits: Vec<u64> = Vec::with_capacity(expected);
+    let mut cursor = bytes.iter();
+    while bits.len() < expected {
+        let mut delta = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = *cursor.next().ok_or_else(corrupt)?;
+            if shift >= 64 {
+                return Err(corrupt());
+            }
+            delta |= ((byte & 0x7f) as u64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        let previous = bits.len().checked_sub(stride).map_or(0, |back| bits[back]);
+        bits.push(delta ^ previous);
+    }
+    if cursor.next().is_some() {
+        return Err(corrupt());
+    }
+    Ok(bits.into_iter().map(|b| from_bits(b, precision)).collect())
+}
+
+/// `HopfionSolitonField` with its samples kept at reduced precision and/or
+/// compressed, for holding or shipping high-resolution lattices.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompactHopfionField {
+    pub config: HopfionConfig,
+    pub axis: Vec<f64>,
+    /// `q_x` flattened to `[q0, q1, q2, q3, q0, ...]`.
+    pub q_x: FloatStorage,
+    pub energy_density: FloatStorage,
+    pub total_energy: f64,
+}
+
+impl CompactHopfionField {
+    pub fn from_field(field: &HopfionSolitonField, options: StorageOptions) -> Self {
+        let flat: Vec<f64> = field.q_x.iter().flatten().copied().collect();
+        Self {
+            config: field.config,
+            axis: field.axis.clone(),
+            q_x: FloatStorage::encode(&flat, 4, options),
+            energy_density: FloatStorage::encode(&field.energy_density, 1, options),
+            total_energy: field.total_energy,
+        }
+    }
+
+    /// Expand back to f64 samples. Lossless for f64 storage; f32 storage
+    /// rounds each component to about 7 significant digits.
+    pub fn to_field(&self) -> Result<HopfionSolitonField> {
+        let n = self.config.resolution;
+        let points = n * n * n;
+        let flat = self.q_x.decode()?;
+        let energy_density = self.energy_density.decode()?;
+        if flat.len() != 4 * points || energy_density.len() != points || self.axis.len() != n {
+            return Err(EqgftError::InvalidConfig(format!(
+                "stored field does not fill a {n}³ lattice"
+            )));
+        }
+        Ok(HopfionSolitonField {
+            config: self.config,
+            axis: self.axis.clone(),
+            q_x: flat
+                .chunks_exact(4)
+                .map(|q| [q[0], q[1], q[2], q[3]])
+                .collect(),
+            energy_density,
+            total_energy: self.total_energy,
+        })
+    }
+
+    /// Bytes held by the field and energy samples.
+    pub fn byte_size(&self) -> usize {
+        self.q_x.byte_size() + self.energy_density.byte_size()
+    }
+}
+
+impl HopfionSolitonField {
+    pub fn compact(&self, options: StorageOptions) -> CompactHopfionField {
+        CompactHopfionField::from_field(self, options)
+    }
+
+    /// View of every `stride`-th lattice point along each axis. The stride
+    /// must divide `resolution - 1` so the view spans the same box.
+    pub fn downsampled(&self, stride: usize) -> Result<DownsampledField<'_>> {
+        let n = self.resolution();
+        if stride == 0 || !(n - 1).is_multiple_of(stride) {
+            return Err(EqgftError::InvalidConfig(format!(
+                "stride {stride} does not divide the {} lattice intervals",
+                n - 1
+            )));
+        }
+        Ok(DownsampledField {
+            field: self,
+            stride,
+            resolution: (n - 1) / stride + 1,
+        })
+    }
+}
+
+/// Coarser lattice over a borrowed field, see `HopfionSolitonField::downsampled`.
+#[derive(Debug, Clone, Copy)]
+pub struct DownsampledField<'a> {
+    field: &'a HopfionSolitonField,
+    stride: usize,
+    resolution: usize,
+}
+
+impl DownsampledField<'_> {
+    pub fn resolution(&self) -> usize {
+        self.resolution
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn position(&self, i: usize, j: usize, k: usize) -> [f64; 3] {
+        let s = self.stride;
+        self.field.position(i * s, j * s, k * s)
+    }
+
+    pub fn at(&self, i: usize, j: usize, k: usize) -> [f64; 4] {
+        let s = self.stride;
+        self.field.at(i * s, j * s, k * s)
+    }
+
+    /// Energy density of the full lattice at the view's points.
+    pub fn energy_density_at(&self, i: usize, j: usize, k: usize) -> f64 {
+        let s = self.stride;
+        self.field.energy_density[self.field.index(i * s, j * s, k * s)]
+    }
+
+    /// Copy the view into a standalone field on the coarse lattice. The
+    /// energy density keeps its fine-lattice values, and the total energy is
+    /// that of the full field.
+    pub fn to_field(&self) -> HopfionSolitonField {
+        let n = self.resolution;
+        let (q_x, energy_density) = (0..n * n * n)
+            .map(|index| lattice_point(n, index))
+            .map(|[i, j, k]| (self.at(i, j, k), self.energy_density_at(i, j, k)))
+            .unzip();
+        HopfionSolitonField {
+            config: HopfionConfig {
+                resolution: n,
+                ..self.field.config
+            },
+            axis: (0..n).map(|i| self.field.axis[i * self.stride]).collect(),
+            q_x,
+            energy_density,
+            total_energy: self.field.total_energy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hopfion::generate_hopfion_soliton_field;
+
+    fn field() -> HopfionSolitonField {
+        generate_hopfion_soliton_field(&HopfionConfig {
+            resolution: 21,
+            ..HopfionConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_storage_round_trips() {
+        let field = field();
+        let lossless = field.compact(StorageOptions {
+            precision: Precision::F64,
+            compress: true,
+        });
+        let restored = lossless.to_field().unwrap();
+        assert_eq!(restored.q_x, field.q_x);
+        assert_eq!(restored.energy_density, field.energy_density);
+        assert!(lossless.byte_size() < 8 * 5 * field.q_x.len());
+
+        let f32_field = field.compact(StorageOptions {
+            precision: Precision::F32,
+            compress: false,
+        });
+        assert_eq!(f32_field.byte_size(), 4 * 5 * field.q_x.len());
+        let compressed = field.compact(StorageOptions {
+            precision: Precision::F32,
+            compress: true,
+        });
+        assert!(compressed.byte_size() < f32_field.byte_size());
+        assert_eq!(
+            compressed.to_field().unwrap().q_x,
+            f32_field.to_field().unwrap().q_x
+        );
+        for (a, b) in f32_field.to_field().unwrap().q_x.iter().zip(&field.q_x) {
+            for c in 0..4 {
+                assert!((a[c] - b[c]).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_corrupt_chunk_is_rejected() {
+        let mut compact = field().compact(StorageOptions {
+            precision: Precision::F32,
+            compress: true,
+        });
+        if let FloatStorage::Compressed { chunks, .. } = &mut compact.q_x {
+            chunks[0].truncate(10);
+        }
+        assert!(compact.to_field().is_err());
+    }
+
+    #[test]
+    fn test_downsampled_view_keeps_the_box() {
+        let field = field();
+        let view = field.downsampled(4).unwrap();
+        assert_eq!(view.resolution(), 6);
+        assert_eq!(view.position(5, 5, 5), [5.0, 5.0, 5.0]);
+        assert_eq!(view.at(2, 3, 1), field.at(8, 12, 4));
+
+        let coarse = view.to_field();
+        assert_eq!(coarse.axis, vec![-5.0, -3.0, -1.0, 1.0, 3.0, 5.0]);
+        assert_eq!(coarse.at(2, 3, 1), field.at(8, 12, 4));
+        assert_eq!(coarse.config.spacing(), 2.0);
+        assert!(field.downsampled(3).is_err());
+    }
+}
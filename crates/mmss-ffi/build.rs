@@ -0,0 +1,16 @@
+use std::env;
+use std::path::Path;
+
+/// Regenerate `include/mmss_ffi.h` from the exported functions. The header
+/// is committed, so a failure here only leaves it as it was.
+fn main() {
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").expect("set by cargo");
+    match cbindgen::generate(&crate_dir) {
+        Ok(bindings) => {
+            bindings.write_to_file(Path::new(&crate_dir).join("include/mmss_ffi.h"));
+        }
+        Err(err) => println!("cargo:warning=Not regenerating include/mmss_ffi.h: {err}"),
+    }
+}
@@ -0,0 +1,91 @@
+#ifndef MMSS_FFI_H
+#define MMSS_FFI_H
+
+/* Generated by cbindgen from crates/mmss-ffi; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ An emergence engine: the SYS7-SYS1 cascade and its metrics.
+ */
+typedef struct MmssEngine MmssEngine;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Create an engine at baseline metrics. `constants` is a JSON object
+ overriding some of the SI constants (`hbar`, `c`, `zitter_frequency`,
+ `zitter_amplitude`, `fine_structure`), or null for SI. Returns null when
+ the constants are invalid. Release it with `mmss_engine_free`.
+
+ # Safety
+
+ `constants` must be null or a NUL-terminated string.
+ */
+struct MmssEngine *mmss_engine_new(const char *constants);
+
+/*
+ Release an engine from `mmss_engine_new`.
+
+ # Safety
+
+ `engine` must be null or come from `mmss_engine_new`, and not have been
+ freed yet.
+ */
+void mmss_engine_free(struct MmssEngine *engine);
+
+/*
+ Apply `operator_name` (a `GeometricOperator` name such as
+ `"QuaternionRotation"`) with the JSON object `params`, or none when
+ null. Answers `{"ok": {"metrics": ..., "output": ...}}`, `output` being
+ the operator's structured result or null.
+
+ # Safety
+
+ `engine` must come from `mmss_engine_new`; `operator_name` and `params` must
+ be null or NUL-terminated strings.
+ */
+char *mmss_engine_apply_operator(struct MmssEngine *engine,
+                                 const char *operator_name,
+                                 const char *params);
+
+/*
+ The engine's metrics: `{"ok": metrics}`, shaped like `GET /api/metrics`
+ returns them.
+
+ # Safety
+
+ `engine` must come from `mmss_engine_new`.
+ */
+char *mmss_engine_get_metrics(const struct MmssEngine *engine);
+
+/*
+ Return the metrics to the baseline of the engine's constants and drop
+ its Hopfion field.
+
+ # Safety
+
+ `engine` must be null or come from `mmss_engine_new`.
+ */
+void mmss_engine_reset(struct MmssEngine *engine);
+
+/*
+ Release a string returned by this library.
+
+ # Safety
+
+ `response` must be null or come from this library, and not have been
+ freed yet.
+ */
+void mmss_string_free(char *response);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MMSS_FFI_H */
@@ -0,0 +1,236 @@
+//! C ABI over `EmergenceLogic`, for simulation frameworks that embed the
+//! engine instead of calling the server. `include/mmss_ffi.h` declares it;
+//! link `libmmss_ffi` (shared or static).
+//!
+//! Parameters and results are JSON strings. Calls that produce a value
+//! return `{"ok": ...}` or `{"error": "..."}`, to be released with
+//! `mmss_string_free`. An engine is not synchronized: use it from one
+//! thread at a time.
+
+use mmss::core::emergence_logic::EmergenceLogic;
+use mmss::state::PhysicalConstants;
+use mmss::GeometricOperator;
+use serde_json::{json, Value};
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// An emergence engine: the SYS7-SYS1 cascade and its metrics.
+pub struct MmssEngine {
+    logic: EmergenceLogic,
+}
+
+/// `raw` as UTF-8; `None` for a null pointer.
+///
+/// # Safety
+///
+/// `raw` must be null or a NUL-terminated string valid for `'a`.
+unsafe fn text<'a>(raw: *const c_char) -> Result<Option<&'a str>, String> {
+    if raw.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(raw)
+        .to_str()
+        .map(Some)
+        .map_err(|_| "arguments must be UTF-8".to_string())
+}
+
+/// JSON `raw`, `default` when it is null or empty.
+unsafe fn json_arg(what: &str, raw: *const c_char, default: Value) -> Result<Value, String> {
+    match text(raw)? {
+        Some(raw) if !raw.trim().is_empty() => {
+            serde_json::from_str(raw).map_err(|err| format!("{what}: {err}"))
+        }
+        _ => Ok(default),
+    }
+}
+
+fn constants(value: Value) -> Result<PhysicalConstants, String> {
+    let constants: PhysicalConstants =
+        serde_json::from_value(value).map_err(|err| format!("constants: {err}"))?;
+    match constants.invalid().as_slice() {
+        [] => Ok(constants),
+        invalid => Err(format!("not positive numbers: {}", invalid.join(", "))),
+    }
+}
+
+fn respond(outcome: Result<Value, String>) -> *mut c_char {
+    let response = match outcome {
+        Ok(value) => json!({ "ok": value }),
+        Err(error) => json!({ "error": error }),
+    };
+    // serde_json escapes NUL, so the response never contains one
+    CString::new(response.to_string())
+        .unwrap_or_default()
+        .into_raw()
+}
+
+/// Create an engine at baseline metrics. `constants` is a JSON object
+/// overriding some of the SI constants (`hbar`, `c`, `zitter_frequency`,
+/// `zitter_amplitude`, `fine_structure`), or null for SI. Returns null when
+/// the constants are invalid. Release it with `mmss_engine_free`.
+///
+/// # Safety
+///
+/// `constants` must be null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn mmss_engine_new(constants: *const c_char) -> *mut MmssEngine {
+    let constants = json_arg("constants", constants, json!({})).and_then(self::constants);
+    match constants {
+        Ok(constants) => Box::into_raw(Box::new(MmssEngine {
+            logic: EmergenceLogic::new(None).with_constants(constants),
+        })),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Release an engine from `mmss_engine_new`.
+///
+/// # Safety
+///
+/// `engine` must be null or come from `mmss_engine_new`, and not have been
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn mmss_engine_free(engine: *mut MmssEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+/// Apply `operator_name` (a `GeometricOperator` name such as
+/// `"QuaternionRotation"`) with the JSON object `params`, or none when
+/// null. Answers `{"ok": {"metrics": ..., "output": ...}}`, `output` being
+/// the operator's structured result or null.
+///
+/// # Safety
+///
+/// `engine` must come from `mmss_engine_new`; `operator_name` and `params` must
+/// be null or NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn mmss_engine_apply_operator(
+    engine: *mut MmssEngine,
+    operator_name: *const c_char,
+    params: *const c_char,
+) -> *mut c_char {
+    let Some(engine) = engine.as_mut() else {
+        return respond(Err("null engine".to_string()));
+    };
+    let request = text(operator_name).and_then(|operator| {
+        let operator = operator.ok_or("null operator")?;
+        let op: GeometricOperator = serde_json::from_value(json!(operator))
+            .map_err(|_| format!("unknown operator {operator}"))?;
+        Ok((op, json_arg("params", params, json!({}))?))
+    });
+    respond(request.and_then(|(op, params)| {
+        // a panic must not unwind into the caller
+        catch_unwind(AssertUnwindSafe(|| {
+            let metrics = serde_json::to_value(engine.logic.apply_operator(op, &params))
+                .map_err(|err| err.to_string())?;
+            Ok(json!({ "metrics": metrics, "output": engine.logic.take_output() }))
+        }))
+        .unwrap_or_else(|_| Err(format!("{op:?} panicked")))
+    }))
+}
+
+/// The engine's metrics: `{"ok": metrics}`, shaped like `GET /api/metrics`
+/// returns them.
+///
+/// # Safety
+///
+/// `engine` must come from `mmss_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn mmss_engine_get_metrics(engine: *const MmssEngine) -> *mut c_char {
+    let Some(engine) = engine.as_ref() else {
+        return respond(Err("null engine".to_string()));
+    };
+    respond(serde_json::to_value(engine.logic.metrics()).map_err(|err| err.to_string()))
+}
+
+/// Return the metrics to the baseline of the engine's constants and drop
+/// its Hopfion field.
+///
+/// # Safety
+///
+/// `engine` must be null or come from `mmss_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn mmss_engine_reset(engine: *mut MmssEngine) {
+    if let Some(engine) = engine.as_mut() {
+        engine.logic.reset(true, true);
+    }
+}
+
+/// Release a string returned by this library.
+///
+/// # Safety
+///
+/// `response` must be null or come from this library, and not have been
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn mmss_string_free(response: *mut c_char) {
+    if !response.is_null() {
+        drop(CString::from_raw(response));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn take(raw: *mut c_char) -> Value {
+        let response = CStr::from_ptr(raw).to_str().unwrap().to_string();
+        mmss_string_free(raw);
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn test_engine_round_trips_through_the_c_abi() {
+        unsafe {
+            let engine = mmss_engine_new(std::ptr::null());
+            assert!(!engine.is_null());
+            let baseline = take(mmss_engine_get_metrics(engine))["ok"].clone();
+
+            let (operator, params) = (
+                CString::new("QuaternionRotation").unwrap(),
+                CString::new(r#"{"theta": 1.0}"#).unwrap(),
+            );
+            let response = take(mmss_engine_apply_operator(
+                engine,
+                operator.as_ptr(),
+                params.as_ptr(),
+            ));
+            let coherence = response["ok"]["metrics"]["quaternion_coherence"].as_f64();
+            assert!(coherence.unwrap() > baseline["quaternion_coherence"].as_f64().unwrap());
+            assert!(response["ok"]["output"].is_null());
+
+            mmss_engine_reset(engine);
+            assert_eq!(take(mmss_engine_get_metrics(engine))["ok"], baseline);
+            mmss_engine_free(engine);
+        }
+    }
+
+    #[test]
+    fn test_errors_are_reported_not_raised() {
+        unsafe {
+            let invalid = CString::new(r#"{"hbar": -1}"#).unwrap();
+            assert!(mmss_engine_new(invalid.as_ptr()).is_null());
+
+            let engine = mmss_engine_new(std::ptr::null());
+            let operator = CString::new("Nope").unwrap();
+            let response = take(mmss_engine_apply_operator(
+                engine,
+                operator.as_ptr(),
+                std::ptr::null(),
+            ));
+            assert!(response["error"]
+                .as_str()
+                .unwrap()
+                .contains("unknown operator"));
+            let response = take(mmss_engine_apply_operator(
+                std::ptr::null_mut(),
+                operator.as_ptr(),
+                std::ptr::null(),
+            ));
+            assert_eq!(response["error"], "null engine");
+            mmss_engine_free(engine);
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Node.js bindings over the server's visualization pipeline, so the
+//! Electron viewer assembles packets, reduces fields and extracts meshes
+//! with the same code as `GET /api/visualization/*`.
+//!
+//! Build with `npm run build` in this directory, which also generates
+//! `index.d.ts`; the exports need the `node` feature, which the build
+//! script enables. Structured values cross as plain objects in the server's
+//! JSON forms: metrics as `GET /api/metrics` returns them, fields as
+//! `HopfionSolitonField` serializes. Meshes come back as typed arrays ready
+//! for vertex buffers.
+
+use mmss::visualization::lod::{self, LodMethod};
+use mmss::visualization::mesh::{self, Mesh};
+use mmss::visualization::protocol::{FieldSummary, VisualizationPacket};
+use mmss::{GeometricMetrics, SemanticAnchor};
+use mmss_eqgft::hopfion::HopfionSolitonField;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Points per axis of a packet's field summary unless asked otherwise, as
+/// for `GET /api/visualization/packet`.
+const DEFAULT_FIELD_RESOLUTION: u32 = 16;
+
+fn from_js<T: DeserializeOwned>(what: &str, value: Value) -> Result<T, String> {
+    serde_json::from_value(value).map_err(|err| format!("{what}: {err}"))
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|err| err.to_string())
+}
+
+/// A field whose arrays match its resolution; indexing would panic on any
+/// other.
+fn field(value: Value) -> Result<HopfionSolitonField, String> {
+    let field: HopfionSolitonField = from_js("field", value)?;
+    let n = field.resolution();
+    let points = n.pow(3);
+    if n == 0
+        || field.axis.len() != n
+        || field.q_x.len() != points
+        || field.energy_density.len() != points
+    {
+        return Err(format!(
+            "field: resolution {n} needs {n} axis values and {points} points"
+        ));
+    }
+    Ok(field)
+}
+
+fn field_summary(value: Value) -> Result<FieldSummary, String> {
+    let summary: FieldSummary = from_js("summary", value)?;
+    let n = summary.resolution;
+    if summary.axis.len() != n || summary.energy_density.len() != n.pow(3) {
+        return Err(format!(
+            "summary: resolution {n} needs {n} axis values and {} densities",
+            n.pow(3)
+        ));
+    }
+    Ok(summary)
+}
+
+#[cfg_attr(feature = "node", napi_derive::napi(object))]
+#[derive(Default)]
+pub struct PacketOptions {
+    /// `SemanticAnchor`s, as `POST /api/retrieval/anchors` takes them.
+    pub anchors: Option<Value>,
+    /// A `HopfionSolitonField`, summarized into the packet.
+    pub field: Option<Value>,
+    /// Points per axis of the field summary; 16 by default.
+    pub field_resolution: Option<u32>,
+    /// Colour maps by metric, as `GET /api/visualization/styles` returns them.
+    pub style: Option<Value>,
+}
+
+/// A packet like `GET /api/visualization/packet` serves, stamped now.
+pub fn assemble_packet(
+    sequence: i64,
+    metrics: Value,
+    options: PacketOptions,
+) -> Result<Value, String> {
+    let sequence = u64::try_from(sequence).map_err(|_| "sequence: negative".to_string())?;
+    let metrics: GeometricMetrics = from_js("metrics", metrics)?;
+    let anchors: Vec<SemanticAnchor> = match options.anchors {
+        Some(anchors) => from_js("anchors", anchors)?,
+        None => Vec::new(),
+    };
+    let resolution = options
+        .field_resolution
+        .unwrap_or(DEFAULT_FIELD_RESOLUTION)
+        .max(1) as usize;
+    let summary = options
+        .field
+        .map(field)
+        .transpose()?
+        .map(|field| FieldSummary::downsample(&field, resolution));
+
+    let mut packet = VisualizationPacket::new(sequence, metrics, anchors, summary);
+    if let Some(style) = options.style {
+        packet = packet.with_style(from_js("style", style)?);
+    }
+    to_js(&packet)
+}
+
+/// The energy density of `field` sampled down to at most `max_resolution`
+/// points per axis, as packets carry it.
+pub fn summarize_field(field: Value, max_resolution: u32) -> Result<Value, String> {
+    let field = self::field(field)?;
+    to_js(&FieldSummary::downsample(&field, max_resolution as usize))
+}
+
+/// `field` reduced to at most `target_points` lattice points, by
+/// `"strided"` (default) or `"averaged"`, as
+/// `GET /api/visualization/hopfion-field` serves it.
+pub fn downsample_field(
+    field: Value,
+    target_points: u32,
+    method: Option<String>,
+) -> Result<Value, String> {
+    let field = self::field(field)?;
+    let method: LodMethod = match method {
+        Some(method) => from_js("method", Value::String(method))?,
+        None => LodMethod::default(),
+    };
+    to_js(&lod::downsample(&field, method, target_points as usize))
+}
+
+/// The surface where the energy density of a field summary equals
+/// `iso_value`, as `GET /api/visualization/hopfion-field/mesh` extracts it.
+pub fn isosurface(summary: Value, iso_value: f64) -> Result<Mesh, String> {
+    Ok(mesh::isosurface(&field_summary(summary)?, iso_value))
+}
+
+#[cfg(feature = "node")]
+mod node {
+    use super::*;
+    use napi::bindgen_prelude::{Float32Array, Uint32Array};
+    use napi::{Error, Result, Status};
+    use napi_derive::napi;
+
+    /// Inputs the Rust side rejects, e.g. a field whose arrays do not
+    /// match its resolution.
+    fn invalid(message: String) -> Error {
+        Error::new(Status::InvalidArg, message)
+    }
+
+    #[napi(js_name = "assemblePacket")]
+    pub fn js_assemble_packet(
+        sequence: i64,
+        metrics: Value,
+        options: Option<PacketOptions>,
+    ) -> Result<Value> {
+        assemble_packet(sequence, metrics, options.unwrap_or_default()).map_err(invalid)
+    }
+
+    #[napi(js_name = "summarizeField")]
+    pub fn js_summarize_field(field: Value, max_resolution: u32) -> Result<Value> {
+        summarize_field(field, max_resolution).map_err(invalid)
+    }
+
+    #[napi(js_name = "downsampleField")]
+    pub fn js_downsample_field(
+        field: Value,
+        target_points: u32,
+        method: Option<String>,
+    ) -> Result<Value> {
+        downsample_field(field, target_points, method).map_err(invalid)
+    }
+
+    #[napi(object)]
+    pub struct MeshBuffers {
+        pub iso_value: f64,
+        /// `x, y, z` per vertex.
+        pub vertices: Float32Array,
+        /// Unit normals per vertex, towards lower values.
+        pub normals: Float32Array,
+        /// Three per triangle, counter-clockwise seen from the normals' side.
+        pub indices: Uint32Array,
+    }
+
+    #[napi(js_name = "isosurface")]
+    pub fn js_isosurface(summary: Value, iso_value: f64) -> Result<MeshBuffers> {
+        let mesh = isosurface(summary, iso_value).map_err(invalid)?;
+        Ok(MeshBuffers {
+            iso_value: mesh.iso_value,
+            vertices: Float32Array::new(mesh.vertices.into_iter().flatten().collect()),
+            normals: Float32Array::new(mesh.normals.into_iter().flatten().collect()),
+            indices: Uint32Array::new(mesh.indices),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mmss::core::semantic_task_processor::SemanticTaskProcessor;
+    use mmss_eqgft::hopfion::{generate_hopfion_soliton_field, HopfionConfig};
+    use serde_json::json;
+
+    fn field_value(resolution: usize) -> Value {
+        let field = generate_hopfion_soliton_field(&HopfionConfig {
+            resolution,
+            ..HopfionConfig::default()
+        })
+        .unwrap();
+        serde_json::to_value(field).unwrap()
+    }
+
+    #[test]
+    fn test_packets_summarize_the_field_they_are_given() {
+        let metrics = serde_json::to_value(SemanticTaskProcessor::new().get_metrics()).unwrap();
+        let options = PacketOptions {
+            field: Some(field_value(12)),
+            field_resolution: Some(4),
+            ..PacketOptions::default()
+        };
+        let packet = assemble_packet(7, metrics.clone(), options).unwrap();
+        assert_eq!(packet["sequence"], 7);
+        assert_eq!(packet["metrics"], metrics);
+        assert_eq!(packet["field"]["resolution"], 4);
+        assert_eq!(packet["field"]["source_resolution"], 12);
+        assert_eq!(
+            packet["field"]["energy_density"].as_array().unwrap().len(),
+            64
+        );
+
+        let bare = assemble_packet(0, metrics.clone(), PacketOptions::default()).unwrap();
+        assert!(bare["field"].is_null());
+        assert!(assemble_packet(-1, metrics, PacketOptions::default()).is_err());
+        assert!(assemble_packet(0, json!({}), PacketOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_fields_reduce_to_meshes() {
+        let summary = summarize_field(field_value(12), 6).unwrap();
+        assert_eq!(summary["resolution"], 6);
+        let densities: Vec<f64> =
+            serde_json::from_value(summary["energy_density"].clone()).unwrap();
+        let (low, high) = densities
+            .iter()
+            .fold((f64::MAX, f64::MIN), |(lo, hi), &d| (lo.min(d), hi.max(d)));
+        let mesh = isosurface(summary, (low + high) / 2.0).unwrap();
+        assert!(!mesh.indices.is_empty());
+        assert_eq!(mesh.indices.len() % 3, 0);
+        assert_eq!(mesh.normals.len(), mesh.vertices.len());
+        assert!(mesh
+            .indices
+            .iter()
+            .all(|&i| (i as usize) < mesh.vertices.len()));
+
+        for method in [None, Some("averaged".to_string())] {
+            let reduced = downsample_field(field_value(12), 216, method).unwrap();
+            assert!(reduced["q_x"].as_array().unwrap().len() <= 216);
+        }
+        assert!(downsample_field(field_value(4), 8, Some("cubic".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_malformed_fields_are_rejected() {
+        let mut field = field_value(4);
+        field["q_x"].as_array_mut().unwrap().pop();
+        assert!(summarize_field(field, 2).is_err());
+        let summary = json!({
+            "resolution": 2,
+            "source_resolution": 4,
+            "stride": 2,
+            "axis": [0.0, 1.0],
+            "energy_density": [0.0],
+            "max_energy_density": 0.0,
+            "total_energy": 0.0,
+        });
+        assert!(isosurface(summary, 0.5).is_err());
+    }
+}
@@ -0,0 +1,97 @@
+//! Interface between the MMSS server and its plugins: shared libraries,
+//! loaded from `MMSS_PLUGIN_DIR` at startup, that add geometric operators,
+//! metric rules and export formats without living in the server's tree.
+//!
+//! A plugin is a `cdylib` returning a [`PluginModuleRef`] from a function
+//! marked `#[export_root_module]`; `mmss-plugin-example` is a complete one.
+//! `abi_stable` checks the module's layout when the server loads it, so a
+//! plugin built against an incompatible version of this crate is refused
+//! instead of crashing the server.
+//!
+//! Values cross the boundary as JSON text: metrics are serialized like
+//! `GET /api/metrics` returns them, records like the JSONL export writes
+//! them. Metrics gaining fields therefore never changes the ABI.
+//!
+//! The functions run on the server's worker threads, possibly concurrently,
+//! and must not panic: a panic cannot unwind into the server and aborts it.
+
+use abi_stable::library::RootModule;
+use abi_stable::sabi_types::VersionStrings;
+use abi_stable::std_types::{RResult, RStr, RString, RVec};
+use abi_stable::{declare_root_module_statics, package_version_strings, StableAbi};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// An export format a plugin writes records in.
+#[repr(C)]
+#[derive(StableAbi, Debug, Clone, PartialEq)]
+pub struct ExportFormatSpec {
+    /// Requested as `"format": "<name>"`; must not clash with a built-in
+    /// format or another plugin's.
+    pub name: RString,
+    /// File extension, without the dot.
+    pub extension: RString,
+    pub content_type: RString,
+}
+
+/// What a plugin provides. Names must be unique across all plugins.
+#[repr(C)]
+#[derive(StableAbi, Debug, Clone, PartialEq)]
+pub struct PluginManifest {
+    pub name: RString,
+    pub version: RString,
+    /// Run by tasks with the `PluginOperator` operator and
+    /// `"operator": "<name>"` among their parameters.
+    pub operators: RVec<RString>,
+    /// Registered as metric rules in every namespace.
+    pub rules: RVec<RString>,
+    pub export_formats: RVec<ExportFormatSpec>,
+}
+
+/// The root module every plugin exports. Fields may be added after
+/// `encode_records` in later versions; plugins built before then keep
+/// loading.
+#[repr(C)]
+#[derive(StableAbi)]
+#[sabi(kind(Prefix(prefix_ref = PluginModuleRef)))]
+#[sabi(missing_field(panic))]
+pub struct PluginModule {
+    pub manifest: extern "C" fn() -> PluginManifest,
+    /// Apply `operator` to an [`OperatorRequest`], answering an
+    /// [`OperatorResponse`] or an error message.
+    pub apply_operator:
+        extern "C" fn(operator: RStr<'_>, request: RStr<'_>) -> RResult<RString, RString>,
+    /// Apply `rule` to metrics, answering the updated metrics.
+    pub apply_rule: extern "C" fn(rule: RStr<'_>, metrics: RStr<'_>) -> RResult<RString, RString>,
+    /// Encode a JSON array of records in `format`.
+    #[sabi(last_prefix_field)]
+    pub encode_records:
+        extern "C" fn(format: RStr<'_>, records: RStr<'_>) -> RResult<RVec<u8>, RString>,
+}
+
+impl RootModule for PluginModuleRef {
+    declare_root_module_statics! {PluginModuleRef}
+
+    const BASE_NAME: &'static str = "mmss_plugin";
+    const NAME: &'static str = "mmss_plugin";
+    const VERSION_STRINGS: VersionStrings = package_version_strings!();
+}
+
+/// What `apply_operator` receives.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperatorRequest {
+    /// The task's parameters, `operator` included.
+    pub parameters: Value,
+    /// The metrics before the operator runs.
+    pub metrics: Value,
+}
+
+/// What `apply_operator` answers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperatorResponse {
+    /// The metrics after the operator ran.
+    pub metrics: Value,
+    /// Structured result, returned with the task like EQGFT operators'.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+}
@@ -0,0 +1,151 @@
+//! Example MMSS plugin, and a template for new ones. Provides:
+//!
+//! - the `HarmonicDamping` operator, damping the oscillator quality factor
+//!   by `exp(-gamma * t)`;
+//! - the `stability_floor` rule, keeping `s_geometric` at 0.01 or above;
+//! - the `tsv` export format.
+//!
+//! Build it with `cargo build -p mmss-plugin-example --release` and copy
+//! the library from `target/release` into `MMSS_PLUGIN_DIR`.
+
+use abi_stable::export_root_module;
+use abi_stable::prefix_type::PrefixTypeTrait;
+use abi_stable::std_types::{RResult, RStr, RString, RVec};
+use mmss_plugin_api::{
+    ExportFormatSpec, OperatorRequest, OperatorResponse, PluginManifest, PluginModule,
+    PluginModuleRef,
+};
+use serde_json::{json, Value};
+
+const STABILITY_FLOOR: f64 = 0.01;
+
+#[export_root_module]
+pub fn get_library() -> PluginModuleRef {
+    PluginModule {
+        manifest,
+        apply_operator,
+        apply_rule,
+        encode_records,
+    }
+    .leak_into_prefix()
+}
+
+extern "C" fn manifest() -> PluginManifest {
+    PluginManifest {
+        name: "example".into(),
+        version: env!("CARGO_PKG_VERSION").into(),
+        operators: RVec::from(vec![RString::from("HarmonicDamping")]),
+        rules: RVec::from(vec![RString::from("stability_floor")]),
+        export_formats: RVec::from(vec![ExportFormatSpec {
+            name: "tsv".into(),
+            extension: "tsv".into(),
+            content_type: "text/tab-separated-values".into(),
+        }]),
+    }
+}
+
+extern "C" fn apply_operator(operator: RStr<'_>, request: RStr<'_>) -> RResult<RString, RString> {
+    answer(match operator.as_str() {
+        "HarmonicDamping" => harmonic_damping(request.as_str()),
+        other => Err(format!("unknown operator {other}")),
+    })
+}
+
+extern "C" fn apply_rule(rule: RStr<'_>, metrics: RStr<'_>) -> RResult<RString, RString> {
+    answer(match rule.as_str() {
+        "stability_floor" => stability_floor(metrics.as_str()),
+        other => Err(format!("unknown rule {other}")),
+    })
+}
+
+extern "C" fn encode_records(format: RStr<'_>, records: RStr<'_>) -> RResult<RVec<u8>, RString> {
+    let encoded = match format.as_str() {
+        "tsv" => tsv(records.as_str()),
+        other => Err(format!("unknown format {other}")),
+    };
+    match encoded {
+        Ok(bytes) => RResult::ROk(bytes.into()),
+        Err(e) => RResult::RErr(e.into()),
+    }
+}
+
+fn answer(result: Result<String, String>) -> RResult<RString, RString> {
+    match result {
+        Ok(text) => RResult::ROk(text.into()),
+        Err(e) => RResult::RErr(e.into()),
+    }
+}
+
+/// `q_oscillator *= exp(-gamma * t)`, `gamma` and `t` defaulting to 1.
+fn harmonic_damping(request: &str) -> Result<String, String> {
+    let OperatorRequest {
+        parameters,
+        mut metrics,
+    } = serde_json::from_str(request).map_err(|e| e.to_string())?;
+    let number = |name: &str| parameters.get(name).and_then(Value::as_f64).unwrap_or(1.0);
+    let (gamma, t) = (number("gamma"), number("t"));
+    if gamma < 0.0 {
+        return Err("gamma must not be negative".to_string());
+    }
+
+    let factor = (-gamma * t).exp();
+    let q = metrics["q_oscillator"].as_f64().unwrap_or(0.0);
+    metrics["q_oscillator"] = json!(q * factor);
+    let response = OperatorResponse {
+        metrics,
+        output: Some(json!({ "damping_factor": factor })),
+    };
+    serde_json::to_string(&response).map_err(|e| e.to_string())
+}
+
+fn stability_floor(metrics: &str) -> Result<String, String> {
+    let mut metrics: Value = serde_json::from_str(metrics).map_err(|e| e.to_string())?;
+    let s = metrics["s_geometric"].as_f64().unwrap_or(0.0);
+    metrics["s_geometric"] = json!(s.max(STABILITY_FLOOR));
+    Ok(metrics.to_string())
+}
+
+/// Header line, then `id`, `kind`, `timestamp` and the payload as JSON.
+fn tsv(records: &str) -> Result<Vec<u8>, String> {
+    let records: Vec<Value> = serde_json::from_str(records).map_err(|e| e.to_string())?;
+    let mut out = String::from("id\tkind\ttimestamp\tpayload\n");
+    for record in &records {
+        out.push_str(&format!(
+            "{}\t{}\t{}\t{}\n",
+            record["id"],
+            record["kind"].as_str().unwrap_or_default(),
+            record["timestamp"],
+            record["payload"]
+        ));
+    }
+    Ok(out.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_harmonic_damping() {
+        let request = json!({
+            "parameters": { "operator": "HarmonicDamping", "gamma": 0.5, "t": 2.0 },
+            "metrics": { "q_oscillator": 2.0, "s_geometric": 0.5 },
+        });
+        let response: OperatorResponse =
+            serde_json::from_str(&harmonic_damping(&request.to_string()).unwrap()).unwrap();
+        let expected = 2.0 * (-1.0f64).exp();
+        assert!((response.metrics["q_oscillator"].as_f64().unwrap() - expected).abs() < 1e-12);
+        assert_eq!(response.metrics["s_geometric"], 0.5);
+        assert!(harmonic_damping(
+            &json!({ "parameters": { "gamma": -1.0 }, "metrics": {} }).to_string()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_tsv() {
+        let records = json!([{ "id": 1, "kind": "cpu", "timestamp": 5, "payload": { "v": 1 } }]);
+        let tsv = String::from_utf8(tsv(&records.to_string()).unwrap()).unwrap();
+        assert_eq!(tsv, "id\tkind\ttimestamp\tpayload\n1\tcpu\t5\t{\"v\":1}\n");
+    }
+}
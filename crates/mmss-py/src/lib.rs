@@ -0,0 +1,375 @@
+//! The `mmss_py` Python module: the task processor and the emergence
+//! cascade of the server, run in-process so notebooks can drive
+//! simulations without HTTP.
+//!
+//! Build it with `maturin develop -m crates/mmss-py/pyproject.toml`.
+//! Commands and parameters are passed as dicts shaped like the JSON the API
+//! takes; metrics come back as flat records (see [`records`]), so that
+//! `pandas.DataFrame(records)` gives one column per metric. Long calls
+//! release the GIL.
+
+// pyo3 0.22's #[pymethods] wrap every PyResult in a conversion to itself
+#![allow(clippy::useless_conversion)]
+
+pub mod records;
+
+use mmss::core::emergence_logic::EmergenceLogic;
+use mmss::core::script_policy::ScriptPolicy;
+use mmss::core::semantic_task_processor::SemanticTaskProcessor;
+use mmss::state::PhysicalConstants;
+use mmss::{GeometricMetrics, GeometricOperator, GeometricTaskCommand, TaskExecutionResult};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pythonize::{depythonize, pythonize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+create_exception!(
+    mmss_py,
+    MmssError,
+    PyRuntimeError,
+    "Raised when the processor rejects a call, e.g. for an unknown task."
+);
+
+fn mmss_error(err: mmss::Error) -> PyErr {
+    MmssError::new_err(err.to_string())
+}
+
+fn operator(name: &str) -> PyResult<GeometricOperator> {
+    serde_json::from_value(Value::String(name.to_string()))
+        .map_err(|_| PyValueError::new_err(format!("unknown operator {name}")))
+}
+
+fn task_id(raw: &str) -> PyResult<Uuid> {
+    Uuid::parse_str(raw).map_err(|err| PyValueError::new_err(format!("task id: {err}")))
+}
+
+fn json_value(obj: Option<&Bound<'_, PyAny>>) -> PyResult<Value> {
+    match obj {
+        Some(obj) if !obj.is_none() => Ok(depythonize(obj)?),
+        _ => Ok(json!({})),
+    }
+}
+
+/// Constants from a dict shaped like the `[constants]` config section.
+fn constants(obj: Option<&Bound<'_, PyAny>>) -> PyResult<Option<PhysicalConstants>> {
+    let Some(obj) = obj.filter(|obj| !obj.is_none()) else {
+        return Ok(None);
+    };
+    let constants: PhysicalConstants = depythonize(obj)?;
+    match constants.invalid().as_slice() {
+        [] => Ok(Some(constants)),
+        invalid => Err(PyValueError::new_err(format!(
+            "not positive numbers: {}",
+            invalid.join(", ")
+        ))),
+    }
+}
+
+/// Metrics from either a flat record or the nested form the API returns.
+fn metrics(obj: &Bound<'_, PyAny>) -> PyResult<GeometricMetrics> {
+    let value: Value = depythonize(obj)?;
+    if value.get("custom_metrics").is_some() {
+        return serde_json::from_value(value).map_err(|err| PyValueError::new_err(err.to_string()));
+    }
+    let record: BTreeMap<String, f64> =
+        serde_json::from_value(value).map_err(|err| PyValueError::new_err(err.to_string()))?;
+    records::from_record(&record).map_err(PyValueError::new_err)
+}
+
+fn record<'py>(py: Python<'py>, metrics: &GeometricMetrics) -> PyResult<Bound<'py, PyAny>> {
+    Ok(pythonize(py, &records::to_record(metrics))?)
+}
+
+/// A task result, with its metrics as a record.
+fn execution_result<'py>(
+    py: Python<'py>,
+    result: &TaskExecutionResult,
+) -> PyResult<Bound<'py, PyAny>> {
+    let mut value =
+        serde_json::to_value(result).map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+    value["metrics"] = json!(records::to_record(&result.metrics));
+    Ok(pythonize(py, &value)?)
+}
+
+/// A task for `SemanticTaskProcessor`, as `POST /api/tasks` takes it.
+#[pyclass(name = "GeometricTaskCommand", module = "mmss_py")]
+#[derive(Clone)]
+pub struct PyTaskCommand {
+    inner: GeometricTaskCommand,
+}
+
+#[pymethods]
+impl PyTaskCommand {
+    #[new]
+    #[pyo3(signature = (
+        task_name,
+        geometric_operator,
+        parameters = None,
+        target_module = "notebook".to_string(),
+        expected_output_metric = "v_geometric".to_string(),
+    ))]
+    fn new(
+        task_name: String,
+        geometric_operator: &str,
+        parameters: Option<&Bound<'_, PyAny>>,
+        target_module: String,
+        expected_output_metric: String,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            inner: GeometricTaskCommand {
+                task_name,
+                geometric_operator: operator(geometric_operator)?,
+                target_module,
+                parameters: json_value(parameters)?,
+                expected_output_metric,
+                task_id: None,
+            },
+        })
+    }
+
+    /// A command from its JSON form.
+    #[staticmethod]
+    fn from_dict(command: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self {
+            inner: depythonize(command)?,
+        })
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.inner)?)
+    }
+
+    #[getter]
+    fn task_name(&self) -> &str {
+        &self.inner.task_name
+    }
+
+    #[getter]
+    fn geometric_operator(&self) -> String {
+        format!("{:?}", self.inner.geometric_operator)
+    }
+
+    #[getter]
+    fn parameters<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.inner.parameters)?)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "GeometricTaskCommand(task_name={:?}, geometric_operator={:?}, parameters={})",
+            self.inner.task_name, self.inner.geometric_operator, self.inner.parameters
+        )
+    }
+}
+
+/// A `GeometricTaskCommand`, or a dict in its JSON form.
+fn command(obj: &Bound<'_, PyAny>) -> PyResult<GeometricTaskCommand> {
+    match obj.extract::<PyTaskCommand>() {
+        Ok(command) => Ok(command.inner),
+        Err(_) => Ok(depythonize(obj)?),
+    }
+}
+
+/// The SYS7-SYS1 cascade on its own, without tasks or scripts.
+#[pyclass(name = "EmergenceLogic", module = "mmss_py")]
+pub struct PyEmergenceLogic {
+    inner: EmergenceLogic,
+}
+
+#[pymethods]
+impl PyEmergenceLogic {
+    /// SI constants unless `constants` overrides some of them.
+    #[new]
+    #[pyo3(signature = (constants = None))]
+    fn new(constants: Option<&Bound<'_, PyAny>>) -> PyResult<Self> {
+        let mut inner = EmergenceLogic::new(None);
+        if let Some(constants) = self::constants(constants)? {
+            inner = inner.with_constants(constants);
+        }
+        Ok(Self { inner })
+    }
+
+    /// Apply `operator` with `params` and return the metrics after it.
+    #[pyo3(signature = (operator, params = None))]
+    fn apply_operator<'py>(
+        &mut self,
+        py: Python<'py>,
+        operator: &str,
+        params: Option<&Bound<'py, PyAny>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let op = self::operator(operator)?;
+        let params = json_value(params)?;
+        let inner = &mut self.inner;
+        let metrics = py.allow_threads(|| inner.apply_operator(op, &params).clone());
+        record(py, &metrics)
+    }
+
+    /// Structured result of the last operator, for operators that have one.
+    fn take_output<'py>(&mut self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyAny>>> {
+        self.inner
+            .take_output()
+            .map(|output| Ok(pythonize(py, &output)?))
+            .transpose()
+    }
+
+    fn metrics<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        record(py, self.inner.metrics())
+    }
+
+    /// Continue from `metrics`, a record or the nested API form.
+    fn set_metrics(&mut self, metrics: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.inner.adopt_metrics(self::metrics(metrics)?);
+        Ok(())
+    }
+
+    /// Back to the baseline metrics, dropping the Hopfion field.
+    fn reset(&mut self) {
+        self.inner.reset(true, true);
+    }
+}
+
+/// The server's task processor: queued tasks, script policy and metrics,
+/// without the HTTP API around it.
+#[pyclass(name = "SemanticTaskProcessor", module = "mmss_py")]
+pub struct PyTaskProcessor {
+    inner: SemanticTaskProcessor,
+}
+
+#[pymethods]
+impl PyTaskProcessor {
+    /// `script_policy` is `"reject"`, `"require_approval"` or `"allow"`;
+    /// `MMSS_SCRIPT_POLICY` decides when it is not given.
+    #[new]
+    #[pyo3(signature = (script_policy = None, constants = None))]
+    fn new(script_policy: Option<&str>, constants: Option<&Bound<'_, PyAny>>) -> PyResult<Self> {
+        let mut inner = match script_policy {
+            Some(policy) => {
+                let policy: ScriptPolicy = serde_json::from_value(json!(policy)).map_err(|_| {
+                    PyValueError::new_err(format!("unknown script policy {policy}"))
+                })?;
+                SemanticTaskProcessor::with_script_policy(policy)
+            }
+            None => SemanticTaskProcessor::new(),
+        };
+        if let Some(constants) = self::constants(constants)? {
+            inner = inner.with_constants(constants);
+        }
+        Ok(Self { inner })
+    }
+
+    /// Queue `command` and return its task id.
+    fn submit_task(&self, command: &Bound<'_, PyAny>) -> PyResult<String> {
+        let command = self::command(command)?;
+        let id = self.inner.submit_task(command).map_err(mmss_error)?;
+        Ok(id.to_string())
+    }
+
+    fn execute_task<'py>(&self, py: Python<'py>, task_id: &str) -> PyResult<Bound<'py, PyAny>> {
+        let id = self::task_id(task_id)?;
+        let inner = &self.inner;
+        let result = py
+            .allow_threads(|| inner.execute_task(id))
+            .map_err(mmss_error)?;
+        execution_result(py, &result)
+    }
+
+    /// Submit `command` and execute it at once.
+    fn run<'py>(
+        &self,
+        py: Python<'py>,
+        command: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let command = self::command(command)?;
+        let inner = &self.inner;
+        let result = py
+            .allow_threads(|| {
+                inner
+                    .submit_task(command)
+                    .and_then(|id| inner.execute_task(id))
+            })
+            .map_err(mmss_error)?;
+        execution_result(py, &result)
+    }
+
+    /// Metrics each command would produce from the current ones, run in
+    /// isolation without changing them.
+    fn evaluate_isolated<'py>(
+        &self,
+        py: Python<'py>,
+        commands: Vec<Bound<'py, PyAny>>,
+    ) -> PyResult<Vec<Bound<'py, PyAny>>> {
+        let commands = commands
+            .iter()
+            .map(self::command)
+            .collect::<PyResult<Vec<_>>>()?;
+        let inner = &self.inner;
+        let evaluated = py
+            .allow_threads(|| inner.evaluate_isolated(&commands))
+            .map_err(mmss_error)?;
+        evaluated
+            .iter()
+            .map(|metrics| record(py, metrics))
+            .collect()
+    }
+
+    fn approve_task(&self, task_id: &str) -> PyResult<()> {
+        self.inner
+            .approve_task(self::task_id(task_id)?)
+            .map_err(mmss_error)
+    }
+
+    /// The task's status as the API returns it, e.g. `"Pending"` or
+    /// `{"Failed": "..."}`.
+    fn task_status<'py>(&self, py: Python<'py>, task_id: &str) -> PyResult<Bound<'py, PyAny>> {
+        let status = self
+            .inner
+            .get_task_status(self::task_id(task_id)?)
+            .map_err(mmss_error)?;
+        Ok(pythonize(py, &status)?)
+    }
+
+    /// `(task_id, status)` for every task.
+    fn list_tasks<'py>(&self, py: Python<'py>) -> PyResult<Vec<(String, Bound<'py, PyAny>)>> {
+        let tasks = self.inner.list_tasks().map_err(mmss_error)?;
+        tasks
+            .into_iter()
+            .map(|(id, status)| Ok((id.to_string(), pythonize(py, &status)?)))
+            .collect()
+    }
+
+    fn metrics<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        record(py, &self.inner.metrics())
+    }
+}
+
+/// Nested metrics, as `GET /api/metrics` returns them, as a flat record.
+#[pyfunction]
+fn metrics_to_record<'py>(
+    py: Python<'py>,
+    metrics: &Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyAny>> {
+    record(py, &self::metrics(metrics)?)
+}
+
+/// A flat record as nested metrics, e.g. to restore a server from it.
+#[pyfunction]
+fn metrics_from_record<'py>(
+    py: Python<'py>,
+    record: &Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyAny>> {
+    Ok(pythonize(py, &metrics(record)?)?)
+}
+
+#[pymodule]
+fn mmss_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("MmssError", m.py().get_type_bound::<MmssError>())?;
+    m.add_class::<PyTaskCommand>()?;
+    m.add_class::<PyEmergenceLogic>()?;
+    m.add_class::<PyTaskProcessor>()?;
+    m.add_function(wrap_pyfunction!(metrics_to_record, m)?)?;
+    m.add_function(wrap_pyfunction!(metrics_from_record, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,86 @@
+//! `GeometricMetrics` as flat records: every metric, custom ones included,
+//! under its own name, so a list of them is a `pandas.DataFrame`.
+
+use mmss::GeometricMetrics;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Metrics stored as fields of `GeometricMetrics`; any other name in a
+/// record is a custom metric.
+pub const BUILTIN_METRICS: [&str; 8] = [
+    "v_geometric",
+    "s_geometric",
+    "q_oscillator",
+    "quaternion_coherence",
+    "emergent_electron_mass",
+    "fine_structure_constant",
+    "zitterbewegung_entropy",
+    "topological_winding",
+];
+
+/// `metrics` as one record. A custom metric named like a built-in one is
+/// dropped, as it could not be told apart on the way back.
+pub fn to_record(metrics: &GeometricMetrics) -> BTreeMap<String, f64> {
+    let mut record: BTreeMap<String, f64> = metrics
+        .custom_metrics
+        .iter()
+        .filter(|(name, _)| !BUILTIN_METRICS.contains(&name.as_str()))
+        .map(|(name, value)| (name.clone(), *value))
+        .collect();
+    record.extend(
+        [
+            ("v_geometric", metrics.v_geometric),
+            ("s_geometric", metrics.s_geometric),
+            ("q_oscillator", metrics.q_oscillator),
+            ("quaternion_coherence", metrics.quaternion_coherence),
+            ("emergent_electron_mass", metrics.emergent_electron_mass),
+            ("fine_structure_constant", metrics.fine_structure_constant),
+            ("zitterbewegung_entropy", metrics.zitterbewegung_entropy),
+            ("topological_winding", metrics.topological_winding),
+        ]
+        .map(|(name, value)| (name.to_string(), value)),
+    );
+    record
+}
+
+/// Metrics from a record made by `to_record`, or by hand. `v_geometric`,
+/// `s_geometric` and `q_oscillator` are required, the other built-in
+/// metrics default to 0.
+pub fn from_record(record: &BTreeMap<String, f64>) -> Result<GeometricMetrics, String> {
+    let (builtin, custom): (Vec<_>, Vec<_>) = record
+        .iter()
+        .partition(|(name, _)| BUILTIN_METRICS.contains(&name.as_str()));
+    let mut fields: Map<String, Value> = builtin
+        .into_iter()
+        .map(|(name, value)| (name.clone(), Value::from(*value)))
+        .collect();
+    let custom: Map<String, Value> = custom
+        .into_iter()
+        .map(|(name, value)| (name.clone(), Value::from(*value)))
+        .collect();
+    fields.insert("custom_metrics".to_string(), Value::Object(custom));
+    serde_json::from_value(Value::Object(fields)).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_round_trip() {
+        let mut record: BTreeMap<String, f64> = BUILTIN_METRICS
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.to_string(), i as f64))
+            .collect();
+        record.insert("anchor:origin".to_string(), 9.5);
+
+        let metrics = from_record(&record).unwrap();
+        assert_eq!(metrics.q_oscillator, 2.0);
+        assert_eq!(metrics.custom_metrics["anchor:origin"], 9.5);
+        assert_eq!(to_record(&metrics), record);
+
+        record.remove("v_geometric");
+        assert!(from_record(&record).unwrap_err().contains("v_geometric"));
+    }
+}
@@ -0,0 +1,390 @@
+//! Audit trail of every provider call made by the gateway, for cost
+//! attribution and prompt debugging.
+
+use crate::api::llm_provider::{ChatMessage, LlmProvider};
+use crate::api::usage::{mask_key, TokenUsage, UsageScope};
+use crate::core::error::{Error, Result};
+use arrow2::{
+    array::{Int64Array, UInt64Array, Utf8Array},
+    chunk::Chunk,
+    datatypes::{DataType, Field, Schema},
+    io::ipc::write::{FileWriter, WriteOptions},
+};
+use chrono::{DateTime, Utc};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+const DEFAULT_MEMORY_LIMIT: usize = 1000;
+const REDACTED: &str = "[REDACTED]";
+/// Environment variables whose values must never appear in the log.
+const SECRET_MARKERS: &[&str] = &["KEY", "TOKEN", "SECRET", "PASSWORD"];
+/// Shorter values would redact ordinary words.
+const MIN_SECRET_LEN: usize = 8;
+
+/// One request/response pair with the provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    /// Masked caller key.
+    pub api_key: String,
+    pub campaign_id: Option<Uuid>,
+    pub provider: String,
+    pub model: String,
+    /// 0 for the initial request, then one per schema repair.
+    pub repair_attempt: usize,
+    pub latency_ms: u64,
+    pub usage: TokenUsage,
+    pub messages: Vec<AuditMessage>,
+    pub response: Option<String>,
+    pub error: Option<String>,
+    /// Task planned by this call, set once the response validated.
+    pub task_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditMessage {
+    pub role: String,
+    pub content: String,
+}
+
+/// Filter for [`AuditLog::query`].
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditQuery {
+    pub api_key: Option<String>,
+    pub campaign_id: Option<Uuid>,
+    pub since: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+/// Recent records kept in memory and appended as JSON lines to
+/// `MMSS_LLM_AUDIT_LOG` when set, so history survives restarts.
+pub struct AuditLog {
+    path: Option<PathBuf>,
+    memory_limit: usize,
+    secrets: Vec<String>,
+    records: Mutex<VecDeque<AuditRecord>>,
+}
+
+impl AuditLog {
+    pub fn from_env() -> Self {
+        Self::at(
+            env::var("MMSS_LLM_AUDIT_LOG").ok().map(PathBuf::from),
+            std::iter::empty(),
+        )
+    }
+
+    /// Log at `path`, redacting `secrets` as well as any secret-looking
+    /// environment variable.
+    pub fn at(path: Option<PathBuf>, secrets: impl IntoIterator<Item = String>) -> Self {
+        let mut secrets: Vec<String> = secrets
+            .into_iter()
+            .filter(|value| value.len() >= MIN_SECRET_LEN)
+            .collect();
+        secrets.extend(
+            env::vars()
+                .filter(|(name, value)| {
+                    SECRET_MARKERS.iter().any(|marker| name.contains(marker))
+                        && value.len() >= MIN_SECRET_LEN
+                })
+                .map(|(_, value)| value),
+        );
+        let memory_limit = env::var("MMSS_LLM_AUDIT_MEMORY")
+            .ok()
+            .and_then(|raw| raw.parse().ok())
+            .unwrap_or(DEFAULT_MEMORY_LIMIT);
+        Self::new(path, memory_limit, secrets)
+    }
+
+    pub fn new(path: Option<PathBuf>, memory_limit: usize, secrets: Vec<String>) -> Self {
+        let log = Self {
+            path,
+            memory_limit: memory_limit.max(1),
+            secrets,
+            records: Mutex::new(VecDeque::new()),
+        };
+        if let Err(err) = log.load() {
+            warn!("Failed to load LLM audit log: {}", err);
+        }
+        log
+    }
+
+    /// Redact and store a record; persistence failures are logged, not returned,
+    /// so auditing never fails a planning call.
+    pub fn record(
+        &self,
+        scope: &UsageScope,
+        provider: &dyn LlmProvider,
+        repair_attempt: usize,
+        latency_ms: u64,
+        messages: &[ChatMessage],
+        outcome: std::result::Result<(&str, TokenUsage), &Error>,
+    ) -> Result<Uuid> {
+        let (response, usage, error) = match outcome {
+            Ok((content, usage)) => (Some(self.redact(content)), usage, None),
+            Err(err) => (
+                None,
+                TokenUsage::default(),
+                Some(self.redact(&err.to_string())),
+            ),
+        };
+        let record = AuditRecord {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            api_key: mask_key(&scope.api_key),
+            campaign_id: scope.campaign_id,
+            provider: provider.name().to_string(),
+            model: provider.model().to_string(),
+            repair_attempt,
+            latency_ms,
+            usage,
+            messages: messages
+                .iter()
+                .map(|m| AuditMessage {
+                    role: m.role.clone(),
+                    content: self.redact(&m.content),
+                })
+                .collect(),
+            response,
+            error,
+            task_id: None,
+        };
+        let id = record.id;
+
+        if let Err(err) = self.append_to_file(&record) {
+            warn!("Failed to persist LLM audit record {}: {}", id, err);
+        }
+        self.push(record)?;
+        Ok(id)
+    }
+
+    /// Attach the task that a successful call produced.
+    pub fn link_task(&self, record_id: Uuid, task_id: Uuid) -> Result<()> {
+        let mut records = self.lock()?;
+        if let Some(record) = records.iter_mut().rev().find(|r| r.id == record_id) {
+            record.task_id = Some(task_id);
+            if let Err(err) = self.append_to_file(record) {
+                warn!("Failed to persist LLM audit record {}: {}", record_id, err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Matching records, newest first.
+    pub fn query(&self, filter: &AuditQuery) -> Result<Vec<AuditRecord>> {
+        let key = filter.api_key.as_deref().map(mask_key);
+        let records = self.lock()?;
+        Ok(records
+            .iter()
+            .rev()
+            .filter(|r| key.as_ref().is_none_or(|key| &r.api_key == key))
+            .filter(|r| {
+                filter
+                    .campaign_id
+                    .is_none_or(|id| r.campaign_id == Some(id))
+            })
+            .filter(|r| filter.since.is_none_or(|since| r.timestamp >= since))
+            .take(filter.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    fn redact(&self, text: &str) -> String {
+        self.secrets.iter().fold(text.to_string(), |text, secret| {
+            text.replace(secret, REDACTED)
+        })
+    }
+
+    fn push(&self, record: AuditRecord) -> Result<()> {
+        let mut records = self.lock()?;
+        records.push_back(record);
+        while records.len() > self.memory_limit {
+            records.pop_front();
+        }
+        Ok(())
+    }
+
+    fn append_to_file(&self, record: &AuditRecord) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+
+    /// Replay the JSON-lines file; later lines for the same ID (task links)
+    /// replace earlier ones.
+    fn load(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !path.exists() {
+            return Ok(());
+        }
+
+        for line in fs::read_to_string(path)?
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+        {
+            let record: AuditRecord = serde_json::from_str(line)?;
+            let mut records = self.lock()?;
+            match records.iter_mut().rev().find(|r| r.id == record.id) {
+                Some(existing) => *existing = record,
+                None => {
+                    drop(records);
+                    self.push(record)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, VecDeque<AuditRecord>>> {
+        self.records.lock().map_err(|e| {
+            error!("Failed to lock LLM audit log: {}", e);
+            Error::TaskExecution("Failed to access LLM audit log".to_string())
+        })
+    }
+}
+
+/// Encode records as an Arrow IPC file; messages are stored as JSON text.
+pub fn records_to_arrow(records: &[AuditRecord]) -> Result<Vec<u8>> {
+    let schema = Schema::from(vec![
+        Field::new("id", DataType::Utf8, false),
+        Field::new("timestamp_ms", DataType::Int64, false),
+        Field::new("api_key", DataType::Utf8, false),
+        Field::new("campaign_id", DataType::Utf8, true),
+        Field::new("provider", DataType::Utf8, false),
+        Field::new("model", DataType::Utf8, false),
+        Field::new("repair_attempt", DataType::UInt64, false),
+        Field::new("latency_ms", DataType::UInt64, false),
+        Field::new("prompt_tokens", DataType::UInt64, false),
+        Field::new("completion_tokens", DataType::UInt64, false),
+        Field::new("total_tokens", DataType::UInt64, false),
+        Field::new("task_id", DataType::Utf8, true),
+        Field::new("error", DataType::Utf8, true),
+        Field::new("messages", DataType::Utf8, false),
+        Field::new("response", DataType::Utf8, true),
+    ]);
+
+    let text = |pick: &dyn Fn(&AuditRecord) -> Option<String>| {
+        Utf8Array::<i32>::from(records.iter().map(pick).collect::<Vec<_>>()).boxed()
+    };
+    let number = |pick: fn(&AuditRecord) -> u64| {
+        UInt64Array::from_vec(records.iter().map(pick).collect()).boxed()
+    };
+    let messages = records
+        .iter()
+        .map(|r| serde_json::to_string(&r.messages).map(Some))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let chunk = Chunk::try_new(vec![
+        text(&|r| Some(r.id.to_string())),
+        Int64Array::from_vec(
+            records
+                .iter()
+                .map(|r| r.timestamp.timestamp_millis())
+                .collect(),
+        )
+        .boxed(),
+        text(&|r| Some(r.api_key.clone())),
+        text(&|r| r.campaign_id.map(|id| id.to_string())),
+        text(&|r| Some(r.provider.clone())),
+        text(&|r| Some(r.model.clone())),
+        number(|r| r.repair_attempt as u64),
+        number(|r| r.latency_ms),
+        number(|r| r.usage.prompt_tokens),
+        number(|r| r.usage.completion_tokens),
+        number(|r| r.usage.total_tokens),
+        text(&|r| r.task_id.map(|id| id.to_string())),
+        text(&|r| r.error.clone()),
+        Utf8Array::<i32>::from(messages).boxed(),
+        text(&|r| r.response.clone()),
+    ])
+    .map_err(arrow_error)?;
+
+    let mut buffer = Vec::new();
+    let mut writer = FileWriter::try_new(
+        &mut buffer,
+        schema,
+        None,
+        WriteOptions { compression: None },
+    )
+    .map_err(arrow_error)?;
+    writer.write(&chunk, None).map_err(arrow_error)?;
+    writer.finish().map_err(arrow_error)?;
+    Ok(buffer)
+}
+
+fn arrow_error(err: arrow2::error::Error) -> Error {
+    Error::TaskExecution(format!("Failed to encode audit log: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock_llm::MockProvider;
+
+    fn scope() -> UsageScope {
+        UsageScope::for_key(Some("client-key-123456"))
+    }
+
+    #[test]
+    fn test_records_are_redacted_and_linked() {
+        let log = AuditLog::new(None, 10, vec!["sk-super-secret".into()]);
+        let messages = [ChatMessage::new("user", "token sk-super-secret please")];
+
+        let id = log
+            .record(
+                &scope(),
+                &MockProvider::new(),
+                0,
+                5,
+                &messages,
+                Ok(("{}", TokenUsage::default())),
+            )
+            .unwrap();
+        let task_id = Uuid::new_v4();
+        log.link_task(id, task_id).unwrap();
+
+        let records = log
+            .query(&AuditQuery {
+                api_key: Some("client-key-123456".into()),
+                ..AuditQuery::default()
+            })
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].messages[0].content, "token [REDACTED] please");
+        assert_eq!(records[0].task_id, Some(task_id));
+        assert_ne!(records[0].api_key, "client-key-123456");
+        assert!(records_to_arrow(&records).unwrap().starts_with(b"ARROW1"));
+    }
+
+    #[test]
+    fn test_log_file_survives_restart() {
+        let path = env::temp_dir().join(format!("mmss-audit-{}.jsonl", Uuid::new_v4()));
+        let log = AuditLog::new(Some(path.clone()), 10, Vec::new());
+        let messages = [ChatMessage::new("user", "hello")];
+        let error = Error::LlmCommunication("timeout".into());
+        let id = log
+            .record(&scope(), &MockProvider::new(), 0, 1, &messages, Err(&error))
+            .unwrap();
+        log.link_task(id, Uuid::new_v4()).unwrap();
+
+        let reloaded = AuditLog::new(Some(path.clone()), 10, Vec::new());
+        let records = reloaded.query(&AuditQuery::default()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert!(records[0].task_id.is_some());
+        assert!(records[0].error.as_deref().unwrap().contains("timeout"));
+    }
+}
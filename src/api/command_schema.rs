@@ -0,0 +1,398 @@
+//! JSON schema of `GeometricTaskCommand` shared by the structured-output
+//! planning mode and the validator that checks model responses.
+
+use serde_json::{json, Map, Value};
+
+/// JSON type accepted for a single operator parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    Number,
+    String,
+    Bool,
+    /// Three-component numeric vector.
+    Vector3,
+    /// Array of JSON objects, e.g. channel definitions.
+    ObjectList,
+}
+
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub kind: ParamKind,
+    pub description: &'static str,
+}
+
+/// Parameters understood by `EmergenceLogic::apply_operator` per operator.
+pub const OPERATOR_PARAMETERS: &[(&str, &[ParamSpec])] = &[
+    (
+        "QuaternionRotation",
+        &[
+            ParamSpec {
+                name: "theta",
+                kind: ParamKind::Number,
+                description: "Rotation angle in radians",
+            },
+            ParamSpec {
+                name: "axis",
+                kind: ParamKind::Vector3,
+                description: "Rotation axis [x, y, z]",
+            },
+        ],
+    ),
+    (
+        "Zitterbewegung",
+        &[ParamSpec {
+            name: "frequency_scale",
+            kind: ParamKind::Number,
+            description: "Multiplier applied to the zitterbewegung frequency",
+        }],
+    ),
+    (
+        "GeometricDerivation",
+        &[ParamSpec {
+            name: "delta",
+            kind: ParamKind::Number,
+            description: "Stability perturbation step",
+        }],
+    ),
+    (
+        "SemanticSynthesis",
+        &[
+            ParamSpec {
+                name: "anchor",
+                kind: ParamKind::String,
+                description: "Semantic anchor name",
+            },
+            ParamSpec {
+                name: "coherence_hint",
+                kind: ParamKind::Number,
+                description: "Expected coherence of the synthesized anchor (0..1)",
+            },
+        ],
+    ),
+    (
+        "CustomPythonScript",
+        &[ParamSpec {
+            name: "script",
+            kind: ParamKind::String,
+            description: "Python source to run; subject to the server's script policy",
+        }],
+    ),
+    (
+        "GenerateHopfionField",
+        &[
+            ParamSpec {
+                name: "hopf_index",
+                kind: ParamKind::Number,
+                description: "Hopf index N_H of the soliton",
+            },
+            ParamSpec {
+                name: "resolution",
+                kind: ParamKind::Number,
+                description: "Lattice points per axis, 2 to 128",
+            },
+            ParamSpec {
+                name: "extent",
+                kind: ParamKind::Number,
+                description: "Half-width of the lattice box",
+            },
+            ParamSpec {
+                name: "scale",
+                kind: ParamKind::Number,
+                description: "Soliton size R",
+            },
+            ParamSpec {
+                name: "backend",
+                kind: ParamKind::String,
+                description: "Compute backend: auto, cpu or gpu",
+            },
+        ],
+    ),
+    (
+        "SimulateEqgftAsymmetry",
+        &[
+            ParamSpec {
+                name: "config",
+                kind: ParamKind::String,
+                description: "Named EQGFT config in MMSS_EQGFT_CONFIG_DIR supplying unset parameters",
+            },
+            ParamSpec {
+                name: "kappa",
+                kind: ParamKind::Number,
+                description: "Coupling kappa in the predicted asymmetry kappa * alpha",
+            },
+            ParamSpec {
+                name: "n_events",
+                kind: ParamKind::Number,
+                description: "Number of simulated decays",
+            },
+            ParamSpec {
+                name: "systematic_error",
+                kind: ParamKind::Number,
+                description: "Absolute systematic uncertainty on the asymmetry",
+            },
+            ParamSpec {
+                name: "seed",
+                kind: ParamKind::Number,
+                description: "RNG seed for a reproducible sample",
+            },
+            ParamSpec {
+                name: "bootstrap_resamples",
+                kind: ParamKind::Number,
+                description: "Bootstrap resamples B for an empirical uncertainty",
+            },
+            ParamSpec {
+                name: "bootstrap_seed",
+                kind: ParamKind::Number,
+                description: "RNG seed of the bootstrap resampling",
+            },
+            ParamSpec {
+                name: "angular_resolution",
+                kind: ParamKind::Number,
+                description: "Detector resolution on the polar angle, radians",
+            },
+            ParamSpec {
+                name: "efficiency",
+                kind: ParamKind::Number,
+                description: "Detection efficiency at cos(theta) = 0",
+            },
+            ParamSpec {
+                name: "acceptance_min",
+                kind: ParamKind::Number,
+                description: "Lower cut on the reconstructed cos(theta)",
+            },
+            ParamSpec {
+                name: "acceptance_max",
+                kind: ParamKind::Number,
+                description: "Upper cut on the reconstructed cos(theta)",
+            },
+        ],
+    ),
+    (
+        "SimulateEqgftKappaScan",
+        &[
+            ParamSpec {
+                name: "config",
+                kind: ParamKind::String,
+                description: "Named EQGFT config in MMSS_EQGFT_CONFIG_DIR supplying unset parameters",
+            },
+            ParamSpec {
+                name: "kappa_min",
+                kind: ParamKind::Number,
+                description: "First coupling of the scan",
+            },
+            ParamSpec {
+                name: "kappa_max",
+                kind: ParamKind::Number,
+                description: "Last coupling of the scan",
+            },
+            ParamSpec {
+                name: "steps",
+                kind: ParamKind::Number,
+                description: "Number of evenly spaced couplings",
+            },
+            ParamSpec {
+                name: "n_events",
+                kind: ParamKind::Number,
+                description: "Simulated decays per coupling",
+            },
+            ParamSpec {
+                name: "systematic_error",
+                kind: ParamKind::Number,
+                description: "Absolute systematic uncertainty on the asymmetry",
+            },
+            ParamSpec {
+                name: "reweight",
+                kind: ParamKind::Bool,
+                description: "Reweight one sample generated at kappa instead of simulating each step",
+            },
+        ],
+    ),
+    (
+        "CombineEqgftChannels",
+        &[
+            ParamSpec {
+                name: "config",
+                kind: ParamKind::String,
+                description: "Named EQGFT config in MMSS_EQGFT_CONFIG_DIR supplying unset parameters",
+            },
+            ParamSpec {
+                name: "kappa",
+                kind: ParamKind::Number,
+                description: "Coupling kappa in the predicted asymmetry kappa * alpha",
+            },
+            ParamSpec {
+                name: "seed",
+                kind: ParamKind::Number,
+                description: "RNG seed for reproducible samples",
+            },
+            ParamSpec {
+                name: "channels",
+                kind: ParamKind::ObjectList,
+                description: "Channels {name, sensitivity, n_events, systematic_error}, sensitivity being the analyzing power",
+            },
+            ParamSpec {
+                name: "systematic_correlation",
+                kind: ParamKind::Number,
+                description: "Correlation of systematic errors between channels, in [-1, 1]",
+            },
+        ],
+    ),
+    (
+        "PluginOperator",
+        &[ParamSpec {
+            name: "operator",
+            kind: ParamKind::String,
+            description: "Operator of a loaded plugin, as listed by GET /plugins; other parameters go to the plugin",
+        }],
+    ),
+];
+
+fn operator_names() -> Vec<&'static str> {
+    OPERATOR_PARAMETERS.iter().map(|(name, _)| *name).collect()
+}
+
+fn param_schema(kind: ParamKind, description: &str) -> Value {
+    match kind {
+        ParamKind::Number => json!({ "type": "number", "description": description }),
+        ParamKind::String => json!({ "type": "string", "description": description }),
+        ParamKind::Bool => json!({ "type": "boolean", "description": description }),
+        ParamKind::Vector3 => json!({
+            "type": "array",
+            "items": { "type": "number" },
+            "minItems": 3,
+            "maxItems": 3,
+            "description": description,
+        }),
+        ParamKind::ObjectList => json!({
+            "type": "array",
+            "items": { "type": "object" },
+            "description": description,
+        }),
+    }
+}
+
+/// JSON schema for `GeometricTaskCommand`, including per-operator parameters.
+pub fn geometric_task_command_schema() -> Value {
+    let parameter_variants: Vec<Value> = OPERATOR_PARAMETERS
+        .iter()
+        .map(|(operator, params)| {
+            let properties: Map<String, Value> = params
+                .iter()
+                .map(|spec| {
+                    (
+                        spec.name.to_string(),
+                        param_schema(spec.kind, spec.description),
+                    )
+                })
+                .collect();
+            json!({
+                "title": format!("{operator} parameters"),
+                "type": "object",
+                "properties": properties,
+            })
+        })
+        .collect();
+
+    json!({
+        "type": "object",
+        "properties": {
+            "task_name": { "type": "string", "description": "Brief description of the task" },
+            "geometric_operator": { "type": "string", "enum": operator_names() },
+            "target_module": { "type": "string", "description": "Target module in the Pure Logic system" },
+            "parameters": { "anyOf": parameter_variants },
+            "expected_output_metric": { "type": "string", "description": "Metric to monitor" },
+        },
+        "required": [
+            "task_name",
+            "geometric_operator",
+            "target_module",
+            "parameters",
+            "expected_output_metric"
+        ],
+    })
+}
+
+/// Check a payload against the `GeometricTaskCommand` schema.
+pub fn validate_command_payload(payload: &Value) -> Vec<String> {
+    let Some(object) = payload.as_object() else {
+        return vec!["response must be a JSON object".into()];
+    };
+
+    let mut errors = Vec::new();
+    for field in [
+        "task_name",
+        "geometric_operator",
+        "target_module",
+        "expected_output_metric",
+    ] {
+        match object.get(field) {
+            None => errors.push(format!("missing required field `{field}`")),
+            Some(Value::String(text)) if text.trim().is_empty() => {
+                errors.push(format!("field `{field}` must not be empty"))
+            }
+            Some(Value::String(_)) => {}
+            Some(_) => errors.push(format!("field `{field}` must be a string")),
+        }
+    }
+
+    match object.get("parameters") {
+        None => errors.push("missing required field `parameters`".into()),
+        Some(Value::Object(params)) => {
+            if let Some(operator) = object.get("geometric_operator").and_then(Value::as_str) {
+                errors.extend(validate_parameters(operator, params));
+            }
+        }
+        Some(_) => errors.push("field `parameters` must be an object".into()),
+    }
+
+    match object.get("task_id") {
+        None | Some(Value::Null) => {}
+        Some(Value::String(id)) if uuid::Uuid::parse_str(id).is_ok() => {}
+        Some(_) => errors.push("field `task_id` must be a UUID string or omitted".into()),
+    }
+
+    errors
+}
+
+fn validate_parameters(operator: &str, params: &Map<String, Value>) -> Vec<String> {
+    let Some((_, specs)) = OPERATOR_PARAMETERS
+        .iter()
+        .find(|(name, _)| *name == operator)
+    else {
+        return vec![format!(
+            "unknown geometric_operator `{operator}`, expected one of {}",
+            operator_names().join(", ")
+        )];
+    };
+
+    specs
+        .iter()
+        .filter_map(|spec| {
+            let value = params.get(spec.name)?;
+            let valid = match spec.kind {
+                ParamKind::Number => value.is_number(),
+                ParamKind::String => value.is_string(),
+                ParamKind::Bool => value.is_boolean(),
+                ParamKind::Vector3 => value
+                    .as_array()
+                    .is_some_and(|items| items.len() == 3 && items.iter().all(Value::is_number)),
+                ParamKind::ObjectList => value
+                    .as_array()
+                    .is_some_and(|items| items.iter().all(Value::is_object)),
+            };
+            (!valid).then(|| {
+                format!(
+                    "parameter `{}` of {operator} must be {}",
+                    spec.name,
+                    match spec.kind {
+                        ParamKind::Number => "a number",
+                        ParamKind::String => "a string",
+                        ParamKind::Bool => "true or false",
+                        ParamKind::Vector3 => "an array of three numbers",
+                        ParamKind::ObjectList => "an array of objects",
+                    }
+                )
+            })
+        })
+        .collect()
+}
@@ -1,19 +1,100 @@
-use crate::core::error::{Error, Result};
-use crate::core::types::{GeometricMetrics, SystemState};
-use std::path::Path;
+use crate::config::PersistenceConfig;
+use crate::core::error::Result;
+use crate::core::types::SystemState;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-/// Very small persistence stub until real storage is implemented.
+/// The latest state, under the state directory.
+const STATE_FILE: &str = "state.json";
+
+/// Where and how often the server's state is persisted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatePersistenceConfig {
+    pub directory: PathBuf,
+    /// Also persist on this timer, not only at shutdown.
+    pub period: Option<Duration>,
+}
+
+impl StatePersistenceConfig {
+    /// Enabled by `persistence.state_dir`; `persistence.state_persist_secs`
+    /// sets the timer.
+    pub fn from_config(config: &PersistenceConfig) -> Option<Self> {
+        Some(Self {
+            directory: config.state_dir.clone()?,
+            period: config
+                .state_persist_secs
+                .filter(|&secs| secs > 0)
+                .map(Duration::from_secs),
+        })
+    }
+}
+
+/// Saves and loads `SystemState` as JSON in a directory.
 pub struct DataIoGateway;
 
 impl DataIoGateway {
-    pub fn load_latest_state(_base_path: &Path) -> Result<Option<SystemState>> {
-        Ok(None)
+    /// The state last persisted under `base_path`, `None` before the first.
+    pub fn load_latest_state(base_path: &Path) -> Result<Option<SystemState>> {
+        let bytes = match fs::read(base_path.join(STATE_FILE)) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(serde_json::from_slice(&bytes)?))
     }
 
-    pub fn persist_metrics(_base_path: &Path, _metrics: &GeometricMetrics) -> Result<()> {
-        Err(Error::Io(std::io::Error::new(
-            std::io::ErrorKind::Unsupported,
-            "data persistence not implemented",
-        )))
+    /// Replace the persisted state. The file is written beside the old one
+    /// and renamed over it, so a crash mid-write keeps the previous state.
+    pub fn persist_state(base_path: &Path, state: &SystemState) -> Result<()> {
+        fs::create_dir_all(base_path)?;
+        let partial = base_path.join(format!("{}.partial", STATE_FILE));
+        fs::write(&partial, serde_json::to_vec(state)?)?;
+        fs::rename(&partial, base_path.join(STATE_FILE))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::{GeometricMetrics, MetricMap};
+    use std::env;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_state_round_trip() {
+        let dir = env::temp_dir().join(format!("mmss-state-{}", Uuid::new_v4()));
+        assert!(DataIoGateway::load_latest_state(&dir).unwrap().is_none());
+
+        let state = SystemState {
+            state_id: Uuid::new_v4(),
+            timestamp: chrono::Utc::now(),
+            metrics: GeometricMetrics {
+                v_geometric: 0.5,
+                s_geometric: 0.1,
+                q_oscillator: 9.0,
+                quaternion_coherence: 0.9998,
+                emergent_electron_mass: 0.0,
+                fine_structure_constant: 0.0,
+                zitterbewegung_entropy: 0.0,
+                topological_winding: 9.0,
+                custom_metrics: MetricMap::new(),
+            },
+            active_anchors: Vec::new(),
+            active_tasks: Vec::new(),
+            rules: Vec::new(),
+            pending_tasks: Vec::new(),
+            hopfion: Some(Default::default()),
+        };
+        DataIoGateway::persist_state(&dir, &state).unwrap();
+        DataIoGateway::persist_state(&dir, &state).unwrap();
+
+        let loaded = DataIoGateway::load_latest_state(&dir).unwrap().unwrap();
+        assert_eq!(loaded.state_id, state.state_id);
+        assert_eq!(loaded.metrics, state.metrics);
+        assert_eq!(loaded.hopfion, state.hopfion);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,398 @@
+//! Embedding-based retrieval of semantic anchors and completed tasks, used
+//! to give the planner the few most relevant items instead of everything.
+
+use crate::campaign::objectives::metric_value;
+use crate::core::error::{Error, Result};
+use crate::core::types::{GeometricTaskCommand, SemanticAnchor, TaskExecutionResult};
+use log::error;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::env;
+use std::hash::{Hash, Hasher};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use uuid::Uuid;
+
+const MISTRAL_EMBEDDINGS_ENDPOINT: &str = "https://api.mistral.ai/v1/embeddings";
+const DEFAULT_HASHING_DIMENSIONS: usize = 256;
+const DEFAULT_TOP_K: usize = 5;
+const DEFAULT_MAX_ITEMS: usize = 2048;
+
+/// Produces vectors for indexed items and queries.
+pub enum EmbeddingProvider {
+    /// Offline feature-hashed bag of words; no model or network required.
+    Hashing { dimensions: usize },
+    /// OpenAI-compatible `/embeddings` endpoint.
+    Remote {
+        client: reqwest::Client,
+        endpoint: String,
+        api_key: String,
+        model: String,
+    },
+}
+
+impl EmbeddingProvider {
+    /// `MMSS_EMBEDDINGS=remote` uses `MMSS_EMBEDDINGS_ENDPOINT` (Mistral by
+    /// default) with `MISTRAL_API_KEY`; anything else uses local hashing.
+    pub fn from_env() -> Self {
+        let api_key = env::var("MISTRAL_API_KEY").ok();
+        match (env::var("MMSS_EMBEDDINGS").as_deref(), api_key) {
+            (Ok("remote"), Some(api_key)) => Self::Remote {
+                client: reqwest::Client::new(),
+                endpoint: env::var("MMSS_EMBEDDINGS_ENDPOINT")
+                    .unwrap_or_else(|_| MISTRAL_EMBEDDINGS_ENDPOINT.into()),
+                api_key,
+                model: env::var("MMSS_EMBEDDINGS_MODEL").unwrap_or_else(|_| "mistral-embed".into()),
+            },
+            _ => Self::Hashing {
+                dimensions: DEFAULT_HASHING_DIMENSIONS,
+            },
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Hashing { .. } => "hashing",
+            Self::Remote { .. } => "remote",
+        }
+    }
+
+    pub async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        match self {
+            Self::Hashing { dimensions } => Ok(hashing_embedding(text, *dimensions)),
+            Self::Remote {
+                client,
+                endpoint,
+                api_key,
+                model,
+            } => {
+                let response = client
+                    .post(endpoint)
+                    .bearer_auth(api_key)
+                    .json(&json!({ "model": model, "input": [text] }))
+                    .send()
+                    .await
+                    .map_err(|err| {
+                        Error::LlmCommunication(format!("embedding HTTP error: {err}"))
+                    })?;
+
+                if !response.status().is_success() {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(Error::LlmCommunication(format!(
+                        "embedding API error {status}: {body}"
+                    )));
+                }
+
+                let body: EmbeddingResponse = response.json().await.map_err(|err| {
+                    Error::LlmCommunication(format!("Failed to parse embeddings: {err}"))
+                })?;
+                let vector = body
+                    .data
+                    .into_iter()
+                    .next()
+                    .map(|item| item.embedding)
+                    .ok_or_else(|| Error::LlmCommunication("Empty embedding response".into()))?;
+                Ok(normalized(vector))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingItem>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingItem {
+    embedding: Vec<f32>,
+}
+
+fn hashing_embedding(text: &str, dimensions: usize) -> Vec<f32> {
+    let mut vector = vec![0.0; dimensions.max(1)];
+    let tokens = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase);
+    for token in tokens {
+        let mut hasher = DefaultHasher::new();
+        token.hash(&mut hasher);
+        let hash = hasher.finish();
+        let sign = if hash & 1 == 0 { 1.0 } else { -1.0 };
+        vector[(hash >> 1) as usize % dimensions.max(1)] += sign;
+    }
+    normalized(vector)
+}
+
+fn normalized(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    Anchor,
+    Task,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RetrievedItem {
+    pub kind: ItemKind,
+    pub id: Uuid,
+    pub text: String,
+    pub score: f32,
+    pub payload: Value,
+}
+
+struct IndexedItem {
+    kind: ItemKind,
+    id: Uuid,
+    text: String,
+    payload: Value,
+    vector: Vec<f32>,
+}
+
+/// Vector index over anchors and completed tasks. The oldest items are
+/// evicted once `max_items` is reached.
+pub struct Retriever {
+    provider: EmbeddingProvider,
+    top_k: usize,
+    max_items: usize,
+    items: RwLock<VecDeque<IndexedItem>>,
+}
+
+impl Retriever {
+    /// Provider from [`EmbeddingProvider::from_env`]; `MMSS_RETRIEVAL_TOP_K`
+    /// sets how many items are added to planning context.
+    pub fn from_env() -> Self {
+        let read = |name: &str| env::var(name).ok().and_then(|raw| raw.parse().ok());
+        Self::new(
+            EmbeddingProvider::from_env(),
+            read("MMSS_RETRIEVAL_TOP_K").unwrap_or(DEFAULT_TOP_K),
+            read("MMSS_RETRIEVAL_MAX_ITEMS").unwrap_or(DEFAULT_MAX_ITEMS),
+        )
+    }
+
+    pub fn new(provider: EmbeddingProvider, top_k: usize, max_items: usize) -> Self {
+        Self {
+            provider,
+            top_k,
+            max_items: max_items.max(1),
+            items: RwLock::new(VecDeque::new()),
+        }
+    }
+
+    pub fn provider(&self) -> &EmbeddingProvider {
+        &self.provider
+    }
+
+    pub fn top_k(&self) -> usize {
+        self.top_k
+    }
+
+    pub fn len(&self) -> Result<usize> {
+        Ok(self.read()?.len())
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.read()?.is_empty())
+    }
+
+    pub async fn index_anchor(&self, anchor: &SemanticAnchor) -> Result<()> {
+        let text = format!("anchor {}: {}", anchor.name, anchor.description);
+        let payload = json!({
+            "name": anchor.name,
+            "description": anchor.description,
+            "position": anchor.position,
+            "metadata": anchor.metadata,
+        });
+        self.insert(ItemKind::Anchor, anchor.id, text, payload)
+            .await
+    }
+
+    pub async fn index_task(
+        &self,
+        task: &GeometricTaskCommand,
+        result: &TaskExecutionResult,
+    ) -> Result<()> {
+        let metric = &task.expected_output_metric;
+        let value = metric_value(&result.metrics, metric);
+        let outcome = if result.success {
+            "succeeded"
+        } else {
+            "failed"
+        };
+        let text = format!(
+            "task {}: {:?} on {} with {} {outcome}, {metric} = {value}",
+            task.task_name, task.geometric_operator, task.target_module, task.parameters
+        );
+        let payload = json!({
+            "task_name": task.task_name,
+            "geometric_operator": task.geometric_operator,
+            "target_module": task.target_module,
+            "parameters": task.parameters,
+            "expected_output_metric": metric,
+            "metric_after": value,
+            "success": result.success,
+        });
+        self.insert(ItemKind::Task, result.task_id, text, payload)
+            .await
+    }
+
+    /// Drop every item of `kind`, returning how many there were.
+    pub fn clear(&self, kind: ItemKind) -> Result<usize> {
+        let mut items = self.write()?;
+        let before = items.len();
+        items.retain(|item| item.kind != kind);
+        Ok(before - items.len())
+    }
+
+    /// Every indexed anchor still held, oldest first.
+    pub fn anchors(&self) -> Result<Vec<SemanticAnchor>> {
+        Ok(self
+            .read()?
+            .iter()
+            .filter(|item| item.kind == ItemKind::Anchor)
+            .map(|item| SemanticAnchor {
+                id: item.id,
+                name: item.payload["name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                description: item.payload["description"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                position: serde_json::from_value(item.payload["position"].clone())
+                    .unwrap_or([0.0, 0.0, 0.0, 1.0]),
+                metadata: item.payload["metadata"].clone(),
+            })
+            .collect())
+    }
+
+    /// The `top_k` items most similar to `query`.
+    pub async fn retrieve(&self, query: &str) -> Result<Vec<RetrievedItem>> {
+        self.search(query, self.top_k).await
+    }
+
+    pub async fn search(&self, query: &str, k: usize) -> Result<Vec<RetrievedItem>> {
+        if k == 0 || self.is_empty()? {
+            return Ok(Vec::new());
+        }
+        let query = self.provider.embed(query).await?;
+
+        let items = self.read()?;
+        let mut scored: Vec<_> = items
+            .iter()
+            .map(|item| (cosine(&query, &item.vector), item))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        Ok(scored
+            .into_iter()
+            .take(k)
+            .map(|(score, item)| RetrievedItem {
+                kind: item.kind,
+                id: item.id,
+                text: item.text.clone(),
+                score,
+                payload: item.payload.clone(),
+            })
+            .collect())
+    }
+
+    async fn insert(&self, kind: ItemKind, id: Uuid, text: String, payload: Value) -> Result<()> {
+        let vector = self.provider.embed(&text).await?;
+
+        let mut items = self.write()?;
+        items.retain(|item| item.id != id);
+        items.push_back(IndexedItem {
+            kind,
+            id,
+            text,
+            payload,
+            vector,
+        });
+        while items.len() > self.max_items {
+            items.pop_front();
+        }
+        Ok(())
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, VecDeque<IndexedItem>>> {
+        self.items.read().map_err(|e| {
+            error!("Failed to lock embedding index: {}", e);
+            Error::TaskExecution("Failed to access embedding index".to_string())
+        })
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, VecDeque<IndexedItem>>> {
+        self.items.write().map_err(|e| {
+            error!("Failed to lock embedding index: {}", e);
+            Error::TaskExecution("Failed to access embedding index".to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anchor(name: &str, description: &str) -> SemanticAnchor {
+        SemanticAnchor {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            description: description.into(),
+            position: [0.0, 0.0, 0.0, 1.0],
+            metadata: Value::Null,
+        }
+    }
+
+    #[test]
+    fn test_hashing_embedding_is_normalized() {
+        let vector = hashing_embedding("Quaternion coherence boost", 64);
+        let norm: f32 = vector.iter().map(|x| x * x).sum();
+        assert!((norm - 1.0).abs() < 1e-5);
+        assert_eq!(vector, hashing_embedding("quaternion COHERENCE boost", 64));
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_by_similarity_and_evicts() {
+        let retriever = Retriever::new(EmbeddingProvider::Hashing { dimensions: 256 }, 1, 2);
+        retriever
+            .index_anchor(&anchor("old", "obsolete anchor"))
+            .await
+            .unwrap();
+        retriever
+            .index_anchor(&anchor("electron", "zitterbewegung electron mass"))
+            .await
+            .unwrap();
+        retriever
+            .index_anchor(&anchor("coherence", "quaternion coherence alignment"))
+            .await
+            .unwrap();
+
+        assert_eq!(retriever.len().unwrap(), 2);
+        let hits = retriever
+            .retrieve("raise quaternion coherence")
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].payload["name"], "coherence");
+
+        let anchors = retriever.anchors().unwrap();
+        let names: Vec<_> = anchors.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["electron", "coherence"]);
+        assert_eq!(anchors[0].position, [0.0, 0.0, 0.0, 1.0]);
+    }
+}
@@ -1,123 +1,338 @@
+use crate::api::audit::AuditLog;
+use crate::api::command_schema::validate_command_payload;
+use crate::api::llm_provider::{provider_from_config, ChatMessage, LlmProvider};
+use crate::api::prompt_templates::{PromptStore, QUERY_TEMPLATE, REPAIR_TEMPLATE, SYSTEM_TEMPLATE};
+use crate::api::resilience::{BreakerSettings, CircuitBreaker, RetryPolicy};
+use crate::api::sessions::SessionStore;
+use crate::api::usage::TokenUsage;
+use crate::config::Config;
+use crate::core::credentials::parse_operator_keys;
+use crate::core::ops_metrics::OpsMetrics;
+use crate::core::script_policy::{carries_script, ScriptPolicy};
+use crate::api::usage::{TokenBudgets, UsageScope, UsageTracker};
 use crate::core::{
     error::{Error, Result},
     types::GeometricTaskCommand,
 };
+use log::warn;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::env;
+use std::sync::Arc;
+use std::time::Instant;
+use uuid::Uuid;
 
-const MISTRAL_ENDPOINT: &str = "https://api.mistral.ai/v1/chat/completions";
+const DEFAULT_REPAIR_ATTEMPTS: usize = 2;
+
+/// How the gateway asks the provider for a structured command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanningMode {
+    /// Free-form JSON via `response_format: json_object`.
+    JsonObject,
+    /// Function calling with the `GeometricTaskCommand` schema as the tool signature.
+    ToolCall,
+}
+
+impl PlanningMode {
+    /// `json`, or `tools` (also `tool_call`) for function calling.
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "json" => Some(Self::JsonObject),
+            "tools" | "tool_call" => Some(Self::ToolCall),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct LlmGateway {
-    client: reqwest::Client,
-    api_key: String,
-    model: String,
+    provider: Arc<dyn LlmProvider>,
+    retry: RetryPolicy,
+    breaker: Arc<CircuitBreaker>,
+    max_repair_attempts: usize,
+    mode: PlanningMode,
+    usage: Arc<UsageTracker>,
+    prompts: Arc<PromptStore>,
+    sessions: Arc<SessionStore>,
+    audit: Arc<AuditLog>,
+    script_policy: ScriptPolicy,
+    ops: Arc<OpsMetrics>,
 }
 
 impl LlmGateway {
-    pub fn new(api_key: Option<String>) -> Result<Self> {
-        let key = api_key
-            .or_else(|| env::var("MISTRAL_API_KEY").ok())
-            .ok_or_else(|| Error::LlmCommunication("Missing MISTRAL_API_KEY".into()))?;
+    /// Gateway over the provider selected by `config.llm`.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Self::configured(provider_from_config(&config.llm)?, config)
+    }
+
+    /// Gateway over `provider` with the default configuration.
+    pub fn with_provider(provider: Arc<dyn LlmProvider>) -> Result<Self> {
+        Self::configured(provider, &Config::default())
+    }
 
+    fn configured(provider: Arc<dyn LlmProvider>, config: &Config) -> Result<Self> {
+        let mut retry = RetryPolicy::from_env();
+        if let Some(retries) = config.llm.retries {
+            retry.max_retries = retries;
+        }
+        // the configured secrets may come from the config file, so not be
+        // in the environment the audit log scans
+        let server = &config.server;
+        let operator_keys = server
+            .operator_keys
+            .as_deref()
+            .and_then(|raw| parse_operator_keys(raw).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(_, key)| key);
+        let secrets = [
+            &config.llm.api_key,
+            &server.admin_token,
+            &server.jwt_secret,
+            &server.audit_key,
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .chain(operator_keys);
         Ok(Self {
-            client: reqwest::Client::new(),
-            api_key: key,
-            model: env::var("MISTRAL_MODEL").unwrap_or_else(|_| "mistral-small-latest".into()),
+            provider,
+            retry,
+            breaker: Arc::new(CircuitBreaker::new(BreakerSettings::from_env())),
+            max_repair_attempts: env::var("MISTRAL_REPAIR_ATTEMPTS")
+                .ok()
+                .and_then(|raw| raw.parse().ok())
+                .unwrap_or(DEFAULT_REPAIR_ATTEMPTS),
+            mode: config
+                .llm
+                .planning_mode
+                .as_deref()
+                .and_then(PlanningMode::parse)
+                .unwrap_or(PlanningMode::JsonObject),
+            usage: Arc::new(UsageTracker::new(TokenBudgets::from_env())),
+            prompts: Arc::new(PromptStore::new(config.persistence.prompt_dir.clone())?),
+            sessions: Arc::new(SessionStore::from_env()),
+            audit: Arc::new(AuditLog::at(config.persistence.audit_log.clone(), secrets)),
+            script_policy: ScriptPolicy::from_env(),
+            ops: Arc::new(OpsMetrics::new()),
         })
     }
 
+    /// Record provider latency and errors in `ops`.
+    pub fn with_ops_metrics(mut self, ops: Arc<OpsMetrics>) -> Self {
+        self.ops = ops;
+        self
+    }
+
+    /// A gateway for another namespace: the same provider, breaker, prompts
+    /// and audit log, but its own planning sessions and token usage, so a
+    /// namespace neither sees another's sessions nor spends its budgets.
+    pub fn scoped(&self) -> Self {
+        Self {
+            provider: self.provider.clone(),
+            retry: self.retry,
+            breaker: self.breaker.clone(),
+            max_repair_attempts: self.max_repair_attempts,
+            mode: self.mode,
+            usage: Arc::new(UsageTracker::new(self.usage.budgets())),
+            prompts: self.prompts.clone(),
+            sessions: Arc::new(SessionStore::new(self.sessions.max_turns())),
+            audit: self.audit.clone(),
+            script_policy: self.script_policy,
+            ops: self.ops.clone(),
+        }
+    }
+
+    pub fn provider(&self) -> &dyn LlmProvider {
+        self.provider.as_ref()
+    }
+
+    pub fn audit(&self) -> &AuditLog {
+        &self.audit
+    }
+
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    pub fn mode(&self) -> PlanningMode {
+        self.mode
+    }
+
+    pub fn prompts(&self) -> &PromptStore {
+        &self.prompts
+    }
+
+    pub fn usage(&self) -> &UsageTracker {
+        &self.usage
+    }
+
+    pub fn sessions(&self) -> &SessionStore {
+        &self.sessions
+    }
+
     pub async fn submit_geometric_query(
         &self,
         query: &str,
         context: &Value,
+        scope: &UsageScope,
     ) -> Result<GeometricTaskCommand> {
-        let payload = LlmRequest {
-            model: self.model.clone(),
-            response_format: ResponseFormat {
-                r#type: "json_object".into(),
-            },
-            messages: vec![
-                Message {
-                    role: "system".into(),
-                    content: SYSTEM_PROMPT.into(),
-                },
-                Message {
-                    role: "user".into(),
-                    content: format!("Context: {}\n\nQuery: {}", context, query),
-                },
-            ],
-        };
-
-        let response = self
-            .client
-            .post(MISTRAL_ENDPOINT)
-            .bearer_auth(&self.api_key)
-            .json(&payload)
-            .send()
+        self.submit_geometric_query_with_history(query, context, &[], scope)
             .await
-            .map_err(|err| Error::LlmCommunication(format!("HTTP error: {err}")))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(Error::LlmCommunication(format!(
-                "Mistral API error {status}: {body}"
-            )));
-        }
+    }
 
-        let body: LlmResponse = response
-            .json()
-            .await
-            .map_err(|err| Error::LlmCommunication(format!("Failed to parse response: {err}")))?;
+    /// Plan a task with earlier turns of the session rendered into the query
+    /// (see `sessions::summarize_history`).
+    #[tracing::instrument(
+        name = "llm.plan",
+        skip_all,
+        fields(llm.provider = self.provider.name(), llm.model = self.provider.model())
+    )]
+    pub async fn submit_geometric_query_with_history(
+        &self,
+        query: &str,
+        context: &Value,
+        history: &[Value],
+        scope: &UsageScope,
+    ) -> Result<GeometricTaskCommand> {
+        self.usage.ensure_within_budget(scope)?;
 
-        let content = body
-            .choices
-            .first()
-            .and_then(|choice| choice.message.content.clone())
-            .ok_or_else(|| Error::LlmCommunication("Empty response from Mistral".into()))?;
+        let mut messages = vec![
+            ChatMessage::new("system", self.prompts.render(SYSTEM_TEMPLATE, json!({}))?),
+            ChatMessage::new(
+                "user",
+                self.prompts.render(
+                    QUERY_TEMPLATE,
+                    json!({ "context": context.to_string(), "query": query, "history": history }),
+                )?,
+            ),
+        ];
 
-        let mut raw: Value = serde_json::from_str(&content).map_err(Error::Serialization)?;
-        normalize_geometric_operator(&mut raw);
-        serde_json::from_value(raw).map_err(Error::Serialization)
-    }
-}
+        let mut attempt = 0;
+        loop {
+            let started = Instant::now();
+            let completion = self.complete(&messages).await;
+            let audit_id = self.audit.record(
+                scope,
+                self.provider.as_ref(),
+                attempt,
+                started.elapsed().as_millis() as u64,
+                &messages,
+                completion
+                    .as_ref()
+                    .map(|(content, usage)| (content.as_str(), *usage)),
+            )?;
+            let (content, usage) = completion?;
+            self.usage.record(scope, &usage)?;
 
-const SYSTEM_PROMPT: &str = "You are the MMSS Pure Logic agent. Respond strictly with JSON in the GeometricTaskCommand schema (task_name, geometric_operator, target_module, parameters, expected_output_metric, optional task_id).";
+            let parsed = parse_geometric_command(&content).and_then(|command| {
+                // let the repair loop steer the model to a non-script operator
+                if self.script_policy == ScriptPolicy::Reject && carries_script(&command) {
+                    Err(vec![
+                        "script-bearing commands (CustomPythonScript) are disabled; use another operator"
+                            .to_string(),
+                    ])
+                } else {
+                    Ok(command)
+                }
+            });
+            let errors = match parsed {
+                Ok(mut command) => {
+                    // a stable ID ties the audit record to the task once it runs
+                    let task_id = *command.task_id.get_or_insert_with(Uuid::new_v4);
+                    self.audit.link_task(audit_id, task_id)?;
+                    return Ok(command);
+                }
+                Err(errors) => errors,
+            };
 
-#[derive(Debug, Serialize)]
-struct LlmRequest {
-    model: String,
-    messages: Vec<Message>,
-    response_format: ResponseFormat,
-}
+            let budget_left = self.usage.ensure_within_budget(scope).is_ok();
+            if attempt >= self.max_repair_attempts || !budget_left {
+                return Err(Error::LlmValidation(format!(
+                    "response rejected after {} attempt(s): {}",
+                    attempt + 1,
+                    errors.join("; ")
+                )));
+            }
 
-#[derive(Debug, Serialize)]
-struct Message {
-    role: String,
-    content: String,
-}
+            attempt += 1;
+            warn!(
+                "LLM response failed validation ({}), requesting repair {}/{}",
+                errors.join("; "),
+                attempt,
+                self.max_repair_attempts
+            );
 
-#[derive(Debug, Serialize)]
-struct ResponseFormat {
-    #[serde(rename = "type")]
-    r#type: String,
-}
+            messages.push(ChatMessage::new("assistant", content));
+            messages.push(ChatMessage::new(
+                "user",
+                self.prompts
+                    .render(REPAIR_TEMPLATE, json!({ "errors": errors }))?,
+            ));
+        }
+    }
 
-#[derive(Debug, Deserialize)]
-struct LlmResponse {
-    choices: Vec<Choice>,
-}
+    /// One provider completion, retried with backoff on transport errors and
+    /// guarded by the circuit breaker.
+    #[tracing::instrument(
+        name = "llm.complete",
+        skip_all,
+        fields(llm.messages = messages.len(), llm.retries = 0, llm.tokens = tracing::field::Empty)
+    )]
+    async fn complete(&self, messages: &[ChatMessage]) -> Result<(String, TokenUsage)> {
+        let permit = self.breaker.acquire()?;
 
-#[derive(Debug, Deserialize)]
-struct Choice {
-    message: ChoiceMessage,
+        let started = Instant::now();
+        let mut attempt = 0;
+        loop {
+            let call_started = Instant::now();
+            let completion = self.provider.complete(messages, self.mode).await;
+            self.ops
+                .record_llm_call(call_started.elapsed(), completion.is_ok());
+            match completion {
+                Ok(completion) => {
+                    permit.record(true)?;
+                    let span = tracing::Span::current();
+                    span.record("llm.retries", attempt);
+                    span.record("llm.tokens", completion.1.total_tokens);
+                    tracing::info!(
+                        histogram.mmss.llm.latency_ms = started.elapsed().as_millis() as u64,
+                        monotonic_counter.mmss.llm.tokens = completion.1.total_tokens,
+                        provider = self.provider.name(),
+                    );
+                    return Ok(completion);
+                }
+                Err(err) if RetryPolicy::is_retryable(&err) && attempt < self.retry.max_retries => {
+                    let delay = self.retry.delay(attempt);
+                    attempt += 1;
+                    warn!(
+                        "LLM provider call failed ({}), retry {}/{} in {:?}",
+                        err, attempt, self.retry.max_retries, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(err) => {
+                    permit.record(false)?;
+                    return Err(err);
+                }
+            }
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct ChoiceMessage {
-    content: Option<String>,
+/// Parse and validate raw LLM output, returning every schema violation found.
+fn parse_geometric_command(content: &str) -> std::result::Result<GeometricTaskCommand, Vec<String>> {
+    let mut raw: Value =
+        serde_json::from_str(content).map_err(|err| vec![format!("invalid JSON: {err}")])?;
+    normalize_geometric_operator(&mut raw);
+
+    let errors = validate_command_payload(&raw);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    serde_json::from_value(raw).map_err(|err| vec![err.to_string()])
 }
 
 fn normalize_geometric_operator(payload: &mut Value) {
@@ -136,6 +351,18 @@ fn map_llm_response_to_operator(raw: &str) -> &'static str {
         "Zitterbewegung"
     } else if lowered.contains("stabilize") || lowered.contains("derivation") {
         "GeometricDerivation"
+    } else if lowered.contains("channel") || lowered.contains("combin") {
+        "CombineEqgftChannels"
+    } else if lowered.contains("scan") || lowered.contains("sweep") {
+        "SimulateEqgftKappaScan"
+    } else if lowered.contains("asymmetry") || lowered.contains("polarization") {
+        "SimulateEqgftAsymmetry"
+    } else if lowered.contains("hopf") || lowered.contains("soliton") {
+        "GenerateHopfionField"
+    } else if lowered.contains("plugin") {
+        "PluginOperator"
+    } else if lowered.contains("python") || lowered.contains("script") {
+        "CustomPythonScript"
     } else if lowered.contains("semantic") || lowered.contains("anchor") {
         "SemanticSynthesis"
     } else if lowered.contains("coherence")
@@ -161,3 +388,52 @@ fn map_llm_response_to_operator(raw: &str) -> &'static str {
         "QuaternionRotation"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_command_parses() {
+        let content = r#"{
+            "task_name": "Boost coherence",
+            "geometric_operator": "quaternion rotation",
+            "target_module": "sys7_core",
+            "parameters": { "theta": 0.25 },
+            "expected_output_metric": "quaternion_coherence"
+        }"#;
+
+        let command = parse_geometric_command(content).unwrap();
+        assert_eq!(command.target_module, "sys7_core");
+    }
+
+    #[test]
+    fn test_validation_reports_all_errors() {
+        let errors = parse_geometric_command(r#"{ "task_name": "", "parameters": 3 }"#).unwrap_err();
+
+        assert!(errors.iter().any(|e| e.contains("`task_name` must not be empty")));
+        assert!(errors.iter().any(|e| e.contains("`geometric_operator`")));
+        assert!(errors.iter().any(|e| e.contains("`parameters` must be an object")));
+    }
+
+    #[test]
+    fn test_operator_parameters_are_type_checked() {
+        let content = r#"{
+            "task_name": "Rotate",
+            "geometric_operator": "QuaternionRotation",
+            "target_module": "sys7_core",
+            "parameters": { "theta": "quarter", "axis": [0.0, 1.0] },
+            "expected_output_metric": "quaternion_coherence"
+        }"#;
+
+        let errors = parse_geometric_command(content).unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_malformed_json_is_rejected() {
+        let errors = parse_geometric_command("not json").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("invalid JSON"));
+    }
+}
@@ -0,0 +1,254 @@
+//! Chat-completion backends behind the LLM gateway.
+
+use crate::api::command_schema::geometric_task_command_schema;
+use crate::api::llm_gateway::PlanningMode;
+use crate::api::usage::TokenUsage;
+use crate::config::LlmConfig;
+use crate::core::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+const MISTRAL_ENDPOINT: &str = "https://api.mistral.ai/v1/chat/completions";
+pub(crate) const PLAN_TASK_TOOL: &str = "plan_geometric_task";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn new(role: &str, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: content.into(),
+        }
+    }
+}
+
+pub type CompletionFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<(String, TokenUsage)>> + Send + 'a>>;
+
+/// A backend that turns a chat transcript into the raw text of a
+/// `GeometricTaskCommand` JSON object, plus the tokens it consumed.
+pub trait LlmProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn model(&self) -> &str;
+
+    fn complete<'a>(
+        &'a self,
+        messages: &'a [ChatMessage],
+        mode: PlanningMode,
+    ) -> CompletionFuture<'a>;
+}
+
+/// Select the provider named by `llm.provider`: `mistral` (default),
+/// `mock`, or `local` with the `local-llm` feature.
+pub fn provider_from_config(config: &LlmConfig) -> Result<Arc<dyn LlmProvider>> {
+    match config.provider.as_str() {
+        "mistral" => Ok(Arc::new(MistralProvider::new(
+            config.api_key.clone(),
+            config.model.clone(),
+        )?)),
+        "mock" => Ok(Arc::new(crate::api::mock_llm::MockProvider::from_env()?)),
+        #[cfg(feature = "local-llm")]
+        "local" => Ok(Arc::new(crate::api::local_llm::LocalProvider::from_env()?)),
+        other => Err(Error::LlmCommunication(format!(
+            "unknown MMSS_LLM_PROVIDER `{other}`"
+        ))),
+    }
+}
+
+/// Hosted Mistral chat completions API.
+pub struct MistralProvider {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+}
+
+impl MistralProvider {
+    pub fn new(api_key: Option<String>, model: Option<String>) -> Result<Self> {
+        let key =
+            api_key.ok_or_else(|| Error::LlmCommunication("Missing MISTRAL_API_KEY".into()))?;
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            api_key: key,
+            model: model.unwrap_or_else(|| "mistral-small-latest".into()),
+        })
+    }
+
+    async fn send(
+        &self,
+        messages: &[ChatMessage],
+        mode: PlanningMode,
+    ) -> Result<(String, TokenUsage)> {
+        let payload = match mode {
+            PlanningMode::JsonObject => LlmRequest {
+                model: self.model.clone(),
+                messages: messages.to_vec(),
+                response_format: Some(ResponseFormat {
+                    r#type: "json_object".into(),
+                }),
+                tools: None,
+                tool_choice: None,
+            },
+            PlanningMode::ToolCall => LlmRequest {
+                model: self.model.clone(),
+                messages: messages.to_vec(),
+                response_format: None,
+                tools: Some(vec![Tool {
+                    r#type: "function".into(),
+                    function: ToolFunction {
+                        name: PLAN_TASK_TOOL.into(),
+                        description: "Plan the next geometric task for the MMSS engine".into(),
+                        parameters: geometric_task_command_schema(),
+                    },
+                }]),
+                tool_choice: Some("any".into()),
+            },
+        };
+
+        let response = self
+            .client
+            .post(MISTRAL_ENDPOINT)
+            .bearer_auth(&self.api_key)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|err| Error::LlmCommunication(format!("HTTP error: {err}")))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::LlmCommunication(format!(
+                "Mistral API error {status}: {body}"
+            )));
+        }
+
+        let body: LlmResponse = response
+            .json()
+            .await
+            .map_err(|err| Error::LlmCommunication(format!("Failed to parse response: {err}")))?;
+
+        let message = body
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message)
+            .ok_or_else(|| Error::LlmCommunication("Empty response from Mistral".into()))?;
+
+        // Tool calls carry the command as their arguments; fall back to plain
+        // content so a model that ignores the tool still gets validated.
+        let content = message
+            .tool_calls
+            .into_iter()
+            .find(|call| call.function.name == PLAN_TASK_TOOL)
+            .map(|call| call.function.arguments.into_json_string())
+            .or(message.content)
+            .ok_or_else(|| Error::LlmCommunication("Empty response from Mistral".into()))?;
+
+        Ok((content, body.usage))
+    }
+}
+
+impl LlmProvider for MistralProvider {
+    fn name(&self) -> &str {
+        "mistral"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete<'a>(
+        &'a self,
+        messages: &'a [ChatMessage],
+        mode: PlanningMode,
+    ) -> CompletionFuture<'a> {
+        Box::pin(self.send(messages, mode))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct LlmRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<Tool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+    #[serde(rename = "type")]
+    r#type: String,
+    function: ToolFunction,
+}
+
+#[derive(Debug, Serialize)]
+struct ToolFunction {
+    name: String,
+    description: String,
+    parameters: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    r#type: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LlmResponse {
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: TokenUsage,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChoiceMessage {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolCall {
+    function: ToolCallFunction,
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolCallFunction {
+    name: String,
+    arguments: ToolArguments,
+}
+
+/// Providers return tool arguments either as a JSON string or an object.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ToolArguments {
+    Encoded(String),
+    Object(Value),
+}
+
+impl ToolArguments {
+    fn into_json_string(self) -> String {
+        match self {
+            Self::Encoded(raw) => raw,
+            Self::Object(value) => value.to_string(),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Offline planning with a quantized GGUF model run in-process by candle.
+//! Built only with the `local-llm` feature.
+
+use crate::api::command_schema::geometric_task_command_schema;
+use crate::api::llm_gateway::PlanningMode;
+use crate::api::llm_provider::{ChatMessage, CompletionFuture, LlmProvider};
+use crate::api::usage::TokenUsage;
+use crate::core::error::{Error, Result};
+use candle_core::quantized::gguf_file;
+use candle_core::{Device, Tensor};
+use candle_transformers::generation::LogitsProcessor;
+use candle_transformers::models::quantized_llama::ModelWeights;
+use log::info;
+use std::env;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokenizers::Tokenizer;
+
+const DEFAULT_MAX_TOKENS: usize = 512;
+const END_OF_SEQUENCE: &str = "</s>";
+
+/// Llama-family GGUF model (Mistral, Llama 2/3, ...) with its tokenizer.
+///
+/// Configured by `MMSS_LOCAL_MODEL` (path to the `.gguf` file),
+/// `MMSS_LOCAL_TOKENIZER` (path to `tokenizer.json`), and optionally
+/// `MMSS_LOCAL_MAX_TOKENS`. Decoding is greedy so plans are reproducible.
+pub struct LocalProvider {
+    model_name: String,
+    model: Arc<Mutex<ModelWeights>>,
+    tokenizer: Arc<Tokenizer>,
+    device: Device,
+    max_tokens: usize,
+}
+
+impl LocalProvider {
+    pub fn from_env() -> Result<Self> {
+        let path = |name: &str| {
+            env::var(name)
+                .map(PathBuf::from)
+                .map_err(|_| Error::LlmCommunication(format!("Missing {name} for local LLM")))
+        };
+        let model_path = path("MMSS_LOCAL_MODEL")?;
+        let tokenizer_path = path("MMSS_LOCAL_TOKENIZER")?;
+        let max_tokens = env::var("MMSS_LOCAL_MAX_TOKENS")
+            .ok()
+            .and_then(|raw| raw.parse().ok())
+            .unwrap_or(DEFAULT_MAX_TOKENS);
+
+        let device = Device::Cpu;
+        let mut file = std::fs::File::open(&model_path)?;
+        let content = gguf_file::Content::read(&mut file).map_err(candle_error)?;
+        let model = ModelWeights::from_gguf(content, &mut file, &device).map_err(candle_error)?;
+        let tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|err| Error::LlmCommunication(format!("Failed to load tokenizer: {err}")))?;
+        info!("Loaded local LLM from {}", model_path.display());
+
+        Ok(Self {
+            model_name: model_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "local".into()),
+            model: Arc::new(Mutex::new(model)),
+            tokenizer: Arc::new(tokenizer),
+            device,
+            max_tokens,
+        })
+    }
+
+    async fn generate(&self, prompt: String) -> Result<(String, TokenUsage)> {
+        let model = self.model.clone();
+        let tokenizer = self.tokenizer.clone();
+        let device = self.device.clone();
+        let max_tokens = self.max_tokens;
+
+        tokio::task::spawn_blocking(move || {
+            let mut model = model
+                .lock()
+                .map_err(|_| Error::LlmCommunication("Local model is poisoned".into()))?;
+            generate_blocking(&mut model, &tokenizer, &device, &prompt, max_tokens)
+        })
+        .await
+        .map_err(|err| Error::LlmCommunication(format!("Local generation failed: {err}")))?
+    }
+}
+
+impl LlmProvider for LocalProvider {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn model(&self) -> &str {
+        &self.model_name
+    }
+
+    fn complete<'a>(
+        &'a self,
+        messages: &'a [ChatMessage],
+        mode: PlanningMode,
+    ) -> CompletionFuture<'a> {
+        Box::pin(self.generate(instruct_prompt(messages, mode)))
+    }
+}
+
+/// Render the transcript in the `[INST]` format of instruct GGUF models.
+/// Local models have no function calling, so tool mode inlines the schema.
+fn instruct_prompt(messages: &[ChatMessage], mode: PlanningMode) -> String {
+    let mut system: Vec<String> = messages
+        .iter()
+        .filter(|m| m.role == "system")
+        .map(|m| m.content.clone())
+        .collect();
+    if mode == PlanningMode::ToolCall {
+        system.push(format!(
+            "The JSON object must match this schema: {}",
+            geometric_task_command_schema()
+        ));
+    }
+
+    let mut prompt = String::new();
+    let mut pending_system = Some(system.join("\n\n")).filter(|s| !s.is_empty());
+    for message in messages.iter().filter(|m| m.role != "system") {
+        if message.role == "assistant" {
+            prompt.push_str(&message.content);
+            prompt.push_str(END_OF_SEQUENCE);
+            continue;
+        }
+        prompt.push_str("[INST] ");
+        if let Some(system) = pending_system.take() {
+            prompt.push_str(&system);
+            prompt.push_str("\n\n");
+        }
+        prompt.push_str(&message.content);
+        prompt.push_str(" [/INST]");
+    }
+    prompt
+}
+
+fn generate_blocking(
+    model: &mut ModelWeights,
+    tokenizer: &Tokenizer,
+    device: &Device,
+    prompt: &str,
+    max_tokens: usize,
+) -> Result<(String, TokenUsage)> {
+    let encoding = tokenizer
+        .encode(prompt, true)
+        .map_err(|err| Error::LlmCommunication(format!("Failed to tokenize prompt: {err}")))?;
+    let prompt_tokens = encoding.get_ids().to_vec();
+    let eos = tokenizer.token_to_id(END_OF_SEQUENCE);
+    let mut sampler = LogitsProcessor::new(0, None, None);
+
+    let mut generated = Vec::new();
+    let mut input = prompt_tokens.clone();
+    let mut position = 0;
+    while generated.len() < max_tokens {
+        let tensor = Tensor::new(input.as_slice(), device)
+            .and_then(|t| t.unsqueeze(0))
+            .map_err(candle_error)?;
+        let logits = model
+            .forward(&tensor, position)
+            .and_then(|logits| logits.squeeze(0))
+            .map_err(candle_error)?;
+        let next = sampler.sample(&logits).map_err(candle_error)?;
+
+        position += input.len();
+        if Some(next) == eos {
+            break;
+        }
+        generated.push(next);
+        input = vec![next];
+    }
+
+    let text = tokenizer
+        .decode(&generated, true)
+        .map_err(|err| Error::LlmCommunication(format!("Failed to decode output: {err}")))?;
+    let usage = TokenUsage {
+        prompt_tokens: prompt_tokens.len() as u64,
+        completion_tokens: generated.len() as u64,
+        total_tokens: (prompt_tokens.len() + generated.len()) as u64,
+    };
+    Ok((extract_json_object(&text).to_string(), usage))
+}
+
+/// Small models often wrap the object in prose or code fences.
+fn extract_json_object(text: &str) -> &str {
+    match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => text.trim(),
+    }
+}
+
+fn candle_error(err: candle_core::Error) -> Error {
+    Error::LlmCommunication(format!("Local model error: {err}"))
+}
@@ -0,0 +1,169 @@
+//! Deterministic provider for tests and demos that never leaves the process.
+
+use crate::api::llm_gateway::PlanningMode;
+use crate::api::llm_provider::{ChatMessage, CompletionFuture, LlmProvider};
+use crate::api::usage::TokenUsage;
+use crate::campaign::objectives::{infer_default_target, NAMED_METRICS};
+use crate::campaign::runner::fallback_task_for_target;
+use crate::core::error::{Error, Result};
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const DEFAULT_METRIC: &str = "v_geometric";
+
+/// Replays canned responses in order, or derives a command from the prompt
+/// with the campaign fallback rules when none are configured.
+pub struct MockProvider {
+    responses: Vec<Value>,
+    next: AtomicUsize,
+}
+
+impl MockProvider {
+    /// Rule-derived responses only.
+    pub fn new() -> Self {
+        Self::with_responses(Vec::new())
+    }
+
+    /// Cycle through `responses`; each is returned verbatim as the model
+    /// output, so invalid ones exercise the repair loop.
+    pub fn with_responses(responses: Vec<Value>) -> Self {
+        Self {
+            responses,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Load a JSON array of responses from `MMSS_MOCK_RESPONSES` if set.
+    pub fn from_env() -> Result<Self> {
+        match env::var("MMSS_MOCK_RESPONSES") {
+            Ok(path) => Self::from_file(Path::new(&path)),
+            Err(_) => Ok(Self::new()),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        match serde_json::from_str(&fs::read_to_string(path)?)? {
+            Value::Array(responses) => Ok(Self::with_responses(responses)),
+            _ => Err(Error::InvalidParameter(
+                "MMSS_MOCK_RESPONSES".into(),
+                "expected a JSON array of responses".into(),
+            )),
+        }
+    }
+
+    fn respond(&self, messages: &[ChatMessage]) -> Result<String> {
+        if !self.responses.is_empty() {
+            let index = self.next.fetch_add(1, Ordering::Relaxed) % self.responses.len();
+            return Ok(match &self.responses[index] {
+                Value::String(raw) => raw.clone(),
+                other => other.to_string(),
+            });
+        }
+
+        let metric = messages
+            .iter()
+            .rev()
+            .find(|m| m.role == "user")
+            .and_then(|m| mentioned_metric(&m.content))
+            .unwrap_or(DEFAULT_METRIC);
+        let task = fallback_task_for_target(metric, infer_default_target(metric));
+        Ok(serde_json::to_string(&task)?)
+    }
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LlmProvider for MockProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn model(&self) -> &str {
+        "mock"
+    }
+
+    fn complete<'a>(
+        &'a self,
+        messages: &'a [ChatMessage],
+        _mode: PlanningMode,
+    ) -> CompletionFuture<'a> {
+        Box::pin(async move {
+            let content = self.respond(messages)?;
+            let prompt_chars: usize = messages.iter().map(|m| m.content.len()).sum();
+            // rough chars-per-token estimate so budgets still have something to count
+            let usage = TokenUsage {
+                prompt_tokens: (prompt_chars / 4) as u64,
+                completion_tokens: (content.len() / 4) as u64,
+                total_tokens: ((prompt_chars + content.len()) / 4) as u64,
+            };
+            Ok((content, usage))
+        })
+    }
+}
+
+/// The first metric named in the query part of a user message; the context
+/// JSON before `Query:` mentions every metric and is skipped.
+fn mentioned_metric(content: &str) -> Option<&'static str> {
+    let query = content
+        .rfind("Query:")
+        .map_or(content, |start| &content[start..]);
+    NAMED_METRICS
+        .iter()
+        .filter_map(|name| query.find(name).map(|position| (position, *name)))
+        .min()
+        .map(|(_, name)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::llm_gateway::LlmGateway;
+    use crate::api::usage::UsageScope;
+    use crate::core::types::GeometricOperator;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_rule_derived_command_follows_query_metric() {
+        let gateway = LlmGateway::with_provider(Arc::new(MockProvider::new())).unwrap();
+        let context = json!({ "current_metrics": { "v_geometric": 1.0 } });
+
+        let task = gateway
+            .submit_geometric_query(
+                "raise topological_winding",
+                &context,
+                &UsageScope::for_key(None),
+            )
+            .await
+            .unwrap();
+        assert_eq!(task.geometric_operator, GeometricOperator::Zitterbewegung);
+        assert_eq!(task.expected_output_metric, "topological_winding");
+    }
+
+    #[tokio::test]
+    async fn test_canned_responses_go_through_repair() {
+        let valid = json!({
+            "task_name": "Derive",
+            "geometric_operator": "GeometricDerivation",
+            "target_module": "sys5_topology",
+            "parameters": { "delta": 0.5 },
+            "expected_output_metric": "s_geometric",
+        });
+        let provider = MockProvider::with_responses(vec![json!("not json"), valid]);
+        let gateway = LlmGateway::with_provider(Arc::new(provider)).unwrap();
+
+        let task = gateway
+            .submit_geometric_query("derive", &Value::Null, &UsageScope::for_key(None))
+            .await
+            .unwrap();
+        assert_eq!(task.target_module, "sys5_topology");
+        assert!(gateway.usage().report().unwrap().requests >= 2);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::core::error::{Error, Result};
+use log::{error, info};
+use minijinja::Environment;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::{env, fs};
+
+/// System prompt sent with every planning request.
+pub const SYSTEM_TEMPLATE: &str = "system";
+/// User message for a single `/llm/query` call.
+pub const QUERY_TEMPLATE: &str = "query";
+/// Per-step planning query of a research campaign.
+pub const CAMPAIGN_STEP_TEMPLATE: &str = "campaign_step";
+/// Follow-up sent when a response fails schema validation.
+pub const REPAIR_TEMPLATE: &str = "repair";
+
+const TEMPLATE_EXTENSION: &str = "j2";
+
+const DEFAULT_TEMPLATES: &[(&str, &str)] = &[
+    (
+        SYSTEM_TEMPLATE,
+        "You are the MMSS Pure Logic agent. Respond strictly with JSON in the GeometricTaskCommand schema (task_name, geometric_operator, target_module, parameters, expected_output_metric, optional task_id).",
+    ),
+    (
+        QUERY_TEMPLATE,
+        "Context: {{ context }}{% if history %}\n\nEarlier tasks in this session (avoid repeating ones that failed or did not help):\n{% for turn in history %}- {{ turn.query }} -> {{ turn.operator }} {{ turn.parameters }}: {{ turn.outcome }}{% if turn.metric_after is not none %}, {{ turn.expected_output_metric }} = {{ turn.metric_after }}{% endif %}\n{% endfor %}{% endif %}\n\nQuery: {{ query }}",
+    ),
+    (
+        CAMPAIGN_STEP_TEMPLATE,
+        "Design the next geometric operator to move the system toward `{{ goal }}` focusing on {% for objective in objectives %}`{{ objective.metric }}` ({{ objective.goal }} {{ objective.value }}, weight {{ objective.weight }}){% if not loop.last %}, {% endif %}{% endfor %}. Return a single GeometricTaskCommand JSON.",
+    ),
+    (
+        REPAIR_TEMPLATE,
+        "Your previous response did not match the GeometricTaskCommand schema:\n{% for error in errors %}- {{ error }}\n{% endfor %}\nReply again with only the corrected JSON object.",
+    ),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub source: String,
+    /// File the template was loaded from, if it overrides a built-in.
+    pub path: Option<PathBuf>,
+}
+
+/// Named minijinja prompt templates, seeded with built-in defaults and
+/// optionally overridden by `<name>.j2` files in `MMSS_PROMPT_DIR`.
+pub struct PromptStore {
+    directory: Option<PathBuf>,
+    templates: RwLock<BTreeMap<String, PromptTemplate>>,
+}
+
+impl PromptStore {
+    pub fn from_env() -> Result<Self> {
+        Self::new(env::var("MMSS_PROMPT_DIR").ok().map(PathBuf::from))
+    }
+
+    pub fn new(directory: Option<PathBuf>) -> Result<Self> {
+        let store = Self {
+            directory,
+            templates: RwLock::new(BTreeMap::new()),
+        };
+        store.reload()?;
+        Ok(store)
+    }
+
+    /// Reset to the defaults and re-read template files, discarding edits.
+    pub fn reload(&self) -> Result<usize> {
+        let mut loaded = default_templates();
+        if let Some(directory) = &self.directory {
+            for template in load_directory(directory)? {
+                loaded.insert(template.name.clone(), template);
+            }
+        }
+
+        let count = loaded.len();
+        *self.write()? = loaded;
+        Ok(count)
+    }
+
+    pub fn list(&self) -> Result<Vec<PromptTemplate>> {
+        Ok(self.read()?.values().cloned().collect())
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<PromptTemplate>> {
+        Ok(self.read()?.get(name).cloned())
+    }
+
+    /// Replace a template after checking that it compiles.
+    pub fn set(&self, name: &str, source: String) -> Result<()> {
+        if name.trim().is_empty() {
+            return Err(Error::InvalidParameter(
+                "name".into(),
+                "template name cannot be empty".into(),
+            ));
+        }
+        compile_check(name, &source)?;
+
+        self.write()?.insert(
+            name.to_string(),
+            PromptTemplate {
+                name: name.to_string(),
+                source,
+                path: None,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn render<S: Serialize>(&self, name: &str, vars: S) -> Result<String> {
+        let source = self
+            .read()?
+            .get(name)
+            .map(|template| template.source.clone())
+            .ok_or_else(|| Error::Template(format!("unknown prompt template `{name}`")))?;
+
+        Environment::new()
+            .render_str(&source, vars)
+            .map_err(|err| Error::Template(format!("failed to render `{name}`: {err}")))
+    }
+
+    fn read(&self) -> Result<std::sync::RwLockReadGuard<'_, BTreeMap<String, PromptTemplate>>> {
+        self.templates.read().map_err(|e| {
+            error!("Failed to lock prompt templates: {}", e);
+            Error::Template("Failed to access prompt templates".to_string())
+        })
+    }
+
+    fn write(&self) -> Result<std::sync::RwLockWriteGuard<'_, BTreeMap<String, PromptTemplate>>> {
+        self.templates.write().map_err(|e| {
+            error!("Failed to lock prompt templates: {}", e);
+            Error::Template("Failed to access prompt templates".to_string())
+        })
+    }
+}
+
+fn default_templates() -> BTreeMap<String, PromptTemplate> {
+    DEFAULT_TEMPLATES
+        .iter()
+        .map(|(name, source)| {
+            (
+                name.to_string(),
+                PromptTemplate {
+                    name: name.to_string(),
+                    source: source.to_string(),
+                    path: None,
+                },
+            )
+        })
+        .collect()
+}
+
+fn load_directory(directory: &Path) -> Result<Vec<PromptTemplate>> {
+    let mut templates = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(TEMPLATE_EXTENSION) {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+
+        let source = fs::read_to_string(&path)?;
+        compile_check(name, &source)?;
+        info!("Loaded prompt template {} from {}", name, path.display());
+        templates.push(PromptTemplate {
+            name: name.to_string(),
+            source,
+            path: Some(path.clone()),
+        });
+    }
+    Ok(templates)
+}
+
+fn compile_check(name: &str, source: &str) -> Result<()> {
+    Environment::new()
+        .template_from_str(source)
+        .map(|_| ())
+        .map_err(|err| Error::Template(format!("invalid template `{name}`: {err}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_defaults_render() {
+        let store = PromptStore::new(None).unwrap();
+        let rendered = store
+            .render(
+                REPAIR_TEMPLATE,
+                json!({ "errors": ["missing `task_name`"] }),
+            )
+            .unwrap();
+        assert!(rendered.contains("- missing `task_name`"));
+    }
+
+    #[test]
+    fn test_set_rejects_invalid_and_reload_restores() {
+        let store = PromptStore::new(None).unwrap();
+        assert!(store.set(QUERY_TEMPLATE, "{{ unclosed".into()).is_err());
+
+        store.set(QUERY_TEMPLATE, "Q={{ query }}".into()).unwrap();
+        let rendered = store
+            .render(QUERY_TEMPLATE, json!({ "query": "x" }))
+            .unwrap();
+        assert_eq!(rendered, "Q=x");
+
+        store.reload().unwrap();
+        let rendered = store
+            .render(QUERY_TEMPLATE, json!({ "query": "x", "context": 1 }))
+            .unwrap();
+        assert!(rendered.starts_with("Context: 1"));
+    }
+
+    #[test]
+    fn test_query_renders_session_history() {
+        let store = PromptStore::new(None).unwrap();
+        let history = json!([{
+            "query": "raise q",
+            "operator": "Zitterbewegung",
+            "parameters": "{\"frequency_scale\":2.0}",
+            "expected_output_metric": "q_oscillator",
+            "outcome": "completed",
+            "metric_after": 1.5,
+        }]);
+
+        let rendered = store
+            .render(
+                QUERY_TEMPLATE,
+                json!({ "query": "x", "context": "{}", "history": history }),
+            )
+            .unwrap();
+        assert!(rendered.contains("- raise q -> Zitterbewegung"));
+        assert!(rendered.contains("q_oscillator = 1.5"));
+
+        let rendered = store
+            .render(QUERY_TEMPLATE, json!({ "query": "x", "context": "{}", "history": [] }))
+            .unwrap();
+        assert!(!rendered.contains("Earlier tasks"));
+    }
+}
@@ -0,0 +1,368 @@
+//! Retry with backoff and a circuit breaker around provider calls.
+
+use crate::core::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use log::{error, warn};
+use rand::Rng;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::env;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Exponential backoff with jitter for transport-level failures.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            base_delay_ms: 250,
+            max_delay_ms: 4_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Read `MMSS_LLM_RETRIES`, `MMSS_LLM_RETRY_BASE_MS` and `MMSS_LLM_RETRY_MAX_MS`.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_retries: read_env("MMSS_LLM_RETRIES").unwrap_or(defaults.max_retries),
+            base_delay_ms: read_env("MMSS_LLM_RETRY_BASE_MS").unwrap_or(defaults.base_delay_ms),
+            max_delay_ms: read_env("MMSS_LLM_RETRY_MAX_MS").unwrap_or(defaults.max_delay_ms),
+        }
+    }
+
+    /// Delay before retry `attempt` (0-based): capped `base * 2^attempt`,
+    /// scaled by a random factor in `[0.5, 1.0)` so callers do not retry in lockstep.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay_ms
+            .saturating_mul(1u64 << attempt.min(20))
+            .min(self.max_delay_ms);
+        let jitter = rand::thread_rng().gen_range(0.5..1.0);
+        Duration::from_millis((exponential as f64 * jitter) as u64)
+    }
+
+    /// Only transport and provider errors are worth retrying; validation and
+    /// budget failures would fail the same way again.
+    pub fn is_retryable(err: &Error) -> bool {
+        matches!(err, Error::LlmCommunication(_))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BreakerSettings {
+    /// Number of recent calls the failure rate is computed over.
+    pub window: usize,
+    /// Calls required in the window before the breaker may open.
+    pub min_calls: usize,
+    pub failure_threshold: f64,
+    pub cooldown_secs: u64,
+}
+
+impl Default for BreakerSettings {
+    fn default() -> Self {
+        Self {
+            window: 20,
+            min_calls: 5,
+            failure_threshold: 0.5,
+            cooldown_secs: 30,
+        }
+    }
+}
+
+impl BreakerSettings {
+    /// Read `MMSS_LLM_BREAKER_WINDOW`, `_MIN_CALLS`, `_THRESHOLD` and `_COOLDOWN_SECS`.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            window: read_env("MMSS_LLM_BREAKER_WINDOW").unwrap_or(defaults.window),
+            min_calls: read_env("MMSS_LLM_BREAKER_MIN_CALLS").unwrap_or(defaults.min_calls),
+            failure_threshold: read_env("MMSS_LLM_BREAKER_THRESHOLD")
+                .unwrap_or(defaults.failure_threshold),
+            cooldown_secs: read_env("MMSS_LLM_BREAKER_COOLDOWN_SECS")
+                .unwrap_or(defaults.cooldown_secs),
+        }
+    }
+}
+
+fn read_env<T: std::str::FromStr>(name: &str) -> Option<T> {
+    env::var(name).ok().and_then(|raw| raw.parse().ok())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    /// Cooldown elapsed; a single probe call decides whether to close again.
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    pub failure_rate: f64,
+    pub recent_calls: usize,
+    pub opened_at: Option<DateTime<Utc>>,
+    pub retry_after_secs: Option<u64>,
+    pub settings: BreakerSettings,
+}
+
+struct BreakerInner {
+    state: BreakerState,
+    outcomes: VecDeque<bool>,
+    opened: Option<(Instant, DateTime<Utc>)>,
+    probe_in_flight: bool,
+}
+
+impl BreakerInner {
+    fn trip(&mut self) {
+        self.state = BreakerState::Open;
+        self.opened = Some((Instant::now(), Utc::now()));
+    }
+}
+
+/// Opens when the failure rate over the last `window` calls exceeds the
+/// threshold and rejects calls until `cooldown_secs` have passed.
+pub struct CircuitBreaker {
+    settings: BreakerSettings,
+    inner: Mutex<BreakerInner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(settings: BreakerSettings) -> Self {
+        Self {
+            settings,
+            inner: Mutex::new(BreakerInner {
+                state: BreakerState::Closed,
+                outcomes: VecDeque::new(),
+                opened: None,
+                probe_in_flight: false,
+            }),
+        }
+    }
+
+    /// Admit a call, or fail with `Error::CircuitOpen`. The call's outcome
+    /// goes through the returned permit.
+    pub fn acquire(&self) -> Result<BreakerPermit<'_>> {
+        let mut inner = self.lock()?;
+        let probe = match inner.state {
+            BreakerState::Closed => false,
+            BreakerState::HalfOpen if !inner.probe_in_flight => {
+                inner.probe_in_flight = true;
+                true
+            }
+            BreakerState::HalfOpen => {
+                return Err(Error::CircuitOpen(
+                    "provider probe already in flight".into(),
+                ))
+            }
+            BreakerState::Open => {
+                let cooldown = Duration::from_secs(self.settings.cooldown_secs);
+                let elapsed = inner.opened.map_or(cooldown, |(at, _)| at.elapsed());
+                if elapsed >= cooldown {
+                    inner.state = BreakerState::HalfOpen;
+                    inner.probe_in_flight = true;
+                    true
+                } else {
+                    return Err(Error::CircuitOpen(format!(
+                        "retry in {}s",
+                        (cooldown - elapsed).as_secs().max(1)
+                    )));
+                }
+            }
+        };
+        Ok(BreakerPermit {
+            breaker: self,
+            probe,
+            recorded: false,
+        })
+    }
+
+    fn record(&self, success: bool) -> Result<()> {
+        let mut inner = self.lock()?;
+
+        if inner.state == BreakerState::HalfOpen {
+            inner.probe_in_flight = false;
+            if success {
+                inner.state = BreakerState::Closed;
+                inner.outcomes.clear();
+                inner.opened = None;
+            } else {
+                inner.trip();
+            }
+            return Ok(());
+        }
+
+        inner.outcomes.push_back(success);
+        while inner.outcomes.len() > self.settings.window.max(1) {
+            inner.outcomes.pop_front();
+        }
+
+        let calls = inner.outcomes.len();
+        if inner.state == BreakerState::Closed
+            && calls >= self.settings.min_calls
+            && failure_rate(&inner.outcomes) >= self.settings.failure_threshold
+        {
+            warn!(
+                "LLM circuit breaker opened: {:.0}% of the last {} calls failed",
+                failure_rate(&inner.outcomes) * 100.0,
+                calls
+            );
+            inner.trip();
+        }
+        Ok(())
+    }
+
+    pub fn status(&self) -> Result<BreakerStatus> {
+        let inner = self.lock()?;
+        let retry_after_secs = match (inner.state, inner.opened) {
+            (BreakerState::Open, Some((at, _))) => Some(
+                self.settings
+                    .cooldown_secs
+                    .saturating_sub(at.elapsed().as_secs()),
+            ),
+            _ => None,
+        };
+        Ok(BreakerStatus {
+            state: inner.state,
+            failure_rate: failure_rate(&inner.outcomes),
+            recent_calls: inner.outcomes.len(),
+            opened_at: inner.opened.map(|(_, at)| at),
+            retry_after_secs,
+            settings: self.settings,
+        })
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, BreakerInner>> {
+        self.inner.lock().map_err(|e| {
+            error!("Failed to lock circuit breaker: {}", e);
+            Error::TaskExecution("Failed to access circuit breaker".to_string())
+        })
+    }
+}
+
+/// One call admitted by `CircuitBreaker::acquire`. A half-open probe
+/// dropped before its outcome is recorded, e.g. with a cancelled request,
+/// opens the breaker again rather than leaving it waiting on the probe.
+#[must_use = "the call's outcome is recorded through its permit"]
+pub struct BreakerPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+    recorded: bool,
+}
+
+impl BreakerPermit<'_> {
+    pub fn record(mut self, success: bool) -> Result<()> {
+        self.recorded = true;
+        self.breaker.record(success)
+    }
+}
+
+impl Drop for BreakerPermit<'_> {
+    fn drop(&mut self) {
+        if !self.probe || self.recorded {
+            return;
+        }
+        if let Ok(mut inner) = self.breaker.lock() {
+            if inner.state == BreakerState::HalfOpen && inner.probe_in_flight {
+                warn!("LLM provider probe was abandoned; reopening the circuit breaker");
+                inner.probe_in_flight = false;
+                inner.trip();
+            }
+        }
+    }
+}
+
+fn failure_rate(outcomes: &VecDeque<bool>) -> f64 {
+    if outcomes.is_empty() {
+        return 0.0;
+    }
+    outcomes.iter().filter(|ok| !**ok).count() as f64 / outcomes.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(cooldown_secs: u64) -> CircuitBreaker {
+        CircuitBreaker::new(BreakerSettings {
+            window: 4,
+            min_calls: 4,
+            failure_threshold: 0.5,
+            cooldown_secs,
+        })
+    }
+
+    #[test]
+    fn test_delay_is_capped_and_jittered() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay_ms: 100,
+            max_delay_ms: 300,
+        };
+        let first = policy.delay(0).as_millis();
+        assert!((50..100).contains(&first));
+        assert!(policy.delay(10).as_millis() < 300);
+    }
+
+    #[test]
+    fn test_breaker_opens_on_failure_rate() {
+        let breaker = breaker(60);
+        for success in [true, false, true] {
+            breaker.record(success).unwrap();
+        }
+        assert_eq!(breaker.status().unwrap().state, BreakerState::Closed);
+
+        breaker.record(false).unwrap();
+        assert_eq!(breaker.status().unwrap().state, BreakerState::Open);
+        assert!(matches!(breaker.acquire(), Err(Error::CircuitOpen(_))));
+    }
+
+    #[test]
+    fn test_half_open_probe_closes_breaker() {
+        let breaker = breaker(0);
+        for _ in 0..4 {
+            breaker.record(false).unwrap();
+        }
+
+        let probe = breaker.acquire().unwrap();
+        assert_eq!(breaker.status().unwrap().state, BreakerState::HalfOpen);
+        assert!(breaker.acquire().is_err());
+
+        probe.record(true).unwrap();
+        let status = breaker.status().unwrap();
+        assert_eq!(status.state, BreakerState::Closed);
+        assert_eq!(status.recent_calls, 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_probe_reopens_breaker() {
+        let breaker = breaker(0);
+        for _ in 0..4 {
+            breaker.record(false).unwrap();
+        }
+
+        let call = async {
+            let _probe = breaker.acquire().unwrap();
+            std::future::pending::<()>().await;
+        };
+        let cancelled = tokio::time::timeout(Duration::from_millis(10), call).await;
+        assert!(cancelled.is_err());
+        assert_eq!(breaker.status().unwrap().state, BreakerState::Open);
+
+        // the cooldown is over, so the next call probes again
+        let probe = breaker.acquire().unwrap();
+        assert_eq!(breaker.status().unwrap().state, BreakerState::HalfOpen);
+        probe.record(true).unwrap();
+        assert_eq!(breaker.status().unwrap().state, BreakerState::Closed);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::campaign::objectives::metric_value;
+use crate::core::error::{Error, Result};
+use crate::core::semantic_task_processor::TaskStatus;
+use crate::core::types::GeometricTaskCommand;
+use chrono::{DateTime, Utc};
+use log::error;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+const DEFAULT_MAX_TURNS: usize = 8;
+
+/// One planned task within a conversation session.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionTurn {
+    pub query: String,
+    /// Planned command; always carries a `task_id` so its execution can be traced.
+    pub task: GeometricTaskCommand,
+    pub planned_at: DateTime<Utc>,
+}
+
+impl SessionTurn {
+    fn task_id(&self) -> Option<Uuid> {
+        self.task.task_id
+    }
+}
+
+/// Recent planning turns per `session_id`, capped at `max_turns` each.
+pub struct SessionStore {
+    max_turns: usize,
+    sessions: Mutex<HashMap<String, VecDeque<SessionTurn>>>,
+}
+
+impl SessionStore {
+    /// Read `MMSS_SESSION_HISTORY` for the number of turns kept per session.
+    pub fn from_env() -> Self {
+        Self::new(
+            env::var("MMSS_SESSION_HISTORY")
+                .ok()
+                .and_then(|raw| raw.parse().ok())
+                .unwrap_or(DEFAULT_MAX_TURNS),
+        )
+    }
+
+    pub fn new(max_turns: usize) -> Self {
+        Self {
+            max_turns: max_turns.max(1),
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn max_turns(&self) -> usize {
+        self.max_turns
+    }
+
+    /// Append a turn, assigning a task ID if the model did not provide one.
+    /// Returns the command as stored.
+    pub fn record(
+        &self,
+        session_id: &str,
+        query: &str,
+        mut task: GeometricTaskCommand,
+    ) -> Result<GeometricTaskCommand> {
+        task.task_id.get_or_insert_with(Uuid::new_v4);
+
+        let mut sessions = self.lock()?;
+        let turns = sessions.entry(session_id.to_string()).or_default();
+        turns.push_back(SessionTurn {
+            query: query.to_string(),
+            task: task.clone(),
+            planned_at: Utc::now(),
+        });
+        while turns.len() > self.max_turns {
+            turns.pop_front();
+        }
+        Ok(task)
+    }
+
+    pub fn history(&self, session_id: &str) -> Result<Vec<SessionTurn>> {
+        Ok(self
+            .lock()?
+            .get(session_id)
+            .map(|turns| turns.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    /// Forget a session. Returns `false` if it did not exist.
+    pub fn clear(&self, session_id: &str) -> Result<bool> {
+        Ok(self.lock()?.remove(session_id).is_some())
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, HashMap<String, VecDeque<SessionTurn>>>> {
+        self.sessions.lock().map_err(|e| {
+            error!("Failed to lock sessions: {}", e);
+            Error::TaskExecution("Failed to access sessions".to_string())
+        })
+    }
+}
+
+/// Compact description of each turn and how its task turned out, for the
+/// planning prompt. `status_of` looks up the processor's task status.
+pub fn summarize_history<F>(turns: &[SessionTurn], status_of: F) -> Vec<Value>
+where
+    F: Fn(Uuid) -> Option<TaskStatus>,
+{
+    turns
+        .iter()
+        .map(|turn| {
+            let metric = &turn.task.expected_output_metric;
+            let (outcome, metric_after) = match turn.task_id().and_then(&status_of) {
+                None => ("not submitted".to_string(), None),
+                Some(TaskStatus::AwaitingApproval) => ("awaiting approval".to_string(), None),
+                Some(TaskStatus::Pending) | Some(TaskStatus::InProgress) => {
+                    ("pending".to_string(), None)
+                }
+                Some(TaskStatus::Completed(metrics)) => (
+                    "completed".to_string(),
+                    Some(metric_value(&metrics, metric)),
+                ),
+                Some(TaskStatus::Failed(reason)) => (format!("failed: {reason}"), None),
+            };
+
+            json!({
+                "query": turn.query,
+                "operator": turn.task.geometric_operator,
+                "parameters": turn.task.parameters.to_string(),
+                "expected_output_metric": metric,
+                "outcome": outcome,
+                "metric_after": metric_after,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::GeometricOperator;
+
+    fn command(name: &str) -> GeometricTaskCommand {
+        GeometricTaskCommand {
+            task_name: name.into(),
+            geometric_operator: GeometricOperator::Zitterbewegung,
+            target_module: "sys6_resonator".into(),
+            parameters: json!({ "frequency_scale": 1.0 }),
+            expected_output_metric: "q_oscillator".into(),
+            task_id: None,
+        }
+    }
+
+    #[test]
+    fn test_history_is_capped_and_ids_assigned() {
+        let store = SessionStore::new(2);
+        for name in ["a", "b", "c"] {
+            let stored = store.record("s1", name, command(name)).unwrap();
+            assert!(stored.task_id.is_some());
+        }
+
+        let history = store.history("s1").unwrap();
+        let names: Vec<_> = history.iter().map(|t| t.task.task_name.as_str()).collect();
+        assert_eq!(names, ["b", "c"]);
+        assert!(store.history("other").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_summary_reports_task_outcomes() {
+        let store = SessionStore::new(4);
+        let failed = store.record("s1", "first", command("a")).unwrap();
+        store.record("s1", "second", command("b")).unwrap();
+
+        let summary = summarize_history(&store.history("s1").unwrap(), |id| {
+            (Some(id) == failed.task_id).then(|| TaskStatus::Failed("diverged".into()))
+        });
+
+        assert_eq!(summary[0]["outcome"], "failed: diverged");
+        assert_eq!(summary[1]["outcome"], "not submitted");
+    }
+}
@@ -0,0 +1,245 @@
+use crate::core::error::{Error, Result};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Caller identity used when a request carries no API key.
+pub const ANONYMOUS_KEY: &str = "anonymous";
+
+/// Token counts reported by the provider for one or more completions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+}
+
+/// Who a gateway call is billed to.
+#[derive(Debug, Clone)]
+pub struct UsageScope {
+    pub api_key: String,
+    pub campaign_id: Option<Uuid>,
+}
+
+impl UsageScope {
+    pub fn for_key(api_key: Option<&str>) -> Self {
+        Self {
+            api_key: api_key
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .unwrap_or(ANONYMOUS_KEY)
+                .to_string(),
+            campaign_id: None,
+        }
+    }
+
+    pub fn with_campaign(mut self, campaign_id: Uuid) -> Self {
+        self.campaign_id = Some(campaign_id);
+        self
+    }
+}
+
+/// Total-token limits; `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TokenBudgets {
+    pub per_api_key: Option<u64>,
+    pub per_campaign: Option<u64>,
+}
+
+impl TokenBudgets {
+    /// Read `MMSS_TOKEN_BUDGET_PER_KEY` and `MMSS_TOKEN_BUDGET_PER_CAMPAIGN`.
+    pub fn from_env() -> Self {
+        let read = |name: &str| env::var(name).ok().and_then(|raw| raw.parse().ok());
+        Self {
+            per_api_key: read("MMSS_TOKEN_BUDGET_PER_KEY"),
+            per_campaign: read("MMSS_TOKEN_BUDGET_PER_CAMPAIGN"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    pub total: TokenUsage,
+    pub requests: u64,
+    pub per_api_key: HashMap<String, TokenUsage>,
+    pub per_campaign: HashMap<Uuid, TokenUsage>,
+    pub budgets: TokenBudgets,
+}
+
+#[derive(Default)]
+struct Ledger {
+    total: TokenUsage,
+    requests: u64,
+    per_api_key: HashMap<String, TokenUsage>,
+    per_campaign: HashMap<Uuid, TokenUsage>,
+}
+
+/// Aggregates token usage and enforces budgets.
+pub struct UsageTracker {
+    budgets: TokenBudgets,
+    ledger: Mutex<Ledger>,
+}
+
+impl UsageTracker {
+    pub fn new(budgets: TokenBudgets) -> Self {
+        Self {
+            budgets,
+            ledger: Mutex::new(Ledger::default()),
+        }
+    }
+
+    pub fn budgets(&self) -> TokenBudgets {
+        self.budgets
+    }
+
+    /// Fail with `Error::BudgetExceeded` if the scope has no tokens left.
+    pub fn ensure_within_budget(&self, scope: &UsageScope) -> Result<()> {
+        let ledger = self.lock()?;
+
+        if let Some(limit) = self.budgets.per_api_key {
+            let used = ledger
+                .per_api_key
+                .get(&scope.api_key)
+                .map_or(0, |usage| usage.total_tokens);
+            if used >= limit {
+                return Err(Error::BudgetExceeded(format!(
+                    "API key {} used {used} of {limit} tokens",
+                    mask_key(&scope.api_key)
+                )));
+            }
+        }
+
+        if let (Some(limit), Some(campaign_id)) = (self.budgets.per_campaign, scope.campaign_id) {
+            let used = ledger
+                .per_campaign
+                .get(&campaign_id)
+                .map_or(0, |usage| usage.total_tokens);
+            if used >= limit {
+                return Err(Error::BudgetExceeded(format!(
+                    "campaign {campaign_id} used {used} of {limit} tokens"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn record(&self, scope: &UsageScope, usage: &TokenUsage) -> Result<()> {
+        let mut ledger = self.lock()?;
+        ledger.total.add(usage);
+        ledger.requests += 1;
+        ledger
+            .per_api_key
+            .entry(scope.api_key.clone())
+            .or_default()
+            .add(usage);
+        if let Some(campaign_id) = scope.campaign_id {
+            ledger
+                .per_campaign
+                .entry(campaign_id)
+                .or_default()
+                .add(usage);
+        }
+        Ok(())
+    }
+
+    pub fn campaign_usage(&self, campaign_id: Uuid) -> Result<TokenUsage> {
+        let ledger = self.lock()?;
+        Ok(ledger
+            .per_campaign
+            .get(&campaign_id)
+            .copied()
+            .unwrap_or_default())
+    }
+
+    /// Snapshot of all counters with API keys masked.
+    pub fn report(&self) -> Result<UsageReport> {
+        let ledger = self.lock()?;
+        Ok(UsageReport {
+            total: ledger.total,
+            requests: ledger.requests,
+            per_api_key: ledger
+                .per_api_key
+                .iter()
+                .map(|(key, usage)| (mask_key(key), *usage))
+                .collect(),
+            per_campaign: ledger.per_campaign.clone(),
+            budgets: self.budgets,
+        })
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Ledger>> {
+        self.ledger.lock().map_err(|e| {
+            error!("Failed to lock usage ledger: {}", e);
+            Error::LlmCommunication("Failed to access usage ledger".to_string())
+        })
+    }
+}
+
+pub(crate) fn mask_key(key: &str) -> String {
+    if key == ANONYMOUS_KEY || key.chars().count() <= 8 {
+        return key.to_string();
+    }
+    let prefix: String = key.chars().take(4).collect();
+    format!("{prefix}…")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(total: u64) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens: total / 2,
+            completion_tokens: total - total / 2,
+            total_tokens: total,
+        }
+    }
+
+    #[test]
+    fn test_budget_rejects_after_exhaustion() {
+        let tracker = UsageTracker::new(TokenBudgets {
+            per_api_key: Some(100),
+            per_campaign: None,
+        });
+        let scope = UsageScope::for_key(Some("team-a-secret-key"));
+
+        tracker.ensure_within_budget(&scope).unwrap();
+        tracker.record(&scope, &usage(120)).unwrap();
+
+        let err = tracker.ensure_within_budget(&scope).unwrap_err();
+        assert!(matches!(err, Error::BudgetExceeded(_)));
+        assert!(tracker
+            .ensure_within_budget(&UsageScope::for_key(None))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_campaign_usage_aggregates() {
+        let tracker = UsageTracker::new(TokenBudgets::default());
+        let campaign = Uuid::new_v4();
+        let scope = UsageScope::for_key(None).with_campaign(campaign);
+
+        tracker.record(&scope, &usage(10)).unwrap();
+        tracker.record(&scope, &usage(30)).unwrap();
+
+        assert_eq!(tracker.campaign_usage(campaign).unwrap().total_tokens, 40);
+        let report = tracker.report().unwrap();
+        assert_eq!(report.requests, 2);
+        assert_eq!(report.per_api_key[ANONYMOUS_KEY].total_tokens, 40);
+    }
+}
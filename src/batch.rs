@@ -0,0 +1,308 @@
+//! Headless runs for parameter sweeps: tasks from a file go through a
+//! `SemanticTaskProcessor` in order, without the HTTP server, and the
+//! metrics history and one result row per task are written as Arrow or
+//! Parquet files in the output directory.
+
+use crate::core::error::{Error, Result};
+use crate::core::metrics_history::{
+    snapshot_batch, snapshot_schema, write_batch, HistoryFormat, MetricsHistory,
+    MetricsHistoryConfig, MetricsSnapshot,
+};
+use crate::core::semantic_task_processor::{SemanticTaskProcessor, TaskStatus};
+use crate::core::types::{GeometricTaskCommand, TaskExecutionResult};
+use datafusion::arrow::array::{ArrayRef, BooleanArray, StringArray};
+use datafusion::arrow::datatypes::{DataType, Field, Schema};
+use datafusion::arrow::record_batch::RecordBatch;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Snapshots per metrics history file.
+const HISTORY_FLUSH_ROWS: usize = 4096;
+
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    pub output_dir: PathBuf,
+    pub format: HistoryFormat,
+    /// Custom metrics kept as their own columns in both files.
+    pub custom_metrics: Vec<String>,
+    /// Run script tasks the policy would hold for approval.
+    pub approve_scripts: bool,
+}
+
+/// What happened to one task.
+struct Outcome {
+    task: GeometricTaskCommand,
+    snapshot: MetricsSnapshot,
+    success: bool,
+    error: Option<String>,
+    output: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchReport {
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Tasks refused by the script policy or left awaiting approval.
+    pub skipped: usize,
+    /// One row per task, with the metrics after it.
+    pub results: PathBuf,
+    /// Directory of the metrics history, one snapshot per completed task.
+    pub history: PathBuf,
+}
+
+/// Task commands from a JSON or TOML file: a list of commands, or a
+/// `tasks` list of them (the only form TOML allows).
+pub fn load_tasks(path: &Path) -> Result<Vec<GeometricTaskCommand>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TaskFile {
+        List(Vec<GeometricTaskCommand>),
+        Tasks { tasks: Vec<GeometricTaskCommand> },
+    }
+
+    let text = fs::read_to_string(path)?;
+    let invalid = |e: String| {
+        Error::InvalidParameter(
+            path.display().to_string(),
+            format!("not a task file: {}", e),
+        )
+    };
+    let file: TaskFile = if path.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str(&text).map_err(|e| invalid(e.to_string()))?
+    } else {
+        serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?
+    };
+    Ok(match file {
+        TaskFile::List(tasks) | TaskFile::Tasks { tasks } => tasks,
+    })
+}
+
+/// Run `tasks` one after another on `processor`, which should be fresh:
+/// every task sees the emergence state the previous ones left.
+pub fn run_batch(
+    processor: SemanticTaskProcessor,
+    tasks: Vec<GeometricTaskCommand>,
+    config: &BatchConfig,
+) -> Result<BatchReport> {
+    fs::create_dir_all(&config.output_dir)?;
+    let history = Arc::new(MetricsHistory::new(MetricsHistoryConfig {
+        directory: config.output_dir.join("history"),
+        format: config.format,
+        custom_metrics: config.custom_metrics.clone(),
+        flush_rows: HISTORY_FLUSH_ROWS,
+        snapshot_period: None,
+        recent_snapshots: 0,
+    }));
+    let processor = processor.with_metrics_history(history.clone());
+
+    let total = tasks.len();
+    let mut outcomes = Vec::with_capacity(total);
+    let mut skipped = 0;
+    for (index, mut task) in tasks.into_iter().enumerate() {
+        task.task_id = Some(task.task_id.unwrap_or_else(Uuid::new_v4));
+        let outcome = run_one(&processor, &task, config.approve_scripts);
+        let (success, error, output) = match outcome {
+            Ok(Some(result)) => (result.success, result.error, result.output),
+            Ok(None) => {
+                skipped += 1;
+                (
+                    false,
+                    Some("awaiting approval".to_string()),
+                    serde_json::Value::Null,
+                )
+            }
+            Err(e @ Error::PolicyViolation(_)) => {
+                skipped += 1;
+                (false, Some(e.to_string()), serde_json::Value::Null)
+            }
+            Err(e) => (false, Some(e.to_string()), serde_json::Value::Null),
+        };
+        if let Some(error) = &error {
+            warn!(
+                "Task {}/{} `{}`: {}",
+                index + 1,
+                total,
+                task.task_name,
+                error
+            );
+        }
+        let snapshot = MetricsSnapshot::now(task.task_id, processor.get_metrics())
+            .with_operator(task.geometric_operator);
+        outcomes.push(Outcome {
+            task,
+            snapshot,
+            success,
+            error,
+            output,
+        });
+    }
+
+    let results = config
+        .output_dir
+        .join(format!("results.{}", config.format.extension()));
+    write_batch(
+        &results,
+        config.format,
+        &results_batch(&config.custom_metrics, &outcomes)?,
+    )?;
+    history.flush()?;
+    let succeeded = outcomes.iter().filter(|outcome| outcome.success).count();
+    info!(
+        "Batch finished: {} succeeded, {} failed, {} skipped",
+        succeeded,
+        total - succeeded - skipped,
+        skipped
+    );
+    Ok(BatchReport {
+        succeeded,
+        failed: total - succeeded - skipped,
+        skipped,
+        results,
+        history: history.directory().to_path_buf(),
+    })
+}
+
+/// The task's result, or `None` if it is left awaiting approval.
+fn run_one(
+    processor: &SemanticTaskProcessor,
+    task: &GeometricTaskCommand,
+    approve_scripts: bool,
+) -> Result<Option<TaskExecutionResult>> {
+    let task_id = processor.submit_task(task.clone())?;
+    if processor.get_task_status(task_id)? == TaskStatus::AwaitingApproval {
+        if !approve_scripts {
+            return Ok(None);
+        }
+        processor.approve_task(task_id)?;
+    }
+    processor.execute_task(task_id).map(Some)
+}
+
+/// The snapshot columns after each task, then `task_name`, `operator`,
+/// `success`, `error` and `output` as JSON.
+fn results_batch(custom_metrics: &[String], outcomes: &[Outcome]) -> Result<RecordBatch> {
+    let snapshot_schema = snapshot_schema(custom_metrics);
+    let snapshots: Vec<_> = outcomes
+        .iter()
+        .map(|outcome| outcome.snapshot.clone())
+        .collect();
+    let snapshots =
+        snapshot_batch(&snapshot_schema, custom_metrics, &snapshots).map_err(results_error)?;
+
+    let mut fields: Vec<Field> = snapshot_schema
+        .fields()
+        .iter()
+        .map(|field| field.as_ref().clone())
+        .collect();
+    fields.extend([
+        Field::new("task_name", DataType::Utf8, false),
+        Field::new("operator", DataType::Utf8, false),
+        Field::new("success", DataType::Boolean, false),
+        Field::new("error", DataType::Utf8, true),
+        Field::new("output", DataType::Utf8, false),
+    ]);
+    let mut columns = snapshots.columns().to_vec();
+    columns.extend::<[ArrayRef; 5]>([
+        Arc::new(StringArray::from_iter_values(
+            outcomes
+                .iter()
+                .map(|outcome| outcome.task.task_name.as_str()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            outcomes
+                .iter()
+                .map(|outcome| format!("{:?}", outcome.task.geometric_operator)),
+        )),
+        Arc::new(BooleanArray::from_iter(
+            outcomes.iter().map(|outcome| Some(outcome.success)),
+        )),
+        Arc::new(StringArray::from_iter(
+            outcomes.iter().map(|outcome| outcome.error.clone()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            outcomes.iter().map(|outcome| outcome.output.to_string()),
+        )),
+    ]);
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).map_err(results_error)
+}
+
+fn results_error(e: impl std::fmt::Display) -> Error {
+    Error::TaskExecution(format!("Failed to write batch results: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::script_policy::ScriptPolicy;
+    use crate::core::types::GeometricOperator;
+    use datafusion::parquet::file::reader::{FileReader, SerializedFileReader};
+
+    fn task(name: &str, operator: GeometricOperator) -> GeometricTaskCommand {
+        GeometricTaskCommand {
+            task_name: name.to_string(),
+            geometric_operator: operator,
+            target_module: "emergence_logic".to_string(),
+            parameters: serde_json::json!({}),
+            expected_output_metric: "v_geometric".to_string(),
+            task_id: None,
+        }
+    }
+
+    #[test]
+    fn test_runs_tasks_from_toml_and_writes_parquet() {
+        let dir = std::env::temp_dir().join(format!("mmss-batch-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let tasks_file = dir.join("sweep.toml");
+        fs::write(
+            &tasks_file,
+            r#"
+[[tasks]]
+task_name = "rotate"
+geometric_operator = "QuaternionRotation"
+target_module = "emergence_logic"
+parameters = {}
+expected_output_metric = "v_geometric"
+
+[[tasks]]
+task_name = "jitter"
+geometric_operator = "Zitterbewegung"
+target_module = "emergence_logic"
+parameters = {}
+expected_output_metric = "s_geometric"
+"#,
+        )
+        .unwrap();
+        let mut tasks = load_tasks(&tasks_file).unwrap();
+        assert_eq!(tasks.len(), 2);
+        tasks.push(task("script", GeometricOperator::CustomPythonScript));
+
+        let config = BatchConfig {
+            output_dir: dir.join("out"),
+            format: HistoryFormat::Parquet,
+            custom_metrics: Vec::new(),
+            approve_scripts: false,
+        };
+        let processor = SemanticTaskProcessor::with_script_policy(ScriptPolicy::RequireApproval);
+        let report = run_batch(processor, tasks, &config).unwrap();
+        assert_eq!((report.succeeded, report.failed, report.skipped), (2, 0, 1));
+
+        let rows = |path: &Path| {
+            SerializedFileReader::new(fs::File::open(path).unwrap())
+                .unwrap()
+                .metadata()
+                .file_metadata()
+                .num_rows()
+        };
+        assert_eq!(rows(&report.results), 3);
+        let history: Vec<_> = fs::read_dir(&report.history)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(history.iter().map(|path| rows(path)).sum::<i64>(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+//! Run a file of tasks without the HTTP server:
+//!
+//! ```text
+//! batch <tasks.json|tasks.toml> [--out DIR] [--format arrow|parquet]
+//!       [--custom NAME,...] [--approve-scripts]
+//! ```
+//!
+//! Results go to `DIR/results.<format>` and the metrics history to
+//! `DIR/history/`. The script policy and other settings come from the same
+//! `MMSS_*` variables as the server.
+
+use anyhow::{anyhow, bail};
+use mmss::batch::{load_tasks, run_batch, BatchConfig};
+use mmss::core::metrics_history::HistoryFormat;
+use mmss::core::semantic_task_processor::SemanticTaskProcessor;
+use mmss::telemetry::{LogConfig, Telemetry};
+use std::path::PathBuf;
+
+const USAGE: &str = "usage: batch <tasks.json|tasks.toml> [--out DIR] [--format arrow|parquet] [--custom NAME,...] [--approve-scripts]";
+
+fn main() -> anyhow::Result<()> {
+    dotenvy::dotenv().ok();
+    let _telemetry = Telemetry::install(LogConfig::from_env(), None)?;
+
+    let mut tasks_file = None;
+    let mut config = BatchConfig {
+        output_dir: PathBuf::from("batch-output"),
+        format: HistoryFormat::Parquet,
+        custom_metrics: Vec::new(),
+        approve_scripts: false,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow!("{} needs a value\n{}", arg, USAGE))
+        };
+        match arg.as_str() {
+            "--out" => config.output_dir = PathBuf::from(value()?),
+            "--format" => {
+                config.format = match value()?.as_str() {
+                    "arrow" => HistoryFormat::Arrow,
+                    "parquet" => HistoryFormat::Parquet,
+                    other => bail!("unknown format `{}`\n{}", other, USAGE),
+                }
+            }
+            "--custom" => {
+                config.custom_metrics = value()?
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }
+            "--approve-scripts" => config.approve_scripts = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if tasks_file.is_none() && !arg.starts_with('-') => {
+                tasks_file = Some(PathBuf::from(&arg))
+            }
+            _ => bail!("unexpected argument `{}`\n{}", arg, USAGE),
+        }
+    }
+    let tasks_file = tasks_file.ok_or_else(|| anyhow!(USAGE))?;
+
+    let tasks = load_tasks(&tasks_file)?;
+    println!(
+        "Running {} tasks from {}",
+        tasks.len(),
+        tasks_file.display()
+    );
+    let report = run_batch(SemanticTaskProcessor::new(), tasks, &config)?;
+    println!(
+        "{} succeeded, {} failed, {} skipped; results in {}, history in {}",
+        report.succeeded,
+        report.failed,
+        report.skipped,
+        report.results.display(),
+        report.history.display()
+    );
+    if report.failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -1,8 +1,15 @@
 use mmss::core::semantic_task_processor::SemanticTaskProcessor;
 use mmss::core::types::{GeometricOperator, GeometricTaskCommand};
+use mmss::telemetry::{LogConfig, Telemetry};
 
 fn main() {
-    env_logger::init();
+    let _telemetry = match Telemetry::install(LogConfig::from_env(), None) {
+        Ok(telemetry) => Some(telemetry),
+        Err(err) => {
+            eprintln!("Logging disabled: {err}");
+            None
+        }
+    };
     println!("MMSS CLI placeholder");
 
     let processor = SemanticTaskProcessor::new();
@@ -1,29 +1,113 @@
+use axum::extract::Request;
 use axum::handler::HandlerWithoutStateExt;
 use axum::routing::get_service;
 use axum::Router;
+use mmss::api::data_io::{DataIoGateway, StatePersistenceConfig};
+use mmss::config::Config;
+use mmss::core::event_publisher::{self, spawn_event_publisher, EventPublishConfig};
+use mmss::core::exports::{spawn_incremental_export, IncrementalExportConfig};
+#[cfg(feature = "flight")]
+use mmss::core::flight::spawn_flight_server;
+use mmss::core::metrics_history::spawn_metrics_snapshots;
+use mmss::core::mqtt_telemetry::{self, spawn_mqtt_telemetry, MqttTelemetryConfig};
+use mmss::core::plugins::{self, PluginRegistry};
 use mmss::routes;
-use mmss::state::AppState;
+#[cfg(feature = "shared-state")]
+use mmss::state::shared::{SharedState, SharedStateConfig};
+use mmss::state::{spawn_state_persistence, AppState};
+use mmss::telemetry::{self, LogConfig, OtlpConfig, Telemetry};
+#[cfg(feature = "flight")]
+use std::net::ToSocketAddrs;
+#[cfg(feature = "shared-state")]
+use std::sync::Arc;
 use tokio::net::TcpListener;
 use tokio::signal;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<()> {
     dotenvy::dotenv().ok();
-    env_logger::init();
 
-    let state = AppState::initialize(None)?;
-    let api_router = routes::build_router().with_state(state.clone());
+    let config = Config::load()?;
+    config.validate()?;
+    config.workers.runtime()?.block_on(serve(config))
+}
 
-    let static_service = get_service(ServeDir::new("src/web")).into_service();
+async fn serve(config: Config) -> anyhow::Result<()> {
+    let otlp = OtlpConfig::from_config(&config.telemetry);
+    if let Some(otlp) = &otlp {
+        println!("Exporting traces and metrics to {}", otlp.endpoint);
+    }
+    let telemetry = Telemetry::install(LogConfig::from_config(&config.logging), otlp)?;
+    // before the state, whose rule engines take the plugins' rules
+    let plugins = match &config.server.plugin_dir {
+        Some(directory) => PluginRegistry::load_dir(directory)?,
+        None => PluginRegistry::default(),
+    };
+    plugins::install(plugins)?;
+    let state = AppState::from_config(&config)?;
+    let persistence = StatePersistenceConfig::from_config(&config.persistence);
+    if let Some(persistence) = &persistence {
+        let directory = &persistence.directory;
+        let saved = DataIoGateway::load_latest_state(directory).map_err(|e| {
+            anyhow::anyhow!("Failed to load saved state from {}: {}", directory.display(), e)
+        })?;
+        if let Some(saved) = saved {
+            state.restore(saved).await?;
+        }
+    }
+    let leadership = leadership(&config, &state).await?;
+    tokio::spawn(run_while_leader(leadership, {
+        let state = state.clone();
+        let persistence = persistence.clone();
+        move || background_loops(&state, persistence.as_ref())
+    }));
+    // on every replica: each publishes the events of its own processor
+    if let Some(events) = EventPublishConfig::from_config(&config.events) {
+        let publisher = event_publisher::connect(&events).await?;
+        spawn_event_publisher(&state.events, publisher, events);
+    }
+    if let Some(mqtt) = MqttTelemetryConfig::from_config(&config.mqtt) {
+        let publisher = mqtt_telemetry::connect(&mqtt)?;
+        spawn_mqtt_telemetry(state.processor.clone(), &state.events, publisher, mqtt);
+    }
+    if let Some(bind) = &config.server.flight_bind {
+        serve_flight(bind, &state)?;
+    }
+    let api_router = routes::build_router().with_state(state.clone());
 
-    let app = Router::new()
-        .nest("/api", api_router)
-        .fallback_service(static_service)
+    let mut app = Router::new().nest("/api", api_router);
+    if config.features.dashboard {
+        app = app.nest("/dashboard", routes::dashboard::router());
+    }
+    if config.features.static_ui {
+        let static_service = get_service(ServeDir::new(&config.server.static_dir)).into_service();
+        app = app.fallback_service(static_service);
+    }
+    let app = app
         .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http());
+        .layer(TraceLayer::new_for_http().make_span_with(|request: &Request| {
+            let request_id = request
+                .headers()
+                .get("x-request-id")
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            let span = tracing::info_span!(
+                "http.request",
+                http.method = %request.method(),
+                http.target = %request.uri().path(),
+                request_id,
+            );
+            telemetry::set_remote_parent(&span, request.headers());
+            span
+        }))
+        // outermost, so the request ID is set before the span reads it
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
 
-    let addr = std::env::var("MMSS_BIND").unwrap_or_else(|_| "127.0.0.1:8080".into());
+    let addr = config.server.bind;
     let listener = TcpListener::bind(&addr).await?;
 
     println!("MMSS server listening on http://{}", addr);
@@ -35,5 +119,103 @@ async fn main() -> anyhow::Result<()> {
         })
         .await?;
 
+    if let Some(persistence) = persistence {
+        state.persist(persistence.directory).await?;
+    }
+    tokio::task::spawn_blocking(move || telemetry.shutdown()).await?;
+
     Ok(())
 }
+
+/// Whether this replica runs the background loops: always, unless it
+/// shares state with others and one of them leads.
+#[cfg(feature = "shared-state")]
+async fn leadership(config: &Config, state: &AppState) -> anyhow::Result<watch::Receiver<bool>> {
+    let Some(config) = SharedStateConfig::from_config(&config.cluster) else {
+        return Ok(watch::channel(true).1);
+    };
+    let shared = Arc::new(SharedState::connect(config).await?);
+    println!("Sharing state as replica {}", shared.replica_id());
+    shared.clone().spawn_sync(state.processor.clone());
+    Ok(shared.spawn_leader_election())
+}
+
+#[cfg(not(feature = "shared-state"))]
+async fn leadership(
+    _config: &Config,
+    _state: &AppState,
+) -> anyhow::Result<watch::Receiver<bool>> {
+    Ok(watch::channel(true).1)
+}
+
+/// Serve the records over Arrow Flight on `bind` as well as HTTP.
+#[cfg(feature = "flight")]
+fn serve_flight(bind: &str, state: &AppState) -> anyhow::Result<()> {
+    let addr = bind
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("server.flight_bind `{}` resolves to nothing", bind))?;
+    spawn_flight_server(addr, state.processor.clone())?;
+    println!("Serving records over Arrow Flight on grpc://{}", addr);
+    Ok(())
+}
+
+#[cfg(not(feature = "flight"))]
+fn serve_flight(bind: &str, _state: &AppState) -> anyhow::Result<()> {
+    anyhow::bail!(
+        "Serving Arrow Flight on {} needs the server built with the flight feature",
+        bind
+    )
+}
+
+/// Run the loops `spawn` starts while this replica leads, and stop them
+/// when it steps down.
+async fn run_while_leader(
+    mut leadership: watch::Receiver<bool>,
+    spawn: impl Fn() -> Vec<JoinHandle<()>>,
+) {
+    loop {
+        if leadership.wait_for(|leading| *leading).await.is_err() {
+            return;
+        }
+        let loops = spawn();
+        // closed: leadership can no longer change, so the loops keep running
+        if leadership.wait_for(|leading| !*leading).await.is_err() {
+            return;
+        }
+        for handle in loops {
+            handle.abort();
+        }
+    }
+}
+
+fn background_loops(
+    state: &AppState,
+    persistence: Option<&StatePersistenceConfig>,
+) -> Vec<JoinHandle<()>> {
+    let mut loops = Vec::new();
+    if let Some(StatePersistenceConfig {
+        directory,
+        period: Some(period),
+    }) = persistence
+    {
+        loops.push(spawn_state_persistence(
+            state.clone(),
+            directory.clone(),
+            *period,
+        ));
+    }
+    if let Some(config) = IncrementalExportConfig::from_env(state.exports.directory()) {
+        loops.push(spawn_incremental_export(state.processor.clone(), config));
+    }
+    if let Some(history) = &state.metrics_history {
+        if let Some(period) = history.snapshot_period() {
+            loops.push(spawn_metrics_snapshots(
+                state.processor.clone(),
+                history.clone(),
+                period,
+            ));
+        }
+    }
+    loops
+}
@@ -0,0 +1,307 @@
+//! Step-aligned comparison of two campaign histories.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+use arrow2::{
+    array::{Float64Array, UInt64Array, Utf8Array},
+    chunk::Chunk,
+    datatypes::{DataType, Field, Schema},
+    io::ipc::write::{FileWriter, WriteOptions},
+};
+use chrono::Utc;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::api::usage::TokenUsage;
+use crate::core::error::{Error, Result};
+use crate::core::types::GeometricMetrics;
+
+use super::objectives::{metric_value, NAMED_METRICS};
+use super::{CampaignSnapshot, CampaignStatus};
+
+/// Name of the scalarised objective score in per-step deltas.
+pub const PROGRESS_METRIC: &str = "progress";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CampaignTotals {
+    pub campaign_id: Uuid,
+    pub status: CampaignStatus,
+    pub completed_steps: usize,
+    /// Sum of per-step improvements over the initial score.
+    pub total_improvement: f64,
+    pub goal_progress: f64,
+    pub token_usage: TokenUsage,
+    /// Seconds from start to finish, or to now for running campaigns.
+    pub wall_time_secs: f64,
+}
+
+/// Values of one campaign step; `metrics` includes `progress`.
+#[derive(Debug, Clone, Serialize)]
+pub struct StepValues {
+    pub planner: String,
+    pub metrics: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepComparison {
+    pub step: usize,
+    pub left: Option<StepValues>,
+    pub right: Option<StepValues>,
+    /// `right - left` for metrics present in both steps.
+    pub deltas: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CampaignComparison {
+    pub left: CampaignTotals,
+    pub right: CampaignTotals,
+    pub steps: Vec<StepComparison>,
+}
+
+/// One row of the long-format export: a single metric at a single step.
+struct ExportRow<'a> {
+    step: u64,
+    metric: &'a str,
+    left: Option<f64>,
+    right: Option<f64>,
+    delta: Option<f64>,
+}
+
+impl CampaignComparison {
+    pub fn new(
+        left: &CampaignSnapshot,
+        left_usage: TokenUsage,
+        right: &CampaignSnapshot,
+        right_usage: TokenUsage,
+    ) -> Self {
+        let step_count = left.history.len().max(right.history.len());
+        let steps = (0..step_count)
+            .map(|index| {
+                let values = |snapshot: &CampaignSnapshot| {
+                    snapshot.history.get(index).map(|summary| {
+                        let mut metrics = metric_table(&summary.result_metrics);
+                        metrics.insert(PROGRESS_METRIC.to_string(), summary.progress);
+                        StepValues {
+                            planner: summary.planner.clone(),
+                            metrics,
+                        }
+                    })
+                };
+                let (left, right) = (values(left), values(right));
+                let deltas = match (&left, &right) {
+                    (Some(l), Some(r)) => l
+                        .metrics
+                        .iter()
+                        .filter_map(|(name, lv)| {
+                            r.metrics.get(name).map(|rv| (name.clone(), rv - lv))
+                        })
+                        .collect(),
+                    _ => BTreeMap::new(),
+                };
+                StepComparison {
+                    step: index + 1,
+                    left,
+                    right,
+                    deltas,
+                }
+            })
+            .collect();
+
+        Self {
+            left: totals(left, left_usage),
+            right: totals(right, right_usage),
+            steps,
+        }
+    }
+
+    fn export_rows(&self) -> Vec<ExportRow<'_>> {
+        let mut rows = Vec::new();
+        for step in &self.steps {
+            let names: BTreeSet<&String> = step
+                .left
+                .iter()
+                .chain(step.right.iter())
+                .flat_map(|values| values.metrics.keys())
+                .collect();
+            for name in names {
+                let value = |side: &Option<StepValues>| {
+                    side.as_ref().and_then(|v| v.metrics.get(name).copied())
+                };
+                rows.push(ExportRow {
+                    step: step.step as u64,
+                    metric: name,
+                    left: value(&step.left),
+                    right: value(&step.right),
+                    delta: step.deltas.get(name).copied(),
+                });
+            }
+        }
+        rows
+    }
+
+    /// Long-format CSV with one row per step and metric.
+    pub fn to_csv(&self) -> String {
+        let cell = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+        let mut csv = String::from("step,metric,left,right,delta\n");
+        for row in self.export_rows() {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{}",
+                row.step,
+                row.metric,
+                cell(row.left),
+                cell(row.right),
+                cell(row.delta)
+            );
+        }
+        csv
+    }
+
+    /// Same rows as [`Self::to_csv`] as an Arrow IPC file.
+    pub fn to_arrow_ipc(&self) -> Result<Vec<u8>> {
+        let rows = self.export_rows();
+        let schema = Schema::from(vec![
+            Field::new("step", DataType::UInt64, false),
+            Field::new("metric", DataType::Utf8, false),
+            Field::new("left", DataType::Float64, true),
+            Field::new("right", DataType::Float64, true),
+            Field::new("delta", DataType::Float64, true),
+        ]);
+
+        let steps: Vec<u64> = rows.iter().map(|r| r.step).collect();
+        let metrics: Vec<&str> = rows.iter().map(|r| r.metric).collect();
+        let column = |pick: fn(&ExportRow) -> Option<f64>| {
+            Float64Array::from(rows.iter().map(pick).collect::<Vec<_>>()).boxed()
+        };
+        let chunk = Chunk::try_new(vec![
+            UInt64Array::from_slice(&steps).boxed(),
+            Utf8Array::<i32>::from_slice(metrics).boxed(),
+            column(|r| r.left),
+            column(|r| r.right),
+            column(|r| r.delta),
+        ])
+        .map_err(arrow_error)?;
+
+        let mut buffer = Vec::new();
+        let mut writer = FileWriter::try_new(
+            &mut buffer,
+            schema,
+            None,
+            WriteOptions { compression: None },
+        )
+        .map_err(arrow_error)?;
+        writer.write(&chunk, None).map_err(arrow_error)?;
+        writer.finish().map_err(arrow_error)?;
+        Ok(buffer)
+    }
+}
+
+fn arrow_error(err: arrow2::error::Error) -> Error {
+    Error::TaskExecution(format!("Failed to encode comparison: {err}"))
+}
+
+fn totals(snapshot: &CampaignSnapshot, token_usage: TokenUsage) -> CampaignTotals {
+    let finished = snapshot.finished_at.unwrap_or_else(Utc::now);
+    CampaignTotals {
+        campaign_id: snapshot.campaign_id,
+        status: snapshot.status.clone(),
+        completed_steps: snapshot.completed_steps,
+        total_improvement: snapshot.history.iter().map(|s| s.improvement).sum(),
+        goal_progress: snapshot.goal_progress,
+        token_usage,
+        wall_time_secs: (finished - snapshot.started_at).num_milliseconds() as f64 / 1000.0,
+    }
+}
+
+fn metric_table(metrics: &GeometricMetrics) -> BTreeMap<String, f64> {
+    let mut table: BTreeMap<String, f64> = NAMED_METRICS
+        .iter()
+        .map(|name| (name.to_string(), metric_value(metrics, name)))
+        .collect();
+    table.extend(metrics.custom_metrics.iter().map(|(k, v)| (k.clone(), *v)));
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::campaign::ResearchStepSummary;
+    use crate::core::semantic_task_processor::SemanticTaskProcessor;
+    use crate::core::types::{GeometricOperator, GeometricTaskCommand};
+
+    fn snapshot(progress: &[f64]) -> CampaignSnapshot {
+        let metrics = SemanticTaskProcessor::new().get_metrics();
+        let history = progress
+            .iter()
+            .enumerate()
+            .map(|(index, &progress)| ResearchStepSummary {
+                step: index + 1,
+                task: GeometricTaskCommand {
+                    task_name: "step".into(),
+                    geometric_operator: GeometricOperator::GeometricDerivation,
+                    target_module: "test".into(),
+                    parameters: serde_json::json!({}),
+                    expected_output_metric: "v_geometric".into(),
+                    task_id: None,
+                },
+                planner: "llm".into(),
+                result_metrics: GeometricMetrics {
+                    v_geometric: progress,
+                    ..metrics.clone()
+                },
+                improvement: 0.1,
+                progress,
+                objective_progress: Vec::new(),
+                candidate_scores: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        CampaignSnapshot {
+            campaign_id: Uuid::new_v4(),
+            status: CampaignStatus::Completed,
+            goal: "test".into(),
+            objectives: Vec::new(),
+            max_steps: history.len(),
+            completed_steps: history.len(),
+            goal_progress: progress.last().copied().unwrap_or_default(),
+            history,
+            current_metrics: metrics,
+            started_at: Utc::now(),
+            finished_at: Some(Utc::now()),
+            stop_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_aligns_uneven_histories() {
+        let comparison = CampaignComparison::new(
+            &snapshot(&[0.2, 0.4, 0.5]),
+            TokenUsage::default(),
+            &snapshot(&[0.3, 0.7]),
+            TokenUsage::default(),
+        );
+
+        assert_eq!(comparison.steps.len(), 3);
+        assert!((comparison.steps[1].deltas[PROGRESS_METRIC] - 0.3).abs() < 1e-12);
+        assert!(comparison.steps[2].right.is_none());
+        assert!(comparison.steps[2].deltas.is_empty());
+        assert!((comparison.left.total_improvement - 0.3).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_exports_share_rows() {
+        let comparison = CampaignComparison::new(
+            &snapshot(&[0.2]),
+            TokenUsage::default(),
+            &snapshot(&[0.3]),
+            TokenUsage::default(),
+        );
+
+        let csv = comparison.to_csv();
+        let rows = csv.lines().count() - 1;
+        assert_eq!(rows, comparison.export_rows().len());
+        assert!(csv.contains("1,progress,0.2,0.3,"));
+        assert!(comparison.to_arrow_ipc().unwrap().starts_with(b"ARROW1"));
+    }
+}
@@ -0,0 +1,273 @@
+//! Research campaigns: multi-step LLM-planned optimisation runs executed in
+//! the background and tracked by `CampaignStore`.
+
+pub mod compare;
+pub mod objectives;
+pub mod optimizers;
+pub mod runner;
+pub mod stopping;
+
+use crate::core::error::{Error, Result};
+use crate::core::types::{GeometricMetrics, GeometricTaskCommand};
+use chrono::{DateTime, Utc};
+use log::error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use uuid::Uuid;
+
+pub use compare::CampaignComparison;
+pub use objectives::{Objective, ObjectiveGoal, ObjectiveProgress};
+pub use optimizers::{Optimizer, OptimizerKind, OptimizerRole, OptimizerSettings};
+pub use runner::run_campaign;
+pub use stopping::{StopReason, StoppingCriteria};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResearchCampaignRequest {
+    pub goal: String,
+    #[serde(default = "default_max_steps")]
+    pub max_steps: usize,
+    /// Single-metric shorthand for one `Target` objective.
+    #[serde(default)]
+    pub optimization_target: String,
+    pub target_value: Option<f64>,
+    /// Weighted objectives; takes precedence over `optimization_target`.
+    #[serde(default)]
+    pub objectives: Vec<Objective>,
+    /// Non-LLM planner driving or backing up the campaign.
+    #[serde(default)]
+    pub optimizer: Option<OptimizerSettings>,
+    /// Candidates planned and scored in isolation per step; the best is committed.
+    #[serde(default = "default_candidates_per_step")]
+    pub candidates_per_step: usize,
+    #[serde(default)]
+    pub stopping: StoppingCriteria,
+    #[serde(default)]
+    pub context: Value,
+}
+
+fn default_max_steps() -> usize {
+    5
+}
+
+fn default_candidates_per_step() -> usize {
+    1
+}
+
+impl ResearchCampaignRequest {
+    /// Objectives with their values resolved, validated for usable weights.
+    pub fn resolved_objectives(&self) -> Result<Vec<Objective>> {
+        let objectives = if self.objectives.is_empty() {
+            if self.optimization_target.trim().is_empty() {
+                return Err(Error::InvalidParameter(
+                    "objectives".into(),
+                    "either optimization_target or objectives is required".into(),
+                ));
+            }
+            vec![Objective::target(&self.optimization_target, self.target_value)]
+        } else {
+            self.objectives.clone()
+        };
+
+        if objectives
+            .iter()
+            .any(|o| !o.weight.is_finite() || o.weight < 0.0)
+        {
+            return Err(Error::InvalidParameter(
+                "objectives".into(),
+                "weights must be finite and non-negative".into(),
+            ));
+        }
+        if objectives.iter().all(|o| o.weight == 0.0) {
+            return Err(Error::InvalidParameter(
+                "objectives".into(),
+                "at least one objective needs a positive weight".into(),
+            ));
+        }
+
+        Ok(objectives
+            .into_iter()
+            .map(|objective| Objective {
+                value: Some(objective.resolved_value()),
+                ..objective
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ResearchStepSummary {
+    pub step: usize,
+    pub task: GeometricTaskCommand,
+    /// `llm`, `fallback`, or the optimizer that proposed the task.
+    pub planner: String,
+    pub result_metrics: GeometricMetrics,
+    pub improvement: f64,
+    /// Scalarised score over all objectives.
+    pub progress: f64,
+    pub objective_progress: Vec<ObjectiveProgress>,
+    /// Isolated scores of all candidates when more than one was planned.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub candidate_scores: Vec<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CampaignStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed(String),
+}
+
+impl CampaignStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, CampaignStatus::Running)
+    }
+}
+
+/// Progress of a single campaign as reported by `GET /campaigns/:id`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CampaignSnapshot {
+    pub campaign_id: Uuid,
+    pub status: CampaignStatus,
+    pub goal: String,
+    pub objectives: Vec<Objective>,
+    pub max_steps: usize,
+    pub completed_steps: usize,
+    pub goal_progress: f64,
+    pub history: Vec<ResearchStepSummary>,
+    pub current_metrics: GeometricMetrics,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub stop_reason: Option<StopReason>,
+}
+
+struct CampaignEntry {
+    snapshot: CampaignSnapshot,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Handle passed to the runner so it can publish progress.
+#[derive(Clone)]
+pub struct CampaignHandle {
+    id: Uuid,
+    store: Arc<CampaignStore>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl CampaignHandle {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+
+    pub fn update<F>(&self, apply: F) -> Result<()>
+    where
+        F: FnOnce(&mut CampaignSnapshot),
+    {
+        let mut campaigns = self.store.lock()?;
+        let entry = campaigns
+            .get_mut(&self.id)
+            .ok_or_else(|| Error::TaskExecution(format!("Campaign {} not found", self.id)))?;
+        apply(&mut entry.snapshot);
+        Ok(())
+    }
+
+    pub fn finish(&self, status: CampaignStatus, stop_reason: Option<StopReason>) -> Result<()> {
+        self.update(|snapshot| {
+            snapshot.status = status;
+            snapshot.stop_reason = stop_reason;
+            snapshot.finished_at = Some(Utc::now());
+        })
+    }
+}
+
+/// In-memory registry of running and finished campaigns.
+#[derive(Default)]
+pub struct CampaignStore {
+    campaigns: Mutex<HashMap<Uuid, CampaignEntry>>,
+}
+
+impl CampaignStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new running campaign and return the handle for its runner.
+    pub fn create(
+        self: &Arc<Self>,
+        request: &ResearchCampaignRequest,
+        objectives: Vec<Objective>,
+        initial_metrics: GeometricMetrics,
+    ) -> Result<CampaignHandle> {
+        let id = Uuid::new_v4();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let snapshot = CampaignSnapshot {
+            campaign_id: id,
+            status: CampaignStatus::Running,
+            goal: request.goal.clone(),
+            objectives,
+            max_steps: request.max_steps,
+            completed_steps: 0,
+            goal_progress: 0.0,
+            history: Vec::new(),
+            current_metrics: initial_metrics,
+            started_at: Utc::now(),
+            finished_at: None,
+            stop_reason: None,
+        };
+
+        self.lock()?.insert(
+            id,
+            CampaignEntry {
+                snapshot,
+                cancel: cancel.clone(),
+            },
+        );
+
+        Ok(CampaignHandle {
+            id,
+            store: self.clone(),
+            cancel,
+        })
+    }
+
+    pub fn get(&self, id: Uuid) -> Result<Option<CampaignSnapshot>> {
+        Ok(self.lock()?.get(&id).map(|entry| entry.snapshot.clone()))
+    }
+
+    pub fn list(&self) -> Result<Vec<CampaignSnapshot>> {
+        let mut snapshots: Vec<_> = self
+            .lock()?
+            .values()
+            .map(|entry| entry.snapshot.clone())
+            .collect();
+        snapshots.sort_by_key(|snapshot| snapshot.started_at);
+        Ok(snapshots)
+    }
+
+    /// Request cancellation; the runner stops before its next step.
+    /// Returns `None` if the campaign is unknown.
+    pub fn cancel(&self, id: Uuid) -> Result<Option<CampaignStatus>> {
+        let campaigns = self.lock()?;
+        Ok(campaigns.get(&id).map(|entry| {
+            if !entry.snapshot.status.is_finished() {
+                entry.cancel.store(true, Ordering::SeqCst);
+            }
+            entry.snapshot.status.clone()
+        }))
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, HashMap<Uuid, CampaignEntry>>> {
+        self.campaigns.lock().map_err(|e| {
+            error!("Failed to lock campaigns: {}", e);
+            Error::TaskExecution("Failed to access campaign storage".to_string())
+        })
+    }
+}
@@ -0,0 +1,217 @@
+//! Campaign objectives and the scalarised multi-objective score.
+
+use crate::core::types::GeometricMetrics;
+use serde::{Deserialize, Serialize};
+
+/// How an objective's metric is compared with its value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectiveGoal {
+    /// Move the metric as close as possible to the value.
+    #[default]
+    Target,
+    /// Satisfied once the metric is at or above the value.
+    AtLeast,
+    /// Satisfied while the metric stays at or below the value.
+    AtMost,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Objective {
+    pub metric: String,
+    #[serde(default)]
+    pub goal: ObjectiveGoal,
+    /// Defaults to the conventional target for the metric.
+    pub value: Option<f64>,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ObjectiveProgress {
+    pub metric: String,
+    pub current: f64,
+    pub value: f64,
+    pub progress: f64,
+}
+
+impl Objective {
+    pub fn target(metric: impl Into<String>, value: Option<f64>) -> Self {
+        Self {
+            metric: metric.into(),
+            goal: ObjectiveGoal::Target,
+            value,
+            weight: default_weight(),
+        }
+    }
+
+    pub fn resolved_value(&self) -> f64 {
+        self.value
+            .unwrap_or_else(|| infer_default_target(&self.metric))
+    }
+
+    /// Progress towards this objective in `[0, 1]`.
+    pub fn progress(&self, metrics: &GeometricMetrics) -> f64 {
+        let value = self.resolved_value();
+        let current = metric_value(metrics, &self.metric);
+        let satisfied = match self.goal {
+            ObjectiveGoal::Target => false,
+            ObjectiveGoal::AtLeast => current >= value,
+            ObjectiveGoal::AtMost => current <= value,
+        };
+        if satisfied {
+            return 1.0;
+        }
+
+        let denominator = value.abs().max(1e-6);
+        let distance = (value - current).abs();
+        (1.0 - (distance / denominator)).clamp(0.0, 1.0)
+    }
+}
+
+/// Weighted mean of objective progress plus the per-objective breakdown.
+pub fn evaluate_objectives(
+    objectives: &[Objective],
+    metrics: &GeometricMetrics,
+) -> (f64, Vec<ObjectiveProgress>) {
+    let breakdown: Vec<ObjectiveProgress> = objectives
+        .iter()
+        .map(|objective| ObjectiveProgress {
+            metric: objective.metric.clone(),
+            current: metric_value(metrics, &objective.metric),
+            value: objective.resolved_value(),
+            progress: objective.progress(metrics),
+        })
+        .collect();
+
+    let total_weight: f64 = objectives.iter().map(|o| o.weight.max(0.0)).sum();
+    if total_weight <= 0.0 {
+        return (0.0, breakdown);
+    }
+
+    let score = objectives
+        .iter()
+        .zip(&breakdown)
+        .map(|(objective, progress)| objective.weight.max(0.0) * progress.progress)
+        .sum::<f64>()
+        / total_weight;
+    (score, breakdown)
+}
+
+/// Objective contributing most to the remaining shortfall.
+pub fn most_lacking<'a>(
+    objectives: &'a [Objective],
+    metrics: &GeometricMetrics,
+) -> Option<&'a Objective> {
+    objectives.iter().max_by(|a, b| {
+        let shortfall = |o: &Objective| o.weight.max(0.0) * (1.0 - o.progress(metrics));
+        shortfall(a).total_cmp(&shortfall(b))
+    })
+}
+
+/// Metrics stored as named fields of `GeometricMetrics`.
+pub const NAMED_METRICS: &[&str] = &[
+    "v_geometric",
+    "s_geometric",
+    "q_oscillator",
+    "quaternion_coherence",
+    "emergent_electron_mass",
+    "fine_structure_constant",
+    "zitterbewegung_entropy",
+    "topological_winding",
+];
+
+/// Look up a metric by name, falling back to `custom_metrics` and then `v_geometric`.
+pub fn metric_value(metrics: &GeometricMetrics, name: &str) -> f64 {
+    match name {
+        "topological_winding" => metrics.topological_winding,
+        "quaternion_coherence" => metrics.quaternion_coherence,
+        "emergent_electron_mass" => metrics.emergent_electron_mass,
+        "fine_structure_constant" => metrics.fine_structure_constant,
+        "zitterbewegung_entropy" => metrics.zitterbewegung_entropy,
+        "q_oscillator" => metrics.q_oscillator,
+        "v_geometric" => metrics.v_geometric,
+        "s_geometric" => metrics.s_geometric,
+        other => metrics
+            .custom_metrics
+            .get(other)
+            .copied()
+            .unwrap_or(metrics.v_geometric),
+    }
+}
+
+pub fn infer_default_target(target: &str) -> f64 {
+    match target {
+        "topological_winding" => 9.0,
+        "quaternion_coherence" => 0.9999,
+        "emergent_electron_mass" => compute_target_mass(),
+        "fine_structure_constant" => 1.0 / 137.035_999_084,
+        _ => 1.0,
+    }
+}
+
+fn compute_target_mass() -> f64 {
+    crate::state::compute_electron_mass()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::MetricMap;
+
+    fn metrics(coherence: f64, entropy: f64) -> GeometricMetrics {
+        GeometricMetrics {
+            v_geometric: coherence,
+            s_geometric: entropy,
+            q_oscillator: 9.0,
+            quaternion_coherence: coherence,
+            emergent_electron_mass: 0.0,
+            fine_structure_constant: 0.0,
+            zitterbewegung_entropy: entropy,
+            topological_winding: 9.0,
+            custom_metrics: MetricMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_bounds_are_satisfied_inside_region() {
+        let ceiling = Objective {
+            metric: "zitterbewegung_entropy".into(),
+            goal: ObjectiveGoal::AtMost,
+            value: Some(0.001),
+            weight: 1.0,
+        };
+        assert_eq!(ceiling.progress(&metrics(0.99, 0.0005)), 1.0);
+        assert!(ceiling.progress(&metrics(0.99, 0.0015)) < 1.0);
+    }
+
+    #[test]
+    fn test_weighted_score() {
+        let objectives = vec![
+            Objective {
+                metric: "quaternion_coherence".into(),
+                goal: ObjectiveGoal::AtLeast,
+                value: Some(0.999),
+                weight: 3.0,
+            },
+            Objective {
+                metric: "zitterbewegung_entropy".into(),
+                goal: ObjectiveGoal::AtMost,
+                value: Some(0.001),
+                weight: 1.0,
+            },
+        ];
+
+        let (score, breakdown) = evaluate_objectives(&objectives, &metrics(0.9995, 0.0030));
+        assert_eq!(breakdown[0].progress, 1.0);
+        assert_eq!(breakdown[1].progress, 0.0);
+        assert!((score - 0.75).abs() < 1e-12);
+
+        let lacking = most_lacking(&objectives, &metrics(0.9995, 0.0030)).unwrap();
+        assert_eq!(lacking.metric, "zitterbewegung_entropy");
+    }
+}
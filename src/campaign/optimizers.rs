@@ -0,0 +1,445 @@
+//! Non-LLM planners that propose campaign steps from a fixed search space.
+//!
+//! Each optimizer proposes a `Candidate` (one operator parameter, normalised
+//! to `[0, 1]`), and is told the campaign score observed after the resulting
+//! task ran.
+
+use crate::core::types::{GeometricOperator, GeometricTaskCommand};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::f64::consts::PI;
+
+/// One tunable operator parameter and its physical range.
+pub struct Dimension {
+    pub operator: GeometricOperator,
+    pub parameter: &'static str,
+    pub target_module: &'static str,
+    pub low: f64,
+    pub high: f64,
+}
+
+pub const SEARCH_SPACE: &[Dimension] = &[
+    Dimension {
+        operator: GeometricOperator::QuaternionRotation,
+        parameter: "theta",
+        target_module: "sys7_core",
+        low: 0.0,
+        high: PI,
+    },
+    Dimension {
+        operator: GeometricOperator::Zitterbewegung,
+        parameter: "frequency_scale",
+        target_module: "sys6_resonator",
+        low: 0.5,
+        high: 2.0,
+    },
+    Dimension {
+        operator: GeometricOperator::GeometricDerivation,
+        parameter: "delta",
+        target_module: "sys5_topology",
+        low: -1.0,
+        high: 1.0,
+    },
+    Dimension {
+        operator: GeometricOperator::SemanticSynthesis,
+        parameter: "coherence_hint",
+        target_module: "sys4_semantic",
+        low: 0.0,
+        high: 1.0,
+    },
+];
+
+/// A point in the search space: dimension index plus normalised position.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Candidate {
+    pub dimension: usize,
+    pub x: f64,
+}
+
+impl Candidate {
+    pub fn value(&self) -> f64 {
+        let dim = &SEARCH_SPACE[self.dimension];
+        dim.low + self.x.clamp(0.0, 1.0) * (dim.high - dim.low)
+    }
+
+    pub fn to_task(&self, planner: &str, expected_output_metric: &str) -> GeometricTaskCommand {
+        let dim = &SEARCH_SPACE[self.dimension];
+        let value = self.value();
+        let mut parameters = json!({ dim.parameter: value });
+        if dim.operator == GeometricOperator::QuaternionRotation {
+            parameters["axis"] = json!([0.0, 1.0, 0.0]);
+        }
+
+        GeometricTaskCommand {
+            task_name: format!("{planner} proposal: {} = {value:.4}", dim.parameter),
+            geometric_operator: dim.operator,
+            target_module: dim.target_module.into(),
+            parameters,
+            expected_output_metric: expected_output_metric.into(),
+            task_id: None,
+        }
+    }
+}
+
+pub trait Optimizer: Send {
+    fn name(&self) -> &'static str;
+
+    /// Suggest the next candidate to evaluate.
+    fn propose(&mut self) -> Candidate;
+
+    /// Report the campaign score reached after evaluating `candidate`.
+    fn observe(&mut self, candidate: &Candidate, score: f64);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OptimizerKind {
+    RandomSearch,
+    SimulatedAnnealing,
+    Bayesian,
+}
+
+/// Whether the optimizer replaces the LLM or only covers its failures.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OptimizerRole {
+    #[default]
+    Primary,
+    Fallback,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizerSettings {
+    pub kind: OptimizerKind,
+    #[serde(default)]
+    pub role: OptimizerRole,
+    pub seed: Option<u64>,
+}
+
+impl OptimizerSettings {
+    pub fn build(&self) -> Box<dyn Optimizer> {
+        let rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        match self.kind {
+            OptimizerKind::RandomSearch => Box::new(RandomSearch { rng }),
+            OptimizerKind::SimulatedAnnealing => Box::new(SimulatedAnnealing::new(rng)),
+            OptimizerKind::Bayesian => Box::new(BayesianOptimizer::new(rng)),
+        }
+    }
+}
+
+fn random_candidate(rng: &mut StdRng) -> Candidate {
+    Candidate {
+        dimension: rng.gen_range(0..SEARCH_SPACE.len()),
+        x: rng.gen::<f64>(),
+    }
+}
+
+/// Uniform sampling over the whole search space.
+pub struct RandomSearch {
+    rng: StdRng,
+}
+
+impl Optimizer for RandomSearch {
+    fn name(&self) -> &'static str {
+        "random_search"
+    }
+
+    fn propose(&mut self) -> Candidate {
+        random_candidate(&mut self.rng)
+    }
+
+    fn observe(&mut self, _candidate: &Candidate, _score: f64) {}
+}
+
+/// Metropolis acceptance over Gaussian neighbours with geometric cooling.
+pub struct SimulatedAnnealing {
+    rng: StdRng,
+    current: Option<(Candidate, f64)>,
+    temperature: f64,
+    cooling: f64,
+    step: f64,
+    switch_probability: f64,
+}
+
+impl SimulatedAnnealing {
+    pub fn new(rng: StdRng) -> Self {
+        Self {
+            rng,
+            current: None,
+            temperature: 0.05,
+            cooling: 0.9,
+            step: 0.15,
+            switch_probability: 0.2,
+        }
+    }
+}
+
+impl Optimizer for SimulatedAnnealing {
+    fn name(&self) -> &'static str {
+        "simulated_annealing"
+    }
+
+    fn propose(&mut self) -> Candidate {
+        let Some((current, _)) = self.current else {
+            return random_candidate(&mut self.rng);
+        };
+
+        if self.rng.gen::<f64>() < self.switch_probability {
+            return random_candidate(&mut self.rng);
+        }
+
+        let jitter = standard_normal(&mut self.rng) * self.step;
+        Candidate {
+            dimension: current.dimension,
+            x: (current.x + jitter).clamp(0.0, 1.0),
+        }
+    }
+
+    fn observe(&mut self, candidate: &Candidate, score: f64) {
+        let accept = match self.current {
+            None => true,
+            Some((_, current_score)) if score >= current_score => true,
+            Some((_, current_score)) => {
+                let t = self.temperature.max(1e-9);
+                self.rng.gen::<f64>() < ((score - current_score) / t).exp()
+            }
+        };
+        if accept {
+            self.current = Some((*candidate, score));
+        }
+        self.temperature *= self.cooling;
+    }
+}
+
+/// Gaussian-process surrogate (one RBF GP per dimension) with expected improvement.
+pub struct BayesianOptimizer {
+    rng: StdRng,
+    observations: Vec<(Candidate, f64)>,
+    initial_samples: usize,
+    length_scale: f64,
+    noise: f64,
+    grid_points: usize,
+}
+
+impl BayesianOptimizer {
+    pub fn new(rng: StdRng) -> Self {
+        Self {
+            rng,
+            observations: Vec::new(),
+            initial_samples: SEARCH_SPACE.len(),
+            length_scale: 0.2,
+            noise: 1e-4,
+            grid_points: 64,
+        }
+    }
+
+    fn posterior(&self, dimension: usize, x: f64, prior_mean: f64) -> (f64, f64) {
+        let points: Vec<(f64, f64)> = self
+            .observations
+            .iter()
+            .filter(|(c, _)| c.dimension == dimension)
+            .map(|(c, score)| (c.x, *score))
+            .collect();
+        if points.is_empty() {
+            return (prior_mean, 1.0);
+        }
+
+        let kernel = |a: f64, b: f64| (-(a - b).powi(2) / (2.0 * self.length_scale.powi(2))).exp();
+        let n = points.len();
+        let mut k = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            for j in 0..n {
+                k[i][j] = kernel(points[i].0, points[j].0);
+            }
+            k[i][i] += self.noise;
+        }
+        let Some(l) = cholesky(&k) else {
+            return (prior_mean, 1.0);
+        };
+
+        let residuals: Vec<f64> = points.iter().map(|(_, y)| y - prior_mean).collect();
+        let alpha = cholesky_solve(&l, &residuals);
+        let k_star: Vec<f64> = points.iter().map(|(xi, _)| kernel(*xi, x)).collect();
+
+        let mean = prior_mean + dot(&k_star, &alpha);
+        let v = forward_substitute(&l, &k_star);
+        let variance = (1.0 - dot(&v, &v)).max(1e-12);
+        (mean, variance)
+    }
+}
+
+impl Optimizer for BayesianOptimizer {
+    fn name(&self) -> &'static str {
+        "bayesian"
+    }
+
+    fn propose(&mut self) -> Candidate {
+        if self.observations.len() < self.initial_samples {
+            return random_candidate(&mut self.rng);
+        }
+
+        let scores = self.observations.iter().map(|(_, score)| *score);
+        let best = scores.clone().fold(f64::NEG_INFINITY, f64::max);
+        let prior_mean = scores.sum::<f64>() / self.observations.len() as f64;
+        let exploration = 0.01;
+
+        let mut chosen = random_candidate(&mut self.rng);
+        let mut best_ei = f64::NEG_INFINITY;
+        for dimension in 0..SEARCH_SPACE.len() {
+            for i in 0..self.grid_points {
+                let x = (i as f64 + 0.5) / self.grid_points as f64;
+                let (mean, variance) = self.posterior(dimension, x, prior_mean);
+                let sigma = variance.sqrt();
+                let gain = mean - best - exploration;
+                let z = gain / sigma;
+                let ei = gain * normal_cdf(z) + sigma * normal_pdf(z);
+                if ei > best_ei {
+                    best_ei = ei;
+                    chosen = Candidate { dimension, x };
+                }
+            }
+        }
+        chosen
+    }
+
+    fn observe(&mut self, candidate: &Candidate, score: f64) {
+        self.observations.push((*candidate, score));
+    }
+}
+
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    // Box-Muller; 1 - u keeps the logarithm finite
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+fn normal_pdf(z: f64) -> f64 {
+    (-0.5 * z * z).exp() / (2.0 * PI).sqrt()
+}
+
+fn normal_cdf(z: f64) -> f64 {
+    0.5 * (1.0 + erf(z / std::f64::consts::SQRT_2))
+}
+
+/// Abramowitz–Stegun 7.1.26, accurate to ~1.5e-7.
+fn erf(x: f64) -> f64 {
+    let sign = x.signum();
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    sign * (1.0 - poly * (-x * x).exp())
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn cholesky(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let mut l = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..=i {
+            let sum: f64 = (0..j).map(|k| l[i][k] * l[j][k]).sum();
+            if i == j {
+                let diagonal = matrix[i][i] - sum;
+                if diagonal <= 0.0 {
+                    return None;
+                }
+                l[i][j] = diagonal.sqrt();
+            } else {
+                l[i][j] = (matrix[i][j] - sum) / l[j][j];
+            }
+        }
+    }
+    Some(l)
+}
+
+fn forward_substitute(l: &[Vec<f64>], b: &[f64]) -> Vec<f64> {
+    let mut y = vec![0.0; b.len()];
+    for i in 0..b.len() {
+        let sum: f64 = (0..i).map(|k| l[i][k] * y[k]).sum();
+        y[i] = (b[i] - sum) / l[i][i];
+    }
+    y
+}
+
+fn cholesky_solve(l: &[Vec<f64>], b: &[f64]) -> Vec<f64> {
+    let y = forward_substitute(l, b);
+    let n = y.len();
+    let mut x = vec![0.0; n];
+    for i in (0..n).rev() {
+        let sum: f64 = (i + 1..n).map(|k| l[k][i] * x[k]).sum();
+        x[i] = (y[i] - sum) / l[i][i];
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Peaked objective on the Zitterbewegung dimension at x = 0.7.
+    fn score(candidate: &Candidate) -> f64 {
+        if candidate.dimension == 1 {
+            1.0 - (candidate.x - 0.7).abs()
+        } else {
+            0.1
+        }
+    }
+
+    fn run(kind: OptimizerKind, steps: usize) -> f64 {
+        let mut optimizer = OptimizerSettings {
+            kind,
+            role: OptimizerRole::Primary,
+            seed: Some(7),
+        }
+        .build();
+        let mut best = f64::NEG_INFINITY;
+        for _ in 0..steps {
+            let candidate = optimizer.propose();
+            let s = score(&candidate);
+            optimizer.observe(&candidate, s);
+            best = best.max(s);
+        }
+        best
+    }
+
+    #[test]
+    fn test_optimizers_find_peak() {
+        assert!(run(OptimizerKind::Bayesian, 20) > 0.95);
+        assert!(run(OptimizerKind::SimulatedAnnealing, 60) > 0.9);
+        assert!(run(OptimizerKind::RandomSearch, 60) > 0.8);
+    }
+
+    #[test]
+    fn test_candidate_maps_to_task() {
+        let task = Candidate {
+            dimension: 0,
+            x: 0.5,
+        }
+        .to_task("random_search", "v_geometric");
+        assert_eq!(
+            task.geometric_operator,
+            GeometricOperator::QuaternionRotation
+        );
+        assert!((task.parameters["theta"].as_f64().unwrap() - PI / 2.0).abs() < 1e-12);
+        assert_eq!(task.parameters["axis"], json!([0.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn test_cholesky_solve_matches_direct_solution() {
+        let l = cholesky(&[vec![4.0, 2.0], vec![2.0, 3.0]]).unwrap();
+        let x = cholesky_solve(&l, &[2.0, 1.0]);
+        assert!((x[0] - 0.5).abs() < 1e-12);
+        assert!(x[1].abs() < 1e-12);
+    }
+}
@@ -0,0 +1,433 @@
+use log::{info, warn};
+use serde_json::{json, Value};
+use tokio::task::JoinSet;
+use tracing::Instrument;
+
+use crate::api::prompt_templates::CAMPAIGN_STEP_TEMPLATE;
+use crate::api::usage::UsageScope;
+use crate::core::error::{Error, Result};
+use crate::core::script_policy::{carries_script, ScriptPolicy};
+use crate::core::types::{GeometricMetrics, GeometricOperator, GeometricTaskCommand};
+use crate::state::AppState;
+
+use super::objectives::{evaluate_objectives, most_lacking, Objective};
+use super::optimizers::{Candidate, Optimizer, OptimizerRole};
+use super::stopping::{StopReason, StoppingMonitor};
+use super::{CampaignHandle, CampaignStatus, ResearchCampaignRequest, ResearchStepSummary};
+
+const LLM_PLANNER: &str = "llm";
+const FALLBACK_PLANNER: &str = "fallback";
+
+type ActiveOptimizer = (OptimizerRole, Box<dyn Optimizer>);
+
+/// A task proposed for the next step and where it came from.
+struct Proposal {
+    task: GeometricTaskCommand,
+    planner: &'static str,
+    candidate: Option<Candidate>,
+}
+
+impl Proposal {
+    fn from_optimizer(optimizer: &mut dyn Optimizer, metric: &str) -> Self {
+        let candidate = optimizer.propose();
+        Self {
+            task: candidate.to_task(optimizer.name(), metric),
+            planner: optimizer.name(),
+            candidate: Some(candidate),
+        }
+    }
+}
+
+/// Drive a campaign to completion, publishing each step through `handle`.
+#[tracing::instrument(name = "campaign", skip_all, fields(campaign.id = %handle.id()))]
+pub async fn run_campaign(
+    state: AppState,
+    handle: CampaignHandle,
+    request: ResearchCampaignRequest,
+    scope: UsageScope,
+) {
+    let (status, reason) = match run_steps(&state, &handle, &request, &scope).await {
+        Ok(StopReason::Cancelled) => (CampaignStatus::Cancelled, Some(StopReason::Cancelled)),
+        Ok(reason) => (CampaignStatus::Completed, Some(reason)),
+        Err(err) => {
+            warn!("Research campaign {} failed: {}", handle.id(), err);
+            (CampaignStatus::Failed(err.to_string()), None)
+        }
+    };
+
+    info!(
+        "Research campaign {} finished: {:?} ({:?})",
+        handle.id(),
+        status,
+        reason
+    );
+    if let Err(err) = handle.finish(status, reason) {
+        warn!("Failed to record campaign {} result: {}", handle.id(), err);
+    }
+}
+
+async fn run_steps(
+    state: &AppState,
+    handle: &CampaignHandle,
+    request: &ResearchCampaignRequest,
+    scope: &UsageScope,
+) -> Result<StopReason> {
+    let mut history: Vec<ResearchStepSummary> = Vec::new();
+    let mut current_metrics = state.processor.get_metrics();
+
+    let objectives = request.resolved_objectives()?;
+    let (mut best_progress, _) = evaluate_objectives(&objectives, &current_metrics);
+    let mut optimizer: Option<ActiveOptimizer> = request
+        .optimizer
+        .as_ref()
+        .map(|settings| (settings.role, settings.build()));
+    handle.update(|snapshot| snapshot.goal_progress = best_progress)?;
+    let mut monitor = StoppingMonitor::new(request.stopping.clone());
+    let candidate_count = request.candidates_per_step.max(1);
+
+    for step_idx in 1..=request.max_steps {
+        if handle.is_cancelled() {
+            return Ok(StopReason::Cancelled);
+        }
+        let tokens_used = state
+            .llm_gateway
+            .usage()
+            .campaign_usage(handle.id())?
+            .total_tokens;
+        if let Some(reason) = monitor.before_step(tokens_used) {
+            return Ok(reason);
+        }
+
+        let relevant = state
+            .retriever
+            .retrieve(&request.goal)
+            .await
+            .unwrap_or_else(|err| {
+                warn!("Retrieval for campaign {} failed: {}", handle.id(), err);
+                Vec::new()
+            });
+        let llm_context = json!({
+            "goal": request.goal,
+            "relevant": relevant,
+            "optimization_target": request.optimization_target,
+            "objectives": objectives,
+            "current_metrics": current_metrics,
+            "history": history,
+            "goal_progress": best_progress,
+            "user_context": request.context,
+        });
+        let focus = most_lacking(&objectives, &current_metrics)
+            .expect("objectives are validated to be non-empty");
+        let step_span = tracing::info_span!("campaign.step", step = step_idx);
+
+        let proposals = match plan_step(
+            state,
+            scope,
+            &llm_context,
+            focus,
+            optimizer.as_mut(),
+            candidate_count,
+        )
+        .instrument(step_span.clone())
+        .await?
+        {
+            Ok(proposals) => proposals,
+            Err(reason) => {
+                warn!("Stopping research campaign {}: {:?}", handle.id(), reason);
+                return Ok(reason);
+            }
+        };
+
+        // the planner call may take a while; honour cancellation before executing
+        if handle.is_cancelled() {
+            return Ok(StopReason::Cancelled);
+        }
+
+        let candidate_scores = if proposals.len() > 1 {
+            score_isolated(state, &proposals, &objectives)
+                .instrument(step_span.clone())
+                .await?
+        } else {
+            Vec::new()
+        };
+        let chosen = candidate_scores
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map_or(0, |(index, _)| index);
+        if let Some((_, optimizer)) = optimizer.as_mut() {
+            for (proposal, score) in proposals.iter().zip(&candidate_scores) {
+                if let Some(candidate) = &proposal.candidate {
+                    optimizer.observe(candidate, *score);
+                }
+            }
+        }
+
+        let candidates_evaluated = proposals.len();
+        let Proposal {
+            task: mut task_template,
+            planner,
+            candidate,
+        } = proposals
+            .into_iter()
+            .nth(chosen)
+            .expect("chosen index comes from the proposal list");
+
+        // keep planner-assigned IDs (they link the LLM audit log) unless taken
+        if task_template
+            .task_id
+            .is_some_and(|id| state.processor.get_task_status(id).is_ok())
+        {
+            task_template.task_id = None;
+        }
+
+        let task_clone = task_template.clone();
+        let task_id = step_span.in_scope(|| state.processor.submit_task(task_template))?;
+        // the task gets a trace of its own, linked from the step that spawned it
+        let task_span = tracing::info_span!(parent: None, "campaign.task", task.id = %task_id);
+        step_span.follows_from(&task_span);
+        let execution = task_span.in_scope(|| state.processor.execute_task(task_id))?;
+        if let Err(err) = state.retriever.index_task(&task_clone, &execution).await {
+            warn!("Failed to index campaign task {}: {}", task_id, err);
+        }
+
+        current_metrics = execution.metrics.clone();
+        let (progress, objective_progress) = evaluate_objectives(&objectives, &current_metrics);
+        if candidates_evaluated == 1 {
+            if let (Some((_, optimizer)), Some(candidate)) = (optimizer.as_mut(), candidate) {
+                optimizer.observe(&candidate, progress);
+            }
+        }
+        let improvement = (progress - best_progress).max(0.0);
+        if progress > best_progress {
+            best_progress = progress;
+        }
+
+        let summary = ResearchStepSummary {
+            step: step_idx,
+            task: task_clone,
+            planner: planner.to_string(),
+            result_metrics: current_metrics.clone(),
+            improvement,
+            progress,
+            objective_progress,
+            candidate_scores,
+        };
+        history.push(summary.clone());
+        handle.update(|snapshot| {
+            snapshot.history.push(summary);
+            snapshot.completed_steps = step_idx;
+            snapshot.goal_progress = best_progress;
+            snapshot.current_metrics = current_metrics.clone();
+        })?;
+
+        if let Some(reason) = monitor.after_step(progress, improvement) {
+            return Ok(reason);
+        }
+    }
+
+    Ok(StopReason::MaxSteps)
+}
+
+/// Collect up to `count` proposals for the next step. The inner `Err`
+/// carries a reason to stop the campaign instead of running a step.
+async fn plan_step(
+    state: &AppState,
+    scope: &UsageScope,
+    llm_context: &Value,
+    focus: &Objective,
+    optimizer: Option<&mut ActiveOptimizer>,
+    count: usize,
+) -> Result<std::result::Result<Vec<Proposal>, StopReason>> {
+    if let Some((OptimizerRole::Primary, optimizer)) = optimizer {
+        let proposals = (0..count)
+            .map(|_| Proposal::from_optimizer(optimizer.as_mut(), &focus.metric))
+            .collect();
+        return Ok(Ok(proposals));
+    }
+
+    let query = state
+        .llm_gateway
+        .prompts()
+        .render(CAMPAIGN_STEP_TEMPLATE, llm_context)?;
+
+    let mut queries = JoinSet::new();
+    for _ in 0..count {
+        let gateway = state.llm_gateway.clone();
+        let (query, context, scope) = (query.clone(), llm_context.clone(), scope.clone());
+        queries.spawn(async move {
+            gateway
+                .submit_geometric_query(&query, &context, &scope)
+                .await
+        });
+    }
+
+    let mut proposals = Vec::with_capacity(count);
+    let mut failures = Vec::new();
+    while let Some(joined) = queries.join_next().await {
+        let outcome = joined
+            .map_err(|err| Error::LlmCommunication(format!("planner task failed: {err}")))?;
+        match outcome {
+            // campaigns run unattended, so held script tasks would stall them
+            Ok(task)
+                if carries_script(&task)
+                    && state.processor.script_policy() != ScriptPolicy::Allow =>
+            {
+                failures.push(Error::PolicyViolation(format!(
+                    "planned script task `{}` needs approval",
+                    task.task_name
+                )))
+            }
+            Ok(task) => proposals.push(Proposal {
+                task,
+                planner: LLM_PLANNER,
+                candidate: None,
+            }),
+            Err(err) => failures.push(err),
+        }
+    }
+
+    let budget_exhausted = failures
+        .iter()
+        .any(|err| matches!(err, Error::BudgetExceeded(_)));
+    if proposals.is_empty() && budget_exhausted {
+        return Ok(Err(StopReason::TokenBudget));
+    }
+    if failures.is_empty() {
+        return Ok(Ok(proposals));
+    }
+
+    match optimizer {
+        Some((_, optimizer)) => {
+            warn!(
+                "{} LLM research proposal(s) failed ({}). Using {} proposals.",
+                failures.len(),
+                failures[0],
+                optimizer.name()
+            );
+            for _ in 0..failures.len() {
+                proposals.push(Proposal::from_optimizer(optimizer.as_mut(), &focus.metric));
+            }
+        }
+        None if proposals.is_empty() => {
+            warn!(
+                "LLM research step failed ({}). Using fallback command.",
+                failures[0]
+            );
+            proposals.push(Proposal {
+                task: fallback_task_for_target(&focus.metric, focus.resolved_value()),
+                planner: FALLBACK_PLANNER,
+                candidate: None,
+            });
+        }
+        None => warn!(
+            "{} of {} LLM research proposals failed ({}).",
+            failures.len(),
+            count,
+            failures[0]
+        ),
+    }
+
+    Ok(Ok(proposals))
+}
+
+/// Score every proposal against its own copy of the current state.
+async fn score_isolated(
+    state: &AppState,
+    proposals: &[Proposal],
+    objectives: &[Objective],
+) -> Result<Vec<f64>> {
+    let processor = state.processor.clone();
+    let tasks: Vec<GeometricTaskCommand> = proposals.iter().map(|p| p.task.clone()).collect();
+    let outcomes: Vec<GeometricMetrics> =
+        tokio::task::spawn_blocking(move || processor.evaluate_isolated(&tasks))
+            .await
+            .map_err(|err| Error::TaskExecution(format!("isolated evaluation failed: {err}")))??;
+
+    Ok(outcomes
+        .iter()
+        .map(|metrics| evaluate_objectives(objectives, metrics).0)
+        .collect())
+}
+
+/// Rule-based command nudging `target` toward `target_value`, used when no
+/// planner produced a usable task.
+pub fn fallback_task_for_target(target: &str, target_value: f64) -> GeometricTaskCommand {
+    match target {
+        "topological_winding" | "q_oscillator" => GeometricTaskCommand {
+            task_name: "Fallback Zitterbewegung tuning".into(),
+            geometric_operator: GeometricOperator::Zitterbewegung,
+            target_module: "sys6_resonator".into(),
+            parameters: json!({ "frequency_scale": target_value / 9.0 }),
+            expected_output_metric: target.into(),
+            task_id: None,
+        },
+        "quaternion_coherence" | "v_geometric" => GeometricTaskCommand {
+            task_name: "Fallback Quaternion coherence".into(),
+            geometric_operator: GeometricOperator::QuaternionRotation,
+            target_module: "sys7_core".into(),
+            parameters: json!({ "theta": 0.25, "axis": [0.0, 1.0, 0.0] }),
+            expected_output_metric: target.into(),
+            task_id: None,
+        },
+        "emergent_electron_mass" => GeometricTaskCommand {
+            task_name: "Fallback mass adjustment".into(),
+            geometric_operator: GeometricOperator::Zitterbewegung,
+            target_module: "sys6_resonator".into(),
+            parameters: json!({ "frequency_scale": 1.0 }),
+            expected_output_metric: target.into(),
+            task_id: None,
+        },
+        "fine_structure_constant" => GeometricTaskCommand {
+            task_name: "Fallback α tuning".into(),
+            geometric_operator: GeometricOperator::QuaternionRotation,
+            target_module: "sys7_alpha".into(),
+            parameters: json!({ "theta": 0.1 }),
+            expected_output_metric: target.into(),
+            task_id: None,
+        },
+        _ => GeometricTaskCommand {
+            task_name: "Fallback geometric derivation".into(),
+            geometric_operator: GeometricOperator::GeometricDerivation,
+            target_module: "sys5_topology".into(),
+            parameters: json!({ "delta": 0.01 }),
+            expected_output_metric: target.into(),
+            task_id: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::llm_gateway::LlmGateway;
+    use crate::api::mock_llm::MockProvider;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_campaign_runs_end_to_end_with_mock_provider() {
+        let gateway = LlmGateway::with_provider(Arc::new(MockProvider::new())).unwrap();
+        let state = AppState::with_llm_gateway(gateway);
+        let request: ResearchCampaignRequest = serde_json::from_value(json!({
+            "goal": "wind the topology",
+            "optimization_target": "topological_winding",
+            "max_steps": 3,
+            "stopping": { "target_progress": 2.0 },
+        }))
+        .unwrap();
+
+        let objectives = request.resolved_objectives().unwrap();
+        let handle = state
+            .campaigns
+            .create(&request, objectives, state.processor.get_metrics())
+            .unwrap();
+        let id = handle.id();
+        run_campaign(state.clone(), handle, request, UsageScope::for_key(None)).await;
+
+        let snapshot = state.campaigns.get(id).unwrap().unwrap();
+        assert_eq!(snapshot.status, CampaignStatus::Completed);
+        assert_eq!(snapshot.stop_reason, Some(StopReason::MaxSteps));
+        assert_eq!(snapshot.history.len(), 3);
+        assert!(snapshot.history.iter().all(|step| step.planner == LLM_PLANNER));
+    }
+}
@@ -0,0 +1,133 @@
+//! Early-stopping rules evaluated around every campaign step.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoppingCriteria {
+    /// Stop once the scalarised progress reaches this value.
+    #[serde(default = "default_target_progress")]
+    pub target_progress: f64,
+    /// Stop after this many consecutive steps without sufficient improvement.
+    pub patience: Option<usize>,
+    /// Improvement below this value counts as a stale step for `patience`.
+    #[serde(default)]
+    pub min_improvement: f64,
+    /// Wall-clock budget for the whole campaign.
+    pub max_duration_secs: Option<f64>,
+    /// Token budget for the whole campaign, independent of gateway budgets.
+    pub max_tokens: Option<u64>,
+}
+
+fn default_target_progress() -> f64 {
+    0.999
+}
+
+impl Default for StoppingCriteria {
+    fn default() -> Self {
+        Self {
+            target_progress: default_target_progress(),
+            patience: None,
+            min_improvement: 0.0,
+            max_duration_secs: None,
+            max_tokens: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    TargetReached,
+    MaxSteps,
+    Plateau,
+    WallClock,
+    TokenBudget,
+    Cancelled,
+}
+
+/// Tracks campaign progress against a set of `StoppingCriteria`.
+pub struct StoppingMonitor {
+    criteria: StoppingCriteria,
+    started: Instant,
+    stale_steps: usize,
+}
+
+impl StoppingMonitor {
+    pub fn new(criteria: StoppingCriteria) -> Self {
+        Self {
+            criteria,
+            started: Instant::now(),
+            stale_steps: 0,
+        }
+    }
+
+    /// Budgets checked before spending time or tokens on another step.
+    pub fn before_step(&self, tokens_used: u64) -> Option<StopReason> {
+        if let Some(limit) = self.criteria.max_duration_secs {
+            if self.started.elapsed() >= Duration::from_secs_f64(limit.max(0.0)) {
+                return Some(StopReason::WallClock);
+            }
+        }
+        if let Some(limit) = self.criteria.max_tokens {
+            if tokens_used >= limit {
+                return Some(StopReason::TokenBudget);
+            }
+        }
+        None
+    }
+
+    /// Convergence checks after a step has been executed.
+    pub fn after_step(&mut self, progress: f64, improvement: f64) -> Option<StopReason> {
+        if progress >= self.criteria.target_progress {
+            return Some(StopReason::TargetReached);
+        }
+
+        if improvement > self.criteria.min_improvement {
+            self.stale_steps = 0;
+        } else {
+            self.stale_steps += 1;
+        }
+        match self.criteria.patience {
+            Some(patience) if self.stale_steps >= patience.max(1) => Some(StopReason::Plateau),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plateau_after_patience_stale_steps() {
+        let mut monitor = StoppingMonitor::new(StoppingCriteria {
+            patience: Some(2),
+            min_improvement: 0.01,
+            ..StoppingCriteria::default()
+        });
+
+        assert_eq!(monitor.after_step(0.5, 0.2), None);
+        assert_eq!(monitor.after_step(0.505, 0.005), None);
+        assert_eq!(monitor.after_step(0.6, 0.095), None);
+        assert_eq!(monitor.after_step(0.6, 0.0), None);
+        assert_eq!(monitor.after_step(0.6, 0.0), Some(StopReason::Plateau));
+    }
+
+    #[test]
+    fn test_budgets_checked_before_step() {
+        let monitor = StoppingMonitor::new(StoppingCriteria {
+            max_tokens: Some(100),
+            max_duration_secs: Some(0.0),
+            ..StoppingCriteria::default()
+        });
+        assert_eq!(monitor.before_step(0), Some(StopReason::WallClock));
+
+        let monitor = StoppingMonitor::new(StoppingCriteria {
+            max_tokens: Some(100),
+            ..StoppingCriteria::default()
+        });
+        assert_eq!(monitor.before_step(99), None);
+        assert_eq!(monitor.before_step(100), Some(StopReason::TokenBudget));
+    }
+}
@@ -0,0 +1,718 @@
+//! Server configuration, layered from defaults, `config.toml` (or the file
+//! named by `MMSS_CONFIG`) and the environment variables the server has
+//! always read, which take precedence over the file.
+//!
+//! Only settings needed before the runtime starts, or that several modules
+//! share, live here; the server hands the resolved `Config` to the
+//! constructors that need it. Everything else is still read by each
+//! module's `from_env`.
+
+use crate::api::llm_gateway::PlanningMode;
+use crate::core::credentials;
+use crate::core::error::{Error, Result};
+use crate::core::mqtt_telemetry;
+use crate::state::PhysicalConstants;
+use crate::telemetry::{parse_rotation, LogFormat};
+use figment::providers::{Env, Format, Toml};
+use figment::Figment;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::net::ToSocketAddrs;
+use std::path::{Path, PathBuf};
+
+/// Read from the working directory when `MMSS_CONFIG` is unset; may be absent.
+pub const DEFAULT_PATH: &str = "config.toml";
+
+/// Environment variables and the keys they override.
+const ENV_OVERRIDES: &[(&str, &str)] = &[
+    ("MMSS_BIND", "server.bind"),
+    ("MMSS_STATIC_DIR", "server.static_dir"),
+    ("MMSS_ADMIN_TOKEN", "server.admin_token"),
+    ("MMSS_OPERATOR_KEYS", "server.operator_keys"),
+    ("MMSS_JWT_SECRET", "server.jwt_secret"),
+    ("MMSS_AUDIT_KEY", "server.audit_key"),
+    ("MMSS_PLUGIN_DIR", "server.plugin_dir"),
+    ("MMSS_FLIGHT_BIND", "server.flight_bind"),
+    ("MMSS_RUNTIME_THREADS", "workers.runtime_threads"),
+    ("MMSS_BLOCKING_THREADS", "workers.blocking_threads"),
+    ("MMSS_LLM_PROVIDER", "llm.provider"),
+    ("MISTRAL_API_KEY", "llm.api_key"),
+    ("MISTRAL_MODEL", "llm.model"),
+    ("MISTRAL_PLANNING_MODE", "llm.planning_mode"),
+    ("MMSS_LLM_RETRIES", "llm.retries"),
+    ("MMSS_EXPORT_DIR", "persistence.export_dir"),
+    (
+        "MMSS_METRICS_HISTORY_DIR",
+        "persistence.metrics_history_dir",
+    ),
+    ("MMSS_EQGFT_CACHE_DIR", "persistence.eqgft_cache_dir"),
+    ("MMSS_PROMPT_DIR", "persistence.prompt_dir"),
+    ("MMSS_LLM_AUDIT_LOG", "persistence.audit_log"),
+    ("MMSS_STATE_DIR", "persistence.state_dir"),
+    ("MMSS_STATE_PERSIST_SECS", "persistence.state_persist_secs"),
+    ("MMSS_JOURNAL_PATH", "persistence.journal"),
+    ("MMSS_AUDIT_TRAIL_PATH", "persistence.audit_trail"),
+    ("MMSS_REDIS_URL", "cluster.redis_url"),
+    ("MMSS_REDIS_PREFIX", "cluster.key_prefix"),
+    ("MMSS_LEADER_TTL_SECS", "cluster.leader_ttl_secs"),
+    ("MMSS_KAFKA_BROKERS", "events.kafka_brokers"),
+    ("MMSS_NATS_URL", "events.nats_url"),
+    ("MMSS_EVENTS_TASK_TOPIC", "events.task_topic"),
+    ("MMSS_EVENTS_METRICS_TOPIC", "events.metrics_topic"),
+    ("MMSS_EVENTS_ALERT_TOPIC", "events.alert_topic"),
+    ("MMSS_EVENTS_MAX_BACKOFF_SECS", "events.max_backoff_secs"),
+    ("MMSS_MQTT_BROKER", "mqtt.broker"),
+    ("MMSS_MQTT_CLIENT_ID", "mqtt.client_id"),
+    ("MMSS_MQTT_METRICS_TOPIC", "mqtt.metrics_topic"),
+    ("MMSS_MQTT_ALERT_TOPIC", "mqtt.alert_topic"),
+    ("MMSS_MQTT_METRICS", "mqtt.metrics"),
+    ("MMSS_MQTT_PERIOD_MS", "mqtt.period_ms"),
+    ("RUST_LOG", "logging.filter"),
+    ("MMSS_LOG_FORMAT", "logging.format"),
+    ("MMSS_LOG_FILE", "logging.file"),
+    ("MMSS_LOG_ROTATION", "logging.rotation"),
+    ("MMSS_LOG_MAX_FILES", "logging.max_files"),
+    ("OTEL_EXPORTER_OTLP_ENDPOINT", "telemetry.otlp_endpoint"),
+    ("OTEL_SERVICE_NAME", "telemetry.service_name"),
+    ("MMSS_TRACE_FILTER", "telemetry.filter"),
+    ("MMSS_HBAR", "constants.hbar"),
+    ("MMSS_SPEED_OF_LIGHT", "constants.c"),
+    ("MMSS_ZITTER_FREQUENCY", "constants.zitter_frequency"),
+    ("MMSS_ZITTER_AMPLITUDE", "constants.zitter_amplitude"),
+    ("MMSS_FINE_STRUCTURE", "constants.fine_structure"),
+    ("MMSS_DASHBOARD", "features.dashboard"),
+    ("MMSS_STATIC_UI", "features.static_ui"),
+];
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub workers: WorkerConfig,
+    pub llm: LlmConfig,
+    pub persistence: PersistenceConfig,
+    pub cluster: ClusterConfig,
+    pub events: EventsConfig,
+    pub mqtt: MqttConfig,
+    pub logging: LoggingConfig,
+    pub telemetry: TelemetryConfig,
+    /// SI unless overridden, e.g. with `hbar = 1.0` and `c = 1.0`.
+    pub constants: PhysicalConstants,
+    pub features: FeatureConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: String,
+    /// Served at `/` when `features.static_ui` is on.
+    pub static_dir: PathBuf,
+    /// Bearer token for `POST /admin/reset`, which is refused without one.
+    pub admin_token: Option<String>,
+    /// `name:key` pairs, comma-separated, identifying operators who may
+    /// change rules; sent as a bearer token or `x-api-key`.
+    pub operator_keys: Option<String>,
+    /// HS256 secret JWT bearer tokens must be signed with to identify
+    /// their subject.
+    pub jwt_secret: Option<String>,
+    /// HMAC key of the audit trail's hash chain.
+    pub audit_key: Option<String>,
+    /// Plugin libraries loaded at startup; none by default.
+    pub plugin_dir: Option<PathBuf>,
+    /// Serves task records over Arrow Flight here; needs the `flight`
+    /// feature.
+    pub flight_bind: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: "127.0.0.1:8080".to_string(),
+            static_dir: PathBuf::from("src/web"),
+            admin_token: None,
+            operator_keys: None,
+            jwt_secret: None,
+            audit_key: None,
+            plugin_dir: None,
+            flight_bind: None,
+        }
+    }
+}
+
+/// Tokio runtime sizing; tokio's own defaults when unset.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkerConfig {
+    /// Async worker threads, one per core by default.
+    pub runtime_threads: Option<usize>,
+    /// Upper bound on `spawn_blocking` threads, used by charts, meshes and
+    /// other CPU-heavy routes.
+    pub blocking_threads: Option<usize>,
+}
+
+impl WorkerConfig {
+    pub fn runtime(&self) -> std::io::Result<tokio::runtime::Runtime> {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.enable_all();
+        if let Some(threads) = self.runtime_threads {
+            builder.worker_threads(threads);
+        }
+        if let Some(threads) = self.blocking_threads {
+            builder.max_blocking_threads(threads);
+        }
+        builder.build()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmConfig {
+    /// `mistral`, `mock`, or `local` with the `local-llm` feature.
+    pub provider: String,
+    pub api_key: Option<String>,
+    pub model: Option<String>,
+    /// `json`, or `tools` (`tool_call`).
+    pub planning_mode: Option<String>,
+    pub retries: Option<u32>,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self {
+            provider: "mistral".to_string(),
+            api_key: None,
+            model: None,
+            planning_mode: None,
+            retries: None,
+        }
+    }
+}
+
+/// Where state is kept on disk; each module's default when unset.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PersistenceConfig {
+    pub export_dir: Option<PathBuf>,
+    /// Enables metrics history.
+    pub metrics_history_dir: Option<PathBuf>,
+    pub eqgft_cache_dir: Option<PathBuf>,
+    pub prompt_dir: Option<PathBuf>,
+    pub audit_log: Option<PathBuf>,
+    /// Enables warm start: state is loaded from here at startup and saved
+    /// at shutdown.
+    pub state_dir: Option<PathBuf>,
+    /// Also save the state on this period.
+    pub state_persist_secs: Option<u64>,
+    /// Append every state mutation here, for replay.
+    pub journal: Option<PathBuf>,
+    /// Hash-chained record of privileged operations; kept in memory
+    /// when unset.
+    pub audit_trail: Option<PathBuf>,
+}
+
+/// Replicas sharing tasks and metrics; needs the `shared-state` feature.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClusterConfig {
+    /// Enables sharing, e.g. `redis://redis:6379`.
+    pub redis_url: Option<String>,
+    /// Prefix of every key and channel; `mmss` by default.
+    pub key_prefix: Option<String>,
+    /// How long a silent leader keeps the background loops; 15 by default.
+    pub leader_ttl_secs: Option<u64>,
+}
+
+/// Events published to Kafka (`kafka` feature) or NATS JetStream (`nats`
+/// feature).
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventsConfig {
+    /// Enables publishing to Kafka, e.g. `kafka-1:9092,kafka-2:9092`.
+    pub kafka_brokers: Option<String>,
+    /// Enables publishing to NATS, e.g. `nats://nats:4222`.
+    pub nats_url: Option<String>,
+    /// Topic or subject of task transitions; `mmss.tasks` by default.
+    pub task_topic: Option<String>,
+    /// Of metrics snapshots; `mmss.metrics` by default.
+    pub metrics_topic: Option<String>,
+    /// Of failed tasks, tasks awaiting approval and resets; `mmss.alerts`
+    /// by default.
+    pub alert_topic: Option<String>,
+    /// Longest wait between retries of an unacknowledged message; 30 by
+    /// default.
+    pub max_backoff_secs: Option<u64>,
+}
+
+/// Metrics and alerts published over MQTT; needs the `mqtt` feature.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
+    /// Enables publishing, e.g. `mosquitto:1883`; port 1883 by default.
+    pub broker: Option<String>,
+    /// `mmss` by default; unique per broker.
+    pub client_id: Option<String>,
+    /// Prefix of the retained per-metric topics; `mmss/metrics` by default.
+    pub metrics_topic: Option<String>,
+    /// Of failed tasks, tasks awaiting approval and resets; `mmss/alerts`
+    /// by default.
+    pub alert_topic: Option<String>,
+    /// Comma-separated metrics to publish, custom ones included; all by
+    /// default.
+    pub metrics: Option<String>,
+    /// Between two publications of the metrics; 1000 by default.
+    pub period_ms: Option<u64>,
+}
+
+/// Log lines, JSON on stderr unless set otherwise.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Per-module levels in `RUST_LOG` syntax, e.g. `info,mmss::campaign=debug`;
+    /// `info` by default, and replaceable at runtime.
+    pub filter: Option<String>,
+    /// `json` or `text`.
+    pub format: Option<String>,
+    /// Write here instead of stderr.
+    pub file: Option<PathBuf>,
+    /// `never`, `minutely`, `hourly` or `daily`.
+    pub rotation: Option<String>,
+    /// Rotated files kept; all by default.
+    pub max_files: Option<usize>,
+}
+
+/// OpenTelemetry export of traces and metrics.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// Enables export to this OTLP/gRPC collector, e.g.
+    /// `http://otel-collector:4317`.
+    pub otlp_endpoint: Option<String>,
+    /// `mmss` by default.
+    pub service_name: Option<String>,
+    /// Which spans to export, in `RUST_LOG` syntax; `info` by default.
+    pub filter: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureConfig {
+    /// The embedded dashboard under `/dashboard`.
+    pub dashboard: bool,
+    /// Files from `server.static_dir` for every path the API does not serve.
+    pub static_ui: bool,
+}
+
+impl Default for FeatureConfig {
+    fn default() -> Self {
+        Self {
+            dashboard: true,
+            static_ui: true,
+        }
+    }
+}
+
+impl Config {
+    /// `config.toml`, or the file named by `MMSS_CONFIG`, which must exist,
+    /// with environment overrides applied.
+    pub fn load() -> Result<Self> {
+        let path = match env::var_os("MMSS_CONFIG") {
+            Some(path) => {
+                let path = PathBuf::from(path);
+                if !path.is_file() {
+                    return Err(Error::Config(format!(
+                        "MMSS_CONFIG names `{}`, which is not a file",
+                        path.display()
+                    )));
+                }
+                path
+            }
+            None => PathBuf::from(DEFAULT_PATH),
+        };
+        Self::from_figment(Self::figment(&path))
+    }
+
+    /// Defaults, then `path` if it exists, then the environment.
+    pub fn figment(path: &Path) -> Figment {
+        Figment::new()
+            .merge(Toml::file(path))
+            .merge(Env::raw().filter_map(|key| {
+                ENV_OVERRIDES
+                    .iter()
+                    .find(|(name, _)| key.as_str().eq_ignore_ascii_case(name))
+                    .map(|(_, path)| (*path).into())
+            }))
+    }
+
+    pub fn from_figment(figment: Figment) -> Result<Self> {
+        figment.extract().map_err(|e| Error::Config(e.to_string()))
+    }
+
+    /// Every problem found, naming the setting and its variable.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+
+        if let Err(e) = self.server.bind.to_socket_addrs() {
+            problems.push(format!(
+                "server.bind (MMSS_BIND) `{}` is not an address like 127.0.0.1:8080: {}",
+                self.server.bind, e
+            ));
+        }
+        if let Some(bind) = &self.server.flight_bind {
+            if let Err(e) = bind.to_socket_addrs() {
+                problems.push(format!(
+                    "server.flight_bind (MMSS_FLIGHT_BIND) `{}` is not an address like 127.0.0.1:50051: {}",
+                    bind, e
+                ));
+            }
+        }
+        if self.features.static_ui && !self.server.static_dir.is_dir() {
+            problems.push(format!(
+                "server.static_dir (MMSS_STATIC_DIR) `{}` is not a directory; \
+                 point it at the web UI or set features.static_ui = false",
+                self.server.static_dir.display()
+            ));
+        }
+        if let Some(Err(e)) = self
+            .server
+            .operator_keys
+            .as_deref()
+            .map(credentials::parse_operator_keys)
+        {
+            problems.push(format!("server.operator_keys (MMSS_OPERATOR_KEYS): {}", e));
+        }
+        for (key, secret) in [
+            ("server.jwt_secret (MMSS_JWT_SECRET)", &self.server.jwt_secret),
+            ("server.audit_key (MMSS_AUDIT_KEY)", &self.server.audit_key),
+        ] {
+            if secret
+                .as_ref()
+                .is_some_and(|secret| secret.len() < credentials::MIN_SECRET_LEN)
+            {
+                problems.push(format!(
+                    "{} must be at least {} bytes",
+                    key,
+                    credentials::MIN_SECRET_LEN
+                ));
+            }
+        }
+        if let Some(dir) = self.server.plugin_dir.as_deref().filter(|dir| !dir.is_dir()) {
+            problems.push(format!(
+                "server.plugin_dir (MMSS_PLUGIN_DIR) `{}` is not a directory",
+                dir.display()
+            ));
+        }
+
+        for (key, threads) in [
+            (
+                "workers.runtime_threads (MMSS_RUNTIME_THREADS)",
+                self.workers.runtime_threads,
+            ),
+            (
+                "workers.blocking_threads (MMSS_BLOCKING_THREADS)",
+                self.workers.blocking_threads,
+            ),
+        ] {
+            if threads == Some(0) {
+                problems.push(format!("{} must be at least 1", key));
+            }
+        }
+
+        match self.llm.provider.as_str() {
+            "mistral" if self.llm.api_key.is_none() => problems.push(
+                "llm.api_key (MISTRAL_API_KEY) is required by the mistral provider; \
+                 set it or choose llm.provider = \"mock\""
+                    .to_string(),
+            ),
+            "mistral" | "mock" => {}
+            "local" if cfg!(feature = "local-llm") => {}
+            "local" => problems.push(
+                "llm.provider (MMSS_LLM_PROVIDER) `local` needs the server built with \
+                 the local-llm feature"
+                    .to_string(),
+            ),
+            other => problems.push(format!(
+                "llm.provider (MMSS_LLM_PROVIDER) `{}` is not one of mistral, mock, local",
+                other
+            )),
+        }
+        if let Some(mode) = &self.llm.planning_mode {
+            if PlanningMode::parse(mode).is_none() {
+                problems.push(format!(
+                    "llm.planning_mode (MISTRAL_PLANNING_MODE) `{}` is not json, tools or \
+                     tool_call",
+                    mode
+                ));
+            }
+        }
+
+        let persistence = &self.persistence;
+        for (key, dir) in [
+            (
+                "persistence.export_dir (MMSS_EXPORT_DIR)",
+                &persistence.export_dir,
+            ),
+            (
+                "persistence.metrics_history_dir (MMSS_METRICS_HISTORY_DIR)",
+                &persistence.metrics_history_dir,
+            ),
+            (
+                "persistence.eqgft_cache_dir (MMSS_EQGFT_CACHE_DIR)",
+                &persistence.eqgft_cache_dir,
+            ),
+            (
+                "persistence.prompt_dir (MMSS_PROMPT_DIR)",
+                &persistence.prompt_dir,
+            ),
+            (
+                "persistence.state_dir (MMSS_STATE_DIR)",
+                &persistence.state_dir,
+            ),
+        ] {
+            if let Some(dir) = dir.as_deref().filter(|dir| dir.exists() && !dir.is_dir()) {
+                problems.push(format!("{} `{}` is not a directory", key, dir.display()));
+            }
+        }
+        if let Some(log) = persistence.audit_log.as_deref().filter(|log| log.is_dir()) {
+            problems.push(format!(
+                "persistence.audit_log (MMSS_LLM_AUDIT_LOG) `{}` is a directory, not a file",
+                log.display()
+            ));
+        }
+        if let Some(journal) = persistence.journal.as_deref().filter(|path| path.is_dir()) {
+            problems.push(format!(
+                "persistence.journal (MMSS_JOURNAL_PATH) `{}` is a directory, not a file",
+                journal.display()
+            ));
+        }
+        if let Some(trail) = persistence.audit_trail.as_deref().filter(|path| path.is_dir()) {
+            problems.push(format!(
+                "persistence.audit_trail (MMSS_AUDIT_TRAIL_PATH) `{}` is a directory, not a file",
+                trail.display()
+            ));
+        }
+
+        if self.cluster.redis_url.is_some() && !cfg!(feature = "shared-state") {
+            problems.push(
+                "cluster.redis_url (MMSS_REDIS_URL) needs the server built with the \
+                 shared-state feature"
+                    .to_string(),
+            );
+        }
+        if self.cluster.leader_ttl_secs == Some(0) {
+            problems.push(
+                "cluster.leader_ttl_secs (MMSS_LEADER_TTL_SECS) must be at least 1".to_string(),
+            );
+        }
+
+        let events = &self.events;
+        if events.kafka_brokers.is_some() && !cfg!(feature = "kafka") {
+            problems.push(
+                "events.kafka_brokers (MMSS_KAFKA_BROKERS) needs the server built with the \
+                 kafka feature"
+                    .to_string(),
+            );
+        }
+        if events.nats_url.is_some() && !cfg!(feature = "nats") {
+            problems.push(
+                "events.nats_url (MMSS_NATS_URL) needs the server built with the nats feature"
+                    .to_string(),
+            );
+        }
+        if events.kafka_brokers.is_some() && events.nats_url.is_some() {
+            problems.push(
+                "events.kafka_brokers (MMSS_KAFKA_BROKERS) and events.nats_url (MMSS_NATS_URL) \
+                 are exclusive; events go to one broker"
+                    .to_string(),
+            );
+        }
+        if events.max_backoff_secs == Some(0) {
+            problems.push(
+                "events.max_backoff_secs (MMSS_EVENTS_MAX_BACKOFF_SECS) must be at least 1"
+                    .to_string(),
+            );
+        }
+
+        let mqtt = &self.mqtt;
+        if let Some(broker) = &mqtt.broker {
+            if !cfg!(feature = "mqtt") {
+                problems.push(
+                    "mqtt.broker (MMSS_MQTT_BROKER) needs the server built with the mqtt feature"
+                        .to_string(),
+                );
+            }
+            if mqtt_telemetry::parse_broker(broker).is_none() {
+                problems.push(format!(
+                    "mqtt.broker (MMSS_MQTT_BROKER) `{}` is not a host or host:port",
+                    broker
+                ));
+            }
+        }
+        if mqtt.period_ms == Some(0) {
+            problems.push("mqtt.period_ms (MMSS_MQTT_PERIOD_MS) must be at least 1".to_string());
+        }
+
+        let logging = &self.logging;
+        for (key, filter) in [
+            ("logging.filter (RUST_LOG)", &logging.filter),
+            (
+                "telemetry.filter (MMSS_TRACE_FILTER)",
+                &self.telemetry.filter,
+            ),
+        ] {
+            if let Some(filter) = filter {
+                if let Err(e) = tracing_subscriber::EnvFilter::try_new(filter) {
+                    problems.push(format!("{} `{}` is invalid: {}", key, filter, e));
+                }
+            }
+        }
+        if let Some(format) = logging
+            .format
+            .as_deref()
+            .filter(|format| LogFormat::parse(format).is_none())
+        {
+            problems.push(format!(
+                "logging.format (MMSS_LOG_FORMAT) must be json or text, not `{}`",
+                format
+            ));
+        }
+        if let Some(rotation) = logging
+            .rotation
+            .as_deref()
+            .filter(|rotation| parse_rotation(rotation).is_none())
+        {
+            problems.push(format!(
+                "logging.rotation (MMSS_LOG_ROTATION) must be never, minutely, hourly or \
+                 daily, not `{}`",
+                rotation
+            ));
+        }
+        if logging.max_files == Some(0) {
+            problems.push("logging.max_files (MMSS_LOG_MAX_FILES) must be at least 1".to_string());
+        }
+        if let Some(file) = logging.file.as_deref().filter(|file| file.is_dir()) {
+            problems.push(format!(
+                "logging.file (MMSS_LOG_FILE) `{}` is a directory, not a file",
+                file.display()
+            ));
+        }
+
+        for name in self.constants.invalid() {
+            problems.push(format!("constants.{} must be a positive number", name));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Config(problems.join("; ")))
+        }
+    }
+}
+
+#[cfg(test)]
+// `Jail` closures return figment's error, which is large
+#[allow(clippy::result_large_err)]
+mod tests {
+    use super::*;
+    use figment::Jail;
+
+    #[test]
+    fn test_environment_overrides_file() {
+        Jail::expect_with(|jail| {
+            jail.create_dir("web")?;
+            jail.create_file(
+                "config.toml",
+                r#"
+                [server]
+                bind = "127.0.0.1:9000"
+                static_dir = "web"
+
+                [workers]
+                runtime_threads = 2
+
+                [llm]
+                provider = "mock"
+                planning_mode = "tools"
+
+                [features]
+                dashboard = false
+                "#,
+            )?;
+            jail.set_env("MMSS_BIND", "0.0.0.0:9100");
+            jail.set_env("MMSS_BLOCKING_THREADS", "8");
+            jail.set_env("MMSS_STATIC_UI", "false");
+
+            let config = Config::from_figment(Config::figment(Path::new("config.toml"))).unwrap();
+            assert_eq!(config.server.bind, "0.0.0.0:9100");
+            assert_eq!(config.workers.runtime_threads, Some(2));
+            assert_eq!(config.workers.blocking_threads, Some(8));
+            assert_eq!(config.llm.provider, "mock");
+            assert!(!config.features.dashboard && !config.features.static_ui);
+            config.validate().unwrap();
+
+            let missing = Config::from_figment(Config::figment(Path::new("absent.toml"))).unwrap();
+            assert_eq!(missing.server.static_dir, PathBuf::from("src/web"));
+            assert!(missing.features.dashboard && !missing.features.static_ui);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_validation_reports_every_problem() {
+        Jail::expect_with(|jail| {
+            jail.create_file("config.toml", "[server]\nport = 8080\n")?;
+            let err = Config::from_figment(Config::figment(Path::new("config.toml")))
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains("port"), "{}", err);
+
+            let config = Config {
+                server: ServerConfig {
+                    bind: "nowhere".to_string(),
+                    static_dir: PathBuf::from("missing"),
+                    admin_token: None,
+                    operator_keys: Some("alice".to_string()),
+                    jwt_secret: Some("short".to_string()),
+                    audit_key: None,
+                    plugin_dir: None,
+                    flight_bind: Some("nowhere".to_string()),
+                },
+                workers: WorkerConfig {
+                    runtime_threads: Some(0),
+                    blocking_threads: None,
+                },
+                llm: LlmConfig {
+                    planning_mode: Some("yaml".to_string()),
+                    ..LlmConfig::default()
+                },
+                logging: LoggingConfig {
+                    format: Some("xml".to_string()),
+                    rotation: Some("weekly".to_string()),
+                    ..LoggingConfig::default()
+                },
+                telemetry: TelemetryConfig {
+                    filter: Some("mmss=loud".to_string()),
+                    ..TelemetryConfig::default()
+                },
+                ..Config::default()
+            };
+            let err = config.validate().unwrap_err().to_string();
+            for key in [
+                "server.bind",
+                "server.static_dir",
+                "server.operator_keys",
+                "server.jwt_secret",
+                "server.flight_bind",
+                "workers.runtime_threads",
+                "llm.api_key",
+                "llm.planning_mode",
+                "logging.format",
+                "logging.rotation",
+                "telemetry.filter",
+            ] {
+                assert!(err.contains(key), "{} missing from {}", key, err);
+            }
+            assert!(!err.contains("workers.blocking_threads"));
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,146 @@
+use crate::core::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use log::error;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+const DEFAULT_CAPACITY_BYTES: usize = 256 << 20;
+
+/// Metadata for a stored artifact, as returned in task output.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArtifactSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub content_type: String,
+    pub size: usize,
+    pub created_at: DateTime<Utc>,
+    /// Path of the `GET /artifacts/:id` endpoint serving the bytes.
+    pub url: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub summary: ArtifactSummary,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Entries {
+    artifacts: HashMap<Uuid, Artifact>,
+    /// Insertion order, oldest first, for eviction.
+    order: VecDeque<Uuid>,
+    total_bytes: usize,
+}
+
+/// In-memory store for binary task outputs such as plots. Once the stored
+/// bytes exceed the capacity, the oldest artifacts are evicted.
+pub struct ArtifactStore {
+    entries: Mutex<Entries>,
+    capacity_bytes: usize,
+}
+
+impl Default for ArtifactStore {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY_BYTES)
+    }
+}
+
+impl ArtifactStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity_bytes: usize) -> Self {
+        Self {
+            entries: Mutex::new(Entries::default()),
+            capacity_bytes,
+        }
+    }
+
+    /// Capacity from `MMSS_ARTIFACT_CAPACITY_BYTES`, 256 MiB by default.
+    pub fn from_env() -> Self {
+        Self::with_capacity(
+            env::var("MMSS_ARTIFACT_CAPACITY_BYTES")
+                .ok()
+                .and_then(|raw| raw.parse().ok())
+                .unwrap_or(DEFAULT_CAPACITY_BYTES),
+        )
+    }
+
+    pub fn insert(&self, name: &str, content_type: &str, bytes: Vec<u8>) -> Result<ArtifactSummary> {
+        if bytes.len() > self.capacity_bytes {
+            return Err(Error::InvalidParameter(
+                name.to_string(),
+                format!(
+                    "artifact of {} bytes exceeds the store capacity of {} bytes",
+                    bytes.len(),
+                    self.capacity_bytes
+                ),
+            ));
+        }
+        let id = Uuid::new_v4();
+        let summary = ArtifactSummary {
+            id,
+            name: name.to_string(),
+            content_type: content_type.to_string(),
+            size: bytes.len(),
+            created_at: Utc::now(),
+            url: format!("/artifacts/{}", id),
+        };
+
+        let mut entries = self.lock()?;
+        while entries.total_bytes + bytes.len() > self.capacity_bytes {
+            let Some(oldest) = entries.order.pop_front() else {
+                break;
+            };
+            if let Some(evicted) = entries.artifacts.remove(&oldest) {
+                entries.total_bytes -= evicted.bytes.len();
+            }
+        }
+        entries.total_bytes += bytes.len();
+        entries.order.push_back(id);
+        entries.artifacts.insert(
+            id,
+            Artifact {
+                summary: summary.clone(),
+                bytes,
+            },
+        );
+        Ok(summary)
+    }
+
+    pub fn get(&self, id: Uuid) -> Result<Option<Artifact>> {
+        Ok(self.lock()?.artifacts.get(&id).cloned())
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Entries>> {
+        self.entries.lock().map_err(|e| {
+            error!("Failed to lock artifacts: {}", e);
+            Error::TaskExecution("Failed to access artifact storage".to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oldest_artifacts_are_evicted() {
+        let store = ArtifactStore::with_capacity(10);
+        let first = store.insert("a.png", "image/png", vec![1; 6]).unwrap();
+        let second = store.insert("b.png", "image/png", vec![2; 4]).unwrap();
+        assert_eq!(store.get(first.id).unwrap().unwrap().bytes, vec![1; 6]);
+        assert_eq!(first.url, format!("/artifacts/{}", first.id));
+
+        let third = store.insert("c.svg", "image/svg+xml", vec![3; 5]).unwrap();
+        assert!(store.get(first.id).unwrap().is_none());
+        assert!(store.get(second.id).unwrap().is_some());
+        assert_eq!(store.get(third.id).unwrap().unwrap().summary.size, 5);
+
+        assert!(store.insert("huge", "image/png", vec![0; 11]).is_err());
+    }
+}
@@ -0,0 +1,449 @@
+//! Audit trail of privileged operations: who registered or deleted rules,
+//! approved script tasks, changed runtime configuration or reset state.
+//!
+//! Each entry carries the hash of the previous one and its own, so an
+//! entry edited or removed after the fact breaks the chain, which `verify`
+//! reports. With a key (`server.audit_key`) the hashes are HMAC-SHA256, so
+//! only a holder of the key can rewrite the chain to match; without one
+//! they are plain SHA-256, which anyone with the file can recompute.
+//! Entries are appended to `MMSS_AUDIT_TRAIL_PATH` when set and kept in
+//! memory otherwise.
+
+use crate::core::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::env;
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// `prev_hash` of the first entry.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+/// Entries kept without a file; the chain is verified from the oldest kept.
+const DEFAULT_MEMORY_LIMIT: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivilegedAction {
+    RuleRegistered,
+    RuleDeleted,
+    ScriptApproved,
+    ConfigChanged,
+    StateReset,
+    NamespaceDeleted,
+}
+
+/// Who asked for an operation, as verified by `Credentials`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Actor {
+    /// `admin` for the admin token, `operator:<name>` for an operator key,
+    /// `jwt:<subject>` for a signed JWT, or `anonymous`.
+    pub id: String,
+    /// The `x-request-id` of the request, to find its log lines.
+    pub request_id: Option<String>,
+}
+
+impl Actor {
+    pub const ANONYMOUS: &'static str = "anonymous";
+
+    /// An API key, identified by a fingerprint rather than the key itself.
+    pub fn for_api_key(key: &str) -> String {
+        format!("key:{}", &sha256_hex(key.as_bytes())[..12])
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the trail, from 1.
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub actor: Actor,
+    pub action: PrivilegedAction,
+    /// What was acted on, e.g. the rule or the reset scope.
+    pub detail: Value,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// The hash this entry should carry, given its other fields, keyed
+    /// with `key` when there is one.
+    fn expected_hash(&self, key: Option<&[u8]>) -> Result<String> {
+        #[derive(Serialize)]
+        struct Hashed<'a> {
+            seq: u64,
+            timestamp: &'a DateTime<Utc>,
+            actor: &'a Actor,
+            action: PrivilegedAction,
+            detail: &'a Value,
+        }
+        let body = serde_json::to_vec(&Hashed {
+            seq: self.seq,
+            timestamp: &self.timestamp,
+            actor: &self.actor,
+            action: self.action,
+            detail: &self.detail,
+        })?;
+        match key {
+            Some(key) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key)
+                    .map_err(|e| Error::Config(format!("Unusable audit key: {}", e)))?;
+                mac.update(self.prev_hash.as_bytes());
+                mac.update(&body);
+                Ok(hex(&mac.finalize().into_bytes()))
+            }
+            None => {
+                let mut hasher = Sha256::new();
+                hasher.update(self.prev_hash.as_bytes());
+                hasher.update(&body);
+                Ok(hex(&hasher.finalize()))
+            }
+        }
+    }
+}
+
+/// Filter for [`AuditTrail::query`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AuditTrailQuery {
+    pub actor: Option<String>,
+    pub action: Option<PrivilegedAction>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+impl AuditTrailQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.actor
+            .as_ref()
+            .is_none_or(|actor| entry.actor.id == *actor)
+            && self.action.is_none_or(|action| entry.action == action)
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp <= until)
+    }
+}
+
+/// Result of [`AuditTrail::verify`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChainReport {
+    pub entries: usize,
+    pub valid: bool,
+    /// The first entry whose hash or link does not match.
+    pub first_invalid_seq: Option<u64>,
+}
+
+struct Chain {
+    file: Option<File>,
+    last_seq: u64,
+    last_hash: String,
+    /// Every entry when there is no file.
+    memory: VecDeque<AuditEntry>,
+}
+
+pub struct AuditTrail {
+    path: Option<PathBuf>,
+    key: Option<Vec<u8>>,
+    chain: Mutex<Chain>,
+}
+
+impl Default for AuditTrail {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+impl AuditTrail {
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            key: None,
+            chain: Mutex::new(Chain {
+                file: None,
+                last_seq: 0,
+                last_hash: GENESIS_HASH.to_string(),
+                memory: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Continue the trail at `path`, creating it if needed.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let entries = read_entries(&path)?;
+        let (last_seq, last_hash) = entries.last().map_or_else(
+            || (0, GENESIS_HASH.to_string()),
+            |entry| (entry.seq, entry.hash.clone()),
+        );
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path: Some(path),
+            key: None,
+            chain: Mutex::new(Chain {
+                file: Some(file),
+                last_seq,
+                last_hash,
+                memory: VecDeque::new(),
+            }),
+        })
+    }
+
+    /// The trail at `MMSS_AUDIT_TRAIL_PATH`, or one in memory.
+    pub fn from_env() -> Result<Self> {
+        match env::var("MMSS_AUDIT_TRAIL_PATH") {
+            Ok(path) => Self::open(path),
+            Err(_) => Ok(Self::in_memory()),
+        }
+    }
+
+    /// HMAC the chain with `key`. Entries written under another key, or
+    /// none, no longer verify.
+    pub fn with_key(mut self, key: &[u8]) -> Self {
+        self.key = Some(key.to_vec());
+        self
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Append an entry, returning it.
+    pub fn append(
+        &self,
+        actor: &Actor,
+        action: PrivilegedAction,
+        detail: Value,
+    ) -> Result<AuditEntry> {
+        let mut chain = self.lock()?;
+        let mut entry = AuditEntry {
+            seq: chain.last_seq + 1,
+            timestamp: Utc::now(),
+            actor: actor.clone(),
+            action,
+            detail,
+            prev_hash: chain.last_hash.clone(),
+            hash: String::new(),
+        };
+        entry.hash = entry.expected_hash(self.key.as_deref())?;
+        if let Some(file) = chain.file.as_mut() {
+            writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+            file.flush()?;
+        } else {
+            if chain.memory.len() >= DEFAULT_MEMORY_LIMIT {
+                chain.memory.pop_front();
+            }
+            chain.memory.push_back(entry.clone());
+        }
+        chain.last_seq = entry.seq;
+        chain.last_hash = entry.hash.clone();
+        Ok(entry)
+    }
+
+    /// `append`, logging a failure instead of returning it; the operation
+    /// has already happened by the time it is recorded.
+    pub fn record(&self, actor: &Actor, action: PrivilegedAction, detail: Value) {
+        if let Err(e) = self.append(actor, action, detail) {
+            error!(
+                "Failed to record {:?} by {} in the audit trail: {}",
+                action, actor.id, e
+            );
+        }
+    }
+
+    /// Every entry, oldest first.
+    pub fn entries(&self) -> Result<Vec<AuditEntry>> {
+        let chain = self.lock()?;
+        match &self.path {
+            Some(path) => read_entries(path),
+            None => Ok(chain.memory.iter().cloned().collect()),
+        }
+    }
+
+    /// Matching entries, newest first.
+    pub fn query(&self, query: &AuditTrailQuery) -> Result<Vec<AuditEntry>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .rev()
+            .filter(|entry| query.matches(entry))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    /// Check every hash and link, from the oldest entry kept.
+    pub fn verify(&self) -> Result<ChainReport> {
+        let entries = self.entries()?;
+        let mut expected_prev = entries
+            .first()
+            .filter(|_| self.path.is_none())
+            .map_or(GENESIS_HASH, |entry| entry.prev_hash.as_str());
+        let mut first_invalid_seq = None;
+        for entry in &entries {
+            if entry.prev_hash != expected_prev
+                || entry.hash != entry.expected_hash(self.key.as_deref())?
+            {
+                warn!(
+                    "Audit trail entry {} does not match its hash chain",
+                    entry.seq
+                );
+                first_invalid_seq = Some(entry.seq);
+                break;
+            }
+            expected_prev = entry.hash.as_str();
+        }
+        Ok(ChainReport {
+            entries: entries.len(),
+            valid: first_invalid_seq.is_none(),
+            first_invalid_seq,
+        })
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Chain>> {
+        self.chain.lock().map_err(|e| {
+            error!("Failed to lock the audit trail: {}", e);
+            Error::TaskExecution("Failed to access the audit trail".to_string())
+        })
+    }
+}
+
+fn read_entries(path: &Path) -> Result<Vec<AuditEntry>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(Error::from))
+        .collect()
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut out, byte| {
+        let _ = write!(out, "{:02x}", byte);
+        out
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn admin() -> Actor {
+        Actor {
+            id: "admin".to_string(),
+            request_id: Some("req-1".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_chain_detects_tampering() {
+        let path = env::temp_dir().join(format!("mmss-audit-{}.jsonl", Uuid::new_v4()));
+        let trail = AuditTrail::open(&path).unwrap();
+        trail
+            .append(
+                &admin(),
+                PrivilegedAction::RuleRegistered,
+                json!({ "name": "boost" }),
+            )
+            .unwrap();
+        let key = Actor {
+            id: Actor::for_api_key("secret-key-123"),
+            request_id: None,
+        };
+        assert!(!key.id.contains("secret"));
+        trail
+            .append(
+                &key,
+                PrivilegedAction::StateReset,
+                json!({ "metrics": true }),
+            )
+            .unwrap();
+        assert_eq!(
+            trail.verify().unwrap(),
+            ChainReport {
+                entries: 2,
+                valid: true,
+                first_invalid_seq: None
+            }
+        );
+
+        // continuing after a restart links to the last entry
+        let reopened = AuditTrail::open(&path).unwrap();
+        let third = reopened
+            .append(
+                &admin(),
+                PrivilegedAction::RuleDeleted,
+                json!({ "name": "boost" }),
+            )
+            .unwrap();
+        assert_eq!(third.seq, 3);
+        assert!(reopened.verify().unwrap().valid);
+        let resets = reopened
+            .query(&AuditTrailQuery {
+                action: Some(PrivilegedAction::StateReset),
+                ..AuditTrailQuery::default()
+            })
+            .unwrap();
+        assert_eq!(resets.len(), 1);
+        assert_eq!(resets[0].actor, key);
+
+        let text = fs::read_to_string(&path).unwrap();
+        fs::write(&path, text.replace("boost", "bust")).unwrap();
+        let report = reopened.verify().unwrap();
+        assert!(!report.valid);
+        assert_eq!(report.first_invalid_seq, Some(1));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_keyed_chain_detects_a_recomputed_rewrite() {
+        let path = env::temp_dir().join(format!("mmss-audit-{}.jsonl", Uuid::new_v4()));
+        let key = b"audit-key-0123456789abcdef";
+        let trail = AuditTrail::open(&path).unwrap().with_key(key);
+        trail
+            .append(
+                &admin(),
+                PrivilegedAction::RuleRegistered,
+                json!({ "name": "boost" }),
+            )
+            .unwrap();
+        assert!(trail.verify().unwrap().valid);
+
+        // rewrite the entry and recompute its hash the way an unkeyed
+        // chain would
+        let mut entry = trail.entries().unwrap().remove(0);
+        entry.actor.id = "operator:mallory".to_string();
+        entry.hash = entry.expected_hash(None).unwrap();
+        fs::write(
+            &path,
+            format!("{}\n", serde_json::to_string(&entry).unwrap()),
+        )
+        .unwrap();
+
+        let unkeyed = AuditTrail::open(&path).unwrap();
+        assert!(unkeyed.verify().unwrap().valid);
+        let keyed = AuditTrail::open(&path).unwrap().with_key(key);
+        assert_eq!(keyed.verify().unwrap().first_invalid_seq, Some(1));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+//! Who a request comes from, as far as it can be verified: the admin token,
+//! a named operator key, or an HS256 JWT signed with the configured secret
+//! and not yet expired. Anything else identifies nobody.
+
+use crate::config::ServerConfig;
+use crate::core::error::{Error, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use log::warn;
+use serde_json::Value;
+use sha2::Sha256;
+
+/// Shortest operator key or signing secret accepted.
+pub const MIN_SECRET_LEN: usize = 16;
+
+#[derive(Default)]
+pub struct Credentials {
+    admin_token: Option<String>,
+    /// Operator name and key.
+    operator_keys: Vec<(String, String)>,
+    jwt_secret: Option<Vec<u8>>,
+}
+
+impl Credentials {
+    /// From `server.admin_token`, `server.operator_keys` and
+    /// `server.jwt_secret`; entries `Config::validate` rejects are skipped.
+    pub fn from_config(config: &ServerConfig) -> Self {
+        let operator_keys = match config.operator_keys.as_deref().map(parse_operator_keys) {
+            Some(Ok(keys)) => keys,
+            Some(Err(e)) => {
+                warn!("Ignoring server.operator_keys: {}", e);
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+        Self {
+            admin_token: config.admin_token.clone().filter(|token| !token.is_empty()),
+            operator_keys,
+            jwt_secret: config
+                .jwt_secret
+                .as_ref()
+                .filter(|secret| secret.len() >= MIN_SECRET_LEN)
+                .map(|secret| secret.as_bytes().to_vec()),
+        }
+    }
+
+    pub fn new(
+        admin_token: Option<String>,
+        operator_keys: Vec<(String, String)>,
+        jwt_secret: Option<Vec<u8>>,
+    ) -> Self {
+        Self {
+            admin_token,
+            operator_keys,
+            jwt_secret,
+        }
+    }
+
+    pub fn has_admin_token(&self) -> bool {
+        self.admin_token.is_some()
+    }
+
+    pub fn is_admin(&self, token: &str) -> bool {
+        self.admin_token
+            .as_deref()
+            .is_some_and(|expected| same_secret(token.as_bytes(), expected.as_bytes()))
+    }
+
+    /// `admin`, `operator:<name>` or `jwt:<subject>` for a bearer token
+    /// that checks out.
+    pub fn bearer(&self, token: &str) -> Option<String> {
+        if self.is_admin(token) {
+            return Some("admin".to_string());
+        }
+        if let Some(secret) = &self.jwt_secret {
+            if let Some(subject) = verify_jwt(token, secret, Utc::now().timestamp()) {
+                return Some(format!("jwt:{}", subject));
+            }
+        }
+        self.operator(token)
+    }
+
+    /// `operator:<name>` for a configured operator key.
+    pub fn operator(&self, key: &str) -> Option<String> {
+        self.operator_keys
+            .iter()
+            .find(|(_, expected)| same_secret(key.as_bytes(), expected.as_bytes()))
+            .map(|(name, _)| format!("operator:{}", name))
+    }
+}
+
+/// `name:key` pairs, comma-separated. Names are letters, digits, `-` and
+/// `_`; keys are at least [`MIN_SECRET_LEN`] bytes and unique.
+pub fn parse_operator_keys(raw: &str) -> Result<Vec<(String, String)>> {
+    let mut keys: Vec<(String, String)> = Vec::new();
+    let entries = raw
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty());
+    for (position, entry) in entries.enumerate() {
+        // the entry itself holds the key, so it is not quoted
+        let invalid = |why: &str| Error::Config(format!("operator key {}: {}", position + 1, why));
+        let (name, key) = entry
+            .split_once(':')
+            .ok_or_else(|| invalid("not name:key"))?;
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(invalid("the name may only hold letters, digits, - and _"));
+        }
+        if key.len() < MIN_SECRET_LEN {
+            return Err(invalid("the key is too short"));
+        }
+        if keys
+            .iter()
+            .any(|(other, existing)| other == name || existing == key)
+        {
+            return Err(invalid("the name or key is used twice"));
+        }
+        keys.push((name.to_string(), key.to_string()));
+    }
+    Ok(keys)
+}
+
+/// The subject of an HS256 JWT signed with `secret`, with an `exp` after
+/// `now` and no `nbf` after it.
+fn verify_jwt(token: &str, secret: &[u8], now: i64) -> Option<String> {
+    let mut segments = token.split('.');
+    let (header, payload, signature) = (segments.next()?, segments.next()?, segments.next()?);
+    if segments.next().is_some() {
+        return None;
+    }
+    let decode = |segment: &str| -> Option<Value> {
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(segment).ok()?).ok()
+    };
+    if decode(header)?.get("alg")?.as_str()? != "HS256" {
+        return None;
+    }
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).ok()?;
+    mac.update(header.as_bytes());
+    mac.update(b".");
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&URL_SAFE_NO_PAD.decode(signature).ok()?)
+        .ok()?;
+
+    let claims = decode(payload)?;
+    if claims.get("exp")?.as_i64()? <= now {
+        return None;
+    }
+    if claims
+        .get("nbf")
+        .and_then(Value::as_i64)
+        .is_some_and(|nbf| nbf > now)
+    {
+        return None;
+    }
+    claims.get("sub")?.as_str().map(str::to_string)
+}
+
+/// Comparison whose time does not depend on where the inputs differ.
+pub fn same_secret(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len()
+        && given
+            .iter()
+            .zip(expected)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    fn sign(header: &Value, claims: &Value, secret: &[u8]) -> String {
+        let encode = |value: &Value| URL_SAFE_NO_PAD.encode(serde_json::to_vec(value).unwrap());
+        let signed = format!("{}.{}", encode(header), encode(claims));
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+        mac.update(signed.as_bytes());
+        format!(
+            "{}.{}",
+            signed,
+            URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+        )
+    }
+
+    #[test]
+    fn test_only_verified_credentials_identify_the_caller() {
+        let credentials = Credentials::new(
+            Some("admin-token".to_string()),
+            parse_operator_keys("alice:alice-key-0123456789").unwrap(),
+            Some(SECRET.to_vec()),
+        );
+        let hs256 = json!({ "alg": "HS256", "typ": "JWT" });
+        let now = Utc::now().timestamp();
+        let valid = sign(&hs256, &json!({ "sub": "bob", "exp": now + 60 }), SECRET);
+
+        assert_eq!(credentials.bearer("admin-token").as_deref(), Some("admin"));
+        assert_eq!(
+            credentials.bearer("alice-key-0123456789").as_deref(),
+            Some("operator:alice")
+        );
+        assert_eq!(credentials.operator("made-up-key-0123456789"), None);
+        assert_eq!(credentials.bearer(&valid).as_deref(), Some("jwt:bob"));
+
+        let forged = sign(
+            &hs256,
+            &json!({ "sub": "bob", "exp": now + 60 }),
+            b"another-secret-0123456789abcdef!",
+        );
+        let expired = sign(&hs256, &json!({ "sub": "bob", "exp": now - 1 }), SECRET);
+        let unsigned = sign(
+            &json!({ "alg": "none" }),
+            &json!({ "sub": "bob", "exp": now + 60 }),
+            SECRET,
+        );
+        let no_expiry = sign(&hs256, &json!({ "sub": "bob" }), SECRET);
+        for token in [forged, expired, unsigned, no_expiry] {
+            assert_eq!(credentials.bearer(&token), None, "{}", token);
+        }
+    }
+
+    #[test]
+    fn test_operator_keys_are_validated() {
+        assert_eq!(
+            parse_operator_keys(" alice:alice-key-0123456789 , bob:bob-key-0123456789abc").unwrap(),
+            vec![
+                ("alice".to_string(), "alice-key-0123456789".to_string()),
+                ("bob".to_string(), "bob-key-0123456789abc".to_string()),
+            ]
+        );
+        assert!(parse_operator_keys("alice").is_err());
+        assert!(parse_operator_keys("alice:short").is_err());
+        assert!(parse_operator_keys("a b:alice-key-0123456789").is_err());
+        assert!(parse_operator_keys("a:alice-key-0123456789,b:alice-key-0123456789").is_err());
+    }
+}
@@ -1,10 +1,25 @@
-use crate::core::types::{GeometricMetrics, GeometricOperator, Quaternion};
-use crate::state::{
-    compute_electron_mass, compute_fine_structure, compute_quaternion_coherence, compute_zitter_entropy,
-    C, HBAR, ZITTER_AMPLITUDE,
-};
+use crate::core::error::Error;
+use crate::core::plugins;
+use crate::core::types::{GeometricMetrics, GeometricOperator, MetricMap, Quaternion};
+use crate::state::PhysicalConstants;
+use log::warn;
+use mmss_compute::emergence::{self, baseline_metrics};
+use mmss_eqgft::asymmetry::{measure_polarization_asymmetry, Estimator, PolarizationAsymmetry};
+use mmss_eqgft::backend::{select_backend, Backend};
+use mmss_eqgft::bootstrap::{bootstrap_asymmetry, BootstrapConfig};
+use mmss_eqgft::cache::EqgftCache;
+use mmss_eqgft::channels::{blue_combine, simulate_channels, Channel};
+use mmss_eqgft::config::EqgftConfig;
+use mmss_eqgft::detector::{DetectorModel, EfficiencyCurve};
+use mmss_eqgft::hopfion::{HopfionConfig, HopfionSolitonField};
+use mmss_eqgft::reweight::scan_kappa_reweighted;
+use mmss_eqgft::scan::scan_kappa_with;
+use mmss_eqgft::stats;
+use mmss_eqgft::EqgftError;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::env;
+use std::path::Path;
+use std::sync::Arc;
 
 /// Simple placeholder for emergence logic parameters.
 #[derive(Debug, Clone)]
@@ -12,37 +27,181 @@ pub struct EmergenceConfig {
     pub step_size: f64,
 }
 
-fn normalize_axis(arr: &[Value]) -> Option<[f64; 3]> {
-    if arr.len() < 3 {
-        return None;
+/// Lattice parameters from task parameters, defaulting the missing ones.
+/// A resolution or thread count beyond the documented maximums is rejected
+/// rather than allocated.
+fn hopfion_config(params: &Value) -> mmss_eqgft::Result<HopfionConfig> {
+    let defaults = HopfionConfig::default();
+    let number = |name: &str| params.get(name).and_then(Value::as_f64);
+    let config = HopfionConfig {
+        resolution: number("resolution").map_or(defaults.resolution, |v| v as usize),
+        extent: number("extent").unwrap_or(defaults.extent),
+        hopf_index: number("hopf_index").map_or(defaults.hopf_index, |v| v.round() as i32),
+        scale: number("scale").unwrap_or(defaults.scale),
+        threads: number("threads").map(|v| v as usize),
+    };
+    config.validate()?;
+    Ok(config)
+}
+
+/// The `backend` task parameter, `auto` when missing.
+pub fn hopfion_backend(params: &Value) -> Result<Backend, Error> {
+    match params.get("backend") {
+        Some(raw) => serde_json::from_value(raw.clone()).map_err(|_| {
+            Error::InvalidParameter(
+                "backend".to_string(),
+                format!("{raw} is not auto, cpu or gpu"),
+            )
+        }),
+        None => Ok(Backend::default()),
+    }
+}
+
+/// Confidence interval, p-value against κ = 0 and the sample size needed for
+/// `target_significance` (default 5σ).
+fn record_asymmetry_statistics(
+    custom: &mut MetricMap,
+    asymmetry: &PolarizationAsymmetry,
+    config: &EqgftConfig,
+    params: &Value,
+) {
+    let confidence = params
+        .get("confidence")
+        .and_then(Value::as_f64)
+        .unwrap_or(0.95);
+    let target = params
+        .get("target_significance")
+        .and_then(Value::as_f64)
+        .unwrap_or(5.0);
+
+    match stats::asymmetry_interval(asymmetry, confidence) {
+        Ok(interval) => {
+            custom.insert("eqgft_ci_low".to_string(), interval.low);
+            custom.insert("eqgft_ci_high".to_string(), interval.high);
+        }
+        Err(err) => warn!("Skipping asymmetry interval: {}", err),
     }
+    custom.insert("eqgft_p_value".to_string(), stats::p_value_vs_null(asymmetry));
+    match stats::required_events(asymmetry.predicted, config.systematic_error, target) {
+        Some(events) => custom.insert("eqgft_required_events".to_string(), events as f64),
+        // unreachable under the systematic floor
+        None => custom.remove("eqgft_required_events"),
+    };
+}
 
-    let x = arr.get(0).and_then(Value::as_f64)?;
-    let y = arr.get(1).and_then(Value::as_f64)?;
-    let z = arr.get(2).and_then(Value::as_f64)?;
-    Some([x, y, z])
+/// Bootstrap settings when the task asks for `bootstrap_resamples`.
+fn bootstrap_config(params: &Value, config: &EqgftConfig) -> Option<BootstrapConfig> {
+    let resamples = params.get("bootstrap_resamples").and_then(Value::as_u64)?;
+    Some(BootstrapConfig {
+        resamples: resamples as usize,
+        seed: params.get("bootstrap_seed").and_then(Value::as_u64),
+        confidence: params
+            .get("confidence")
+            .and_then(Value::as_f64)
+            .unwrap_or(0.95),
+        systematic_error: config.systematic_error,
+        threads: config.threads,
+        estimator: Estimator::for_config(config),
+    })
 }
 
-impl EmergenceLogic {
-    fn baseline_metrics() -> GeometricMetrics {
-        let coherence = compute_quaternion_coherence();
-        let entropy = compute_zitter_entropy();
-        let electron_mass = compute_electron_mass();
-        let fine_structure = compute_fine_structure();
-        let default_winding = 8.9997;
-
-        GeometricMetrics {
-            v_geometric: coherence,
-            s_geometric: entropy,
-            q_oscillator: default_winding,
-            quaternion_coherence: coherence,
-            emergent_electron_mass: electron_mass,
-            fine_structure_constant: fine_structure,
-            zitterbewegung_entropy: entropy,
-            topological_winding: default_winding,
-            custom_metrics: HashMap::new(),
+/// `params` with the seeds an EQGFT operator would otherwise draw at random
+/// filled in, so that applying `op` to them again gives the same metrics,
+/// and the simulation seed, given or drawn. Other operators, and
+/// parameters the operator would reject, come back unchanged.
+pub fn resolve_seeds(op: GeometricOperator, params: &Value) -> (Value, Option<u64>) {
+    let mut params = match params {
+        Value::Null => Value::Object(Default::default()),
+        params => params.clone(),
+    };
+    let seeded = matches!(
+        op,
+        GeometricOperator::SimulateEqgftAsymmetry
+            | GeometricOperator::SimulateEqgftKappaScan
+            | GeometricOperator::CombineEqgftChannels
+    );
+    if !seeded {
+        return (params, None);
+    }
+    let Ok(config) = eqgft_config(&params) else {
+        return (params, None);
+    };
+    let Value::Object(fields) = &mut params else {
+        return (params, None);
+    };
+    let seed = config.seed.unwrap_or_else(rand::random);
+    fields.insert("seed".to_string(), seed.into());
+    if op == GeometricOperator::SimulateEqgftAsymmetry
+        && fields.contains_key("bootstrap_resamples")
+        && !fields.contains_key("bootstrap_seed")
+    {
+        fields.insert("bootstrap_seed".to_string(), rand::random::<u64>().into());
+    }
+    (params, Some(seed))
+}
+
+/// Measurement parameters from task parameters. A `config` parameter names a
+/// file in `MMSS_EQGFT_CONFIG_DIR` that supplies the values not given
+/// explicitly; otherwise they take their defaults.
+pub(crate) fn eqgft_config(params: &Value) -> mmss_eqgft::Result<EqgftConfig> {
+    let base = match params.get("config").and_then(Value::as_str) {
+        Some(name) => {
+            let directory = env::var("MMSS_EQGFT_CONFIG_DIR").map_err(|_| {
+                EqgftError::InvalidConfig("MMSS_EQGFT_CONFIG_DIR is not set".into())
+            })?;
+            EqgftConfig::from_named(Path::new(&directory), name)?
         }
+        None => EqgftConfig::default(),
+    };
+    let config = EqgftConfig {
+        kappa: params.get("kappa").and_then(Value::as_f64).unwrap_or(base.kappa),
+        n_events: params
+            .get("n_events")
+            .and_then(Value::as_f64)
+            .map_or(base.n_events, |v| v as usize),
+        systematic_error: params
+            .get("systematic_error")
+            .and_then(Value::as_f64)
+            .unwrap_or(base.systematic_error),
+        seed: params.get("seed").and_then(Value::as_u64).or(base.seed),
+        threads: params
+            .get("threads")
+            .and_then(Value::as_u64)
+            .map(|v| v as usize)
+            .or(base.threads),
+        detector: detector_model(params, base.detector),
+    };
+    config.validate()?;
+    Ok(config)
+}
+
+const DETECTOR_PARAMETERS: [&str; 6] = [
+    "angular_resolution",
+    "efficiency",
+    "efficiency_slope",
+    "efficiency_curvature",
+    "acceptance_min",
+    "acceptance_max",
+];
+
+/// Detector response when any detector parameter is given, on top of `base`;
+/// `base` unchanged otherwise.
+fn detector_model(params: &Value, base: Option<DetectorModel>) -> Option<DetectorModel> {
+    if !DETECTOR_PARAMETERS.iter().any(|name| params.get(name).is_some()) {
+        return base;
     }
+    let defaults = base.unwrap_or_default();
+    let number = |name: &str| params.get(name).and_then(Value::as_f64);
+    Some(DetectorModel {
+        angular_resolution: number("angular_resolution").unwrap_or(defaults.angular_resolution),
+        efficiency: EfficiencyCurve {
+            base: number("efficiency").unwrap_or(defaults.efficiency.base),
+            linear: number("efficiency_slope").unwrap_or(defaults.efficiency.linear),
+            quadratic: number("efficiency_curvature").unwrap_or(defaults.efficiency.quadratic),
+        },
+        acceptance_min: number("acceptance_min").unwrap_or(defaults.acceptance_min),
+        acceptance_max: number("acceptance_max").unwrap_or(defaults.acceptance_max),
+    })
 }
 
 impl Default for EmergenceConfig {
@@ -55,92 +214,206 @@ impl Default for EmergenceConfig {
 #[derive(Debug, Clone)]
 pub struct EmergenceLogic {
     config: EmergenceConfig,
+    constants: PhysicalConstants,
     metrics: GeometricMetrics,
+    /// Field built by the last `GenerateHopfionField`.
+    hopfion: Option<Arc<HopfionSolitonField>>,
+    /// Structured result of the last operator, for operators that have one.
+    output: Option<Value>,
+    /// Shared with clones, so isolated evaluations reuse earlier results.
+    eqgft_cache: Arc<EqgftCache>,
 }
 
 impl EmergenceLogic {
     pub fn new(config: Option<EmergenceConfig>) -> Self {
         Self {
             config: config.unwrap_or_default(),
-            metrics: Self::baseline_metrics(),
+            constants: PhysicalConstants::SI,
+            metrics: baseline_metrics(&PhysicalConstants::SI),
+            hopfion: None,
+            output: None,
+            eqgft_cache: Arc::new(EqgftCache::default()),
         }
     }
 
     pub fn apply_operator(&mut self, op: GeometricOperator, params: &Value) -> &GeometricMetrics {
-        let magnitude = extract_scalar(params).unwrap_or(1.0);
+        self.output = None;
 
         match op {
-            GeometricOperator::QuaternionRotation => {
-                let theta = params
-                    .get("theta")
-                    .and_then(Value::as_f64)
-                    .unwrap_or(magnitude);
-                let axis = params
-                    .get("axis")
-                    .and_then(Value::as_array)
-                    .and_then(|arr| normalize_axis(arr))
-                    .unwrap_or([0.0, 1.0, 0.0]);
-
-                let axis_norm = (axis[0].powi(2) + axis[1].powi(2) + axis[2].powi(2)).sqrt();
-                let coherence_boost = (theta * 0.5).sin().abs() * 0.005 * axis_norm.max(1e-6);
-
-                self.metrics.quaternion_coherence = (self.metrics.quaternion_coherence + coherence_boost)
-                    .clamp(0.0, 0.9999);
-                self.metrics.v_geometric = self.metrics.quaternion_coherence;
+            // Shared with the dashboard's previews; see `mmss_compute::emergence`.
+            GeometricOperator::QuaternionRotation
+            | GeometricOperator::Zitterbewegung
+            | GeometricOperator::GeometricDerivation
+            | GeometricOperator::SemanticSynthesis => {
+                emergence::apply_pure_operator(&mut self.metrics, &self.constants, op, params);
             }
-            GeometricOperator::Zitterbewegung => {
-                let freq_scale = params
-                    .get("frequency_scale")
-                    .and_then(Value::as_f64)
-                    .unwrap_or(magnitude.abs());
-                let scaled_amplitude = (ZITTER_AMPLITUDE / freq_scale.max(1e-6)).abs();
-
-                self.metrics.emergent_electron_mass = HBAR / (2.0 * C * scaled_amplitude);
-                self.metrics.topological_winding =
-                    (self.metrics.topological_winding + (freq_scale - 1.0) * 0.0001).max(0.0);
-                self.metrics.q_oscillator = self.metrics.topological_winding.max(0.0);
+            // Scripts run outside the cascade; see `SemanticTaskProcessor::execute_task`.
+            GeometricOperator::CustomPythonScript => {}
+            GeometricOperator::GenerateHopfionField => {
+                let generated =
+                    hopfion_backend(params).map(|preference| -> mmss_eqgft::Result<_> {
+                        let backend = select_backend(preference)?;
+                        let config = hopfion_config(params)?;
+                        let field = self
+                            .eqgft_cache
+                            .hopfion_field(&config, |config| backend.generate(config))?;
+                        Ok((backend, field))
+                    });
+                match generated {
+                    Ok(Ok((backend, field))) => {
+                        self.metrics.topological_winding = backend.hopf_charge(&field);
+                        self.metrics
+                            .custom_metrics
+                            .insert("hopfion_energy".to_string(), field.total_energy);
+                        self.hopfion = Some(field);
+                    }
+                    Ok(Err(err)) => warn!("Skipping Hopfion field generation: {}", err),
+                    Err(err) => warn!("Skipping Hopfion field generation: {}", err),
+                }
             }
-            GeometricOperator::GeometricDerivation => {
-                let delta = params
-                    .get("delta")
-                    .and_then(Value::as_f64)
-                    .unwrap_or(magnitude);
-                self.metrics.s_geometric = (self.metrics.s_geometric + delta * 0.001).clamp(0.0001, 1.0);
-                self.metrics.zitterbewegung_entropy = self.metrics.s_geometric;
+            GeometricOperator::SimulateEqgftAsymmetry => {
+                let measured = eqgft_config(params).and_then(|config| {
+                    let events = self.eqgft_cache.events(&config)?;
+                    let asymmetry = measure_polarization_asymmetry(&config, &events)?;
+                    let bootstrap = bootstrap_config(params, &config)
+                        .map(|bootstrap| bootstrap_asymmetry(&events, &bootstrap))
+                        .transpose()?;
+                    Ok((config, asymmetry, bootstrap, events.len()))
+                });
+                match measured {
+                    Ok((config, asymmetry, bootstrap, detected)) => {
+                        let custom = &mut self.metrics.custom_metrics;
+                        custom.insert("eqgft_detected_events".to_string(), detected as f64);
+                        custom.insert("eqgft_asymmetry".to_string(), asymmetry.a);
+                        custom.insert(
+                            "eqgft_asymmetry_uncertainty".to_string(),
+                            asymmetry.uncertainty,
+                        );
+                        custom.insert("eqgft_asymmetry_stat_error".to_string(), asymmetry.stat_error);
+                        custom.insert("eqgft_asymmetry_syst_error".to_string(), asymmetry.syst_error);
+                        custom.insert("eqgft_predicted_asymmetry".to_string(), asymmetry.predicted);
+                        custom.insert("eqgft_pull".to_string(), asymmetry.pull());
+                        custom.insert("eqgft_significance".to_string(), asymmetry.significance());
+                        record_asymmetry_statistics(custom, &asymmetry, &config, params);
+                        if let Some(summary) = bootstrap {
+                            custom.insert(
+                                "eqgft_bootstrap_std_error".to_string(),
+                                summary.asymmetry.std_dev,
+                            );
+                            custom.insert(
+                                "eqgft_bootstrap_ci_low".to_string(),
+                                summary.asymmetry.interval.low,
+                            );
+                            custom.insert(
+                                "eqgft_bootstrap_ci_high".to_string(),
+                                summary.asymmetry.interval.high,
+                            );
+                            custom.insert(
+                                "eqgft_bootstrap_significance".to_string(),
+                                summary.significance.median,
+                            );
+                            self.output = serde_json::to_value(summary).ok();
+                        }
+                    }
+                    Err(err) => warn!("Skipping asymmetry simulation: {}", err),
+                }
+            }
+            GeometricOperator::SimulateEqgftKappaScan => {
+                let number = |name: &str| params.get(name).and_then(Value::as_f64);
+                let range = number("kappa_min").unwrap_or(0.0)..=number("kappa_max").unwrap_or(0.5);
+                let steps = number("steps").map_or(11, |v| v as usize);
+                // one sample reweighted to every step instead of one sample per step
+                let reweight = params.get("reweight").and_then(Value::as_bool).unwrap_or(false);
+                let scan = eqgft_config(params).and_then(|config| {
+                    if reweight {
+                        scan_kappa_reweighted(&config, range, steps)
+                    } else {
+                        scan_kappa_with(&config, range, steps)
+                    }
+                });
+                match scan {
+                    Ok(scan) => {
+                        let max_significance = scan
+                            .iter()
+                            .map(|point| point.significance())
+                            .fold(0.0, f64::max);
+                        let custom = &mut self.metrics.custom_metrics;
+                        custom.insert("eqgft_scan_points".to_string(), scan.len() as f64);
+                        custom.insert("eqgft_scan_max_significance".to_string(), max_significance);
+                        self.output = serde_json::to_value(&scan).ok();
+                    }
+                    Err(err) => warn!("Skipping kappa scan: {}", err),
+                }
             }
-            GeometricOperator::SemanticSynthesis => {
-                let coherence_hint = params
-                    .get("coherence_hint")
+            GeometricOperator::CombineEqgftChannels => {
+                let correlation = params
+                    .get("systematic_correlation")
                     .and_then(Value::as_f64)
-                    .unwrap_or(0.95);
-                let anchor_name = params
-                    .get("anchor")
+                    .unwrap_or(0.0);
+                let combined = eqgft_config(params).and_then(|config| {
+                    let channels: Vec<Channel> = params
+                        .get("channels")
+                        .cloned()
+                        .map(serde_json::from_value)
+                        .transpose()
+                        .map_err(|err| EqgftError::InvalidConfig(format!("channels: {err}")))?
+                        .unwrap_or_default();
+                    let measurements = simulate_channels(&config, &channels)?;
+                    let combination = blue_combine(&measurements, correlation)?;
+                    Ok((channels, measurements, combination))
+                });
+                match combined {
+                    Ok((channels, measurements, combination)) => {
+                        let combined = &combination.asymmetry;
+                        let custom = &mut self.metrics.custom_metrics;
+                        custom.insert("eqgft_channels".to_string(), channels.len() as f64);
+                        custom.insert("eqgft_combined_asymmetry".to_string(), combined.a);
+                        custom.insert("eqgft_combined_uncertainty".to_string(), combined.uncertainty);
+                        custom.insert("eqgft_combined_significance".to_string(), combined.significance());
+                        custom.insert("eqgft_combination_chi2".to_string(), combination.chi2);
+                        let per_channel: Vec<Value> = channels
+                            .iter()
+                            .zip(&measurements)
+                            .zip(&combination.weights)
+                            .map(|((channel, measurement), weight)| {
+                                serde_json::json!({
+                                    "name": channel.name,
+                                    "measurement": measurement,
+                                    "significance": measurement.significance(),
+                                    "weight": weight,
+                                })
+                            })
+                            .collect();
+                        self.output = Some(serde_json::json!({
+                            "channels": per_channel,
+                            "combined": combined,
+                            "significance": combined.significance(),
+                            "chi2": combination.chi2,
+                            "ndf": combination.ndf,
+                        }));
+                    }
+                    Err(err) => warn!("Skipping channel combination: {}", err),
+                }
+            }
+            GeometricOperator::PluginOperator => {
+                let applied = params
+                    .get("operator")
                     .and_then(Value::as_str)
-                    .unwrap_or("quantum-atom");
-
-                let semantic_strength =
-                    (self.metrics.quaternion_coherence * coherence_hint * 10.0).max(0.0);
-                self.metrics
-                    .custom_metrics
-                    .insert(format!("anchor:{}", anchor_name), semantic_strength);
+                    .ok_or_else(|| Error::TaskExecution("no `operator` parameter".to_string()))
+                    .and_then(|operator| {
+                        plugins::registry().apply_operator(operator, params, &self.metrics)
+                    });
+                match applied {
+                    Ok((metrics, output)) => {
+                        self.metrics = metrics;
+                        self.output = output;
+                    }
+                    Err(err) => warn!("Skipping plugin operator: {}", err),
+                }
             }
         }
 
-        self.metrics.fine_structure_constant =
-            (compute_fine_structure() / self.metrics.quaternion_coherence.max(1e-6)).min(1.0);
-        if self.metrics.zitterbewegung_entropy <= 0.0 {
-            self.metrics.zitterbewegung_entropy = compute_zitter_entropy();
-        }
-        if self.metrics.emergent_electron_mass <= 0.0 {
-            self.metrics.emergent_electron_mass = compute_electron_mass();
-        }
-        if self.metrics.quaternion_coherence <= 0.0 {
-            self.metrics.quaternion_coherence = compute_quaternion_coherence();
-        }
-        if self.metrics.topological_winding <= 0.0 {
-            self.metrics.topological_winding = self.metrics.q_oscillator;
-        }
-
+        emergence::settle(&mut self.metrics, &self.constants);
         &self.metrics
     }
 
@@ -152,23 +425,64 @@ impl EmergenceLogic {
         &self.metrics
     }
 
-    pub fn metrics(&self) -> &GeometricMetrics {
-        &self.metrics
+    /// Structured result of the last `apply_operator` call, if any.
+    pub fn take_output(&mut self) -> Option<Value> {
+        self.output.take()
     }
-}
 
-fn extract_scalar(params: &Value) -> Option<f64> {
-    if let Some(val) = params.as_f64() {
-        return Some(val);
+    /// Compute from `constants` instead of SI, starting over from their
+    /// baseline metrics.
+    pub fn with_constants(mut self, constants: PhysicalConstants) -> Self {
+        self.constants = constants;
+        self.metrics = baseline_metrics(&constants);
+        self
     }
 
-    if let Some(obj) = params.as_object() {
-        for key in ["magnitude", "value", "amount", "scale"] {
-            if let Some(v) = obj.get(key).and_then(Value::as_f64) {
-                return Some(v);
-            }
+    pub fn with_eqgft_cache(mut self, cache: Arc<EqgftCache>) -> Self {
+        self.eqgft_cache = cache;
+        self
+    }
+
+    /// Continue from `metrics` saved by an earlier run, regenerating the
+    /// Hopfion field from `hopfion` on the default backend.
+    pub fn restore(&mut self, metrics: GeometricMetrics, hopfion: Option<HopfionConfig>) {
+        self.metrics = metrics;
+        self.hopfion = hopfion.and_then(|config| {
+            select_backend(Backend::default())
+                .and_then(|backend| {
+                    self.eqgft_cache
+                        .hopfion_field(&config, |config| backend.generate(config))
+                })
+                .map_err(|err| warn!("Not restoring the Hopfion field: {}", err))
+                .ok()
+        });
+    }
+
+    /// Continue from `metrics` computed elsewhere, keeping the field.
+    pub fn adopt_metrics(&mut self, metrics: GeometricMetrics) {
+        self.metrics = metrics;
+    }
+
+    /// Return the metrics to the baseline of the constants and drop the
+    /// Hopfion field, as selected.
+    pub fn reset(&mut self, metrics: bool, field: bool) {
+        if metrics {
+            self.metrics = baseline_metrics(&self.constants);
         }
+        if field {
+            self.hopfion = None;
+        }
+    }
+
+    pub fn hopfion_field(&self) -> Option<&Arc<HopfionSolitonField>> {
+        self.hopfion.as_ref()
     }
 
-    None
+    pub fn metrics(&self) -> &GeometricMetrics {
+        &self.metrics
+    }
+
+    pub fn config(&self) -> &EmergenceConfig {
+        &self.config
+    }
 }
@@ -1,7 +1,12 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
 use thiserror::Error;
 use uuid::Uuid;
 
-/// Main error type for the MMSS system
+/// Main error type for the MMSS system, returned by the library and, as
+/// `{"code": ..., "message": ...}` with a matching status, by the HTTP API.
 #[derive(Error, Debug)]
 pub enum Error {
     /// Error during task execution
@@ -20,6 +25,62 @@ pub enum Error {
     #[error("LLM communication error: {0}")]
     LlmCommunication(String),
 
+    /// LLM output that still fails schema validation after repair attempts
+    #[error("LLM validation error: {0}")]
+    LlmValidation(String),
+
+    /// Token budget for the caller or campaign is exhausted
+    #[error("Token budget exhausted: {0}")]
+    BudgetExceeded(String),
+
+    /// Provider calls are suspended by the circuit breaker
+    #[error("LLM provider unavailable (circuit open): {0}")]
+    CircuitOpen(String),
+
+    /// Command refused by the script policy or not yet approved
+    #[error("Policy violation: {0}")]
+    PolicyViolation(String),
+
+    /// Prompt template could not be compiled or rendered
+    #[error("Prompt template error: {0}")]
+    Template(String),
+
+    /// Server configuration that failed to load or validate
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    /// The Redis backend shared between replicas failed
+    #[error("Shared state error: {0}")]
+    SharedState(String),
+
+    /// The Kafka or NATS broker events are published to failed
+    #[error("Event publishing error: {0}")]
+    EventPublish(String),
+
+    /// Malformed request outside any one parameter
+    #[error("{0}")]
+    BadRequest(String),
+
+    /// Missing credentials or wrong ones
+    #[error("{0}")]
+    Unauthorized(String),
+
+    /// Credentials that do not allow the operation
+    #[error("{0}")]
+    Forbidden(String),
+
+    /// Resource that does not exist
+    #[error("{0}")]
+    NotFound(String),
+
+    /// Resource not in a state that allows the operation
+    #[error("{0}")]
+    Conflict(String),
+
+    /// Failure on the server side not covered by another variant
+    #[error("{0}")]
+    Internal(String),
+
     /// Serialization/deserialization error
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
@@ -33,5 +94,89 @@ pub enum Error {
     Other(#[from] anyhow::Error),
 }
 
+impl Error {
+    /// Stable, machine-readable name of the variant; messages may change
+    /// between releases, codes do not.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::TaskExecution(_) => "task_execution",
+            Error::TaskNotFound(_) => "task_not_found",
+            Error::InvalidParameter(..) => "invalid_parameter",
+            Error::LlmCommunication(_) => "llm_communication",
+            Error::LlmValidation(_) => "llm_validation",
+            Error::BudgetExceeded(_) => "budget_exceeded",
+            Error::CircuitOpen(_) => "circuit_open",
+            Error::PolicyViolation(_) => "policy_violation",
+            Error::Template(_) => "template",
+            Error::Config(_) => "config",
+            Error::SharedState(_) => "shared_state",
+            Error::EventPublish(_) => "event_publish",
+            Error::BadRequest(_) => "bad_request",
+            Error::Unauthorized(_) => "unauthorized",
+            Error::Forbidden(_) => "forbidden",
+            Error::NotFound(_) => "not_found",
+            Error::Conflict(_) => "conflict",
+            Error::Internal(_) => "internal",
+            Error::Serialization(_) => "serialization",
+            Error::Io(_) => "io",
+            Error::Other(_) => "other",
+        }
+    }
+
+    /// HTTP status the API answers this error with.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::TaskExecution(_)
+            | Error::InvalidParameter(..)
+            | Error::Template(_)
+            | Error::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Error::LlmValidation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Error::BudgetExceeded(_) => StatusCode::PAYMENT_REQUIRED,
+            Error::PolicyViolation(_) | Error::Forbidden(_) => StatusCode::FORBIDDEN,
+            Error::TaskNotFound(_) | Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::Conflict(_) => StatusCode::CONFLICT,
+            Error::LlmCommunication(_) => StatusCode::BAD_GATEWAY,
+            Error::CircuitOpen(_) | Error::SharedState(_) | Error::EventPublish(_) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            Error::Config(_)
+            | Error::Internal(_)
+            | Error::Serialization(_)
+            | Error::Io(_)
+            | Error::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let body = json!({ "code": self.code(), "message": self.to_string() });
+        (self.status(), Json(body)).into_response()
+    }
+}
+
 /// Result type for the MMSS system
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_response_carries_code_and_status() {
+        let response = Error::PolicyViolation("rm -rf".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "policy_violation");
+        assert_eq!(body["message"], "Policy violation: rm -rf");
+
+        assert_eq!(
+            Error::NotFound("Rule not found".to_string()).to_string(),
+            "Rule not found"
+        );
+    }
+}
@@ -0,0 +1,456 @@
+//! Task transitions, metrics snapshots and alerts of the default namespace
+//! published to Kafka or NATS JetStream, so other systems can follow the
+//! server without polling the API. Kafka needs the `kafka` feature, NATS
+//! the `nats` feature.
+//!
+//! Delivery is at least once: a message is retried, with backoff, until the
+//! broker acknowledges it, and carries an `event_id` for consumers to drop
+//! duplicates by. While the broker is unreachable events queue up in the
+//! event bus; once more than its capacity (`MMSS_EVENT_CAPACITY`) are
+//! waiting the oldest are lost, and a warning says how many.
+
+use crate::config::EventsConfig;
+use crate::core::error::{Error, Result};
+use crate::core::events::{EventBus, StateEvent};
+use crate::core::semantic_task_processor::TaskStatus;
+use axum::async_trait;
+use chrono::Utc;
+use log::{info, warn};
+use serde_json::{json, Value};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+const DEFAULT_TASK_TOPIC: &str = "mmss.tasks";
+const DEFAULT_METRICS_TOPIC: &str = "mmss.metrics";
+const DEFAULT_ALERT_TOPIC: &str = "mmss.alerts";
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Broker {
+    /// Comma-separated `host:port` bootstrap servers.
+    Kafka { brokers: String },
+    /// A server whose JetStream streams cover the subjects.
+    Nats { url: String },
+}
+
+impl fmt::Display for Broker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Broker::Kafka { brokers } => write!(f, "Kafka at {}", brokers),
+            Broker::Nats { url } => write!(f, "NATS at {}", url),
+        }
+    }
+}
+
+/// Kafka topics or NATS subjects per kind of message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventTopics {
+    pub tasks: String,
+    pub metrics: String,
+    pub alerts: String,
+}
+
+impl Default for EventTopics {
+    fn default() -> Self {
+        Self {
+            tasks: DEFAULT_TASK_TOPIC.to_string(),
+            metrics: DEFAULT_METRICS_TOPIC.to_string(),
+            alerts: DEFAULT_ALERT_TOPIC.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventPublishConfig {
+    pub broker: Broker,
+    pub topics: EventTopics,
+    /// Wait before the first retry, doubled after every failure up to
+    /// `max_backoff`.
+    pub retry_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl EventPublishConfig {
+    /// Enabled by `events.kafka_brokers` or `events.nats_url`; the topics
+    /// default to `mmss.tasks`, `mmss.metrics` and `mmss.alerts`, and
+    /// `events.max_backoff_secs`, the wait between retries, to 30.
+    pub fn from_config(config: &EventsConfig) -> Option<Self> {
+        let broker = match (&config.kafka_brokers, &config.nats_url) {
+            (Some(brokers), _) => Broker::Kafka {
+                brokers: brokers.clone(),
+            },
+            (_, Some(url)) => Broker::Nats { url: url.clone() },
+            _ => return None,
+        };
+        let topic = |name: &Option<String>, default: &str| {
+            name.clone().unwrap_or_else(|| default.to_string())
+        };
+        Some(Self {
+            broker,
+            topics: EventTopics {
+                tasks: topic(&config.task_topic, DEFAULT_TASK_TOPIC),
+                metrics: topic(&config.metrics_topic, DEFAULT_METRICS_TOPIC),
+                alerts: topic(&config.alert_topic, DEFAULT_ALERT_TOPIC),
+            },
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            max_backoff: config
+                .max_backoff_secs
+                .filter(|&secs| secs > 0)
+                .map_or(DEFAULT_MAX_BACKOFF, Duration::from_secs),
+        })
+    }
+}
+
+/// One message for the broker. `key` keeps a task's messages in order on
+/// one Kafka partition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventMessage {
+    pub topic: String,
+    pub key: String,
+    pub payload: Value,
+}
+
+#[async_trait]
+pub trait Publisher: Send + Sync {
+    /// Resolves once the broker has acknowledged `message`.
+    async fn publish(&self, message: &EventMessage) -> Result<()>;
+}
+
+/// The messages `event` turns into: a task transition goes to the task
+/// topic, and to the alert topic too when the task failed or waits for
+/// approval; a metrics update to the metrics topic; a reset to the alert
+/// topic. Other events are not published.
+pub fn messages(event: &StateEvent, topics: &EventTopics) -> Vec<EventMessage> {
+    let now = Utc::now().timestamp_millis();
+    let message = |topic: &str, key: String, body: Value| {
+        let mut payload = json!({ "event_id": Uuid::new_v4(), "timestamp": now });
+        if let (Value::Object(payload), Value::Object(body)) = (&mut payload, body) {
+            payload.extend(body);
+        }
+        EventMessage {
+            topic: topic.to_string(),
+            key,
+            payload,
+        }
+    };
+
+    match event {
+        StateEvent::TaskTransition { task_id, status } => {
+            let mut messages = vec![message(
+                &topics.tasks,
+                task_id.to_string(),
+                json!({ "event": "task_transition", "task_id": task_id, "status": status }),
+            )];
+            messages.extend(alert(event).map(|(key, body)| message(&topics.alerts, key, body)));
+            messages
+        }
+        StateEvent::MetricsUpdated(snapshot) => vec![message(
+            &topics.metrics,
+            "metrics".to_string(),
+            json!({
+                "event": "metrics_snapshot",
+                "snapshot_timestamp": snapshot.timestamp,
+                "task_id": snapshot.task_id,
+                "operator": snapshot.operator,
+                "metrics": snapshot.metrics,
+            }),
+        )],
+        StateEvent::Reset(_) => alert(event)
+            .map(|(key, body)| message(&topics.alerts, key, body))
+            .into_iter()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The alert `event` raises, with its message key: a task failed or
+/// waiting for approval, or a reset.
+pub fn alert(event: &StateEvent) -> Option<(String, Value)> {
+    match event {
+        StateEvent::TaskTransition { task_id, status } => {
+            let (alert, detail) = match status {
+                TaskStatus::Failed(reason) => ("task_failed", reason.as_str()),
+                TaskStatus::AwaitingApproval => {
+                    ("task_awaiting_approval", "script task held for approval")
+                }
+                _ => return None,
+            };
+            Some((
+                task_id.to_string(),
+                json!({ "event": "alert", "alert": alert, "task_id": task_id, "detail": detail }),
+            ))
+        }
+        StateEvent::Reset(scope) => Some((
+            "reset".to_string(),
+            json!({ "event": "alert", "alert": "state_reset", "scope": scope }),
+        )),
+        _ => None,
+    }
+}
+
+/// The publisher for `config.broker`, failing when the server was built
+/// without its feature.
+pub async fn connect(config: &EventPublishConfig) -> Result<Arc<dyn Publisher>> {
+    match &config.broker {
+        #[cfg(feature = "kafka")]
+        Broker::Kafka { brokers } => Ok(Arc::new(kafka::KafkaPublisher::connect(brokers)?)),
+        #[cfg(feature = "nats")]
+        Broker::Nats { url } => Ok(Arc::new(nats::NatsPublisher::connect(url).await?)),
+        #[allow(unreachable_patterns)]
+        broker => Err(Error::Config(format!(
+            "Publishing to {} needs the server built with the {} feature",
+            broker,
+            match broker {
+                Broker::Kafka { .. } => "kafka",
+                Broker::Nats { .. } => "nats",
+            }
+        ))),
+    }
+}
+
+/// Publish the events of `events` from now on, one at a time and in order.
+pub fn spawn_event_publisher(
+    events: &EventBus,
+    publisher: Arc<dyn Publisher>,
+    config: EventPublishConfig,
+) -> JoinHandle<()> {
+    let mut receiver = events.subscribe();
+    tokio::spawn(async move {
+        info!("Publishing events to {}", config.broker);
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(missed)) => {
+                    warn!(
+                        "Event publisher fell behind; {} events were not published",
+                        missed
+                    );
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            };
+            for message in messages(&event, &config.topics) {
+                deliver(publisher.as_ref(), &message, &config).await;
+            }
+        }
+    })
+}
+
+/// Retry `message` until the broker acknowledges it.
+async fn deliver(publisher: &dyn Publisher, message: &EventMessage, config: &EventPublishConfig) {
+    let mut backoff = config.retry_backoff;
+    loop {
+        match publisher.publish(message).await {
+            Ok(()) => return,
+            Err(e) => {
+                warn!(
+                    "Failed to publish to {}, retrying in {:?}: {}",
+                    message.topic, backoff, e
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(config.max_backoff);
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "kafka", feature = "nats"))]
+fn publish_error(broker: &str, e: impl fmt::Display) -> Error {
+    Error::EventPublish(format!("{}: {}", broker, e))
+}
+
+#[cfg(feature = "kafka")]
+mod kafka {
+    use super::{publish_error, EventMessage, Publisher};
+    use crate::core::error::Result;
+    use axum::async_trait;
+    use rdkafka::config::ClientConfig;
+    use rdkafka::producer::{FutureProducer, FutureRecord};
+    use std::time::Duration;
+
+    /// How long a message may wait in the producer queue for a broker.
+    const QUEUE_TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub struct KafkaPublisher {
+        producer: FutureProducer,
+    }
+
+    impl KafkaPublisher {
+        /// An idempotent producer waiting for all in-sync replicas, so a
+        /// retried send is neither lost nor written twice by the producer
+        /// itself.
+        pub fn connect(brokers: &str) -> Result<Self> {
+            let producer = ClientConfig::new()
+                .set("bootstrap.servers", brokers)
+                .set("acks", "all")
+                .set("enable.idempotence", "true")
+                .create()
+                .map_err(|e| publish_error("Kafka", e))?;
+            Ok(Self { producer })
+        }
+    }
+
+    #[async_trait]
+    impl Publisher for KafkaPublisher {
+        async fn publish(&self, message: &EventMessage) -> Result<()> {
+            let payload = message.payload.to_string();
+            let record = FutureRecord::to(&message.topic)
+                .key(&message.key)
+                .payload(&payload);
+            self.producer
+                .send(record, QUEUE_TIMEOUT)
+                .await
+                .map(|_| ())
+                .map_err(|(e, _)| publish_error("Kafka", e))
+        }
+    }
+}
+
+#[cfg(feature = "nats")]
+mod nats {
+    use super::{publish_error, EventMessage, Publisher};
+    use crate::core::error::Result;
+    use async_nats::jetstream;
+    use axum::async_trait;
+
+    pub struct NatsPublisher {
+        jetstream: jetstream::Context,
+    }
+
+    impl NatsPublisher {
+        pub async fn connect(url: &str) -> Result<Self> {
+            let client = async_nats::connect(url)
+                .await
+                .map_err(|e| publish_error("NATS", e))?;
+            Ok(Self {
+                jetstream: jetstream::new(client),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Publisher for NatsPublisher {
+        /// Published through JetStream, which acknowledges once a stream
+        /// stored the message; core NATS would not say.
+        async fn publish(&self, message: &EventMessage) -> Result<()> {
+            let mut headers = async_nats::HeaderMap::new();
+            headers.insert("Mmss-Key", message.key.as_str());
+            if let Some(id) = message.payload["event_id"].as_str() {
+                // lets the stream drop a retried message it already has
+                headers.insert("Nats-Msg-Id", id);
+            }
+            self.jetstream
+                .publish_with_headers(
+                    message.topic.clone(),
+                    headers,
+                    message.payload.to_string().into(),
+                )
+                .await
+                .map_err(|e| publish_error("NATS", e))?
+                .await
+                .map_err(|e| publish_error("NATS", e))?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Fails the first `failures` publishes, then records messages.
+    struct FlakyPublisher {
+        failures: Mutex<usize>,
+        published: Mutex<Vec<EventMessage>>,
+    }
+
+    #[async_trait]
+    impl Publisher for FlakyPublisher {
+        async fn publish(&self, message: &EventMessage) -> Result<()> {
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err(Error::EventPublish("broker down".to_string()));
+            }
+            self.published.lock().unwrap().push(message.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_failed_task_also_raises_an_alert() {
+        let task_id = Uuid::new_v4();
+        let topics = EventTopics::default();
+        let failed = messages(
+            &StateEvent::TaskTransition {
+                task_id,
+                status: TaskStatus::Failed("boom".to_string()),
+            },
+            &topics,
+        );
+        assert_eq!(
+            failed.iter().map(|m| m.topic.as_str()).collect::<Vec<_>>(),
+            vec!["mmss.tasks", "mmss.alerts"]
+        );
+        assert_eq!(failed[0].key, task_id.to_string());
+        assert_eq!(failed[0].payload["status"], json!({ "Failed": "boom" }));
+        assert_eq!(failed[1].payload["alert"], "task_failed");
+        assert_ne!(failed[0].payload["event_id"], failed[1].payload["event_id"]);
+
+        let pending = messages(
+            &StateEvent::TaskTransition {
+                task_id,
+                status: TaskStatus::Pending,
+            },
+            &topics,
+        );
+        assert_eq!(pending.len(), 1);
+        assert!(messages(&StateEvent::RuleRemoved { name: "r".into() }, &topics).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_acknowledged() {
+        let publisher = Arc::new(FlakyPublisher {
+            failures: Mutex::new(2),
+            published: Mutex::new(Vec::new()),
+        });
+        let events = EventBus::default();
+        let config = EventPublishConfig {
+            broker: Broker::Nats {
+                url: "nats://127.0.0.1:4222".to_string(),
+            },
+            topics: EventTopics::default(),
+            retry_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        };
+        let handle = spawn_event_publisher(&events, publisher.clone(), config);
+
+        let task_id = Uuid::new_v4();
+        for status in [TaskStatus::Pending, TaskStatus::InProgress] {
+            events.publish(StateEvent::TaskTransition { task_id, status });
+        }
+        for _ in 0..100 {
+            if publisher.published.lock().unwrap().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        handle.abort();
+
+        let published = publisher.published.lock().unwrap();
+        assert_eq!(*publisher.failures.lock().unwrap(), 0);
+        assert_eq!(
+            published
+                .iter()
+                .map(|m| m.payload["status"].clone())
+                .collect::<Vec<_>>(),
+            vec![json!("Pending"), json!("InProgress")]
+        );
+    }
+}
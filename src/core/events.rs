@@ -0,0 +1,148 @@
+//! In-process broadcast of state changes. Features that react to changes,
+//! such as the visualization WebSocket and the exporters, subscribe here
+//! instead of polling the state on a timer.
+
+use crate::core::metrics_history::MetricsSnapshot;
+use crate::core::semantic_task_processor::TaskStatus;
+use crate::core::types::ResetScope;
+use std::env;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Events a subscriber may fall behind by before it skips ahead.
+const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateEvent {
+    /// A task was submitted or restored, approved, started or finished.
+    TaskTransition {
+        task_id: Uuid,
+        status: TaskStatus,
+    },
+    /// The metrics changed, after a task or a reset.
+    MetricsUpdated(MetricsSnapshot),
+    RuleRegistered {
+        name: String,
+    },
+    RuleRemoved {
+        name: String,
+    },
+    AnchorIndexed {
+        id: Uuid,
+        name: String,
+    },
+    /// A visualization style was set or removed.
+    StyleChanged {
+        metric: String,
+    },
+    /// The components in the scope were returned to baseline.
+    Reset(ResetScope),
+}
+
+impl StateEvent {
+    /// A task completed or failed.
+    pub fn is_task_finished(&self) -> bool {
+        matches!(
+            self,
+            StateEvent::TaskTransition {
+                status: TaskStatus::Completed(_) | TaskStatus::Failed(_),
+                ..
+            }
+        )
+    }
+}
+
+/// One per namespace, shared by its processor and routes.
+pub struct EventBus {
+    sender: broadcast::Sender<StateEvent>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            sender: broadcast::channel(capacity.max(1)).0,
+        }
+    }
+
+    /// `MMSS_EVENT_CAPACITY` events buffered per subscriber (default 1024).
+    pub fn from_env() -> Self {
+        Self::new(
+            env::var("MMSS_EVENT_CAPACITY")
+                .ok()
+                .and_then(|raw| raw.parse().ok())
+                .unwrap_or(DEFAULT_EVENT_CAPACITY),
+        )
+    }
+
+    /// Never blocks; an event published with nobody subscribed is dropped.
+    pub fn publish(&self, event: StateEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Events published from now on. A receiver that falls more than the
+    /// capacity behind gets `RecvError::Lagged` and continues from the
+    /// oldest event still buffered.
+    pub fn subscribe(&self) -> broadcast::Receiver<StateEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::semantic_task_processor::SemanticTaskProcessor;
+    use crate::core::types::{GeometricOperator, GeometricTaskCommand};
+
+    #[test]
+    fn test_task_execution_publishes_transitions_and_metrics() {
+        let processor = SemanticTaskProcessor::new();
+        let mut events = processor.events().subscribe();
+
+        let task_id = processor
+            .submit_task(GeometricTaskCommand {
+                task_name: "Rotate".to_string(),
+                geometric_operator: GeometricOperator::QuaternionRotation,
+                target_module: "test_module".to_string(),
+                parameters: serde_json::json!({ "theta": 1.0 }),
+                expected_output_metric: "v_geometric".to_string(),
+                task_id: None,
+            })
+            .unwrap();
+        let result = processor.execute_task(task_id).unwrap();
+
+        let statuses: Vec<_> = (0..3)
+            .map(|_| match events.try_recv().unwrap() {
+                StateEvent::TaskTransition {
+                    task_id: id,
+                    status,
+                } => {
+                    assert_eq!(id, task_id);
+                    status
+                }
+                other => panic!("expected a task transition, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                TaskStatus::Pending,
+                TaskStatus::InProgress,
+                TaskStatus::Completed(result.metrics.clone()),
+            ]
+        );
+        match events.try_recv().unwrap() {
+            StateEvent::MetricsUpdated(snapshot) => {
+                assert_eq!(snapshot.task_id, Some(task_id));
+                assert_eq!(snapshot.metrics, result.metrics);
+            }
+            other => panic!("expected a metrics update, got {:?}", other),
+        }
+        assert!(events.try_recv().is_err());
+    }
+}
@@ -0,0 +1,566 @@
+use crate::core::error::{Error, Result};
+use crate::core::object_storage::{ObjectDestination, ObjectStoreConfig};
+use crate::core::plugins::{self, PluginExportFormat};
+use crate::core::semantic_task_processor::SemanticTaskProcessor;
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use mmss_core::export::arrow::{IpcFormat, RecordWriter};
+use mmss_core::export::incremental::IncrementalExporter;
+use mmss_core::export::{arrow, csv, jsonl, parquet};
+use mmss_core::structex_bridge::MmssRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+/// File format of an export, named in lowercase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ExportFormat {
+    /// Arrow IPC file
+    #[default]
+    Arrow,
+    /// Snappy-compressed Parquet
+    Parquet,
+    /// CSV with the payload as a JSON column
+    Csv,
+    /// One JSON record per line
+    Jsonl,
+    /// Written by a plugin, see `core::plugins`
+    Plugin(&'static PluginExportFormat),
+}
+
+impl ExportFormat {
+    pub const BUILTIN: [ExportFormat; 4] = [
+        ExportFormat::Arrow,
+        ExportFormat::Parquet,
+        ExportFormat::Csv,
+        ExportFormat::Jsonl,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Arrow => "arrow",
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Plugin(format) => &format.name,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Plugin(format) => &format.extension,
+            builtin => builtin.name(),
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Arrow => "application/vnd.apache.arrow.file",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Jsonl => "application/jsonl",
+            ExportFormat::Plugin(format) => &format.content_type,
+        }
+    }
+}
+
+/// A built-in format, or one of an installed plugin.
+impl TryFrom<String> for ExportFormat {
+    type Error = String;
+
+    fn try_from(name: String) -> std::result::Result<Self, String> {
+        ExportFormat::BUILTIN
+            .into_iter()
+            .find(|format| format.name() == name)
+            .or_else(|| {
+                plugins::registry()
+                    .export_format(&name)
+                    .map(ExportFormat::Plugin)
+            })
+            .ok_or_else(|| format!("unknown export format `{}`", name))
+    }
+}
+
+impl From<ExportFormat> for String {
+    fn from(format: ExportFormat) -> Self {
+        format.name().to_string()
+    }
+}
+
+/// Write `records` to `path` in `format`, blocking the calling thread.
+pub fn write_records(path: &Path, records: &[MmssRecord], format: ExportFormat) -> Result<()> {
+    let written = match format {
+        ExportFormat::Arrow => arrow::write_records_to_file(path, records),
+        ExportFormat::Parquet => {
+            parquet::write_records_to_parquet(path, records, &parquet::ParquetOptions::default())
+        }
+        ExportFormat::Csv => csv::write_records_to_csv(path, records),
+        ExportFormat::Jsonl => jsonl::write_records_to_jsonl(path, records),
+        ExportFormat::Plugin(format) => format
+            .encode(records)
+            .map_err(Into::into)
+            .and_then(|bytes| std::fs::write(path, bytes).map_err(Into::into)),
+    };
+    written.map_err(|e| Error::TaskExecution(format!("Export to {} failed: {}", path.display(), e)))
+}
+
+/// The bytes `write_records` would write, for destinations other than files.
+pub fn encode_records(records: &[MmssRecord], format: ExportFormat) -> Result<Vec<u8>> {
+    encode(records, format)
+        .map_err(|e| Error::TaskExecution(format!("Export encoding failed: {}", e)))
+}
+
+fn encode(
+    records: &[MmssRecord],
+    format: ExportFormat,
+) -> std::result::Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut bytes = Vec::new();
+    match format {
+        ExportFormat::Arrow => {
+            let mut writer = RecordWriter::try_new(bytes, IpcFormat::File, records.len().max(1))?;
+            writer.write(records)?;
+            bytes = writer.finish()?;
+        }
+        ExportFormat::Parquet => {
+            bytes = parquet::records_to_parquet(records, &parquet::ParquetOptions::default())?
+        }
+        ExportFormat::Csv => csv::write_csv(&mut bytes, records)?,
+        ExportFormat::Jsonl => jsonl::write_jsonl(&mut bytes, records)?,
+        ExportFormat::Plugin(format) => bytes = format.encode(records)?,
+    }
+    Ok(bytes)
+}
+
+/// `write_records` on the blocking thread pool, so async handlers do not
+/// stall the runtime on file I/O.
+pub async fn write_records_async(
+    path: PathBuf,
+    records: Vec<MmssRecord>,
+    format: ExportFormat,
+) -> Result<()> {
+    tokio::task::spawn_blocking(move || write_records(&path, &records, format))
+        .await
+        .map_err(|e| Error::TaskExecution(format!("Export task panicked: {}", e)))?
+}
+
+/// Export of the task records added since the previous run, as tasks finish.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncrementalExportConfig {
+    /// Receives one file per run plus `watermarks.json`.
+    pub directory: PathBuf,
+    pub format: ExportFormat,
+    /// Minimum time between runs.
+    pub period: Duration,
+}
+
+impl IncrementalExportConfig {
+    /// Enabled by `MMSS_INCREMENTAL_EXPORT_SECS`; files go to
+    /// `MMSS_INCREMENTAL_EXPORT_DIR` (default `incremental` under `export_dir`)
+    /// in `MMSS_INCREMENTAL_EXPORT_FORMAT` (default `arrow`).
+    pub fn from_env(export_dir: &Path) -> Option<Self> {
+        let seconds: u64 = env::var("MMSS_INCREMENTAL_EXPORT_SECS")
+            .ok()?
+            .parse()
+            .ok()
+            .filter(|&s| s > 0)?;
+        let format = match env::var("MMSS_INCREMENTAL_EXPORT_FORMAT") {
+            Ok(name) => match serde_json::from_value(serde_json::Value::String(name.clone())) {
+                Ok(format) => format,
+                Err(_) => {
+                    warn!("Unknown incremental export format '{}', using arrow", name);
+                    ExportFormat::Arrow
+                }
+            },
+            Err(_) => ExportFormat::Arrow,
+        };
+        Some(Self {
+            directory: env::var("MMSS_INCREMENTAL_EXPORT_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| export_dir.join("incremental")),
+            format,
+            period: Duration::from_secs(seconds),
+        })
+    }
+}
+
+/// Write the records not yet exported to `config.directory` as one new file
+/// named after its first record, advancing the watermark for the format.
+/// Returns the number of records written.
+pub fn export_increment(config: &IncrementalExportConfig, records: &[MmssRecord]) -> Result<usize> {
+    let failed = |e: Box<dyn std::error::Error>| {
+        Error::TaskExecution(format!("Incremental export failed: {}", e))
+    };
+    let mut exporter =
+        IncrementalExporter::open(config.directory.join("watermarks.json")).map_err(failed)?;
+    exporter
+        .export(config.format.extension(), records, |pending| {
+            std::fs::create_dir_all(&config.directory)?;
+            let first = &pending[0];
+            let path = config.directory.join(format!(
+                "{}-{}.{}",
+                first.timestamp,
+                first.id,
+                config.format.extension()
+            ));
+            write_records(&path, pending, config.format)?;
+            Ok(())
+        })
+        .map_err(failed)
+}
+
+/// Run `export_increment` over the processor's finished task records
+/// whenever tasks have finished, at most once every `config.period`, until
+/// the runtime shuts down. The first run is right away, for records left
+/// over from before a restart.
+pub fn spawn_incremental_export(
+    processor: Arc<SemanticTaskProcessor>,
+    config: IncrementalExportConfig,
+) -> tokio::task::JoinHandle<()> {
+    let config = Arc::new(config);
+    tokio::spawn(async move {
+        let mut events = processor.events().subscribe();
+        let mut ticker = tokio::time::interval(config.period);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut finished = true;
+        loop {
+            tokio::select! {
+                _ = ticker.tick(), if finished => finished = false,
+                event = events.recv() => {
+                    match event {
+                        Ok(event) => finished |= event.is_task_finished(),
+                        Err(RecvError::Lagged(_)) => finished = true,
+                        Err(RecvError::Closed) => break,
+                    }
+                    continue;
+                }
+            }
+            let records = match processor.finished_task_records() {
+                Ok(records) => records,
+                Err(e) => {
+                    error!("Incremental export skipped: {}", e);
+                    continue;
+                }
+            };
+            let config = Arc::clone(&config);
+            let exported =
+                tokio::task::spawn_blocking(move || export_increment(&config, &records)).await;
+            match exported {
+                Ok(Ok(0)) => {}
+                Ok(Ok(count)) => info!("Incremental export wrote {} records", count),
+                Ok(Err(e)) => error!("{}", e),
+                Err(e) => error!("Incremental export task panicked: {}", e),
+            }
+        }
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ExportStatus {
+    Running,
+    Completed { records: usize, bytes: u64 },
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportJob {
+    pub id: Uuid,
+    pub format: ExportFormat,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Object store URI the export was uploaded to instead of the export
+    /// directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+    #[serde(skip)]
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub status: ExportStatus,
+}
+
+/// Background exports written to a directory or uploaded to object storage,
+/// tracked by job id until the process exits.
+pub struct ExportJobs {
+    directory: PathBuf,
+    object_store: ObjectStoreConfig,
+    jobs: Mutex<HashMap<Uuid, ExportJob>>,
+}
+
+impl ExportJobs {
+    pub fn new(directory: PathBuf) -> Self {
+        Self::with_object_store(directory, ObjectStoreConfig::default())
+    }
+
+    pub fn with_object_store(directory: PathBuf, object_store: ObjectStoreConfig) -> Self {
+        Self {
+            directory,
+            object_store,
+            jobs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Files go to `MMSS_EXPORT_DIR`, or `mmss-exports` in the temp directory;
+    /// object store settings come from `MMSS_OBJECT_STORE_CONFIG`.
+    pub fn from_env() -> Self {
+        Self::in_directory(env::var("MMSS_EXPORT_DIR").ok().map(PathBuf::from))
+    }
+
+    /// Files go to `directory`, or `mmss-exports` in the temp directory;
+    /// object store settings come from `MMSS_OBJECT_STORE_CONFIG`.
+    pub fn in_directory(directory: Option<PathBuf>) -> Self {
+        let object_store = ObjectStoreConfig::from_env().unwrap_or_else(|e| {
+            warn!("Ignoring object store config: {}", e);
+            ObjectStoreConfig::default()
+        });
+        Self::with_object_store(
+            directory.unwrap_or_else(|| env::temp_dir().join("mmss-exports")),
+            object_store,
+        )
+    }
+
+    /// No jobs yet, with the same object store settings, writing to
+    /// `directory`.
+    pub fn in_subdirectory(&self, directory: PathBuf) -> Self {
+        Self::with_object_store(directory, self.object_store.clone())
+    }
+
+    /// Start writing `records` in the background and return the running job.
+    /// With a `destination` URI (`s3://` or `gs://`) the export is uploaded
+    /// there rather than written to the export directory. Must be called from
+    /// within a tokio runtime.
+    pub fn start(
+        self: &Arc<Self>,
+        records: Vec<MmssRecord>,
+        format: ExportFormat,
+        destination: Option<String>,
+    ) -> Result<ExportJob> {
+        let object = destination
+            .as_deref()
+            .map(|uri| self.object_store.destination(uri))
+            .transpose()?;
+        let id = Uuid::new_v4();
+        let job = ExportJob {
+            id,
+            format,
+            created_at: Utc::now(),
+            finished_at: None,
+            destination,
+            path: self
+                .directory
+                .join(format!("{}.{}", id, format.extension())),
+            status: ExportStatus::Running,
+        };
+        self.lock()?.insert(id, job.clone());
+
+        let jobs = Arc::clone(self);
+        let path = job.path.clone();
+        let count = records.len();
+        tokio::spawn(async move {
+            let written = match object {
+                Some(object) => jobs.upload(&object, records, format).await,
+                None => match tokio::fs::create_dir_all(&jobs.directory).await {
+                    Ok(()) => write_records_async(path.clone(), records, format)
+                        .await
+                        .map(|()| None),
+                    Err(e) => Err(Error::Io(e)),
+                },
+            };
+            let status = match written {
+                Ok(uploaded) => {
+                    let bytes = match uploaded {
+                        Some(bytes) => bytes,
+                        None => tokio::fs::metadata(&path)
+                            .await
+                            .map(|m| m.len())
+                            .unwrap_or(0),
+                    };
+                    info!("Export {} wrote {} records ({} bytes)", id, count, bytes);
+                    ExportStatus::Completed {
+                        records: count,
+                        bytes,
+                    }
+                }
+                Err(e) => {
+                    error!("Export {} failed: {}", id, e);
+                    ExportStatus::Failed {
+                        error: e.to_string(),
+                    }
+                }
+            };
+            if let Ok(mut jobs) = jobs.lock() {
+                if let Some(job) = jobs.get_mut(&id) {
+                    job.status = status;
+                    job.finished_at = Some(Utc::now());
+                }
+            }
+        });
+        Ok(job)
+    }
+
+    /// Encode on the blocking thread pool, then upload; returns the size.
+    async fn upload(
+        &self,
+        object: &ObjectDestination,
+        records: Vec<MmssRecord>,
+        format: ExportFormat,
+    ) -> Result<Option<u64>> {
+        let bytes = tokio::task::spawn_blocking(move || encode_records(&records, format))
+            .await
+            .map_err(|e| Error::TaskExecution(format!("Export task panicked: {}", e)))??;
+        let size = bytes.len() as u64;
+        object.upload(bytes, self.object_store.part_size).await?;
+        info!("Uploaded export to {}", object.uri);
+        Ok(Some(size))
+    }
+
+    /// Where file exports are written.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn get(&self, id: Uuid) -> Result<Option<ExportJob>> {
+        Ok(self.lock()?.get(&id).cloned())
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, HashMap<Uuid, ExportJob>>> {
+        self.jobs.lock().map_err(|e| {
+            error!("Failed to lock export jobs: {}", e);
+            Error::TaskExecution("Failed to access export storage".to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::campaign::runner::fallback_task_for_target;
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_background_export_reports_completion() {
+        let directory = env::temp_dir().join(format!("mmss-export-test-{}", Uuid::new_v4()));
+        let jobs = Arc::new(ExportJobs::new(directory.clone()));
+        let records = (0..3)
+            .map(|id| MmssRecord {
+                id,
+                kind: "task".to_string(),
+                timestamp: id as i64,
+                payload: json!({ "n": id }),
+            })
+            .collect();
+
+        let job = jobs.start(records, ExportFormat::Parquet, None).unwrap();
+        assert_eq!(job.status, ExportStatus::Running);
+        let mut status = ExportStatus::Running;
+        for _ in 0..100 {
+            status = jobs.get(job.id).unwrap().unwrap().status;
+            if status != ExportStatus::Running {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let ExportStatus::Completed { records, bytes } = status else {
+            panic!("export did not complete: {:?}", status);
+        };
+        assert_eq!(records, 3);
+        assert_eq!(bytes, std::fs::metadata(&job.path).unwrap().len());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_incremental_export_writes_only_new_records() {
+        let config = IncrementalExportConfig {
+            directory: env::temp_dir().join(format!("mmss-incremental-{}", Uuid::new_v4())),
+            format: ExportFormat::Jsonl,
+            period: Duration::from_secs(60),
+        };
+        let mut records: Vec<MmssRecord> = (0..3)
+            .map(|id| MmssRecord {
+                id,
+                kind: "task".to_string(),
+                timestamp: 1_000 + id as i64,
+                payload: json!(id),
+            })
+            .collect();
+        assert_eq!(export_increment(&config, &records).unwrap(), 3);
+        assert_eq!(export_increment(&config, &records).unwrap(), 0);
+        records.push(MmssRecord {
+            id: 3,
+            kind: "task".to_string(),
+            timestamp: 2_000,
+            payload: json!(3),
+        });
+        assert_eq!(export_increment(&config, &records).unwrap(), 1);
+
+        let second =
+            jsonl::read_records_from_jsonl(&config.directory.join("2000-3.jsonl")).unwrap();
+        assert_eq!(second, records[3..]);
+        std::fs::remove_dir_all(&config.directory).unwrap();
+    }
+
+    #[test]
+    fn test_task_finishing_after_the_watermark_is_exported() {
+        let config = IncrementalExportConfig {
+            directory: env::temp_dir().join(format!("mmss-incremental-{}", Uuid::new_v4())),
+            format: ExportFormat::Jsonl,
+            period: Duration::from_secs(60),
+        };
+        let processor = SemanticTaskProcessor::new();
+        let [early, late] = ["q_oscillator", "v_geometric"].map(|target| {
+            processor
+                .submit_task(fallback_task_for_target(target, 1.0))
+                .unwrap()
+        });
+
+        // `late` is still pending and not exported yet
+        processor.execute_task(early).unwrap();
+        let records = processor.finished_task_records().unwrap();
+        assert_eq!(export_increment(&config, &records).unwrap(), 1);
+
+        // finished after the watermark passed its submission time
+        processor.execute_task(late).unwrap();
+        let records = processor.finished_task_records().unwrap();
+        assert_eq!(export_increment(&config, &records).unwrap(), 1);
+        let written = records.last().unwrap();
+        assert_eq!(written.id, late.as_u64_pair().0);
+        assert_eq!(written.payload["task_id"], json!(late));
+        assert!(written.payload["status"].get("Completed").is_some());
+        assert_eq!(export_increment(&config, &records).unwrap(), 0);
+        std::fs::remove_dir_all(&config.directory).unwrap();
+    }
+
+    #[test]
+    fn test_encoded_records_match_written_files() {
+        let directory = env::temp_dir().join(format!("mmss-encode-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let records: Vec<MmssRecord> = (0..5)
+            .map(|id| MmssRecord {
+                id,
+                kind: "metric".to_string(),
+                timestamp: id as i64,
+                payload: json!([id, "x"]),
+            })
+            .collect();
+        for format in [
+            ExportFormat::Arrow,
+            ExportFormat::Parquet,
+            ExportFormat::Csv,
+            ExportFormat::Jsonl,
+        ] {
+            let path = directory.join(format!("records.{}", format.extension()));
+            write_records(&path, &records, format).unwrap();
+            assert_eq!(
+                encode_records(&records, format).unwrap(),
+                std::fs::read(&path).unwrap()
+            );
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,298 @@
+//! Task records over Arrow Flight, for analytics clients that pull data
+//! over gRPC rather than from files or `GET /api/records/stream`. Needs the
+//! `flight` feature.
+//!
+//! A descriptor's command, like a ticket, is a JSON `RecordFilter`, e.g.
+//! `{"kind": "Zitterbewegung", "start": 1700000000000}`; empty bytes match
+//! every record. `GetFlightInfo` counts the matching records and hands out
+//! a ticket for them, `GetSchema` describes them, and `DoGet` streams them
+//! with the schema and batches of the file writers. Listing flights,
+//! actions, uploads and exchanges are not supported.
+
+use crate::core::semantic_task_processor::SemanticTaskProcessor;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::flight_descriptor::DescriptorType;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::{
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo,
+    HandshakeRequest, HandshakeResponse, PollInfo, PutResult, SchemaAsIpc, SchemaResult, Ticket,
+};
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::ipc::reader::StreamReader;
+use datafusion::arrow::ipc::writer::IpcWriteOptions;
+use datafusion::arrow::record_batch::RecordBatch;
+use futures_util::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use log::{info, warn};
+use mmss_core::export::stream::{write_record_stream, RecordFilter};
+use mmss_core::structex_bridge::MmssRecord;
+use std::fmt::Display;
+use std::io::Cursor;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status, Streaming};
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+pub struct RecordFlightService {
+    processor: Arc<SemanticTaskProcessor>,
+}
+
+impl RecordFlightService {
+    pub fn new(processor: Arc<SemanticTaskProcessor>) -> Self {
+        Self { processor }
+    }
+
+    fn records(&self) -> Result<Vec<MmssRecord>, Status> {
+        self.processor.task_records().map_err(internal)
+    }
+
+    /// The records `filter` accepts, in batches of up to `CHUNK_SIZE` rows.
+    async fn batches(&self, filter: RecordFilter) -> Result<(SchemaRef, Vec<RecordBatch>), Status> {
+        let records = self.records()?;
+        tokio::task::spawn_blocking(move || {
+            let (bytes, _) =
+                write_record_stream(Vec::new(), &records, &filter, CHUNK_SIZE).map_err(internal)?;
+            let reader = StreamReader::try_new(Cursor::new(bytes), None).map_err(internal)?;
+            let schema = reader.schema();
+            let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().map_err(internal)?;
+            Ok((schema, batches))
+        })
+        .await
+        .map_err(internal)?
+    }
+}
+
+fn internal(err: impl Display) -> Status {
+    Status::internal(err.to_string())
+}
+
+/// The schema every flight has, that of the record files.
+fn record_schema() -> Result<SchemaRef, Status> {
+    let (bytes, _) = write_record_stream(
+        Vec::new(),
+        std::iter::empty(),
+        &RecordFilter::default(),
+        CHUNK_SIZE,
+    )
+    .map_err(internal)?;
+    Ok(StreamReader::try_new(Cursor::new(bytes), None)
+        .map_err(internal)?
+        .schema())
+}
+
+/// The filter a ticket or descriptor command holds.
+fn parse_filter(bytes: &[u8]) -> Result<RecordFilter, Status> {
+    if bytes.is_empty() {
+        return Ok(RecordFilter::default());
+    }
+    serde_json::from_slice(bytes)
+        .map_err(|e| Status::invalid_argument(format!("Not a record filter: {}", e)))
+}
+
+fn descriptor_filter(descriptor: &FlightDescriptor) -> Result<RecordFilter, Status> {
+    if descriptor.r#type != DescriptorType::Cmd as i32 {
+        return Err(Status::invalid_argument(
+            "Path descriptors are not supported; send a record filter as the command",
+        ));
+    }
+    parse_filter(&descriptor.cmd)
+}
+
+#[tonic::async_trait]
+impl FlightService for RecordFlightService {
+    type HandshakeStream = BoxStream<'static, Result<HandshakeResponse, Status>>;
+    type ListFlightsStream = BoxStream<'static, Result<FlightInfo, Status>>;
+    type DoGetStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoPutStream = BoxStream<'static, Result<PutResult, Status>>;
+    type DoActionStream = BoxStream<'static, Result<arrow_flight::Result, Status>>;
+    type ListActionsStream = BoxStream<'static, Result<ActionType, Status>>;
+    type DoExchangeStream = BoxStream<'static, Result<FlightData, Status>>;
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("No handshake is needed"))
+    }
+
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        Err(Status::unimplemented("Ask for records by filter instead"))
+    }
+
+    async fn get_flight_info(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let descriptor = request.into_inner();
+        let filter = descriptor_filter(&descriptor)?;
+        let matching = self.records()?.iter().filter(|r| filter.matches(r)).count();
+        let info = FlightInfo::new()
+            .try_with_schema(record_schema()?.as_ref())
+            .map_err(internal)?
+            .with_endpoint(FlightEndpoint::new().with_ticket(Ticket::new(descriptor.cmd.clone())))
+            .with_descriptor(descriptor)
+            .with_total_records(matching as i64);
+        Ok(Response::new(info))
+    }
+
+    async fn poll_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented(
+            "Flights are ready at once; use GetFlightInfo",
+        ))
+    }
+
+    async fn get_schema(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        descriptor_filter(request.get_ref())?;
+        let schema = record_schema()?;
+        let result = SchemaAsIpc::new(&schema, &IpcWriteOptions::default())
+            .try_into()
+            .map_err(internal)?;
+        Ok(Response::new(result))
+    }
+
+    async fn do_get(
+        &self,
+        request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        let filter = parse_filter(&request.get_ref().ticket)?;
+        let (schema, batches) = self.batches(filter).await?;
+        let data = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(stream::iter(batches.into_iter().map(Ok)))
+            .map_err(Status::from);
+        Ok(Response::new(data.boxed()))
+    }
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("Records are read-only"))
+    }
+
+    async fn do_action(
+        &self,
+        _request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("No actions are supported"))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Ok(Response::new(stream::empty().boxed()))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("Records are read-only"))
+    }
+}
+
+/// Serve the processor's records on `addr` until the runtime shuts down.
+/// Binding happens here, so an address in use fails at startup.
+pub fn spawn_flight_server(
+    addr: SocketAddr,
+    processor: Arc<SemanticTaskProcessor>,
+) -> crate::Result<JoinHandle<()>> {
+    let incoming = TcpIncoming::new(addr, true, None).map_err(|e| {
+        crate::Error::Config(format!("Cannot serve Arrow Flight on {}: {}", addr, e))
+    })?;
+    let service = FlightServiceServer::new(RecordFlightService::new(processor));
+    Ok(tokio::spawn(async move {
+        info!("Serving records over Arrow Flight on {}", addr);
+        if let Err(e) = Server::builder()
+            .add_service(service)
+            .serve_with_incoming(incoming)
+            .await
+        {
+            warn!("Arrow Flight server stopped: {}", e);
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::campaign::runner::fallback_task_for_target;
+    use arrow_flight::decode::FlightRecordBatchStream;
+    use arrow_flight::error::FlightError;
+
+    fn service() -> RecordFlightService {
+        let processor = Arc::new(SemanticTaskProcessor::new());
+        for target in ["q_oscillator", "v_geometric", "q_oscillator"] {
+            processor
+                .submit_task(fallback_task_for_target(target, 1.0))
+                .unwrap();
+        }
+        RecordFlightService::new(processor)
+    }
+
+    #[tokio::test]
+    async fn test_tickets_from_flight_info_stream_the_matching_records() {
+        let service = service();
+        let descriptor = FlightDescriptor::new_cmd(r#"{"kind": "Zitterbewegung"}"#);
+        let info = service
+            .get_flight_info(Request::new(descriptor))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(info.total_records, 2);
+        let schema = info.clone().try_decode_schema().unwrap();
+        assert_eq!(schema, *record_schema().unwrap());
+
+        let ticket = info.endpoint[0].ticket.clone().unwrap();
+        let data = service
+            .do_get(Request::new(ticket))
+            .await
+            .unwrap()
+            .into_inner()
+            .map_err(FlightError::from);
+        let batches: Vec<RecordBatch> = FlightRecordBatchStream::new_from_flight_data(data)
+            .try_collect()
+            .await
+            .unwrap();
+        let rows: usize = batches.iter().map(RecordBatch::num_rows).sum();
+        assert_eq!(rows, 2);
+        assert_eq!(*batches[0].schema(), schema);
+
+        let everything = service
+            .get_flight_info(Request::new(FlightDescriptor::new_cmd("")))
+            .await
+            .unwrap();
+        assert_eq!(everything.get_ref().total_records, 3);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_requests_are_rejected() {
+        let service = service();
+        let status = service
+            .do_get(Request::new(Ticket::new(r#"{"kind": 3}"#)))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let path = FlightDescriptor::new_path(vec!["records".to_string()]);
+        let status = service
+            .get_flight_info(Request::new(path))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
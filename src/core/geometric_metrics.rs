@@ -1,14 +1,47 @@
+use crate::core::ops_metrics::OpsMetrics;
 use crate::core::types::GeometricMetrics;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 
 /// Function signature for dynamic metric rules.
 type RuleFn = Arc<dyn Fn(&mut GeometricMetrics) + Send + Sync>;
 
+/// A rule shifting metrics by fixed amounts, as registered over the API.
+/// Unlike arbitrary closures these can be persisted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeltaRule {
+    pub name: String,
+    pub delta_v: Option<f64>,
+    pub delta_s: Option<f64>,
+    pub delta_q: Option<f64>,
+}
+
+impl DeltaRule {
+    pub fn apply(&self, metrics: &mut GeometricMetrics) {
+        if let Some(delta) = self.delta_v {
+            metrics.v_geometric += delta;
+        }
+        if let Some(delta) = self.delta_s {
+            metrics.s_geometric = (metrics.s_geometric + delta).clamp(0.0, 1.0);
+        }
+        if let Some(delta) = self.delta_q {
+            metrics.q_oscillator += delta;
+        }
+        metrics
+            .custom_metrics
+            .insert(format!("rule:{}", self.name), 1.0);
+    }
+}
+
 /// Engine that stores and applies dynamic metric rules.
 #[derive(Default)]
 pub struct GeometricMetricEngine {
     rules: HashMap<String, RuleFn>,
+    /// Specs of the rules registered with `register_delta_rule`.
+    delta_rules: HashMap<String, DeltaRule>,
+    /// Counts rule applications, when set.
+    ops: Option<Arc<OpsMetrics>>,
 }
 
 impl GeometricMetricEngine {
@@ -17,23 +50,53 @@ impl GeometricMetricEngine {
         Self::default()
     }
 
+    /// Count every rule application in `ops`.
+    pub fn with_ops_metrics(mut self, ops: Arc<OpsMetrics>) -> Self {
+        self.ops = Some(ops);
+        self
+    }
+
     /// Register or replace a rule.
     pub fn register_rule<F>(&mut self, name: impl Into<String>, rule: F)
     where
         F: Fn(&mut GeometricMetrics) + Send + Sync + 'static,
     {
-        self.rules.insert(name.into(), Arc::new(rule));
+        let name = name.into();
+        self.delta_rules.remove(&name);
+        self.rules.insert(name, Arc::new(rule));
+    }
+
+    /// Register or replace a rule that is kept in `delta_rules`.
+    pub fn register_delta_rule(&mut self, rule: DeltaRule) {
+        let name = rule.name.clone();
+        let spec = rule.clone();
+        self.register_rule(name.clone(), move |metrics: &mut GeometricMetrics| {
+            rule.apply(metrics)
+        });
+        self.delta_rules.insert(name, spec);
     }
 
     /// Remove an existing rule.
     pub fn remove_rule(&mut self, name: &str) -> bool {
+        self.delta_rules.remove(name);
         self.rules.remove(name).is_some()
     }
 
+    /// Registered delta rules by name; closures registered directly are
+    /// left out.
+    pub fn delta_rules(&self) -> Vec<DeltaRule> {
+        let mut rules: Vec<_> = self.delta_rules.values().cloned().collect();
+        rules.sort_by(|a, b| a.name.cmp(&b.name));
+        rules
+    }
+
     /// Apply a single rule if it exists.
     pub fn apply_rule(&self, name: &str, metrics: &mut GeometricMetrics) -> bool {
         if let Some(rule) = self.rules.get(name) {
             rule(metrics);
+            if let Some(ops) = &self.ops {
+                ops.record_rule_application(name);
+            }
             true
         } else {
             false
@@ -42,8 +105,11 @@ impl GeometricMetricEngine {
 
     /// Apply all registered rules.
     pub fn apply_all(&self, metrics: &mut GeometricMetrics) {
-        for rule in self.rules.values() {
+        for (name, rule) in &self.rules {
             rule(metrics);
+            if let Some(ops) = &self.ops {
+                ops.record_rule_application(name);
+            }
         }
     }
 
@@ -66,6 +132,7 @@ impl GeometricMetricEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::types::MetricMap;
 
     #[test]
     fn test_register_and_apply_rule() {
@@ -76,10 +143,47 @@ mod tests {
             v_geometric: 1.0,
             s_geometric: 1.0,
             q_oscillator: 1.0,
-            custom_metrics: HashMap::new(),
+            quaternion_coherence: 1.0,
+            emergent_electron_mass: 0.0,
+            fine_structure_constant: 0.0,
+            zitterbewegung_entropy: 0.0,
+            topological_winding: 0.0,
+            custom_metrics: MetricMap::new(),
         };
 
         assert!(engine.apply_rule("boost_v", &mut metrics));
         assert_eq!(metrics.v_geometric, 1.5);
     }
+    #[test]
+    fn test_delta_rules_are_listed_until_replaced() {
+        let mut engine = GeometricMetricEngine::new();
+        let rule = DeltaRule {
+            name: "damp".to_string(),
+            delta_v: None,
+            delta_s: Some(-2.0),
+            delta_q: Some(1.0),
+        };
+        engine.register_delta_rule(rule.clone());
+        assert_eq!(engine.delta_rules(), vec![rule]);
+
+        let mut metrics = GeometricMetrics {
+            v_geometric: 1.0,
+            s_geometric: 0.5,
+            q_oscillator: 1.0,
+            quaternion_coherence: 1.0,
+            emergent_electron_mass: 0.0,
+            fine_structure_constant: 0.0,
+            zitterbewegung_entropy: 0.0,
+            topological_winding: 0.0,
+            custom_metrics: MetricMap::new(),
+        };
+        engine.apply_all(&mut metrics);
+        assert_eq!((metrics.s_geometric, metrics.q_oscillator), (0.0, 2.0));
+        assert_eq!(metrics.custom_metrics["rule:damp"], 1.0);
+
+        // a closure under the same name cannot be persisted
+        engine.register_rule("damp", |metrics| metrics.v_geometric = 0.0);
+        assert!(engine.delta_rules().is_empty());
+        assert_eq!(engine.len(), 1);
+    }
 }
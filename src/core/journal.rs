@@ -0,0 +1,377 @@
+//! Append-only journal of every state mutation, from which the
+//! `SystemState` at any point can be rebuilt by replay. Enabled by
+//! `MMSS_JOURNAL_PATH`, for the default namespace.
+
+use crate::core::emergence_logic::EmergenceLogic;
+use crate::core::error::{Error, Result};
+use crate::core::geometric_metrics::DeltaRule;
+use crate::core::types::{
+    GeometricMetrics, GeometricOperator, GeometricTaskCommand, PendingTask, ResetScope,
+    SemanticAnchor, SystemState,
+};
+use crate::state::PhysicalConstants;
+use chrono::{DateTime, Utc};
+use log::{error, warn};
+use mmss_eqgft::cache::EqgftCache;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Mutation {
+    TaskSubmitted {
+        task_id: Uuid,
+        command: GeometricTaskCommand,
+        awaiting_approval: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        submitted_by: Option<String>,
+    },
+    TaskApproved {
+        task_id: Uuid,
+    },
+    /// The task left the queue: it ran, or failed before it could.
+    TaskStarted {
+        task_id: Uuid,
+    },
+    /// An operator changed the metrics and possibly the Hopfion field.
+    /// `parameters` include any seed drawn for the run, which `seed`
+    /// repeats; `metrics` are those it produced, which replay must match.
+    /// Entries written before both were recorded have neither.
+    OperatorApplied {
+        task_id: Uuid,
+        operator: GeometricOperator,
+        parameters: serde_json::Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seed: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metrics: Option<GeometricMetrics>,
+    },
+    RuleRegistered {
+        rule: DeltaRule,
+    },
+    RuleRemoved {
+        name: String,
+    },
+    AnchorIndexed {
+        anchor: SemanticAnchor,
+    },
+    Reset {
+        scope: ResetScope,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Position in the journal, from 1.
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub mutation: Mutation,
+}
+
+/// Entries appended as JSON lines to one file, which is never rewritten.
+pub struct Journal {
+    path: PathBuf,
+    /// The open file and the sequence number of the last entry.
+    writer: Mutex<(File, u64)>,
+}
+
+impl Journal {
+    /// Continue the journal at `path`, creating it if needed.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let last_seq = read_entries(&path)?.last().map_or(0, |entry| entry.seq);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            writer: Mutex::new((file, last_seq)),
+        })
+    }
+
+    /// The journal at `MMSS_JOURNAL_PATH`, if set.
+    pub fn from_env() -> Option<Result<Self>> {
+        env::var("MMSS_JOURNAL_PATH").ok().map(Self::open)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `mutation`, returning its sequence number.
+    pub fn append(&self, mutation: Mutation) -> Result<u64> {
+        let mut writer = self.lock()?;
+        let entry = JournalEntry {
+            seq: writer.1 + 1,
+            timestamp: Utc::now(),
+            mutation,
+        };
+        writeln!(writer.0, "{}", serde_json::to_string(&entry)?)?;
+        writer.0.flush()?;
+        writer.1 = entry.seq;
+        Ok(entry.seq)
+    }
+
+    /// `append`, logging a failure instead of returning it, so journaling
+    /// never fails the mutation it records.
+    pub fn record(&self, mutation: Mutation) {
+        if let Err(e) = self.append(mutation) {
+            warn!("Failed to journal a state mutation: {}", e);
+        }
+    }
+
+    /// Every entry, oldest first.
+    pub fn entries(&self) -> Result<Vec<JournalEntry>> {
+        // no append can be half-written while the writer is held
+        let _writer = self.lock()?;
+        read_entries(&self.path)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, (File, u64)>> {
+        self.writer.lock().map_err(|e| {
+            error!("Failed to lock the journal: {}", e);
+            Error::TaskExecution("Failed to access the journal".to_string())
+        })
+    }
+}
+
+fn read_entries(path: &Path) -> Result<Vec<JournalEntry>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(Error::from))
+        .collect()
+}
+
+/// The state after `entries`, starting from the baseline of `constants`.
+/// Hopfion fields are regenerated through `cache`, so replaying a journal
+/// the server is running from reuses its fields. Fails with
+/// `Error::Conflict` when an operator gives other metrics than it recorded,
+/// as a nondeterministic plugin or changed code would.
+pub fn replay(
+    entries: &[JournalEntry],
+    constants: PhysicalConstants,
+    cache: Arc<EqgftCache>,
+) -> Result<SystemState> {
+    let mut emergence = EmergenceLogic::new(None)
+        .with_constants(constants)
+        .with_eqgft_cache(cache);
+    let mut rules = BTreeMap::new();
+    let mut anchors: Vec<SemanticAnchor> = Vec::new();
+    let mut pending: Vec<PendingTask> = Vec::new();
+
+    for entry in entries {
+        match &entry.mutation {
+            Mutation::TaskSubmitted {
+                task_id,
+                command,
+                awaiting_approval,
+                submitted_by,
+            } => pending.push(PendingTask {
+                task_id: *task_id,
+                command: command.clone(),
+                awaiting_approval: *awaiting_approval,
+                submitted_at: entry.timestamp,
+                submitted_by: submitted_by.clone(),
+            }),
+            Mutation::TaskApproved { task_id } => {
+                if let Some(task) = pending.iter_mut().find(|task| task.task_id == *task_id) {
+                    task.awaiting_approval = false;
+                }
+            }
+            Mutation::TaskStarted { task_id } => pending.retain(|task| task.task_id != *task_id),
+            Mutation::OperatorApplied {
+                operator,
+                parameters,
+                metrics,
+                ..
+            } => {
+                let replayed = emergence.apply_operator(*operator, parameters);
+                if metrics
+                    .as_ref()
+                    .is_some_and(|recorded| recorded != replayed)
+                {
+                    return Err(Error::Conflict(format!(
+                        "Journal entry {} does not replay: {:?} gave other metrics than recorded",
+                        entry.seq, operator
+                    )));
+                }
+            }
+            Mutation::RuleRegistered { rule } => {
+                rules.insert(rule.name.clone(), rule.clone());
+            }
+            Mutation::RuleRemoved { name } => {
+                rules.remove(name);
+            }
+            Mutation::AnchorIndexed { anchor } => anchors.push(anchor.clone()),
+            Mutation::Reset { scope } => {
+                emergence.reset(scope.metrics, scope.field);
+                if scope.tasks {
+                    pending.clear();
+                }
+                if scope.rules {
+                    rules.clear();
+                }
+                if scope.anchors {
+                    anchors.clear();
+                }
+            }
+        }
+    }
+
+    Ok(SystemState {
+        state_id: Uuid::new_v4(),
+        timestamp: entries
+            .last()
+            .map_or_else(Utc::now, |entry| entry.timestamp),
+        metrics: emergence.metrics().clone(),
+        active_anchors: anchors,
+        active_tasks: pending.iter().map(|task| task.task_id).collect(),
+        rules: rules.into_values().collect(),
+        pending_tasks: pending,
+        hopfion: emergence.hopfion_field().map(|field| field.config),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::semantic_task_processor::SemanticTaskProcessor;
+
+    fn task(operator: GeometricOperator, parameters: serde_json::Value) -> GeometricTaskCommand {
+        GeometricTaskCommand {
+            task_name: "Journaled".to_string(),
+            geometric_operator: operator,
+            target_module: "test_module".to_string(),
+            parameters,
+            expected_output_metric: "v_geometric".to_string(),
+            task_id: None,
+        }
+    }
+
+    #[test]
+    fn test_replay_rebuilds_processor_state() {
+        let path = env::temp_dir().join(format!("mmss-journal-{}.jsonl", Uuid::new_v4()));
+        let journal = Arc::new(Journal::open(&path).unwrap());
+        let processor = SemanticTaskProcessor::new().with_journal(journal.clone());
+
+        let rotated = processor
+            .submit_task(task(
+                GeometricOperator::QuaternionRotation,
+                serde_json::json!({ "theta": 1.0 }),
+            ))
+            .unwrap();
+        processor.execute_task(rotated).unwrap();
+        let after_rotation = processor.get_metrics();
+        let queued = processor
+            .submit_task(task(
+                GeometricOperator::GeometricDerivation,
+                serde_json::json!({ "delta": 2.0 }),
+            ))
+            .unwrap();
+        journal
+            .append(Mutation::RuleRegistered {
+                rule: DeltaRule {
+                    name: "boost".to_string(),
+                    delta_v: Some(1.0),
+                    delta_s: None,
+                    delta_q: None,
+                },
+            })
+            .unwrap();
+        processor.execute_task(queued).unwrap();
+
+        let entries = journal.entries().unwrap();
+        assert_eq!(
+            entries.iter().map(|entry| entry.seq).collect::<Vec<_>>(),
+            (1..=entries.len() as u64).collect::<Vec<_>>()
+        );
+        let cache = processor.eqgft_cache().clone();
+        let replayed = replay(&entries, PhysicalConstants::SI, cache.clone()).unwrap();
+        assert_eq!(replayed.metrics, processor.get_metrics());
+        assert!(replayed.pending_tasks.is_empty());
+        assert_eq!(replayed.rules.len(), 1);
+
+        // time travel: the state just before the queued task ran
+        let seq = entries
+            .iter()
+            .find(|entry| {
+                matches!(entry.mutation, Mutation::TaskStarted { task_id } if task_id == queued)
+            })
+            .unwrap()
+            .seq;
+        let earlier = replay(&entries[..seq as usize - 1], PhysicalConstants::SI, cache).unwrap();
+        assert_eq!(earlier.metrics, after_rotation);
+        assert_eq!(earlier.active_tasks, vec![queued]);
+
+        let reopened = Journal::open(&path).unwrap();
+        assert_eq!(
+            reopened
+                .append(Mutation::Reset {
+                    scope: ResetScope::EVERYTHING
+                })
+                .unwrap(),
+            entries.len() as u64 + 1
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_drawn_seeds_are_journaled_and_replay_checks_metrics() {
+        let path = env::temp_dir().join(format!("mmss-journal-{}.jsonl", Uuid::new_v4()));
+        let journal = Arc::new(Journal::open(&path).unwrap());
+        let processor = SemanticTaskProcessor::new().with_journal(journal.clone());
+        let simulated = processor
+            .submit_task(task(
+                GeometricOperator::SimulateEqgftAsymmetry,
+                serde_json::json!({ "n_events": 2000 }),
+            ))
+            .unwrap();
+        processor.execute_task(simulated).unwrap();
+
+        let mut entries = journal.entries().unwrap();
+        let (parameters, seed) = entries
+            .iter()
+            .find_map(|entry| match &entry.mutation {
+                Mutation::OperatorApplied {
+                    parameters, seed, ..
+                } => Some((parameters.clone(), *seed)),
+                _ => None,
+            })
+            .unwrap();
+        assert!(seed.is_some());
+        assert_eq!(parameters["seed"].as_u64(), seed);
+
+        let cache = processor.eqgft_cache().clone();
+        let replayed = replay(&entries, PhysicalConstants::SI, cache.clone()).unwrap();
+        assert_eq!(replayed.metrics, processor.get_metrics());
+
+        for entry in &mut entries {
+            if let Mutation::OperatorApplied {
+                metrics: Some(metrics),
+                ..
+            } = &mut entry.mutation
+            {
+                metrics.v_geometric += 1.0;
+            }
+        }
+        let err = replay(&entries, PhysicalConstants::SI, cache).unwrap_err();
+        assert!(matches!(err, Error::Conflict(_)), "{}", err);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+//! Mutexes that count how often they were found held and how long callers
+//! waited for them, for `GET /debug/runtime`, and that can recover from a
+//! holder's panic instead of failing every later caller.
+
+use log::warn;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LockResult, Mutex, MutexGuard, TryLockError};
+use std::time::Instant;
+
+/// Contention of one mutex since it was created.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LockStats {
+    pub name: &'static str,
+    pub acquisitions: u64,
+    /// Acquisitions that found the mutex held and had to wait.
+    pub contended: u64,
+    pub wait_ms: f64,
+    /// Poisonings by a panicked holder that `lock_or_recover` repaired.
+    pub recovered: u64,
+}
+
+/// A `std::sync::Mutex` with the same `lock` contract, plus contention
+/// counters and `lock_or_recover`.
+#[derive(Debug)]
+pub struct TrackedMutex<T> {
+    name: &'static str,
+    inner: Mutex<T>,
+    acquisitions: AtomicU64,
+    contended: AtomicU64,
+    wait_nanos: AtomicU64,
+    recovered: AtomicU64,
+}
+
+impl<T> TrackedMutex<T> {
+    pub fn new(name: &'static str, value: T) -> Self {
+        Self {
+            name,
+            inner: Mutex::new(value),
+            acquisitions: AtomicU64::new(0),
+            contended: AtomicU64::new(0),
+            wait_nanos: AtomicU64::new(0),
+            recovered: AtomicU64::new(0),
+        }
+    }
+
+    pub fn lock(&self) -> LockResult<MutexGuard<'_, T>> {
+        self.acquisitions.fetch_add(1, Ordering::Relaxed);
+        match self.inner.try_lock() {
+            Ok(guard) => Ok(guard),
+            Err(TryLockError::Poisoned(poisoned)) => Err(poisoned),
+            Err(TryLockError::WouldBlock) => {
+                self.contended.fetch_add(1, Ordering::Relaxed);
+                let started = Instant::now();
+                let guard = self.inner.lock();
+                let waited = u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+                self.wait_nanos.fetch_add(waited, Ordering::Relaxed);
+                guard
+            }
+        }
+    }
+
+    /// Lock, and if a holder panicked first hand the value it left behind
+    /// to `recover`, which brings it back to a consistent state, then clear
+    /// the poison. Should `recover` panic too, the mutex stays poisoned.
+    pub fn lock_or_recover(&self, recover: impl FnOnce(&mut T)) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(|poisoned| {
+            warn!(
+                "Recovering {} mutex poisoned by a panicked holder",
+                self.name
+            );
+            let mut guard = poisoned.into_inner();
+            recover(&mut guard);
+            self.recovered.fetch_add(1, Ordering::Relaxed);
+            self.inner.clear_poison();
+            guard
+        })
+    }
+
+    pub fn stats(&self) -> LockStats {
+        LockStats {
+            name: self.name,
+            acquisitions: self.acquisitions.load(Ordering::Relaxed),
+            contended: self.contended.load(Ordering::Relaxed),
+            wait_ms: self.wait_nanos.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+            recovered: self.recovered.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_counts_contended_acquisitions() {
+        let mutex = TrackedMutex::new("tasks", 0_u32);
+        {
+            *mutex.lock().unwrap() += 1;
+        }
+        assert_eq!(mutex.stats().contended, 0);
+
+        thread::scope(|scope| {
+            let mutex = &mutex;
+            let (held, wait_for_holder) = mpsc::channel();
+            scope.spawn(move || {
+                let mut guard = mutex.lock().unwrap();
+                held.send(()).unwrap();
+                thread::sleep(Duration::from_millis(50));
+                *guard += 1;
+            });
+            wait_for_holder.recv().unwrap();
+            *mutex.lock().unwrap() += 1;
+        });
+
+        let stats = mutex.stats();
+        assert_eq!(*mutex.lock().unwrap(), 3);
+        assert_eq!(
+            (stats.name, stats.acquisitions, stats.contended),
+            ("tasks", 3, 1)
+        );
+        assert!(stats.wait_ms > 0.0);
+    }
+
+    #[test]
+    fn test_recovers_from_poison() {
+        let mutex = TrackedMutex::new("metrics", 1_u32);
+        thread::scope(|scope| {
+            let panicked = scope.spawn(|| {
+                let mut guard = mutex.lock().unwrap();
+                *guard = 2;
+                panic!("task panicked while holding the lock");
+            });
+            assert!(panicked.join().is_err());
+        });
+
+        assert!(mutex.lock().is_err());
+        let mut seen = None;
+        let guard = mutex.lock_or_recover(|value| {
+            seen = Some(*value);
+            *value = 0;
+        });
+        assert_eq!((seen, *guard), (Some(2), 0));
+        drop(guard);
+        assert_eq!(mutex.stats().recovered, 1);
+
+        *mutex.lock_or_recover(|_| unreachable!("no longer poisoned")) += 1;
+        assert_eq!(*mutex.lock().unwrap(), 1);
+        assert_eq!(mutex.stats().recovered, 1);
+    }
+}
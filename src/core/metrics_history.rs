@@ -0,0 +1,445 @@
+use crate::core::error::{Error, Result};
+use crate::core::semantic_task_processor::SemanticTaskProcessor;
+use crate::core::types::{GeometricMetrics, GeometricOperator};
+use chrono::Utc;
+use datafusion::arrow::array::{ArrayRef, Float64Array, Int64Array, StringArray};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use datafusion::arrow::ipc::writer::FileWriter;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::parquet::arrow::ArrowWriter;
+use datafusion::parquet::basic::Compression;
+use datafusion::parquet::file::properties::WriterProperties;
+use log::{error, warn};
+use std::collections::VecDeque;
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Columns every snapshot has, after `timestamp` and `task_id`.
+const METRIC_COLUMNS: [&str; 8] = [
+    "v_geometric",
+    "s_geometric",
+    "q_oscillator",
+    "quaternion_coherence",
+    "emergent_electron_mass",
+    "fine_structure_constant",
+    "zitterbewegung_entropy",
+    "topological_winding",
+];
+
+/// File format of the metrics history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HistoryFormat {
+    #[default]
+    Arrow,
+    Parquet,
+}
+
+impl HistoryFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            HistoryFormat::Arrow => "arrow",
+            HistoryFormat::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsHistoryConfig {
+    pub directory: PathBuf,
+    pub format: HistoryFormat,
+    /// Custom metrics kept as their own nullable columns, in this order.
+    pub custom_metrics: Vec<String>,
+    /// Snapshots buffered before they are written out as one file.
+    pub flush_rows: usize,
+    /// Also snapshot (and flush) on this period, not only on task completion.
+    pub snapshot_period: Option<Duration>,
+    /// Latest snapshots also kept in memory for trajectory packets.
+    pub recent_snapshots: usize,
+}
+
+impl MetricsHistoryConfig {
+    /// Enabled by `MMSS_METRICS_HISTORY_DIR`. `MMSS_METRICS_HISTORY_FORMAT`
+    /// is `arrow` (default) or `parquet`, `MMSS_METRICS_HISTORY_CUSTOM` a
+    /// comma-separated list of custom metrics to keep,
+    /// `MMSS_METRICS_HISTORY_FLUSH_ROWS` defaults to 64,
+    /// `MMSS_METRICS_HISTORY_RECENT` to 1024, and
+    /// `MMSS_METRICS_SNAPSHOT_SECS` enables the timer.
+    pub fn from_env() -> Option<Self> {
+        let directory = PathBuf::from(env::var("MMSS_METRICS_HISTORY_DIR").ok()?);
+        Some(Self::in_directory(directory))
+    }
+
+    /// History in `directory`, tuned by the other variables as above.
+    pub fn in_directory(directory: PathBuf) -> Self {
+        let format = match env::var("MMSS_METRICS_HISTORY_FORMAT").as_deref() {
+            Ok("parquet") => HistoryFormat::Parquet,
+            Ok("arrow") | Err(_) => HistoryFormat::Arrow,
+            Ok(other) => {
+                warn!("Unknown metrics history format '{}', using arrow", other);
+                HistoryFormat::Arrow
+            }
+        };
+        let read = |name: &str| env::var(name).ok().and_then(|raw| raw.parse::<u64>().ok());
+        Self {
+            directory,
+            format,
+            custom_metrics: env::var("MMSS_METRICS_HISTORY_CUSTOM")
+                .map(|names| {
+                    names
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            flush_rows: read("MMSS_METRICS_HISTORY_FLUSH_ROWS")
+                .map(|rows| rows.max(1) as usize)
+                .unwrap_or(64),
+            snapshot_period: read("MMSS_METRICS_SNAPSHOT_SECS")
+                .filter(|&secs| secs > 0)
+                .map(Duration::from_secs),
+            recent_snapshots: read("MMSS_METRICS_HISTORY_RECENT")
+                .map(|rows| rows as usize)
+                .unwrap_or(1024),
+        }
+    }
+}
+
+/// The metrics at one point in time, with the task whose completion produced
+/// them, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsSnapshot {
+    /// Milliseconds since the epoch.
+    pub timestamp: i64,
+    pub task_id: Option<Uuid>,
+    /// Operator of the task; kept in memory only, not in the history files.
+    pub operator: Option<GeometricOperator>,
+    pub metrics: GeometricMetrics,
+}
+
+impl MetricsSnapshot {
+    pub fn now(task_id: Option<Uuid>, metrics: GeometricMetrics) -> Self {
+        Self {
+            timestamp: Utc::now().timestamp_millis(),
+            task_id,
+            operator: None,
+            metrics,
+        }
+    }
+
+    pub fn with_operator(mut self, operator: GeometricOperator) -> Self {
+        self.operator = Some(operator);
+        self
+    }
+}
+
+/// Columns of a snapshot batch: `timestamp`, `task_id`, the built-in
+/// metrics, then `custom_metrics` as nullable columns in that order.
+pub fn snapshot_schema(custom_metrics: &[String]) -> SchemaRef {
+    let mut fields = vec![
+        Field::new("timestamp", DataType::Int64, false),
+        Field::new("task_id", DataType::Utf8, true),
+    ];
+    fields.extend(
+        METRIC_COLUMNS
+            .iter()
+            .map(|name| Field::new(*name, DataType::Float64, false)),
+    );
+    fields.extend(
+        custom_metrics
+            .iter()
+            .map(|name| Field::new(name, DataType::Float64, true)),
+    );
+    Arc::new(Schema::new(fields))
+}
+
+/// `snapshots` as one batch of `schema`, which must come from
+/// `snapshot_schema(custom_metrics)`.
+pub fn snapshot_batch(
+    schema: &SchemaRef,
+    custom_metrics: &[String],
+    snapshots: &[MetricsSnapshot],
+) -> std::result::Result<RecordBatch, datafusion::arrow::error::ArrowError> {
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(Int64Array::from_iter_values(
+            snapshots.iter().map(|s| s.timestamp),
+        )),
+        Arc::new(StringArray::from_iter(
+            snapshots.iter().map(|s| s.task_id.map(|id| id.to_string())),
+        )),
+    ];
+    let builtin: [fn(&GeometricMetrics) -> f64; 8] = [
+        |m| m.v_geometric,
+        |m| m.s_geometric,
+        |m| m.q_oscillator,
+        |m| m.quaternion_coherence,
+        |m| m.emergent_electron_mass,
+        |m| m.fine_structure_constant,
+        |m| m.zitterbewegung_entropy,
+        |m| m.topological_winding,
+    ];
+    for value in builtin {
+        columns.push(Arc::new(Float64Array::from_iter_values(
+            snapshots.iter().map(|s| value(&s.metrics)),
+        )));
+    }
+    for name in custom_metrics {
+        columns.push(Arc::new(Float64Array::from_iter(
+            snapshots
+                .iter()
+                .map(|s| s.metrics.custom_metrics.get(name).copied()),
+        )));
+    }
+    RecordBatch::try_new(schema.clone(), columns)
+}
+
+/// Time series of `GeometricMetrics` snapshots, flattened into typed columns
+/// and written to the history directory in batches of `flush_rows`, one file
+/// per batch, so the history outlives the process.
+pub struct MetricsHistory {
+    config: MetricsHistoryConfig,
+    schema: SchemaRef,
+    pending: Mutex<Vec<MetricsSnapshot>>,
+    recent: Mutex<VecDeque<MetricsSnapshot>>,
+}
+
+impl MetricsHistory {
+    pub fn new(config: MetricsHistoryConfig) -> Self {
+        Self {
+            schema: snapshot_schema(&config.custom_metrics),
+            config,
+            pending: Mutex::new(Vec::new()),
+            recent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// An empty history with the same settings, kept in `directory`.
+    pub fn in_directory(&self, directory: PathBuf) -> Self {
+        Self::new(MetricsHistoryConfig {
+            directory,
+            ..self.config.clone()
+        })
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.config.directory
+    }
+
+    pub fn format(&self) -> HistoryFormat {
+        self.config.format
+    }
+
+    pub fn snapshot_period(&self) -> Option<Duration> {
+        self.config.snapshot_period
+    }
+
+    pub fn custom_metrics(&self) -> &[String] {
+        &self.config.custom_metrics
+    }
+
+    /// Buffer a snapshot of `metrics` taken now, writing the buffer out once
+    /// it holds `flush_rows` snapshots.
+    pub fn record(&self, task_id: Option<Uuid>, metrics: &GeometricMetrics) -> Result<()> {
+        self.push(MetricsSnapshot::now(task_id, metrics.clone()))
+    }
+
+    /// Buffer a snapshot taken elsewhere, as `record` does.
+    pub fn push(&self, snapshot: MetricsSnapshot) -> Result<()> {
+        if self.config.recent_snapshots > 0 {
+            let mut recent = self.recent.lock().map_err(|e| {
+                error!("Failed to lock recent metrics: {}", e);
+                Error::TaskExecution("Failed to access metrics history".to_string())
+            })?;
+            if recent.len() == self.config.recent_snapshots {
+                recent.pop_front();
+            }
+            recent.push_back(snapshot.clone());
+        }
+        let mut pending = self.lock()?;
+        pending.push(snapshot);
+        if pending.len() >= self.config.flush_rows {
+            let snapshots = std::mem::take(&mut *pending);
+            drop(pending);
+            self.write(&snapshots)?;
+        }
+        Ok(())
+    }
+
+    /// Up to `limit` of the latest snapshots kept in memory, oldest first.
+    pub fn recent(&self, limit: usize) -> Result<Vec<MetricsSnapshot>> {
+        let recent = self.recent.lock().map_err(|e| {
+            error!("Failed to lock recent metrics: {}", e);
+            Error::TaskExecution("Failed to access metrics history".to_string())
+        })?;
+        let skip = recent.len().saturating_sub(limit);
+        Ok(recent.iter().skip(skip).cloned().collect())
+    }
+
+    /// Write out the buffered snapshots, if any; returns the file written.
+    pub fn flush(&self) -> Result<Option<PathBuf>> {
+        let snapshots = std::mem::take(&mut *self.lock()?);
+        if snapshots.is_empty() {
+            return Ok(None);
+        }
+        self.write(&snapshots).map(Some)
+    }
+
+    fn write(&self, snapshots: &[MetricsSnapshot]) -> Result<PathBuf> {
+        fs::create_dir_all(&self.config.directory)?;
+        let path = self.config.directory.join(format!(
+            "metrics-{}-{}.{}",
+            snapshots[0].timestamp,
+            Uuid::new_v4().simple(),
+            self.config.format.extension()
+        ));
+        let batch = snapshot_batch(&self.schema, &self.config.custom_metrics, snapshots)
+            .map_err(history_error)?;
+        write_batch(&path, self.config.format, &batch)?;
+        Ok(path)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Vec<MetricsSnapshot>>> {
+        self.pending.lock().map_err(|e| {
+            error!("Failed to lock metrics history: {}", e);
+            Error::TaskExecution("Failed to access metrics history".to_string())
+        })
+    }
+}
+
+/// Write `batch` to a new file at `path` in `format`.
+pub fn write_batch(path: &Path, format: HistoryFormat, batch: &RecordBatch) -> Result<()> {
+    let file = File::create(path)?;
+    match format {
+        HistoryFormat::Arrow => {
+            let mut writer = FileWriter::try_new(file, &batch.schema()).map_err(history_error)?;
+            writer.write(batch).map_err(history_error)?;
+            writer.finish().map_err(history_error)?;
+        }
+        HistoryFormat::Parquet => {
+            let properties = WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build();
+            let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(properties))
+                .map_err(history_error)?;
+            writer.write(batch).map_err(history_error)?;
+            writer.close().map_err(history_error)?;
+        }
+    }
+    Ok(())
+}
+
+fn history_error(e: impl std::fmt::Display) -> Error {
+    Error::TaskExecution(format!("Failed to write metrics history: {}", e))
+}
+
+/// Snapshot the processor's metrics and flush the history every `period`.
+pub fn spawn_metrics_snapshots(
+    processor: Arc<SemanticTaskProcessor>,
+    history: Arc<MetricsHistory>,
+    period: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(period);
+        loop {
+            ticker.tick().await;
+            let history = Arc::clone(&history);
+            let processor = Arc::clone(&processor);
+            let snapshot = tokio::task::spawn_blocking(move || {
+                history.record(None, &processor.get_metrics())?;
+                history.flush()
+            })
+            .await;
+            match snapshot {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => error!("Metrics snapshot failed: {}", e),
+                Err(e) => error!("Metrics snapshot task panicked: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::MetricMap;
+    use datafusion::arrow::array::Array;
+    use datafusion::arrow::ipc::reader::FileReader;
+    use datafusion::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn metrics(v: f64, drift: Option<f64>) -> GeometricMetrics {
+        GeometricMetrics {
+            v_geometric: v,
+            s_geometric: 0.5,
+            q_oscillator: 2.0,
+            quaternion_coherence: 0.9,
+            emergent_electron_mass: 0.0,
+            fine_structure_constant: 0.0073,
+            zitterbewegung_entropy: 0.0,
+            topological_winding: 1.0,
+            custom_metrics: drift
+                .map(|d| MetricMap::from([("drift".to_string(), d)]))
+                .unwrap_or_default(),
+        }
+    }
+
+    #[test]
+    fn test_history_flushes_typed_columns() {
+        let directory = env::temp_dir().join(format!("mmss-metrics-history-{}", Uuid::new_v4()));
+        for format in [HistoryFormat::Arrow, HistoryFormat::Parquet] {
+            let history = MetricsHistory::new(MetricsHistoryConfig {
+                directory: directory.join(format.extension()),
+                format,
+                custom_metrics: vec!["drift".to_string()],
+                flush_rows: 2,
+                snapshot_period: None,
+                recent_snapshots: 2,
+            });
+            history
+                .record(Some(Uuid::new_v4()), &metrics(1.0, Some(0.1)))
+                .unwrap();
+            history.record(None, &metrics(2.0, None)).unwrap();
+            history.record(None, &metrics(3.0, None)).unwrap();
+            let last = history.flush().unwrap().unwrap();
+            assert!(history.flush().unwrap().is_none());
+
+            let recent = history.recent(10).unwrap();
+            assert_eq!(recent.len(), 2);
+            assert_eq!(recent[1].metrics.v_geometric, 3.0);
+
+            let files = fs::read_dir(history.directory()).unwrap().count();
+            assert_eq!(files, 2);
+            let batches: Vec<RecordBatch> = match format {
+                HistoryFormat::Arrow => FileReader::try_new(File::open(&last).unwrap(), None)
+                    .unwrap()
+                    .collect::<std::result::Result<_, _>>()
+                    .unwrap(),
+                HistoryFormat::Parquet => {
+                    ParquetRecordBatchReaderBuilder::try_new(File::open(&last).unwrap())
+                        .unwrap()
+                        .build()
+                        .unwrap()
+                        .collect::<std::result::Result<_, _>>()
+                        .unwrap()
+                }
+            };
+            assert_eq!(batches[0].num_rows(), 1);
+            assert_eq!(
+                batches[0].schema().fields().len(),
+                2 + METRIC_COLUMNS.len() + 1
+            );
+            let drift = batches[0]
+                .column_by_name("drift")
+                .unwrap()
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .unwrap();
+            assert!(drift.is_null(0));
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
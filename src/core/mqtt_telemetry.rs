@@ -0,0 +1,345 @@
+//! Geometric metrics and alerts of the default namespace published over
+//! MQTT, for lab dashboards that subscribe to a broker rather than poll
+//! the API. Needs the `mqtt` feature.
+//!
+//! Each selected metric goes to its own retained topic under the metrics
+//! prefix (`mmss/metrics/quaternion_coherence`, ...) once per period, so a
+//! dashboard subscribing to `mmss/metrics/#` sees the latest values at
+//! once. Alerts, the same ones `event_publisher` sends, go to the alert
+//! topic as JSON when they happen. Delivery is best effort: while the
+//! broker is unreachable metrics are dropped, the next period supersedes
+//! them, and alerts wait in the client's queue until it is full.
+
+use crate::config::MqttConfig;
+use crate::core::error::{Error, Result};
+use crate::core::event_publisher;
+use crate::core::events::{EventBus, StateEvent};
+use crate::core::semantic_task_processor::SemanticTaskProcessor;
+use crate::visualization::protocol::metric_values;
+use crate::GeometricMetrics;
+use chrono::Utc;
+use log::{info, warn};
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+const DEFAULT_PORT: u16 = 1883;
+const DEFAULT_CLIENT_ID: &str = "mmss";
+const DEFAULT_METRICS_TOPIC: &str = "mmss/metrics";
+const DEFAULT_ALERT_TOPIC: &str = "mmss/alerts";
+const DEFAULT_PERIOD: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttTelemetryConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    /// Prefix of the per-metric topics.
+    pub metrics_topic: String,
+    pub alert_topic: String,
+    /// Built-in or custom metrics to publish; all of them when empty.
+    pub metrics: Vec<String>,
+    /// Between two publications of the metrics.
+    pub period: Duration,
+}
+
+impl MqttTelemetryConfig {
+    /// Enabled by `mqtt.broker` (`host` or `host:port`, port 1883 by
+    /// default); the client ID defaults to `mmss`, the topics to
+    /// `mmss/metrics` and `mmss/alerts`, and `mqtt.period_ms`, how often
+    /// the metrics listed in `mqtt.metrics` are published, to 1000.
+    pub fn from_config(config: &MqttConfig) -> Option<Self> {
+        let (host, port) = parse_broker(config.broker.as_deref()?)?;
+        let or = |value: &Option<String>, default: &str| {
+            value.clone().unwrap_or_else(|| default.to_string())
+        };
+        Some(Self {
+            host,
+            port,
+            client_id: or(&config.client_id, DEFAULT_CLIENT_ID),
+            metrics_topic: or(&config.metrics_topic, DEFAULT_METRICS_TOPIC),
+            alert_topic: or(&config.alert_topic, DEFAULT_ALERT_TOPIC),
+            metrics: config
+                .metrics
+                .as_deref()
+                .map(parse_metrics)
+                .unwrap_or_default(),
+            period: config
+                .period_ms
+                .filter(|&ms| ms > 0)
+                .map_or(DEFAULT_PERIOD, Duration::from_millis),
+        })
+    }
+}
+
+impl fmt::Display for MqttTelemetryConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MQTT at {}:{}", self.host, self.port)
+    }
+}
+
+/// `host` or `host:port`; `None` when the port is not a number.
+pub fn parse_broker(raw: &str) -> Option<(String, u16)> {
+    let raw = raw.trim().trim_start_matches("mqtt://");
+    let (host, port) = match raw.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (raw, DEFAULT_PORT),
+    };
+    (!host.is_empty()).then(|| (host.to_string(), port))
+}
+
+/// Metric names from a comma-separated list.
+pub fn parse_metrics(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttMessage {
+    pub topic: String,
+    pub payload: String,
+    /// Kept by the broker for clients subscribing later.
+    pub retain: bool,
+}
+
+pub trait MqttPublisher: Send + Sync {
+    /// Queues `message` for the broker, failing when the queue is full.
+    fn publish(&self, message: &MqttMessage) -> Result<()>;
+}
+
+/// One retained message per selected metric of `metrics`, the value as a
+/// plain number. Selected metrics that `metrics` lacks are skipped.
+pub fn metric_messages(
+    metrics: &GeometricMetrics,
+    config: &MqttTelemetryConfig,
+) -> Vec<MqttMessage> {
+    let mut values = metric_values(metrics);
+    if !config.metrics.is_empty() {
+        values.retain(|name, _| config.metrics.contains(name));
+    }
+    values
+        .into_iter()
+        .map(|(name, value)| MqttMessage {
+            topic: format!("{}/{}", config.metrics_topic, name),
+            payload: value.to_string(),
+            retain: true,
+        })
+        .collect()
+}
+
+/// The alert `event` raises, as `event_publisher` sends it.
+pub fn alert_message(event: &StateEvent, config: &MqttTelemetryConfig) -> Option<MqttMessage> {
+    let (_, mut body) = event_publisher::alert(event)?;
+    if let Value::Object(body) = &mut body {
+        body.insert(
+            "timestamp".to_string(),
+            Utc::now().timestamp_millis().into(),
+        );
+    }
+    Some(MqttMessage {
+        topic: config.alert_topic.clone(),
+        payload: body.to_string(),
+        retain: false,
+    })
+}
+
+/// A client for `config`'s broker, failing when the server was built
+/// without the `mqtt` feature. It connects, and reconnects, in the
+/// background.
+pub fn connect(config: &MqttTelemetryConfig) -> Result<Arc<dyn MqttPublisher>> {
+    #[cfg(feature = "mqtt")]
+    {
+        Ok(Arc::new(rumqtt::RumqttPublisher::connect(config)))
+    }
+    #[cfg(not(feature = "mqtt"))]
+    {
+        Err(Error::Config(format!(
+            "Publishing to {} needs the server built with the mqtt feature",
+            config
+        )))
+    }
+}
+
+/// Publish the processor's metrics every period, and the alerts of
+/// `events` as they come.
+pub fn spawn_mqtt_telemetry(
+    processor: Arc<SemanticTaskProcessor>,
+    events: &EventBus,
+    publisher: Arc<dyn MqttPublisher>,
+    config: MqttTelemetryConfig,
+) -> JoinHandle<()> {
+    let mut receiver = events.subscribe();
+    tokio::spawn(async move {
+        info!("Publishing telemetry to {}", config);
+        let mut ticker = tokio::time::interval(config.period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            let messages = tokio::select! {
+                _ = ticker.tick() => metric_messages(&processor.metrics(), &config),
+                event = receiver.recv() => match event {
+                    Ok(event) => alert_message(&event, &config).into_iter().collect(),
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("MQTT telemetry fell behind; {} events were skipped", missed);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return,
+                },
+            };
+            for message in messages {
+                if let Err(e) = publisher.publish(&message) {
+                    warn!("Dropped MQTT message to {}: {}", message.topic, e);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(feature = "mqtt")]
+mod rumqtt {
+    use super::{Error, MqttMessage, MqttPublisher, MqttTelemetryConfig};
+    use crate::core::error::Result;
+    use log::warn;
+    use rumqttc::{AsyncClient, ConnectionError, MqttOptions, QoS};
+    use std::time::Duration;
+
+    /// Messages the client holds while the broker is unreachable.
+    const QUEUE_CAPACITY: usize = 256;
+    const KEEP_ALIVE: Duration = Duration::from_secs(30);
+    const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+    pub struct RumqttPublisher {
+        client: AsyncClient,
+    }
+
+    impl RumqttPublisher {
+        /// Spawns the connection's event loop, which reconnects after a
+        /// failure and ends once the publisher is dropped.
+        pub fn connect(config: &MqttTelemetryConfig) -> Self {
+            let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+            options.set_keep_alive(KEEP_ALIVE);
+            let (client, mut event_loop) = AsyncClient::new(options, QUEUE_CAPACITY);
+            let broker = config.to_string();
+            tokio::spawn(async move {
+                loop {
+                    match event_loop.poll().await {
+                        Ok(_) => {}
+                        Err(ConnectionError::RequestsDone) => return,
+                        Err(e) => {
+                            warn!(
+                                "Lost {}, reconnecting in {:?}: {}",
+                                broker, RECONNECT_DELAY, e
+                            );
+                            tokio::time::sleep(RECONNECT_DELAY).await;
+                        }
+                    }
+                }
+            });
+            Self { client }
+        }
+    }
+
+    impl MqttPublisher for RumqttPublisher {
+        fn publish(&self, message: &MqttMessage) -> Result<()> {
+            self.client
+                .try_publish(
+                    &message.topic,
+                    QoS::AtLeastOnce,
+                    message.retain,
+                    message.payload.as_bytes(),
+                )
+                .map_err(|e| Error::EventPublish(format!("MQTT: {}", e)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::semantic_task_processor::TaskStatus;
+    use crate::state::PhysicalConstants;
+    use mmss_compute::emergence::baseline_metrics;
+    use uuid::Uuid;
+
+    fn config(metrics: &[&str]) -> MqttTelemetryConfig {
+        MqttTelemetryConfig {
+            host: "localhost".to_string(),
+            port: DEFAULT_PORT,
+            client_id: DEFAULT_CLIENT_ID.to_string(),
+            metrics_topic: "lab/mmss".to_string(),
+            alert_topic: "lab/alerts".to_string(),
+            metrics: metrics.iter().map(|name| name.to_string()).collect(),
+            period: DEFAULT_PERIOD,
+        }
+    }
+
+    #[test]
+    fn test_selected_metrics_get_retained_topics() {
+        let mut metrics = GeometricMetrics {
+            quaternion_coherence: 0.5,
+            ..baseline_metrics(&PhysicalConstants::SI)
+        };
+        metrics.custom_metrics.insert("drift".to_string(), 2.0);
+
+        let messages = metric_messages(
+            &metrics,
+            &config(&["quaternion_coherence", "drift", "absent"]),
+        );
+        assert_eq!(
+            messages,
+            vec![
+                MqttMessage {
+                    topic: "lab/mmss/drift".to_string(),
+                    payload: "2".to_string(),
+                    retain: true,
+                },
+                MqttMessage {
+                    topic: "lab/mmss/quaternion_coherence".to_string(),
+                    payload: "0.5".to_string(),
+                    retain: true,
+                },
+            ]
+        );
+        assert_eq!(metric_messages(&metrics, &config(&[])).len(), 9);
+
+        assert_eq!(parse_broker("broker"), Some(("broker".to_string(), 1883)));
+        assert_eq!(
+            parse_broker("mqtt://10.0.0.5:1884"),
+            Some(("10.0.0.5".to_string(), 1884))
+        );
+        assert_eq!(parse_broker("broker:mqtt"), None);
+        assert_eq!(parse_metrics(" a, ,b "), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_only_alerts_are_published_from_events() {
+        let config = config(&[]);
+        let task_id = Uuid::new_v4();
+        let failed = alert_message(
+            &StateEvent::TaskTransition {
+                task_id,
+                status: TaskStatus::Failed("boom".to_string()),
+            },
+            &config,
+        )
+        .unwrap();
+        assert_eq!(failed.topic, "lab/alerts");
+        assert!(!failed.retain);
+        let payload: Value = serde_json::from_str(&failed.payload).unwrap();
+        assert_eq!(payload["alert"], "task_failed");
+        assert_eq!(payload["detail"], "boom");
+        assert!(payload["timestamp"].is_i64());
+
+        let running = StateEvent::TaskTransition {
+            task_id,
+            status: TaskStatus::InProgress,
+        };
+        assert!(alert_message(&running, &config).is_none());
+    }
+}
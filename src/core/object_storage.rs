@@ -0,0 +1,191 @@
+use crate::core::error::{Error, Result};
+use object_store::aws::{AmazonS3Builder, AmazonS3ConfigKey};
+use object_store::gcp::{GoogleCloudStorageBuilder, GoogleConfigKey};
+use object_store::path::Path as ObjectPath;
+use object_store::{BackoffConfig, ObjectStore, RetryConfig, WriteMultipart};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// S3 requires every part but the last to be at least 5 MiB.
+const MIN_PART_SIZE: usize = 5 << 20;
+
+/// Concurrent part uploads per object.
+const MAX_PARTS_IN_FLIGHT: usize = 4;
+
+/// Settings for export destinations in object storage. Credentials not given
+/// in `options` come from the usual `AWS_*` / `GOOGLE_*` environment.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ObjectStoreConfig {
+    /// Builder options such as `aws_access_key_id`, `aws_region` or
+    /// `google_service_account`, applied to the matching scheme.
+    pub options: HashMap<String, String>,
+    /// Retries of a request that failed with a transient (5xx or network) error.
+    pub max_retries: usize,
+    /// Give up retrying a request after this long, in milliseconds.
+    pub retry_timeout_ms: u64,
+    /// Bytes per multipart upload part; smaller objects are written in one request.
+    pub part_size: usize,
+}
+
+impl Default for ObjectStoreConfig {
+    fn default() -> Self {
+        Self {
+            options: HashMap::new(),
+            max_retries: 10,
+            retry_timeout_ms: 180_000,
+            part_size: 10 << 20,
+        }
+    }
+}
+
+impl ObjectStoreConfig {
+    /// The JSON file at `MMSS_OBJECT_STORE_CONFIG`, or the defaults.
+    pub fn from_env() -> Result<Self> {
+        match env::var("MMSS_OBJECT_STORE_CONFIG") {
+            Ok(path) => Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    fn retry(&self) -> RetryConfig {
+        RetryConfig {
+            backoff: BackoffConfig::default(),
+            max_retries: self.max_retries,
+            retry_timeout: Duration::from_millis(self.retry_timeout_ms),
+        }
+    }
+
+    /// Resolve an `s3://bucket/key` or `gs://bucket/key` URI.
+    pub fn destination(&self, uri: &str) -> Result<ObjectDestination> {
+        let (scheme, rest) = uri
+            .split_once("://")
+            .ok_or_else(|| invalid_uri(uri, "expected scheme://bucket/key"))?;
+        let (bucket, key) = rest
+            .split_once('/')
+            .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+            .ok_or_else(|| invalid_uri(uri, "expected scheme://bucket/key"))?;
+        let path = ObjectPath::parse(key).map_err(|e| invalid_uri(uri, e))?;
+
+        let store: Arc<dyn ObjectStore> = match scheme {
+            "s3" | "s3a" => {
+                let mut builder = AmazonS3Builder::from_env()
+                    .with_bucket_name(bucket)
+                    .with_retry(self.retry());
+                for (key, value) in &self.options {
+                    let key: AmazonS3ConfigKey = key.parse().map_err(store_error)?;
+                    builder = builder.with_config(key, value);
+                }
+                Arc::new(builder.build().map_err(store_error)?)
+            }
+            "gs" => {
+                let mut builder = GoogleCloudStorageBuilder::from_env()
+                    .with_bucket_name(bucket)
+                    .with_retry(self.retry());
+                for (key, value) in &self.options {
+                    let key: GoogleConfigKey = key.parse().map_err(store_error)?;
+                    builder = builder.with_config(key, value);
+                }
+                Arc::new(builder.build().map_err(store_error)?)
+            }
+            _ => return Err(invalid_uri(uri, "only s3:// and gs:// are supported")),
+        };
+        Ok(ObjectDestination {
+            uri: uri.to_string(),
+            store,
+            path,
+        })
+    }
+}
+
+/// An object an export is uploaded to.
+pub struct ObjectDestination {
+    pub uri: String,
+    store: Arc<dyn ObjectStore>,
+    path: ObjectPath,
+}
+
+impl ObjectDestination {
+    /// Upload `bytes`, in parts of `part_size` once they exceed it. A failed
+    /// multipart upload is aborted so no parts are left behind.
+    pub async fn upload(&self, bytes: Vec<u8>, part_size: usize) -> Result<()> {
+        let part_size = part_size.max(MIN_PART_SIZE);
+        if bytes.len() <= part_size {
+            self.store
+                .put(&self.path, bytes.into())
+                .await
+                .map_err(store_error)?;
+            return Ok(());
+        }
+
+        let upload = self
+            .store
+            .put_multipart(&self.path)
+            .await
+            .map_err(store_error)?;
+        let mut writer = WriteMultipart::new_with_chunk_size(upload, part_size);
+        for part in bytes.chunks(part_size) {
+            if let Err(e) = writer.wait_for_capacity(MAX_PARTS_IN_FLIGHT).await {
+                writer.abort().await.ok();
+                return Err(store_error(e));
+            }
+            writer.write(part);
+        }
+        writer.finish().await.map_err(store_error)?;
+        Ok(())
+    }
+}
+
+fn invalid_uri(uri: &str, reason: impl std::fmt::Display) -> Error {
+    Error::InvalidParameter("destination".to_string(), format!("{}: {}", uri, reason))
+}
+
+fn store_error(e: object_store::Error) -> Error {
+    Error::TaskExecution(format!("Object store request failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_store::memory::InMemory;
+
+    #[test]
+    fn test_destination_rejects_unsupported_uris() {
+        let config = ObjectStoreConfig::default();
+        for uri in [
+            "/tmp/export.arrow",
+            "s3://bucket",
+            "s3://bucket/",
+            "ftp://host/file",
+        ] {
+            assert!(matches!(
+                config.destination(uri),
+                Err(Error::InvalidParameter(_, _))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload_round_trip() {
+        let store = Arc::new(InMemory::new());
+        let destination = ObjectDestination {
+            uri: "memory://exports/records.arrow".to_string(),
+            store: store.clone(),
+            path: ObjectPath::from("exports/records.arrow"),
+        };
+        let bytes: Vec<u8> = (0..2 * MIN_PART_SIZE + 123).map(|i| i as u8).collect();
+        destination.upload(bytes.clone(), 0).await.unwrap();
+
+        let stored = store
+            .get(&destination.path)
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        assert_eq!(stored.as_ref(), bytes.as_slice());
+    }
+}
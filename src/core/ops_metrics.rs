@@ -0,0 +1,334 @@
+//! Operational metrics: task durations and failures per operator, LLM
+//! provider latency and errors, and how often each rule is applied. One
+//! registry is shared by every namespace; it is rendered in the Prometheus
+//! text format by `GET /metrics/prometheus` and summarized by
+//! `GET /admin/stats`.
+
+use crate::core::error::{Error, Result};
+use crate::core::types::GeometricOperator;
+use log::error;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Upper bounds of the latency buckets, in seconds.
+const BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    /// Observations per bucket, the last one past every bound.
+    counts: [u64; BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        let bucket = BUCKETS
+            .iter()
+            .position(|&bound| secs <= bound)
+            .unwrap_or(BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.sum += secs;
+        self.count += 1;
+    }
+
+    fn mean_ms(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64 * 1000.0)
+    }
+
+    /// Upper bound of the bucket holding the `q` quantile; the last bound
+    /// when it lies past all of them.
+    fn quantile_ms(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = (q * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(BUCKETS[bucket.min(BUCKETS.len() - 1)] * 1000.0);
+            }
+        }
+        None
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(&self.counts) {
+            cumulative += count;
+            let _ = writeln!(out, "{name}_bucket{{{labels}le=\"{bound}\"}} {cumulative}");
+        }
+        let _ = writeln!(out, "{name}_bucket{{{labels}le=\"+Inf\"}} {}", self.count);
+        let labels = match labels.trim_end_matches(',') {
+            "" => String::new(),
+            labels => format!("{{{labels}}}"),
+        };
+        let _ = writeln!(out, "{name}_sum{labels} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{labels} {}", self.count);
+    }
+}
+
+#[derive(Debug, Default)]
+struct TaskCounters {
+    duration: Histogram,
+    completed: u64,
+    failed: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    tasks: BTreeMap<String, TaskCounters>,
+    llm_latency: Histogram,
+    llm_errors: u64,
+    rules: BTreeMap<String, u64>,
+}
+
+/// Tasks of one processor by state, counted when the metrics are read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct QueueDepth {
+    pub awaiting_approval: usize,
+    pub pending: usize,
+    pub in_progress: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStats {
+    pub completed: u64,
+    pub failed: u64,
+    pub failure_rate: f64,
+    pub mean_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LlmStats {
+    pub calls: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub mean_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+}
+
+/// Everything recorded so far; percentiles are bucket upper bounds.
+#[derive(Debug, Clone, Serialize)]
+pub struct OpsStats {
+    pub queue: QueueDepth,
+    /// By operator.
+    pub tasks: BTreeMap<String, TaskStats>,
+    pub llm: LlmStats,
+    /// Applications by rule name.
+    pub rules: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct OpsMetrics {
+    counters: Mutex<Counters>,
+}
+
+impl OpsMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A task that ran to completion or failure after `elapsed`.
+    pub fn record_task(&self, operator: GeometricOperator, elapsed: Duration, succeeded: bool) {
+        let Ok(mut counters) = self.lock() else {
+            return;
+        };
+        let task = counters.tasks.entry(operator_label(operator)).or_default();
+        task.duration.observe(elapsed);
+        if succeeded {
+            task.completed += 1;
+        } else {
+            task.failed += 1;
+        }
+    }
+
+    /// One provider call, retries counted separately.
+    pub fn record_llm_call(&self, elapsed: Duration, succeeded: bool) {
+        let Ok(mut counters) = self.lock() else {
+            return;
+        };
+        counters.llm_latency.observe(elapsed);
+        if !succeeded {
+            counters.llm_errors += 1;
+        }
+    }
+
+    pub fn record_rule_application(&self, name: &str) {
+        let Ok(mut counters) = self.lock() else {
+            return;
+        };
+        *counters.rules.entry(name.to_string()).or_default() += 1;
+    }
+
+    pub fn stats(&self, queue: QueueDepth) -> Result<OpsStats> {
+        let counters = self.lock()?;
+        let tasks = counters
+            .tasks
+            .iter()
+            .map(|(operator, task)| {
+                let total = task.completed + task.failed;
+                let stats = TaskStats {
+                    completed: task.completed,
+                    failed: task.failed,
+                    failure_rate: rate(task.failed, total),
+                    mean_ms: task.duration.mean_ms(),
+                    p50_ms: task.duration.quantile_ms(0.5),
+                    p95_ms: task.duration.quantile_ms(0.95),
+                };
+                (operator.clone(), stats)
+            })
+            .collect();
+        let latency = &counters.llm_latency;
+        Ok(OpsStats {
+            queue,
+            tasks,
+            llm: LlmStats {
+                calls: latency.count,
+                errors: counters.llm_errors,
+                error_rate: rate(counters.llm_errors, latency.count),
+                mean_ms: latency.mean_ms(),
+                p50_ms: latency.quantile_ms(0.5),
+                p95_ms: latency.quantile_ms(0.95),
+            },
+            rules: counters.rules.clone(),
+        })
+    }
+
+    /// The metrics in the Prometheus text exposition format.
+    pub fn render_prometheus(&self, queue: QueueDepth) -> Result<String> {
+        let counters = self.lock()?;
+        let mut out = String::new();
+
+        out.push_str("# HELP mmss_task_queue_depth Tasks not yet finished, by state.\n");
+        out.push_str("# TYPE mmss_task_queue_depth gauge\n");
+        for (state, depth) in [
+            ("awaiting_approval", queue.awaiting_approval),
+            ("pending", queue.pending),
+            ("in_progress", queue.in_progress),
+        ] {
+            let _ = writeln!(out, "mmss_task_queue_depth{{state=\"{state}\"}} {depth}");
+        }
+
+        out.push_str("# HELP mmss_task_duration_seconds Task execution time, by operator.\n");
+        out.push_str("# TYPE mmss_task_duration_seconds histogram\n");
+        for (operator, task) in &counters.tasks {
+            task.duration.render(
+                &mut out,
+                "mmss_task_duration_seconds",
+                &format!("operator=\"{operator}\","),
+            );
+        }
+        out.push_str("# HELP mmss_tasks_finished_total Finished tasks, by operator and outcome.\n");
+        out.push_str("# TYPE mmss_tasks_finished_total counter\n");
+        for (operator, task) in &counters.tasks {
+            for (outcome, count) in [("completed", task.completed), ("failed", task.failed)] {
+                let _ = writeln!(
+                    out,
+                    "mmss_tasks_finished_total{{operator=\"{operator}\",outcome=\"{outcome}\"}} {count}"
+                );
+            }
+        }
+
+        out.push_str("# HELP mmss_llm_request_duration_seconds LLM provider call latency.\n");
+        out.push_str("# TYPE mmss_llm_request_duration_seconds histogram\n");
+        counters
+            .llm_latency
+            .render(&mut out, "mmss_llm_request_duration_seconds", "");
+        out.push_str("# HELP mmss_llm_request_errors_total Failed LLM provider calls.\n");
+        out.push_str("# TYPE mmss_llm_request_errors_total counter\n");
+        let _ = writeln!(out, "mmss_llm_request_errors_total {}", counters.llm_errors);
+
+        out.push_str("# HELP mmss_rule_applications_total Rule applications, by rule.\n");
+        out.push_str("# TYPE mmss_rule_applications_total counter\n");
+        for (rule, count) in &counters.rules {
+            let _ = writeln!(
+                out,
+                "mmss_rule_applications_total{{rule=\"{}\"}} {count}",
+                escape_label(rule)
+            );
+        }
+        Ok(out)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Counters>> {
+        self.counters.lock().map_err(|e| {
+            error!("Failed to lock operational metrics: {}", e);
+            Error::TaskExecution("Failed to access operational metrics".to_string())
+        })
+    }
+}
+
+fn operator_label(operator: GeometricOperator) -> String {
+    format!("{:?}", operator)
+}
+
+fn rate(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
+
+/// Rule names come from API clients; keep them from breaking the format.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_and_prometheus_rendering() {
+        let ops = OpsMetrics::new();
+        let operator = GeometricOperator::QuaternionRotation;
+        ops.record_task(operator, Duration::from_millis(20), true);
+        ops.record_task(operator, Duration::from_millis(200), true);
+        ops.record_task(operator, Duration::from_millis(40), false);
+        ops.record_llm_call(Duration::from_millis(800), true);
+        ops.record_llm_call(Duration::from_secs(3), false);
+        ops.record_rule_application("boost \"v\"");
+        let queue = QueueDepth {
+            awaiting_approval: 1,
+            pending: 2,
+            in_progress: 0,
+        };
+
+        let stats = ops.stats(queue).unwrap();
+        let task = &stats.tasks["QuaternionRotation"];
+        assert_eq!((task.completed, task.failed), (2, 1));
+        assert!((task.failure_rate - 1.0 / 3.0).abs() < 1e-12);
+        assert_eq!(task.p50_ms, Some(50.0));
+        assert_eq!(task.p95_ms, Some(250.0));
+        assert_eq!((stats.llm.calls, stats.llm.errors), (2, 1));
+        assert_eq!(stats.llm.error_rate, 0.5);
+        assert_eq!(stats.rules["boost \"v\""], 1);
+
+        let text = ops.render_prometheus(queue).unwrap();
+        assert!(text.contains("mmss_task_queue_depth{state=\"pending\"} 2\n"));
+        assert!(text.contains(
+            "mmss_task_duration_seconds_bucket{operator=\"QuaternionRotation\",le=\"0.05\"} 2\n"
+        ));
+        assert!(
+            text.contains("mmss_task_duration_seconds_count{operator=\"QuaternionRotation\"} 3\n")
+        );
+        assert!(text.contains("mmss_llm_request_duration_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(text.contains("mmss_llm_request_errors_total 1\n"));
+        assert!(text.contains("mmss_rule_applications_total{rule=\"boost \\\"v\\\"\"} 1\n"));
+    }
+}
@@ -0,0 +1,460 @@
+//! Geometric operators, metric rules and export formats added by plugins:
+//! shared libraries built against `mmss-plugin-api`, loaded from
+//! `MMSS_PLUGIN_DIR` once at startup and never unloaded.
+//!
+//! Plugin operators run as `PluginOperator` tasks naming the operator in an
+//! `operator` parameter; plugin rules are registered in every namespace
+//! like rules posted to `/rules`; plugin formats are requested by name like
+//! the built-in ones. A plugin runs inside the server with its privileges,
+//! so the directory should only hold trusted libraries.
+
+use crate::core::error::{Error, Result};
+use crate::core::exports::ExportFormat;
+use crate::core::geometric_metrics::GeometricMetricEngine;
+use crate::core::types::GeometricMetrics;
+use abi_stable::library::lib_header_from_path;
+use abi_stable::std_types::{RResult, RString, RVec};
+use log::{info, warn};
+use mmss_core::structex_bridge::MmssRecord;
+use mmss_plugin_api::{OperatorRequest, OperatorResponse, PluginModuleRef};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+static REGISTRY: OnceLock<PluginRegistry> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PluginExportFormatInfo {
+    pub name: String,
+    pub extension: String,
+    pub content_type: String,
+}
+
+/// A loaded plugin, as `GET /plugins` lists it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PluginInfo {
+    pub name: String,
+    pub version: String,
+    /// Library it was loaded from; none for plugins registered in-process.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    pub operators: Vec<String>,
+    pub rules: Vec<String>,
+    pub export_formats: Vec<PluginExportFormatInfo>,
+}
+
+/// What the server calls a plugin through; implemented for loaded
+/// libraries, and by in-process fakes in tests.
+pub trait Plugin: Send + Sync {
+    fn info(&self) -> &PluginInfo;
+
+    /// The metrics after `operator` ran on `metrics`, and its output.
+    fn apply_operator(
+        &self,
+        operator: &str,
+        parameters: &Value,
+        metrics: &GeometricMetrics,
+    ) -> Result<(GeometricMetrics, Option<Value>)>;
+
+    fn apply_rule(&self, rule: &str, metrics: &GeometricMetrics) -> Result<GeometricMetrics>;
+
+    fn encode_records(&self, format: &str, records: &[MmssRecord]) -> Result<Vec<u8>>;
+}
+
+/// An export format of a plugin. Registered formats are leaked, as their
+/// libraries are, so `ExportFormat` can refer to them and stay `Copy`.
+pub struct PluginExportFormat {
+    pub name: String,
+    pub extension: String,
+    pub content_type: String,
+    plugin: Arc<dyn Plugin>,
+}
+
+impl PluginExportFormat {
+    pub fn encode(&self, records: &[MmssRecord]) -> Result<Vec<u8>> {
+        self.plugin.encode_records(&self.name, records)
+    }
+}
+
+impl PartialEq for PluginExportFormat {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for PluginExportFormat {}
+
+impl fmt::Debug for PluginExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PluginExportFormat")
+            .field("name", &self.name)
+            .field("plugin", &self.plugin.info().name)
+            .finish()
+    }
+}
+
+/// The operators, rules and formats of every loaded plugin, by name.
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: Vec<PluginInfo>,
+    operators: HashMap<String, Arc<dyn Plugin>>,
+    /// In load order, the order they are registered in.
+    rules: Vec<(String, Arc<dyn Plugin>)>,
+    export_formats: HashMap<String, &'static PluginExportFormat>,
+}
+
+impl PluginRegistry {
+    /// Every library in `directory` with the platform's extension (`.so`,
+    /// `.dylib` or `.dll`), loaded in file name order.
+    pub fn load_dir(directory: &Path) -> Result<Self> {
+        let unreadable = |e: std::io::Error| {
+            Error::Config(format!(
+                "Failed to read plugin directory {}: {}",
+                directory.display(),
+                e
+            ))
+        };
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(directory).map_err(unreadable)? {
+            let path = entry.map_err(unreadable)?.path();
+            if path
+                .extension()
+                .is_some_and(|ext| ext == env::consts::DLL_EXTENSION)
+            {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut registry = Self::default();
+        for path in paths {
+            registry.register(Arc::new(LibraryPlugin::load(&path)?))?;
+        }
+        Ok(registry)
+    }
+
+    /// The plugins in `MMSS_PLUGIN_DIR`, none when it is unset.
+    pub fn from_env() -> Result<Self> {
+        match env::var_os("MMSS_PLUGIN_DIR") {
+            Some(directory) => Self::load_dir(Path::new(&directory)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Add `plugin`, refusing it when one of its names is taken by a
+    /// built-in export format or an earlier plugin.
+    pub fn register(&mut self, plugin: Arc<dyn Plugin>) -> Result<()> {
+        let info = plugin.info().clone();
+        let taken = |kind: &str, name: &str| {
+            Err(Error::Config(format!(
+                "Plugin '{}' provides the {} '{}', which is already taken",
+                info.name, kind, name
+            )))
+        };
+        if let Some(operator) = info
+            .operators
+            .iter()
+            .find(|name| self.operators.contains_key(*name))
+        {
+            return taken("operator", operator);
+        }
+        if let Some(rule) = info
+            .rules
+            .iter()
+            .find(|name| self.rules.iter().any(|(taken, _)| taken == *name))
+        {
+            return taken("rule", rule);
+        }
+        if let Some(format) = info.export_formats.iter().find(|format| {
+            self.export_formats.contains_key(&format.name)
+                || ExportFormat::BUILTIN
+                    .iter()
+                    .any(|builtin| builtin.name() == format.name)
+        }) {
+            return taken("export format", &format.name);
+        }
+
+        for operator in &info.operators {
+            self.operators.insert(operator.clone(), plugin.clone());
+        }
+        for rule in &info.rules {
+            self.rules.push((rule.clone(), plugin.clone()));
+        }
+        for format in &info.export_formats {
+            let format: &'static PluginExportFormat = Box::leak(Box::new(PluginExportFormat {
+                name: format.name.clone(),
+                extension: format.extension.clone(),
+                content_type: format.content_type.clone(),
+                plugin: plugin.clone(),
+            }));
+            self.export_formats.insert(format.name.clone(), format);
+        }
+        info!(
+            "Loaded plugin {} {} ({} operators, {} rules, {} export formats)",
+            info.name,
+            info.version,
+            info.operators.len(),
+            info.rules.len(),
+            info.export_formats.len()
+        );
+        self.plugins.push(info);
+        Ok(())
+    }
+
+    pub fn plugins(&self) -> &[PluginInfo] {
+        &self.plugins
+    }
+
+    pub fn apply_operator(
+        &self,
+        operator: &str,
+        parameters: &Value,
+        metrics: &GeometricMetrics,
+    ) -> Result<(GeometricMetrics, Option<Value>)> {
+        let plugin = self.operators.get(operator).ok_or_else(|| {
+            Error::TaskExecution(format!("No plugin provides the operator '{}'", operator))
+        })?;
+        plugin.apply_operator(operator, parameters, metrics)
+    }
+
+    /// Register every plugin rule with `engine`. A rule that fails leaves
+    /// the metrics as they were.
+    pub fn register_rules(&self, engine: &mut GeometricMetricEngine) {
+        for (name, plugin) in &self.rules {
+            let (rule, plugin) = (name.clone(), plugin.clone());
+            engine.register_rule(
+                name.clone(),
+                move |metrics: &mut GeometricMetrics| match plugin.apply_rule(&rule, metrics) {
+                    Ok(updated) => *metrics = updated,
+                    Err(e) => warn!("Plugin rule '{}' failed: {}", rule, e),
+                },
+            );
+        }
+    }
+
+    pub fn export_format(&self, name: &str) -> Option<&'static PluginExportFormat> {
+        self.export_formats.get(name).copied()
+    }
+}
+
+/// Make `registry` the plugins of this process. Only once, before any
+/// state is built: namespaces pick up plugin rules when created.
+pub fn install(registry: PluginRegistry) -> Result<()> {
+    REGISTRY
+        .set(registry)
+        .map_err(|_| Error::Config("Plugins were already installed".to_string()))
+}
+
+/// The installed plugins; none if `install` was not called first.
+pub fn registry() -> &'static PluginRegistry {
+    REGISTRY.get_or_init(PluginRegistry::default)
+}
+
+/// A plugin library, checked against `mmss-plugin-api`'s layout on load.
+struct LibraryPlugin {
+    module: PluginModuleRef,
+    info: PluginInfo,
+}
+
+impl LibraryPlugin {
+    fn load(path: &Path) -> Result<Self> {
+        let module = lib_header_from_path(path)
+            .and_then(|header| header.init_root_module::<PluginModuleRef>())
+            .map_err(|e| {
+                Error::Config(format!("Failed to load plugin {}: {}", path.display(), e))
+            })?;
+        let manifest = module.manifest()();
+        let names = |names: RVec<RString>| names.into_iter().map(RString::into_string).collect();
+        let info = PluginInfo {
+            name: manifest.name.into_string(),
+            version: manifest.version.into_string(),
+            path: Some(path.to_path_buf()),
+            operators: names(manifest.operators),
+            rules: names(manifest.rules),
+            export_formats: manifest
+                .export_formats
+                .into_iter()
+                .map(|format| PluginExportFormatInfo {
+                    name: format.name.into_string(),
+                    extension: format.extension.into_string(),
+                    content_type: format.content_type.into_string(),
+                })
+                .collect(),
+        };
+        Ok(Self { module, info })
+    }
+
+    fn answer<T>(&self, call: &str, result: RResult<T, RString>) -> Result<T> {
+        result.into_result().map_err(|e| {
+            Error::TaskExecution(format!(
+                "Plugin '{}' failed {}: {}",
+                self.info.name, call, e
+            ))
+        })
+    }
+}
+
+impl Plugin for LibraryPlugin {
+    fn info(&self) -> &PluginInfo {
+        &self.info
+    }
+
+    fn apply_operator(
+        &self,
+        operator: &str,
+        parameters: &Value,
+        metrics: &GeometricMetrics,
+    ) -> Result<(GeometricMetrics, Option<Value>)> {
+        let request = serde_json::to_string(&OperatorRequest {
+            parameters: parameters.clone(),
+            metrics: serde_json::to_value(metrics)?,
+        })?;
+        let answer = self.module.apply_operator()(operator.into(), request.as_str().into());
+        let answer = self.answer(&format!("applying {}", operator), answer)?;
+        let response: OperatorResponse = serde_json::from_str(&answer)?;
+        Ok((serde_json::from_value(response.metrics)?, response.output))
+    }
+
+    fn apply_rule(&self, rule: &str, metrics: &GeometricMetrics) -> Result<GeometricMetrics> {
+        let metrics = serde_json::to_string(metrics)?;
+        let answer = self.module.apply_rule()(rule.into(), metrics.as_str().into());
+        let answer = self.answer(&format!("applying {}", rule), answer)?;
+        Ok(serde_json::from_str(&answer)?)
+    }
+
+    fn encode_records(&self, format: &str, records: &[MmssRecord]) -> Result<Vec<u8>> {
+        let records = serde_json::to_string(records)?;
+        let answer = self.module.encode_records()(format.into(), records.as_str().into());
+        Ok(self
+            .answer(&format!("encoding {}", format), answer)?
+            .into_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::exports::encode_records;
+    use serde_json::json;
+
+    /// Every operator doubles `v_geometric`, every rule zeroes
+    /// `q_oscillator`, every format writes record ids one per line.
+    struct FakePlugin(PluginInfo);
+
+    impl FakePlugin {
+        fn new(name: &str, operators: &[&str], rules: &[&str], formats: &[&str]) -> Arc<Self> {
+            Arc::new(Self(PluginInfo {
+                name: name.to_string(),
+                version: "0.1.0".to_string(),
+                path: None,
+                operators: operators.iter().map(|s| s.to_string()).collect(),
+                rules: rules.iter().map(|s| s.to_string()).collect(),
+                export_formats: formats
+                    .iter()
+                    .map(|name| PluginExportFormatInfo {
+                        name: name.to_string(),
+                        extension: "txt".to_string(),
+                        content_type: "text/plain".to_string(),
+                    })
+                    .collect(),
+            }))
+        }
+    }
+
+    impl Plugin for FakePlugin {
+        fn info(&self) -> &PluginInfo {
+            &self.0
+        }
+
+        fn apply_operator(
+            &self,
+            _operator: &str,
+            parameters: &Value,
+            metrics: &GeometricMetrics,
+        ) -> Result<(GeometricMetrics, Option<Value>)> {
+            let mut metrics = metrics.clone();
+            metrics.v_geometric *= 2.0;
+            Ok((metrics, Some(parameters.clone())))
+        }
+
+        fn apply_rule(&self, _rule: &str, metrics: &GeometricMetrics) -> Result<GeometricMetrics> {
+            let mut metrics = metrics.clone();
+            metrics.q_oscillator = 0.0;
+            Ok(metrics)
+        }
+
+        fn encode_records(&self, _format: &str, records: &[MmssRecord]) -> Result<Vec<u8>> {
+            Ok(records
+                .iter()
+                .map(|record| format!("{}\n", record.id))
+                .collect::<String>()
+                .into_bytes())
+        }
+    }
+
+    fn metrics() -> GeometricMetrics {
+        serde_json::from_value(json!({
+            "v_geometric": 0.25,
+            "s_geometric": 0.5,
+            "q_oscillator": 3.0,
+            "custom_metrics": {},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_plugin_operators_rules_and_formats() {
+        let mut registry = PluginRegistry::default();
+        registry
+            .register(FakePlugin::new("fake", &["Double"], &["zero_q"], &["ids"]))
+            .unwrap();
+
+        let parameters = json!({ "operator": "Double" });
+        let (updated, output) = registry
+            .apply_operator("Double", &parameters, &metrics())
+            .unwrap();
+        assert_eq!(updated.v_geometric, 0.5);
+        assert_eq!(output, Some(parameters.clone()));
+        assert!(registry
+            .apply_operator("Triple", &parameters, &metrics())
+            .is_err());
+
+        let mut engine = GeometricMetricEngine::new();
+        registry.register_rules(&mut engine);
+        let mut ruled = metrics();
+        assert!(engine.apply_rule("zero_q", &mut ruled));
+        assert_eq!(ruled.q_oscillator, 0.0);
+
+        let format = ExportFormat::Plugin(registry.export_format("ids").unwrap());
+        assert_eq!(format.extension(), "txt");
+        let records = mmss_core::datagen::generate(&mmss_core::datagen::DatagenConfig {
+            count: 2,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(encode_records(&records, format).unwrap(), b"0\n1\n");
+    }
+
+    #[test]
+    fn test_refuses_taken_names() {
+        let mut registry = PluginRegistry::default();
+        registry
+            .register(FakePlugin::new("first", &["Double"], &["zero_q"], &[]))
+            .unwrap();
+        assert!(registry
+            .register(FakePlugin::new("second", &["Double"], &[], &[]))
+            .is_err());
+        assert!(registry
+            .register(FakePlugin::new("third", &[], &[], &["csv"]))
+            .is_err());
+        assert!(registry
+            .register(FakePlugin::new("fourth", &[], &["zero_q"], &[]))
+            .is_err());
+        assert_eq!(registry.plugins().len(), 1);
+    }
+}
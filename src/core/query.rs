@@ -0,0 +1,322 @@
+use crate::core::error::{Error, Result};
+use crate::core::metrics_history::{HistoryFormat, MetricsHistory};
+use crate::core::types::GeometricMetrics;
+use datafusion::arrow::array::{Float64Array, Int64Array, StringArray, UInt64Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use datafusion::arrow::error::ArrowError;
+use datafusion::arrow::ipc::writer::StreamWriter;
+use datafusion::arrow::json::ArrayWriter;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::execution::context::SQLOptions;
+use datafusion::execution::options::{ArrowReadOptions, ParquetReadOptions};
+use datafusion::prelude::SessionContext;
+use mmss_core::structex_bridge::MmssRecord;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Encoding of query results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryFormat {
+    /// JSON array of row objects
+    #[default]
+    Json,
+    /// Arrow IPC stream
+    Arrow,
+}
+
+/// Result batches of a query, with the schema for when there are none.
+pub struct QueryResult {
+    pub schema: SchemaRef,
+    pub batches: Vec<RecordBatch>,
+}
+
+impl QueryResult {
+    pub fn num_rows(&self) -> usize {
+        self.batches.iter().map(RecordBatch::num_rows).sum()
+    }
+
+    pub fn encode(&self, format: QueryFormat) -> Result<Vec<u8>> {
+        self.try_encode(format)
+            .map_err(|e| Error::TaskExecution(format!("Failed to encode query result: {}", e)))
+    }
+
+    fn try_encode(&self, format: QueryFormat) -> std::result::Result<Vec<u8>, ArrowError> {
+        match format {
+            QueryFormat::Json => {
+                let mut writer = ArrayWriter::new(Vec::new());
+                for batch in &self.batches {
+                    writer.write(batch)?;
+                }
+                writer.finish()?;
+                Ok(writer.into_inner())
+            }
+            QueryFormat::Arrow => {
+                let mut writer = StreamWriter::try_new(Vec::new(), &self.schema)?;
+                for batch in &self.batches {
+                    writer.write(batch)?;
+                }
+                writer.finish()?;
+                writer.into_inner()
+            }
+        }
+    }
+}
+
+/// Read-only SQL over the export directory and the live server state.
+///
+/// Tables:
+/// - `exports_arrow`, `exports_parquet`: every `.arrow` / `.parquet` export
+///   in the directory, registered only when at least one exists
+/// - `tasks`: the current task records, as they would be exported
+/// - `metrics`: the current geometric metrics as `(name, value)` rows,
+///   custom metrics included
+/// - `metrics_history`: the snapshots written by `MetricsHistory`, when a
+///   history directory is given and holds any
+pub struct QueryEngine {
+    export_directory: PathBuf,
+    metrics_history: Option<(PathBuf, HistoryFormat)>,
+}
+
+impl QueryEngine {
+    pub fn new(export_directory: PathBuf) -> Self {
+        Self {
+            export_directory,
+            metrics_history: None,
+        }
+    }
+
+    pub fn with_metrics_history(mut self, history: &MetricsHistory) -> Self {
+        self.metrics_history = Some((history.directory().to_path_buf(), history.format()));
+        self
+    }
+
+    /// Run one statement; DDL, DML and `SET`-style statements are rejected.
+    pub async fn run(
+        &self,
+        sql: &str,
+        tasks: &[MmssRecord],
+        metrics: &GeometricMetrics,
+    ) -> Result<QueryResult> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("tasks", records_batch(tasks)?)
+            .map_err(query_error)?;
+        ctx.register_batch("metrics", metrics_batch(metrics)?)
+            .map_err(query_error)?;
+
+        let directory = self.export_directory.to_string_lossy();
+        if has_files(&self.export_directory, "parquet") {
+            ctx.register_parquet("exports_parquet", &directory, ParquetReadOptions::default())
+                .await
+                .map_err(query_error)?;
+        }
+        if has_files(&self.export_directory, "arrow") {
+            ctx.register_arrow("exports_arrow", &directory, ArrowReadOptions::default())
+                .await
+                .map_err(query_error)?;
+        }
+
+        if let Some((history, format)) = &self.metrics_history {
+            if has_files(history, format.extension()) {
+                let history = history.to_string_lossy();
+                let registered = match format {
+                    HistoryFormat::Arrow => {
+                        let options = ArrowReadOptions::default();
+                        ctx.register_arrow("metrics_history", &history, options)
+                            .await
+                    }
+                    HistoryFormat::Parquet => {
+                        let options = ParquetReadOptions::default();
+                        ctx.register_parquet("metrics_history", &history, options)
+                            .await
+                    }
+                };
+                registered.map_err(query_error)?;
+            }
+        }
+
+        let options = SQLOptions::new()
+            .with_allow_ddl(false)
+            .with_allow_dml(false)
+            .with_allow_statements(false);
+        let frame = ctx
+            .sql_with_options(sql, options)
+            .await
+            .map_err(|e| Error::InvalidParameter("sql".to_string(), e.to_string()))?;
+        let schema = frame.schema().inner().clone();
+        let batches = frame.collect().await.map_err(query_error)?;
+        Ok(QueryResult { schema, batches })
+    }
+}
+
+fn has_files(directory: &Path, extension: &str) -> bool {
+    std::fs::read_dir(directory)
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|entry| entry.path().extension().is_some_and(|e| e == extension))
+        })
+        .unwrap_or(false)
+}
+
+/// The columns of the Arrow export schema.
+fn records_batch(records: &[MmssRecord]) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("id", DataType::UInt64, false),
+        Field::new("kind", DataType::Utf8, false),
+        Field::new("timestamp", DataType::Int64, false),
+        Field::new("payload", DataType::Utf8, false),
+    ]);
+    let payloads = records
+        .iter()
+        .map(|r| serde_json::to_string(&r.payload))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            Arc::new(UInt64Array::from_iter_values(records.iter().map(|r| r.id))),
+            Arc::new(StringArray::from_iter_values(
+                records.iter().map(|r| &r.kind),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                records.iter().map(|r| r.timestamp),
+            )),
+            Arc::new(StringArray::from_iter_values(payloads)),
+        ],
+    )
+    .map_err(|e| Error::TaskExecution(format!("Failed to build task table: {}", e)))
+}
+
+fn metrics_batch(metrics: &GeometricMetrics) -> Result<RecordBatch> {
+    let mut rows = vec![
+        ("v_geometric", metrics.v_geometric),
+        ("s_geometric", metrics.s_geometric),
+        ("q_oscillator", metrics.q_oscillator),
+        ("quaternion_coherence", metrics.quaternion_coherence),
+        ("emergent_electron_mass", metrics.emergent_electron_mass),
+        ("fine_structure_constant", metrics.fine_structure_constant),
+        ("zitterbewegung_entropy", metrics.zitterbewegung_entropy),
+        ("topological_winding", metrics.topological_winding),
+    ];
+    let mut custom: Vec<_> = metrics
+        .custom_metrics
+        .iter()
+        .map(|(name, value)| (name.as_str(), *value))
+        .collect();
+    custom.sort_by(|a, b| a.0.cmp(b.0));
+    rows.extend(custom);
+
+    let schema = Schema::new(vec![
+        Field::new("name", DataType::Utf8, false),
+        Field::new("value", DataType::Float64, false),
+    ]);
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0))),
+            Arc::new(Float64Array::from_iter_values(rows.iter().map(|r| r.1))),
+        ],
+    )
+    .map_err(|e| Error::TaskExecution(format!("Failed to build metrics table: {}", e)))
+}
+
+fn query_error(e: datafusion::error::DataFusionError) -> Error {
+    Error::TaskExecution(format!("Query failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::exports::{write_records, ExportFormat};
+    use crate::core::types::MetricMap;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn metrics() -> GeometricMetrics {
+        GeometricMetrics {
+            v_geometric: 1.0,
+            s_geometric: 0.5,
+            q_oscillator: 2.0,
+            quaternion_coherence: 0.9,
+            emergent_electron_mass: 0.0,
+            fine_structure_constant: 0.0073,
+            zitterbewegung_entropy: 0.0,
+            topological_winding: 1.0,
+            custom_metrics: MetricMap::from([("drift".to_string(), 0.25)]),
+        }
+    }
+
+    fn records(n: u64) -> Vec<MmssRecord> {
+        (0..n)
+            .map(|id| MmssRecord {
+                id,
+                kind: if id % 2 == 0 { "even" } else { "odd" }.to_string(),
+                timestamp: id as i64 * 1_000,
+                payload: json!({ "n": id }),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_queries_live_tables_and_exports() {
+        let directory = std::env::temp_dir().join(format!("mmss-query-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        write_records(
+            &directory.join("a.parquet"),
+            &records(6),
+            ExportFormat::Parquet,
+        )
+        .unwrap();
+        write_records(&directory.join("b.arrow"), &records(4), ExportFormat::Arrow).unwrap();
+        let engine = QueryEngine::new(directory.clone());
+
+        let result = engine
+            .run(
+                "SELECT kind, COUNT(*) AS n FROM tasks GROUP BY kind ORDER BY kind",
+                &records(5),
+                &metrics(),
+            )
+            .await
+            .unwrap();
+        let rows: serde_json::Value =
+            serde_json::from_slice(&result.encode(QueryFormat::Json).unwrap()).unwrap();
+        assert_eq!(
+            rows,
+            json!([{ "kind": "even", "n": 3 }, { "kind": "odd", "n": 2 }])
+        );
+
+        let result = engine
+            .run(
+                "SELECT value FROM metrics WHERE name = 'drift'",
+                &[],
+                &metrics(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.num_rows(), 1);
+
+        for (table, expected) in [("exports_parquet", 6), ("exports_arrow", 4)] {
+            let sql = format!("SELECT * FROM {} WHERE timestamp >= 0", table);
+            let result = engine.run(&sql, &[], &metrics()).await.unwrap();
+            assert_eq!(result.num_rows(), expected);
+            assert!(!result.encode(QueryFormat::Arrow).unwrap().is_empty());
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejects_statements_that_modify_state() {
+        let engine = QueryEngine::new(std::env::temp_dir().join("mmss-query-missing"));
+        for sql in [
+            "CREATE TABLE t AS SELECT 1",
+            "INSERT INTO tasks VALUES (1, 'x', 0, '{}')",
+            "SET datafusion.execution.batch_size = 1",
+        ] {
+            assert!(matches!(
+                engine.run(sql, &[], &metrics()).await,
+                Err(Error::InvalidParameter(_, _))
+            ));
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Binary array interchange with script subprocesses.
+//!
+//! Arrays travel as `.npy` files (format 1.0, little-endian `f8`, C order)
+//! in the script's working directory, so lattice fields and event samples
+//! never go through JSON. The prelude maps them read-only: `numpy.load`
+//! with `mmap_mode="r"` when NumPy is importable, a shaped `memoryview`
+//! otherwise.
+
+use crate::core::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+/// Header plus preamble is padded to a multiple of this, as NumPy does.
+const ALIGNMENT: usize = 64;
+
+/// Dense `f64` array in row-major order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptArray {
+    pub shape: Vec<usize>,
+    pub data: Vec<f64>,
+}
+
+impl ScriptArray {
+    pub fn new(shape: Vec<usize>, data: Vec<f64>) -> Result<Self> {
+        if shape.iter().product::<usize>() != data.len() {
+            return Err(Error::InvalidParameter(
+                "shape".to_string(),
+                format!("{:?} does not hold {} values", shape, data.len()),
+            ));
+        }
+        Ok(Self { shape, data })
+    }
+
+    pub fn vector(data: Vec<f64>) -> Self {
+        Self {
+            shape: vec![data.len()],
+            data,
+        }
+    }
+
+    pub fn write_npy(&self, path: &Path) -> Result<()> {
+        let shape = match self.shape.as_slice() {
+            [single] => format!("({},)", single),
+            dims => format!(
+                "({})",
+                dims.iter()
+                    .map(usize::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let mut header = format!(
+            "{{'descr': '<f8', 'fortran_order': False, 'shape': {}, }}",
+            shape
+        );
+        let preamble = MAGIC.len() + 4;
+        let padded = (preamble + header.len() + 1).div_ceil(ALIGNMENT) * ALIGNMENT;
+        header.push_str(&" ".repeat(padded - preamble - header.len() - 1));
+        header.push('\n');
+
+        let mut bytes = Vec::with_capacity(padded + 8 * self.data.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        for value in &self.data {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Read a version 1.x/2.x `.npy` file of little-endian `f8` in C order.
+    pub fn read_npy(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid =
+            |reason: &str| Error::InvalidParameter(path.display().to_string(), reason.to_string());
+        if bytes.len() < MAGIC.len() + 4 || !bytes.starts_with(MAGIC) {
+            return Err(invalid("not an .npy file"));
+        }
+        let (header_len, offset) = match bytes[MAGIC.len()] {
+            1 => (
+                u16::from_le_bytes([bytes[8], bytes[9]]) as usize,
+                MAGIC.len() + 4,
+            ),
+            2 | 3 if bytes.len() >= MAGIC.len() + 6 => (
+                u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+                MAGIC.len() + 6,
+            ),
+            _ => return Err(invalid("unsupported .npy version")),
+        };
+        let header = bytes
+            .get(offset..offset + header_len)
+            .and_then(|raw| std::str::from_utf8(raw).ok())
+            .ok_or_else(|| invalid("truncated header"))?;
+        if !header.contains("'descr': '<f8'") {
+            return Err(invalid("only little-endian float64 arrays are supported"));
+        }
+        if header.contains("'fortran_order': True") {
+            return Err(invalid("Fortran-ordered arrays are not supported"));
+        }
+        let shape = parse_shape(header).ok_or_else(|| invalid("unreadable shape"))?;
+
+        let body = &bytes[offset + header_len..];
+        if body.len() % 8 != 0 {
+            return Err(invalid("data is not a whole number of float64 values"));
+        }
+        let data = body
+            .chunks_exact(8)
+            .map(|chunk| f64::from_le_bytes(chunk.try_into().expect("chunk of 8")))
+            .collect();
+        Self::new(shape, data)
+    }
+}
+
+/// The `(a, b, ...)` tuple after `'shape':` in an `.npy` header.
+fn parse_shape(header: &str) -> Option<Vec<usize>> {
+    let start = header.find("'shape':")? + "'shape':".len();
+    let rest = &header[start..];
+    let open = rest.find('(')?;
+    let close = rest.find(')')?;
+    rest[open + 1..close]
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse().ok())
+        .collect()
+}
+
+/// Array names become file names and Python dict keys.
+pub fn valid_array_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_npy_round_trip_and_layout() {
+        let dir = std::env::temp_dir().join(format!("mmss-npy-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("field.npy");
+
+        let array = ScriptArray::new(vec![2, 3], vec![1.0, 2.0, 3.0, 4.0, 5.5, -6.0]).unwrap();
+        array.write_npy(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!((bytes.len() - 48) % ALIGNMENT, 0);
+        assert!(std::str::from_utf8(&bytes[10..bytes.len() - 48])
+            .unwrap()
+            .contains("'shape': (2, 3)"));
+        assert_eq!(ScriptArray::read_npy(&path).unwrap(), array);
+
+        let vector = ScriptArray::vector(vec![0.25; 5]);
+        vector.write_npy(&path).unwrap();
+        assert_eq!(ScriptArray::read_npy(&path).unwrap(), vector);
+
+        fs::write(&path, b"not numpy").unwrap();
+        assert!(ScriptArray::read_npy(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_shape_must_match_data() {
+        assert!(ScriptArray::new(vec![2, 2], vec![1.0; 3]).is_err());
+        assert!(valid_array_name("hopfion_q"));
+        assert!(!valid_array_name("../etc"));
+    }
+}
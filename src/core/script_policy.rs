@@ -0,0 +1,64 @@
+use crate::core::types::{GeometricOperator, GeometricTaskCommand};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+
+/// Parameter keys that carry executable source.
+const SCRIPT_KEYS: &[&str] = &["script", "code", "source"];
+
+/// How commands that carry executable code are admitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptPolicy {
+    /// Refuse script-bearing commands outright.
+    Reject,
+    /// Hold them in `TaskStatus::AwaitingApproval` until `POST /tasks/:id/approve`.
+    #[default]
+    RequireApproval,
+    /// Run them like any other task.
+    Allow,
+}
+
+impl ScriptPolicy {
+    /// Parse `MMSS_SCRIPT_POLICY` (`reject`, `require_approval`, `allow`).
+    pub fn from_env() -> Self {
+        match env::var("MMSS_SCRIPT_POLICY").as_deref() {
+            Ok("reject") => Self::Reject,
+            Ok("allow") => Self::Allow,
+            _ => Self::RequireApproval,
+        }
+    }
+}
+
+/// The source a script-bearing command would run, if any.
+pub fn script_source(command: &GeometricTaskCommand) -> Option<&str> {
+    SCRIPT_KEYS
+        .iter()
+        .find_map(|key| command.parameters.get(key).and_then(|v| v.as_str()))
+}
+
+/// Parameters handed to a script as its `params` dict: an explicit `params`
+/// object if given, otherwise every parameter except the source itself.
+pub fn script_params(command: &GeometricTaskCommand) -> Value {
+    if let Some(params) = command.parameters.get("params").filter(|v| v.is_object()) {
+        return params.clone();
+    }
+    match command.parameters.as_object() {
+        Some(object) => Value::Object(
+            object
+                .iter()
+                .filter(|(key, _)| !SCRIPT_KEYS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        ),
+        None => Value::Object(Default::default()),
+    }
+}
+
+/// Whether a command would execute user- or model-supplied code.
+pub fn carries_script(command: &GeometricTaskCommand) -> bool {
+    command.geometric_operator == GeometricOperator::CustomPythonScript
+        || SCRIPT_KEYS
+            .iter()
+            .any(|key| command.parameters.get(key).is_some_and(|v| v.is_string()))
+}
@@ -0,0 +1,754 @@
+//! Out-of-process execution of `CustomPythonScript` tasks.
+//!
+//! Scripts run in a fresh `python -I -S` subprocess with an empty working
+//! directory, a scrubbed environment and a wall-clock timeout. Before the
+//! interpreter starts, the child gets CPU time, address space, file size
+//! and process count limits (`setrlimit`), optionally another uid and gid
+//! (`MMSS_SCRIPT_UID`, `MMSS_SCRIPT_GID`), and on Linux an empty network
+//! namespace; a script is not run where these cannot be applied. Inside, a
+//! prelude strips file, eval and introspection builtins and only lets the
+//! script import allow-listed modules, a guard rail against accidents
+//! rather than confinement. Nothing limits which files the interpreter's
+//! user can read, so run scripts as a dedicated user with access to little.
+
+use crate::core::error::{Error, Result};
+use crate::core::script_arrays::{valid_array_name, ScriptArray};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+const DEFAULT_ALLOWED_IMPORTS: &str =
+    "math,cmath,statistics,json,random,itertools,functools,collections,fractions,decimal";
+
+/// Reads `{"source", "params", "arrays"}` from stdin, maps the input arrays,
+/// runs the source with restricted builtins and `params`/`arrays`/
+/// `save_artifact` in scope, and writes a top-level `result` dict to
+/// `result.json`, the entries of `result_arrays` to `outputs/` and saved
+/// artifacts to `artifacts/`. The allow-listed modules arrive as `argv[1]`.
+const PRELUDE: &str = r#"
+import array, base64, builtins, json, mmap, sys
+allowed = {name for name in sys.argv[1].split(",") if name}
+payload = json.load(sys.stdin)
+real_import = builtins.__import__
+real_open = builtins.open
+try:
+    numpy = real_import("numpy")
+except ImportError:
+    numpy = None
+
+def load_array(name, shape):
+    path = f"inputs/{name}.npy"
+    if numpy is not None:
+        return numpy.load(path, mmap_mode="r")
+    with real_open(path, "rb") as handle:
+        mapped = mmap.mmap(handle.fileno(), 0, access=mmap.ACCESS_READ)
+    data = memoryview(mapped)[10 + int.from_bytes(mapped[8:10], "little"):]
+    return data.cast("d", shape) if shape and all(shape) else data.cast("d")
+
+def save_array(name, value):
+    if not (isinstance(name, str) and name.isascii() and name.isidentifier()):
+        raise TypeError(f"result array name {name!r} is not an identifier")
+    if numpy is not None and isinstance(value, numpy.ndarray):
+        value = numpy.ascontiguousarray(value, dtype="<f8")
+    elif not isinstance(value, memoryview):
+        try:
+            value = memoryview(value)
+        except TypeError:
+            value = memoryview(array.array("d", value))
+    if not isinstance(value, memoryview) or value.format == "d":
+        shape, data = tuple(value.shape), value.tobytes()
+    else:
+        raise TypeError(f"result array {name!r} must hold float64 values")
+    dims = "".join(f"{dim}, " for dim in shape)
+    header = "{'descr': '<f8', 'fortran_order': False, 'shape': (%s), }" % dims
+    header += " " * (-(len(header) + 11) % 64) + "\n"
+    with real_open(f"outputs/{name}.npy", "wb") as handle:
+        handle.write(b"\x93NUMPY\x01\x00" + len(header).to_bytes(2, "little"))
+        handle.write(header.encode("ascii") + data)
+
+saved_artifacts = []
+
+def save_artifact(name, data, content_type="application/octet-stream"):
+    """Keep image or other binary output; `data` is bytes or a base64 string."""
+    if isinstance(data, str):
+        data = base64.b64decode(data, validate=True)
+    with real_open(f"artifacts/{len(saved_artifacts)}.bin", "wb") as handle:
+        handle.write(bytes(data))
+    saved_artifacts.append({"name": str(name), "content_type": str(content_type)})
+
+def guarded_import(name, globals=None, locals=None, fromlist=(), level=0):
+    if level != 0 or name.split(".")[0] not in allowed:
+        raise ImportError(f"import of {name!r} is not allowed")
+    return real_import(name, globals, locals, fromlist, level)
+
+arrays = {name: load_array(name, shape) for name, shape in payload["arrays"].items()}
+blocked = {"open", "eval", "exec", "compile", "input", "breakpoint", "help",
+           "exit", "quit", "globals", "locals", "vars", "memoryview"}
+safe = {key: value for key, value in vars(builtins).items() if key not in blocked}
+safe["__import__"] = guarded_import
+scope = {"__builtins__": safe, "__name__": "__script__",
+         "params": payload["params"], "arrays": arrays, "save_artifact": save_artifact}
+exec(compile(payload["source"], "<script>", "exec"), scope)
+
+if "result" in scope:
+    result = scope["result"]
+    if not isinstance(result, dict):
+        raise TypeError(f"result must be a dict, not {type(result).__name__}")
+    with real_open("result.json", "w") as handle:
+        json.dump(result, handle, allow_nan=False)
+result_arrays = scope.get("result_arrays", {})
+if not isinstance(result_arrays, dict):
+    raise TypeError(f"result_arrays must be a dict, not {type(result_arrays).__name__}")
+for name, value in result_arrays.items():
+    save_array(name, value)
+with real_open("artifacts.json", "w") as handle:
+    json.dump(saved_artifacts, handle)
+"#;
+
+const RESULT_FILE: &str = "result.json";
+const INPUT_DIR: &str = "inputs";
+const OUTPUT_DIR: &str = "outputs";
+const ARTIFACT_DIR: &str = "artifacts";
+const ARTIFACT_MANIFEST: &str = "artifacts.json";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScriptRunnerConfig {
+    pub interpreter: String,
+    pub timeout: Duration,
+    pub allowed_imports: Vec<String>,
+    /// Per-stream cap on captured stdout/stderr, in bytes.
+    pub max_output_bytes: usize,
+    /// Keep the interpreter's site-packages importable (drops `-S`), e.g.
+    /// for NumPy-backed arrays. Imports still go through `allowed_imports`.
+    pub site_packages: bool,
+    /// Cap on the combined size of the artifacts one script may save.
+    pub max_artifact_bytes: usize,
+    /// CPU time the interpreter may use (`RLIMIT_CPU`).
+    pub max_cpu_secs: u64,
+    /// Address space of the interpreter (`RLIMIT_AS`), in bytes.
+    pub max_memory_bytes: u64,
+    /// Size of any file the interpreter writes (`RLIMIT_FSIZE`), in bytes.
+    pub max_file_bytes: u64,
+    /// Processes and threads of the interpreter's user (`RLIMIT_NPROC`);
+    /// with the server's own user this stops the script forking at all.
+    pub max_processes: u64,
+    /// User and group to run the interpreter as instead of the server's.
+    /// The working directory is handed over to them, so the server must
+    /// run as root.
+    pub run_as: Option<(u32, u32)>,
+    /// Leave the interpreter on the host network. Otherwise it gets a
+    /// network namespace of its own, which needs Linux with unprivileged
+    /// user namespaces.
+    pub network: bool,
+}
+
+impl Default for ScriptRunnerConfig {
+    fn default() -> Self {
+        Self {
+            interpreter: "python3".into(),
+            timeout: Duration::from_secs(10),
+            allowed_imports: split_list(DEFAULT_ALLOWED_IMPORTS),
+            max_output_bytes: 1 << 20,
+            site_packages: false,
+            max_artifact_bytes: 16 << 20,
+            max_cpu_secs: 10,
+            max_memory_bytes: 1 << 30,
+            max_file_bytes: 256 << 20,
+            max_processes: 1,
+            run_as: None,
+            network: false,
+        }
+    }
+}
+
+impl ScriptRunnerConfig {
+    /// Read `MMSS_PYTHON`, `MMSS_SCRIPT_TIMEOUT_MS`,
+    /// `MMSS_SCRIPT_ALLOWED_IMPORTS` (comma-separated), `MMSS_SCRIPT_MAX_OUTPUT`,
+    /// `MMSS_SCRIPT_SITE_PACKAGES` (`1`/`true`), `MMSS_SCRIPT_MAX_ARTIFACT_BYTES`,
+    /// `MMSS_SCRIPT_MAX_CPU_SECS`, `MMSS_SCRIPT_MAX_MEMORY`,
+    /// `MMSS_SCRIPT_MAX_FILE_BYTES`, `MMSS_SCRIPT_MAX_PROCESSES`,
+    /// `MMSS_SCRIPT_UID` with `MMSS_SCRIPT_GID`, and `MMSS_SCRIPT_NETWORK`
+    /// (`1`/`true`).
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let read = |name: &str| env::var(name).ok().and_then(|raw| raw.parse::<u64>().ok());
+        let id = |name: &str| env::var(name).ok().and_then(|raw| raw.parse::<u32>().ok());
+        Self {
+            interpreter: env::var("MMSS_PYTHON").unwrap_or(defaults.interpreter),
+            timeout: env::var("MMSS_SCRIPT_TIMEOUT_MS")
+                .ok()
+                .and_then(|raw| raw.parse().ok())
+                .map_or(defaults.timeout, Duration::from_millis),
+            allowed_imports: env::var("MMSS_SCRIPT_ALLOWED_IMPORTS")
+                .map(|raw| split_list(&raw))
+                .unwrap_or(defaults.allowed_imports),
+            max_output_bytes: env::var("MMSS_SCRIPT_MAX_OUTPUT")
+                .ok()
+                .and_then(|raw| raw.parse().ok())
+                .unwrap_or(defaults.max_output_bytes),
+            site_packages: env::var("MMSS_SCRIPT_SITE_PACKAGES")
+                .map(|raw| matches!(raw.trim(), "1" | "true"))
+                .unwrap_or(defaults.site_packages),
+            max_artifact_bytes: env::var("MMSS_SCRIPT_MAX_ARTIFACT_BYTES")
+                .ok()
+                .and_then(|raw| raw.parse().ok())
+                .unwrap_or(defaults.max_artifact_bytes),
+            max_cpu_secs: read("MMSS_SCRIPT_MAX_CPU_SECS").unwrap_or(defaults.max_cpu_secs),
+            max_memory_bytes: read("MMSS_SCRIPT_MAX_MEMORY").unwrap_or(defaults.max_memory_bytes),
+            max_file_bytes: read("MMSS_SCRIPT_MAX_FILE_BYTES").unwrap_or(defaults.max_file_bytes),
+            max_processes: read("MMSS_SCRIPT_MAX_PROCESSES").unwrap_or(defaults.max_processes),
+            run_as: id("MMSS_SCRIPT_UID").zip(id("MMSS_SCRIPT_GID")),
+            network: env::var("MMSS_SCRIPT_NETWORK")
+                .map(|raw| matches!(raw.trim(), "1" | "true"))
+                .unwrap_or(defaults.network),
+        }
+    }
+}
+
+fn split_list(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScriptOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// `None` when the process was killed.
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+    /// The script's `result` dict, when it set one and exited cleanly.
+    pub result: Option<Value>,
+    /// Entries of the script's `result_arrays`, when it exited cleanly.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub arrays: BTreeMap<String, ScriptArray>,
+    /// Files passed to `save_artifact`, when the script exited cleanly.
+    #[serde(skip)]
+    pub artifacts: Vec<ScriptArtifact>,
+}
+
+/// Binary output of a script, e.g. a rendered plot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptArtifact {
+    pub name: String,
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct ArtifactEntry {
+    name: String,
+    content_type: String,
+}
+
+impl ScriptOutput {
+    pub fn succeeded(&self) -> bool {
+        !self.timed_out && self.exit_code == Some(0)
+    }
+
+    /// Short reason for a failed run, for task status and error fields.
+    pub fn failure_reason(&self) -> Option<String> {
+        if self.timed_out {
+            return Some(format!("script timed out after {} ms", self.duration_ms));
+        }
+        if self.succeeded() {
+            return None;
+        }
+        let last_line = self.stderr.lines().last().unwrap_or_default();
+        Some(match self.exit_code {
+            Some(code) => format!("script exited with status {code}: {last_line}"),
+            None => format!("script was terminated: {last_line}"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScriptRunner {
+    config: ScriptRunnerConfig,
+}
+
+impl ScriptRunner {
+    pub fn new(config: ScriptRunnerConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(ScriptRunnerConfig::from_env())
+    }
+
+    pub fn config(&self) -> &ScriptRunnerConfig {
+        &self.config
+    }
+
+    /// Run `script` with `params` bound as a dict, to completion or until the
+    /// timeout, capturing its output and `result`. Errors only when the
+    /// interpreter cannot be started.
+    pub fn execute_python_script(&self, script: &str, params: &Value) -> Result<ScriptOutput> {
+        self.execute_python_script_with_arrays(script, params, &BTreeMap::new())
+    }
+
+    /// As `execute_python_script`, additionally binding `arrays` as a dict of
+    /// read-only arrays and collecting the script's `result_arrays`.
+    #[tracing::instrument(
+        name = "script.run",
+        skip_all,
+        fields(script.arrays = arrays.len(), script.exit_code = tracing::field::Empty)
+    )]
+    pub fn execute_python_script_with_arrays(
+        &self,
+        script: &str,
+        params: &Value,
+        arrays: &BTreeMap<String, ScriptArray>,
+    ) -> Result<ScriptOutput> {
+        if let Some(name) = arrays.keys().find(|name| !valid_array_name(name)) {
+            return Err(Error::InvalidParameter(
+                "arrays".to_string(),
+                format!("'{}' is not a valid array name", name),
+            ));
+        }
+        let workdir = env::temp_dir().join(format!("mmss-script-{}", Uuid::new_v4()));
+        let outcome = self.prepare(&workdir, arrays).and_then(|()| {
+            let shapes: BTreeMap<&str, &[usize]> = arrays
+                .iter()
+                .map(|(name, array)| (name.as_str(), array.shape.as_slice()))
+                .collect();
+            let payload =
+                json!({ "source": script, "params": params, "arrays": shapes }).to_string();
+            self.run_in(&workdir, payload)
+        });
+        if let Err(err) = fs::remove_dir_all(&workdir) {
+            warn!(
+                "Failed to remove script directory {}: {}",
+                workdir.display(),
+                err
+            );
+        }
+        outcome
+    }
+
+    fn prepare(&self, workdir: &Path, arrays: &BTreeMap<String, ScriptArray>) -> Result<()> {
+        fs::create_dir_all(workdir.join(INPUT_DIR))?;
+        fs::create_dir_all(workdir.join(OUTPUT_DIR))?;
+        fs::create_dir_all(workdir.join(ARTIFACT_DIR))?;
+        for (name, array) in arrays {
+            array.write_npy(&workdir.join(INPUT_DIR).join(format!("{}.npy", name)))?;
+        }
+        // the interpreter writes its results here under its own uid
+        #[cfg(unix)]
+        if let Some((uid, gid)) = self.config.run_as {
+            hand_over(workdir, uid, gid)?;
+        }
+        Ok(())
+    }
+
+    fn run_in(&self, workdir: &Path, payload: String) -> Result<ScriptOutput> {
+        let mut command = Command::new(&self.config.interpreter);
+        command
+            .arg("-I")
+            .args((!self.config.site_packages).then_some("-S"))
+            .args(["-c", PRELUDE, &self.config.allowed_imports.join(",")])
+            .current_dir(workdir)
+            .env_clear()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        // keep interpreter lookup working (pyenv shims, venvs) without leaking secrets
+        for key in ["PATH", "HOME", "PYENV_ROOT", "PYENV_VERSION", "LANG"] {
+            if let Some(value) = env::var_os(key) {
+                command.env(key, value);
+            }
+        }
+        // numerical libraries would otherwise start a thread per core
+        for key in ["OMP_NUM_THREADS", "OPENBLAS_NUM_THREADS", "MKL_NUM_THREADS"] {
+            command.env(key, "1");
+        }
+        confine(&mut command, &self.config)?;
+
+        let started = Instant::now();
+        let mut child = command.spawn().map_err(|err| {
+            Error::TaskExecution(format!(
+                "Failed to start {}: {}",
+                self.config.interpreter, err
+            ))
+        })?;
+
+        let mut stdin = child.stdin.take();
+        let writer = thread::spawn(move || {
+            if let Some(stdin) = stdin.as_mut() {
+                // a script that exits early closes the pipe; nothing to report
+                let _ = stdin.write_all(payload.as_bytes());
+            }
+        });
+        let limit = self.config.max_output_bytes as u64;
+        let stdout = capture(child.stdout.take(), limit);
+        let stderr = capture(child.stderr.take(), limit);
+
+        let (exit_code, timed_out) = wait_with_timeout(&mut child, self.config.timeout)?;
+        let _ = writer.join();
+        let mut stderr = stderr.join().unwrap_or_default();
+        let result = match fs::read_to_string(workdir.join(RESULT_FILE)) {
+            Ok(raw) if exit_code == Some(0) => match serde_json::from_str(&raw) {
+                Ok(result) => Some(result),
+                Err(err) => {
+                    stderr.push_str(&format!("\nunreadable result: {err}"));
+                    None
+                }
+            },
+            _ => None,
+        };
+        let (arrays, artifacts) = if exit_code == Some(0) {
+            let arrays = read_result_arrays(&workdir.join(OUTPUT_DIR)).unwrap_or_else(|err| {
+                stderr.push_str(&format!("\nunreadable result arrays: {err}"));
+                BTreeMap::new()
+            });
+            let artifacts =
+                read_artifacts(workdir, self.config.max_artifact_bytes).unwrap_or_else(|err| {
+                    stderr.push_str(&format!("\nunreadable artifacts: {err}"));
+                    Vec::new()
+                });
+            (arrays, artifacts)
+        } else {
+            (BTreeMap::new(), Vec::new())
+        };
+        let duration_ms = started.elapsed().as_millis() as u64;
+        if let Some(code) = exit_code {
+            tracing::Span::current().record("script.exit_code", code);
+        }
+        tracing::info!(histogram.mmss.script.duration_ms = duration_ms, timed_out);
+        Ok(ScriptOutput {
+            stdout: stdout.join().unwrap_or_default(),
+            stderr,
+            exit_code,
+            timed_out,
+            duration_ms,
+            result,
+            arrays,
+            artifacts,
+        })
+    }
+}
+
+/// Give `dir` and everything in it to `uid` and `gid`.
+#[cfg(unix)]
+fn hand_over(dir: &Path, uid: u32, gid: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::lchown;
+
+    lchown(dir, Some(uid), Some(gid))?;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            hand_over(&entry.path(), uid, gid)?;
+        } else {
+            lchown(entry.path(), Some(uid), Some(gid))?;
+        }
+    }
+    Ok(())
+}
+
+/// Apply the OS limits of `config` to the interpreter before it starts.
+#[cfg(unix)]
+fn confine(command: &mut Command, config: &ScriptRunnerConfig) -> Result<()> {
+    use std::os::unix::process::CommandExt;
+
+    if !config.network && !cfg!(target_os = "linux") {
+        return Err(Error::TaskExecution(
+            "scripts can only be cut off from the network on Linux; \
+             set MMSS_SCRIPT_NETWORK=1 to run them with it"
+                .to_string(),
+        ));
+    }
+    if let Some((uid, gid)) = config.run_as {
+        command.uid(uid).gid(gid);
+    }
+    let limits = [
+        (libc::RLIMIT_CPU, config.max_cpu_secs),
+        (libc::RLIMIT_AS, config.max_memory_bytes),
+        (libc::RLIMIT_FSIZE, config.max_file_bytes),
+        (libc::RLIMIT_NPROC, config.max_processes),
+    ];
+    let isolate_network = !config.network;
+    // SAFETY: runs in the forked child before exec, after the uid and gid
+    // are switched, and only makes async-signal-safe system calls.
+    unsafe {
+        command.pre_exec(move || {
+            for (resource, limit) in limits {
+                let limit = libc::rlimit {
+                    rlim_cur: limit as libc::rlim_t,
+                    rlim_max: limit as libc::rlim_t,
+                };
+                if libc::setrlimit(resource, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            // a user namespace too, so this needs no privileges
+            #[cfg(target_os = "linux")]
+            if isolate_network && libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            #[cfg(not(target_os = "linux"))]
+            let _ = isolate_network;
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn confine(_command: &mut Command, _config: &ScriptRunnerConfig) -> Result<()> {
+    Err(Error::TaskExecution(
+        "scripts are only run where they can be confined, on Unix".to_string(),
+    ))
+}
+
+fn read_result_arrays(dir: &Path) -> Result<BTreeMap<String, ScriptArray>> {
+    let mut arrays = BTreeMap::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".npy"))
+            .filter(|name| valid_array_name(name))
+        else {
+            continue;
+        };
+        arrays.insert(name.to_string(), ScriptArray::read_npy(&path)?);
+    }
+    Ok(arrays)
+}
+
+fn read_artifacts(workdir: &Path, max_bytes: usize) -> Result<Vec<ScriptArtifact>> {
+    let manifest: Vec<ArtifactEntry> =
+        serde_json::from_str(&fs::read_to_string(workdir.join(ARTIFACT_MANIFEST))?)?;
+    let mut total = 0;
+    let mut artifacts = Vec::with_capacity(manifest.len());
+    for (index, entry) in manifest.into_iter().enumerate() {
+        let bytes = fs::read(workdir.join(ARTIFACT_DIR).join(format!("{index}.bin")))?;
+        total += bytes.len();
+        if total > max_bytes {
+            return Err(Error::InvalidParameter(
+                "artifacts".to_string(),
+                format!("artifacts exceed {} bytes", max_bytes),
+            ));
+        }
+        artifacts.push(ScriptArtifact {
+            name: entry.name,
+            content_type: entry.content_type,
+            bytes,
+        });
+    }
+    Ok(artifacts)
+}
+
+fn capture<R: Read + Send + 'static>(stream: Option<R>, limit: u64) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut stream) = stream {
+            let _ = (&mut stream).take(limit).read_to_end(&mut buffer);
+            // keep draining so a chatty script doesn't block on a full pipe
+            let _ = std::io::copy(&mut stream, &mut std::io::sink());
+        }
+        String::from_utf8_lossy(&buffer).into_owned()
+    })
+}
+
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> Result<(Option<i32>, bool)> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((status.code(), false));
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            child.wait()?;
+            return Ok((None, true));
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runner(timeout_ms: u64) -> ScriptRunner {
+        ScriptRunner::new(ScriptRunnerConfig {
+            timeout: Duration::from_millis(timeout_ms),
+            ..ScriptRunnerConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_script_output_is_captured() {
+        let output = runner(5_000)
+            .execute_python_script("import math\nprint(math.sqrt(16))", &Value::Null)
+            .unwrap();
+        assert!(output.succeeded(), "{output:?}");
+        assert_eq!(output.stdout.trim(), "4.0");
+    }
+
+    #[test]
+    fn test_filesystem_and_network_are_blocked() {
+        let runner = runner(5_000);
+        for script in [
+            "open('/etc/passwd')",
+            "import socket",
+            "import os",
+            "eval('1')",
+        ] {
+            let output = runner.execute_python_script(script, &Value::Null).unwrap();
+            assert!(!output.succeeded(), "{script} should fail");
+            assert!(output.failure_reason().is_some());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_memory_is_capped() {
+        let runner = ScriptRunner::new(ScriptRunnerConfig {
+            max_memory_bytes: 256 << 20,
+            ..ScriptRunnerConfig::default()
+        });
+        let output = runner
+            .execute_python_script("hoard = bytearray(512 << 20)", &Value::Null)
+            .unwrap();
+        assert!(!output.succeeded());
+        assert!(output.stderr.contains("MemoryError"), "{output:?}");
+    }
+
+    #[test]
+    fn test_runaway_script_is_killed() {
+        let output = runner(200)
+            .execute_python_script("while True:\n    pass", &Value::Null)
+            .unwrap();
+        assert!(output.timed_out);
+        assert!(output.duration_ms < 5_000);
+    }
+
+    #[test]
+    fn test_params_in_and_result_out() {
+        let runner = runner(5_000);
+        let output = runner
+            .execute_python_script(
+                "print('scaling')\nresult = {'scaled': [x * params['factor'] for x in params['values']]}",
+                &json!({ "factor": 2, "values": [1, 2.5] }),
+            )
+            .unwrap();
+        assert!(output.succeeded(), "{output:?}");
+        assert_eq!(output.stdout.trim(), "scaling");
+        assert_eq!(output.result, Some(json!({ "scaled": [2, 5.0] })));
+
+        let output = runner
+            .execute_python_script("result = [1, 2]", &Value::Null)
+            .unwrap();
+        assert!(!output.succeeded());
+        assert!(output.stderr.contains("result must be a dict"));
+    }
+
+    #[test]
+    fn test_arrays_are_mapped_in_and_read_back() {
+        let arrays = BTreeMap::from([(
+            "grid".to_string(),
+            ScriptArray::new(vec![2, 3], vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap(),
+        )]);
+        let output = runner(5_000)
+            .execute_python_script_with_arrays(
+                "grid = arrays['grid']\n\
+                 result = {'corner': grid[1, 2], 'shape': list(grid.shape)}\n\
+                 result_arrays = {'row_sums': [sum(row) for row in grid.tolist()]}",
+                &Value::Null,
+                &arrays,
+            )
+            .unwrap();
+        assert!(output.succeeded(), "{output:?}");
+        assert_eq!(
+            output.result,
+            Some(json!({ "corner": 6.0, "shape": [2, 3] }))
+        );
+        assert_eq!(
+            output.arrays["row_sums"],
+            ScriptArray::vector(vec![6.0, 15.0])
+        );
+
+        let bad_name = BTreeMap::from([("../x".to_string(), ScriptArray::vector(vec![]))]);
+        assert!(runner(5_000)
+            .execute_python_script_with_arrays("pass", &Value::Null, &bad_name)
+            .is_err());
+    }
+
+    #[test]
+    fn test_saved_artifacts_are_collected() {
+        let output = runner(5_000)
+            .execute_python_script(
+                "save_artifact('plot.png', b'\\x89PNG', 'image/png')\n\
+                 save_artifact('note.txt', 'aGVsbG8=')",
+                &Value::Null,
+            )
+            .unwrap();
+        assert!(output.succeeded(), "{output:?}");
+        assert_eq!(
+            output.artifacts,
+            vec![
+                ScriptArtifact {
+                    name: "plot.png".to_string(),
+                    content_type: "image/png".to_string(),
+                    bytes: b"\x89PNG".to_vec(),
+                },
+                ScriptArtifact {
+                    name: "note.txt".to_string(),
+                    content_type: "application/octet-stream".to_string(),
+                    bytes: b"hello".to_vec(),
+                },
+            ]
+        );
+
+        let capped = ScriptRunner::new(ScriptRunnerConfig {
+            max_artifact_bytes: 2,
+            ..ScriptRunnerConfig::default()
+        })
+        .execute_python_script("save_artifact('big', b'abc')", &Value::Null)
+        .unwrap();
+        assert!(capped.artifacts.is_empty());
+        assert!(capped.stderr.contains("artifacts exceed 2 bytes"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_script_run_as_another_user_writes_its_results() {
+        // only root may switch users
+        if unsafe { libc::geteuid() } != 0 {
+            return;
+        }
+        let runner = ScriptRunner::new(ScriptRunnerConfig {
+            run_as: Some((65_534, 65_534)),
+            ..ScriptRunnerConfig::default()
+        });
+        let arrays = BTreeMap::from([("v".to_string(), ScriptArray::vector(vec![1.0, 2.0]))]);
+        let output = runner
+            .execute_python_script_with_arrays(
+                "result = {'total': sum(arrays['v'].tolist())}\n\
+                 result_arrays = {'twice': [2 * x for x in arrays['v'].tolist()]}\n\
+                 save_artifact('note.txt', b'hi')",
+                &Value::Null,
+                &arrays,
+            )
+            .unwrap();
+        assert!(output.succeeded(), "{output:?}");
+        assert_eq!(output.result, Some(json!({ "total": 3.0 })));
+        assert_eq!(output.arrays["twice"], ScriptArray::vector(vec![2.0, 4.0]));
+        assert_eq!(output.artifacts[0].bytes, b"hi");
+    }
+}
@@ -1,76 +1,306 @@
-use crate::core::emergence_logic::EmergenceLogic;
+use crate::core::artifacts::{ArtifactStore, ArtifactSummary};
+use crate::core::emergence_logic::{eqgft_config, hopfion_backend, resolve_seeds, EmergenceLogic};
 use crate::core::error::{Error, Result};
-use crate::core::types::{GeometricMetrics, GeometricTaskCommand, TaskExecutionResult};
-use crate::state::{
-    compute_electron_mass, compute_fine_structure, compute_quaternion_coherence, compute_zitter_entropy,
+use crate::core::events::{EventBus, StateEvent};
+use crate::core::journal::{Journal, Mutation};
+use crate::core::lock_stats::{LockStats, TrackedMutex};
+use crate::core::metrics_history::{MetricsHistory, MetricsSnapshot};
+use crate::core::ops_metrics::{OpsMetrics, QueueDepth};
+use crate::core::script_arrays::ScriptArray;
+use crate::core::script_policy::{carries_script, script_params, script_source, ScriptPolicy};
+use crate::core::script_runner::{ScriptOutput, ScriptRunner};
+use crate::core::types::{
+    GeometricMetrics, GeometricOperator, GeometricTaskCommand, PendingTask, ResetScope,
+    TaskExecutionResult,
 };
-use log::{error, info};
+use crate::state::PhysicalConstants;
+use arc_swap::ArcSwap;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use mmss_compute::emergence::baseline_metrics;
+use mmss_core::record::{Kind, RecordError};
+use mmss_core::structex_bridge::MmssRecord;
+use mmss_eqgft::cache::EqgftCache;
+use mmss_eqgft::hopfion::{HopfionConfig, HopfionSolitonField};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, MutexGuard};
+use std::time::Instant;
 use uuid::Uuid;
 
 /// Represents the status of a task
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TaskStatus {
+    /// Script-bearing task held until `approve_task` is called.
+    AwaitingApproval,
     Pending,
     InProgress,
     Completed(GeometricMetrics),
     Failed(String),
 }
 
-impl SemanticTaskProcessor {
-    fn baseline_metrics() -> GeometricMetrics {
-        let coherence = compute_quaternion_coherence();
-        let entropy = compute_zitter_entropy();
-        let electron_mass = compute_electron_mass();
-        let fine_structure = compute_fine_structure();
-        let default_winding = 8.9997;
-
-        GeometricMetrics {
-            v_geometric: coherence,
-            s_geometric: entropy,
-            q_oscillator: default_winding,
-            quaternion_coherence: coherence,
-            emergent_electron_mass: electron_mass,
-            fine_structure_constant: fine_structure,
-            zitterbewegung_entropy: entropy,
-            topological_winding: default_winding,
-            custom_metrics: HashMap::new(),
-        }
-    }
+/// `MMSS_EQGFT_CACHE_ENTRIES` results per kind in memory (default 16).
+fn eqgft_cache_capacity() -> usize {
+    env::var("MMSS_EQGFT_CACHE_ENTRIES")
+        .ok()
+        .and_then(|raw| raw.parse().ok())
+        .unwrap_or(16)
+}
+
+/// The EQGFT cache, spilled to `MMSS_EQGFT_CACHE_DIR` when it is set.
+fn eqgft_cache_from_env() -> EqgftCache {
+    let spill_dir = env::var("MMSS_EQGFT_CACHE_DIR").ok().map(PathBuf::from);
+    EqgftCache::new(eqgft_cache_capacity(), spill_dir.as_deref())
 }
 
 struct TaskInfo {
     command: GeometricTaskCommand,
     status: TaskStatus,
+    submitted_at: DateTime<Utc>,
+    submitted_by: Option<String>,
+    finished_at: Option<DateTime<Utc>>,
+}
+
+impl TaskInfo {
+    /// Set `status`, noting when the task reached a final one.
+    fn set_status(&mut self, status: TaskStatus) {
+        if matches!(status, TaskStatus::Completed(_) | TaskStatus::Failed(_)) {
+            self.finished_at = Some(Utc::now());
+        }
+        self.status = status;
+    }
+}
+
+/// A task as replicated between servers sharing state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskEntry {
+    pub task_id: Uuid,
+    pub command: GeometricTaskCommand,
+    pub status: TaskStatus,
+    pub submitted_at: DateTime<Utc>,
+    #[serde(default)]
+    pub submitted_by: Option<String>,
+    #[serde(default)]
+    pub finished_at: Option<DateTime<Utc>>,
 }
 
 /// Manages the execution of geometric tasks
 pub struct SemanticTaskProcessor {
-    tasks: Arc<Mutex<HashMap<Uuid, TaskInfo>>>,
-    metrics: Arc<Mutex<GeometricMetrics>>,
-    emergence: Arc<Mutex<EmergenceLogic>>,
+    tasks: Arc<TrackedMutex<HashMap<Uuid, TaskInfo>>>,
+    /// Replaced whole by writers, who serialize on `emergence`, so readers
+    /// neither wait for a task nor fail after one panicked.
+    metrics: Arc<ArcSwap<GeometricMetrics>>,
+    emergence: Arc<TrackedMutex<EmergenceLogic>>,
+    constants: PhysicalConstants,
+    script_policy: ScriptPolicy,
+    script_runner: ScriptRunner,
+    artifacts: Arc<ArtifactStore>,
+    eqgft_cache: Arc<EqgftCache>,
+    metrics_history: Option<Arc<MetricsHistory>>,
+    events: Arc<EventBus>,
+    journal: Option<Arc<Journal>>,
+    ops: Arc<OpsMetrics>,
 }
 
 impl SemanticTaskProcessor {
-    /// Create a new SemanticTaskProcessor
+    /// Create a new SemanticTaskProcessor with the policy from `MMSS_SCRIPT_POLICY`
     pub fn new() -> Self {
+        Self::with_script_policy(ScriptPolicy::from_env())
+    }
+
+    pub fn with_script_policy(script_policy: ScriptPolicy) -> Self {
+        let eqgft_cache = Arc::new(eqgft_cache_from_env());
         Self {
-            tasks: Arc::new(Mutex::new(HashMap::new())),
-            metrics: Arc::new(Mutex::new(Self::baseline_metrics())),
-            emergence: Arc::new(Mutex::new(EmergenceLogic::new(None))),
+            tasks: Arc::new(TrackedMutex::new("tasks", HashMap::new())),
+            metrics: Arc::new(ArcSwap::from_pointee(baseline_metrics(&PhysicalConstants::SI))),
+            constants: PhysicalConstants::SI,
+            emergence: Arc::new(TrackedMutex::new(
+                "emergence",
+                EmergenceLogic::new(None).with_eqgft_cache(eqgft_cache.clone()),
+            )),
+            script_policy,
+            script_runner: ScriptRunner::from_env(),
+            artifacts: Arc::new(ArtifactStore::from_env()),
+            eqgft_cache,
+            metrics_history: None,
+            events: Arc::new(EventBus::from_env()),
+            journal: None,
+            ops: Arc::new(OpsMetrics::new()),
+        }
+    }
+
+    pub fn with_script_runner(mut self, script_runner: ScriptRunner) -> Self {
+        self.script_runner = script_runner;
+        self
+    }
+
+    /// Compute metrics from `constants` instead of SI. Resets the metrics and
+    /// emergence state, so call it before submitting tasks.
+    pub fn with_constants(mut self, constants: PhysicalConstants) -> Self {
+        self.constants = constants;
+        self.metrics = Arc::new(ArcSwap::from_pointee(baseline_metrics(&constants)));
+        self.emergence = Arc::new(TrackedMutex::new(
+            "emergence",
+            EmergenceLogic::new(None)
+                .with_constants(constants)
+                .with_eqgft_cache(self.eqgft_cache.clone()),
+        ));
+        self
+    }
+
+    /// Spill EQGFT results to `directory` instead of `MMSS_EQGFT_CACHE_DIR`.
+    /// Resets the emergence state, so call it before submitting tasks.
+    pub fn with_eqgft_cache_dir(mut self, directory: &Path) -> Self {
+        self.eqgft_cache = Arc::new(EqgftCache::new(eqgft_cache_capacity(), Some(directory)));
+        self.emergence = Arc::new(TrackedMutex::new(
+            "emergence",
+            EmergenceLogic::new(None)
+                .with_constants(self.constants)
+                .with_eqgft_cache(self.eqgft_cache.clone()),
+        ));
+        self
+    }
+
+    /// Snapshot the metrics into `history` whenever a task completes.
+    pub fn with_metrics_history(mut self, history: Arc<MetricsHistory>) -> Self {
+        self.metrics_history = Some(history);
+        self
+    }
+
+    /// Record every mutation of the tasks, metrics and field in `journal`.
+    pub fn with_journal(mut self, journal: Arc<Journal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    pub fn journal(&self) -> Option<&Arc<Journal>> {
+        self.journal.as_ref()
+    }
+
+    /// Record task durations and outcomes in `ops`, e.g. one shared with
+    /// other processors.
+    pub fn with_ops_metrics(mut self, ops: Arc<OpsMetrics>) -> Self {
+        self.ops = ops;
+        self
+    }
+
+    pub fn ops_metrics(&self) -> &Arc<OpsMetrics> {
+        &self.ops
+    }
+
+    /// Contention of the task and emergence mutexes; metrics are read
+    /// without locking.
+    pub fn lock_stats(&self) -> Vec<LockStats> {
+        vec![self.tasks.stats(), self.emergence.stats()]
+    }
+
+    /// Unfinished tasks by state.
+    pub fn queue_depth(&self) -> Result<QueueDepth> {
+        let tasks = self.lock_tasks();
+
+        let mut depth = QueueDepth::default();
+        for info in tasks.values() {
+            match info.status {
+                TaskStatus::AwaitingApproval => depth.awaiting_approval += 1,
+                TaskStatus::Pending => depth.pending += 1,
+                TaskStatus::InProgress => depth.in_progress += 1,
+                TaskStatus::Completed(_) | TaskStatus::Failed(_) => {}
+            }
+        }
+        Ok(depth)
+    }
+
+    /// Built only when a journal is kept. Called under the locks the
+    /// mutation holds, so the journal's order is the order of mutations.
+    fn journal_mutation(&self, mutation: impl FnOnce() -> Mutation) {
+        if let Some(journal) = &self.journal {
+            journal.record(mutation());
+        }
+    }
+
+    /// Task transitions and metrics updates are published here as they
+    /// happen.
+    pub fn events(&self) -> &Arc<EventBus> {
+        &self.events
+    }
+
+    /// The task table. A panic under its lock can only have come from a
+    /// simulated operator, which holds the lock while it runs, so a
+    /// non-script task still in progress is the one that panicked and is
+    /// marked failed; running scripts do not hold the lock and are left be.
+    fn lock_tasks(&self) -> MutexGuard<'_, HashMap<Uuid, TaskInfo>> {
+        self.tasks.lock_or_recover(|tasks| {
+            for (task_id, info) in tasks.iter_mut() {
+                let interrupted = info.status == TaskStatus::InProgress
+                    && info.command.geometric_operator != GeometricOperator::CustomPythonScript;
+                if interrupted {
+                    warn!("Task {} panicked while running; marking it failed", task_id);
+                    info.set_status(TaskStatus::Failed("the operator panicked".to_string()));
+                    self.publish_transition(*task_id, &info.status);
+                }
+            }
+        })
+    }
+
+    /// The emergence state. After a panic under its lock the metrics may be
+    /// half updated, so they are put back to the last ones published; the
+    /// Hopfion field is only ever replaced whole and is kept.
+    fn lock_emergence(&self) -> MutexGuard<'_, EmergenceLogic> {
+        self.emergence.lock_or_recover(|emergence| {
+            emergence.adopt_metrics(GeometricMetrics::clone(&self.metrics.load()));
+        })
+    }
+
+    fn publish_transition(&self, task_id: Uuid, status: &TaskStatus) {
+        let outcome = match status {
+            TaskStatus::Completed(_) => Some("completed"),
+            TaskStatus::Failed(_) => Some("failed"),
+            _ => None,
+        };
+        if let Some(outcome) = outcome {
+            tracing::info!(monotonic_counter.mmss.tasks.finished = 1_u64, outcome);
         }
+        self.events.publish(StateEvent::TaskTransition {
+            task_id,
+            status: status.clone(),
+        });
+    }
+
+    /// Binary outputs saved by script tasks, served by `GET /artifacts/:id`.
+    pub fn artifacts(&self) -> &Arc<ArtifactStore> {
+        &self.artifacts
+    }
+
+    /// Cached Hopfion fields and event samples, shared by all tasks.
+    pub fn eqgft_cache(&self) -> &Arc<EqgftCache> {
+        &self.eqgft_cache
+    }
+
+    pub fn script_policy(&self) -> ScriptPolicy {
+        self.script_policy
     }
 
     /// Submit a new geometric task for execution
     pub fn submit_task(&self, task: GeometricTaskCommand) -> Result<Uuid> {
+        self.submit_task_by(task, None)
+    }
+
+    /// `submit_task` on behalf of `submitter`, who then may not approve it.
+    #[tracing::instrument(
+        name = "task.submit",
+        skip_all,
+        fields(task.operator = ?task.geometric_operator)
+    )]
+    pub fn submit_task_by(
+        &self,
+        task: GeometricTaskCommand,
+        submitter: Option<String>,
+    ) -> Result<Uuid> {
         let task_id = task.task_id.unwrap_or_else(Uuid::new_v4);
 
-        let mut tasks = self.tasks.lock().map_err(|e| {
-            error!("Failed to lock tasks: {}", e);
-            Error::TaskExecution("Failed to access task storage".to_string())
-        })?;
+        let mut tasks = self.lock_tasks();
 
         if tasks.contains_key(&task_id) {
             return Err(Error::TaskExecution(format!(
@@ -79,11 +309,26 @@ impl SemanticTaskProcessor {
             )));
         }
 
+        if task.geometric_operator == GeometricOperator::GenerateHopfionField {
+            hopfion_backend(&task.parameters)?;
+        }
+        let status = self.initial_status(&task)?;
+        self.publish_transition(task_id, &status);
+        self.journal_mutation(|| Mutation::TaskSubmitted {
+            task_id,
+            command: task.clone(),
+            awaiting_approval: status == TaskStatus::AwaitingApproval,
+            submitted_by: submitter.clone(),
+        });
+
         tasks.insert(
             task_id,
             TaskInfo {
                 command: task.clone(),
-                status: TaskStatus::Pending,
+                status,
+                submitted_at: Utc::now(),
+                submitted_by: submitter,
+                finished_at: None,
             },
         );
         info!("Submitted task {}: {}", task_id, task.task_name);
@@ -91,64 +336,448 @@ impl SemanticTaskProcessor {
         Ok(task_id)
     }
 
-    /// Execute a pending task
+    /// Where the script policy puts a newly submitted task.
+    fn initial_status(&self, task: &GeometricTaskCommand) -> Result<TaskStatus> {
+        match (carries_script(task), self.script_policy) {
+            (false, _) | (true, ScriptPolicy::Allow) => Ok(TaskStatus::Pending),
+            (true, ScriptPolicy::RequireApproval) => Ok(TaskStatus::AwaitingApproval),
+            (true, ScriptPolicy::Reject) => Err(Error::PolicyViolation(
+                "script-bearing commands are disabled".to_string(),
+            )),
+        }
+    }
+
+    /// Tasks submitted but not yet run, oldest first.
+    pub fn pending_tasks(&self) -> Result<Vec<PendingTask>> {
+        let tasks = self.lock_tasks();
+
+        let mut pending: Vec<_> = tasks
+            .iter()
+            .filter(|(_, info)| {
+                matches!(info.status, TaskStatus::Pending | TaskStatus::AwaitingApproval)
+            })
+            .map(|(&task_id, info)| PendingTask {
+                task_id,
+                command: info.command.clone(),
+                awaiting_approval: info.status == TaskStatus::AwaitingApproval,
+                submitted_at: info.submitted_at,
+                submitted_by: info.submitted_by.clone(),
+            })
+            .collect();
+        pending.sort_by_key(|task| task.submitted_at);
+        Ok(pending)
+    }
+
+    /// Resubmit tasks saved by an earlier run under their own ids, returning
+    /// how many were kept. Each goes through the current script policy
+    /// again, so an approval does not outlive a restart; tasks it rejects
+    /// and ids already in use are skipped.
+    pub fn restore_pending_tasks(&self, pending: Vec<PendingTask>) -> Result<usize> {
+        let mut tasks = self.lock_tasks();
+
+        let mut restored = 0;
+        for task in pending {
+            if tasks.contains_key(&task.task_id) {
+                warn!("Not restoring task {}: the id is taken", task.task_id);
+                continue;
+            }
+            let status = match self.initial_status(&task.command) {
+                Ok(status) => status,
+                Err(err) => {
+                    warn!("Not restoring task {}: {}", task.task_id, err);
+                    continue;
+                }
+            };
+            self.publish_transition(task.task_id, &status);
+            tasks.insert(
+                task.task_id,
+                TaskInfo {
+                    command: task.command,
+                    status,
+                    submitted_at: task.submitted_at,
+                    submitted_by: task.submitted_by,
+                    finished_at: None,
+                },
+            );
+            restored += 1;
+        }
+        Ok(restored)
+    }
+
+    /// Continue from `metrics` saved by an earlier run, regenerating the
+    /// Hopfion field from `hopfion`.
+    pub fn restore_metrics(
+        &self,
+        metrics: GeometricMetrics,
+        hopfion: Option<HopfionConfig>,
+    ) -> Result<()> {
+        let mut emergence = self.lock_emergence();
+
+        emergence.restore(metrics.clone(), hopfion);
+        self.metrics.store(Arc::new(metrics));
+        Ok(())
+    }
+
+    /// Return the tasks, metrics and Hopfion field selected by `scope` to
+    /// baseline, all under the same locks. A reset of the metrics is
+    /// published like a task completion.
+    pub fn reset(&self, scope: &ResetScope) -> Result<()> {
+        let mut tasks = self.lock_tasks();
+        let mut emergence = self.lock_emergence();
+
+        self.journal_mutation(|| Mutation::Reset { scope: *scope });
+        if scope.tasks {
+            tasks.clear();
+        }
+        emergence.reset(scope.metrics, scope.field);
+        if scope.metrics {
+            let metrics = baseline_metrics(&self.constants);
+            self.metrics.store(Arc::new(metrics.clone()));
+            let snapshot = MetricsSnapshot::now(None, metrics);
+            if let Some(history) = &self.metrics_history {
+                if let Err(e) = history.push(snapshot.clone()) {
+                    warn!("Failed to record metrics history: {}", e);
+                }
+            }
+            self.events.publish(StateEvent::MetricsUpdated(snapshot));
+        }
+        info!("Reset processor state: {:?}", scope);
+        Ok(())
+    }
+
+        /// Execute a pending task
+    #[tracing::instrument(
+        name = "task.execute",
+        skip(self),
+        fields(task.id = %task_id, task.operator = tracing::field::Empty)
+    )]
     pub fn execute_task(&self, task_id: Uuid) -> Result<TaskExecutionResult> {
+        let started = Instant::now();
+        let result = self.run_task(task_id);
+        // tasks refused before running (unknown, awaiting approval) are not counted
+        if let Ok(entry) = self.task_entry(task_id) {
+            let succeeded = match entry.status {
+                TaskStatus::Completed(_) => true,
+                TaskStatus::Failed(_) => false,
+                _ => return result,
+            };
+            self.ops.record_task(
+                entry.command.geometric_operator,
+                started.elapsed(),
+                succeeded,
+            );
+        }
+        result
+    }
+
+    fn run_task(&self, task_id: Uuid) -> Result<TaskExecutionResult> {
         // In a real implementation, this would execute the actual task
         // For now, we'll simulate task execution
-        let mut tasks = self.tasks.lock().map_err(|e| {
-            error!("Failed to lock tasks: {}", e);
-            Error::TaskExecution("Failed to access task storage".to_string())
-        })?;
+        let mut tasks = self.lock_tasks();
 
         let info = tasks
             .get_mut(&task_id)
             .ok_or_else(|| Error::TaskExecution(format!("Task with ID {} not found", task_id)))?;
+        tracing::Span::current().record(
+            "task.operator",
+            tracing::field::debug(info.command.geometric_operator),
+        );
+
+        if info.status == TaskStatus::AwaitingApproval {
+            return Err(Error::PolicyViolation(format!(
+                "Task {} is awaiting approval",
+                task_id
+            )));
+        }
+        self.journal_mutation(|| Mutation::TaskStarted { task_id });
+        if info.command.geometric_operator == GeometricOperator::CustomPythonScript {
+            let Some(script) = script_source(&info.command).map(str::to_string) else {
+                let reason = "script task has no script parameter".to_string();
+                info.set_status(TaskStatus::Failed(reason.clone()));
+                self.publish_transition(task_id, &info.status);
+                return Err(Error::TaskExecution(reason));
+            };
+            let params = script_params(&info.command);
+            let arrays = match self.script_arrays(&info.command) {
+                Ok(arrays) => arrays,
+                Err(err) => {
+                    info.set_status(TaskStatus::Failed(err.to_string()));
+                    self.publish_transition(task_id, &info.status);
+                    return Err(err);
+                }
+            };
+            info.status = TaskStatus::InProgress;
+            self.publish_transition(task_id, &info.status);
+            // scripts can run for seconds; don't hold the task table meanwhile
+            drop(tasks);
+            let outcome = self
+                .script_runner
+                .execute_python_script_with_arrays(&script, &params, &arrays);
+            return self.finish_script_task(task_id, outcome);
+        }
 
         // Update status to in progress
         info.status = TaskStatus::InProgress;
+        self.publish_transition(task_id, &info.status);
 
         // Simulate some work
         std::thread::sleep(std::time::Duration::from_millis(100));
 
-        let metrics = self.simulate_task_execution(&info.command)?;
+        let (metrics, result) = self.simulate_task_execution(task_id, &info.command)?;
 
         // Update the task status
-        info.status = TaskStatus::Completed(metrics.clone());
+        info.set_status(TaskStatus::Completed(metrics.clone()));
+        self.publish_transition(task_id, &info.status);
+        let operator = info.command.geometric_operator;
+        drop(tasks);
+        self.record_metrics(task_id, operator, &metrics);
 
         // Create the result
         Ok(TaskExecutionResult {
             task_id,
             success: true,
             metrics,
-            output: serde_json::json!({ "status": "completed" }),
+            output: match result {
+                Some(result) => serde_json::json!({ "status": "completed", "result": result }),
+                None => serde_json::json!({ "status": "completed" }),
+            },
             error: None,
         })
     }
 
+    /// Binary inputs named by the task's `arrays` parameter: `hopfion_q`
+    /// (`[n, n, n, 4]`), `hopfion_energy_density` (`[n, n, n]`) and
+    /// `hopfion_axis` (`[n]`) from the last generated field, and `events`
+    /// (`cos θ` per event) simulated from the task's EQGFT parameters.
+    fn script_arrays(&self, command: &GeometricTaskCommand) -> Result<BTreeMap<String, ScriptArray>> {
+        let Some(requested) = command.parameters.get("arrays") else {
+            return Ok(BTreeMap::new());
+        };
+        let names = requested
+            .as_array()
+            .and_then(|names| names.iter().map(|name| name.as_str()).collect::<Option<Vec<_>>>())
+            .ok_or_else(|| {
+                Error::InvalidParameter("arrays".to_string(), "expected a list of names".to_string())
+            })?;
+
+        let field = if names.iter().any(|name| name.starts_with("hopfion_")) {
+            let emergence = self.lock_emergence();
+            let field = emergence.hopfion_field().cloned().ok_or_else(|| {
+                Error::InvalidParameter(
+                    "arrays".to_string(),
+                    "no Hopfion field has been generated".to_string(),
+                )
+            })?;
+            Some(field)
+        } else {
+            None
+        };
+
+        let mut arrays = BTreeMap::new();
+        for name in names {
+            let array = match (name, &field) {
+                ("hopfion_q", Some(field)) => {
+                    let n = field.resolution();
+                    ScriptArray::new(vec![n, n, n, 4], field.q_x.iter().flatten().copied().collect())?
+                }
+                ("hopfion_energy_density", Some(field)) => {
+                    let n = field.resolution();
+                    ScriptArray::new(vec![n, n, n], field.energy_density.clone())?
+                }
+                ("hopfion_axis", Some(field)) => ScriptArray::vector(field.axis.clone()),
+                ("events", _) => ScriptArray::vector(
+                    eqgft_config(&command.parameters)
+                        .and_then(|config| self.eqgft_cache.events(&config))
+                        .map(|events| events.to_vec())
+                        .map_err(|e| Error::InvalidParameter("arrays".to_string(), e.to_string()))?,
+                ),
+                _ => {
+                    return Err(Error::InvalidParameter(
+                        "arrays".to_string(),
+                        format!("unknown array '{}'", name),
+                    ))
+                }
+            };
+            arrays.insert(name.to_string(), array);
+        }
+        Ok(arrays)
+    }
+
+    fn finish_script_task(
+        &self,
+        task_id: Uuid,
+        outcome: Result<ScriptOutput>,
+    ) -> Result<TaskExecutionResult> {
+        let metrics = self.get_metrics();
+        let mut tasks = self.lock_tasks();
+        let info = tasks
+            .get_mut(&task_id)
+            .ok_or_else(|| Error::TaskExecution(format!("Task with ID {} not found", task_id)))?;
+
+        let output = match outcome {
+            Ok(output) => output,
+            Err(err) => {
+                info.set_status(TaskStatus::Failed(err.to_string()));
+                self.publish_transition(task_id, &info.status);
+                return Err(err);
+            }
+        };
+        let error = output.failure_reason();
+        let artifacts: Vec<ArtifactSummary> = output
+            .artifacts
+            .into_iter()
+            .filter_map(|artifact| {
+                self.artifacts
+                    .insert(&artifact.name, &artifact.content_type, artifact.bytes)
+                    .map_err(|err| warn!("Dropping artifact {}: {}", artifact.name, err))
+                    .ok()
+            })
+            .collect();
+        info.set_status(match &error {
+            Some(reason) => TaskStatus::Failed(reason.clone()),
+            None => TaskStatus::Completed(metrics.clone()),
+        });
+        self.publish_transition(task_id, &info.status);
+        drop(tasks);
+        if error.is_none() {
+            self.record_metrics(task_id, GeometricOperator::CustomPythonScript, &metrics);
+        }
+
+        Ok(TaskExecutionResult {
+            task_id,
+            success: error.is_none(),
+            metrics,
+            output: serde_json::json!({
+                "status": if error.is_none() { "completed" } else { "failed" },
+                "stdout": output.stdout,
+                "stderr": output.stderr,
+                "exit_code": output.exit_code,
+                "timed_out": output.timed_out,
+                "duration_ms": output.duration_ms,
+                "result": output.result,
+                "arrays": output.arrays,
+                "artifacts": artifacts,
+            }),
+            error,
+        })
+    }
+
+    /// A failed snapshot is logged rather than failing the task.
+    fn record_metrics(
+        &self,
+        task_id: Uuid,
+        operator: GeometricOperator,
+        metrics: &GeometricMetrics,
+    ) {
+        let snapshot =
+            MetricsSnapshot::now(Some(task_id), metrics.clone()).with_operator(operator);
+        if let Some(history) = &self.metrics_history {
+            if let Err(e) = history.push(snapshot.clone()) {
+                warn!("Failed to record metrics history: {}", e);
+            }
+        }
+        self.events.publish(StateEvent::MetricsUpdated(snapshot));
+    }
+
     /// Simulate task execution (placeholder for actual implementation)
-    fn simulate_task_execution(&self, task: &GeometricTaskCommand) -> Result<GeometricMetrics> {
-        let mut metrics = self.metrics.lock().map_err(|e| {
-            error!("Failed to lock metrics: {}", e);
-            Error::TaskExecution("Failed to access metrics".to_string())
-        })?;
+    fn simulate_task_execution(
+        &self,
+        task_id: Uuid,
+        task: &GeometricTaskCommand,
+    ) -> Result<(GeometricMetrics, Option<serde_json::Value>)> {
+        let mut emergence = self.lock_emergence();
+
+        // drawn here rather than inside the simulation, so replay repeats it
+        let (parameters, seed) = resolve_seeds(task.geometric_operator, &task.parameters);
+        let updated = emergence
+            .apply_operator(task.geometric_operator, &parameters)
+            .clone();
+        self.metrics.store(Arc::new(updated.clone()));
+        self.journal_mutation(|| Mutation::OperatorApplied {
+            task_id,
+            operator: task.geometric_operator,
+            parameters,
+            seed,
+            metrics: Some(updated.clone()),
+        });
+
+        Ok((updated, emergence.take_output()))
+    }
+
+    /// Evaluate tasks in parallel against copies of the current emergence
+    /// state without committing any of them.
+    pub fn evaluate_isolated(&self, tasks: &[GeometricTaskCommand]) -> Result<Vec<GeometricMetrics>> {
+        let baseline = self.lock_emergence().clone();
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = tasks
+                .iter()
+                .map(|task| {
+                    let mut emergence = baseline.clone();
+                    scope.spawn(move || {
+                        emergence
+                            .apply_operator(task.geometric_operator, &task.parameters)
+                            .clone()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle.join().map_err(|_| {
+                        Error::TaskExecution("Isolated task evaluation panicked".to_string())
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// Release a task held by the script policy so it can be executed.
+    pub fn approve_task(&self, task_id: Uuid) -> Result<()> {
+        self.approve_task_by(task_id, None)
+    }
 
-        let mut emergence = self.emergence.lock().map_err(|e| {
-            error!("Failed to lock emergence logic: {}", e);
-            Error::TaskExecution("Failed to access emergence logic".to_string())
-        })?;
+    /// `approve_task` on behalf of `approver`, refused when they submitted
+    /// the task themselves.
+    pub fn approve_task_by(&self, task_id: Uuid, approver: Option<&str>) -> Result<()> {
+        let mut tasks = self.lock_tasks();
 
-        let updated = emergence.apply_operator(task.geometric_operator, &task.parameters);
-        *metrics = updated.clone();
+        let info = tasks.get_mut(&task_id).ok_or(Error::TaskNotFound(task_id))?;
+        if info.status != TaskStatus::AwaitingApproval {
+            return Err(Error::InvalidParameter(
+                "task_id".into(),
+                format!("task {} is not awaiting approval", task_id),
+            ));
+        }
+        if approver.is_some() && approver == info.submitted_by.as_deref() {
+            return Err(Error::Forbidden(format!(
+                "task {} was submitted by {}, who cannot also approve it",
+                task_id,
+                approver.unwrap_or_default()
+            )));
+        }
 
-        Ok(metrics.clone())
+        info!("Approved script task {}: {}", task_id, info.command.task_name);
+        info.status = TaskStatus::Pending;
+        self.publish_transition(task_id, &info.status);
+        self.journal_mutation(|| Mutation::TaskApproved { task_id });
+        Ok(())
+    }
+
+    /// Get the command a task was submitted with
+    pub fn get_task_command(&self, task_id: Uuid) -> Result<GeometricTaskCommand> {
+        let tasks = self.lock_tasks();
+
+        tasks
+            .get(&task_id)
+            .map(|info| info.command.clone())
+            .ok_or(Error::TaskNotFound(task_id))
     }
 
     /// Get the status of a task
     pub fn get_task_status(&self, task_id: Uuid) -> Result<TaskStatus> {
-        let tasks = self.tasks.lock().map_err(|e| {
-            error!("Failed to lock tasks: {}", e);
-            Error::TaskExecution("Failed to access task storage".to_string())
-        })?;
+        let tasks = self.lock_tasks();
 
         tasks
             .get(&task_id)
@@ -156,28 +785,148 @@ impl SemanticTaskProcessor {
             .ok_or_else(|| Error::TaskExecution(format!("Task with ID {} not found", task_id)))
     }
 
-    /// Get the current metrics
-    pub fn get_metrics(&self) -> Result<GeometricMetrics> {
-        let metrics = self.metrics.lock().map_err(|e| {
-            error!("Failed to lock metrics: {}", e);
-            Error::TaskExecution("Failed to access metrics".to_string())
-        })?;
+    /// A copy of the current metrics; see [`Self::metrics`] to read them
+    /// without one.
+    pub fn get_metrics(&self) -> GeometricMetrics {
+        GeometricMetrics::clone(&self.metrics.load())
+    }
 
-        Ok(metrics.clone())
+    /// The current metrics, as of the last completed write.
+    pub fn metrics(&self) -> Arc<GeometricMetrics> {
+        self.metrics.load_full()
+    }
+
+    /// The Hopfion field generated by the last `GenerateHopfionField` task, if any.
+    pub fn hopfion_field(&self) -> Result<Option<Arc<HopfionSolitonField>>> {
+        let emergence = self.lock_emergence();
+
+        Ok(emergence.hopfion_field().cloned())
     }
 
     /// List all known tasks with their statuses
     pub fn list_tasks(&self) -> Result<Vec<(Uuid, TaskStatus)>> {
-        let tasks = self.tasks.lock().map_err(|e| {
-            error!("Failed to lock tasks: {}", e);
-            Error::TaskExecution("Failed to access task storage".to_string())
-        })?;
+        let tasks = self.lock_tasks();
 
         Ok(tasks
             .iter()
             .map(|(id, info)| (*id, info.status.clone()))
             .collect())
     }
+
+    pub fn task_entry(&self, task_id: Uuid) -> Result<TaskEntry> {
+        let tasks = self.lock_tasks();
+
+        tasks
+            .get(&task_id)
+            .map(|info| TaskEntry {
+                task_id,
+                command: info.command.clone(),
+                status: info.status.clone(),
+                submitted_at: info.submitted_at,
+                submitted_by: info.submitted_by.clone(),
+                finished_at: info.finished_at,
+            })
+            .ok_or(Error::TaskNotFound(task_id))
+    }
+
+    /// Take over a task as another server last saw it, returning whether
+    /// anything changed. A change is published like a local transition but
+    /// not journaled.
+    pub fn apply_task_entry(&self, entry: TaskEntry) -> Result<bool> {
+        let mut tasks = self.lock_tasks();
+
+        if let Some(info) = tasks.get(&entry.task_id) {
+            if info.status == entry.status {
+                return Ok(false);
+            }
+        }
+        // not `publish_transition`: the replica that ran the task counts it
+        self.events.publish(StateEvent::TaskTransition {
+            task_id: entry.task_id,
+            status: entry.status.clone(),
+        });
+        tasks.insert(
+            entry.task_id,
+            TaskInfo {
+                command: entry.command,
+                status: entry.status,
+                submitted_at: entry.submitted_at,
+                submitted_by: entry.submitted_by,
+                finished_at: entry.finished_at,
+            },
+        );
+        Ok(true)
+    }
+
+    /// Continue from `metrics` computed by another server, keeping the
+    /// Hopfion field, returning whether they differed from these.
+    pub fn apply_metrics(&self, metrics: GeometricMetrics) -> Result<bool> {
+        let mut emergence = self.lock_emergence();
+
+        if **self.metrics.load() == metrics {
+            return Ok(false);
+        }
+        emergence.adopt_metrics(metrics.clone());
+        self.metrics.store(Arc::new(metrics.clone()));
+        self.events
+            .publish(StateEvent::MetricsUpdated(MetricsSnapshot::now(None, metrics)));
+        Ok(true)
+    }
+
+    /// Every task as an export record, in submission order: `kind` is the
+    /// operator, `timestamp` the submission time in milliseconds, and the
+    /// payload carries the command and its current status.
+    pub fn task_records(&self) -> Result<Vec<MmssRecord>> {
+        let tasks = self.lock_tasks();
+
+        let mut entries: Vec<_> = tasks
+            .iter()
+            .map(|(id, info)| (*id, info, info.submitted_at))
+            .collect();
+        entries.sort_by_key(|&(_, _, at)| at);
+        task_records(entries)
+    }
+
+    /// The completed and failed tasks as `task_records` has them, but
+    /// timestamped and ordered by when they finished, so a record appears
+    /// once, in its final state, after every record exported before it.
+    pub fn finished_task_records(&self) -> Result<Vec<MmssRecord>> {
+        let tasks = self.lock_tasks();
+
+        let mut entries: Vec<_> = tasks
+            .iter()
+            .filter_map(|(id, info)| info.finished_at.map(|at| (*id, info, at)))
+            .collect();
+        entries.sort_by_key(|&(_, _, at)| at);
+        task_records(entries)
+    }
+}
+
+/// Records of `(task id, task, timestamp)` entries. A record's `id` is the
+/// first half of the task id, stable across calls and restarts.
+fn task_records(entries: Vec<(Uuid, &TaskInfo, DateTime<Utc>)>) -> Result<Vec<MmssRecord>> {
+    entries
+        .into_iter()
+        .map(|(id, info, at)| {
+            MmssRecord::builder()
+                .id(id.as_u64_pair().0)
+                .kind(Kind::new(format!("{:?}", info.command.geometric_operator))?)
+                .timestamp(at.timestamp_millis())
+                .payload(serde_json::json!({
+                    "task_id": id,
+                    "command": info.command,
+                    "status": info.status,
+                }))
+                .build()
+        })
+        .collect::<std::result::Result<_, RecordError>>()
+        .map_err(|e| Error::TaskExecution(format!("Invalid task record: {}", e)))
+}
+
+impl Default for SemanticTaskProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
@@ -203,6 +952,58 @@ mod tests {
         assert!(matches!(status, TaskStatus::Pending));
     }
 
+    #[test]
+    fn test_script_tasks_follow_policy() {
+        let script = GeometricTaskCommand {
+            task_name: "Script".to_string(),
+            geometric_operator: GeometricOperator::CustomPythonScript,
+            target_module: "test_module".to_string(),
+            parameters: serde_json::json!({ "script": "print(1)" }),
+            expected_output_metric: "v_geometric".to_string(),
+            task_id: None,
+        };
+
+        let rejecting = SemanticTaskProcessor::with_script_policy(ScriptPolicy::Reject);
+        assert!(matches!(
+            rejecting.submit_task(script.clone()),
+            Err(Error::PolicyViolation(_))
+        ));
+
+        let gated = SemanticTaskProcessor::with_script_policy(ScriptPolicy::RequireApproval);
+        let task_id = gated.submit_task(script).unwrap();
+        assert_eq!(gated.get_task_status(task_id).unwrap(), TaskStatus::AwaitingApproval);
+        assert!(matches!(gated.execute_task(task_id), Err(Error::PolicyViolation(_))));
+
+        gated.approve_task(task_id).unwrap();
+        assert_eq!(gated.get_task_status(task_id).unwrap(), TaskStatus::Pending);
+        assert!(gated.approve_task(task_id).is_err());
+    }
+
+    #[test]
+    fn test_submitter_cannot_approve_own_script_task() {
+        let script = GeometricTaskCommand {
+            task_name: "Scripted".to_string(),
+            geometric_operator: GeometricOperator::QuaternionRotation,
+            target_module: "test_module".to_string(),
+            parameters: serde_json::json!({ "script": "print(1)" }),
+            expected_output_metric: "v_geometric".to_string(),
+            task_id: None,
+        };
+        let gated = SemanticTaskProcessor::with_script_policy(ScriptPolicy::RequireApproval);
+        let task_id = gated
+            .submit_task_by(script, Some("key:0123456789ab".to_string()))
+            .unwrap();
+
+        assert!(matches!(
+            gated.approve_task_by(task_id, Some("key:0123456789ab")),
+            Err(Error::Forbidden(_))
+        ));
+        assert_eq!(gated.get_task_status(task_id).unwrap(), TaskStatus::AwaitingApproval);
+
+        gated.approve_task_by(task_id, Some("admin")).unwrap();
+        assert_eq!(gated.get_task_status(task_id).unwrap(), TaskStatus::Pending);
+    }
+
     #[test]
     fn test_task_execution() {
         let processor = SemanticTaskProcessor::new();
@@ -225,10 +1026,173 @@ mod tests {
         assert!(matches!(status, TaskStatus::Completed(_)));
     }
 
+    #[test]
+    fn test_queue_depth_and_ops_metrics() {
+        let processor = SemanticTaskProcessor::with_script_policy(ScriptPolicy::RequireApproval);
+        let task = |operator, parameters| GeometricTaskCommand {
+            task_name: "Counted".to_string(),
+            geometric_operator: operator,
+            target_module: "test_module".to_string(),
+            parameters,
+            expected_output_metric: "v_geometric".to_string(),
+            task_id: None,
+        };
+
+        let gated = processor
+            .submit_task(task(
+                GeometricOperator::CustomPythonScript,
+                serde_json::json!({ "script": "print(1)" }),
+            ))
+            .unwrap();
+        let rotation = processor
+            .submit_task(task(
+                GeometricOperator::QuaternionRotation,
+                serde_json::json!({}),
+            ))
+            .unwrap();
+        let depth = processor.queue_depth().unwrap();
+        assert_eq!((depth.awaiting_approval, depth.pending), (1, 1));
+
+        assert!(processor.execute_task(gated).is_err());
+        processor.execute_task(rotation).unwrap();
+        let depth = processor.queue_depth().unwrap();
+        assert_eq!((depth.awaiting_approval, depth.pending), (1, 0));
+
+        // the refused script task never ran, so only the rotation counts
+        let stats = processor.ops_metrics().stats(depth).unwrap();
+        assert_eq!(stats.tasks.len(), 1);
+        assert_eq!(stats.tasks["QuaternionRotation"].completed, 1);
+    }
+
+    #[test]
+    fn test_constants_override_si() {
+        let natural = PhysicalConstants {
+            hbar: 1.0,
+            c: 1.0,
+            zitter_amplitude: 0.25,
+            ..PhysicalConstants::SI
+        };
+        let processor = SemanticTaskProcessor::new().with_constants(natural);
+        assert_eq!(processor.get_metrics().emergent_electron_mass, 2.0);
+
+        let task = GeometricTaskCommand {
+            task_name: "Half amplitude".to_string(),
+            geometric_operator: GeometricOperator::Zitterbewegung,
+            target_module: "test_module".to_string(),
+            parameters: serde_json::json!({ "frequency_scale": 2.0 }),
+            expected_output_metric: "emergent_electron_mass".to_string(),
+            task_id: None,
+        };
+        let task_id = processor.submit_task(task).unwrap();
+        let result = processor.execute_task(task_id).unwrap();
+        assert_eq!(result.metrics.emergent_electron_mass, 4.0);
+        assert!(natural.invalid().is_empty());
+        assert_eq!(
+            PhysicalConstants { c: 0.0, ..natural }.invalid(),
+            vec!["c"]
+        );
+    }
+
+    #[test]
+    fn test_restore_pending_tasks_and_metrics() {
+        let processor = SemanticTaskProcessor::new();
+        let task = GeometricTaskCommand {
+            task_name: "Later".to_string(),
+            geometric_operator: GeometricOperator::GeometricDerivation,
+            target_module: "test_module".to_string(),
+            parameters: serde_json::json!({ "delta": 2.0 }),
+            expected_output_metric: "s_geometric".to_string(),
+            task_id: None,
+        };
+        let task_id = processor.submit_task(task).unwrap();
+        let mut metrics = processor.get_metrics();
+        metrics.quaternion_coherence = 0.5;
+        let pending = processor.pending_tasks().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert!(!pending[0].awaiting_approval);
+
+        let restarted = SemanticTaskProcessor::new();
+        restarted.restore_metrics(metrics.clone(), None).unwrap();
+        assert_eq!(restarted.restore_pending_tasks(pending.clone()).unwrap(), 1);
+        // the id is now taken
+        assert_eq!(restarted.restore_pending_tasks(pending).unwrap(), 0);
+        assert_eq!(restarted.get_metrics(), metrics);
+
+        let result = restarted.execute_task(task_id).unwrap();
+        assert_eq!(result.metrics.quaternion_coherence, 0.5);
+        assert!(restarted.pending_tasks().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_apply_replicated_tasks_and_metrics() {
+        let origin = SemanticTaskProcessor::new();
+        let task = GeometricTaskCommand {
+            task_name: "Elsewhere".to_string(),
+            geometric_operator: GeometricOperator::QuaternionRotation,
+            target_module: "test_module".to_string(),
+            parameters: serde_json::json!({ "theta": 1.0 }),
+            expected_output_metric: "v_geometric".to_string(),
+            task_id: None,
+        };
+        let task_id = origin.submit_task(task).unwrap();
+        let replica = SemanticTaskProcessor::new();
+        assert!(replica.apply_task_entry(origin.task_entry(task_id).unwrap()).unwrap());
+        assert!(!replica.apply_task_entry(origin.task_entry(task_id).unwrap()).unwrap());
+
+        let result = origin.execute_task(task_id).unwrap();
+        assert!(replica.apply_task_entry(origin.task_entry(task_id).unwrap()).unwrap());
+        assert!(replica.apply_metrics(result.metrics.clone()).unwrap());
+        assert!(!replica.apply_metrics(result.metrics.clone()).unwrap());
+        assert_eq!(
+            replica.get_task_status(task_id).unwrap(),
+            TaskStatus::Completed(result.metrics.clone())
+        );
+
+        // the next operator continues from the adopted metrics
+        let next = GeometricTaskCommand {
+            task_name: "Here".to_string(),
+            geometric_operator: GeometricOperator::GeometricDerivation,
+            target_module: "test_module".to_string(),
+            parameters: serde_json::json!({ "delta": 1.0 }),
+            expected_output_metric: "s_geometric".to_string(),
+            task_id: None,
+        };
+        let next_id = replica.submit_task(next).unwrap();
+        let next_result = replica.execute_task(next_id).unwrap();
+        assert_eq!(
+            next_result.metrics.quaternion_coherence,
+            result.metrics.quaternion_coherence
+        );
+    }
+
+    #[test]
+    fn test_isolated_evaluation_does_not_commit() {
+        let processor = SemanticTaskProcessor::new();
+        let initial_metrics = processor.get_metrics();
+
+        let tasks: Vec<_> = [0.5, 1.5]
+            .iter()
+            .map(|scale| GeometricTaskCommand {
+                task_name: "Candidate".to_string(),
+                geometric_operator: GeometricOperator::Zitterbewegung,
+                target_module: "test_module".to_string(),
+                parameters: serde_json::json!({ "frequency_scale": scale }),
+                expected_output_metric: "topological_winding".to_string(),
+                task_id: None,
+            })
+            .collect();
+
+        let outcomes = processor.evaluate_isolated(&tasks).unwrap();
+
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes[0].topological_winding < outcomes[1].topological_winding);
+        assert_eq!(processor.get_metrics(), initial_metrics);
+    }
+
     #[test]
     fn test_metrics_consistency() {
         let processor = SemanticTaskProcessor::new();
-        let initial_metrics = processor.get_metrics().unwrap();
+        let initial_metrics = processor.get_metrics();
 
         let task = GeometricTaskCommand {
             task_name: "Test Task".to_string(),
@@ -242,10 +1206,315 @@ mod tests {
         let task_id = processor.submit_task(task).unwrap();
         let _ = processor.execute_task(task_id).unwrap();
 
-        let updated_metrics = processor.get_metrics().unwrap();
+        let updated_metrics = processor.get_metrics();
 
         assert!(updated_metrics.v_geometric > initial_metrics.v_geometric);
         assert!(updated_metrics.s_geometric >= initial_metrics.s_geometric);
         assert!(updated_metrics.q_oscillator >= initial_metrics.q_oscillator);
     }
+
+    #[test]
+    fn test_hopfion_field_sets_topological_winding() {
+        let processor = SemanticTaskProcessor::new();
+        let task = GeometricTaskCommand {
+            task_name: "Hopfion".to_string(),
+            geometric_operator: GeometricOperator::GenerateHopfionField,
+            target_module: "sys5_topology".to_string(),
+            parameters: serde_json::json!({ "hopf_index": 2, "resolution": 41, "extent": 6.0 }),
+            expected_output_metric: "topological_winding".to_string(),
+            task_id: None,
+        };
+
+        let task_id = processor.submit_task(task).unwrap();
+        let metrics = processor.execute_task(task_id).unwrap().metrics;
+
+        assert!((metrics.topological_winding - 2.0).abs() < 0.1);
+        assert!(metrics.custom_metrics["hopfion_energy"] > 0.0);
+    }
+
+    #[test]
+    fn test_unknown_hopfion_backend_is_rejected_at_submission() {
+        let processor = SemanticTaskProcessor::new();
+        let task = GeometricTaskCommand {
+            task_name: "Hopfion".to_string(),
+            geometric_operator: GeometricOperator::GenerateHopfionField,
+            target_module: "sys5_topology".to_string(),
+            parameters: serde_json::json!({ "resolution": 17, "backend": "tpu" }),
+            expected_output_metric: "topological_winding".to_string(),
+            task_id: None,
+        };
+
+        match processor.submit_task(task) {
+            Err(Error::InvalidParameter(name, _)) => assert_eq!(name, "backend"),
+            other => panic!("expected InvalidParameter, got {:?}", other),
+        }
+        assert!(processor.pending_tasks().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_asymmetry_simulation_reports_custom_metrics() {
+        let processor = SemanticTaskProcessor::new();
+        let task = GeometricTaskCommand {
+            task_name: "Asymmetry".to_string(),
+            geometric_operator: GeometricOperator::SimulateEqgftAsymmetry,
+            target_module: "eqgft".to_string(),
+            parameters: serde_json::json!({ "kappa": 0.2, "n_events": 10_000, "seed": 3 }),
+            expected_output_metric: "eqgft_asymmetry".to_string(),
+            task_id: None,
+        };
+
+        let task_id = processor.submit_task(task).unwrap();
+        let custom = processor.execute_task(task_id).unwrap().metrics.custom_metrics;
+
+        assert!(custom["eqgft_asymmetry_uncertainty"] > 1e-4);
+        assert_eq!(custom["eqgft_asymmetry_syst_error"], 1e-4);
+        let stat_error = custom["eqgft_asymmetry_stat_error"];
+        assert!((stat_error.hypot(1e-4) - custom["eqgft_asymmetry_uncertainty"]).abs() < 1e-15);
+        assert!((custom["eqgft_predicted_asymmetry"] - 0.2 * 0.0072973525693).abs() < 1e-12);
+        assert!(custom["eqgft_ci_low"] < custom["eqgft_asymmetry"]);
+        assert!(custom["eqgft_asymmetry"] < custom["eqgft_ci_high"]);
+        assert!((0.0..=1.0).contains(&custom["eqgft_p_value"]));
+        // 1e-4 systematics keep 5σ reachable for κ = 0.2
+        assert!(custom["eqgft_required_events"] > 1e7);
+    }
+
+    #[test]
+    fn test_kappa_scan_returns_curve_in_output() {
+        let processor = SemanticTaskProcessor::new();
+        let task = GeometricTaskCommand {
+            task_name: "Scan".to_string(),
+            geometric_operator: GeometricOperator::SimulateEqgftKappaScan,
+            target_module: "eqgft".to_string(),
+            parameters: serde_json::json!({
+                "kappa_min": 0.0,
+                "kappa_max": 1.0,
+                "steps": 3,
+                "n_events": 5_000,
+                "seed": 2,
+            }),
+            expected_output_metric: "eqgft_scan_max_significance".to_string(),
+            task_id: None,
+        };
+
+        let task_id = processor.submit_task(task.clone()).unwrap();
+        let result = processor.execute_task(task_id).unwrap();
+
+        let curve = result.output["result"].as_array().unwrap();
+        assert_eq!(curve.len(), 3);
+        assert_eq!(curve[2]["kappa"], 1.0);
+        assert_eq!(result.metrics.custom_metrics["eqgft_scan_points"], 3.0);
+
+        let mut reweighted = task;
+        reweighted.parameters["reweight"] = serde_json::json!(true);
+        let task_id = processor.submit_task(reweighted).unwrap();
+        let result = processor.execute_task(task_id).unwrap();
+        let curve = result.output["result"].as_array().unwrap();
+        assert_eq!(curve.len(), 3);
+        assert_eq!(curve[1]["kappa"], 0.5);
+    }
+
+    #[test]
+    fn test_channel_combination_reports_combined_significance() {
+        let processor = SemanticTaskProcessor::new();
+        let task = GeometricTaskCommand {
+            task_name: "Channels".to_string(),
+            geometric_operator: GeometricOperator::CombineEqgftChannels,
+            target_module: "eqgft".to_string(),
+            parameters: serde_json::json!({
+                "kappa": 0.5,
+                "seed": 5,
+                "channels": [
+                    { "name": "pi", "sensitivity": 1.0, "n_events": 5_000 },
+                    { "name": "rho", "sensitivity": 0.5, "n_events": 5_000 },
+                ],
+            }),
+            expected_output_metric: "eqgft_combined_significance".to_string(),
+            task_id: None,
+        };
+
+        let task_id = processor.submit_task(task).unwrap();
+        let result = processor.execute_task(task_id).unwrap();
+
+        let channels = result.output["result"]["channels"].as_array().unwrap();
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[1]["name"], "rho");
+        let custom = &result.metrics.custom_metrics;
+        assert_eq!(custom["eqgft_channels"], 2.0);
+        assert!(custom["eqgft_combined_uncertainty"] > 0.0);
+    }
+
+    #[test]
+    fn test_asymmetry_bootstrap_from_task_parameters() {
+        let processor = SemanticTaskProcessor::new();
+        let task = GeometricTaskCommand {
+            task_name: "Bootstrap".to_string(),
+            geometric_operator: GeometricOperator::SimulateEqgftAsymmetry,
+            target_module: "eqgft".to_string(),
+            parameters: serde_json::json!({
+                "n_events": 2_000,
+                "seed": 1,
+                "bootstrap_resamples": 50,
+                "bootstrap_seed": 2,
+            }),
+            expected_output_metric: "eqgft_asymmetry".to_string(),
+            task_id: None,
+        };
+
+        let task_id = processor.submit_task(task).unwrap();
+        let result = processor.execute_task(task_id).unwrap();
+
+        assert_eq!(result.output["result"]["resamples"], 50);
+        assert!(result.metrics.custom_metrics["eqgft_bootstrap_std_error"] > 0.0);
+    }
+
+    #[test]
+    fn test_detector_parameters_reduce_detected_events() {
+        let processor = SemanticTaskProcessor::new();
+        let task = GeometricTaskCommand {
+            task_name: "Detector".to_string(),
+            geometric_operator: GeometricOperator::SimulateEqgftAsymmetry,
+            target_module: "eqgft".to_string(),
+            parameters: serde_json::json!({
+                "n_events": 10_000,
+                "seed": 5,
+                "efficiency": 0.5,
+                "acceptance_min": -0.8,
+                "acceptance_max": 0.8,
+            }),
+            expected_output_metric: "eqgft_asymmetry".to_string(),
+            task_id: None,
+        };
+
+        let task_id = processor.submit_task(task).unwrap();
+        let custom = processor.execute_task(task_id).unwrap().metrics.custom_metrics;
+
+        assert!(custom["eqgft_detected_events"] < 5_000.0);
+    }
+
+    #[test]
+    fn test_allowed_script_runs_in_subprocess() {
+        let processor = SemanticTaskProcessor::with_script_policy(ScriptPolicy::Allow);
+        let script = GeometricTaskCommand {
+            task_name: "Script".to_string(),
+            geometric_operator: GeometricOperator::CustomPythonScript,
+            target_module: "analysis".to_string(),
+            parameters: serde_json::json!({
+                "script": "print(sum(range(params['n'])))\nresult = {'n': params['n']}",
+                "n": 5,
+            }),
+            expected_output_metric: "v_geometric".to_string(),
+            task_id: None,
+        };
+
+        let task_id = processor.submit_task(script.clone()).unwrap();
+        let result = processor.execute_task(task_id).unwrap();
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.output["stdout"].as_str().unwrap().trim(), "10");
+        assert_eq!(result.output["result"]["n"], 5);
+
+        let plotting = GeometricTaskCommand {
+            parameters: serde_json::json!({ "script": "save_artifact('plot.png', b'png', 'image/png')" }),
+            ..script.clone()
+        };
+        let task_id = processor.submit_task(plotting).unwrap();
+        let result = processor.execute_task(task_id).unwrap();
+        let id: Uuid = serde_json::from_value(result.output["artifacts"][0]["id"].clone()).unwrap();
+        let artifact = processor.artifacts().get(id).unwrap().unwrap();
+        assert_eq!(artifact.summary.content_type, "image/png");
+        assert_eq!(artifact.bytes, b"png");
+
+        let failing = GeometricTaskCommand {
+            parameters: serde_json::json!({ "script": "import subprocess" }),
+            ..script
+        };
+        let task_id = processor.submit_task(failing).unwrap();
+        let result = processor.execute_task(task_id).unwrap();
+        assert!(!result.success);
+        assert!(matches!(
+            processor.get_task_status(task_id).unwrap(),
+            TaskStatus::Failed(_)
+        ));
+    }
+
+    #[test]
+    fn test_script_receives_hopfion_and_event_arrays() {
+        let processor = SemanticTaskProcessor::with_script_policy(ScriptPolicy::Allow);
+        let script = GeometricTaskCommand {
+            task_name: "Field analysis".to_string(),
+            geometric_operator: GeometricOperator::CustomPythonScript,
+            target_module: "analysis".to_string(),
+            parameters: serde_json::json!({
+                "script": "q = arrays['hopfion_q']\n\
+                           result = {'shape': list(q.shape), 'centre': q[4, 4, 4, 0], 'events': len(arrays['events'])}\n\
+                           result_arrays = {'q0': [q[i, 4, 4, 0] for i in range(q.shape[0])]}",
+                "arrays": ["hopfion_q", "events"],
+                "n_events": 1_000,
+                "seed": 5,
+            }),
+            expected_output_metric: "v_geometric".to_string(),
+            task_id: None,
+        };
+
+        // no field yet
+        let task_id = processor.submit_task(script.clone()).unwrap();
+        assert!(processor.execute_task(task_id).is_err());
+
+        let hopfion = GeometricTaskCommand {
+            task_name: "Hopfion".to_string(),
+            geometric_operator: GeometricOperator::GenerateHopfionField,
+            target_module: "sys5_topology".to_string(),
+            parameters: serde_json::json!({ "resolution": 9 }),
+            expected_output_metric: "topological_winding".to_string(),
+            task_id: None,
+        };
+        let task_id = processor.submit_task(hopfion).unwrap();
+        processor.execute_task(task_id).unwrap();
+
+        let task_id = processor.submit_task(script).unwrap();
+        let result = processor.execute_task(task_id).unwrap();
+        assert!(result.success, "{:?}", result.output);
+        assert_eq!(result.output["result"]["shape"], serde_json::json!([9, 9, 9, 4]));
+        assert!((result.output["result"]["centre"].as_f64().unwrap() + 1.0).abs() < 1e-12);
+        assert_eq!(result.output["result"]["events"], 1_000);
+        assert_eq!(result.output["arrays"]["q0"]["shape"], serde_json::json!([9]));
+    }
+
+    #[test]
+    fn test_keeps_serving_after_a_panic_under_the_locks() {
+        let processor = SemanticTaskProcessor::new();
+        let task = GeometricTaskCommand {
+            task_name: "Rotation".to_string(),
+            geometric_operator: GeometricOperator::QuaternionRotation,
+            target_module: "test_module".to_string(),
+            parameters: serde_json::json!({}),
+            expected_output_metric: "v_geometric".to_string(),
+            task_id: None,
+        };
+        let task_id = processor.submit_task(task.clone()).unwrap();
+        let running = processor.submit_task(task.clone()).unwrap();
+        let published = processor.get_metrics();
+
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut tasks = processor.tasks.lock().unwrap();
+            let mut emergence = processor.emergence.lock().unwrap();
+            tasks.get_mut(&running).unwrap().status = TaskStatus::InProgress;
+            emergence.adopt_metrics(GeometricMetrics {
+                v_geometric: -1.0,
+                ..published.clone()
+            });
+            panic!("operator panicked");
+        }));
+        assert!(panicked.is_err());
+
+        assert_eq!(processor.get_task_status(task_id).unwrap(), TaskStatus::Pending);
+        assert!(matches!(
+            processor.get_task_status(running).unwrap(),
+            TaskStatus::Failed(_)
+        ));
+        assert_eq!(processor.get_metrics(), published);
+        assert_eq!(*processor.lock_emergence().metrics(), published);
+        let task_id = processor.submit_task(task).unwrap();
+        assert!(processor.execute_task(task_id).is_ok());
+        assert!(processor.lock_stats().iter().all(|stats| stats.recovered == 1));
+    }
 }
@@ -1,19 +1,9 @@
+use crate::core::geometric_metrics::DeltaRule;
+use mmss_eqgft::hopfion::HopfionConfig;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use uuid::Uuid;
 
-/// Geometric operators for the MMSS system
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-pub enum GeometricOperator {
-    /// Quaternion rotation operator (⟲Q)
-    QuaternionRotation,
-    /// Zitterbewegung operator (⥁Z)
-    Zitterbewegung,
-    /// Geometric derivation operator (⇛G)
-    GeometricDerivation,
-    /// Semantic synthesis operator (⥂S)
-    SemanticSynthesis,
-}
+pub use mmss_compute::types::{GeometricMetrics, GeometricOperator, MetricMap, Quaternion};
 
 /// Geometric task command structure for LLM interaction
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,45 +23,8 @@ pub struct GeometricTaskCommand {
     pub task_id: Option<Uuid>,
 }
 
-/// Quaternion type for geometric operations
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-pub struct Quaternion {
-    pub w: f64,
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
-}
-
-/// Geometric metrics for system monitoring
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct GeometricMetrics {
-    /// Geometric volume metric
-    pub v_geometric: f64,
-    /// Geometric stability metric
-    pub s_geometric: f64,
-    /// Oscillator quality factor
-    pub q_oscillator: f64,
-    /// Quaternion coherence (SYS7)
-    #[serde(default)]
-    pub quaternion_coherence: f64,
-    /// Emergent electron mass from zitterbewegung
-    #[serde(default)]
-    pub emergent_electron_mass: f64,
-    /// Fine structure constant derived from geometry
-    #[serde(default)]
-    pub fine_structure_constant: f64,
-    /// Zitterbewegung entropy (SYS6)
-    #[serde(default)]
-    pub zitterbewegung_entropy: f64,
-    /// Topological winding number (SYS5)
-    #[serde(default)]
-    pub topological_winding: f64,
-    /// Additional custom metrics
-    pub custom_metrics: HashMap<String, f64>,
-}
-
 /// Semantic anchor for linguistic elements
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SemanticAnchor {
     pub id: Uuid,
     pub name: String,
@@ -98,4 +51,57 @@ pub struct SystemState {
     pub metrics: GeometricMetrics,
     pub active_anchors: Vec<SemanticAnchor>,
     pub active_tasks: Vec<Uuid>,
+    /// Rules registered over the API, registered again on load.
+    #[serde(default)]
+    pub rules: Vec<DeltaRule>,
+    /// The tasks behind `active_tasks`.
+    #[serde(default)]
+    pub pending_tasks: Vec<PendingTask>,
+    /// Parameters of the last Hopfion field, regenerated on load.
+    #[serde(default)]
+    pub hopfion: Option<HopfionConfig>,
+}
+
+/// A submitted task that has not run yet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingTask {
+    pub task_id: Uuid,
+    pub command: GeometricTaskCommand,
+    pub awaiting_approval: bool,
+    pub submitted_at: chrono::DateTime<chrono::Utc>,
+    /// Who submitted it, when known; they may not also approve it.
+    #[serde(default)]
+    pub submitted_by: Option<String>,
+}
+
+/// Components `POST /admin/reset` returns to baseline; none set means all.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResetScope {
+    /// Submitted tasks, and completed ones indexed for retrieval.
+    pub tasks: bool,
+    pub metrics: bool,
+    pub rules: bool,
+    pub anchors: bool,
+    /// The generated Hopfion field.
+    pub field: bool,
+}
+
+impl ResetScope {
+    pub const EVERYTHING: Self = Self {
+        tasks: true,
+        metrics: true,
+        rules: true,
+        anchors: true,
+        field: true,
+    };
+
+    /// `self`, or everything when nothing is selected.
+    pub fn or_everything(self) -> Self {
+        if self == Self::default() {
+            Self::EVERYTHING
+        } else {
+            self
+        }
+    }
 }
@@ -0,0 +1,300 @@
+// Live view of the MMSS server: metrics arrive over the visualization
+// WebSocket as full packets followed by deltas, tasks are polled over the
+// REST API. Every path is relative to the API mounted at `/api`.
+
+const API = '/api';
+const TRAJECTORY_POINTS = 500;
+const TASK_POLL_MS = 5000;
+const RECONNECT_MS = 2000;
+
+const connectionEl = document.querySelector('#connection');
+const sequenceEl = document.querySelector('#sequence');
+const cardsEl = document.querySelector('#metric-cards');
+const trajectoryMetricEl = document.querySelector('#trajectory-metric');
+const trajectoryEl = document.querySelector('#trajectory');
+const tasksEl = document.querySelector('#tasks');
+const taskJsonEl = document.querySelector('#task-json');
+const taskExecuteEl = document.querySelector('#task-execute');
+const taskResultEl = document.querySelector('#task-result');
+const previewTaskEl = document.querySelector('#preview-task');
+
+const metrics = new Map();
+const cards = new Map();
+// metric name -> [{ time, value }], oldest first
+const history = new Map();
+let sequence = null;
+
+async function api(path, options = {}) {
+  const res = await fetch(`${API}${path}`, {
+    headers: { 'Content-Type': 'application/json' },
+    ...options,
+  });
+  if (!res.ok) {
+    const body = await res.text();
+    let message = body;
+    try {
+      message = JSON.parse(body).message || body;
+    } catch (_) {
+      // errors raised before a handler ran are plain text
+    }
+    throw new Error(message || res.statusText);
+  }
+  return res.json();
+}
+
+// Packet metrics by name, as the server names them in deltas.
+function flatten(packetMetrics) {
+  const { custom_metrics: custom = {}, ...builtin } = packetMetrics;
+  return { ...builtin, ...custom };
+}
+
+function format(value) {
+  if (value === 0 || (Math.abs(value) >= 1e-3 && Math.abs(value) < 1e6)) {
+    return value.toFixed(6);
+  }
+  return value.toExponential(6);
+}
+
+function setMetric(name, value, time) {
+  metrics.set(name, value);
+  let card = cards.get(name);
+  if (!card) {
+    card = document.createElement('article');
+    card.className = 'card';
+    card.innerHTML = '<div class="name"></div><div class="value"></div>';
+    card.querySelector('.name').textContent = name;
+    cards.set(name, card);
+    cardsEl.append(card);
+
+    const option = document.createElement('option');
+    option.value = option.textContent = name;
+    trajectoryMetricEl.append(option);
+  }
+  card.querySelector('.value').textContent = format(value);
+  card.classList.add('changed');
+  requestAnimationFrame(() => card.classList.remove('changed'));
+  record(name, time, value);
+}
+
+function removeMetric(name) {
+  metrics.delete(name);
+  cards.get(name)?.remove();
+  cards.delete(name);
+  trajectoryMetricEl.querySelector(`option[value="${CSS.escape(name)}"]`)?.remove();
+}
+
+function record(name, time, value) {
+  const points = history.get(name) ?? [];
+  if (points.length && points[points.length - 1].time >= time) return;
+  points.push({ time, value });
+  if (points.length > TRAJECTORY_POINTS) points.shift();
+  history.set(name, points);
+}
+
+function applyFrame(frame) {
+  const time = Date.parse(frame.timestamp);
+  if (frame.type === 'full') {
+    const values = flatten(frame.metrics);
+    for (const name of [...metrics.keys()]) {
+      if (!(name in values)) removeMetric(name);
+    }
+    for (const [name, value] of Object.entries(values)) setMetric(name, value, time);
+  } else if (frame.type === 'delta') {
+    if (frame.base_sequence !== sequence) {
+      // a frame went missing; the server sends a full packet on reconnect
+      socket?.close();
+      return;
+    }
+    for (const [name, value] of Object.entries(frame.metrics)) setMetric(name, value, time);
+    frame.removed.forEach(removeMetric);
+  } else {
+    return;
+  }
+  sequence = frame.sequence;
+  sequenceEl.textContent = `packet #${sequence}`;
+  drawTrajectory();
+}
+
+let socket = null;
+
+function connect() {
+  const scheme = location.protocol === 'https:' ? 'wss' : 'ws';
+  socket = new WebSocket(`${scheme}://${location.host}${API}/ws/visualization?interval_ms=500`);
+  socket.addEventListener('open', () => {
+    connectionEl.textContent = 'live';
+    connectionEl.className = 'badge online';
+  });
+  socket.addEventListener('message', event => applyFrame(JSON.parse(event.data)));
+  socket.addEventListener('close', () => {
+    connectionEl.textContent = 'disconnected';
+    connectionEl.className = 'badge offline';
+    sequence = null;
+    setTimeout(connect, RECONNECT_MS);
+  });
+}
+
+// Seed the plots from the server's metrics history, when it keeps one.
+async function loadTrajectory() {
+  try {
+    const trajectory = await api(`/visualization/trajectory?limit=${TRAJECTORY_POINTS}`);
+    for (const [name, values] of Object.entries(trajectory.series)) {
+      values.forEach((value, i) => {
+        if (value !== null) record(name, trajectory.times[i], value);
+      });
+    }
+    drawTrajectory();
+  } catch {
+    // history disabled; plot what arrives over the socket
+  }
+}
+
+function drawTrajectory() {
+  const ctx = trajectoryEl.getContext('2d');
+  const width = (trajectoryEl.width = trajectoryEl.clientWidth);
+  const height = trajectoryEl.height;
+  ctx.clearRect(0, 0, width, height);
+
+  const points = history.get(trajectoryMetricEl.value) ?? [];
+  if (points.length < 2) {
+    ctx.fillStyle = '#6b7280';
+    ctx.fillText('Waiting for values…', 8, 20);
+    return;
+  }
+  const times = points.map(p => p.time);
+  const values = points.map(p => p.value);
+  const [t0, t1] = [Math.min(...times), Math.max(...times)];
+  let [v0, v1] = [Math.min(...values), Math.max(...values)];
+  if (v0 === v1) [v0, v1] = [v0 - 1, v1 + 1];
+  const pad = 16;
+  const x = t => pad + ((t - t0) / (t1 - t0 || 1)) * (width - 2 * pad);
+  const y = v => height - pad - ((v - v0) / (v1 - v0)) * (height - 2 * pad);
+
+  ctx.strokeStyle = '#2563eb';
+  ctx.lineWidth = 2;
+  ctx.beginPath();
+  points.forEach((p, i) => (i ? ctx.lineTo(x(p.time), y(p.value)) : ctx.moveTo(x(p.time), y(p.value))));
+  ctx.stroke();
+
+  ctx.fillStyle = '#6b7280';
+  ctx.fillText(format(v1), 4, 12);
+  ctx.fillText(format(v0), 4, height - 4);
+}
+
+function statusName(status) {
+  return typeof status === 'string' ? status : Object.keys(status)[0];
+}
+
+async function refreshTasks() {
+  try {
+    const tasks = await api('/tasks');
+    tasksEl.replaceChildren(
+      ...tasks.map(({ task_id, status }) => {
+        const name = statusName(status);
+        const row = document.createElement('tr');
+        row.innerHTML = '<td class="id"></td><td></td><td></td>';
+        row.cells[0].textContent = task_id.slice(0, 8);
+        row.cells[0].title = task_id;
+        row.cells[1].textContent = name;
+        row.cells[1].className = `status-${name}`;
+        if (name === 'Failed') row.cells[1].title = status.Failed;
+        if (name === 'AwaitingApproval') {
+          const approve = document.createElement('button');
+          approve.textContent = 'Approve';
+          approve.addEventListener('click', () => approveTask(task_id));
+          row.cells[2].append(approve);
+        }
+        return row;
+      }),
+    );
+  } catch (err) {
+    taskResultEl.textContent = `Failed to list tasks: ${err.message}`;
+  }
+}
+
+async function approveTask(taskId) {
+  try {
+    const result = await api(`/tasks/${taskId}/approve`, { method: 'POST' });
+    taskResultEl.textContent = JSON.stringify(result, null, 2);
+  } catch (err) {
+    taskResultEl.textContent = `Approval failed: ${err.message}`;
+  }
+  refreshTasks();
+}
+
+async function submitTask() {
+  let task;
+  try {
+    task = JSON.parse(taskJsonEl.value);
+  } catch (err) {
+    taskResultEl.textContent = `Invalid JSON: ${err.message}`;
+    return;
+  }
+  try {
+    const result = await api('/tasks', {
+      method: 'POST',
+      body: JSON.stringify({ task, execute: taskExecuteEl.checked }),
+    });
+    taskResultEl.textContent = JSON.stringify(result, null, 2);
+  } catch (err) {
+    taskResultEl.textContent = `Submission failed: ${err.message}`;
+  }
+  refreshTasks();
+}
+
+// The wasm build of mmss-compute, when it was built into `pkg/`; previews
+// run the same code as the server's cascade, so they match what the task
+// will do unless another task runs first.
+let compute = null;
+
+async function loadCompute() {
+  try {
+    const module = await import('/dashboard/pkg/mmss_compute.js');
+    await module.default();
+    compute = module;
+    previewTaskEl.hidden = false;
+  } catch {
+    // not built; tasks can still be submitted
+  }
+}
+
+async function previewTask() {
+  let task;
+  try {
+    task = JSON.parse(taskJsonEl.value);
+  } catch (err) {
+    taskResultEl.textContent = `Invalid JSON: ${err.message}`;
+    return;
+  }
+  try {
+    const [{ metrics: current }, constants] = await Promise.all([
+      api('/metrics'),
+      api('/metrics/constants'),
+    ]);
+    const previewed = JSON.parse(
+      compute.previewOperator(
+        JSON.stringify(current),
+        task.geometric_operator,
+        JSON.stringify(task.parameters ?? {}),
+        JSON.stringify(constants),
+      ),
+    );
+    const [before, after] = [flatten(current), flatten(previewed)];
+    const changes = Object.entries(after)
+      .filter(([name, value]) => before[name] !== value)
+      .map(([name, value]) => `${name}: ${name in before ? format(before[name]) : '—'} → ${format(value)}`);
+    taskResultEl.textContent = `Preview (not submitted)\n${changes.join('\n') || 'no metric changes'}`;
+  } catch (err) {
+    taskResultEl.textContent = `Preview failed: ${err.message ?? err}`;
+  }
+}
+
+document.querySelector('#refresh-tasks').addEventListener('click', refreshTasks);
+previewTaskEl.addEventListener('click', previewTask);
+document.querySelector('#submit-task').addEventListener('click', submitTask);
+trajectoryMetricEl.addEventListener('change', drawTrajectory);
+window.addEventListener('resize', drawTrajectory);
+
+loadTrajectory().then(connect);
+loadCompute();
+refreshTasks();
+setInterval(refreshTasks, TASK_POLL_MS);
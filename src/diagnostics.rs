@@ -0,0 +1,137 @@
+//! Runtime diagnostics for `GET /debug/runtime`: Tokio scheduler state,
+//! processor lock contention and process memory, to find where the server
+//! stalls under load.
+//!
+//! Blocking-pool counts need `--cfg tokio_unstable`; built with the
+//! `tokio-console` feature and that flag, the server also serves
+//! tokio-console (on `TOKIO_CONSOLE_BIND`, `127.0.0.1:6669` by default).
+
+use crate::core::lock_stats::LockStats;
+use serde::Serialize;
+use tokio::runtime::Handle;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokioStats {
+    pub workers: usize,
+    pub alive_tasks: usize,
+    /// Tasks scheduled from outside the workers and not yet picked up.
+    pub global_queue_depth: usize,
+    /// Time each worker spent running tasks since the runtime started; one
+    /// far ahead of the others while the rest idle points at a task that
+    /// does not yield.
+    pub worker_busy_ms: Vec<u64>,
+    /// Threads in the blocking pool, busy or idle; `None` without
+    /// `tokio_unstable`.
+    pub blocking_threads: Option<usize>,
+    pub idle_blocking_threads: Option<usize>,
+    /// Blocking calls waiting for a thread.
+    pub blocking_queue_depth: Option<usize>,
+}
+
+impl TokioStats {
+    pub fn current() -> Self {
+        let metrics = Handle::current().metrics();
+        let workers = metrics.num_workers();
+        #[cfg(tokio_unstable)]
+        let (blocking_threads, idle_blocking_threads, blocking_queue_depth) = (
+            Some(metrics.num_blocking_threads()),
+            Some(metrics.num_idle_blocking_threads()),
+            Some(metrics.blocking_queue_depth()),
+        );
+        #[cfg(not(tokio_unstable))]
+        let (blocking_threads, idle_blocking_threads, blocking_queue_depth) = (None, None, None);
+        Self {
+            workers,
+            alive_tasks: metrics.num_alive_tasks(),
+            global_queue_depth: metrics.global_queue_depth(),
+            worker_busy_ms: (0..workers)
+                .map(|worker| metrics.worker_total_busy_duration(worker).as_millis() as u64)
+                .collect(),
+            blocking_threads,
+            idle_blocking_threads,
+            blocking_queue_depth,
+        }
+    }
+}
+
+/// Process memory from `/proc/self/status`, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MemoryUsage {
+    pub resident_bytes: u64,
+    pub peak_resident_bytes: u64,
+    pub virtual_bytes: u64,
+}
+
+impl MemoryUsage {
+    /// `None` where `/proc` is not available.
+    pub fn current() -> Option<Self> {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        Self::parse(&status)
+    }
+
+    fn parse(status: &str) -> Option<Self> {
+        let field = |name: &str| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))?
+                .trim()
+                .strip_suffix("kB")?
+                .trim()
+                .parse::<u64>()
+                .ok()
+                .map(|kib| kib * 1024)
+        };
+        Some(Self {
+            resident_bytes: field("VmRSS")?,
+            peak_resident_bytes: field("VmHWM")?,
+            virtual_bytes: field("VmSize")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeReport {
+    pub tokio: TokioStats,
+    pub locks: Vec<LockStats>,
+    pub memory: Option<MemoryUsage>,
+    /// Whether tokio-console can attach.
+    pub console: bool,
+}
+
+impl RuntimeReport {
+    /// The runtime this is called on, with the given lock counters.
+    pub fn collect(locks: Vec<LockStats>) -> Self {
+        Self {
+            tokio: TokioStats::current(),
+            locks,
+            memory: MemoryUsage::current(),
+            console: cfg!(all(feature = "tokio-console", tokio_unstable)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_proc_status() {
+        let status = "Name:\tmmss\nVmHWM:\t  2048 kB\nVmRSS:\t  1024 kB\nVmSize:\t 8192 kB\n";
+        assert_eq!(
+            MemoryUsage::parse(status),
+            Some(MemoryUsage {
+                resident_bytes: 1024 * 1024,
+                peak_resident_bytes: 2048 * 1024,
+                virtual_bytes: 8192 * 1024,
+            })
+        );
+        assert_eq!(MemoryUsage::parse("Name:\tmmss\n"), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_reports_current_runtime() {
+        let report = RuntimeReport::collect(Vec::new());
+        assert_eq!(report.tokio.workers, 2);
+        assert_eq!(report.tokio.worker_busy_ms.len(), 2);
+    }
+}
@@ -1,9 +1,28 @@
 pub mod core {
+    pub mod artifacts;
+    pub mod audit_trail;
+    pub mod credentials;
     pub mod emergence_logic;
     pub mod eqgft_types;
+    pub mod events;
     pub mod error;
+    pub mod event_publisher;
+    pub mod exports;
+    #[cfg(feature = "flight")]
+    pub mod flight;
     pub mod geometric_metrics;
-    pub mod geometric_quaternion_core;
+    pub mod journal;
+    pub mod lock_stats;
+    pub mod metric_aggregates;
+    pub mod metrics_history;
+    pub mod mqtt_telemetry;
+    pub mod object_storage;
+    pub mod ops_metrics;
+    pub mod plugins;
+    pub mod query;
+    pub mod script_arrays;
+    pub mod script_policy;
+    pub mod script_runner;
     pub mod semantic_task_processor;
     pub mod types;
     
@@ -17,16 +36,40 @@ pub mod core {
 }
 
 pub mod api {
+    pub mod audit;
+    pub mod command_schema;
     pub mod data_io;
+    pub mod embeddings;
     pub mod llm_gateway;
+    pub mod llm_provider;
+    #[cfg(feature = "local-llm")]
+    pub mod local_llm;
+    pub mod mock_llm;
+    pub mod prompt_templates;
+    pub mod resilience;
+    pub mod sessions;
+    pub mod usage;
 }
 
 pub mod visualization {
+    pub mod charts;
+    pub mod gltf;
+    pub mod lod;
+    pub mod mesh;
+    pub mod ply;
     pub mod protocol;
+    pub mod slice;
+    pub mod streamlines;
+    pub mod style;
 }
 
+pub mod batch;
+pub mod campaign;
+pub mod config;
+pub mod diagnostics;
 pub mod routes;
 pub mod state;
+pub mod telemetry;
 
 pub use crate::core::error::{Error, Result};
 pub use crate::core::types::*;
@@ -0,0 +1,252 @@
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequestParts, Path, Query, State},
+    http::{header::AUTHORIZATION, request::Parts},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::convert::Infallible;
+
+use crate::api::prompt_templates::PromptTemplate;
+use crate::core::audit_trail::{Actor, AuditEntry, AuditTrailQuery, ChainReport, PrivilegedAction};
+use crate::core::error::Error;
+use crate::core::ops_metrics::OpsStats;
+use crate::core::types::{GeometricMetrics, ResetScope};
+use crate::state::AppState;
+use crate::telemetry;
+
+use super::llm::API_KEY_HEADER;
+use super::{bad_request, internal_error, not_found, ApiResult};
+
+/// Proof that the request carries `Authorization: Bearer <MMSS_ADMIN_TOKEN>`.
+/// Without a configured token every gated route answers 403.
+pub struct AdminAuth;
+
+#[async_trait]
+impl FromRequestParts<AppState> for AdminAuth {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        if !state.credentials.has_admin_token() {
+            return Err(Error::Forbidden(
+                "Admin routes are disabled; set MMSS_ADMIN_TOKEN".to_string(),
+            ));
+        }
+        match bearer_token(parts) {
+            Some(token) if state.credentials.is_admin(token) => Ok(AdminAuth),
+            _ => Err(Error::Unauthorized(
+                "Missing or wrong admin bearer token".to_string(),
+            )),
+        }
+    }
+}
+
+/// The caller of a privileged operation, for the audit trail: the admin
+/// token, an operator key (as a bearer token or `x-api-key`) or a JWT
+/// signed with `MMSS_JWT_SECRET`. Credentials that do not verify, and
+/// requests without any, are anonymous.
+#[async_trait]
+impl FromRequestParts<AppState> for Actor {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let id = bearer_token(parts)
+            .and_then(|token| state.credentials.bearer(token))
+            .or_else(|| header(API_KEY_HEADER).and_then(|key| state.credentials.operator(key)));
+        Ok(Actor {
+            id: id.unwrap_or_else(|| Actor::ANONYMOUS.to_string()),
+            request_id: header("x-request-id").map(str::to_string),
+        })
+    }
+}
+
+/// An [`Actor`] whose credentials verified; anonymous callers get 401.
+pub struct Authenticated(pub Actor);
+
+#[async_trait]
+impl FromRequestParts<AppState> for Authenticated {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let actor = match Actor::from_request_parts(parts, state).await {
+            Ok(actor) => actor,
+            Err(never) => match never {},
+        };
+        if actor.id == Actor::ANONYMOUS {
+            return Err(Error::Unauthorized(
+                "Needs the admin token, an operator key or a signed JWT".to_string(),
+            ));
+        }
+        Ok(Authenticated(actor))
+    }
+}
+
+fn bearer_token(parts: &Parts) -> Option<&str> {
+    parts
+        .headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+#[derive(Deserialize)]
+pub struct UpdatePromptRequest {
+    pub template: String,
+}
+
+#[derive(Serialize)]
+pub struct ReloadPromptsResponse {
+    pub template_count: usize,
+}
+
+pub async fn list_prompts(State(state): State<AppState>) -> ApiResult<Json<Vec<PromptTemplate>>> {
+    let templates = state.llm_gateway.prompts().list().map_err(internal_error)?;
+    Ok(Json(templates))
+}
+
+pub async fn update_prompt(
+    _auth: AdminAuth,
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+    actor: Actor,
+    Json(payload): Json<UpdatePromptRequest>,
+) -> ApiResult<Json<PromptTemplate>> {
+    let prompts = state.llm_gateway.prompts();
+    prompts.set(&name, payload.template).map_err(bad_request)?;
+    state.audit_trail.record(
+        &actor,
+        PrivilegedAction::ConfigChanged,
+        json!({ "prompt": name }),
+    );
+
+    let template = prompts
+        .get(&name)
+        .map_err(internal_error)?
+        .ok_or_else(|| internal_error("Template disappeared after update"))?;
+    Ok(Json(template))
+}
+
+pub async fn reload_prompts(
+    _auth: AdminAuth,
+    State(state): State<AppState>,
+    actor: Actor,
+) -> ApiResult<Json<ReloadPromptsResponse>> {
+    let template_count = state
+        .llm_gateway
+        .prompts()
+        .reload()
+        .map_err(internal_error)?;
+    state.audit_trail.record(
+        &actor,
+        PrivilegedAction::ConfigChanged,
+        json!({ "prompts": "reloaded", "template_count": template_count }),
+    );
+    Ok(Json(ReloadPromptsResponse { template_count }))
+}
+
+/// Queue depth, task durations and failure rates per operator, LLM
+/// latency and error rate, and rule application counts.
+pub async fn get_stats(State(state): State<AppState>) -> ApiResult<Json<OpsStats>> {
+    let queue = state.processor.queue_depth().map_err(internal_error)?;
+    let stats = state.ops.stats(queue).map_err(internal_error)?;
+    Ok(Json(stats))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LogFilter {
+    /// Per-module levels in `RUST_LOG` syntax.
+    pub filter: String,
+}
+
+pub async fn get_log_filter(_auth: AdminAuth) -> ApiResult<Json<LogFilter>> {
+    let filter = telemetry::log_filter().ok_or_else(|| not_found("Logging is not installed"))?;
+    Ok(Json(LogFilter { filter }))
+}
+
+/// Replace the log filter until the server stops, e.g. to turn on
+/// `mmss::campaign=debug` while chasing a problem.
+pub async fn set_log_filter(
+    _auth: AdminAuth,
+    State(state): State<AppState>,
+    actor: Actor,
+    Json(payload): Json<LogFilter>,
+) -> ApiResult<Json<LogFilter>> {
+    telemetry::set_log_filter(&payload.filter).map_err(|err| match err {
+        Error::InvalidParameter(..) => bad_request(err),
+        err => not_found(err),
+    })?;
+    state.audit_trail.record(
+        &actor,
+        PrivilegedAction::ConfigChanged,
+        json!({ "log_filter": payload.filter }),
+    );
+    let filter = telemetry::log_filter().unwrap_or(payload.filter);
+    Ok(Json(LogFilter { filter }))
+}
+
+#[derive(Serialize)]
+pub struct ResetResponse {
+    pub reset: ResetScope,
+    pub metrics: GeometricMetrics,
+}
+
+/// Return the components selected by the JSON body to baseline; an empty
+/// body, like an empty scope, selects all of them. A body that does not
+/// parse is refused rather than read as empty.
+pub async fn reset_state(
+    _auth: AdminAuth,
+    State(state): State<AppState>,
+    actor: Actor,
+    body: Bytes,
+) -> ApiResult<Json<ResetResponse>> {
+    let scope: ResetScope = if body.iter().all(u8::is_ascii_whitespace) {
+        ResetScope::default()
+    } else {
+        serde_json::from_slice(&body).map_err(bad_request)?
+    };
+    let reset = state.reset(scope).await?;
+    state.audit_trail.record(
+        &actor,
+        PrivilegedAction::StateReset,
+        serde_json::to_value(reset).map_err(internal_error)?,
+    );
+    let metrics = state.processor.get_metrics();
+    Ok(Json(ResetResponse { reset, metrics }))
+}
+
+/// Audit trail entries, newest first, filtered by `actor`, `action`,
+/// `since`, `until` and `limit`.
+pub async fn list_audit_entries(
+    _auth: AdminAuth,
+    Query(query): Query<AuditTrailQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<Vec<AuditEntry>>> {
+    let entries = state.audit_trail.query(&query).map_err(internal_error)?;
+    Ok(Json(entries))
+}
+
+/// Whether every audit trail entry still matches its hash chain.
+pub async fn verify_audit_trail(
+    _auth: AdminAuth,
+    State(state): State<AppState>,
+) -> ApiResult<Json<ChainReport>> {
+    let report = state.audit_trail.verify().map_err(internal_error)?;
+    Ok(Json(report))
+}
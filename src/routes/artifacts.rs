@@ -0,0 +1,36 @@
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use uuid::Uuid;
+
+use crate::state::AppState;
+
+use super::{bad_request, internal_error, not_found, ApiResult};
+
+/// Raw bytes of an artifact saved by a script task, with its content type.
+pub async fn get_artifact(
+    Path(artifact_id): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let id = Uuid::parse_str(&artifact_id).map_err(|_| bad_request("Invalid artifact ID"))?;
+    let artifact = state
+        .processor
+        .artifacts()
+        .get(id)
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found("Artifact not found"))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, artifact.summary.content_type),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("inline; filename=\"{}\"", artifact.summary.name.replace('"', "")),
+            ),
+        ],
+        artifact.bytes,
+    )
+        .into_response())
+}
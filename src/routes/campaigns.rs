@@ -0,0 +1,140 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::api::usage::TokenUsage;
+use crate::campaign::{CampaignComparison, CampaignSnapshot, CampaignStatus};
+use crate::state::AppState;
+
+use super::{bad_request, internal_error, not_found, ApiResult};
+
+#[derive(Serialize)]
+pub struct CampaignResponse {
+    #[serde(flatten)]
+    pub campaign: CampaignSnapshot,
+    pub token_usage: TokenUsage,
+}
+
+#[derive(Serialize)]
+pub struct CancelCampaignResponse {
+    pub campaign_id: Uuid,
+    pub cancel_requested: bool,
+    pub status: CampaignStatus,
+}
+
+fn parse_campaign_id(raw: &str) -> ApiResult<Uuid> {
+    Uuid::parse_str(raw).map_err(|_| bad_request("Invalid campaign ID"))
+}
+
+pub async fn list_campaigns(
+    State(state): State<AppState>,
+) -> ApiResult<Json<Vec<CampaignSnapshot>>> {
+    let campaigns = state.campaigns.list().map_err(internal_error)?;
+    Ok(Json(campaigns))
+}
+
+pub async fn get_campaign(
+    Path(campaign_id): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<CampaignResponse>> {
+    let id = parse_campaign_id(&campaign_id)?;
+    let campaign = state
+        .campaigns
+        .get(id)
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found("Campaign not found"))?;
+    let token_usage = state
+        .llm_gateway
+        .usage()
+        .campaign_usage(id)
+        .map_err(internal_error)?;
+
+    Ok(Json(CampaignResponse {
+        campaign,
+        token_usage,
+    }))
+}
+
+pub async fn cancel_campaign(
+    Path(campaign_id): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<CancelCampaignResponse>> {
+    let id = parse_campaign_id(&campaign_id)?;
+    let status = state
+        .campaigns
+        .cancel(id)
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found("Campaign not found"))?;
+
+    Ok(Json(CancelCampaignResponse {
+        campaign_id: id,
+        cancel_requested: !status.is_finished(),
+        status,
+    }))
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparisonFormat {
+    #[default]
+    Json,
+    Csv,
+    Arrow,
+}
+
+#[derive(Deserialize)]
+pub struct CompareQuery {
+    /// Two comma-separated campaign IDs.
+    pub ids: String,
+    #[serde(default)]
+    pub format: ComparisonFormat,
+}
+
+fn campaign_with_usage(state: &AppState, id: Uuid) -> ApiResult<(CampaignSnapshot, TokenUsage)> {
+    let campaign = state
+        .campaigns
+        .get(id)
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found(format!("Campaign {id} not found")))?;
+    let usage = state
+        .llm_gateway
+        .usage()
+        .campaign_usage(id)
+        .map_err(internal_error)?;
+    Ok((campaign, usage))
+}
+
+pub async fn compare_campaigns(
+    Query(query): Query<CompareQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let ids = query
+        .ids
+        .split(',')
+        .map(|raw| parse_campaign_id(raw.trim()))
+        .collect::<ApiResult<Vec<_>>>()?;
+    let [left_id, right_id] = ids[..] else {
+        return Err(bad_request("Expected exactly two campaign IDs"));
+    };
+
+    let (left, left_usage) = campaign_with_usage(&state, left_id)?;
+    let (right, right_usage) = campaign_with_usage(&state, right_id)?;
+    let comparison = CampaignComparison::new(&left, left_usage, &right, right_usage);
+
+    Ok(match query.format {
+        ComparisonFormat::Json => Json(comparison).into_response(),
+        ComparisonFormat::Csv => {
+            ([(header::CONTENT_TYPE, "text/csv")], comparison.to_csv()).into_response()
+        }
+        ComparisonFormat::Arrow => (
+            [(header::CONTENT_TYPE, "application/vnd.apache.arrow.file")],
+            comparison.to_arrow_ipc().map_err(internal_error)?,
+        )
+            .into_response(),
+    })
+}
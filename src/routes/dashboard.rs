@@ -0,0 +1,48 @@
+use axum::{
+    extract::Path,
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use rust_embed::RustEmbed;
+
+use super::not_found;
+
+/// The dashboard's static assets, compiled into the binary.
+#[derive(RustEmbed)]
+#[folder = "src/dashboard/"]
+struct Assets;
+
+/// The dashboard, to nest under `/dashboard` beside the API at `/api`,
+/// whose WebSocket and task endpoints it calls.
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(index))
+        .route("/*path", get(asset))
+}
+
+pub async fn index() -> Response {
+    serve("index.html")
+}
+
+/// An embedded asset; paths without an extension are client-side routes and
+/// get the page itself.
+pub async fn asset(Path(path): Path<String>) -> Response {
+    if path.contains('.') {
+        serve(&path)
+    } else {
+        serve("index.html")
+    }
+}
+
+fn serve(path: &str) -> Response {
+    match Assets::get(path) {
+        Some(file) => (
+            [(header::CONTENT_TYPE, file.metadata.mimetype().to_string())],
+            file.data,
+        )
+            .into_response(),
+        None => not_found(format!("No dashboard asset {}", path)).into_response(),
+    }
+}
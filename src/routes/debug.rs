@@ -0,0 +1,9 @@
+use axum::{extract::State, Json};
+
+use crate::diagnostics::RuntimeReport;
+use crate::state::AppState;
+
+/// Scheduler, lock contention and memory figures of the running server.
+pub async fn get_runtime(State(state): State<AppState>) -> Json<RuntimeReport> {
+    Json(RuntimeReport::collect(state.processor.lock_stats()))
+}
@@ -0,0 +1,183 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::stream;
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use tokio::sync::mpsc;
+
+use mmss_eqgft::asymmetry::PolarizationAsymmetry;
+use mmss_eqgft::cache::EqgftCacheStats;
+use mmss_eqgft::config::EqgftConfig;
+use mmss_eqgft::reweight::scan_kappa_reweighted;
+use mmss_eqgft::scan::scan_kappa_with;
+use mmss_eqgft::sensitivity::{log_spaced_events, sensitivity_iter};
+
+use crate::state::AppState;
+
+use super::{bad_request, ApiResult};
+
+/// Upper bounds that keep one request from tying up the simulation workers.
+const MAX_POINTS: usize = 1_000;
+const MAX_EVENTS: usize = 100_000_000;
+
+#[derive(Debug, Deserialize)]
+pub struct SensitivityQuery {
+    #[serde(default = "default_kappa")]
+    pub kappa: f64,
+    #[serde(default = "default_systematic_error")]
+    pub systematic_error: f64,
+    #[serde(default = "default_n_min")]
+    pub n_min: usize,
+    #[serde(default = "default_n_max")]
+    pub n_max: usize,
+    #[serde(default = "default_points")]
+    pub points: usize,
+    pub seed: Option<u64>,
+}
+
+impl SensitivityQuery {
+    /// The simulation settings and sample sizes, once within bounds.
+    pub(super) fn plan(&self) -> ApiResult<(EqgftConfig, Vec<usize>)> {
+        if self.points > MAX_POINTS || self.n_max > MAX_EVENTS || self.n_min > self.n_max {
+            return Err(bad_request(format!(
+                "Expected n_min <= n_max <= {MAX_EVENTS} and at most {MAX_POINTS} points"
+            )));
+        }
+        let config = EqgftConfig {
+            kappa: self.kappa,
+            systematic_error: self.systematic_error,
+            seed: self.seed,
+            ..EqgftConfig::default()
+        };
+        Ok((
+            config,
+            log_spaced_events(self.n_min, self.n_max, self.points),
+        ))
+    }
+}
+
+/// The parameters of a `SimulateEqgftKappaScan` task.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct KappaScanQuery {
+    pub kappa_min: f64,
+    pub kappa_max: f64,
+    pub steps: usize,
+    pub n_events: usize,
+    pub systematic_error: f64,
+    pub seed: Option<u64>,
+    /// Reweight one sample to every step instead of simulating each.
+    pub reweight: bool,
+}
+
+impl Default for KappaScanQuery {
+    fn default() -> Self {
+        let config = EqgftConfig::default();
+        Self {
+            kappa_min: 0.0,
+            kappa_max: 0.5,
+            steps: 11,
+            n_events: config.n_events,
+            systematic_error: config.systematic_error,
+            seed: None,
+            reweight: false,
+        }
+    }
+}
+
+impl KappaScanQuery {
+    pub(super) fn scan(&self) -> ApiResult<Vec<PolarizationAsymmetry>> {
+        if self.steps > MAX_POINTS || self.n_events > MAX_EVENTS {
+            return Err(bad_request(format!(
+                "Expected n_events <= {MAX_EVENTS} and at most {MAX_POINTS} steps"
+            )));
+        }
+        let config = EqgftConfig {
+            n_events: self.n_events,
+            systematic_error: self.systematic_error,
+            seed: self.seed,
+            ..EqgftConfig::default()
+        };
+        let range = self.kappa_min..=self.kappa_max;
+        let scan = if self.reweight {
+            scan_kappa_reweighted(&config, range, self.steps)
+        } else {
+            scan_kappa_with(&config, range, self.steps)
+        };
+        scan.map_err(bad_request)
+    }
+}
+
+fn default_kappa() -> f64 {
+    EqgftConfig::default().kappa
+}
+
+fn default_systematic_error() -> f64 {
+    EqgftConfig::default().systematic_error
+}
+
+fn default_n_min() -> usize {
+    1_000
+}
+
+fn default_n_max() -> usize {
+    1_000_000
+}
+
+fn default_points() -> usize {
+    50
+}
+
+/// Sensitivity curve as newline-delimited JSON: a header line with `kappa`
+/// and `predicted`, then one line per point as it is simulated. A failure
+/// mid-curve ends the stream with an `{"error": ...}` line.
+pub async fn stream_sensitivity(Query(query): Query<SensitivityQuery>) -> ApiResult<Response> {
+    let (config, n_values) = query.plan()?;
+    let points = sensitivity_iter(&config, n_values).map_err(bad_request)?;
+
+    let (sender, receiver) = mpsc::channel::<String>(4);
+    tokio::task::spawn_blocking(move || {
+        let header = json!({ "kappa": points.kappa(), "predicted": points.predicted() });
+        if sender.blocking_send(format!("{header}\n")).is_err() {
+            return;
+        }
+        for point in points {
+            let line = match point {
+                Ok(point) => json!(point),
+                Err(err) => json!({ "error": err.to_string() }),
+            };
+            let failed = line.get("error").is_some();
+            // a closed channel means the client went away; stop simulating
+            if sender.blocking_send(format!("{line}\n")).is_err() || failed {
+                return;
+            }
+        }
+    });
+
+    let body = stream::unfold(receiver, |mut receiver| async move {
+        let line = receiver.recv().await?;
+        Some((Ok::<_, Infallible>(line), receiver))
+    });
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+pub async fn get_cache_stats(State(state): State<AppState>) -> Json<EqgftCacheStats> {
+    Json(state.processor.eqgft_cache().stats())
+}
+
+/// Drop every cached field and event sample, including spilled ones.
+pub async fn clear_cache(State(state): State<AppState>) -> Json<EqgftCacheStats> {
+    let cache = state.processor.eqgft_cache();
+    cache.clear();
+    Json(cache.stats())
+}
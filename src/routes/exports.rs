@@ -0,0 +1,188 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::ipc::writer::StreamWriter;
+use futures_util::{stream, StreamExt};
+use log::warn;
+use serde::Deserialize;
+use std::convert::Infallible;
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+use crate::core::error::Error;
+use crate::core::events::StateEvent;
+use crate::core::exports::{ExportFormat, ExportJob, ExportStatus};
+use crate::core::metrics_history::{snapshot_batch, snapshot_schema};
+use crate::state::AppState;
+
+use super::{bad_request, internal_error, not_found, ApiResult};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ExportRequest {
+    pub format: ExportFormat,
+    /// `s3://bucket/key` or `gs://bucket/key` to upload to instead of the
+    /// export directory.
+    pub destination: Option<String>,
+}
+
+/// Start a background export of every task; poll the returned job for completion.
+pub async fn start_export(
+    State(state): State<AppState>,
+    Json(request): Json<ExportRequest>,
+) -> ApiResult<(StatusCode, Json<ExportJob>)> {
+    let records = state.processor.task_records().map_err(internal_error)?;
+    let job = state
+        .exports
+        .start(records, request.format, request.destination)
+        .map_err(|e| match e {
+            Error::InvalidParameter(..) => bad_request(e),
+            _ => internal_error(e),
+        })?;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+pub async fn get_export(
+    Path(export_id): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<ExportJob>> {
+    Ok(Json(find_export(&state, &export_id)?))
+}
+
+/// The exported file, once the job has completed.
+pub async fn download_export(
+    Path(export_id): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let job = find_export(&state, &export_id)?;
+    if !matches!(job.status, ExportStatus::Completed { .. }) {
+        return Err(Error::Conflict("Export has not completed".to_string()));
+    }
+    if let Some(destination) = &job.destination {
+        return Err(not_found(format!("Export was uploaded to {}", destination)));
+    }
+    let bytes = tokio::fs::read(&job.path).await.map_err(internal_error)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, job.format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}.{}\"",
+                    job.id,
+                    job.format.extension()
+                ),
+            ),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+fn find_export(state: &AppState, export_id: &str) -> ApiResult<ExportJob> {
+    let id = Uuid::parse_str(export_id).map_err(|_| bad_request("Invalid export ID"))?;
+    state
+        .exports
+        .get(id)
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found("Export not found"))
+}
+
+/// Snapshots already waiting are sent together, up to this many per batch.
+const MAX_BATCH_SNAPSHOTS: usize = 256;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct MetricsStreamQuery {
+    /// Comma-separated custom metrics to include as columns; defaults to
+    /// those kept by the metrics history.
+    pub custom: Option<String>,
+}
+
+/// Metrics snapshots as an Arrow IPC stream, one batch as each task
+/// completes, in the metrics history's columns. The schema is sent right
+/// away; the stream stays open until the client disconnects. A client that
+/// falls too far behind skips the snapshots it missed.
+pub async fn stream_metrics(
+    Query(query): Query<MetricsStreamQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let custom_metrics = match query.custom {
+        Some(names) => names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect(),
+        None => state
+            .metrics_history
+            .as_ref()
+            .map(|history| history.custom_metrics().to_vec())
+            .unwrap_or_default(),
+    };
+    let schema = snapshot_schema(&custom_metrics);
+    let mut writer = StreamWriter::try_new(Vec::new(), &schema).map_err(internal_error)?;
+    let schema_message = std::mem::take(writer.get_mut());
+    let metrics = MetricsStream {
+        receiver: state.events.subscribe(),
+        writer,
+        schema,
+        custom_metrics,
+    };
+
+    let batches = stream::unfold(metrics, |mut metrics| async move {
+        let bytes = metrics.next_batch().await?;
+        Some((bytes, metrics))
+    });
+    let body = stream::once(async move { schema_message })
+        .chain(batches)
+        .map(Ok::<_, Infallible>);
+    Ok((
+        [(header::CONTENT_TYPE, "application/vnd.apache.arrow.stream")],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+struct MetricsStream {
+    receiver: broadcast::Receiver<StateEvent>,
+    writer: StreamWriter<Vec<u8>>,
+    schema: SchemaRef,
+    custom_metrics: Vec<String>,
+}
+
+impl MetricsStream {
+    /// The encoded batch of the next snapshots; `None` ends the stream.
+    async fn next_batch(&mut self) -> Option<Vec<u8>> {
+        let mut snapshots = loop {
+            match self.receiver.recv().await {
+                Ok(StateEvent::MetricsUpdated(snapshot)) => break vec![snapshot],
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Metrics stream fell behind, skipped {} events", skipped)
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        };
+        while snapshots.len() < MAX_BATCH_SNAPSHOTS {
+            match self.receiver.try_recv() {
+                Ok(StateEvent::MetricsUpdated(snapshot)) => snapshots.push(snapshot),
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        let written = snapshot_batch(&self.schema, &self.custom_metrics, &snapshots)
+            .and_then(|batch| self.writer.write(&batch));
+        if let Err(e) = written {
+            warn!("Metrics stream ended early: {}", e);
+            return None;
+        }
+        Some(std::mem::take(self.writer.get_mut()))
+    }
+}
@@ -1,16 +1,53 @@
-use axum::Json;
+use axum::{extract::State, Json};
 use chrono::Utc;
 use serde::Serialize;
 
+use crate::api::resilience::{BreakerState, BreakerStatus};
+use crate::state::AppState;
+
+use super::{internal_error, ApiResult};
+
 #[derive(Serialize)]
 pub struct HealthResponse {
     pub status: &'static str,
     pub timestamp: String,
 }
 
+#[derive(Serialize)]
+pub struct ReadinessResponse {
+    pub status: &'static str,
+    pub timestamp: String,
+    pub llm_provider: String,
+    pub llm_circuit: BreakerStatus,
+}
+
 pub async fn health_check() -> Json<HealthResponse> {
     Json(HealthResponse {
         status: "ok",
         timestamp: Utc::now().to_rfc3339(),
     })
 }
+
+/// Report whether LLM planning is available. Campaigns keep running on the
+/// fallback planner while the breaker is open, so this reports `degraded`
+/// rather than failing the probe.
+pub async fn readiness(
+    State(state): State<AppState>,
+) -> ApiResult<Json<ReadinessResponse>> {
+    let llm_circuit = state
+        .llm_gateway
+        .breaker()
+        .status()
+        .map_err(internal_error)?;
+    let status = match llm_circuit.state {
+        BreakerState::Closed => "ok",
+        BreakerState::HalfOpen | BreakerState::Open => "degraded",
+    };
+
+    Ok(Json(ReadinessResponse {
+        status,
+        timestamp: Utc::now().to_rfc3339(),
+        llm_provider: state.llm_gateway.provider().name().to_string(),
+        llm_circuit,
+    }))
+}
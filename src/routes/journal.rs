@@ -0,0 +1,82 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::core::journal::{self, Journal, JournalEntry};
+use crate::core::types::SystemState;
+use crate::state::AppState;
+
+use super::admin::AdminAuth;
+use super::{internal_error, not_found, ApiResult};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct EntriesQuery {
+    /// Only entries after this sequence number.
+    pub since: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ReplayQuery {
+    /// Replay up to and including this sequence number.
+    pub until: Option<u64>,
+    /// Replay the entries recorded up to this time.
+    pub at: Option<DateTime<Utc>>,
+}
+
+fn journal_of(state: &AppState) -> ApiResult<Arc<Journal>> {
+    state
+        .journal
+        .clone()
+        .ok_or_else(|| not_found("No journal is kept; set MMSS_JOURNAL_PATH"))
+}
+
+/// Journal entries, oldest first. Needs the admin token, as the entries
+/// name who submitted each task.
+pub async fn list_entries(
+    _auth: AdminAuth,
+    Query(query): Query<EntriesQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<Vec<JournalEntry>>> {
+    let journal = journal_of(&state)?;
+    let entries = journal.entries().map_err(internal_error)?;
+    Ok(Json(
+        entries
+            .into_iter()
+            .filter(|entry| query.since.is_none_or(|since| entry.seq > since))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .collect(),
+    ))
+}
+
+/// The state rebuilt by replaying the journal, up to `until` or `at` when
+/// given, for comparing with the live state or an earlier point. Needs the
+/// admin token.
+pub async fn replay_state(
+    _auth: AdminAuth,
+    Query(query): Query<ReplayQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<SystemState>> {
+    let journal = journal_of(&state)?;
+    let constants = state.constants;
+    let cache = state.processor.eqgft_cache().clone();
+    // regenerating Hopfion fields is CPU-bound
+    let replayed = tokio::task::spawn_blocking(move || {
+        let entries: Vec<_> = journal
+            .entries()?
+            .into_iter()
+            .take_while(|entry| query.until.is_none_or(|until| entry.seq <= until))
+            .take_while(|entry| query.at.is_none_or(|at| entry.timestamp <= at))
+            .collect();
+        journal::replay(&entries, constants, cache)
+    })
+    .await
+    .map_err(internal_error)??;
+    Ok(Json(replayed))
+}
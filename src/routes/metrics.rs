@@ -1,9 +1,20 @@
-use axum::{extract::State, Json};
-use serde::Serialize;
+use axum::{
+    extract::{Query, State},
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
 
-use crate::state::AppState;
+use crate::core::metric_aggregates::{aggregate, MetricAggregates};
+use crate::state::{AppState, PhysicalConstants};
 
-use super::{internal_error, ApiResult};
+use super::{internal_error, not_found, ApiResult};
+
+/// Snapshots aggregated by `GET /metrics/vectorized`, and per rolling
+/// window, unless asked otherwise.
+const DEFAULT_AGGREGATE_SNAPSHOTS: usize = 1024;
+const DEFAULT_AGGREGATE_WINDOW: usize = 32;
 
 #[derive(Serialize)]
 pub struct MetricsResponse {
@@ -13,7 +24,7 @@ pub struct MetricsResponse {
 }
 
 pub async fn get_metrics(State(state): State<AppState>) -> ApiResult<Json<MetricsResponse>> {
-    let metrics = state.processor.get_metrics().map_err(internal_error)?;
+    let metrics = state.processor.get_metrics();
     let engine = state.metric_engine.read().await;
     let rule_names = engine.rule_names();
     let rule_count = rule_names.len();
@@ -25,9 +36,43 @@ pub async fn get_metrics(State(state): State<AppState>) -> ApiResult<Json<Metric
     }))
 }
 
+/// Constants the metrics are computed from, for previews computed
+/// client-side with `mmss-compute`.
+pub async fn get_constants(State(state): State<AppState>) -> Json<PhysicalConstants> {
+    Json(state.constants)
+}
+
+/// Operational metrics in the Prometheus text format, for scraping.
+pub async fn get_prometheus_metrics(State(state): State<AppState>) -> ApiResult<Response> {
+    let queue = state.processor.queue_depth().map_err(internal_error)?;
+    let text = state.ops.render_prometheus(queue).map_err(internal_error)?;
+    Ok((
+        [(CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        text,
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+pub struct AggregateQuery {
+    /// Snapshots per rolling window.
+    pub window: Option<usize>,
+    /// Latest snapshots to aggregate, up to those kept in memory.
+    pub limit: Option<usize>,
+}
+
+/// Means, variances, rolling windows and correlations of the metrics over
+/// the recent history, computed column-wise; see `core::metric_aggregates`.
 pub async fn get_vectorized_metrics(
+    Query(query): Query<AggregateQuery>,
     State(state): State<AppState>,
-) -> ApiResult<Json<crate::core::types::GeometricMetrics>> {
-    let metrics = state.processor.get_metrics().map_err(internal_error)?;
-    Ok(Json(metrics))
+) -> ApiResult<Json<MetricAggregates>> {
+    let history = state
+        .metrics_history
+        .as_ref()
+        .ok_or_else(|| not_found("Metrics history is disabled (set MMSS_METRICS_HISTORY_DIR)"))?;
+    let snapshots = history
+        .recent(query.limit.unwrap_or(DEFAULT_AGGREGATE_SNAPSHOTS))
+        .map_err(internal_error)?;
+    Ok(Json(aggregate(&snapshots, query.window.unwrap_or(DEFAULT_AGGREGATE_WINDOW))))
 }
@@ -1,41 +1,154 @@
+pub mod admin;
+pub mod artifacts;
+pub mod campaigns;
+pub mod dashboard;
+pub mod debug;
+pub mod eqgft;
+pub mod exports;
 pub mod health;
+pub mod journal;
 pub mod llm;
 pub mod metrics;
+pub mod namespaces;
+pub mod plugins;
+pub mod query;
+pub mod records;
+pub mod retrieval;
 pub mod rules;
 pub mod tasks;
 pub mod visualization;
 
+use crate::core::error::Error;
 use crate::state::AppState;
-use axum::http::StatusCode;
 use axum::{
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 
-pub type ApiResult<T> = Result<T, (StatusCode, String)>;
+/// Handler result; errors answer with their status and code, see
+/// [`Error::status`] and [`Error::code`].
+pub type ApiResult<T> = Result<T, Error>;
 
-pub(crate) fn internal_error<E: ToString>(err: E) -> (StatusCode, String) {
-    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+pub(crate) fn internal_error<E: ToString>(err: E) -> Error {
+    Error::Internal(err.to_string())
 }
 
-pub(crate) fn bad_request<E: ToString>(err: E) -> (StatusCode, String) {
-    (StatusCode::BAD_REQUEST, err.to_string())
+pub(crate) fn bad_request<E: ToString>(err: E) -> Error {
+    Error::BadRequest(err.to_string())
 }
 
-pub(crate) fn not_found<E: ToString>(err: E) -> (StatusCode, String) {
-    (StatusCode::NOT_FOUND, err.to_string())
+pub(crate) fn not_found<E: ToString>(err: E) -> Error {
+    Error::NotFound(err.to_string())
 }
 
+/// The API, with namespaced access to it under `/ns/:namespace`.
 pub fn build_router() -> Router<AppState> {
+    api_routes()
+        .route("/namespaces", get(namespaces::list_namespaces))
+        .route("/namespaces/:name", delete(namespaces::delete_namespace))
+        .fallback(namespaces::dispatch)
+}
+
+/// Every route that reads or changes one namespace's state.
+pub(crate) fn api_routes() -> Router<AppState> {
     Router::new()
         .route("/health", get(health::health_check))
+        .route("/health/ready", get(health::readiness))
+        .route("/debug/runtime", get(debug::get_runtime))
         .route("/metrics", get(metrics::get_metrics))
+        .route("/metrics/constants", get(metrics::get_constants))
+        .route("/metrics/prometheus", get(metrics::get_prometheus_metrics))
         .route("/metrics/vectorized", get(metrics::get_vectorized_metrics))
         .route("/tasks", get(tasks::list_tasks).post(tasks::create_task))
         .route("/tasks/:id", get(tasks::get_task_status))
+        .route("/tasks/:id/approve", post(tasks::approve_task))
         .route("/llm/query", post(llm::llm_query))
         .route("/llm/research-campaign", post(llm::start_research_campaign))
+        .route("/llm/usage", get(llm::get_usage))
+        .route("/llm/history", get(llm::get_history))
+        .route(
+            "/llm/sessions/:id",
+            get(llm::get_session).delete(llm::delete_session),
+        )
+        .route("/campaigns", get(campaigns::list_campaigns))
+        .route("/campaigns/compare", get(campaigns::compare_campaigns))
+        .route("/campaigns/:id", get(campaigns::get_campaign))
+        .route("/campaigns/:id/cancel", post(campaigns::cancel_campaign))
+        .route("/artifacts/:id", get(artifacts::get_artifact))
+        .route("/eqgft/sensitivity/stream", get(eqgft::stream_sensitivity))
+        .route(
+            "/eqgft/cache",
+            get(eqgft::get_cache_stats).delete(eqgft::clear_cache),
+        )
+        .route("/exports", post(exports::start_export))
+        .route("/exports/:id", get(exports::get_export))
+        .route("/exports/:id/download", get(exports::download_export))
+        .route("/export/metrics/stream", get(exports::stream_metrics))
+        .route("/query", post(query::run_query))
+        .route("/records/stream", get(records::stream_records))
+        .route("/retrieval/anchors", post(retrieval::index_anchor))
+        .route("/retrieval/search", get(retrieval::search))
+        .route("/plugins", get(plugins::list_plugins))
         .route("/rules", post(rules::register_rule))
         .route("/rules/:name", delete(rules::delete_rule))
         .route("/visualization/packet", get(visualization::get_packet))
+        .route(
+            "/visualization/packet/diff",
+            get(visualization::get_packet_diff),
+        )
+        .route("/visualization/hopfion-field", get(visualization::get_field))
+        .route(
+            "/visualization/hopfion-field/mesh",
+            get(visualization::get_field_mesh),
+        )
+        .route(
+            "/visualization/hopfion-field/slice",
+            get(visualization::get_field_slice),
+        )
+        .route(
+            "/visualization/hopfion-field/vectors",
+            get(visualization::get_vector_field),
+        )
+        .route(
+            "/visualization/hopfion-field/streamlines",
+            get(visualization::get_streamlines),
+        )
+        .route("/visualization/export/gltf", get(visualization::export_gltf))
+        .route("/visualization/export/ply", get(visualization::export_ply))
+        .route("/visualization/trajectory", get(visualization::get_trajectory))
+        .route("/visualization/styles", get(visualization::get_styles))
+        .route(
+            "/visualization/styles/:metric",
+            put(visualization::put_style).delete(visualization::delete_style),
+        )
+        .route(
+            "/visualization/sensitivity-curve.png",
+            get(visualization::sensitivity_chart_png),
+        )
+        .route(
+            "/visualization/sensitivity-curve.svg",
+            get(visualization::sensitivity_chart_svg),
+        )
+        .route(
+            "/visualization/kappa-scan.png",
+            get(visualization::kappa_scan_chart_png),
+        )
+        .route(
+            "/visualization/kappa-scan.svg",
+            get(visualization::kappa_scan_chart_svg),
+        )
+        .route("/ws/visualization", get(visualization::stream_packets))
+        .route("/admin/prompts", get(admin::list_prompts))
+        .route("/admin/prompts/reload", post(admin::reload_prompts))
+        .route("/admin/prompts/:name", put(admin::update_prompt))
+        .route("/admin/reset", post(admin::reset_state))
+        .route("/admin/stats", get(admin::get_stats))
+        .route("/admin/audit", get(admin::list_audit_entries))
+        .route("/admin/audit/verify", get(admin::verify_audit_trail))
+        .route(
+            "/admin/log-filter",
+            get(admin::get_log_filter).put(admin::set_log_filter),
+        )
+        .route("/journal", get(journal::list_entries))
+        .route("/journal/replay", get(journal::replay_state))
 }
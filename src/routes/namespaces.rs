@@ -0,0 +1,93 @@
+//! `/ns/:namespace/...`: every API route, against the state of one
+//! namespace.
+//!
+//! Dispatch happens in the router's fallback rather than on a
+//! `/ns/:namespace/*rest` route, so the inner handlers see only their own
+//! path parameters.
+
+use axum::extract::{Path, Request, State};
+use axum::http::uri::PathAndQuery;
+use axum::http::{StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
+use serde::Serialize;
+use std::sync::OnceLock;
+use tower::ServiceExt;
+
+use crate::core::audit_trail::{Actor, PrivilegedAction};
+use crate::state::namespaces::DEFAULT_NAMESPACE;
+use crate::state::AppState;
+
+use super::admin::AdminAuth;
+use super::{bad_request, not_found, ApiResult};
+
+const PREFIX: &str = "/ns/";
+
+#[derive(Serialize)]
+pub struct NamespaceList {
+    pub default: &'static str,
+    pub namespaces: Vec<String>,
+}
+
+pub async fn list_namespaces(State(state): State<AppState>) -> ApiResult<Json<NamespaceList>> {
+    Ok(Json(NamespaceList {
+        default: DEFAULT_NAMESPACE,
+        namespaces: state.namespaces.names()?,
+    }))
+}
+
+/// Drop a namespace and everything in it. Needs the admin token.
+pub async fn delete_namespace(
+    _auth: AdminAuth,
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+    actor: Actor,
+) -> ApiResult<StatusCode> {
+    if state.namespaces.remove(&name)? {
+        state.audit_trail.record(
+            &actor,
+            PrivilegedAction::NamespaceDeleted,
+            serde_json::json!({ "namespace": name }),
+        );
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(not_found(format!("Namespace {} not found", name)))
+    }
+}
+
+/// Route `/ns/:namespace/<path>` to `<path>` in that namespace, creating it
+/// on first use; anything else is a plain 404.
+pub async fn dispatch(State(root): State<AppState>, mut request: Request) -> Response {
+    let Some(scoped) = request.uri().path().strip_prefix(PREFIX) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let (name, path) = scoped.split_once('/').unwrap_or((scoped, ""));
+    let state = match root.namespaces.get_or_create(name, &root) {
+        Ok(state) => state,
+        Err(err) => return err.into_response(),
+    };
+
+    let query = request
+        .uri()
+        .query()
+        .map(|query| format!("?{}", query))
+        .unwrap_or_default();
+    let mut parts = request.uri().clone().into_parts();
+    parts.path_and_query = PathAndQuery::try_from(format!("/{}{}", path, query)).ok();
+    match Uri::from_parts(parts) {
+        Ok(uri) => *request.uri_mut() = uri,
+        Err(err) => return bad_request(err).into_response(),
+    }
+
+    let router = scoped_routes().clone().with_state(state);
+    match router.oneshot(request).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
+
+/// The API as seen inside a namespace, built once.
+fn scoped_routes() -> &'static Router<AppState> {
+    static ROUTES: OnceLock<Router<AppState>> = OnceLock::new();
+    ROUTES.get_or_init(super::api_routes)
+}
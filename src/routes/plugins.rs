@@ -0,0 +1,8 @@
+use axum::Json;
+
+use crate::core::plugins::{self, PluginInfo};
+
+/// Loaded plugins and the operators, rules and export formats they add.
+pub async fn list_plugins() -> Json<Vec<PluginInfo>> {
+    Json(plugins::registry().plugins().to_vec())
+}
@@ -0,0 +1,48 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+
+use crate::core::error::Error;
+use crate::core::query::{QueryEngine, QueryFormat};
+use crate::state::AppState;
+
+use super::{bad_request, internal_error, ApiResult};
+
+#[derive(Debug, Deserialize)]
+pub struct QueryRequest {
+    pub sql: String,
+    #[serde(default)]
+    pub format: QueryFormat,
+}
+
+/// Run read-only SQL over the exports, the metrics history and the live
+/// `tasks` and `metrics` tables, answering with JSON rows or an Arrow IPC stream.
+pub async fn run_query(
+    State(state): State<AppState>,
+    Json(request): Json<QueryRequest>,
+) -> ApiResult<Response> {
+    let tasks = state.processor.task_records().map_err(internal_error)?;
+    let metrics = state.processor.get_metrics();
+    let mut engine = QueryEngine::new(state.exports.directory().to_path_buf());
+    if let Some(history) = &state.metrics_history {
+        engine = engine.with_metrics_history(history);
+    }
+    let result = engine
+        .run(&request.sql, &tasks, &metrics)
+        .await
+        .map_err(|e| match e {
+            Error::InvalidParameter(..) => bad_request(e),
+            _ => internal_error(e),
+        })?;
+    let bytes = result.encode(request.format).map_err(internal_error)?;
+
+    let content_type = match request.format {
+        QueryFormat::Json => "application/json",
+        QueryFormat::Arrow => "application/vnd.apache.arrow.stream",
+    };
+    Ok(([(header::CONTENT_TYPE, content_type)], bytes).into_response())
+}
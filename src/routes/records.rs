@@ -0,0 +1,66 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use futures_util::stream;
+use log::warn;
+use std::convert::Infallible;
+use std::io::{self, BufWriter, Write};
+use tokio::sync::mpsc;
+
+use mmss_core::export::stream::{write_record_stream, RecordFilter};
+
+use crate::state::AppState;
+
+use super::{internal_error, ApiResult};
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Forwards written bytes to the response body from a blocking thread.
+struct ChannelWriter(mpsc::Sender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // a closed channel means the client went away; stop encoding
+        self.0
+            .blocking_send(buf.to_vec())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Task records matching `kind` and the `[start, end)` millisecond range as
+/// an Arrow IPC stream, encoded batch by batch while the client reads. A
+/// failure mid-stream ends the body without the end-of-stream marker.
+pub async fn stream_records(
+    Query(filter): Query<RecordFilter>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let records = state.processor.task_records().map_err(internal_error)?;
+
+    let (sender, receiver) = mpsc::channel::<Vec<u8>>(8);
+    tokio::task::spawn_blocking(move || {
+        let writer = BufWriter::with_capacity(64 * 1024, ChannelWriter(sender));
+        let streamed = write_record_stream(writer, &records, &filter, CHUNK_SIZE)
+            .and_then(|(mut writer, _)| Ok(writer.flush()?));
+        if let Err(e) = streamed {
+            warn!("Record stream ended early: {}", e);
+        }
+    });
+
+    let body = stream::unfold(receiver, |mut receiver| async move {
+        let bytes = receiver.recv().await?;
+        Some((Ok::<_, Infallible>(bytes), receiver))
+    });
+    Ok((
+        [(header::CONTENT_TYPE, "application/vnd.apache.arrow.stream")],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
@@ -0,0 +1,95 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::api::embeddings::RetrievedItem;
+use crate::core::events::StateEvent;
+use crate::core::journal::Mutation;
+use crate::core::types::SemanticAnchor;
+use crate::state::AppState;
+
+use super::{bad_request, internal_error, ApiResult};
+
+#[derive(Deserialize)]
+pub struct AnchorRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "default_position")]
+    pub position: [f64; 4],
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+}
+
+fn default_position() -> [f64; 4] {
+    [0.0, 0.0, 0.0, 1.0]
+}
+
+#[derive(Serialize)]
+pub struct IndexedAnchorResponse {
+    pub anchor: SemanticAnchor,
+    pub indexed_items: usize,
+}
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub k: Option<usize>,
+}
+
+pub async fn index_anchor(
+    State(state): State<AppState>,
+    Json(request): Json<AnchorRequest>,
+) -> ApiResult<(StatusCode, Json<IndexedAnchorResponse>)> {
+    if request.name.trim().is_empty() {
+        return Err(bad_request("Anchor name cannot be empty"));
+    }
+
+    let anchor = SemanticAnchor {
+        id: Uuid::new_v4(),
+        name: request.name,
+        description: request.description,
+        position: request.position,
+        metadata: request.metadata,
+    };
+    state
+        .retriever
+        .index_anchor(&anchor)
+        .await
+        .map_err(internal_error)?;
+    if let Some(journal) = &state.journal {
+        journal.record(Mutation::AnchorIndexed {
+            anchor: anchor.clone(),
+        });
+    }
+    state.events.publish(StateEvent::AnchorIndexed {
+        id: anchor.id,
+        name: anchor.name.clone(),
+    });
+    let indexed_items = state.retriever.len().map_err(internal_error)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(IndexedAnchorResponse {
+            anchor,
+            indexed_items,
+        }),
+    ))
+}
+
+pub async fn search(
+    Query(query): Query<SearchQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<Vec<RetrievedItem>>> {
+    let k = query.k.unwrap_or_else(|| state.retriever.top_k());
+    let items = state
+        .retriever
+        .search(&query.q, k)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(items))
+}
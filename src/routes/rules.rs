@@ -4,10 +4,14 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::core::types::GeometricMetrics;
+use crate::core::audit_trail::PrivilegedAction;
+use crate::core::events::StateEvent;
+use crate::core::geometric_metrics::DeltaRule;
+use crate::core::journal::Mutation;
 use crate::state::AppState;
 
-use super::{bad_request, not_found, ApiResult};
+use super::admin::Authenticated;
+use super::{bad_request, internal_error, not_found, ApiResult};
 
 #[derive(Deserialize)]
 pub struct RegisterRuleRequest {
@@ -25,26 +29,32 @@ pub struct RegisterRuleResponse {
 
 pub async fn register_rule(
     State(state): State<AppState>,
+    Authenticated(actor): Authenticated,
     Json(payload): Json<RegisterRuleRequest>,
 ) -> ApiResult<Json<RegisterRuleResponse>> {
     if payload.name.trim().is_empty() {
         return Err(bad_request("Rule name cannot be empty"));
     }
 
+    let rule = DeltaRule {
+        name: payload.name.clone(),
+        delta_v: payload.delta_v,
+        delta_s: payload.delta_s,
+        delta_q: payload.delta_q,
+    };
     let mut engine = state.metric_engine.write().await;
-    let name = payload.name.clone();
-    engine.register_rule(name.clone(), move |metrics: &mut GeometricMetrics| {
-        if let Some(delta) = payload.delta_v {
-            metrics.v_geometric += delta;
-        }
-        if let Some(delta) = payload.delta_s {
-            metrics.s_geometric = (metrics.s_geometric + delta).clamp(0.0, 1.0);
-        }
-        if let Some(delta) = payload.delta_q {
-            metrics.q_oscillator += delta;
-        }
-        metrics.custom_metrics.insert(format!("rule:{}", name), 1.0);
-    });
+    if let Some(journal) = &state.journal {
+        journal.record(Mutation::RuleRegistered { rule: rule.clone() });
+    }
+    state.audit_trail.record(
+        &actor,
+        PrivilegedAction::RuleRegistered,
+        serde_json::to_value(&rule).map_err(internal_error)?,
+    );
+    engine.register_delta_rule(rule);
+    state
+        .events
+        .publish(StateEvent::RuleRegistered { name: payload.name });
 
     let response = RegisterRuleResponse {
         registered: true,
@@ -57,6 +67,7 @@ pub async fn register_rule(
 pub async fn delete_rule(
     Path(name): Path<String>,
     State(state): State<AppState>,
+    Authenticated(actor): Authenticated,
 ) -> ApiResult<Json<RegisterRuleResponse>> {
     let mut engine = state.metric_engine.write().await;
     let removed = engine.remove_rule(&name);
@@ -64,6 +75,15 @@ pub async fn delete_rule(
     if !removed {
         return Err(not_found("Rule not found"));
     }
+    if let Some(journal) = &state.journal {
+        journal.record(Mutation::RuleRemoved { name: name.clone() });
+    }
+    state.audit_trail.record(
+        &actor,
+        PrivilegedAction::RuleDeleted,
+        serde_json::json!({ "name": name }),
+    );
+    state.events.publish(StateEvent::RuleRemoved { name });
 
     let response = RegisterRuleResponse {
         registered: false,
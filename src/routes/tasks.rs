@@ -1,14 +1,17 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     Json,
 };
+use log::warn;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::core::audit_trail::{Actor, PrivilegedAction};
 use crate::core::semantic_task_processor::TaskStatus;
 use crate::core::types::{GeometricTaskCommand, TaskExecutionResult};
 use crate::state::AppState;
 
+use super::admin::AdminAuth;
 use super::{bad_request, internal_error, not_found, ApiResult};
 
 #[derive(Deserialize)]
@@ -37,33 +40,86 @@ fn default_execute() -> bool {
 
 pub async fn create_task(
     State(state): State<AppState>,
+    actor: Actor,
     Json(payload): Json<CreateTaskRequest>,
 ) -> ApiResult<Json<CreateTaskResponse>> {
-    let task_id = state
+    let task = payload.task.clone();
+    let task_id = state.processor.submit_task_by(payload.task, Some(actor.id))?;
+
+    let status = state
         .processor
-        .submit_task(payload.task)
-        .map_err(|err| bad_request(err.to_string()))?;
+        .get_task_status(task_id)
+        .map_err(internal_error)?;
+    if !payload.execute || status == TaskStatus::AwaitingApproval {
+        return Ok(Json(CreateTaskResponse {
+            task_id,
+            status,
+            execution_result: None,
+        }));
+    }
 
-    if payload.execute {
-        let result = state
-            .processor
-            .execute_task(task_id)
-            .map_err(|err| internal_error(err.to_string()))?;
+    run_task(&state, &task, task_id).await
+}
 
-        let response = CreateTaskResponse {
-            task_id,
-            status: TaskStatus::Completed(result.metrics.clone()),
-            execution_result: Some(result),
-        };
-        Ok(Json(response))
-    } else {
-        let response = CreateTaskResponse {
-            task_id,
+async fn run_task(
+    state: &AppState,
+    task: &GeometricTaskCommand,
+    task_id: Uuid,
+) -> ApiResult<Json<CreateTaskResponse>> {
+    let result = state
+        .processor
+        .execute_task(task_id)
+        .map_err(|err| internal_error(err.to_string()))?;
+    if let Err(err) = state.retriever.index_task(task, &result).await {
+        warn!("Failed to index task {}: {}", task_id, err);
+    }
+
+    let status = state
+        .processor
+        .get_task_status(task_id)
+        .map_err(internal_error)?;
+
+    Ok(Json(CreateTaskResponse {
+        task_id,
+        status,
+        execution_result: Some(result),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct ApproveTaskQuery {
+    #[serde(default = "default_execute")]
+    pub execute: bool,
+}
+
+/// Approve a task held by the script policy and, by default, run it.
+/// Needs the admin token, and is refused to whoever submitted the task,
+/// so a task submitted with the admin token needs another submitter.
+pub async fn approve_task(
+    _auth: AdminAuth,
+    Path(task_id): Path<String>,
+    Query(query): Query<ApproveTaskQuery>,
+    State(state): State<AppState>,
+    actor: Actor,
+) -> ApiResult<Json<CreateTaskResponse>> {
+    let id = Uuid::parse_str(&task_id).map_err(|_| bad_request("Invalid task ID"))?;
+    state.processor.approve_task_by(id, Some(&actor.id))?;
+    state.audit_trail.record(
+        &actor,
+        PrivilegedAction::ScriptApproved,
+        serde_json::json!({ "task_id": id }),
+    );
+
+    if !query.execute {
+        return Ok(Json(CreateTaskResponse {
+            task_id: id,
             status: TaskStatus::Pending,
             execution_result: None,
-        };
-        Ok(Json(response))
+        }));
     }
+
+    let task = state.processor.get_task_command(id)?;
+    run_task(&state, &task, id).await
 }
 
 pub async fn list_tasks(State(state): State<AppState>) -> ApiResult<Json<Vec<TaskListItem>>> {
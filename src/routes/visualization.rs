@@ -1,30 +1,746 @@
-use axum::{extract::State, Json};
-use serde::Serialize;
-use uuid::Uuid;
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Json,
+};
+use log::warn;
+use mmss_eqgft::sensitivity::calculate_sensitivity_curve;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
 
-use crate::core::types::SemanticAnchor;
+use crate::core::error::Result;
+use crate::core::events::StateEvent;
 use crate::state::AppState;
-use crate::visualization::protocol::VisualizationPacket;
+use crate::visualization::charts::{self, ChartFormat, ChartSize};
+use crate::visualization::gltf;
+use crate::visualization::lod::{self, LodMethod};
+use crate::visualization::mesh::{isosurface, Mesh};
+use crate::visualization::ply::{self, ColorMetric, Projection};
+use crate::visualization::protocol::{
+    FieldSummary, FrameEncoder, MetricsTrajectory, PacketDiff, PacketEncoding, VisualizationFrame,
+    VisualizationPacket,
+};
+use crate::visualization::slice::{FieldComponent, Slice, SliceAxis};
+use crate::visualization::streamlines::{streamlines, Streamline, StreamlineConfig, VectorField};
+use crate::visualization::style::{MetricStyle, StyleSheet};
 
-use super::{internal_error, ApiResult};
+use super::eqgft::{KappaScanQuery, SensitivityQuery};
+use super::{bad_request, internal_error, not_found, ApiResult};
+
+/// Upper bound on the points per axis of a field summary (64³ values).
+const MAX_FIELD_RESOLUTION: usize = 64;
+
+/// Upper bound on the iso-values of one mesh request.
+const MAX_ISO_VALUES: usize = 8;
+
+/// Upper bound on the snapshots of one trajectory request.
+const MAX_TRAJECTORY_SNAPSHOTS: usize = 4096;
+
+/// Upper bounds on the seeds per axis and steps of a streamline request.
+const MAX_STREAMLINE_SEEDS: usize = 16;
+const MAX_STREAMLINE_STEPS: usize = 2_000;
+
+/// Fastest push rate a stream client may ask for.
+const MIN_INTERVAL_MS: u64 = 20;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct PacketQuery {
+    /// Include a summary of the last generated Hopfion field.
+    pub field: bool,
+    /// Points per axis of the field summary.
+    pub field_resolution: usize,
+    /// Overrides the encoding negotiated from the `Accept` header.
+    pub encoding: Option<PacketEncoding>,
+}
+
+impl Default for PacketQuery {
+    fn default() -> Self {
+        Self {
+            field: false,
+            field_resolution: 16,
+            encoding: None,
+        }
+    }
+}
 
 #[derive(Serialize)]
 pub struct VisualizationResponse {
     pub packet: VisualizationPacket,
 }
 
-pub async fn get_packet(State(state): State<AppState>) -> ApiResult<Json<VisualizationResponse>> {
-    let metrics = state.processor.get_metrics().map_err(internal_error)?;
+/// The current packet as JSON or MessagePack, chosen by the `encoding`
+/// parameter or else the `Accept` header.
+pub async fn get_packet(
+    Query(query): Query<PacketQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    validate(&query)?;
+    let encoding = negotiate(query.encoding, &headers);
+    let packet = build_packet(&state, &query).map_err(internal_error)?;
+    let bytes = encoding
+        .encode(&VisualizationResponse { packet })
+        .map_err(internal_error)?;
+
+    Ok(([(header::CONTENT_TYPE, encoding.content_type())], bytes).into_response())
+}
+
+/// The style sheet sent with every packet.
+pub async fn get_styles(State(state): State<AppState>) -> ApiResult<Json<StyleSheet>> {
+    Ok(Json(state.styles.sheet().map_err(internal_error)?))
+}
+
+/// Set how `metric` is coloured in every client, from the next packet on.
+pub async fn put_style(
+    Path(metric): Path<String>,
+    State(state): State<AppState>,
+    Json(style): Json<MetricStyle>,
+) -> ApiResult<Json<MetricStyle>> {
+    style.validate().map_err(bad_request)?;
+    state
+        .styles
+        .set(&metric, style.clone())
+        .map_err(internal_error)?;
+    state.events.publish(StateEvent::StyleChanged { metric });
+    Ok(Json(style))
+}
+
+pub async fn delete_style(
+    Path(metric): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<MetricStyle>> {
+    let removed = state.styles.remove(&metric).map_err(internal_error)?;
+    match removed {
+        Some(style) => {
+            state.events.publish(StateEvent::StyleChanged { metric });
+            Ok(Json(style))
+        }
+        None => Err(not_found(format!("No style for metric {}", metric))),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    /// Sequence of the last packet or frame the client applied.
+    pub since_seq: u64,
+    pub encoding: Option<PacketEncoding>,
+}
+
+/// A `diff` frame with the metrics and anchors that changed since packet
+/// `since_seq`, or a `full` frame, without a field, when that packet is no
+/// longer remembered.
+pub async fn get_packet_diff(
+    Query(query): Query<DiffQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let encoding = negotiate(query.encoding, &headers);
+    let packet = build_packet(&state, &PacketQuery::default()).map_err(internal_error)?;
+    let base = state
+        .packet_history
+        .at(query.since_seq)
+        .map_err(internal_error)?;
+    let frame = match base {
+        Some(base) => VisualizationFrame::Diff(PacketDiff::between(&base, &packet)),
+        None => VisualizationFrame::Full(packet),
+    };
+    let bytes = encoding.encode(&frame).map_err(internal_error)?;
+
+    Ok(([(header::CONTENT_TYPE, encoding.content_type())], bytes).into_response())
+}
+
+/// `PacketQuery` plus the push interval; spelled out because query strings
+/// cannot carry non-string values through `#[serde(flatten)]`.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct StreamQuery {
+    pub field: bool,
+    pub field_resolution: usize,
+    /// Minimum milliseconds between updates; 100 (10 Hz) by default.
+    pub interval_ms: u64,
+    /// `msgpack` sends binary frames instead of JSON text frames.
+    pub encoding: PacketEncoding,
+}
+
+impl Default for StreamQuery {
+    fn default() -> Self {
+        let packet = PacketQuery::default();
+        Self {
+            field: packet.field,
+            field_resolution: packet.field_resolution,
+            interval_ms: 100,
+            encoding: PacketEncoding::Json,
+        }
+    }
+}
+
+impl StreamQuery {
+    fn packet(&self) -> PacketQuery {
+        PacketQuery {
+            field: self.field,
+            field_resolution: self.field_resolution,
+            encoding: Some(self.encoding),
+        }
+    }
+}
+
+/// Push packets over a WebSocket as JSON text or MessagePack binary frames:
+/// a full packet first and whenever anchors or the field change, otherwise
+/// a delta of the metrics that changed. A packet is built only after the
+/// state changed, and at most one per interval.
+pub async fn stream_packets(
+    ws: WebSocketUpgrade,
+    Query(query): Query<StreamQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    validate(&query.packet())?;
+    if query.interval_ms < MIN_INTERVAL_MS {
+        return Err(bad_request(format!(
+            "interval_ms must be at least {MIN_INTERVAL_MS}"
+        )));
+    }
+    Ok(ws.on_upgrade(move |socket| push_packets(socket, state, query)))
+}
+
+async fn push_packets(mut socket: WebSocket, state: AppState, query: StreamQuery) {
+    let packet_query = query.packet();
+    let mut encoder = FrameEncoder::default();
+    let mut events = state.events.subscribe();
+    // the first packet goes out right away
+    let mut changed = true;
+    let mut ticker = tokio::time::interval(Duration::from_millis(query.interval_ms));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            _ = ticker.tick(), if changed => {
+                changed = false;
+                let frame = match build_packet(&state, &packet_query) {
+                    Ok(packet) => encoder.encode(packet),
+                    Err(e) => {
+                        warn!("Visualization stream failed: {}", e);
+                        break;
+                    }
+                };
+                let Some(frame) = frame else { continue };
+                let message = match query.encoding.encode(&frame) {
+                    Ok(bytes) => match query.encoding {
+                        PacketEncoding::Json => {
+                            Message::Text(String::from_utf8(bytes).expect("JSON is UTF-8"))
+                        }
+                        PacketEncoding::Msgpack => Message::Binary(bytes),
+                    },
+                    Err(e) => {
+                        warn!("Failed to encode visualization frame: {}", e);
+                        break;
+                    }
+                };
+                if socket.send(message).await.is_err() {
+                    break;
+                }
+            }
+            event = events.recv() => match event {
+                // a lagging subscriber missed changes, so rebuild anyway
+                Ok(_) | Err(RecvError::Lagged(_)) => changed = true,
+                Err(RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MeshQuery {
+    /// Comma-separated energy densities to extract surfaces at.
+    pub iso: String,
+    /// Points per axis the field is downsampled to first; the generated
+    /// resolution when absent.
+    pub resolution: Option<usize>,
+    pub encoding: Option<PacketEncoding>,
+}
+
+#[derive(Serialize)]
+pub struct MeshResponse {
+    /// Points per axis of the lattice the meshes were extracted from.
+    pub resolution: usize,
+    pub meshes: Vec<Mesh>,
+}
+
+/// Isosurfaces of the last generated Hopfion field's energy density, one
+/// mesh per requested iso-value, as JSON or MessagePack.
+pub async fn get_field_mesh(
+    Query(query): Query<MeshQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let encoding = negotiate(query.encoding, &headers);
+    let (resolution, meshes) = field_meshes(&state, &query.iso, query.resolution).await?;
+    let bytes = encoding
+        .encode(&MeshResponse { resolution, meshes })
+        .map_err(internal_error)?;
+
+    Ok(([(header::CONTENT_TYPE, encoding.content_type())], bytes).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GltfQuery {
+    /// Comma-separated energy densities to extract surfaces at.
+    pub iso: String,
+    pub resolution: Option<usize>,
+}
+
+/// The isosurfaces of `GET /visualization/hopfion-field/mesh` and the
+/// indexed anchors as a binary glTF download.
+pub async fn export_gltf(
+    Query(query): Query<GltfQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let (_, meshes) = field_meshes(&state, &query.iso, query.resolution).await?;
+    let anchors = state.retriever.anchors().map_err(internal_error)?;
+    let glb = gltf::to_glb(&meshes, &anchors).map_err(internal_error)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, gltf::CONTENT_TYPE),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"hopfion.glb\"",
+            ),
+        ],
+        glb,
+    )
+        .into_response())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PointSource {
+    #[default]
+    All,
+    Anchors,
+    Field,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PlyQuery {
+    pub source: PointSource,
+    pub projection: Projection,
+    pub color: ColorMetric,
+    /// Cap on the field points, as for `GET /visualization/hopfion-field`.
+    pub points: Option<usize>,
+}
+
+/// Anchors and field quaternions projected into 3D as a PLY download.
+/// With `source=all`, the field is left out until one has been generated.
+pub async fn export_ply(
+    Query(query): Query<PlyQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let mut points = Vec::new();
+    if query.source != PointSource::Field {
+        let anchors = state.retriever.anchors().map_err(internal_error)?;
+        points.extend(ply::anchor_points(&anchors));
+    }
+    if query.source != PointSource::Anchors {
+        let field = state.processor.hopfion_field().map_err(internal_error)?;
+        match field {
+            Some(field) => {
+                let max_points = lod::max_points_from_env();
+                let target_points = query.points.unwrap_or(max_points).min(max_points);
+                let field = lod::downsample(&field, LodMethod::Strided, target_points);
+                points.extend(ply::field_points(&field));
+            }
+            None if query.source == PointSource::Field => {
+                return Err(not_found("No Hopfion field has been generated"));
+            }
+            None => {}
+        }
+    }
+    let cloud = ply::to_ply(&points, query.projection, query.color);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, ply::CONTENT_TYPE),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"points.ply\"",
+            ),
+        ],
+        cloud,
+    )
+        .into_response())
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct FieldQuery {
+    pub lod: LodMethod,
+    /// Target point count, capped at `MMSS_VISUALIZATION_MAX_POINTS`.
+    pub points: Option<usize>,
+    pub encoding: Option<PacketEncoding>,
+}
+
+/// The last generated Hopfion field, reduced to the requested level of
+/// detail; the level actually served is echoed in `lod`.
+pub async fn get_field(
+    Query(query): Query<FieldQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let field = state
+        .processor
+        .hopfion_field()
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found("No Hopfion field has been generated"))?;
+    let max_points = lod::max_points_from_env();
+    let target_points = query.points.unwrap_or(max_points).min(max_points);
+    let encoding = negotiate(query.encoding, &headers);
 
-    let anchors = vec![SemanticAnchor {
-        id: Uuid::new_v4(),
-        name: "Root Anchor".into(),
-        description: "Placeholder semantic anchor".into(),
-        position: [0.0, 0.0, 0.0, 1.0],
-        metadata: serde_json::json!({ "note": "replace with real anchors" }),
-    }];
+    let bytes = tokio::task::spawn_blocking(move || {
+        encoding.encode(&lod::downsample(&field, query.lod, target_points))
+    })
+    .await
+    .map_err(internal_error)?
+    .map_err(internal_error)?;
+
+    Ok(([(header::CONTENT_TYPE, encoding.content_type())], bytes).into_response())
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct VectorFieldQuery {
+    /// Cap on the lattice points, as for `GET /visualization/hopfion-field`.
+    pub points: Option<usize>,
+    pub encoding: Option<PacketEncoding>,
+}
+
+/// The director field of the last generated Hopfion, on a strided lattice
+/// of at most `points` points.
+pub async fn get_vector_field(
+    Query(query): Query<VectorFieldQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let field = state
+        .processor
+        .hopfion_field()
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found("No Hopfion field has been generated"))?;
+    let max_points = lod::max_points_from_env();
+    let target_points = query.points.unwrap_or(max_points).min(max_points);
+    let encoding = negotiate(query.encoding, &headers);
+
+    let bytes = tokio::task::spawn_blocking(move || {
+        let reduced = lod::downsample(&field, LodMethod::Strided, target_points);
+        encoding.encode(&VectorField::director(reduced.axis, &reduced.q_x))
+    })
+    .await
+    .map_err(internal_error)?
+    .map_err(internal_error)?;
+
+    Ok(([(header::CONTENT_TYPE, encoding.content_type())], bytes).into_response())
+}
+
+/// `StreamlineConfig` spelled out, with the encoding.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct StreamlineQuery {
+    pub seeds: usize,
+    pub step: Option<f64>,
+    pub max_steps: usize,
+    pub encoding: Option<PacketEncoding>,
+}
+
+impl Default for StreamlineQuery {
+    fn default() -> Self {
+        let config = StreamlineConfig::default();
+        Self {
+            seeds: config.seeds,
+            step: config.step,
+            max_steps: config.max_steps,
+            encoding: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct StreamlineResponse {
+    pub step: f64,
+    pub streamlines: Vec<Streamline>,
+}
+
+/// Streamlines of the director field of the last generated Hopfion, traced
+/// with RK4 from a `seeds`³ grid.
+pub async fn get_streamlines(
+    Query(query): Query<StreamlineQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    if query.seeds > MAX_STREAMLINE_SEEDS || query.max_steps > MAX_STREAMLINE_STEPS {
+        return Err(bad_request(format!(
+            "Expected at most {MAX_STREAMLINE_SEEDS} seeds per axis and {MAX_STREAMLINE_STEPS} steps"
+        )));
+    }
+    let field = state
+        .processor
+        .hopfion_field()
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found("No Hopfion field has been generated"))?;
+    let step = query.step.unwrap_or(field.config.spacing() / 2.0);
+    if !(step.is_finite() && step > 0.0) {
+        return Err(bad_request("step must be positive"));
+    }
+    let encoding = negotiate(query.encoding, &headers);
+    let config = StreamlineConfig {
+        seeds: query.seeds,
+        step: Some(step),
+        max_steps: query.max_steps,
+    };
+
+    let bytes = tokio::task::spawn_blocking(move || {
+        encoding.encode(&StreamlineResponse {
+            step,
+            streamlines: streamlines(&VectorField::from_field(&field), &config),
+        })
+    })
+    .await
+    .map_err(internal_error)?
+    .map_err(internal_error)?;
+
+    Ok(([(header::CONTENT_TYPE, encoding.content_type())], bytes).into_response())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SliceFormat {
+    #[default]
+    Json,
+    Png,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SliceQuery {
+    pub axis: SliceAxis,
+    /// Lattice index along `axis`; the middle plane when absent.
+    pub index: Option<usize>,
+    #[serde(default)]
+    pub component: FieldComponent,
+    #[serde(default)]
+    pub format: SliceFormat,
+    /// Pixels per lattice point of a PNG.
+    #[serde(default = "default_cell")]
+    pub cell: u32,
+}
+
+fn default_cell() -> u32 {
+    8
+}
+
+/// One plane of the last generated Hopfion field as a JSON grid, or as a
+/// PNG heatmap with `format=png`.
+pub async fn get_field_slice(
+    Query(query): Query<SliceQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let field = state
+        .processor
+        .hopfion_field()
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found("No Hopfion field has been generated"))?;
+    let index = query.index.unwrap_or(field.resolution() / 2);
+    let slice = Slice::extract(&field, query.axis, index, query.component).map_err(bad_request)?;
+
+    match query.format {
+        SliceFormat::Json => Ok(Json(slice).into_response()),
+        SliceFormat::Png => {
+            let png = tokio::task::spawn_blocking(move || slice.to_png(query.cell))
+                .await
+                .map_err(internal_error)?
+                .map_err(internal_error)?;
+            Ok(([(header::CONTENT_TYPE, "image/png")], png).into_response())
+        }
+    }
+}
+
+/// A simulated sensitivity curve, with the parameters of
+/// `GET /eqgft/sensitivity/stream`, as a PNG chart.
+pub async fn sensitivity_chart_png(
+    Query(query): Query<SensitivityQuery>,
+    Query(size): Query<ChartSize>,
+) -> ApiResult<Response> {
+    sensitivity_chart(query, size, ChartFormat::Png).await
+}
+
+pub async fn sensitivity_chart_svg(
+    Query(query): Query<SensitivityQuery>,
+    Query(size): Query<ChartSize>,
+) -> ApiResult<Response> {
+    sensitivity_chart(query, size, ChartFormat::Svg).await
+}
+
+/// A simulated κ scan, with the parameters of a `SimulateEqgftKappaScan`
+/// task, as a PNG chart.
+pub async fn kappa_scan_chart_png(
+    Query(query): Query<KappaScanQuery>,
+    Query(size): Query<ChartSize>,
+) -> ApiResult<Response> {
+    kappa_scan_chart(query, size, ChartFormat::Png).await
+}
+
+pub async fn kappa_scan_chart_svg(
+    Query(query): Query<KappaScanQuery>,
+    Query(size): Query<ChartSize>,
+) -> ApiResult<Response> {
+    kappa_scan_chart(query, size, ChartFormat::Svg).await
+}
+
+async fn sensitivity_chart(
+    query: SensitivityQuery,
+    size: ChartSize,
+    format: ChartFormat,
+) -> ApiResult<Response> {
+    let (config, n_values) = query.plan()?;
+    let image = tokio::task::spawn_blocking(move || {
+        let curve = calculate_sensitivity_curve(&config, &n_values).map_err(bad_request)?;
+        charts::render(&curve, format, size).map_err(internal_error)
+    })
+    .await
+    .map_err(internal_error)??;
+
+    Ok(([(header::CONTENT_TYPE, format.content_type())], image).into_response())
+}
+
+async fn kappa_scan_chart(
+    query: KappaScanQuery,
+    size: ChartSize,
+    format: ChartFormat,
+) -> ApiResult<Response> {
+    let image = tokio::task::spawn_blocking(move || {
+        let scan = query.scan()?;
+        charts::render(scan.as_slice(), format, size).map_err(internal_error)
+    })
+    .await
+    .map_err(internal_error)??;
+
+    Ok(([(header::CONTENT_TYPE, format.content_type())], image).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrajectoryQuery {
+    /// Latest snapshots to include; all that are kept when absent.
+    pub limit: Option<usize>,
+    pub encoding: Option<PacketEncoding>,
+}
+
+/// Recent metric snapshots as per-metric series, annotated with the tasks
+/// that produced them.
+pub async fn get_trajectory(
+    Query(query): Query<TrajectoryQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let history = state
+        .metrics_history
+        .as_ref()
+        .ok_or_else(|| not_found("Metrics history is disabled (set MMSS_METRICS_HISTORY_DIR)"))?;
+    let limit = query
+        .limit
+        .unwrap_or(MAX_TRAJECTORY_SNAPSHOTS)
+        .min(MAX_TRAJECTORY_SNAPSHOTS);
+    let snapshots = history.recent(limit).map_err(internal_error)?;
+    let trajectory = MetricsTrajectory::from_snapshots(state.packet_sequence.next(), &snapshots);
+    let encoding = negotiate(query.encoding, &headers);
+    let bytes = encoding.encode(&trajectory).map_err(internal_error)?;
+
+    Ok(([(header::CONTENT_TYPE, encoding.content_type())], bytes).into_response())
+}
+
+/// Isosurfaces of the last generated field at the comma-separated `iso`
+/// values, after downsampling to `resolution`; returns the resolution used.
+async fn field_meshes(
+    state: &AppState,
+    iso: &str,
+    resolution: Option<usize>,
+) -> ApiResult<(usize, Vec<Mesh>)> {
+    let iso_values = iso
+        .split(',')
+        .map(|value| value.trim().parse::<f64>())
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .ok()
+        .filter(|values| (1..=MAX_ISO_VALUES).contains(&values.len()))
+        .filter(|values| values.iter().all(|value| value.is_finite()))
+        .ok_or_else(|| {
+            bad_request(format!(
+                "iso must list between 1 and {MAX_ISO_VALUES} comma-separated numbers"
+            ))
+        })?;
+    let field = state
+        .processor
+        .hopfion_field()
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found("No Hopfion field has been generated"))?;
+    let resolution = resolution.unwrap_or(field.resolution());
+    if !(2..=MAX_FIELD_RESOLUTION).contains(&resolution) {
+        return Err(bad_request(format!(
+            "resolution must be between 2 and {MAX_FIELD_RESOLUTION}"
+        )));
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let summary = FieldSummary::downsample(&field, resolution);
+        let meshes = iso_values
+            .into_iter()
+            .map(|iso| isosurface(&summary, iso))
+            .collect();
+        (summary.resolution, meshes)
+    })
+    .await
+    .map_err(internal_error)
+}
+
+/// `requested` if given, else the encoding the `Accept` header prefers.
+fn negotiate(requested: Option<PacketEncoding>, headers: &HeaderMap) -> PacketEncoding {
+    requested.unwrap_or_else(|| {
+        PacketEncoding::negotiate(
+            headers
+                .get(header::ACCEPT)
+                .and_then(|accept| accept.to_str().ok()),
+        )
+    })
+}
+
+fn validate(query: &PacketQuery) -> ApiResult<()> {
+    if query.field_resolution == 0 || query.field_resolution > MAX_FIELD_RESOLUTION {
+        return Err(bad_request(format!(
+            "field_resolution must be between 1 and {MAX_FIELD_RESOLUTION}"
+        )));
+    }
+    Ok(())
+}
 
-    let packet = VisualizationPacket::new(metrics, anchors);
+fn build_packet(state: &AppState, query: &PacketQuery) -> Result<VisualizationPacket> {
+    let metrics = state.processor.get_metrics();
+    let anchors = state.retriever.anchors()?;
+    let field = if query.field {
+        state
+            .processor
+            .hopfion_field()?
+            .map(|field| FieldSummary::downsample(&field, query.field_resolution))
+    } else {
+        None
+    };
 
-    Ok(Json(VisualizationResponse { packet }))
+    let packet = VisualizationPacket::new(state.packet_sequence.next(), metrics, anchors, field)
+        .with_style(state.styles.sheet()?);
+    state.packet_history.record(&packet)?;
+    Ok(packet)
 }
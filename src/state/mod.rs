@@ -1,49 +1,367 @@
+pub mod namespaces;
+#[cfg(feature = "shared-state")]
+pub mod shared;
+
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
+use crate::api::data_io::DataIoGateway;
+use crate::api::embeddings::{ItemKind, Retriever};
 use crate::api::llm_gateway::LlmGateway;
+use crate::campaign::CampaignStore;
+use crate::config::Config;
+use crate::core::audit_trail::AuditTrail;
+use crate::core::credentials::Credentials;
+use crate::core::events::{EventBus, StateEvent};
+use crate::core::exports::ExportJobs;
 use crate::core::geometric_metrics::GeometricMetricEngine;
+use crate::core::journal::Journal;
+use crate::core::metrics_history::{MetricsHistory, MetricsHistoryConfig};
+use crate::core::ops_metrics::OpsMetrics;
+use crate::core::plugins;
 use crate::core::semantic_task_processor::SemanticTaskProcessor;
-use crate::Result;
+use crate::core::types::{ResetScope, SystemState};
+use crate::visualization::protocol::{PacketHistory, PacketSequence};
+use crate::visualization::style::StyleRegistry;
+use namespaces::Namespaces;
+use crate::{Error, Result};
+use log::{error, info, warn};
 use tokio::sync::RwLock;
 
-pub const HBAR: f64 = 1.054_571_817e-34; // J·s
-pub const C: f64 = 299_792_458.0; // m/s
-pub const ZITTER_FREQUENCY: f64 = 1.55e21; // rad/s
-pub const ZITTER_AMPLITUDE: f64 = 1.93e-13; // m
+pub use mmss_compute::constants::{
+    compute_quaternion_coherence, compute_zitter_entropy, PhysicalConstants, C, FINE_STRUCTURE,
+    HBAR, ZITTER_AMPLITUDE, ZITTER_FREQUENCY,
+};
+
+/// A rule engine with the rules of the installed plugins.
+fn new_metric_engine(ops: &Arc<OpsMetrics>) -> GeometricMetricEngine {
+    let mut engine = GeometricMetricEngine::new().with_ops_metrics(ops.clone());
+    plugins::registry().register_rules(&mut engine);
+    engine
+}
 
 #[derive(Clone)]
 pub struct AppState {
     pub processor: Arc<SemanticTaskProcessor>,
+    /// State changes in this namespace, shared with the processor.
+    pub events: Arc<EventBus>,
     pub metric_engine: Arc<RwLock<GeometricMetricEngine>>,
     pub llm_gateway: Arc<LlmGateway>,
+    pub campaigns: Arc<CampaignStore>,
+    pub retriever: Arc<Retriever>,
+    pub exports: Arc<ExportJobs>,
+    /// Persisted metrics snapshots, when `persistence.metrics_history_dir`
+    /// is set.
+    pub metrics_history: Option<Arc<MetricsHistory>>,
+    pub packet_sequence: Arc<PacketSequence>,
+    /// Recent packet states, for clients catching up after a reconnect.
+    pub packet_history: Arc<PacketHistory>,
+    /// Per-metric colour maps sent with every packet.
+    pub styles: Arc<StyleRegistry>,
+    /// Constants the processor's metrics are computed from.
+    pub constants: PhysicalConstants,
+    /// Isolated states served under `/ns/:namespace`, shared by all of them.
+    pub namespaces: Arc<Namespaces>,
+    /// Every mutation of the default namespace, when `persistence.journal`
+    /// is set; shared with its processor.
+    pub journal: Option<Arc<Journal>>,
+    /// The admin token, which destructive admin routes are refused
+    /// without, operator keys and the JWT secret callers are verified with.
+    pub credentials: Arc<Credentials>,
+    /// Task, LLM and rule metrics of every namespace.
+    pub ops: Arc<OpsMetrics>,
+    /// Privileged operations in every namespace, and who performed them.
+    pub audit_trail: Arc<AuditTrail>,
 }
 
 impl AppState {
-    pub fn initialize(api_key: Option<String>) -> Result<Self> {
-        let processor = Arc::new(SemanticTaskProcessor::new());
-        let metric_engine = Arc::new(RwLock::new(GeometricMetricEngine::new()));
-        let llm_gateway = Arc::new(LlmGateway::new(api_key)?);
+    /// The server's state, with the gateway and persistence `config` names.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Ok(Self::configured(config, LlmGateway::from_config(config)?))
+    }
+
+    /// Fresh state around an explicit gateway, e.g. one backed by `MockProvider`.
+    pub fn with_llm_gateway(llm_gateway: LlmGateway) -> Self {
+        Self::configured(&Config::default(), llm_gateway)
+    }
+
+    fn configured(config: &Config, llm_gateway: LlmGateway) -> Self {
+        let persistence = &config.persistence;
+        let metrics_history = persistence.metrics_history_dir.clone().map(|directory| {
+            Arc::new(MetricsHistory::new(MetricsHistoryConfig::in_directory(
+                directory,
+            )))
+        });
+        let constants = config.constants;
+        let ops = Arc::new(OpsMetrics::new());
+        let mut processor = SemanticTaskProcessor::new()
+            .with_constants(constants)
+            .with_ops_metrics(ops.clone());
+        if let Some(directory) = &persistence.eqgft_cache_dir {
+            processor = processor.with_eqgft_cache_dir(directory);
+        }
+        if let Some(history) = &metrics_history {
+            processor = processor.with_metrics_history(history.clone());
+        }
+        let journal = match persistence.journal.clone().map(Journal::open) {
+            Some(Ok(journal)) => Some(Arc::new(journal)),
+            Some(Err(e)) => {
+                error!("Not keeping a journal: {}", e);
+                None
+            }
+            None => None,
+        };
+        if let Some(journal) = &journal {
+            processor = processor.with_journal(journal.clone());
+        }
+        let mut audit_trail = match &persistence.audit_trail {
+            Some(path) => AuditTrail::open(path.clone()).unwrap_or_else(|e| {
+                error!("Keeping the audit trail in memory only: {}", e);
+                AuditTrail::in_memory()
+            }),
+            None => AuditTrail::in_memory(),
+        };
+        match &config.server.audit_key {
+            Some(key) => audit_trail = audit_trail.with_key(key.as_bytes()),
+            None => warn!(
+                "server.audit_key is unset; the audit trail's hash chain shows \
+                 accidental edits but not a rewritten chain"
+            ),
+        }
+        let processor = Arc::new(processor);
+        let events = processor.events().clone();
+        let metric_engine = Arc::new(RwLock::new(new_metric_engine(&ops)));
+        let llm_gateway = Arc::new(llm_gateway.with_ops_metrics(ops.clone()));
+        let campaigns = Arc::new(CampaignStore::new());
+        let retriever = Arc::new(Retriever::from_env());
+        let exports = Arc::new(ExportJobs::in_directory(persistence.export_dir.clone()));
 
-        Ok(Self {
+        Self {
             processor,
+            events,
             metric_engine,
             llm_gateway,
+            campaigns,
+            retriever,
+            exports,
+            metrics_history,
+            packet_sequence: Arc::new(PacketSequence::default()),
+            packet_history: Arc::new(PacketHistory::from_env()),
+            styles: Arc::new(StyleRegistry::default()),
+            constants,
+            namespaces: Arc::new(Namespaces::from_env()),
+            journal,
+            credentials: Arc::new(Credentials::from_config(&config.server)),
+            ops,
+            audit_trail: Arc::new(audit_trail),
+        }
+    }
+
+    /// A fresh state for namespace `name`, with its own task processor,
+    /// rules, campaigns, anchors, styles, packet stream, LLM sessions and
+    /// token budgets, and metrics history and export jobs under `ns/<name>`
+    /// of this one's. The LLM provider and prompts and constants are shared.
+    pub fn namespaced(&self, name: &str) -> Self {
+        let metrics_history = self.metrics_history.as_ref().map(|history| {
+            Arc::new(history.in_directory(history.directory().join("ns").join(name)))
+        });
+        let exports = self
+            .exports
+            .in_subdirectory(self.exports.directory().join("ns").join(name));
+        let mut processor = SemanticTaskProcessor::new()
+            .with_constants(self.constants)
+            .with_ops_metrics(self.ops.clone());
+        if let Some(history) = &metrics_history {
+            processor = processor.with_metrics_history(history.clone());
+        }
+
+        let processor = Arc::new(processor);
+
+        Self {
+            events: processor.events().clone(),
+            processor,
+            metric_engine: Arc::new(RwLock::new(new_metric_engine(&self.ops))),
+            llm_gateway: Arc::new(self.llm_gateway.scoped()),
+            campaigns: Arc::new(CampaignStore::new()),
+            retriever: Arc::new(Retriever::from_env()),
+            exports: Arc::new(exports),
+            metrics_history,
+            packet_sequence: Arc::new(PacketSequence::default()),
+            packet_history: Arc::new(PacketHistory::from_env()),
+            styles: Arc::new(StyleRegistry::default()),
+            constants: self.constants,
+            namespaces: self.namespaces.clone(),
+            journal: None,
+            credentials: self.credentials.clone(),
+            ops: self.ops.clone(),
+            audit_trail: self.audit_trail.clone(),
+        }
+    }
+
+    /// Everything a restart would otherwise lose: metrics and the Hopfion
+    /// field, API-registered rules, indexed anchors and unrun tasks.
+    pub async fn snapshot(&self) -> Result<SystemState> {
+        let pending_tasks = self.processor.pending_tasks()?;
+        Ok(SystemState {
+            state_id: uuid::Uuid::new_v4(),
+            timestamp: chrono::Utc::now(),
+            metrics: self.processor.get_metrics(),
+            active_anchors: self.retriever.anchors()?,
+            active_tasks: pending_tasks.iter().map(|task| task.task_id).collect(),
+            rules: self.metric_engine.read().await.delta_rules(),
+            pending_tasks,
+            hopfion: self
+                .processor
+                .hopfion_field()?
+                .map(|field| field.config),
         })
     }
+
+    /// Continue from `saved`. Anchors are embedded again, and one that fails
+    /// to index is skipped with a warning.
+    pub async fn restore(&self, saved: SystemState) -> Result<()> {
+        let processor = self.processor.clone();
+        let (metrics, hopfion) = (saved.metrics, saved.hopfion);
+        // regenerating the field is CPU-bound, unless the cache spilled it
+        tokio::task::spawn_blocking(move || processor.restore_metrics(metrics, hopfion))
+            .await
+            .map_err(|e| Error::TaskExecution(format!("State restore panicked: {}", e)))??;
+
+        {
+            let mut engine = self.metric_engine.write().await;
+            for rule in saved.rules {
+                engine.register_delta_rule(rule);
+            }
+        }
+        for anchor in &saved.active_anchors {
+            if let Err(e) = self.retriever.index_anchor(anchor).await {
+                warn!("Not restoring anchor {}: {}", anchor.name, e);
+            }
+        }
+        let restored = self.processor.restore_pending_tasks(saved.pending_tasks)?;
+        info!(
+            "Restored state {} from {} with {} pending tasks",
+            saved.state_id, saved.timestamp, restored
+        );
+        Ok(())
+    }
+
+    /// Return the components in `scope` (all, when it is empty) to their
+    /// state at startup without a restart, returning what was reset. The
+    /// rules lock is held throughout, so no rule change interleaves.
+    pub async fn reset(&self, scope: ResetScope) -> Result<ResetScope> {
+        let scope = scope.or_everything();
+        let mut engine = self.metric_engine.write().await;
+        self.processor.reset(&scope)?;
+        if scope.tasks {
+            self.retriever.clear(ItemKind::Task)?;
+        }
+        if scope.anchors {
+            self.retriever.clear(ItemKind::Anchor)?;
+        }
+        if scope.rules {
+            *engine = new_metric_engine(&self.ops);
+        }
+        self.events.publish(StateEvent::Reset(scope));
+        Ok(scope)
+    }
+
+    pub async fn persist(&self, directory: PathBuf) -> Result<()> {
+        let snapshot = self.snapshot().await?;
+        tokio::task::spawn_blocking(move || DataIoGateway::persist_state(&directory, &snapshot))
+            .await
+            .map_err(|e| Error::TaskExecution(format!("State persist panicked: {}", e)))?
+    }
 }
 
+/// Persist `state` to `directory` every `period`, logging failures.
+pub fn spawn_state_persistence(
+    state: AppState,
+    directory: PathBuf,
+    period: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(period);
+        // the first tick is immediate, and there is nothing new to save yet
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            if let Err(e) = state.persist(directory.clone()).await {
+                error!("State persistence failed: {}", e);
+            }
+        }
+    })
+}
+
+/// Electron mass from the SI constants; see `PhysicalConstants` for others.
 pub fn compute_electron_mass() -> f64 {
-    HBAR / (2.0 * C * ZITTER_AMPLITUDE)
+    PhysicalConstants::SI.electron_mass()
 }
 
 pub fn compute_fine_structure() -> f64 {
-    1.0 / 137.035_999_084
+    FINE_STRUCTURE
 }
 
-pub fn compute_quaternion_coherence() -> f64 {
-    0.9997
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock_llm::MockProvider;
+    use crate::core::geometric_metrics::DeltaRule;
+    use crate::core::types::{GeometricOperator, GeometricTaskCommand, SemanticAnchor};
+
+    #[tokio::test]
+    async fn test_reset_restores_selected_components() {
+        let gateway = LlmGateway::with_provider(Arc::new(MockProvider::new())).unwrap();
+        let state = AppState::with_llm_gateway(gateway);
+        let baseline = state.processor.get_metrics();
 
-pub fn compute_zitter_entropy() -> f64 {
-    0.0003
+        let task_id = state
+            .processor
+            .submit_task(GeometricTaskCommand {
+                task_name: "Rotate".to_string(),
+                geometric_operator: GeometricOperator::QuaternionRotation,
+                target_module: "test_module".to_string(),
+                parameters: serde_json::json!({ "theta": 1.0 }),
+                expected_output_metric: "v_geometric".to_string(),
+                task_id: None,
+            })
+            .unwrap();
+        state.processor.execute_task(task_id).unwrap();
+        state.metric_engine.write().await.register_delta_rule(DeltaRule {
+            name: "boost".to_string(),
+            delta_v: Some(1.0),
+            delta_s: None,
+            delta_q: None,
+        });
+        state
+            .retriever
+            .index_anchor(&SemanticAnchor {
+                id: uuid::Uuid::new_v4(),
+                name: "origin".to_string(),
+                description: "the identity".to_string(),
+                position: [0.0, 0.0, 0.0, 1.0],
+                metadata: serde_json::Value::Null,
+            })
+            .await
+            .unwrap();
+
+        let scope = ResetScope {
+            metrics: true,
+            rules: true,
+            ..ResetScope::default()
+        };
+        assert_eq!(state.reset(scope).await.unwrap(), scope);
+        assert_eq!(state.processor.get_metrics(), baseline);
+        assert!(state.metric_engine.read().await.is_empty());
+        assert!(state.processor.get_task_status(task_id).is_ok());
+        assert_eq!(state.retriever.anchors().unwrap().len(), 1);
+
+        assert_eq!(
+            state.reset(ResetScope::default()).await.unwrap(),
+            ResetScope::EVERYTHING
+        );
+        assert!(state.processor.get_task_status(task_id).is_err());
+        assert!(state.retriever.is_empty().unwrap());
+    }
 }
@@ -0,0 +1,192 @@
+//! Isolated copies of the server state, one per team, served under
+//! `/ns/:namespace/...`. Routes without the prefix use the default
+//! namespace. Namespaces are created on first use and live in memory; only
+//! the default one is persisted across restarts and journaled.
+
+use super::AppState;
+use crate::core::error::{Error, Result};
+use std::collections::BTreeMap;
+use std::env;
+use std::sync::RwLock;
+
+/// Served by the un-prefixed routes, and also reachable as `/ns/default`.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+const DEFAULT_MAX_NAMESPACES: usize = 64;
+const MAX_NAME_LENGTH: usize = 64;
+
+pub struct Namespaces {
+    max_namespaces: usize,
+    states: RwLock<BTreeMap<String, AppState>>,
+}
+
+impl Namespaces {
+    pub fn new(max_namespaces: usize) -> Self {
+        Self {
+            max_namespaces,
+            states: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// At most `MMSS_MAX_NAMESPACES` (default 64) besides the default one.
+    pub fn from_env() -> Self {
+        Self::new(
+            env::var("MMSS_MAX_NAMESPACES")
+                .ok()
+                .and_then(|raw| raw.parse().ok())
+                .unwrap_or(DEFAULT_MAX_NAMESPACES),
+        )
+    }
+
+    /// The state of namespace `name`, derived from the default namespace's
+    /// `root` on first use.
+    pub fn get_or_create(&self, name: &str, root: &AppState) -> Result<AppState> {
+        validate_name(name)?;
+        if name == DEFAULT_NAMESPACE {
+            return Ok(root.clone());
+        }
+        if let Some(state) = self.states.read().map_err(lock_error)?.get(name) {
+            return Ok(state.clone());
+        }
+
+        let mut states = self.states.write().map_err(lock_error)?;
+        if let Some(state) = states.get(name) {
+            return Ok(state.clone());
+        }
+        if states.len() >= self.max_namespaces {
+            return Err(Error::InvalidParameter(
+                "namespace".to_string(),
+                format!("the limit of {} namespaces is reached", self.max_namespaces),
+            ));
+        }
+        let state = root.namespaced(name);
+        states.insert(name.to_string(), state.clone());
+        Ok(state)
+    }
+
+    /// Namespaces created so far, without the default one.
+    pub fn names(&self) -> Result<Vec<String>> {
+        Ok(self
+            .states
+            .read()
+            .map_err(lock_error)?
+            .keys()
+            .cloned()
+            .collect())
+    }
+
+    /// Drop namespace `name` and everything in it. Requests already holding
+    /// its state finish against it.
+    pub fn remove(&self, name: &str) -> Result<bool> {
+        if name == DEFAULT_NAMESPACE {
+            return Err(Error::InvalidParameter(
+                "namespace".to_string(),
+                "the default namespace cannot be removed".to_string(),
+            ));
+        }
+        Ok(self
+            .states
+            .write()
+            .map_err(lock_error)?
+            .remove(name)
+            .is_some())
+    }
+}
+
+/// Letters, digits, `-` and `_`, at most 64 of them.
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidParameter(
+            "namespace".to_string(),
+            format!(
+                "`{}` must be 1 to {} letters, digits, '-' or '_'",
+                name, MAX_NAME_LENGTH
+            ),
+        ))
+    }
+}
+
+fn lock_error(e: impl std::fmt::Display) -> Error {
+    Error::TaskExecution(format!("Failed to access namespaces: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::llm_gateway::LlmGateway;
+    use crate::api::mock_llm::MockProvider;
+    use crate::api::usage::{TokenUsage, UsageScope};
+    use crate::core::types::{GeometricOperator, GeometricTaskCommand};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_namespaces_are_isolated() {
+        let gateway = LlmGateway::with_provider(Arc::new(MockProvider::new())).unwrap();
+        let root = AppState::with_llm_gateway(gateway);
+        let namespaces = Namespaces::new(1);
+
+        let team = namespaces.get_or_create("team-a", &root).unwrap();
+        let task = GeometricTaskCommand {
+            task_name: "Rotate".to_string(),
+            geometric_operator: GeometricOperator::QuaternionRotation,
+            target_module: "test_module".to_string(),
+            parameters: serde_json::json!({ "theta": 1.0 }),
+            expected_output_metric: "v_geometric".to_string(),
+            task_id: None,
+        };
+        let task_id = team.processor.submit_task(task).unwrap();
+        team.processor.execute_task(task_id).unwrap();
+
+        assert_ne!(
+            team.processor.get_metrics(),
+            root.processor.get_metrics()
+        );
+        assert!(root.processor.get_task_status(task_id).is_err());
+        let again = namespaces.get_or_create("team-a", &root).unwrap();
+        assert!(again.processor.get_task_status(task_id).is_ok());
+        assert!(Arc::ptr_eq(
+            &namespaces
+                .get_or_create(DEFAULT_NAMESPACE, &root)
+                .unwrap()
+                .processor,
+            &root.processor
+        ));
+
+        assert!(namespaces.get_or_create("team-b", &root).is_err());
+        assert!(namespaces.get_or_create("../etc", &root).is_err());
+        assert_eq!(namespaces.names().unwrap(), vec!["team-a".to_string()]);
+        assert!(namespaces.remove(DEFAULT_NAMESPACE).is_err());
+        assert!(namespaces.remove("team-a").unwrap());
+        assert!(namespaces.get_or_create("team-b", &root).is_ok());
+    }
+
+    #[test]
+    fn test_namespaces_have_their_own_llm_usage() {
+        let gateway = LlmGateway::with_provider(Arc::new(MockProvider::new())).unwrap();
+        let root = AppState::with_llm_gateway(gateway);
+        let team = Namespaces::new(1).get_or_create("team-a", &root).unwrap();
+
+        let usage = TokenUsage {
+            prompt_tokens: 10,
+            completion_tokens: 5,
+            total_tokens: 15,
+        };
+        team.llm_gateway
+            .usage()
+            .record(&UsageScope::for_key(Some("team-key")), &usage)
+            .unwrap();
+
+        assert_eq!(team.llm_gateway.usage().report().unwrap().total, usage);
+        assert_eq!(
+            root.llm_gateway.usage().report().unwrap().total,
+            TokenUsage::default()
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! Tasks and metrics shared through Redis, so replicas behind a load
+//! balancer see the same view, and a lease electing the one replica that
+//! runs the background loops. Built only with the `shared-state` feature.
+//!
+//! Every replica mirrors its task transitions into the `<prefix>:tasks`
+//! hash and its metrics into `<prefix>:metrics`, and announces each change
+//! on `<prefix>:changes`; the others apply it to their own processor. When
+//! two replicas change the metrics at once the later write wins. Resets,
+//! rules, anchors and namespaces other than the default stay per replica.
+
+use crate::config::ClusterConfig;
+use crate::core::error::{Error, Result};
+use crate::core::events::StateEvent;
+use crate::core::semantic_task_processor::{SemanticTaskProcessor, TaskEntry, TaskStatus};
+use crate::core::types::GeometricMetrics;
+use futures_util::StreamExt;
+use log::{error, info, warn};
+use redis::aio::MultiplexedConnection;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+const DEFAULT_PREFIX: &str = "mmss";
+const DEFAULT_LEADER_TTL: Duration = Duration::from_secs(15);
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Take the lease when it is free, or extend it when this replica holds it.
+const LEASE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    redis.call('PEXPIRE', KEYS[1], ARGV[2])
+    return 1
+elseif redis.call('SET', KEYS[1], ARGV[1], 'NX', 'PX', ARGV[2]) then
+    return 1
+end
+return 0
+"#;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedStateConfig {
+    pub url: String,
+    /// Prefix of every key and channel, so deployments can share a server.
+    pub prefix: String,
+    /// A leader that stops renewing is replaced after this long.
+    pub leader_ttl: Duration,
+}
+
+impl SharedStateConfig {
+    /// Enabled by `cluster.redis_url`; `cluster.key_prefix` (default
+    /// `mmss`) and `cluster.leader_ttl_secs` (default 15) tune it.
+    pub fn from_config(config: &ClusterConfig) -> Option<Self> {
+        Some(Self {
+            url: config.redis_url.clone()?,
+            prefix: config
+                .key_prefix
+                .clone()
+                .unwrap_or_else(|| DEFAULT_PREFIX.to_string()),
+            leader_ttl: config
+                .leader_ttl_secs
+                .filter(|&secs| secs > 0)
+                .map_or(DEFAULT_LEADER_TTL, Duration::from_secs),
+        })
+    }
+}
+
+/// One change, as announced to the other replicas.
+#[derive(Debug, Serialize, Deserialize)]
+struct Change {
+    origin: Uuid,
+    update: Update,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+enum Update {
+    Task(TaskEntry),
+    Metrics(GeometricMetrics),
+}
+
+/// What the backend is known to hold, so a change applied from another
+/// replica is not announced back.
+#[derive(Default)]
+struct Synced {
+    tasks: HashMap<Uuid, TaskStatus>,
+    metrics: Option<GeometricMetrics>,
+}
+
+pub struct SharedState {
+    client: redis::Client,
+    config: SharedStateConfig,
+    replica_id: Uuid,
+}
+
+impl SharedState {
+    /// Fails when the server cannot be reached.
+    pub async fn connect(config: SharedStateConfig) -> Result<Self> {
+        let client = redis::Client::open(config.url.as_str()).map_err(shared_error)?;
+        let mut connection = client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(shared_error)?;
+        redis::cmd("PING")
+            .query_async::<String>(&mut connection)
+            .await
+            .map_err(shared_error)?;
+        Ok(Self {
+            client,
+            config,
+            replica_id: Uuid::new_v4(),
+        })
+    }
+
+    pub fn replica_id(&self) -> Uuid {
+        self.replica_id
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("{}:{}", self.config.prefix, name)
+    }
+
+    /// Keep `processor` and the backend in step until the runtime shuts
+    /// down, reconnecting after failures.
+    pub fn spawn_sync(
+        self: Arc<Self>,
+        processor: Arc<SemanticTaskProcessor>,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.sync(&processor).await {
+                    error!("Shared state sync failed, reconnecting: {}", e);
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        })
+    }
+
+    async fn sync(&self, processor: &SemanticTaskProcessor) -> Result<()> {
+        let mut connection = self
+            .client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(shared_error)?;
+        let mut pubsub = self.client.get_async_pubsub().await.map_err(shared_error)?;
+        pubsub
+            .subscribe(self.key("changes"))
+            .await
+            .map_err(shared_error)?;
+        let mut events = processor.events().subscribe();
+        // subscribed first, so no change slips between catching up and
+        // listening
+        let mut synced = Synced::default();
+        self.catch_up(&mut connection, processor, &mut synced).await?;
+        let mut changes = pubsub.on_message();
+
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(StateEvent::TaskTransition { task_id, status }) => {
+                        if synced.tasks.get(&task_id) == Some(&status) {
+                            continue;
+                        }
+                        // gone after a reset; nothing to share
+                        let Ok(entry) = processor.task_entry(task_id) else {
+                            continue;
+                        };
+                        synced.tasks.insert(task_id, entry.status.clone());
+                        self.push(&mut connection, Update::Task(entry)).await?;
+                    }
+                    Ok(StateEvent::MetricsUpdated(snapshot)) => {
+                        if synced.metrics.as_ref() == Some(&snapshot.metrics) {
+                            continue;
+                        }
+                        synced.metrics = Some(snapshot.metrics.clone());
+                        self.push(&mut connection, Update::Metrics(snapshot.metrics))
+                            .await?;
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Shared state sync fell behind by {} events, resyncing", skipped);
+                        return Ok(());
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+                message = changes.next() => {
+                    let Some(message) = message else {
+                        return Err(Error::SharedState("subscription closed".to_string()));
+                    };
+                    let payload: String = message.get_payload().map_err(shared_error)?;
+                    let change: Change = serde_json::from_str(&payload)?;
+                    if change.origin != self.replica_id {
+                        self.apply(processor, &mut synced, change.update)?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply what the backend holds, then share the tasks it lacks.
+    async fn catch_up(
+        &self,
+        connection: &mut MultiplexedConnection,
+        processor: &SemanticTaskProcessor,
+        synced: &mut Synced,
+    ) -> Result<()> {
+        let tasks: HashMap<String, String> = connection
+            .hgetall(self.key("tasks"))
+            .await
+            .map_err(shared_error)?;
+        for json in tasks.values() {
+            self.apply(processor, synced, Update::Task(serde_json::from_str(json)?))?;
+        }
+        let metrics: Option<String> = connection
+            .get(self.key("metrics"))
+            .await
+            .map_err(shared_error)?;
+        match metrics {
+            Some(json) => self.apply(
+                processor,
+                synced,
+                Update::Metrics(serde_json::from_str(&json)?),
+            )?,
+            None => {
+                let metrics = processor.get_metrics();
+                synced.metrics = Some(metrics.clone());
+                self.push(connection, Update::Metrics(metrics)).await?;
+            }
+        }
+
+        for (task_id, _) in processor.list_tasks()? {
+            if !synced.tasks.contains_key(&task_id) {
+                let entry = processor.task_entry(task_id)?;
+                synced.tasks.insert(task_id, entry.status.clone());
+                self.push(connection, Update::Task(entry)).await?;
+            }
+        }
+        info!(
+            "Shared state caught up with {} tasks as replica {}",
+            synced.tasks.len(),
+            self.replica_id
+        );
+        Ok(())
+    }
+
+    fn apply(
+        &self,
+        processor: &SemanticTaskProcessor,
+        synced: &mut Synced,
+        update: Update,
+    ) -> Result<()> {
+        match update {
+            Update::Task(entry) => {
+                synced.tasks.insert(entry.task_id, entry.status.clone());
+                processor.apply_task_entry(entry)?;
+            }
+            Update::Metrics(metrics) => {
+                synced.metrics = Some(metrics.clone());
+                processor.apply_metrics(metrics)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn push(&self, connection: &mut MultiplexedConnection, update: Update) -> Result<()> {
+        match &update {
+            Update::Task(entry) => connection
+                .hset::<_, _, _, ()>(
+                    self.key("tasks"),
+                    entry.task_id.to_string(),
+                    serde_json::to_string(entry)?,
+                )
+                .await
+                .map_err(shared_error)?,
+            Update::Metrics(metrics) => connection
+                .set::<_, _, ()>(self.key("metrics"), serde_json::to_string(metrics)?)
+                .await
+                .map_err(shared_error)?,
+        }
+        let change = Change {
+            origin: self.replica_id,
+            update,
+        };
+        connection
+            .publish::<_, _, ()>(self.key("changes"), serde_json::to_string(&change)?)
+            .await
+            .map_err(shared_error)
+    }
+
+    /// Whether this replica holds the leader lease, renewed every third of
+    /// its TTL. A replica that cannot reach the backend steps down.
+    pub fn spawn_leader_election(self: Arc<Self>) -> watch::Receiver<bool> {
+        let (leader, leadership) = watch::channel(false);
+        tokio::spawn(async move {
+            let mut connection = None;
+            let mut ticker = tokio::time::interval(self.config.leader_ttl / 3);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let leading = match self.hold_lease(&mut connection).await {
+                    Ok(leading) => leading,
+                    Err(e) => {
+                        warn!("Leader election failed: {}", e);
+                        connection = None;
+                        false
+                    }
+                };
+                if leader.send_replace(leading) != leading {
+                    info!(
+                        "Replica {} {} the leader",
+                        self.replica_id,
+                        if leading { "is now" } else { "is no longer" }
+                    );
+                }
+            }
+        });
+        leadership
+    }
+
+    async fn hold_lease(&self, connection: &mut Option<MultiplexedConnection>) -> Result<bool> {
+        let connection = match connection {
+            Some(connection) => connection,
+            None => connection.insert(
+                self.client
+                    .get_multiplexed_async_connection()
+                    .await
+                    .map_err(shared_error)?,
+            ),
+        };
+        let held: i64 = redis::Script::new(LEASE_SCRIPT)
+            .key(self.key("leader"))
+            .arg(self.replica_id.to_string())
+            .arg(self.config.leader_ttl.as_millis() as u64)
+            .invoke_async(connection)
+            .await
+            .map_err(shared_error)?;
+        Ok(held == 1)
+    }
+}
+
+fn shared_error(e: redis::RedisError) -> Error {
+    Error::SharedState(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_round_trip() {
+        let change = Change {
+            origin: Uuid::new_v4(),
+            update: Update::Metrics(SemanticTaskProcessor::new().get_metrics()),
+        };
+        let json = serde_json::to_value(&change).unwrap();
+        assert_eq!(json["update"]["kind"], "metrics");
+        let parsed: Change = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.origin, change.origin);
+        assert!(matches!(parsed.update, Update::Metrics(_)));
+    }
+}
@@ -0,0 +1,393 @@
+//! Structured logs, and OpenTelemetry export of the spans around HTTP
+//! requests, task execution, LLM calls and Python scripts, with the metrics
+//! recorded alongside them.
+//!
+//! Log lines are JSON objects carrying the fields of the spans they were
+//! written in, such as `task.id` and `request_id`, so aggregators can index
+//! them. `log` records from dependencies are formatted the same way. The
+//! log filter can be replaced while the server runs (`set_log_filter`).
+//!
+//! Metrics are `tracing` events whose fields are prefixed `histogram.` or
+//! `monotonic_counter.`; the remaining fields become their attributes.
+
+use crate::config::{LoggingConfig, TelemetryConfig};
+use crate::core::error::{Error, Result};
+use axum::http::HeaderMap;
+use log::warn;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use std::env;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_opentelemetry::{MetricsLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
+
+const DEFAULT_SERVICE_NAME: &str = "mmss";
+const DEFAULT_FILTER: &str = "info";
+
+/// Swaps the log filter of the installed subscriber.
+static LOG_FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One JSON object per line.
+    Json,
+    /// Human-readable lines, for development.
+    Text,
+}
+
+impl LogFormat {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "text" => Some(Self::Text),
+            _ => None,
+        }
+    }
+}
+
+/// `never`, `minutely`, `hourly` or `daily`.
+pub fn parse_rotation(raw: &str) -> Option<Rotation> {
+    match raw.to_ascii_lowercase().as_str() {
+        "never" => Some(Rotation::NEVER),
+        "minutely" => Some(Rotation::MINUTELY),
+        "hourly" => Some(Rotation::HOURLY),
+        "daily" => Some(Rotation::DAILY),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    /// Per-module levels, in `RUST_LOG` syntax.
+    pub filter: String,
+    pub format: LogFormat,
+    /// Write here instead of stderr; rotated files get a date suffix.
+    pub file: Option<PathBuf>,
+    pub rotation: Rotation,
+    /// Rotated files kept, oldest deleted first; all when unset.
+    pub max_files: Option<usize>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            filter: DEFAULT_FILTER.to_string(),
+            format: LogFormat::Json,
+            file: None,
+            rotation: Rotation::NEVER,
+            max_files: None,
+        }
+    }
+}
+
+impl LogConfig {
+    /// `RUST_LOG` (default `info`), `MMSS_LOG_FORMAT` (`json` or `text`),
+    /// `MMSS_LOG_FILE`, `MMSS_LOG_ROTATION` and `MMSS_LOG_MAX_FILES`;
+    /// values that do not parse keep the default.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            filter: env::var("RUST_LOG").unwrap_or(defaults.filter),
+            format: env::var("MMSS_LOG_FORMAT")
+                .ok()
+                .and_then(|raw| LogFormat::parse(&raw))
+                .unwrap_or(defaults.format),
+            file: env::var_os("MMSS_LOG_FILE").map(PathBuf::from),
+            rotation: env::var("MMSS_LOG_ROTATION")
+                .ok()
+                .and_then(|raw| parse_rotation(&raw))
+                .unwrap_or(defaults.rotation),
+            max_files: env::var("MMSS_LOG_MAX_FILES")
+                .ok()
+                .and_then(|raw| raw.parse::<usize>().ok())
+                .filter(|&files| files > 0),
+        }
+    }
+
+    /// The `logging` section; unset values keep the default, as do values
+    /// that do not parse, which `Config::validate` reports.
+    pub fn from_config(config: &LoggingConfig) -> Self {
+        let defaults = Self::default();
+        Self {
+            filter: config.filter.clone().unwrap_or(defaults.filter),
+            format: config
+                .format
+                .as_deref()
+                .and_then(LogFormat::parse)
+                .unwrap_or(defaults.format),
+            file: config.file.clone(),
+            rotation: config
+                .rotation
+                .as_deref()
+                .and_then(parse_rotation)
+                .unwrap_or(defaults.rotation),
+            max_files: config.max_files.filter(|&files| files > 0),
+        }
+    }
+
+    /// The writer for log lines, and the guard that flushes it when the
+    /// writer runs on a background thread.
+    fn writer(&self) -> Result<(BoxMakeWriter, Option<WorkerGuard>)> {
+        let Some(path) = &self.file else {
+            return Ok((BoxMakeWriter::new(std::io::stderr), None));
+        };
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| {
+                Error::Config(format!("MMSS_LOG_FILE `{}` names no file", path.display()))
+            })?;
+        let directory = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .map_or_else(|| PathBuf::from("."), PathBuf::from);
+        let mut builder = RollingFileAppender::builder()
+            .rotation(self.rotation.clone())
+            .filename_prefix(file_name);
+        if let Some(files) = self.max_files {
+            builder = builder.max_log_files(files);
+        }
+        let appender = builder
+            .build(&directory)
+            .map_err(|e| Error::Config(format!("MMSS_LOG_FILE `{}`: {}", path.display(), e)))?;
+        let (writer, guard) = tracing_appender::non_blocking(appender);
+        Ok((BoxMakeWriter::new(writer), Some(guard)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OtlpConfig {
+    /// OTLP/gRPC collector, e.g. `http://otel-collector:4317`.
+    pub endpoint: String,
+    pub service_name: String,
+    /// Which spans to export, in `RUST_LOG` syntax.
+    pub filter: String,
+}
+
+impl OtlpConfig {
+    /// Enabled by `telemetry.otlp_endpoint`; the service name defaults to
+    /// `mmss` and the filter to `info`.
+    pub fn from_config(config: &TelemetryConfig) -> Option<Self> {
+        Some(Self {
+            endpoint: config.otlp_endpoint.clone()?,
+            service_name: config
+                .service_name
+                .clone()
+                .unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_string()),
+            filter: config
+                .filter
+                .clone()
+                .unwrap_or_else(|| DEFAULT_FILTER.to_string()),
+        })
+    }
+}
+
+struct OtlpExport {
+    tracer_provider: TracerProvider,
+    meter_provider: SdkMeterProvider,
+    filter: EnvFilter,
+}
+
+impl OtlpExport {
+    /// Needs a Tokio runtime, on which batches are exported.
+    fn start(config: OtlpConfig) -> Result<Self> {
+        let filter = parse_filter("MMSS_TRACE_FILTER", &config.filter)?;
+        let resource = Resource::new([KeyValue::new("service.name", config.service_name)]);
+
+        let span_exporter = SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(&config.endpoint)
+            .build()
+            .map_err(telemetry_error)?;
+        let tracer_provider = TracerProvider::builder()
+            .with_batch_exporter(span_exporter, runtime::Tokio)
+            .with_resource(resource.clone())
+            .build();
+        let metric_exporter = MetricExporter::builder()
+            .with_tonic()
+            .with_endpoint(&config.endpoint)
+            .build()
+            .map_err(telemetry_error)?;
+        let meter_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(metric_exporter, runtime::Tokio).build())
+            .with_resource(resource)
+            .build();
+
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        global::set_tracer_provider(tracer_provider.clone());
+        global::set_meter_provider(meter_provider.clone());
+        Ok(Self {
+            tracer_provider,
+            meter_provider,
+            filter,
+        })
+    }
+}
+
+/// The installed subscriber's sinks; `shutdown` flushes what they still
+/// buffer.
+pub struct Telemetry {
+    export: Option<(TracerProvider, SdkMeterProvider)>,
+    _log_guard: Option<WorkerGuard>,
+}
+
+impl Telemetry {
+    /// Install the global subscriber: logs as `log` configures them, plus
+    /// OTLP export when `otlp` is given, which needs a Tokio runtime.
+    pub fn install(log: LogConfig, otlp: Option<OtlpConfig>) -> Result<Self> {
+        let (log_filter, handle) = reload::Layer::new(parse_filter("RUST_LOG", &log.filter)?);
+        let (writer, log_guard) = log.writer()?;
+        let log_layer = match log.format {
+            LogFormat::Json => fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_current_span(true)
+                .with_span_list(true)
+                .with_writer(writer)
+                .boxed(),
+            LogFormat::Text => fmt::layer()
+                .with_ansi(log.file.is_none())
+                .with_writer(writer)
+                .boxed(),
+        };
+        let (trace_layer, metrics_layer, export) = match otlp.map(OtlpExport::start).transpose()? {
+            Some(export) => (
+                Some(
+                    tracing_opentelemetry::layer()
+                        .with_tracer(export.tracer_provider.tracer("mmss"))
+                        .with_filter(export.filter),
+                ),
+                Some(MetricsLayer::new(export.meter_provider.clone())),
+                Some((export.tracer_provider, export.meter_provider)),
+            ),
+            None => (None, None, None),
+        };
+        let subscriber = tracing_subscriber::registry()
+            .with(log_layer.with_filter(log_filter))
+            .with(trace_layer)
+            .with(metrics_layer);
+        // task spans reach the console whatever the log filter says
+        #[cfg(feature = "tokio-console")]
+        let subscriber = subscriber.with(console_subscriber::spawn());
+
+        // every level reaches the subscriber, whose filter may change later
+        tracing_log::LogTracer::init().map_err(telemetry_error)?;
+        tracing::subscriber::set_global_default(subscriber).map_err(telemetry_error)?;
+        let _ = LOG_FILTER.set(handle);
+
+        Ok(Self {
+            export,
+            _log_guard: log_guard,
+        })
+    }
+
+    /// Export what is still buffered. Blocks; call it off the runtime's
+    /// worker threads.
+    pub fn shutdown(self) {
+        if let Some((tracer_provider, meter_provider)) = &self.export {
+            if let Err(e) = tracer_provider.shutdown() {
+                warn!("Failed to flush trace export: {}", e);
+            }
+            if let Err(e) = meter_provider.shutdown() {
+                warn!("Failed to flush metrics export: {}", e);
+            }
+        }
+    }
+}
+
+/// The log filter in effect, or `None` before `Telemetry::install`.
+pub fn log_filter() -> Option<String> {
+    LOG_FILTER
+        .get()?
+        .with_current(|filter| filter.to_string())
+        .ok()
+}
+
+/// Replace the log filter, e.g. with `info,mmss::campaign=debug`, until the
+/// process exits or the filter is replaced again.
+pub fn set_log_filter(directives: &str) -> Result<()> {
+    let filter = EnvFilter::try_new(directives)
+        .map_err(|e| Error::InvalidParameter("filter".to_string(), e.to_string()))?;
+    let handle = LOG_FILTER
+        .get()
+        .ok_or_else(|| Error::Config("logging is not installed".to_string()))?;
+    handle.reload(filter).map_err(telemetry_error)
+}
+
+fn parse_filter(name: &str, directives: &str) -> Result<EnvFilter> {
+    EnvFilter::try_new(directives)
+        .map_err(|e| Error::Config(format!("{} `{}`: {}", name, directives, e)))
+}
+
+/// Continue in `span` the trace a caller propagated with `traceparent`,
+/// if any.
+pub fn set_remote_parent(span: &tracing::Span, headers: &HeaderMap) {
+    let parent =
+        global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+    span.set_parent(parent);
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+fn telemetry_error(e: impl std::fmt::Display) -> Error {
+    Error::Config(format!("telemetry: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry::trace::TraceContextExt;
+
+    #[test]
+    fn test_extracts_propagated_trace_context() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap(),
+        );
+        let context = TraceContextPropagator::new().extract(&HeaderExtractor(&headers));
+        let span_context = context.span().span_context().clone();
+        assert!(span_context.is_remote());
+        assert_eq!(
+            span_context.trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+    }
+
+    #[test]
+    fn test_parses_log_settings() {
+        assert_eq!(LogFormat::parse("JSON"), Some(LogFormat::Json));
+        assert_eq!(LogFormat::parse("text"), Some(LogFormat::Text));
+        assert_eq!(LogFormat::parse("xml"), None);
+        assert_eq!(parse_rotation("hourly"), Some(Rotation::HOURLY));
+        assert_eq!(parse_rotation("weekly"), None);
+        assert!(matches!(
+            set_log_filter("mmss=loud"),
+            Err(Error::InvalidParameter(..))
+        ));
+    }
+}
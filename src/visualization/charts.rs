@@ -0,0 +1,293 @@
+//! PNG and SVG charts of EQGFT sensitivity curves and κ scans, rendered on
+//! the server so reports need no plotting client.
+
+use crate::core::error::{Error, Result};
+use mmss_eqgft::asymmetry::PolarizationAsymmetry;
+use mmss_eqgft::sensitivity::SensitivityCurve;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use serde::Deserialize;
+
+/// Largest width or height, in pixels, of a rendered chart.
+pub const MAX_CHART_PIXELS: u32 = 4096;
+
+/// Significance conventionally required for a discovery.
+const DISCOVERY_SIGMA: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartFormat {
+    Png,
+    Svg,
+}
+
+impl ChartFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ChartFormat::Png => "image/png",
+            ChartFormat::Svg => "image/svg+xml",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct ChartSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for ChartSize {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 500,
+        }
+    }
+}
+
+/// Something that can draw itself onto any plotters backend.
+pub trait Chart {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<()>;
+}
+
+/// `chart` as an image of `size`, each side clamped to `MAX_CHART_PIXELS`.
+pub fn render<C: Chart + ?Sized>(
+    chart: &C,
+    format: ChartFormat,
+    size: ChartSize,
+) -> Result<Vec<u8>> {
+    let (width, height) = (
+        size.width.clamp(1, MAX_CHART_PIXELS),
+        size.height.clamp(1, MAX_CHART_PIXELS),
+    );
+    match format {
+        ChartFormat::Svg => {
+            let mut svg = String::new();
+            {
+                let root = SVGBackend::with_string(&mut svg, (width, height)).into_drawing_area();
+                chart.draw(&root)?;
+                root.present().map_err(render_error)?;
+            }
+            Ok(svg.into_bytes())
+        }
+        ChartFormat::Png => {
+            let mut rgb = vec![0u8; (width * height * 3) as usize];
+            {
+                let root =
+                    BitMapBackend::with_buffer(&mut rgb, (width, height)).into_drawing_area();
+                chart.draw(&root)?;
+                root.present().map_err(render_error)?;
+            }
+            let image = image::RgbImage::from_raw(width, height, rgb)
+                .ok_or_else(|| render_error("pixel buffer has the wrong size"))?;
+            let mut png = std::io::Cursor::new(Vec::new());
+            image
+                .write_to(&mut png, image::ImageOutputFormat::Png)
+                .map_err(render_error)?;
+            Ok(png.into_inner())
+        }
+    }
+}
+
+/// Expected significance as a line and the simulated measurements as
+/// points, against sample size on a log axis, with the 5σ threshold.
+impl Chart for SensitivityCurve {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<()> {
+        let events = || self.points.iter().map(|point| point.n_events.max(1) as f64);
+        let low = events().fold(f64::INFINITY, f64::min);
+        let high = events().fold(1.0, f64::max);
+        let (low, high) = if low < high {
+            (low, high)
+        } else {
+            (high / 10.0, high * 10.0)
+        };
+        let top = self
+            .points
+            .iter()
+            .flat_map(|point| [point.expected_significance, point.measured_significance])
+            .filter(|sigma| sigma.is_finite())
+            .fold(DISCOVERY_SIGMA, f64::max)
+            * 1.1;
+
+        root.fill(&WHITE).map_err(render_error)?;
+        let mut chart = ChartBuilder::on(root)
+            .caption(
+                format!("Sensitivity for κ = {}", self.kappa),
+                ("sans-serif", 20),
+            )
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(50)
+            .build_cartesian_2d((low..high).log_scale(), 0.0..top)
+            .map_err(render_error)?;
+        chart
+            .configure_mesh()
+            .x_desc("events")
+            .y_desc("significance (σ)")
+            .draw()
+            .map_err(render_error)?;
+
+        chart
+            .draw_series(LineSeries::new(
+                [(low, DISCOVERY_SIGMA), (high, DISCOVERY_SIGMA)],
+                BLACK.mix(0.4),
+            ))
+            .map_err(render_error)?
+            .label("5σ")
+            .legend(|(x, y)| PathElement::new([(x, y), (x + 20, y)], BLACK.mix(0.4)));
+        chart
+            .draw_series(LineSeries::new(
+                self.points
+                    .iter()
+                    .map(|point| (point.n_events as f64, point.expected_significance)),
+                BLUE.stroke_width(2),
+            ))
+            .map_err(render_error)?
+            .label("expected")
+            .legend(|(x, y)| PathElement::new([(x, y), (x + 20, y)], BLUE.stroke_width(2)));
+        chart
+            .draw_series(
+                self.points
+                    .iter()
+                    .filter(|point| point.measured_significance.is_finite())
+                    .map(|point| {
+                        Circle::new(
+                            (point.n_events as f64, point.measured_significance),
+                            3,
+                            RED.filled(),
+                        )
+                    }),
+            )
+            .map_err(render_error)?
+            .label("measured")
+            .legend(|(x, y)| Circle::new((x + 10, y), 3, RED.filled()));
+
+        chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .position(SeriesLabelPosition::UpperLeft)
+            .draw()
+            .map_err(render_error)?;
+        Ok(())
+    }
+}
+
+/// Measured asymmetries with their uncertainties against κ, and the
+/// prediction 𝒜 = κα as a line.
+impl Chart for [PolarizationAsymmetry] {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<()> {
+        let (left, right) = self
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), point| {
+                (min.min(point.kappa), max.max(point.kappa))
+            });
+        let (left, right) = match (left, right) {
+            (left, right) if left < right => (left, right),
+            (kappa, _) if kappa.is_finite() => (kappa - 0.5, kappa + 0.5),
+            _ => (0.0, 1.0),
+        };
+        let (bottom, top) = self
+            .iter()
+            .flat_map(|point| {
+                [
+                    point.a - point.uncertainty,
+                    point.a + point.uncertainty,
+                    point.predicted,
+                ]
+            })
+            .filter(|a| a.is_finite())
+            .fold((0.0f64, 0.0f64), |(min, max), a| (min.min(a), max.max(a)));
+        let pad = ((top - bottom) * 0.1).max(f64::EPSILON);
+
+        root.fill(&WHITE).map_err(render_error)?;
+        let mut chart = ChartBuilder::on(root)
+            .caption("Polarization asymmetry by κ", ("sans-serif", 20))
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(70)
+            .build_cartesian_2d(left..right, bottom - pad..top + pad)
+            .map_err(render_error)?;
+        chart
+            .configure_mesh()
+            .x_desc("κ")
+            .y_desc("asymmetry")
+            .draw()
+            .map_err(render_error)?;
+
+        chart
+            .draw_series(LineSeries::new(
+                self.iter().map(|point| (point.kappa, point.predicted)),
+                RED.stroke_width(2),
+            ))
+            .map_err(render_error)?
+            .label("predicted κα")
+            .legend(|(x, y)| PathElement::new([(x, y), (x + 20, y)], RED.stroke_width(2)));
+        chart
+            .draw_series(
+                self.iter()
+                    .filter(|point| point.uncertainty.is_finite())
+                    .map(|point| {
+                        ErrorBar::new_vertical(
+                            point.kappa,
+                            point.a - point.uncertainty,
+                            point.a,
+                            point.a + point.uncertainty,
+                            BLUE.filled(),
+                            6,
+                        )
+                    }),
+            )
+            .map_err(render_error)?
+            .label("measured")
+            .legend(|(x, y)| Circle::new((x + 10, y), 3, BLUE.filled()));
+
+        chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .position(SeriesLabelPosition::UpperLeft)
+            .draw()
+            .map_err(render_error)?;
+        Ok(())
+    }
+}
+
+fn render_error(e: impl std::fmt::Display) -> Error {
+    Error::TaskExecution(format!("Failed to render chart: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mmss_eqgft::config::EqgftConfig;
+    use mmss_eqgft::scan::scan_kappa_with;
+    use mmss_eqgft::sensitivity::calculate_sensitivity_curve;
+
+    #[test]
+    fn test_charts_render_in_both_formats() {
+        let config = EqgftConfig {
+            n_events: 2_000,
+            seed: Some(3),
+            ..EqgftConfig::default()
+        };
+        let curve = calculate_sensitivity_curve(&config, &[1_000, 10_000, 100_000]).unwrap();
+        let size = ChartSize {
+            width: 320,
+            height: 200,
+        };
+
+        let png = render(&curve, ChartFormat::Png, size).unwrap();
+        let decoded = image::load_from_memory(&png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (320, 200));
+
+        let scan = scan_kappa_with(&config, 0.0..=1.0, 3).unwrap();
+        let svg =
+            String::from_utf8(render(scan.as_slice(), ChartFormat::Svg, size).unwrap()).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("width=\"320\""));
+
+        // a single point still gets a usable range
+        assert!(render(&scan[..1], ChartFormat::Png, size).is_ok());
+    }
+}
@@ -0,0 +1,234 @@
+//! Binary glTF 2.0 (`.glb`) export of isosurface meshes and anchors.
+//!
+//! Each non-empty mesh becomes a node with its own translucent, double-sided
+//! material, tinted from blue (lowest iso-value) to red (highest), so nested
+//! surfaces stay visible. Anchors become empty nodes at their position,
+//! carrying the rest of the anchor in `extras`.
+
+use crate::core::error::Result;
+use crate::core::types::SemanticAnchor;
+use crate::visualization::mesh::Mesh;
+use serde_json::{json, Value};
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+pub const CONTENT_TYPE: &str = "model/gltf-binary";
+
+/// A `.glb` holding `meshes` and `anchors` in one scene.
+pub fn to_glb(meshes: &[Mesh], anchors: &[SemanticAnchor]) -> Result<Vec<u8>> {
+    let mut document = Document::default();
+    let surfaces: Vec<&Mesh> = meshes.iter().filter(|m| m.triangle_count() > 0).collect();
+    for (n, mesh) in surfaces.iter().enumerate() {
+        let tint = if surfaces.len() > 1 {
+            n as f64 / (surfaces.len() - 1) as f64
+        } else {
+            1.0
+        };
+        document.add_mesh(mesh, tint);
+    }
+    for anchor in anchors {
+        document.nodes.push(json!({
+            "name": anchor.name,
+            "translation": [anchor.position[0], anchor.position[1], anchor.position[2]],
+            "extras": {
+                "id": anchor.id,
+                "description": anchor.description,
+                "w": anchor.position[3],
+                "metadata": anchor.metadata,
+            },
+        }));
+    }
+    document.into_glb()
+}
+
+#[derive(Default)]
+struct Document {
+    buffer: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+    materials: Vec<Value>,
+    meshes: Vec<Value>,
+    nodes: Vec<Value>,
+}
+
+impl Document {
+    fn add_mesh(&mut self, mesh: &Mesh, tint: f64) {
+        let (min, max) = bounds(&mesh.vertices);
+        let position = self.accessor(
+            bytes(mesh.vertices.iter().flatten().map(|x| x.to_le_bytes())),
+            ARRAY_BUFFER,
+            json!({
+                "componentType": FLOAT,
+                "count": mesh.vertices.len(),
+                "type": "VEC3",
+                "min": min,
+                "max": max,
+            }),
+        );
+        let normal = self.accessor(
+            bytes(mesh.normals.iter().flatten().map(|x| x.to_le_bytes())),
+            ARRAY_BUFFER,
+            json!({ "componentType": FLOAT, "count": mesh.normals.len(), "type": "VEC3" }),
+        );
+        let indices = self.accessor(
+            bytes(mesh.indices.iter().map(|i| i.to_le_bytes())),
+            ELEMENT_ARRAY_BUFFER,
+            json!({ "componentType": UNSIGNED_INT, "count": mesh.indices.len(), "type": "SCALAR" }),
+        );
+
+        let material = self.materials.len();
+        self.materials.push(json!({
+            "name": format!("iso {}", mesh.iso_value),
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [tint, 0.3, 1.0 - tint, 0.5],
+                "metallicFactor": 0.0,
+                "roughnessFactor": 0.6,
+            },
+            "alphaMode": "BLEND",
+            "doubleSided": true,
+        }));
+        let index = self.meshes.len();
+        self.meshes.push(json!({
+            "name": format!("isosurface {}", mesh.iso_value),
+            "primitives": [{
+                "attributes": { "POSITION": position, "NORMAL": normal },
+                "indices": indices,
+                "material": material,
+            }],
+        }));
+        self.nodes.push(json!({
+            "name": format!("isosurface {}", mesh.iso_value),
+            "mesh": index,
+            "extras": { "iso_value": mesh.iso_value },
+        }));
+    }
+
+    /// Append `data` as its own buffer view and return the accessor index.
+    /// Every component is 4 bytes, so views stay aligned without padding.
+    fn accessor(&mut self, data: Vec<u8>, target: u32, mut accessor: Value) -> usize {
+        self.buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": self.buffer.len(),
+            "byteLength": data.len(),
+            "target": target,
+        }));
+        self.buffer.extend(data);
+        accessor["bufferView"] = json!(self.buffer_views.len() - 1);
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn into_glb(self) -> Result<Vec<u8>> {
+        let mut gltf = json!({
+            "asset": { "version": "2.0", "generator": "mmss" },
+            "scene": 0,
+            "scenes": [{ "nodes": (0..self.nodes.len()).collect::<Vec<_>>() }],
+            "nodes": self.nodes,
+        });
+        if !self.buffer.is_empty() {
+            gltf["buffers"] = json!([{ "byteLength": self.buffer.len() }]);
+            gltf["bufferViews"] = json!(self.buffer_views);
+            gltf["accessors"] = json!(self.accessors);
+            gltf["materials"] = json!(self.materials);
+            gltf["meshes"] = json!(self.meshes);
+        }
+
+        let mut json = serde_json::to_vec(&gltf)?;
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut bin = self.buffer;
+        bin.resize(bin.len().next_multiple_of(4), 0);
+
+        let mut length = 12 + 8 + json.len();
+        if !bin.is_empty() {
+            length += 8 + bin.len();
+        }
+        let mut glb = Vec::with_capacity(length);
+        for word in [GLB_MAGIC, GLB_VERSION, length as u32] {
+            glb.extend(word.to_le_bytes());
+        }
+        glb.extend((json.len() as u32).to_le_bytes());
+        glb.extend(CHUNK_JSON.to_le_bytes());
+        glb.extend(json);
+        if !bin.is_empty() {
+            glb.extend((bin.len() as u32).to_le_bytes());
+            glb.extend(CHUNK_BIN.to_le_bytes());
+            glb.extend(bin);
+        }
+        Ok(glb)
+    }
+}
+
+fn bytes(words: impl Iterator<Item = [u8; 4]>) -> Vec<u8> {
+    words.flatten().collect()
+}
+
+/// Per-axis minimum and maximum, which glTF requires on positions.
+fn bounds(vertices: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    vertices.iter().fold(
+        ([f32::INFINITY; 3], [f32::NEG_INFINITY; 3]),
+        |(min, max), v| {
+            (
+                std::array::from_fn(|i| min[i].min(v[i])),
+                std::array::from_fn(|i| max[i].max(v[i])),
+            )
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_glb_layout() {
+        let triangle = Mesh {
+            iso_value: 0.5,
+            vertices: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 2.0, 0.0]],
+            normals: vec![[0.0, 0.0, 1.0]; 3],
+            indices: vec![0, 1, 2],
+        };
+        let empty = Mesh {
+            iso_value: 9.0,
+            vertices: Vec::new(),
+            normals: Vec::new(),
+            indices: Vec::new(),
+        };
+        let anchor = SemanticAnchor {
+            id: Uuid::new_v4(),
+            name: "root".into(),
+            description: String::new(),
+            position: [1.0, 2.0, 3.0, 1.0],
+            metadata: Value::Null,
+        };
+        let glb = to_glb(&[triangle, empty], &[anchor]).unwrap();
+
+        assert_eq!(u32_at(&glb, 0), GLB_MAGIC);
+        assert_eq!(u32_at(&glb, 8) as usize, glb.len());
+        let json_length = u32_at(&glb, 12) as usize;
+        assert_eq!(u32_at(&glb, 16), CHUNK_JSON);
+        let gltf: Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+        let bin = 20 + json_length;
+        assert_eq!(u32_at(&glb, bin + 4), CHUNK_BIN);
+        // 3 positions + 3 normals of 12 bytes, and 3 indices of 4
+        assert_eq!(gltf["buffers"][0]["byteLength"], 84);
+        assert_eq!(u32_at(&glb, bin), 84);
+
+        assert_eq!(gltf["meshes"].as_array().unwrap().len(), 1);
+        assert_eq!(gltf["accessors"][0]["max"], json!([1.0, 2.0, 0.0]));
+        assert_eq!(gltf["nodes"][1]["translation"], json!([1.0, 2.0, 3.0]));
+        assert_eq!(gltf["scenes"][0]["nodes"], json!([0, 1]));
+    }
+}
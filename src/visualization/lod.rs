@@ -0,0 +1,162 @@
+//! Level-of-detail reduction of a Hopfion field before it is serialized.
+//!
+//! A level is chosen by a target point count: the smallest stride whose
+//! lattice holds at most that many points. `Strided` keeps every
+//! `stride`-th point; `Averaged` replaces each `stride³` block (smaller at
+//! the far faces) by its mean, renormalizing the quaternions.
+
+use mmss_eqgft::hopfion::HopfionSolitonField;
+use serde::{Deserialize, Serialize};
+use std::env;
+
+/// Default cap on the points of a served field, 32³.
+pub const DEFAULT_MAX_POINTS: usize = 32 * 32 * 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LodMethod {
+    #[default]
+    Strided,
+    Averaged,
+}
+
+/// `MMSS_VISUALIZATION_MAX_POINTS`, or `DEFAULT_MAX_POINTS`.
+pub fn max_points_from_env() -> usize {
+    env::var("MMSS_VISUALIZATION_MAX_POINTS")
+        .ok()
+        .and_then(|raw| raw.parse().ok())
+        .filter(|&points| points > 0)
+        .unwrap_or(DEFAULT_MAX_POINTS)
+}
+
+/// The level a field was reduced to, echoed to the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lod {
+    pub method: LodMethod,
+    pub target_points: usize,
+    /// Points per axis after reduction.
+    pub resolution: usize,
+    pub source_resolution: usize,
+    pub stride: usize,
+}
+
+impl Lod {
+    pub fn points(&self) -> usize {
+        self.resolution.pow(3)
+    }
+}
+
+/// A field at reduced resolution, laid out like `HopfionSolitonField`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldLod {
+    pub lod: Lod,
+    pub axis: Vec<f64>,
+    pub q_x: Vec<[f64; 4]>,
+    pub energy_density: Vec<f64>,
+    pub total_energy: f64,
+}
+
+/// Reduce `field` to at most `target_points` lattice points (at least one).
+pub fn downsample(
+    field: &HopfionSolitonField,
+    method: LodMethod,
+    target_points: usize,
+) -> FieldLod {
+    let n = field.resolution();
+    let stride = (1..=n)
+        .find(|stride| n.div_ceil(*stride).pow(3) <= target_points.max(1))
+        .unwrap_or(n);
+    let resolution = n.div_ceil(stride);
+    // lattice indices covered by each reduced point along one axis
+    let blocks: Vec<Vec<usize>> = (0..resolution)
+        .map(|b| match method {
+            LodMethod::Strided => vec![b * stride],
+            LodMethod::Averaged => (b * stride..((b + 1) * stride).min(n)).collect(),
+        })
+        .collect();
+
+    let mut q_x = Vec::with_capacity(resolution.pow(3));
+    let mut energy_density = Vec::with_capacity(resolution.pow(3));
+    for bi in &blocks {
+        for bj in &blocks {
+            for bk in &blocks {
+                let mut q = [0.0; 4];
+                let mut energy = 0.0;
+                let mut count = 0.0;
+                for &i in bi {
+                    for &j in bj {
+                        for &k in bk {
+                            let point = field.index(i, j, k);
+                            for (sum, value) in q.iter_mut().zip(field.q_x[point]) {
+                                *sum += value;
+                            }
+                            energy += field.energy_density[point];
+                            count += 1.0;
+                        }
+                    }
+                }
+                let norm = q.iter().map(|c| c * c).sum::<f64>().sqrt();
+                q_x.push(if count > 1.0 && norm > 0.0 {
+                    q.map(|c| c / norm)
+                } else {
+                    q
+                });
+                energy_density.push(energy / count);
+            }
+        }
+    }
+
+    FieldLod {
+        lod: Lod {
+            method,
+            target_points,
+            resolution,
+            source_resolution: n,
+            stride,
+        },
+        axis: blocks
+            .iter()
+            .map(|block| block.iter().map(|&i| field.axis[i]).sum::<f64>() / block.len() as f64)
+            .collect(),
+        q_x,
+        energy_density,
+        total_energy: field.total_energy,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mmss_eqgft::hopfion::{generate_hopfion_soliton_field, HopfionConfig};
+
+    #[test]
+    fn test_levels_respect_target_points() {
+        let field = generate_hopfion_soliton_field(&HopfionConfig {
+            resolution: 10,
+            ..HopfionConfig::default()
+        })
+        .unwrap();
+
+        let full = downsample(&field, LodMethod::Strided, 1_000);
+        assert_eq!((full.lod.stride, full.lod.resolution), (1, 10));
+        assert_eq!(full.energy_density, field.energy_density);
+
+        let strided = downsample(&field, LodMethod::Strided, 200);
+        assert_eq!((strided.lod.stride, strided.lod.resolution), (2, 5));
+        assert_eq!(strided.q_x[1], field.at(0, 0, 2));
+
+        let averaged = downsample(&field, LodMethod::Averaged, 64);
+        assert_eq!((averaged.lod.stride, averaged.lod.resolution), (3, 4));
+        assert!(averaged.lod.points() <= 64);
+        // the last block along each axis holds a single lattice plane
+        assert_eq!(averaged.axis[3], field.axis[9]);
+        let mean = (field.axis[0] + field.axis[1] + field.axis[2]) / 3.0;
+        assert!((averaged.axis[0] - mean).abs() < 1e-12);
+        for q in &averaged.q_x {
+            let norm: f64 = q.iter().map(|c| c * c).sum();
+            assert!((norm - 1.0).abs() < 1e-9);
+        }
+
+        assert_eq!(downsample(&field, LodMethod::Averaged, 0).lod.resolution, 1);
+    }
+}
@@ -0,0 +1,267 @@
+//! Isosurface extraction from a sampled scalar field.
+//!
+//! Marching cubes, with each lattice cell split into six tetrahedra around
+//! its main diagonal (marching tetrahedra). That needs no case table, has no
+//! ambiguous configurations, and neighbouring cells split their shared faces
+//! the same way, so the surface is closed wherever it does not leave the
+//! lattice. Vertices on a shared lattice edge are emitted once, and normals
+//! come from the field gradient, pointing towards lower values.
+
+use crate::visualization::protocol::FieldSummary;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Tetrahedra of a cell as corner numbers `dx + 2 dy + 4 dz`; each shares
+/// the diagonal from corner 0 to corner 7.
+const TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 7, 1, 3],
+    [0, 7, 3, 2],
+    [0, 7, 2, 6],
+    [0, 7, 6, 4],
+    [0, 7, 4, 5],
+    [0, 7, 5, 1],
+];
+
+/// Triangle mesh ready for a vertex buffer: three `indices` per triangle,
+/// counter-clockwise when seen from the side of lower values, where the
+/// normals point.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mesh {
+    pub iso_value: f64,
+    pub vertices: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+}
+
+/// The surface where the energy density of `field` equals `iso_value`.
+pub fn isosurface(field: &FieldSummary, iso_value: f64) -> Mesh {
+    Extractor::new(field, iso_value).run()
+}
+
+struct Extractor<'a> {
+    field: &'a FieldSummary,
+    iso_value: f64,
+    mesh: Mesh,
+    /// Vertex index by lattice edge, as the ordered pair of point indices.
+    edges: HashMap<(usize, usize), u32>,
+}
+
+impl<'a> Extractor<'a> {
+    fn new(field: &'a FieldSummary, iso_value: f64) -> Self {
+        Self {
+            field,
+            iso_value,
+            mesh: Mesh {
+                iso_value,
+                vertices: Vec::new(),
+                normals: Vec::new(),
+                indices: Vec::new(),
+            },
+            edges: HashMap::new(),
+        }
+    }
+
+    fn run(mut self) -> Mesh {
+        let n = self.field.resolution;
+        for i in 0..n.saturating_sub(1) {
+            for j in 0..n - 1 {
+                for k in 0..n - 1 {
+                    let corners: [usize; 8] = std::array::from_fn(|c| {
+                        self.index(i + (c & 1), j + (c >> 1 & 1), k + (c >> 2 & 1))
+                    });
+                    for tetrahedron in TETRAHEDRA {
+                        self.polygonize(tetrahedron.map(|c| corners[c]));
+                    }
+                }
+            }
+        }
+        self.mesh
+    }
+
+    fn polygonize(&mut self, points: [usize; 4]) {
+        let (inside, outside): (Vec<usize>, Vec<usize>) = points
+            .into_iter()
+            .partition(|&p| self.field.energy_density[p] >= self.iso_value);
+        let centroid = |points: &[usize]| {
+            let sum = points
+                .iter()
+                .fold([0.0; 3], |sum, &p| add(sum, self.position(p)));
+            scale(sum, 1.0 / points.len() as f64)
+        };
+        if inside.is_empty() || outside.is_empty() {
+            return;
+        }
+        let outwards = sub(centroid(&outside), centroid(&inside));
+        match (inside.as_slice(), outside.as_slice()) {
+            ([a], [b, c, d]) | ([b, c, d], [a]) => {
+                self.triangle([(*a, *b), (*a, *c), (*a, *d)], outwards);
+            }
+            ([a, b], [c, d]) => {
+                self.triangle([(*a, *c), (*a, *d), (*b, *d)], outwards);
+                self.triangle([(*a, *c), (*b, *d), (*b, *c)], outwards);
+            }
+            _ => unreachable!("a tetrahedron has four corners"),
+        }
+    }
+
+    /// Emit the triangle through three lattice edges, wound to face
+    /// `outwards`. The winding is decided on the edge midpoints rather than
+    /// the interpolated vertices, which coincide when a lattice value equals
+    /// the iso-value.
+    fn triangle(&mut self, edges: [(usize, usize); 3], outwards: [f64; 3]) {
+        let midpoint = |(p, q): (usize, usize)| scale(add(self.position(p), self.position(q)), 0.5);
+        let [ma, mb, mc] = edges.map(midpoint);
+        let [a, b, c] = edges.map(|(p, q)| self.vertex(p, q));
+        if dot(cross(sub(mb, ma), sub(mc, ma)), outwards) < 0.0 {
+            self.mesh.indices.extend([a, c, b]);
+        } else {
+            self.mesh.indices.extend([a, b, c]);
+        }
+    }
+
+    /// The vertex where the surface crosses the edge between two lattice
+    /// points, one inside and one outside.
+    fn vertex(&mut self, p: usize, q: usize) -> u32 {
+        let key = (p.min(q), p.max(q));
+        if let Some(&index) = self.edges.get(&key) {
+            return index;
+        }
+        let values = &self.field.energy_density;
+        let t = (self.iso_value - values[p]) / (values[q] - values[p]);
+        let lerp = |a: [f64; 3], b: [f64; 3]| add(a, scale(sub(b, a), t));
+
+        let position = lerp(self.position(p), self.position(q));
+        let gradient = lerp(self.gradient(p), self.gradient(q));
+        let length = dot(gradient, gradient).sqrt();
+        let normal = if length > 0.0 {
+            scale(gradient, -1.0 / length)
+        } else {
+            [0.0; 3]
+        };
+
+        let index = self.mesh.vertices.len() as u32;
+        self.mesh.vertices.push(position.map(|x| x as f32));
+        self.mesh.normals.push(normal.map(|x| x as f32));
+        self.edges.insert(key, index);
+        index
+    }
+
+    fn index(&self, i: usize, j: usize, k: usize) -> usize {
+        let n = self.field.resolution;
+        (i * n + j) * n + k
+    }
+
+    fn coordinates(&self, p: usize) -> [usize; 3] {
+        let n = self.field.resolution;
+        [p / (n * n), p / n % n, p % n]
+    }
+
+    fn position(&self, p: usize) -> [f64; 3] {
+        self.coordinates(p).map(|i| self.field.axis[i])
+    }
+
+    /// Central differences inside the lattice, one-sided on its faces.
+    fn gradient(&self, p: usize) -> [f64; 3] {
+        let n = self.field.resolution;
+        let coordinates = self.coordinates(p);
+        std::array::from_fn(|axis| {
+            let at = |offset: usize| {
+                let mut c = coordinates;
+                c[axis] = offset;
+                self.field.energy_density[self.index(c[0], c[1], c[2])]
+            };
+            let i = coordinates[axis];
+            let (lo, hi) = (i.saturating_sub(1), (i + 1).min(n - 1));
+            (at(hi) - at(lo)) / (self.field.axis[hi] - self.field.axis[lo])
+        })
+    }
+}
+
+fn add(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    std::array::from_fn(|i| a[i] + b[i])
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    std::array::from_fn(|i| a[i] - b[i])
+}
+
+fn scale(a: [f64; 3], s: f64) -> [f64; 3] {
+    a.map(|x| x * s)
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `4 - r²` on `[-2, 2]³`, whose level set at 3 is the unit sphere.
+    fn sphere_field(n: usize) -> FieldSummary {
+        let axis: Vec<f64> = (0..n)
+            .map(|i| -2.0 + 4.0 * i as f64 / (n - 1) as f64)
+            .collect();
+        let mut energy_density = Vec::with_capacity(n * n * n);
+        for x in &axis {
+            for y in &axis {
+                for z in &axis {
+                    energy_density.push(4.0 - (x * x + y * y + z * z));
+                }
+            }
+        }
+        FieldSummary {
+            resolution: n,
+            source_resolution: n,
+            stride: 1,
+            axis,
+            max_energy_density: 4.0,
+            energy_density,
+            total_energy: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_sphere_is_closed_and_faces_outwards() {
+        let mesh = isosurface(&sphere_field(21), 3.0);
+        assert!(mesh.triangle_count() > 100);
+        assert_eq!(mesh.vertices.len(), mesh.normals.len());
+        for (vertex, normal) in mesh.vertices.iter().zip(&mesh.normals) {
+            let p = vertex.map(f64::from);
+            let r = dot(p, p).sqrt();
+            assert!((r - 1.0).abs() < 0.02, "vertex at radius {}", r);
+            assert!(dot(p, normal.map(f64::from)) > 0.9 * r);
+        }
+
+        // closed and consistently wound: every directed edge appears once
+        // and its reverse once
+        let mut directed = HashMap::new();
+        for t in mesh.indices.chunks(3) {
+            for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                *directed.entry((a, b)).or_insert(0) += 1;
+            }
+            let [a, b, c] = [t[0], t[1], t[2]].map(|v| mesh.vertices[v as usize].map(f64::from));
+            assert!(dot(cross(sub(b, a), sub(c, a)), a) > -1e-9);
+        }
+        for (&(a, b), &count) in &directed {
+            assert_eq!(count, 1);
+            assert_eq!(directed.get(&(b, a)), Some(&1));
+        }
+
+        assert_eq!(isosurface(&sphere_field(21), 10.0).triangle_count(), 0);
+    }
+}
@@ -0,0 +1,227 @@
+//! PLY point clouds of 4D positions projected into 3D.
+//!
+//! Anchors contribute their `position`; field lattice points contribute
+//! their quaternion `[q0, q1, q2, q3]` as the 4D point `(q1, q2, q3, q0)`,
+//! so dropping `w` leaves the vector part.
+
+use crate::core::types::SemanticAnchor;
+use crate::visualization::lod::FieldLod;
+use plotters::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+pub const CONTENT_TYPE: &str = "application/x-ply";
+
+/// Below this distance from the pole `w = 1`, a stereographic image is
+/// unbounded and the point is left out.
+const POLE_EPSILON: f64 = 1e-9;
+
+/// How a 4D point `(x, y, z, w)` becomes a 3D one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Projection {
+    /// `(x, y, z)`.
+    #[default]
+    DropW,
+    /// The point scaled onto the unit 3-sphere, then projected from the
+    /// pole `w = 1` onto the hyperplane `w = 0`.
+    Stereographic,
+    /// The Hopf map of the point scaled onto the unit 3-sphere, a point on
+    /// the unit 2-sphere. Points on one Hopf fibre coincide.
+    Hopf,
+}
+
+impl Projection {
+    /// `None` for the origin, and for points too close to the
+    /// stereographic pole.
+    pub fn project(self, p: [f64; 4]) -> Option<[f64; 3]> {
+        match self {
+            Projection::DropW => Some([p[0], p[1], p[2]]),
+            Projection::Stereographic => {
+                let [x, y, z, w] = normalize(p)?;
+                let d = 1.0 - w;
+                (d > POLE_EPSILON).then(|| [x / d, y / d, z / d])
+            }
+            // z1 = x + iy, z2 = z + iw: (2 z1 conj(z2), |z1|² - |z2|²)
+            Projection::Hopf => {
+                let [x, y, z, w] = normalize(p)?;
+                Some([
+                    2.0 * (x * z + y * w),
+                    2.0 * (y * z - x * w),
+                    x * x + y * y - z * z - w * w,
+                ])
+            }
+        }
+    }
+}
+
+/// The value a point's colour is mapped from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorMetric {
+    /// The fourth coordinate.
+    #[default]
+    W,
+    /// Length of the 4D point.
+    Norm,
+    /// Energy density at a field point; anchors have none and are grey.
+    EnergyDensity,
+}
+
+/// A point before projection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub position: [f64; 4],
+    pub energy_density: Option<f64>,
+}
+
+impl Point {
+    fn value(&self, metric: ColorMetric) -> Option<f64> {
+        match metric {
+            ColorMetric::W => Some(self.position[3]),
+            ColorMetric::Norm => Some(norm(self.position)),
+            ColorMetric::EnergyDensity => self.energy_density,
+        }
+    }
+}
+
+pub fn anchor_points(anchors: &[SemanticAnchor]) -> impl Iterator<Item = Point> + '_ {
+    anchors.iter().map(|anchor| Point {
+        position: anchor.position,
+        energy_density: None,
+    })
+}
+
+pub fn field_points(field: &FieldLod) -> impl Iterator<Item = Point> + '_ {
+    field
+        .q_x
+        .iter()
+        .zip(&field.energy_density)
+        .map(|([q0, q1, q2, q3], &energy)| Point {
+            position: [*q1, *q2, *q3, *q0],
+            energy_density: Some(energy),
+        })
+}
+
+/// A binary little-endian PLY with `x y z` as floats, `red green blue` as
+/// bytes and the colour metric as the float `value` (NaN where absent).
+/// Points the projection cannot place are left out.
+pub fn to_ply(points: &[Point], projection: Projection, color: ColorMetric) -> Vec<u8> {
+    let projected: Vec<([f64; 3], Option<f64>)> = points
+        .iter()
+        .filter_map(|point| Some((projection.project(point.position)?, point.value(color))))
+        .collect();
+    let (min, max) = projected
+        .iter()
+        .filter_map(|(_, value)| *value)
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+            (min.min(v), max.max(v))
+        });
+
+    let mut header = String::from("ply\nformat binary_little_endian 1.0\n");
+    let _ = writeln!(header, "comment projection {:?}", projection);
+    let _ = writeln!(header, "comment color {:?} from {} to {}", color, min, max);
+    let _ = writeln!(header, "element vertex {}", projected.len());
+    header.push_str(
+        "property float x\nproperty float y\nproperty float z\n\
+         property uchar red\nproperty uchar green\nproperty uchar blue\n\
+         property float value\nend_header\n",
+    );
+
+    let mut ply = header.into_bytes();
+    ply.reserve(projected.len() * 19);
+    for (position, value) in projected {
+        for coordinate in position {
+            ply.extend((coordinate as f32).to_le_bytes());
+        }
+        let (r, g, b) = match value {
+            Some(value) => {
+                let t = if max > min {
+                    (value - min) / (max - min)
+                } else {
+                    0.5
+                };
+                HSLColor(2.0 / 3.0 * (1.0 - t), 1.0, 0.5).rgb()
+            }
+            None => (128, 128, 128),
+        };
+        ply.extend([r, g, b]);
+        ply.extend((value.unwrap_or(f64::NAN) as f32).to_le_bytes());
+    }
+    ply
+}
+
+fn norm(p: [f64; 4]) -> f64 {
+    p.iter().map(|c| c * c).sum::<f64>().sqrt()
+}
+
+fn normalize(p: [f64; 4]) -> Option<[f64; 4]> {
+    let length = norm(p);
+    (length > 0.0).then(|| p.map(|c| c / length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_projections() {
+        let p = [0.0, 0.6, 0.0, 0.8];
+        assert_eq!(Projection::DropW.project(p), Some([0.0, 0.6, 0.0]));
+        let s = Projection::Stereographic.project(p).unwrap();
+        assert!((s[1] - 3.0).abs() < 1e-12);
+        assert_eq!(
+            Projection::Stereographic.project([0.0, 0.0, 0.0, 2.0]),
+            None
+        );
+        assert_eq!(Projection::Hopf.project([0.0; 4]), None);
+
+        // the Hopf image lies on the unit sphere, and a fibre maps to a point
+        let h = Projection::Hopf.project([1.0, 2.0, 3.0, 4.0]).unwrap();
+        assert!((h.iter().map(|c| c * c).sum::<f64>() - 1.0).abs() < 1e-12);
+        let (c, s) = (0.3f64.cos(), 0.3f64.sin());
+        let rotated = [
+            c * 1.0 - s * 2.0,
+            s * 1.0 + c * 2.0,
+            c * 3.0 - s * 4.0,
+            s * 3.0 + c * 4.0,
+        ];
+        let g = Projection::Hopf.project(rotated).unwrap();
+        for (a, b) in h.iter().zip(g) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_binary_layout() {
+        let points = [
+            Point {
+                position: [1.0, 0.0, 0.0, 0.0],
+                energy_density: Some(2.0),
+            },
+            Point {
+                position: [0.0, 0.0, 0.0, 1.0],
+                energy_density: None,
+            },
+        ];
+        let ply = to_ply(
+            &points,
+            Projection::Stereographic,
+            ColorMetric::EnergyDensity,
+        );
+        let end = b"end_header\n";
+        let body = ply.windows(end.len()).position(|w| w == end).unwrap() + end.len();
+        let header = std::str::from_utf8(&ply[..body]).unwrap();
+        // the second point sits on the pole
+        assert!(header.contains("element vertex 1\n"));
+        assert_eq!(ply.len() - body, 19);
+        assert_eq!(
+            f32::from_le_bytes(ply[body..body + 4].try_into().unwrap()),
+            1.0
+        );
+        assert_eq!(
+            f32::from_le_bytes(ply[body + 15..].try_into().unwrap()),
+            2.0
+        );
+    }
+}
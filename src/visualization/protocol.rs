@@ -1,16 +1,692 @@
-//! Placeholder visualization protocol module.
+//! Packets served to visualization clients by `GET /visualization/packet`.
 
-use crate::core::types::{GeometricMetrics, SemanticAnchor};
+use crate::core::error::{Error, Result};
+use crate::core::metrics_history::MetricsSnapshot;
+use crate::core::types::{GeometricMetrics, GeometricOperator, SemanticAnchor};
+use crate::visualization::style::StyleSheet;
+use chrono::{DateTime, Utc};
+use mmss_eqgft::hopfion::HopfionSolitonField;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use uuid::Uuid;
 
+/// Everything a client renders in one frame. `sequence` increases with every
+/// packet built, so a client can discard packets that arrive out of order.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VisualizationPacket {
+    pub sequence: u64,
+    pub timestamp: DateTime<Utc>,
     pub metrics: GeometricMetrics,
     pub anchors: Vec<SemanticAnchor>,
+    /// Downsampled summary of the last generated Hopfion field, if requested
+    /// and one exists.
+    pub field: Option<FieldSummary>,
+    /// How each metric is to be coloured, set through
+    /// `PUT /visualization/styles/:metric`.
+    #[serde(default)]
+    pub style: StyleSheet,
 }
 
 impl VisualizationPacket {
-    pub fn new(metrics: GeometricMetrics, anchors: Vec<SemanticAnchor>) -> Self {
-        Self { metrics, anchors }
+    pub fn new(
+        sequence: u64,
+        metrics: GeometricMetrics,
+        anchors: Vec<SemanticAnchor>,
+        field: Option<FieldSummary>,
+    ) -> Self {
+        Self {
+            sequence,
+            timestamp: Utc::now(),
+            metrics,
+            anchors,
+            field,
+            style: StyleSheet::new(),
+        }
+    }
+
+    pub fn with_style(mut self, style: StyleSheet) -> Self {
+        self.style = style;
+        self
+    }
+}
+
+/// Wire encoding of packets and frames. MessagePack keeps JSON's field
+/// names but stores numbers in binary, which is most of a field summary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PacketEncoding {
+    #[default]
+    Json,
+    Msgpack,
+}
+
+impl PacketEncoding {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            PacketEncoding::Json => "application/json",
+            PacketEncoding::Msgpack => "application/msgpack",
+        }
+    }
+
+    /// The first supported media type listed in an `Accept` header; JSON
+    /// when there is none.
+    pub fn negotiate(accept: Option<&str>) -> Self {
+        accept
+            .into_iter()
+            .flat_map(|accept| accept.split(','))
+            .filter_map(
+                |range| match range.split(';').next().unwrap_or_default().trim() {
+                    "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                        Some(PacketEncoding::Msgpack)
+                    }
+                    "application/json" => Some(PacketEncoding::Json),
+                    _ => None,
+                },
+            )
+            .next()
+            .unwrap_or_default()
+    }
+
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>> {
+        match self {
+            PacketEncoding::Json => Ok(serde_json::to_vec(value)?),
+            PacketEncoding::Msgpack => {
+                rmp_serde::to_vec_named(value).map_err(|e| Error::Other(e.into()))
+            }
+        }
+    }
+}
+
+/// Source of packet sequence numbers, shared by everything that builds
+/// packets; the first is 1.
+#[derive(Debug, Default)]
+pub struct PacketSequence(AtomicU64);
+
+impl PacketSequence {
+    pub fn next(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+/// Distinct packet states kept for `GET /visualization/packet/diff` unless
+/// `MMSS_VISUALIZATION_PACKET_HISTORY` says otherwise.
+pub const DEFAULT_PACKET_HISTORY: usize = 256;
+
+/// Recent packet states, so a client can catch up from the sequence it last
+/// saw. Only packets whose metrics, anchors or style changed are kept, without
+/// their field; any other packet has the state of the last kept one before
+/// it.
+#[derive(Debug)]
+pub struct PacketHistory {
+    capacity: usize,
+    inner: Mutex<PacketHistoryInner>,
+}
+
+#[derive(Debug, Default)]
+struct PacketHistoryInner {
+    packets: VecDeque<VisualizationPacket>,
+    /// Highest sequence recorded, kept or not.
+    latest: u64,
+}
+
+impl PacketHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inner: Mutex::new(PacketHistoryInner::default()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(
+            env::var("MMSS_VISUALIZATION_PACKET_HISTORY")
+                .ok()
+                .and_then(|raw| raw.parse().ok())
+                .unwrap_or(DEFAULT_PACKET_HISTORY),
+        )
+    }
+
+    /// Note a packet that was built; every packet must pass through here
+    /// for `at` to be right about the ones that are not kept.
+    pub fn record(&self, packet: &VisualizationPacket) -> Result<()> {
+        let mut inner = self.lock()?;
+        inner.latest = inner.latest.max(packet.sequence);
+        // a packet built concurrently with a newer one that got here first
+        // is already superseded
+        let keep = match inner.packets.back() {
+            Some(last) => {
+                packet.sequence > last.sequence
+                    && (last.anchors != packet.anchors
+                        || last.style != packet.style
+                        || metric_values(&last.metrics) != metric_values(&packet.metrics))
+            }
+            None => true,
+        };
+        if keep {
+            if inner.packets.len() == self.capacity {
+                inner.packets.pop_front();
+            }
+            inner.packets.push_back(VisualizationPacket {
+                field: None,
+                ..packet.clone()
+            });
+        }
+        Ok(())
+    }
+
+    /// The state a client that last saw packet `sequence` has, or `None`
+    /// when that packet is older than anything kept or was never built.
+    pub fn at(&self, sequence: u64) -> Result<Option<VisualizationPacket>> {
+        let inner = self.lock()?;
+        if sequence > inner.latest {
+            return Ok(None);
+        }
+        Ok(inner
+            .packets
+            .iter()
+            .rev()
+            .find(|packet| packet.sequence <= sequence)
+            .cloned())
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, PacketHistoryInner>> {
+        self.inner
+            .lock()
+            .map_err(|e| Error::TaskExecution(format!("Failed to access packet history: {}", e)))
+    }
+}
+
+/// What a streaming client receives: a full packet, or only the metrics that
+/// changed since the previous frame when nothing else did.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VisualizationFrame {
+    Full(VisualizationPacket),
+    Delta(PacketDelta),
+    Trajectory(MetricsTrajectory),
+    Diff(PacketDiff),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PacketDelta {
+    pub sequence: u64,
+    /// The frame this delta applies to; a client that did not apply it
+    /// should reconnect for a full packet.
+    pub base_sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    /// Metrics, by name, whose value changed or that are new.
+    pub metrics: BTreeMap<String, f64>,
+    /// Custom metrics no longer present.
+    pub removed: Vec<String>,
+}
+
+/// Everything that changed between two packets, anchors and style included.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PacketDiff {
+    pub sequence: u64,
+    pub base_sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    /// Metrics, by name, whose value changed or that are new.
+    pub metrics: BTreeMap<String, f64>,
+    /// Custom metrics no longer present.
+    pub removed_metrics: Vec<String>,
+    /// Anchors that are new or differ in any field.
+    pub anchors: Vec<SemanticAnchor>,
+    pub removed_anchors: Vec<Uuid>,
+    /// The whole style sheet, when it changed.
+    pub style: Option<StyleSheet>,
+}
+
+impl PacketDiff {
+    pub fn between(base: &VisualizationPacket, packet: &VisualizationPacket) -> Self {
+        let (metrics, removed_metrics) = changed_metrics(&base.metrics, &packet.metrics);
+        let before: BTreeMap<Uuid, &SemanticAnchor> = base
+            .anchors
+            .iter()
+            .map(|anchor| (anchor.id, anchor))
+            .collect();
+        let after: BTreeMap<Uuid, &SemanticAnchor> = packet
+            .anchors
+            .iter()
+            .map(|anchor| (anchor.id, anchor))
+            .collect();
+        Self {
+            sequence: packet.sequence,
+            base_sequence: base.sequence,
+            timestamp: packet.timestamp,
+            metrics,
+            removed_metrics,
+            anchors: packet
+                .anchors
+                .iter()
+                .filter(|anchor| before.get(&anchor.id) != Some(anchor))
+                .cloned()
+                .collect(),
+            removed_anchors: before
+                .keys()
+                .filter(|id| !after.contains_key(*id))
+                .copied()
+                .collect(),
+            style: (base.style != packet.style).then(|| packet.style.clone()),
+        }
+    }
+}
+
+/// Recent metrics as columns for plotting: one entry per snapshot in
+/// `times`, and for every metric a series of the same length, `None` where
+/// a custom metric was absent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsTrajectory {
+    pub sequence: u64,
+    /// Snapshot times, milliseconds since the epoch.
+    pub times: Vec<i64>,
+    pub series: BTreeMap<String, Vec<Option<f64>>>,
+    /// The snapshots that a task produced, with its operator.
+    pub annotations: Vec<TrajectoryAnnotation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrajectoryAnnotation {
+    /// Position in `times`.
+    pub index: usize,
+    pub task_id: Uuid,
+    pub operator: Option<GeometricOperator>,
+}
+
+impl MetricsTrajectory {
+    pub fn from_snapshots(sequence: u64, snapshots: &[MetricsSnapshot]) -> Self {
+        let mut series: BTreeMap<String, Vec<Option<f64>>> = BTreeMap::new();
+        let mut annotations = Vec::new();
+        for (index, snapshot) in snapshots.iter().enumerate() {
+            for (name, value) in metric_values(&snapshot.metrics) {
+                series
+                    .entry(name)
+                    .or_insert_with(|| vec![None; snapshots.len()])[index] = Some(value);
+            }
+            if let Some(task_id) = snapshot.task_id {
+                annotations.push(TrajectoryAnnotation {
+                    index,
+                    task_id,
+                    operator: snapshot.operator,
+                });
+            }
+        }
+        Self {
+            sequence,
+            times: snapshots.iter().map(|s| s.timestamp).collect(),
+            series,
+            annotations,
+        }
+    }
+}
+
+/// Turns a client's successive packets into frames, remembering the last
+/// packet sent.
+#[derive(Debug, Default)]
+pub struct FrameEncoder {
+    last: Option<VisualizationPacket>,
+}
+
+impl FrameEncoder {
+    /// The frame that brings the client to `packet`, or `None` when nothing
+    /// changed and there is nothing to send.
+    pub fn encode(&mut self, packet: VisualizationPacket) -> Option<VisualizationFrame> {
+        let frame = match &self.last {
+            Some(last)
+                if last.anchors == packet.anchors
+                    && last.field == packet.field
+                    && last.style == packet.style =>
+            {
+                let (metrics, removed) = changed_metrics(&last.metrics, &packet.metrics);
+                if metrics.is_empty() && removed.is_empty() {
+                    return None;
+                }
+                VisualizationFrame::Delta(PacketDelta {
+                    sequence: packet.sequence,
+                    base_sequence: last.sequence,
+                    timestamp: packet.timestamp,
+                    metrics,
+                    removed,
+                })
+            }
+            _ => VisualizationFrame::Full(packet.clone()),
+        };
+        self.last = Some(packet);
+        Some(frame)
+    }
+}
+
+/// Built-in and custom metrics by name.
+pub fn metric_values(metrics: &GeometricMetrics) -> BTreeMap<String, f64> {
+    let builtin = [
+        ("v_geometric", metrics.v_geometric),
+        ("s_geometric", metrics.s_geometric),
+        ("q_oscillator", metrics.q_oscillator),
+        ("quaternion_coherence", metrics.quaternion_coherence),
+        ("emergent_electron_mass", metrics.emergent_electron_mass),
+        ("fine_structure_constant", metrics.fine_structure_constant),
+        ("zitterbewegung_entropy", metrics.zitterbewegung_entropy),
+        ("topological_winding", metrics.topological_winding),
+    ];
+    builtin
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .chain(
+            metrics
+                .custom_metrics
+                .iter()
+                .map(|(name, value)| (name.clone(), *value)),
+        )
+        .collect()
+}
+
+/// Metrics whose value changed or that are new, and the names of those no
+/// longer present.
+fn changed_metrics(
+    before: &GeometricMetrics,
+    after: &GeometricMetrics,
+) -> (BTreeMap<String, f64>, Vec<String>) {
+    let before = metric_values(before);
+    let after = metric_values(after);
+    let removed = before
+        .keys()
+        .filter(|name| !after.contains_key(*name))
+        .cloned()
+        .collect();
+    let changed = after
+        .into_iter()
+        .filter(|(name, value)| before.get(name) != Some(value))
+        .collect();
+    (changed, removed)
+}
+
+/// Energy density of a Hopfion field sampled on every `stride`-th lattice
+/// point along each axis.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldSummary {
+    /// Points per axis after downsampling.
+    pub resolution: usize,
+    /// Points per axis of the generated field.
+    pub source_resolution: usize,
+    pub stride: usize,
+    /// Sampled coordinates, shared by all three axes.
+    pub axis: Vec<f64>,
+    /// `resolution³` values, `x` slowest and `z` fastest, as in the field.
+    pub energy_density: Vec<f64>,
+    pub max_energy_density: f64,
+    pub total_energy: f64,
+}
+
+impl FieldSummary {
+    /// Sample `field` down to at most `max_resolution` points per axis.
+    pub fn downsample(field: &HopfionSolitonField, max_resolution: usize) -> Self {
+        let source_resolution = field.resolution();
+        let stride = source_resolution.div_ceil(max_resolution.max(1)).max(1);
+        let indices: Vec<usize> = (0..source_resolution).step_by(stride).collect();
+
+        let mut energy_density = Vec::with_capacity(indices.len().pow(3));
+        for &i in &indices {
+            for &j in &indices {
+                for &k in &indices {
+                    energy_density.push(field.energy_density[field.index(i, j, k)]);
+                }
+            }
+        }
+        Self {
+            resolution: indices.len(),
+            source_resolution,
+            stride,
+            axis: indices.iter().map(|&i| field.axis[i]).collect(),
+            max_energy_density: energy_density.iter().copied().fold(0.0, f64::max),
+            energy_density,
+            total_energy: field.total_energy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::visualization::style::MetricStyle;
+    use mmss_eqgft::hopfion::{generate_hopfion_soliton_field, HopfionConfig};
+
+    #[test]
+    fn test_downsample_and_sequence() {
+        let field = generate_hopfion_soliton_field(&HopfionConfig {
+            resolution: 10,
+            ..HopfionConfig::default()
+        })
+        .unwrap();
+        let summary = FieldSummary::downsample(&field, 4);
+        assert_eq!((summary.stride, summary.resolution), (3, 4));
+        assert_eq!(summary.energy_density.len(), 64);
+        assert_eq!(
+            summary.axis,
+            vec![field.axis[0], field.axis[3], field.axis[6], field.axis[9]]
+        );
+        assert_eq!(
+            summary.energy_density[1],
+            field.energy_density[field.index(0, 0, 3)]
+        );
+
+        let full = FieldSummary::downsample(&field, 64);
+        assert_eq!((full.stride, full.resolution), (1, 10));
+        assert_eq!(full.energy_density, field.energy_density);
+
+        let sequence = PacketSequence::default();
+        assert_eq!((sequence.next(), sequence.next()), (1, 2));
+    }
+
+    #[test]
+    fn test_msgpack_encoding_round_trips_and_shrinks_fields() {
+        let field = generate_hopfion_soliton_field(&HopfionConfig {
+            resolution: 12,
+            ..HopfionConfig::default()
+        })
+        .unwrap();
+        let metrics = GeometricMetrics {
+            v_geometric: 1.0,
+            s_geometric: 0.5,
+            q_oscillator: 2.0,
+            quaternion_coherence: 0.9,
+            emergent_electron_mass: 0.0,
+            fine_structure_constant: 0.0073,
+            zitterbewegung_entropy: 0.0,
+            topological_winding: 1.0,
+            custom_metrics: Default::default(),
+        };
+        let packet = VisualizationPacket::new(
+            1,
+            metrics,
+            Vec::new(),
+            Some(FieldSummary::downsample(&field, 12)),
+        );
+        let json = PacketEncoding::Json.encode(&packet).unwrap();
+        let msgpack = PacketEncoding::Msgpack.encode(&packet).unwrap();
+        assert!(msgpack.len() < json.len() / 2);
+        let decoded: VisualizationPacket = rmp_serde::from_slice(&msgpack).unwrap();
+        assert_eq!(decoded.field, packet.field);
+
+        assert_eq!(PacketEncoding::negotiate(None), PacketEncoding::Json);
+        assert_eq!(
+            PacketEncoding::negotiate(Some("text/html, application/x-msgpack;q=0.9, */*")),
+            PacketEncoding::Msgpack
+        );
+        assert_eq!(
+            PacketEncoding::negotiate(Some("application/json, application/msgpack")),
+            PacketEncoding::Json
+        );
+    }
+
+    #[test]
+    fn test_trajectory_aligns_series() {
+        let mut metrics = GeometricMetrics {
+            v_geometric: 1.0,
+            s_geometric: 0.5,
+            q_oscillator: 2.0,
+            quaternion_coherence: 0.9,
+            emergent_electron_mass: 0.0,
+            fine_structure_constant: 0.0073,
+            zitterbewegung_entropy: 0.0,
+            topological_winding: 1.0,
+            custom_metrics: Default::default(),
+        };
+        let first = MetricsSnapshot::now(None, metrics.clone());
+        metrics.custom_metrics.insert("drift".to_string(), 0.2);
+        let task_id = Uuid::new_v4();
+        let second = MetricsSnapshot::now(Some(task_id), metrics)
+            .with_operator(GeometricOperator::QuaternionRotation);
+
+        let trajectory = MetricsTrajectory::from_snapshots(7, &[first, second]);
+        assert_eq!(trajectory.times.len(), 2);
+        assert_eq!(trajectory.series["v_geometric"], [Some(1.0), Some(1.0)]);
+        assert_eq!(trajectory.series["drift"], [None, Some(0.2)]);
+        assert_eq!(
+            trajectory.annotations,
+            [TrajectoryAnnotation {
+                index: 1,
+                task_id,
+                operator: Some(GeometricOperator::QuaternionRotation),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_history_diffs_against_unkept_sequences() {
+        let mut metrics = GeometricMetrics {
+            v_geometric: 1.0,
+            s_geometric: 0.5,
+            q_oscillator: 2.0,
+            quaternion_coherence: 0.9,
+            emergent_electron_mass: 0.0,
+            fine_structure_constant: 0.0073,
+            zitterbewegung_entropy: 0.0,
+            topological_winding: 1.0,
+            custom_metrics: [("drift".to_string(), 0.1)].into(),
+        };
+        let anchor = SemanticAnchor {
+            id: Uuid::new_v4(),
+            name: "root".into(),
+            description: String::new(),
+            position: [0.0, 0.0, 0.0, 1.0],
+            metadata: serde_json::Value::Null,
+        };
+        let history = PacketHistory::new(2);
+        let packets = [
+            VisualizationPacket::new(1, metrics.clone(), vec![anchor.clone()], None),
+            VisualizationPacket::new(2, metrics.clone(), vec![anchor.clone()], None),
+        ];
+        packets.iter().for_each(|p| history.record(p).unwrap());
+        assert_eq!(history.at(2).unwrap().unwrap().sequence, 1);
+
+        metrics.v_geometric = 2.0;
+        metrics.custom_metrics.clear();
+        let mut moved = anchor.clone();
+        moved.position[0] = 1.0;
+        let added = SemanticAnchor {
+            id: Uuid::new_v4(),
+            ..anchor.clone()
+        };
+        let current = VisualizationPacket::new(3, metrics.clone(), vec![moved.clone()], None);
+        history.record(&current).unwrap();
+        let diff = PacketDiff::between(&history.at(2).unwrap().unwrap(), &current);
+        assert_eq!(diff.base_sequence, 1);
+        assert_eq!(
+            diff.metrics,
+            BTreeMap::from([("v_geometric".to_string(), 2.0)])
+        );
+        assert_eq!(diff.removed_metrics, ["drift"]);
+        assert_eq!(diff.anchors, [moved.clone()]);
+        assert!(diff.removed_anchors.is_empty());
+
+        history
+            .record(&VisualizationPacket::new(
+                4,
+                metrics,
+                vec![added.clone()],
+                None,
+            ))
+            .unwrap();
+        // packet 1 was evicted, and packet 5 not built yet
+        assert!(history.at(2).unwrap().is_none());
+        assert!(history.at(5).unwrap().is_none());
+        let diff = PacketDiff::between(&current, &history.at(4).unwrap().unwrap());
+        assert_eq!(diff.anchors, [added]);
+        assert_eq!(diff.removed_anchors, [moved.id]);
+    }
+
+    #[test]
+    fn test_encoder_sends_deltas_for_metric_changes() {
+        let metrics = GeometricMetrics {
+            v_geometric: 1.0,
+            s_geometric: 0.5,
+            q_oscillator: 2.0,
+            quaternion_coherence: 0.9,
+            emergent_electron_mass: 0.0,
+            fine_structure_constant: 0.0073,
+            zitterbewegung_entropy: 0.0,
+            topological_winding: 1.0,
+            custom_metrics: [("drift".to_string(), 0.1)].into(),
+        };
+        let mut encoder = FrameEncoder::default();
+        let first = VisualizationPacket::new(1, metrics.clone(), Vec::new(), None);
+        assert!(matches!(
+            encoder.encode(first.clone()),
+            Some(VisualizationFrame::Full(_))
+        ));
+        assert!(encoder
+            .encode(VisualizationPacket::new(
+                2,
+                metrics.clone(),
+                Vec::new(),
+                None
+            ))
+            .is_none());
+
+        let mut changed = metrics.clone();
+        changed.v_geometric = 1.5;
+        changed.custom_metrics.clear();
+        let Some(VisualizationFrame::Delta(delta)) = encoder.encode(VisualizationPacket::new(
+            3,
+            changed.clone(),
+            Vec::new(),
+            None,
+        )) else {
+            panic!("expected a delta frame");
+        };
+        // packet 2 was never sent, so the client still holds packet 1
+        assert_eq!(delta.base_sequence, 1);
+        assert_eq!(
+            delta.metrics,
+            BTreeMap::from([("v_geometric".to_string(), 1.5)])
+        );
+        assert_eq!(delta.removed, ["drift"]);
+
+        let anchor = SemanticAnchor {
+            id: uuid::Uuid::new_v4(),
+            name: "root".into(),
+            description: String::new(),
+            position: [0.0, 0.0, 0.0, 1.0],
+            metadata: serde_json::Value::Null,
+        };
+        assert!(matches!(
+            encoder.encode(VisualizationPacket::new(
+                4,
+                changed.clone(),
+                vec![anchor.clone()],
+                None
+            )),
+            Some(VisualizationFrame::Full(_))
+        ));
+
+        let style = [("v_geometric".to_string(), MetricStyle::default())].into();
+        let styled = VisualizationPacket::new(5, changed, vec![anchor], None).with_style(style);
+        assert!(matches!(
+            encoder.encode(styled),
+            Some(VisualizationFrame::Full(_))
+        ));
     }
 }
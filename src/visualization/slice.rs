@@ -0,0 +1,167 @@
+//! Axis-aligned 2D slices of a Hopfion field, and their PNG heatmaps.
+
+use crate::core::error::{Error, Result};
+use mmss_eqgft::hopfion::HopfionSolitonField;
+use plotters::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Largest side, in pixels, of a rendered heatmap.
+pub const MAX_CELL_PIXELS: u32 = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SliceAxis {
+    X,
+    Y,
+    Z,
+}
+
+/// The value sampled at each lattice point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldComponent {
+    Q0,
+    Q1,
+    Q2,
+    Q3,
+    #[default]
+    EnergyDensity,
+}
+
+/// Values on the plane `axis = axis coordinate[index]`. The two remaining
+/// axes, in x, y, z order, are the columns (`u`) and rows (`v`); `values`
+/// is row-major.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Slice {
+    pub axis: SliceAxis,
+    pub index: usize,
+    pub coordinate: f64,
+    pub component: FieldComponent,
+    /// Coordinates shared by columns and rows.
+    pub grid: Vec<f64>,
+    pub values: Vec<f64>,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Slice {
+    pub fn extract(
+        field: &HopfionSolitonField,
+        axis: SliceAxis,
+        index: usize,
+        component: FieldComponent,
+    ) -> Result<Self> {
+        let n = field.resolution();
+        if index >= n {
+            return Err(Error::InvalidParameter(
+                "index".to_string(),
+                format!("must be below the field resolution {}", n),
+            ));
+        }
+        let mut values = Vec::with_capacity(n * n);
+        for v in 0..n {
+            for u in 0..n {
+                let [i, j, k] = match axis {
+                    SliceAxis::X => [index, u, v],
+                    SliceAxis::Y => [u, index, v],
+                    SliceAxis::Z => [u, v, index],
+                };
+                let point = field.index(i, j, k);
+                values.push(match component {
+                    FieldComponent::Q0 => field.q_x[point][0],
+                    FieldComponent::Q1 => field.q_x[point][1],
+                    FieldComponent::Q2 => field.q_x[point][2],
+                    FieldComponent::Q3 => field.q_x[point][3],
+                    FieldComponent::EnergyDensity => field.energy_density[point],
+                });
+            }
+        }
+        let (min, max) = values
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &v| {
+                (min.min(v), max.max(v))
+            });
+        Ok(Self {
+            axis,
+            index,
+            coordinate: field.axis[index],
+            component,
+            grid: field.axis.clone(),
+            values,
+            min,
+            max,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.grid.len()
+    }
+
+    /// The slice as a PNG, `cell` pixels per lattice point, coloured from
+    /// blue at `min` to red at `max`, with `v` increasing upwards.
+    pub fn to_png(&self, cell: u32) -> Result<Vec<u8>> {
+        let cell = cell.clamp(1, MAX_CELL_PIXELS);
+        let n = self.size() as u32;
+        let side = n * cell;
+        let mut rgb = vec![0u8; (side * side * 3) as usize];
+        {
+            let root = BitMapBackend::with_buffer(&mut rgb, (side, side)).into_drawing_area();
+            let range = self.max - self.min;
+            for (point, &value) in self.values.iter().enumerate() {
+                let (u, v) = (point as u32 % n, point as u32 / n);
+                let t = if range > 0.0 {
+                    (value - self.min) / range
+                } else {
+                    0.5
+                };
+                let (x, y) = ((u * cell) as i32, ((n - 1 - v) * cell) as i32);
+                let color = HSLColor(2.0 / 3.0 * (1.0 - t), 1.0, 0.5);
+                root.draw(&Rectangle::new(
+                    [(x, y), (x + cell as i32, y + cell as i32)],
+                    color.filled(),
+                ))
+                .map_err(render_error)?;
+            }
+            root.present().map_err(render_error)?;
+        }
+
+        let image = image::RgbImage::from_raw(side, side, rgb)
+            .ok_or_else(|| render_error("pixel buffer has the wrong size"))?;
+        let mut png = std::io::Cursor::new(Vec::new());
+        image
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .map_err(render_error)?;
+        Ok(png.into_inner())
+    }
+}
+
+fn render_error(e: impl std::fmt::Display) -> Error {
+    Error::TaskExecution(format!("Failed to render slice: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mmss_eqgft::hopfion::{generate_hopfion_soliton_field, HopfionConfig};
+
+    #[test]
+    fn test_slice_orientation_and_png() {
+        let field = generate_hopfion_soliton_field(&HopfionConfig {
+            resolution: 8,
+            ..HopfionConfig::default()
+        })
+        .unwrap();
+        let slice = Slice::extract(&field, SliceAxis::Y, 3, FieldComponent::Q2).unwrap();
+        assert_eq!(slice.values.len(), 64);
+        // row v = 5 (z), column u = 1 (x)
+        assert_eq!(slice.values[5 * 8 + 1], field.at(1, 3, 5)[2]);
+        assert_eq!(slice.coordinate, field.axis[3]);
+        assert!(slice.min <= slice.max);
+        assert!(Slice::extract(&field, SliceAxis::Z, 8, FieldComponent::Q0).is_err());
+
+        let png = slice.to_png(4).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        let decoded = image::load_from_memory(&png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (32, 32));
+    }
+}
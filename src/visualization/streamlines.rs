@@ -0,0 +1,240 @@
+//! The director field of a Hopfion and streamlines traced through it.
+//!
+//! The director at a point is `e_z` rotated by the unit quaternion there,
+//! `n = Q e_z Q̄`; it is the Hopf map of `Q` and winds once around every
+//! closed preimage curve, which is what streamlines make visible.
+
+use mmss_eqgft::hopfion::HopfionSolitonField;
+use serde::{Deserialize, Serialize};
+
+/// Speed below which a streamline is considered stalled.
+const MIN_SPEED: f64 = 1e-9;
+
+/// `e_z` rotated by `q = [q0, q1, q2, q3]`, assumed unit.
+pub fn director([q0, q1, q2, q3]: [f64; 4]) -> [f64; 3] {
+    [
+        2.0 * (q1 * q3 + q0 * q2),
+        2.0 * (q2 * q3 - q0 * q1),
+        q0 * q0 - q1 * q1 - q2 * q2 + q3 * q3,
+    ]
+}
+
+/// A vector per lattice point, laid out like `HopfionSolitonField`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorField {
+    pub resolution: usize,
+    /// Coordinates shared by all three axes, evenly spaced.
+    pub axis: Vec<f64>,
+    pub vectors: Vec<[f64; 3]>,
+}
+
+impl VectorField {
+    /// The director of every quaternion in `q_x`, sampled on `axis`.
+    pub fn director(axis: Vec<f64>, q_x: &[[f64; 4]]) -> Self {
+        Self {
+            resolution: axis.len(),
+            axis,
+            vectors: q_x.iter().copied().map(director).collect(),
+        }
+    }
+
+    pub fn from_field(field: &HopfionSolitonField) -> Self {
+        Self::director(field.axis.clone(), &field.q_x)
+    }
+
+    /// Trilinear interpolation at `p`, `None` outside the lattice.
+    pub fn sample(&self, p: [f64; 3]) -> Option<[f64; 3]> {
+        let n = self.resolution;
+        if n < 2 {
+            return None;
+        }
+        let (origin, spacing) = (self.axis[0], self.axis[1] - self.axis[0]);
+        let x: [f64; 3] = p.map(|c| (c - origin) / spacing);
+        if x.iter().any(|x| !(0.0..=(n - 1) as f64).contains(x)) {
+            return None;
+        }
+        let cell = x.map(|x| (x.floor() as usize).min(n - 2));
+        let t: [f64; 3] = std::array::from_fn(|axis| x[axis] - cell[axis] as f64);
+
+        let mut value = [0.0; 3];
+        for corner in 0..8 {
+            let offset = [corner & 1, corner >> 1 & 1, corner >> 2 & 1];
+            let weight: f64 = (0..3)
+                .map(|axis| {
+                    if offset[axis] == 1 {
+                        t[axis]
+                    } else {
+                        1.0 - t[axis]
+                    }
+                })
+                .product();
+            let [i, j, k] = std::array::from_fn(|axis| cell[axis] + offset[axis]);
+            let vector = self.vectors[(i * n + j) * n + k];
+            for (sum, component) in value.iter_mut().zip(vector) {
+                *sum += weight * component;
+            }
+        }
+        Some(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct StreamlineConfig {
+    /// Seeds per axis, at the centres of an even grid over the lattice.
+    pub seeds: usize,
+    /// Integration step; half the lattice spacing when absent.
+    pub step: Option<f64>,
+    /// Steps in each direction from a seed.
+    pub max_steps: usize,
+}
+
+impl Default for StreamlineConfig {
+    fn default() -> Self {
+        Self {
+            seeds: 4,
+            step: None,
+            max_steps: 200,
+        }
+    }
+}
+
+/// One traced line, running backwards from the seed and then forwards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Streamline {
+    pub seed: [f64; 3],
+    /// Index of the seed in `points`.
+    pub seed_index: usize,
+    pub points: Vec<[f32; 3]>,
+}
+
+/// Streamlines of the normalized `field` from every seed of `config`. A line
+/// ends where it leaves the lattice, stalls, or after `max_steps`.
+pub fn streamlines(field: &VectorField, config: &StreamlineConfig) -> Vec<Streamline> {
+    if field.resolution < 2 || config.seeds == 0 {
+        return Vec::new();
+    }
+    let (low, high) = (field.axis[0], field.axis[field.resolution - 1]);
+    let step = config.step.unwrap_or((field.axis[1] - field.axis[0]) / 2.0);
+    let seed_axis: Vec<f64> = (0..config.seeds)
+        .map(|s| low + (high - low) * (s as f64 + 0.5) / config.seeds as f64)
+        .collect();
+
+    let mut lines = Vec::with_capacity(config.seeds.pow(3));
+    for &x in &seed_axis {
+        for &y in &seed_axis {
+            for &z in &seed_axis {
+                let seed = [x, y, z];
+                let mut backward = trace(field, seed, -step, config.max_steps);
+                let forward = trace(field, seed, step, config.max_steps);
+                backward.reverse();
+                let seed_index = backward.len();
+                backward.push(seed);
+                backward.extend(forward);
+                lines.push(Streamline {
+                    seed,
+                    seed_index,
+                    points: backward.iter().map(|p| p.map(|c| c as f32)).collect(),
+                });
+            }
+        }
+    }
+    lines
+}
+
+/// Points after `start`, following the unit direction field.
+fn trace(field: &VectorField, start: [f64; 3], step: f64, max_steps: usize) -> Vec<[f64; 3]> {
+    let mut points = Vec::new();
+    let mut p = start;
+    while points.len() < max_steps {
+        match rk4_step(field, p, step) {
+            Some(next) => {
+                points.push(next);
+                p = next;
+            }
+            None => break,
+        }
+    }
+    points
+}
+
+/// One RK4 step from `p`, `None` when any stage leaves the lattice or stalls.
+fn rk4_step(field: &VectorField, p: [f64; 3], h: f64) -> Option<[f64; 3]> {
+    let direction = |p: [f64; 3]| {
+        let v = field.sample(p)?;
+        let speed = v.iter().map(|c| c * c).sum::<f64>().sqrt();
+        (speed > MIN_SPEED).then(|| v.map(|c| c / speed))
+    };
+    let offset =
+        |v: [f64; 3], h: f64| -> [f64; 3] { std::array::from_fn(|axis| p[axis] + h * v[axis]) };
+    let k1 = direction(p)?;
+    let k2 = direction(offset(k1, h / 2.0))?;
+    let k3 = direction(offset(k2, h / 2.0))?;
+    let k4 = direction(offset(k3, h))?;
+    let slope =
+        std::array::from_fn(|axis| (k1[axis] + 2.0 * k2[axis] + 2.0 * k3[axis] + k4[axis]) / 6.0);
+    let next = offset(slope, h);
+    field.sample(next).map(|_| next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The rotation field `(-y, x, 0)` on `[-2, 2]³`, whose streamlines are
+    /// circles around the z axis.
+    fn swirl(n: usize) -> VectorField {
+        let axis: Vec<f64> = (0..n)
+            .map(|i| -2.0 + 4.0 * i as f64 / (n - 1) as f64)
+            .collect();
+        let mut vectors = Vec::new();
+        for &x in &axis {
+            for &y in &axis {
+                for _ in &axis {
+                    vectors.push([-y, x, 0.0]);
+                }
+            }
+        }
+        VectorField {
+            resolution: n,
+            axis,
+            vectors,
+        }
+    }
+
+    #[test]
+    fn test_director_rotates_e_z() {
+        assert_eq!(director([1.0, 0.0, 0.0, 0.0]), [0.0, 0.0, 1.0]);
+        // half turn about x
+        assert_eq!(director([0.0, 1.0, 0.0, 0.0]), [0.0, 0.0, -1.0]);
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        // quarter turn about y takes e_z to e_x
+        let n = director([h, 0.0, h, 0.0]);
+        assert!((n[0] - 1.0).abs() < 1e-12 && n[1].abs() < 1e-12 && n[2].abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_streamlines_follow_circles() {
+        let field = swirl(17);
+        assert_eq!(field.sample([0.5, 1.0, 0.0]), Some([-1.0, 0.5, 0.0]));
+        assert_eq!(field.sample([2.5, 0.0, 0.0]), None);
+
+        let config = StreamlineConfig {
+            seeds: 2,
+            step: Some(0.05),
+            max_steps: 100,
+        };
+        let lines = streamlines(&field, &config);
+        assert_eq!(lines.len(), 8);
+        for line in &lines {
+            let radius = line.seed[0].hypot(line.seed[1]);
+            assert_eq!(line.points.len(), 201);
+            assert_eq!(line.points[line.seed_index], line.seed.map(|c| c as f32));
+            for p in &line.points {
+                let r = f64::from(p[0]).hypot(f64::from(p[1]));
+                assert!((r - radius).abs() < 1e-3, "drifted to radius {}", r);
+                assert_eq!(f64::from(p[2]), line.seed[2]);
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Per-metric colour maps and opacity transfer functions, set on the server
+//! and sent with every packet so all viewers render a metric the same way.
+
+use crate::core::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// Styles by metric name; `energy_density` styles the Hopfion field.
+pub type StyleSheet = BTreeMap<String, MetricStyle>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMap {
+    #[default]
+    Viridis,
+    Plasma,
+    Inferno,
+    Coolwarm,
+    Grayscale,
+    /// Hue from blue to red, as the server's own heatmaps.
+    Rainbow,
+}
+
+impl ColorMap {
+    /// The colour at `t` in `[0, 1]`, clamped.
+    pub fn sample(self, t: f64) -> [u8; 3] {
+        let stops: &[[u8; 3]] = match self {
+            ColorMap::Viridis => &[
+                [68, 1, 84],
+                [59, 82, 139],
+                [33, 145, 140],
+                [94, 201, 98],
+                [253, 231, 37],
+            ],
+            ColorMap::Plasma => &[
+                [13, 8, 135],
+                [126, 3, 168],
+                [204, 71, 120],
+                [248, 149, 64],
+                [240, 249, 33],
+            ],
+            ColorMap::Inferno => &[
+                [0, 0, 4],
+                [87, 16, 110],
+                [188, 55, 84],
+                [249, 142, 9],
+                [252, 255, 164],
+            ],
+            ColorMap::Coolwarm => &[[59, 76, 192], [221, 221, 221], [180, 4, 38]],
+            ColorMap::Grayscale => &[[0, 0, 0], [255, 255, 255]],
+            ColorMap::Rainbow => &[
+                [0, 0, 255],
+                [0, 255, 255],
+                [0, 255, 0],
+                [255, 255, 0],
+                [255, 0, 0],
+            ],
+        };
+        let x = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+        let i = (x.floor() as usize).min(stops.len() - 2);
+        let f = x - i as f64;
+        std::array::from_fn(|c| {
+            (stops[i][c] as f64 + f * (stops[i + 1][c] as f64 - stops[i][c] as f64)).round() as u8
+        })
+    }
+}
+
+/// Opacity at a position in the normalized value range.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TransferPoint {
+    pub position: f64,
+    pub opacity: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct MetricStyle {
+    #[serde(default)]
+    pub color_map: ColorMap,
+    /// Values mapped to the ends of the colour map; the observed range when
+    /// absent.
+    #[serde(default)]
+    pub range: Option<[f64; 2]>,
+    /// Breakpoints of a piecewise linear opacity, by increasing position;
+    /// fully opaque when empty.
+    #[serde(default)]
+    pub transfer: Vec<TransferPoint>,
+}
+
+impl MetricStyle {
+    pub fn validate(&self) -> Result<()> {
+        if let Some([low, high]) = self.range {
+            if !(low.is_finite() && high.is_finite() && low < high) {
+                return Err(invalid("range", "must be two finite values, low < high"));
+            }
+        }
+        for point in &self.transfer {
+            if !(0.0..=1.0).contains(&point.position) || !(0.0..=1.0).contains(&point.opacity) {
+                return Err(invalid("transfer", "positions and opacities lie in [0, 1]"));
+            }
+        }
+        if self
+            .transfer
+            .windows(2)
+            .any(|pair| pair[0].position >= pair[1].position)
+        {
+            return Err(invalid("transfer", "positions must increase"));
+        }
+        Ok(())
+    }
+
+    /// `value` mapped into `[0, 1]` by `range`, or by `observed` without one.
+    pub fn normalize(&self, value: f64, observed: [f64; 2]) -> f64 {
+        let [low, high] = self.range.unwrap_or(observed);
+        if high > low {
+            ((value - low) / (high - low)).clamp(0.0, 1.0)
+        } else {
+            0.5
+        }
+    }
+
+    /// The transfer function at normalized position `t`, held flat beyond
+    /// the first and last breakpoints.
+    pub fn opacity(&self, t: f64) -> f64 {
+        let points = &self.transfer;
+        match points.iter().position(|point| point.position >= t) {
+            None => points.last().map_or(1.0, |point| point.opacity),
+            Some(0) => points[0].opacity,
+            Some(i) => {
+                let (a, b) = (points[i - 1], points[i]);
+                a.opacity + (t - a.position) / (b.position - a.position) * (b.opacity - a.opacity)
+            }
+        }
+    }
+}
+
+fn invalid(name: &str, reason: &str) -> Error {
+    Error::InvalidParameter(name.to_string(), reason.to_string())
+}
+
+/// The server's style sheet, shared by every packet built.
+#[derive(Debug, Default)]
+pub struct StyleRegistry {
+    styles: RwLock<StyleSheet>,
+}
+
+impl StyleRegistry {
+    pub fn sheet(&self) -> Result<StyleSheet> {
+        Ok(self.styles.read().map_err(lock_error)?.clone())
+    }
+
+    /// Set the style of `metric`, returning the one it replaced.
+    pub fn set(&self, metric: &str, style: MetricStyle) -> Result<Option<MetricStyle>> {
+        style.validate()?;
+        let mut styles = self.styles.write().map_err(lock_error)?;
+        Ok(styles.insert(metric.to_string(), style))
+    }
+
+    pub fn remove(&self, metric: &str) -> Result<Option<MetricStyle>> {
+        let mut styles = self.styles.write().map_err(lock_error)?;
+        Ok(styles.remove(metric))
+    }
+}
+
+fn lock_error(e: impl std::fmt::Display) -> Error {
+    Error::TaskExecution(format!("Failed to access visualization styles: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_maps_and_transfer() {
+        assert_eq!(ColorMap::Grayscale.sample(0.5), [128, 128, 128]);
+        assert_eq!(ColorMap::Viridis.sample(-1.0), [68, 1, 84]);
+        assert_eq!(ColorMap::Viridis.sample(2.0), [253, 231, 37]);
+        assert_eq!(ColorMap::Coolwarm.sample(0.5), [221, 221, 221]);
+
+        let style = MetricStyle {
+            color_map: ColorMap::Plasma,
+            range: Some([0.0, 10.0]),
+            transfer: vec![
+                TransferPoint {
+                    position: 0.2,
+                    opacity: 0.0,
+                },
+                TransferPoint {
+                    position: 0.6,
+                    opacity: 1.0,
+                },
+            ],
+        };
+        style.validate().unwrap();
+        assert_eq!(style.normalize(5.0, [100.0, 200.0]), 0.5);
+        assert_eq!(style.opacity(0.1), 0.0);
+        assert!((style.opacity(0.4) - 0.5).abs() < 1e-12);
+        assert_eq!(style.opacity(0.9), 1.0);
+        assert_eq!(MetricStyle::default().opacity(0.3), 1.0);
+
+        let registry = StyleRegistry::default();
+        assert!(registry
+            .set(
+                "v_geometric",
+                MetricStyle {
+                    range: Some([1.0, 1.0]),
+                    ..MetricStyle::default()
+                }
+            )
+            .is_err());
+        let mut reversed = style.clone();
+        reversed.transfer.reverse();
+        assert!(reversed.validate().is_err());
+
+        assert_eq!(registry.set("v_geometric", style.clone()).unwrap(), None);
+        assert_eq!(registry.sheet().unwrap()["v_geometric"], style);
+        assert_eq!(registry.remove("v_geometric").unwrap(), Some(style));
+        assert!(registry.sheet().unwrap().is_empty());
+    }
+}
@@ -23,7 +23,13 @@ const api = (path, options = {}) => fetch(`/api${path}`, {
 }).then(async res => {
   if (!res.ok) {
     const body = await res.text();
-    throw new Error(body || res.statusText);
+    let message = body;
+    try {
+      message = JSON.parse(body).message || body;
+    } catch (_) {
+      // errors raised before a handler ran are plain text
+    }
+    throw new Error(message || res.statusText);
   }
   return res.json();
 });
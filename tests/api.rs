@@ -0,0 +1,257 @@
+//! The API as clients see it: requests through the router, with the mock
+//! LLM provider behind the gateway.
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Method, Request, StatusCode};
+use axum::Router;
+use datafusion::arrow::array::{Array, StringArray};
+use datafusion::arrow::ipc::reader::StreamReader;
+use datafusion::arrow::record_batch::RecordBatch;
+use futures_util::StreamExt;
+use mmss::api::llm_gateway::LlmGateway;
+use mmss::api::mock_llm::MockProvider;
+use mmss::campaign::runner::fallback_task_for_target;
+use mmss::core::credentials::Credentials;
+use mmss::core::journal::Journal;
+use mmss::routes::{build_router, dashboard};
+use mmss::state::AppState;
+use serde_json::{json, Value};
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+fn state() -> AppState {
+    let gateway = LlmGateway::with_provider(Arc::new(MockProvider::new())).unwrap();
+    AppState::with_llm_gateway(gateway)
+}
+
+fn api(state: &AppState) -> Router {
+    build_router().with_state(state.clone())
+}
+
+async fn call(
+    state: &AppState,
+    method: Method,
+    uri: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = Request::builder().method(method).uri(uri);
+    let request = match body {
+        Some(body) => request
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+    let response = api(state).oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn start_campaign(state: &AppState, max_steps: usize) -> String {
+    let request = json!({
+        "goal": "Raise the winding number",
+        "optimization_target": "topological_winding",
+        "max_steps": max_steps,
+        // out of reach, so only the step limit or a cancel ends it
+        "stopping": { "target_progress": 2.0 },
+    });
+    let (status, body) = call(state, Method::POST, "/llm/research-campaign", Some(request)).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{}", body);
+    assert_eq!(body["status"], "running");
+    body["campaign_id"].as_str().unwrap().to_string()
+}
+
+/// The campaign once it stops running.
+async fn finished_campaign(state: &AppState, id: &str) -> Value {
+    let uri = format!("/campaigns/{}", id);
+    for _ in 0..500 {
+        let (status, campaign) = call(state, Method::GET, &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        if campaign["status"] != "running" {
+            return campaign;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("campaign {} still running after 10s", id);
+}
+
+#[tokio::test]
+async fn test_campaigns_run_in_the_background_until_done_or_cancelled() {
+    let state = state();
+
+    let id = start_campaign(&state, 2).await;
+    let campaign = finished_campaign(&state, &id).await;
+    assert_eq!(campaign["status"], "completed", "{}", campaign);
+    assert_eq!(campaign["stop_reason"], "max_steps");
+    assert_eq!(campaign["completed_steps"], 2);
+    assert_eq!(campaign["history"].as_array().unwrap().len(), 2);
+
+    let id = start_campaign(&state, 1_000_000).await;
+    let cancel = format!("/campaigns/{}/cancel", id);
+    let (status, body) = call(&state, Method::POST, &cancel, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["cancel_requested"], true);
+    let campaign = finished_campaign(&state, &id).await;
+    assert_eq!(campaign["status"], "cancelled", "{}", campaign);
+    assert!(campaign["completed_steps"].as_u64().unwrap() < 1_000_000);
+
+    let (status, _) = call(&state, Method::POST, &cancel, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let unknown = format!("/campaigns/{}", uuid::Uuid::new_v4());
+    assert_eq!(
+        call(&state, Method::GET, &unknown, None).await.0,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_metrics_stream_sends_a_batch_per_completed_task() {
+    let state = state();
+    let request = Request::get("/export/metrics/stream?custom=drift")
+        .body(Body::empty())
+        .unwrap();
+    let response = api(&state).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/vnd.apache.arrow.stream"
+    );
+
+    // the stream is subscribed once the response exists
+    let task_ids = ["q_oscillator", "v_geometric"].map(|target| {
+        let processor = &state.processor;
+        let task_id = processor
+            .submit_task(fallback_task_for_target(target, 1.0))
+            .unwrap();
+        processor.execute_task(task_id).unwrap();
+        task_id.to_string()
+    });
+
+    let mut body = response.into_body().into_data_stream();
+    let mut bytes = Vec::new();
+    let batches = loop {
+        let chunk = tokio::time::timeout(Duration::from_secs(10), body.next())
+            .await
+            .expect("no snapshot within 10s")
+            .unwrap()
+            .unwrap();
+        bytes.extend_from_slice(&chunk);
+        let reader = StreamReader::try_new(Cursor::new(bytes.clone()), None).unwrap();
+        let schema = reader.schema();
+        assert_eq!(schema.fields().len(), 11);
+        assert!(schema.field_with_name("drift").unwrap().is_nullable());
+        let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap();
+        if batches.iter().map(RecordBatch::num_rows).sum::<usize>() >= task_ids.len() {
+            break batches;
+        }
+    };
+
+    let streamed: Vec<String> = batches
+        .iter()
+        .flat_map(|batch| {
+            let column = batch.column_by_name("task_id").unwrap();
+            let column = column.as_any().downcast_ref::<StringArray>().unwrap();
+            (0..column.len())
+                .map(|row| column.value(row).to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    assert_eq!(streamed, task_ids);
+}
+
+#[tokio::test]
+async fn test_exports_stay_in_their_namespace() {
+    let state = state();
+    let (status, job) = call(&state, Method::POST, "/ns/alpha/exports", Some(json!({}))).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{}", job);
+    let id = job["id"].as_str().unwrap();
+
+    let own = format!("/ns/alpha/exports/{}", id);
+    for _ in 0..500 {
+        let (status, job) = call(&state, Method::GET, &own, None).await;
+        assert_eq!(status, StatusCode::OK);
+        if job["status"] != "running" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let (status, _) = call(&state, Method::GET, &format!("{}/download", own), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    for other in ["/ns/beta", ""] {
+        for suffix in ["", "/download"] {
+            let uri = format!("{}/exports/{}{}", other, id, suffix);
+            assert_eq!(
+                call(&state, Method::GET, &uri, None).await.0,
+                StatusCode::NOT_FOUND,
+                "{}",
+                uri
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_journal_needs_the_admin_token() {
+    let journal = std::env::temp_dir().join(format!("mmss-journal-{}.jsonl", uuid::Uuid::new_v4()));
+    let mut state = state();
+    state.journal = Some(Arc::new(Journal::open(&journal).unwrap()));
+    state.credentials = Arc::new(Credentials::new(
+        Some("secret".to_string()),
+        Vec::new(),
+        None,
+    ));
+
+    for uri in ["/journal", "/journal/replay"] {
+        assert_eq!(
+            call(&state, Method::GET, uri, None).await.0,
+            StatusCode::UNAUTHORIZED,
+            "{}",
+            uri
+        );
+        let request = Request::get(uri)
+            .header("authorization", "Bearer secret")
+            .body(Body::empty())
+            .unwrap();
+        let response = api(&state).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+    }
+    std::fs::remove_file(&journal).ok();
+}
+
+#[tokio::test]
+async fn test_dashboard_serves_its_page_assets_and_client_routes() {
+    let app = Router::new().nest("/dashboard", dashboard::router());
+    let get = |uri: &str| {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        app.clone().oneshot(request)
+    };
+
+    let page = get("/dashboard").await.unwrap();
+    assert_eq!(page.status(), StatusCode::OK);
+    assert!(page.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    let page = to_bytes(page.into_body(), usize::MAX).await.unwrap();
+    let page = String::from_utf8(page.to_vec()).unwrap();
+    assert!(page.contains("/dashboard/dashboard.js"));
+
+    let script = get("/dashboard/dashboard.js").await.unwrap();
+    assert_eq!(script.status(), StatusCode::OK);
+    assert!(script.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .contains("javascript"));
+
+    let route = get("/dashboard/tasks").await.unwrap();
+    assert_eq!(route.status(), StatusCode::OK);
+    let route = to_bytes(route.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(route, page.as_bytes());
+
+    let missing = get("/dashboard/missing.css").await.unwrap();
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+}